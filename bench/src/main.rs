@@ -0,0 +1,207 @@
+//! A load generator that drives a running coordinator through the client library. It creates
+//! ledgers, then appends blocks of each requested size to them from concurrent tasks, and reports
+//! the throughput and the latency percentiles of the appends, which include verifying the
+//! receipts as any client does.
+use clap::{App, Arg};
+use nimble_client::Client;
+use rand::random;
+use std::{
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+
+// the percentiles to report, in thousandths so that their ranks are exact
+const PERCENTILES: [(&str, usize); 4] = [("p50", 500), ("p90", 900), ("p99", 990), ("p99.9", 999)];
+
+/// Returns the latency below which `per_mille` thousandths of the sorted `latencies` fall
+fn percentile(latencies: &[Duration], per_mille: usize) -> Duration {
+  if latencies.is_empty() {
+    return Duration::ZERO;
+  }
+  // the nearest rank
+  let rank = (per_mille * latencies.len()).div_ceil(1000);
+  latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+fn report(block_size: usize, elapsed: Duration, mut latencies: Vec<Duration>, failures: usize) {
+  latencies.sort();
+  let mut line = format!(
+    "block size {} B: {} appends in {:.2?} ({:.1} appends/s), {} failed",
+    block_size,
+    latencies.len(),
+    elapsed,
+    latencies.len() as f64 / elapsed.as_secs_f64(),
+    failures
+  );
+  for (name, per_mille) in PERCENTILES {
+    line.push_str(&format!(
+      ", {} {:.2?}",
+      name,
+      percentile(&latencies, per_mille)
+    ));
+  }
+  if let Some(max) = latencies.last() {
+    line.push_str(&format!(", max {:.2?}", max));
+  }
+  println!("{}", line);
+}
+
+// Appends `requests` blocks of `block_size` bytes to the ledgers from `concurrency` tasks, each
+// of which appends to its own ledgers, since the appends to a ledger are conditional on its tail
+async fn run(
+  client: Arc<Client>,
+  handles: Arc<Vec<Vec<u8>>>,
+  concurrency: usize,
+  requests: usize,
+  block_size: usize,
+) -> (Duration, Vec<Duration>, usize) {
+  let remaining = Arc::new(AtomicUsize::new(requests));
+  let start = Instant::now();
+  let mut tasks = Vec::new();
+  for task in 0..concurrency {
+    let client = client.clone();
+    let handles = handles.clone();
+    let remaining = remaining.clone();
+    tasks.push(tokio::spawn(async move {
+      let mut latencies = Vec::new();
+      let mut failures = 0;
+      let own = (task..handles.len())
+        .step_by(concurrency)
+        .collect::<Vec<_>>();
+      let block = vec![0u8; block_size];
+      let mut next = 0;
+      while remaining
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok()
+      {
+        let ledger = client.ledger(&handles[own[next % own.len()]]);
+        next += 1;
+        let sent = Instant::now();
+        match ledger.append(&block).await {
+          Ok(_entry) => latencies.push(sent.elapsed()),
+          Err(error) => {
+            eprintln!("Failed to append ({:?})", error);
+            failures += 1;
+          },
+        }
+      }
+      (latencies, failures)
+    }));
+  }
+
+  let mut latencies = Vec::with_capacity(requests);
+  let mut failures = 0;
+  for task in tasks {
+    let (task_latencies, task_failures) = task.await.unwrap();
+    latencies.extend(task_latencies);
+    failures += task_failures;
+  }
+  (start.elapsed(), latencies, failures)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  let config = App::new("nimble-bench")
+    .arg(
+      Arg::with_name("coordinator")
+        .short("c")
+        .long("coordinator")
+        .help("The URI of the coordinator")
+        .default_value("http://[::1]:8080"),
+    )
+    .arg(
+      Arg::with_name("concurrency")
+        .short("n")
+        .long("concurrency")
+        .help("The number of appends in progress at once")
+        .default_value("8"),
+    )
+    .arg(
+      Arg::with_name("ledgers")
+        .short("l")
+        .long("ledgers")
+        .help("The number of ledgers to append to, at least one per concurrent append")
+        .default_value("8"),
+    )
+    .arg(
+      Arg::with_name("block_sizes")
+        .short("b")
+        .long("block-sizes")
+        .help("The comma-separated sizes in bytes of the blocks to append, one run per size")
+        .default_value("1024"),
+    )
+    .arg(
+      Arg::with_name("requests")
+        .short("r")
+        .long("requests")
+        .help("The number of appends in each run")
+        .default_value("1000"),
+    );
+  let cli_matches = config.get_matches();
+  let coordinator = cli_matches.value_of("coordinator").unwrap();
+  let concurrency = cli_matches
+    .value_of("concurrency")
+    .unwrap()
+    .parse::<usize>()?;
+  let num_ledgers = cli_matches.value_of("ledgers").unwrap().parse::<usize>()?;
+  let requests = cli_matches.value_of("requests").unwrap().parse::<usize>()?;
+  let block_sizes = cli_matches
+    .value_of("block_sizes")
+    .unwrap()
+    .split(',')
+    .map(|size| size.trim().parse::<usize>())
+    .collect::<Result<Vec<_>, _>>()?;
+  if concurrency == 0 {
+    return Err("The concurrency must be at least 1".into());
+  }
+  let num_ledgers = num_ledgers.max(concurrency);
+
+  let client = Client::connect(coordinator)
+    .await
+    .map_err(|e| format!("Failed to connect to {} ({:?})", coordinator, e))?;
+  let client = Arc::new(client);
+  let mut handles = Vec::with_capacity(num_ledgers);
+  let start = Instant::now();
+  for _ in 0..num_ledgers {
+    let handle = random::<[u8; 16]>().to_vec();
+    if let Err(error) = client.create_ledger(&handle, b"nimble-bench").await {
+      return Err(format!("Failed to create a ledger ({:?})", error).into());
+    }
+    handles.push(handle);
+  }
+  println!("Created {} ledgers in {:.2?}", num_ledgers, start.elapsed());
+
+  let handles = Arc::new(handles);
+  for block_size in block_sizes {
+    let (elapsed, latencies, failures) = run(
+      client.clone(),
+      handles.clone(),
+      concurrency,
+      requests,
+      block_size,
+    )
+    .await;
+    report(block_size, elapsed, latencies, failures);
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_percentile() {
+    let latencies = (1..=1000).map(Duration::from_millis).collect::<Vec<_>>();
+    assert_eq!(percentile(&latencies, 500), Duration::from_millis(500));
+    assert_eq!(percentile(&latencies, 990), Duration::from_millis(990));
+    assert_eq!(percentile(&latencies, 999), Duration::from_millis(999));
+    assert_eq!(percentile(&latencies, 1000), Duration::from_millis(1000));
+    assert_eq!(percentile(&latencies, 0), Duration::from_millis(1));
+    assert_eq!(percentile(&[], 500), Duration::ZERO);
+  }
+}
@@ -0,0 +1,28 @@
+use ledger::errors::VerificationError;
+use tonic::Code;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClientError {
+  /// returned if the coordinator's URI is invalid
+  InvalidCoordinatorUri,
+  /// returned if the client is given an invalid namespace
+  InvalidNamespace,
+  /// returned if a ledger is to be created under a handle derived from an empty nonce
+  InvalidClientNonce,
+  /// returned if the view ledger cannot be read from the coordinator
+  FailedToReadViewLedger,
+  /// returned if a view change read from the coordinator does not verify
+  FailedToApplyViewChange,
+  /// returned if the client fails to acquire the read lock
+  FailedToAcquireReadLock,
+  /// returned if the client fails to acquire the write lock
+  FailedToAcquireWriteLock,
+  /// returned if the coordinator rejects a request, or a transient failure persists after the
+  /// retries of the client's retry policy; carries the gRPC status code
+  RequestFailed(Code),
+  /// returned if a conditional append lost a race with another append; carries the height of the
+  /// ledger's tail, which the client now expects
+  AppendConflict(u64),
+  /// returned if the receipts of a response do not verify
+  VerificationFailed(VerificationError),
+}
@@ -0,0 +1,966 @@
+//! A client library for applications that keep their state in Nimble ledgers. A `Client` talks
+//! to the coordinator and verifies every response against the view ledger, which it follows as
+//! the coordinator reconfigures its endorsers, so applications only ever see entries that a
+//! quorum of endorsers attested. It generates the nonces that make reads fresh, tracks the height
+//! of each ledger's tail so that appends are conditional on it, and retries transient failures
+//! with exponential backoff:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), nimble_client::ClientError> {
+//! let client = nimble_client::Client::connect("http://[::1]:8080").await?;
+//! let ledger = client.create_ledger(b"my-ledger", b"genesis").await?;
+//! let entry = ledger.append(b"first entry").await?;
+//! assert_eq!(entry.height, 1);
+//! # Ok(())
+//! # }
+//! ```
+mod errors;
+
+pub use crate::errors::ClientError;
+// the nonces and keys that applications hand to the client, whose secrets are zeroized on drop
+pub use ledger::{signature::PrivateKey, zeroize::Zeroizing, Nonce};
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+pub mod coordinator_proto {
+  tonic::include_proto!("coordinator_proto");
+}
+
+use coordinator_proto::{
+  call_client::CallClient, AppendReq, AppendResp, GetLedgerByLabelReq, GetLedgerByLabelResp,
+  NewLedgerReq, ReadByIndexReq, ReadByIndexResp, ReadLatestReq, ReadLatestResp, ReadViewByIndexReq,
+  ReadViewByIndexResp, ReadViewTailReq, ReadViewTailResp,
+};
+use ledger::{
+  derive_ledger_handle,
+  errors::VerificationError,
+  namespace::{is_valid_namespace, namespaced_handle},
+  signature::{PrivateKeyTrait, PublicKeyTrait, SignatureTrait},
+  AccessRequest, Block, CustomSerde, EndorsementPolicy, NimbleDigest, NimbleHashTrait,
+  VerifierState, CLIENT_PUBLIC_KEY_METADATA, CLIENT_SIGNATURE_METADATA, RETRY_AFTER_METADATA,
+};
+use rand::random;
+use std::{collections::HashMap, future::Future, sync::RwLock, time::Duration};
+use tokio::sync::Mutex;
+use tonic::{
+  metadata::MetadataValue,
+  transport::{Channel, Endpoint},
+  Code, Request, Response, Status,
+};
+
+/// How a client retries requests that fail for reasons that may pass, such as an unavailable
+/// coordinator or one in maintenance mode
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+  /// the number of times a request is retried before its failure is returned
+  pub max_retries: usize,
+  /// the wait before the first retry, which doubles with each further retry
+  pub initial_backoff: Duration,
+  /// the longest wait between retries
+  pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    RetryPolicy {
+      max_retries: 5,
+      initial_backoff: Duration::from_millis(100),
+      max_backoff: Duration::from_secs(5),
+    }
+  }
+}
+
+impl RetryPolicy {
+  /// returns how long to wait before retry number `attempt`, counting from 0, with jitter so
+  /// that clients that failed together do not retry together
+  pub fn backoff(&self, attempt: usize) -> Duration {
+    let backoff = self
+      .initial_backoff
+      .saturating_mul(1 << attempt.min(16))
+      .min(self.max_backoff);
+    backoff / 2 + backoff.mul_f64(random::<f64>() / 2.0)
+  }
+}
+
+// failures that a retry may not run into
+fn is_transient(status: &Status) -> bool {
+  matches!(
+    status.code(),
+    Code::Unavailable | Code::ResourceExhausted | Code::DeadlineExceeded | Code::Aborted
+  )
+}
+
+// how long the coordinator asked a client that it rate limited to wait before retrying
+fn retry_after(status: &Status) -> Option<Duration> {
+  let retry_after_ms = status.metadata().get(RETRY_AFTER_METADATA)?.to_str().ok()?;
+  retry_after_ms
+    .parse::<u64>()
+    .ok()
+    .map(Duration::from_millis)
+}
+
+// the height of the tail that the coordinator reports when a conditional append conflicts
+fn conflict_height(status: &Status) -> Option<u64> {
+  if status.code() == Code::FailedPrecondition && status.details().len() == 8 {
+    let mut height = [0u8; 8];
+    height.copy_from_slice(status.details());
+    Some(u64::from_le_bytes(height))
+  } else {
+    None
+  }
+}
+
+/// An entry of a ledger whose receipts were verified
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedEntry {
+  pub height: usize,
+  pub block: Vec<u8>,
+  /// the receipts of the entry, which prove to third parties that it was attested
+  pub receipts: Vec<u8>,
+}
+
+// the consistency token of the latest append to each ledger, and the append's height
+type ConsistencyTokens = HashMap<Vec<u8>, (usize, Vec<u8>)>;
+
+/// A connection to a coordinator
+pub struct Client {
+  client: CallClient<Channel>,
+  retry_policy: RetryPolicy,
+  sk: Option<PrivateKey>, // the key the client acts with under ledgers' access policies
+  vs: RwLock<VerifierState>,
+  view_refresh: Mutex<()>, // serializes fetching the view ledger
+  tails: RwLock<HashMap<Vec<u8>, usize>>, // the height of the tail each ledger is expected at
+  policies: RwLock<HashMap<Vec<u8>, EndorsementPolicy>>, // the endorsement policy of each ledger
+  consistency_tokens: RwLock<ConsistencyTokens>,
+  namespace: String, // the namespace the client's ledgers are in; empty for none
+}
+
+/// A ledger, through which entries are appended and read
+pub struct Ledger<'a> {
+  client: &'a Client,
+  handle: Vec<u8>,
+}
+
+impl Client {
+  /// Connects to the coordinator at `uri` and verifies its view ledger
+  pub async fn connect(uri: &str) -> Result<Self, ClientError> {
+    let endpoint = Endpoint::from_shared(uri.to_string());
+    if endpoint.is_err() {
+      return Err(ClientError::InvalidCoordinatorUri);
+    }
+    let client = Client {
+      client: CallClient::new(endpoint.unwrap().connect_lazy()),
+      retry_policy: RetryPolicy::default(),
+      sk: None,
+      vs: RwLock::new(VerifierState::new()),
+      view_refresh: Mutex::new(()),
+      tails: RwLock::new(HashMap::new()),
+      policies: RwLock::new(HashMap::new()),
+      consistency_tokens: RwLock::new(HashMap::new()),
+      namespace: String::new(),
+    };
+
+    // the hash of the genesis block of the view ledger identifies the deployment
+    let ReadViewByIndexResp { block, .. } = client
+      .call(|mut c| async move { c.read_view_by_index(ReadViewByIndexReq { index: 1 }).await })
+      .await
+      .map_err(|_e| ClientError::FailedToReadViewLedger)?;
+    let block = Block::from_bytes(&block).map_err(|_e| ClientError::FailedToReadViewLedger)?;
+    client
+      .vs
+      .write()
+      .map_err(|_e| ClientError::FailedToAcquireWriteLock)?
+      .set_group_identity(block.hash());
+    client.update_view().await?;
+    Ok(client)
+  }
+
+  pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+    self.retry_policy = retry_policy;
+    self
+  }
+
+  /// Signs requests with `sk`, which ledgers with an access policy check
+  pub fn with_signing_key(mut self, sk: PrivateKey) -> Self {
+    self.sk = Some(sk);
+    self
+  }
+
+  /// Creates and opens the client's ledgers in `namespace`, whose ledgers the coordinator keeps
+  /// apart from those of other namespaces
+  pub fn with_namespace(mut self, namespace: &str) -> Result<Self, ClientError> {
+    if !is_valid_namespace(namespace) {
+      return Err(ClientError::InvalidNamespace);
+    }
+    self.namespace = namespace.to_string();
+    Ok(self)
+  }
+
+  /// Sets the number of endorsers each ledger is assigned to, which must match the coordinator
+  pub fn with_shard_size(self, shard_size: usize) -> Result<Self, ClientError> {
+    self
+      .vs
+      .write()
+      .map_err(|_e| ClientError::FailedToAcquireWriteLock)?
+      .set_shard_size(shard_size);
+    Ok(self)
+  }
+
+  /// Creates a ledger with the genesis block `block`. Creating a ledger that exists already with
+  /// the same genesis block succeeds, as when a retry follows a creation whose response was lost;
+  /// one with another genesis block fails with `ALREADY_EXISTS`.
+  pub async fn create_ledger(
+    &self,
+    handle: &[u8],
+    block: &[u8],
+  ) -> Result<Ledger<'_>, ClientError> {
+    self.create_ledger_with_label(handle, block, "").await
+  }
+
+  /// Creates a ledger like `create_ledger`, under the handle that `ledger::derive_ledger_handle`
+  /// derives from `client_nonce` and the application bytes of `block`, so that the client knows
+  /// the handle of the ledger before it exists
+  pub async fn create_derived_ledger(
+    &self,
+    client_nonce: &[u8],
+    block: &[u8],
+  ) -> Result<Ledger<'_>, ClientError> {
+    if client_nonce.is_empty() {
+      return Err(ClientError::InvalidClientNonce);
+    }
+    let handle =
+      derive_ledger_handle(client_nonce, block).map_err(ClientError::VerificationFailed)?;
+    let req = NewLedgerReq {
+      handle: Vec::new(),
+      block: block.to_vec(),
+      label: String::new(),
+      namespace: self.namespace.clone(),
+      client_nonce: client_nonce.to_vec(),
+    };
+    self.new_ledger(req, &handle, block).await
+  }
+
+  /// Creates a ledger like `create_ledger`, under a label that is unique among the ledgers and
+  /// with which `find_ledger` finds the ledger again
+  pub async fn create_labeled_ledger(
+    &self,
+    handle: &[u8],
+    block: &[u8],
+    label: &str,
+  ) -> Result<Ledger<'_>, ClientError> {
+    self.create_ledger_with_label(handle, block, label).await
+  }
+
+  /// Returns the ledger created under `label`. The coordinator is trusted to return the right
+  /// ledger, but what is read from it is verified as from any other.
+  pub async fn find_ledger(&self, label: &str) -> Result<Ledger<'_>, ClientError> {
+    let req = GetLedgerByLabelReq {
+      label: label.to_string(),
+    };
+    let GetLedgerByLabelResp { handle } = self
+      .call(|mut c| {
+        let req = req.clone();
+        async move { c.get_ledger_by_label(req).await }
+      })
+      .await
+      .map_err(|status| ClientError::RequestFailed(status.code()))?;
+    // the handle that the label is bound to carries the ledger's namespace already
+    Ok(Ledger {
+      client: self,
+      handle,
+    })
+  }
+
+  async fn create_ledger_with_label(
+    &self,
+    handle: &[u8],
+    block: &[u8],
+    label: &str,
+  ) -> Result<Ledger<'_>, ClientError> {
+    let req = NewLedgerReq {
+      handle: handle.to_vec(),
+      block: block.to_vec(),
+      label: label.to_string(),
+      namespace: self.namespace.clone(),
+      client_nonce: Vec::new(),
+    };
+    self.new_ledger(req, handle, block).await
+  }
+
+  // Creates the ledger `handle` with `req`, or opens it if it exists with the genesis block
+  // `block` already: the coordinator then returns the receipts of its genesis block, which
+  // verify only for that block
+  async fn new_ledger(
+    &self,
+    req: NewLedgerReq,
+    handle: &[u8],
+    block: &[u8],
+  ) -> Result<Ledger<'_>, ClientError> {
+    let ledger = self.ledger(handle);
+    let res = self
+      .call(|mut c| {
+        let req = req.clone();
+        async move { c.new_ledger(req).await }
+      })
+      .await;
+    let (receipts, exists) = match res {
+      Ok(resp) => (resp.receipts, false),
+      Err(status) if status.code() == Code::AlreadyExists && !status.details().is_empty() => {
+        (status.details().to_vec(), true)
+      },
+      Err(status) => return Err(ClientError::RequestFailed(status.code())),
+    };
+    let res = self
+      .verify(|vs| vs.verify_new_ledger(&ledger.handle, block, &receipts))
+      .await;
+    match res {
+      Err(_error) if exists => return Err(ClientError::RequestFailed(Code::AlreadyExists)),
+      res => res?,
+    }
+    let (policy, _app_bytes) =
+      EndorsementPolicy::from_genesis_bytes(block).map_err(ClientError::VerificationFailed)?;
+    let hash_nonces = NimbleDigest::default().to_bytes();
+    ledger
+      .check_policy(&policy, block, &hash_nonces, 0, None, &receipts)
+      .await?;
+    self.set_policy(&ledger.handle, policy)?;
+    self.set_tail(&ledger.handle, 0)?;
+    Ok(ledger)
+  }
+
+  /// Returns an existing ledger, in the client's namespace if it has one; its tail is read before
+  /// the first append
+  pub fn ledger(&self, handle: &[u8]) -> Ledger<'_> {
+    Ledger {
+      client: self,
+      handle: namespaced_handle(&self.namespace, handle),
+    }
+  }
+
+  // Sends a request built by `call` until it succeeds, fails for good, or the retries run out
+  async fn call<T, F, Fut>(&self, call: F) -> Result<T, Status>
+  where
+    F: Fn(CallClient<Channel>) -> Fut,
+    Fut: Future<Output = Result<Response<T>, Status>>,
+  {
+    let mut attempt = 0;
+    loop {
+      match call(self.client.clone()).await {
+        Ok(resp) => return Ok(resp.into_inner()),
+        Err(status) if is_transient(&status) && attempt < self.retry_policy.max_retries => {
+          let backoff = self.retry_policy.backoff(attempt);
+          tokio::time::sleep(retry_after(&status).map_or(backoff, |wait| wait.max(backoff))).await;
+          attempt += 1;
+        },
+        Err(status) => return Err(status),
+      }
+    }
+  }
+
+  // Attaches the client's credentials for `access_request` on the ledger `handle`, if it has any
+  fn request<T>(&self, message: T, handle: &[u8], access_request: &AccessRequest) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Some(sk) = &self.sk {
+      if let (Ok(pk), Ok(sig)) = (
+        sk.get_public_key(),
+        sk.sign(&access_request.message(handle)),
+      ) {
+        let metadata = request.metadata_mut();
+        metadata.insert_bin(
+          CLIENT_PUBLIC_KEY_METADATA,
+          MetadataValue::from_bytes(&pk.to_bytes()),
+        );
+        metadata.insert_bin(
+          CLIENT_SIGNATURE_METADATA,
+          MetadataValue::from_bytes(&sig.to_bytes()),
+        );
+      }
+    }
+    request
+  }
+
+  fn get_tail(&self, handle: &[u8]) -> Result<Option<usize>, ClientError> {
+    let tails = self
+      .tails
+      .read()
+      .map_err(|_e| ClientError::FailedToAcquireReadLock)?;
+    Ok(tails.get(handle).copied())
+  }
+
+  // the tail only moves forward, since responses to concurrent requests may arrive out of order
+  fn set_tail(&self, handle: &[u8], height: usize) -> Result<(), ClientError> {
+    let mut tails = self
+      .tails
+      .write()
+      .map_err(|_e| ClientError::FailedToAcquireWriteLock)?;
+    let tail = tails.entry(handle.to_vec()).or_insert(height);
+    *tail = (*tail).max(height);
+    Ok(())
+  }
+
+  fn get_policy(&self, handle: &[u8]) -> Result<Option<EndorsementPolicy>, ClientError> {
+    let policies = self
+      .policies
+      .read()
+      .map_err(|_e| ClientError::FailedToAcquireReadLock)?;
+    Ok(policies.get(handle).cloned())
+  }
+
+  fn set_policy(&self, handle: &[u8], policy: EndorsementPolicy) -> Result<(), ClientError> {
+    let mut policies = self
+      .policies
+      .write()
+      .map_err(|_e| ClientError::FailedToAcquireWriteLock)?;
+    policies.insert(handle.to_vec(), policy);
+    Ok(())
+  }
+
+  fn get_consistency_token(&self, handle: &[u8]) -> Result<Vec<u8>, ClientError> {
+    let tokens = self
+      .consistency_tokens
+      .read()
+      .map_err(|_e| ClientError::FailedToAcquireReadLock)?;
+    Ok(
+      tokens
+        .get(handle)
+        .map(|(_height, token)| token.clone())
+        .unwrap_or_default(),
+    )
+  }
+
+  fn set_consistency_token(
+    &self,
+    handle: &[u8],
+    height: usize,
+    token: Vec<u8>,
+  ) -> Result<(), ClientError> {
+    let mut tokens = self
+      .consistency_tokens
+      .write()
+      .map_err(|_e| ClientError::FailedToAcquireWriteLock)?;
+    match tokens.get(handle) {
+      Some((latest, _token)) if *latest >= height => {},
+      _ => {
+        tokens.insert(handle.to_vec(), (height, token));
+      },
+    }
+    Ok(())
+  }
+
+  // Runs `verify` against the verifier state. Receipts from a view the client has not seen yet
+  // make it fetch and verify the missing views and run `verify` once more.
+  async fn verify<T>(
+    &self,
+    verify: impl Fn(&VerifierState) -> Result<T, VerificationError>,
+  ) -> Result<T, ClientError> {
+    let run = |verify: &dyn Fn(&VerifierState) -> Result<T, VerificationError>| {
+      let vs = self
+        .vs
+        .read()
+        .map_err(|_e| ClientError::FailedToAcquireReadLock)?;
+      Ok(verify(&vs))
+    };
+    match run(&verify)? {
+      Err(VerificationError::ViewNotFound) => {},
+      res => return res.map_err(ClientError::VerificationFailed),
+    }
+
+    {
+      let _guard = self.view_refresh.lock().await;
+      // another request may have fetched the missing views while this one waited
+      if let Err(VerificationError::ViewNotFound) = run(&verify)? {
+        self.update_view().await?;
+      }
+    }
+    run(&verify)?.map_err(ClientError::VerificationFailed)
+  }
+
+  // Applies the views that the verifier state lacks, from the tail of the view ledger down
+  async fn update_view(&self) -> Result<(), ClientError> {
+    let start_height = self
+      .vs
+      .read()
+      .map_err(|_e| ClientError::FailedToAcquireReadLock)?
+      .get_view_ledger_height()
+      + 1;
+
+    let ReadViewTailResp {
+      block,
+      receipts,
+      height,
+      attestations,
+      ..
+    } = self
+      .call(|mut c| async move { c.read_view_tail(ReadViewTailReq { nonce: vec![] }).await })
+      .await
+      .map_err(|_e| ClientError::FailedToReadViewLedger)?;
+    self.apply_view_change(&block, &receipts, Some(&attestations))?;
+
+    for index in (start_height..height as usize).rev() {
+      let ReadViewByIndexResp {
+        block, receipts, ..
+      } = self
+        .call(|mut c| async move {
+          let req = ReadViewByIndexReq {
+            index: index as u64,
+          };
+          c.read_view_by_index(req).await
+        })
+        .await
+        .map_err(|_e| ClientError::FailedToReadViewLedger)?;
+      self.apply_view_change(&block, &receipts, None)?;
+    }
+    Ok(())
+  }
+
+  fn apply_view_change(
+    &self,
+    block: &[u8],
+    receipts: &[u8],
+    attestations: Option<&[u8]>,
+  ) -> Result<(), ClientError> {
+    let mut vs = self
+      .vs
+      .write()
+      .map_err(|_e| ClientError::FailedToAcquireWriteLock)?;
+    vs.apply_view_change(block, receipts, attestations)
+      .map_err(|_e| ClientError::FailedToApplyViewChange)
+  }
+}
+
+impl<'a> Ledger<'a> {
+  pub fn handle(&self) -> &[u8] {
+    &self.handle
+  }
+
+  /// Appends `block` on top of the tail the client expects. If another writer appended first,
+  /// the client learns the new tail and returns `ClientError::AppendConflict`, so that the
+  /// application can decide whether to append on top of it.
+  pub async fn append(&self, block: &[u8]) -> Result<VerifiedEntry, ClientError> {
+    let client = self.client;
+    let expected_height = match client.get_tail(&self.handle)? {
+      Some(height) => height + 1,
+      None => self.read_latest().await?.height + 1,
+    };
+    let access_request = AccessRequest::Append {
+      block,
+      expected_height,
+    };
+    let req = AppendReq {
+      handle: self.handle.clone(),
+      block: block.to_vec(),
+      expected_height: expected_height as u64,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
+    };
+
+    let mut attempt = 0;
+    let res = loop {
+      let request = client.request(req.clone(), &self.handle, &access_request);
+      match client.client.clone().append(request).await {
+        Ok(resp) => break Ok(resp.into_inner()),
+        Err(status) if is_transient(&status) && attempt < client.retry_policy.max_retries => {
+          tokio::time::sleep(client.retry_policy.backoff(attempt)).await;
+          attempt += 1;
+        },
+        Err(status) => break Err(status),
+      }
+    };
+
+    let AppendResp {
+      hash_nonces,
+      receipts,
+      consistency_token,
+      ..
+    } = match res {
+      Ok(resp) => resp,
+      Err(status) => {
+        let height = match conflict_height(&status) {
+          Some(height) => height,
+          None => return Err(ClientError::RequestFailed(status.code())),
+        };
+        // a retried append conflicts with itself if an earlier attempt reached the store
+        if attempt > 0 && height as usize >= expected_height {
+          let entry = self.read(expected_height).await?;
+          if entry.block == block {
+            return Ok(entry);
+          }
+        }
+        client.set_tail(&self.handle, height as usize)?;
+        return Err(ClientError::AppendConflict(height));
+      },
+    };
+
+    client
+      .verify(|vs| {
+        vs.verify_append(
+          &self.handle,
+          block,
+          &hash_nonces,
+          expected_height,
+          &receipts,
+        )
+      })
+      .await?;
+    self
+      .verify_policy(block, &hash_nonces, expected_height, None, &receipts)
+      .await?;
+    client.set_tail(&self.handle, expected_height)?;
+    if !consistency_token.is_empty() {
+      client.set_consistency_token(&self.handle, expected_height, consistency_token)?;
+    }
+    Ok(VerifiedEntry {
+      height: expected_height,
+      block: block.to_vec(),
+      receipts,
+    })
+  }
+
+  /// Reads the tail of the ledger with a fresh nonce, so that the entry is the latest one. The
+  /// entry is no older than the client's last append to the ledger, even if the append went
+  /// through another coordinator that shares the ledger store.
+  pub async fn read_latest(&self) -> Result<VerifiedEntry, ClientError> {
+    self.read_latest_with_nonce(&Nonce::random()).await
+  }
+
+  /// Reads the tail of the ledger with `nonce`, which a party that does not trust the client can
+  /// pick to learn that the entry is no older than its challenge
+  pub async fn read_latest_with_nonce(&self, nonce: &Nonce) -> Result<VerifiedEntry, ClientError> {
+    let client = self.client;
+    let nonce = nonce.to_bytes();
+    let access_request = AccessRequest::ReadLatest { nonce: &nonce };
+    let req = ReadLatestReq {
+      handle: self.handle.clone(),
+      nonce: nonce.clone(),
+      consistency_token: client.get_consistency_token(&self.handle)?,
+    };
+    let ReadLatestResp {
+      block,
+      nonces,
+      receipts,
+      ..
+    } = client
+      .call(|mut c| {
+        let request = client.request(req.clone(), &self.handle, &access_request);
+        async move { c.read_latest(request).await }
+      })
+      .await
+      .map_err(|status| ClientError::RequestFailed(status.code()))?;
+    let height = client
+      .verify(|vs| vs.verify_read_latest(&self.handle, &block, &nonces, &nonce, &receipts))
+      .await?;
+    let hash_nonces = NimbleDigest::digest(&nonces).to_bytes();
+    self
+      .verify_policy(&block, &hash_nonces, height, Some(&nonce), &receipts)
+      .await?;
+    client.set_tail(&self.handle, height)?;
+    Ok(VerifiedEntry {
+      height,
+      block,
+      receipts,
+    })
+  }
+
+  /// Reads the entry at `index`
+  pub async fn read(&self, index: usize) -> Result<VerifiedEntry, ClientError> {
+    let (block, nonces, receipts) = self.read_entry(index).await?;
+    let hash_nonces = NimbleDigest::digest(&nonces).to_bytes();
+    self
+      .verify_policy(&block, &hash_nonces, index, None, &receipts)
+      .await?;
+    self.client.set_tail(&self.handle, index)?;
+    Ok(VerifiedEntry {
+      height: index,
+      block,
+      receipts,
+    })
+  }
+
+  // Reads the entry at `index` and checks that a quorum attested it, and returns its block,
+  // nonces, and receipts
+  async fn read_entry(&self, index: usize) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), ClientError> {
+    let client = self.client;
+    let access_request = AccessRequest::ReadByIndex { index };
+    let req = ReadByIndexReq {
+      handle: self.handle.clone(),
+      index: index as u64,
+      nonce: vec![],
+      snapshot: 0,
+    };
+    let ReadByIndexResp {
+      block,
+      nonces,
+      receipts,
+      ..
+    } = client
+      .call(|mut c| {
+        let request = client.request(req.clone(), &self.handle, &access_request);
+        async move { c.read_by_index(request).await }
+      })
+      .await
+      .map_err(|status| ClientError::RequestFailed(status.code()))?;
+    client
+      .verify(|vs| vs.verify_read_by_index(&self.handle, &block, &nonces, index, &receipts))
+      .await?;
+    Ok((block, nonces, receipts))
+  }
+
+  // Returns the endorsement policy that the genesis block of the ledger declares, which is read
+  // and checked against the policy on first use
+  async fn policy(&self) -> Result<EndorsementPolicy, ClientError> {
+    if let Some(policy) = self.client.get_policy(&self.handle)? {
+      return Ok(policy);
+    }
+    let (block, nonces, receipts) = self.read_entry(0).await?;
+    let (policy, _app_bytes) =
+      EndorsementPolicy::from_genesis_bytes(&block).map_err(ClientError::VerificationFailed)?;
+    let hash_nonces = NimbleDigest::digest(&nonces).to_bytes();
+    self
+      .check_policy(&policy, &block, &hash_nonces, 0, None, &receipts)
+      .await?;
+    self.client.set_policy(&self.handle, policy.clone())?;
+    Ok(policy)
+  }
+
+  // Checks that the receipts of the entry at `height` satisfy the ledger's endorsement policy
+  async fn verify_policy(
+    &self,
+    block: &[u8],
+    hash_nonces: &[u8],
+    height: usize,
+    nonce: Option<&[u8]>,
+    receipts: &[u8],
+  ) -> Result<(), ClientError> {
+    let policy = self.policy().await?;
+    self
+      .check_policy(&policy, block, hash_nonces, height, nonce, receipts)
+      .await
+  }
+
+  // The receipts are checked for a majority quorum already, which is all the default policy
+  // asks for
+  async fn check_policy(
+    &self,
+    policy: &EndorsementPolicy,
+    block: &[u8],
+    hash_nonces: &[u8],
+    height: usize,
+    nonce: Option<&[u8]>,
+    receipts: &[u8],
+  ) -> Result<(), ClientError> {
+    if *policy == EndorsementPolicy::Majority {
+      return Ok(());
+    }
+    self
+      .client
+      .verify(|vs| {
+        vs.verify_endorsement_policy(
+          &self.handle,
+          block,
+          hash_nonces,
+          height,
+          nonce,
+          receipts,
+          policy,
+        )
+      })
+      .await
+  }
+}
+
+/// Checks a response to a read of the latest entry of `handle` with `nonce` with only the public
+/// keys of the endorsers of the view that the receipts were issued in, so that a party that
+/// follows no view ledger can learn that the entry is no older than its challenge. Returns the
+/// height of the entry.
+pub fn verify_read_latest(
+  resp: &ReadLatestResp,
+  handle: &[u8],
+  nonce: &[u8],
+  view_keys: &[Vec<u8>],
+) -> Result<usize, ClientError> {
+  if resp.nonce != nonce {
+    return Err(ClientError::VerificationFailed(
+      VerificationError::InvalidNonce,
+    ));
+  }
+  ledger::verify_read_latest_statement(
+    &resp.statement,
+    handle,
+    &resp.block,
+    &resp.nonces,
+    nonce,
+    &resp.receipts,
+    view_keys,
+  )
+  .map_err(ClientError::VerificationFailed)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    ffi::OsString,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+  };
+
+  struct BoxChild {
+    pub child: Child,
+  }
+
+  impl Drop for BoxChild {
+    fn drop(&mut self) {
+      self.child.kill().expect("failed to kill a child process");
+    }
+  }
+
+  // starts `cmd` and waits until it prints a line containing `ready`
+  fn launch(cmd: &OsString, args: &str, ready: &str) -> BoxChild {
+    let mut process = BoxChild {
+      child: Command::new(cmd)
+        .args(args.split_whitespace())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("process failed to start"),
+    };
+
+    let mut buf_reader = BufReader::new(process.child.stdout.take().unwrap());
+    let mut output = String::new();
+    while let Ok(buflen) = buf_reader.read_line(&mut output) {
+      if buflen == 0 || output.contains(ready) {
+        break;
+      }
+    }
+
+    process
+  }
+
+  #[test]
+  fn test_retry_policy() {
+    let policy = RetryPolicy::default();
+    for attempt in 0..40 {
+      let backoff = policy.backoff(attempt);
+      let full = (policy.initial_backoff * (1 << attempt.min(16))).min(policy.max_backoff);
+      assert!(backoff >= full / 2 && backoff <= full);
+    }
+
+    assert!(is_transient(&Status::unavailable("maintenance")));
+    assert!(!is_transient(&Status::invalid_argument("bad handle")));
+    let conflict = Status::with_details(
+      Code::FailedPrecondition,
+      "conflict",
+      5u64.to_le_bytes().to_vec().into(),
+    );
+    assert_eq!(conflict_height(&conflict), Some(5));
+    assert_eq!(
+      conflict_height(&Status::failed_precondition("conflict")),
+      None
+    );
+
+    let mut limited = Status::resource_exhausted("rate limited");
+    limited
+      .metadata_mut()
+      .insert(RETRY_AFTER_METADATA, MetadataValue::from(250u64));
+    assert_eq!(retry_after(&limited), Some(Duration::from_millis(250)));
+    assert_eq!(retry_after(&Status::unavailable("maintenance")), None);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_client() {
+    let (endorser_cmd, coordinator_cmd) = match (
+      std::env::var_os("ENDORSER_CMD"),
+      std::env::var_os("COORDINATOR_CMD"),
+    ) {
+      (Some(endorser_cmd), Some(coordinator_cmd)) => (endorser_cmd, coordinator_cmd),
+      _ => panic!("The ENDORSER_CMD or COORDINATOR_CMD environment variable is not specified"),
+    };
+    let _endorser = launch(&endorser_cmd, "-p 9113", "listening on");
+    let _coordinator = launch(
+      &coordinator_cmd,
+      "-p 8183 -r 8193 -e http://[::1]:9113",
+      "Endorser URIs",
+    );
+
+    let client = Client::connect("http://[::1]:8183").await.unwrap();
+    let ledger = client
+      .create_ledger(b"client-handle", b"genesis")
+      .await
+      .unwrap();
+    let entry = ledger.append(b"first").await.unwrap();
+    assert_eq!(entry.height, 1);
+    let entry = ledger.append(b"second").await.unwrap();
+    assert_eq!(entry.height, 2);
+    assert_eq!(ledger.read(1).await.unwrap().block, b"first".to_vec());
+
+    // another client appends first, so this one learns the new tail from the conflict
+    let other = Client::connect("http://[::1]:8183").await.unwrap();
+    let entry = other
+      .ledger(b"client-handle")
+      .append(b"third")
+      .await
+      .unwrap();
+    assert_eq!(entry.height, 3);
+    assert_eq!(
+      ledger.append(b"fourth").await,
+      Err(ClientError::AppendConflict(3))
+    );
+    assert_eq!(ledger.append(b"fourth").await.unwrap().height, 4);
+    let latest = other.ledger(b"client-handle").read_latest().await.unwrap();
+    assert_eq!((latest.height, latest.block), (4, b"fourth".to_vec()));
+
+    // creating a ledger again opens it if the genesis block is the same
+    assert!(other
+      .create_ledger(b"client-handle", b"genesis")
+      .await
+      .is_ok());
+    assert_eq!(
+      other.create_ledger(b"client-handle", b"other").await.err(),
+      Some(ClientError::RequestFailed(Code::AlreadyExists))
+    );
+
+    // a client knows the handle of a ledger it creates from a nonce before it exists
+    let handle = derive_ledger_handle(b"nonce", b"genesis").unwrap();
+    let derived = client
+      .create_derived_ledger(b"nonce", b"genesis")
+      .await
+      .unwrap();
+    assert_eq!(derived.handle(), handle.as_slice());
+    assert_eq!(
+      other.ledger(&handle).read(0).await.unwrap().block,
+      b"genesis".to_vec()
+    );
+    assert!(other
+      .create_derived_ledger(b"nonce", b"genesis")
+      .await
+      .is_ok());
+
+    // a client that lost the handle finds a labeled ledger by its label
+    client
+      .create_labeled_ledger(b"labeled-handle", b"genesis", "orders")
+      .await
+      .unwrap();
+    let found = other.find_ledger("orders").await.unwrap();
+    assert_eq!(found.handle(), b"labeled-handle");
+    assert_eq!(found.read(0).await.unwrap().block, b"genesis".to_vec());
+    assert_eq!(
+      other.find_ledger("invoices").await.err(),
+      Some(ClientError::RequestFailed(Code::NotFound))
+    );
+
+    // the receipts of a ledger with an endorsement policy are checked against it
+    let genesis = EndorsementPolicy::All.to_genesis_bytes(b"genesis");
+    let ledger = client
+      .create_ledger(b"policy-handle", &genesis)
+      .await
+      .unwrap();
+    assert_eq!(ledger.append(b"first").await.unwrap().height, 1);
+    let ledger = other.ledger(b"policy-handle");
+    assert_eq!(ledger.read(1).await.unwrap().block, b"first".to_vec());
+    assert_eq!(ledger.read_latest().await.unwrap().height, 1);
+    assert_eq!(
+      other.get_policy(ledger.handle()).unwrap(),
+      Some(EndorsementPolicy::All)
+    );
+  }
+}
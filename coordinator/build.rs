@@ -1,4 +1,11 @@
+use std::{env, path::PathBuf};
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-  tonic_build::compile_protos("../proto/coordinator.proto")?;
+  // the descriptors of the coordinator's services are embedded for the reflection service
+  let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+  tonic_build::configure()
+    .file_descriptor_set_path(out_dir.join("coordinator_descriptor.bin"))
+    .compile(&["../proto/coordinator.proto"], &["../proto"])?;
+  tonic_build::compile_protos("../proto/replication.proto")?;
   Ok(())
 }
@@ -0,0 +1,392 @@
+//! Pipelining of the appends to a ledger. Endorsers sign the appends to a ledger strictly in order
+//! of height, so without a pipeline every append to a ledger waits for a full round trip to the
+//! store and the endorsers. An append whose block is in the store joins its ledger's pipeline
+//! instead. While one round of endorsements is in flight, the appends that arrive queue up, and
+//! the coordinator chains their metablocks onto the speculated tail of the ledger. The next round
+//! endorses all of them in one batch, and their receipts must attest to the chained metablocks. A
+//! round that fails rolls the speculation back, since what the endorsers hold is then unknown.
+use crate::errors::CoordinatorError;
+use ledger::{Block, Handle, MetaBlock, NimbleDigest, NimbleHashTrait, Nonces, Receipts};
+use std::{
+  collections::{BTreeMap, HashMap},
+  ops::Deref,
+  sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::sync::{oneshot, watch};
+
+/// An append whose block is in the ledger store and that awaits its endorsements
+pub struct PipelinedAppend {
+  pub height: usize,
+  pub block_hash: NimbleDigest,
+  pub block: Block,
+  pub nonces: Nonces,
+  metablock: Option<MetaBlock>, // speculated, if the tail it follows was known
+  done: oneshot::Sender<Result<Receipts, CoordinatorError>>,
+}
+
+#[derive(Default)]
+struct PipelineState {
+  pending: BTreeMap<usize, PipelinedAppend>,
+  in_flight: bool,
+  tail: Option<MetaBlock>, // the ledger's tail once the appends so far are endorsed, if known
+}
+
+impl PipelineState {
+  // chains the metablocks of the pending appends onto the tail, as far as their heights follow it
+  fn chain_pending(&mut self) {
+    for append in self.pending.values_mut() {
+      match &self.tail {
+        Some(tail) if tail.get_height() + 1 == append.height => {
+          let metablock = tail.next(&append.block_hash);
+          append.metablock = metablock.clone();
+          self.tail = metablock;
+        },
+        _ => {
+          self.tail = None;
+          return;
+        },
+      }
+    }
+  }
+
+  fn rollback(&mut self) {
+    self.tail = None;
+    for append in self.pending.values_mut() {
+      append.metablock = None;
+    }
+  }
+}
+
+/// The pipeline of the appends to one ledger
+pub struct AppendPipeline {
+  order: tokio::sync::Mutex<()>, // held from storing a block until its append joins the pipeline
+  state: Mutex<PipelineState>,
+  rounds: watch::Sender<u64>, // counts the rounds that finished
+}
+
+impl AppendPipeline {
+  fn new() -> Self {
+    AppendPipeline {
+      order: tokio::sync::Mutex::new(()),
+      state: Mutex::new(PipelineState::default()),
+      rounds: watch::channel(0).0,
+    }
+  }
+
+  fn state(&self) -> MutexGuard<'_, PipelineState> {
+    // a poisoned lock only means a thread panicked while it held the state, which stays coherent
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  /// Orders the appends to the ledger: an append holds the returned guard from storing its block
+  /// until it joins the pipeline, so that appends join in the order of their heights
+  pub async fn lock_order(&self) -> tokio::sync::MutexGuard<'_, ()> {
+    self.order.lock().await
+  }
+
+  /// Adds the append of the block at `height`, whose hash with its nonces is `block_hash`, and
+  /// returns where its receipts, or the error of its round, arrive
+  pub fn enqueue(
+    &self,
+    height: usize,
+    block_hash: NimbleDigest,
+    block: Block,
+    nonces: Nonces,
+  ) -> oneshot::Receiver<Result<Receipts, CoordinatorError>> {
+    let (done, receiver) = oneshot::channel();
+    let mut state = self.state();
+    let metablock = match &state.tail {
+      Some(tail) if tail.get_height() + 1 == height => tail.next(&block_hash),
+      _ => None,
+    };
+    state.tail = metablock.clone();
+    state.pending.insert(
+      height,
+      PipelinedAppend {
+        height,
+        block_hash,
+        block,
+        nonces,
+        metablock,
+        done,
+      },
+    );
+    receiver
+  }
+
+  /// Returns a watch on the number of rounds that finished, with which a caller that could not
+  /// start a round waits for the round in flight
+  pub fn watch_rounds(&self) -> watch::Receiver<u64> {
+    self.rounds.subscribe()
+  }
+
+  /// Starts a round with up to `max_appends` pending appends at consecutive heights, unless a
+  /// round is in flight or no append is pending
+  pub fn start_round(self: &Arc<Self>, max_appends: usize) -> Option<Round> {
+    let mut state = self.state();
+    if state.in_flight {
+      return None;
+    }
+    let mut appends: Vec<PipelinedAppend> = Vec::new();
+    while appends.len() < max_appends.max(1) {
+      let next = match state.pending.first_key_value() {
+        Some((height, _append)) => *height,
+        None => break,
+      };
+      if appends.last().is_some_and(|last| last.height + 1 != next) {
+        break;
+      }
+      appends.push(state.pending.remove(&next).unwrap());
+    }
+    if appends.is_empty() {
+      return None;
+    }
+    state.in_flight = true;
+    Some(Round {
+      pipeline: self.clone(),
+      appends,
+      finished: false,
+    })
+  }
+
+  fn is_idle(&self) -> bool {
+    let state = self.state();
+    !state.in_flight && state.pending.is_empty()
+  }
+}
+
+/// A round of endorsements of appends at consecutive heights
+pub struct Round {
+  pipeline: Arc<AppendPipeline>,
+  appends: Vec<PipelinedAppend>,
+  finished: bool,
+}
+
+impl Round {
+  pub fn appends(&self) -> &[PipelinedAppend] {
+    &self.appends
+  }
+
+  /// Hands each append of the round its receipts, which must attest to the metablock chained
+  /// for it, or the error of the round
+  pub fn finish(mut self, res: Result<Vec<Receipts>, CoordinatorError>) {
+    let appends = std::mem::take(&mut self.appends);
+    let mut state = self.pipeline.state();
+    match res {
+      Ok(receipts) if receipts.len() == appends.len() => {
+        let mut speculated = state.tail.is_some();
+        let mut endorsed = None;
+        for (append, receipts) in appends.into_iter().zip(receipts) {
+          let res = match receipts.get_metablock() {
+            // the endorsers may have added a timestamp, which the hash of a metablock leaves out
+            Ok(metablock)
+              if append
+                .metablock
+                .as_ref()
+                .is_none_or(|m| m.hash() == metablock.hash()) =>
+            {
+              endorsed = Some(metablock);
+              Ok(receipts)
+            },
+            _ => {
+              speculated = false;
+              Err(CoordinatorError::EndorsersNotInSync)
+            },
+          };
+          let _ = append.done.send(res);
+        }
+        // the speculation continues from what the endorsers signed where it fell short
+        if !speculated {
+          state.rollback();
+          state.tail = endorsed;
+          state.chain_pending();
+        }
+      },
+      res => {
+        let error = res.err().unwrap_or(CoordinatorError::EndorsersNotInSync);
+        for append in appends {
+          let _ = append.done.send(Err(error.clone()));
+        }
+        state.rollback();
+      },
+    }
+    state.in_flight = false;
+    drop(state);
+    self.finished = true;
+    self.pipeline.rounds.send_modify(|rounds| *rounds += 1);
+  }
+}
+
+impl Drop for Round {
+  // a round that is abandoned, such as when the request that led it is cancelled, fails its
+  // appends and lets another append lead the next round
+  fn drop(&mut self) {
+    if self.finished {
+      return;
+    }
+    self.appends.clear();
+    let mut state = self.pipeline.state();
+    state.rollback();
+    state.in_flight = false;
+    drop(state);
+    self.pipeline.rounds.send_modify(|rounds| *rounds += 1);
+  }
+}
+
+/// The pipelines of the ledgers with appends in progress
+#[derive(Default)]
+pub struct AppendPipelines {
+  pipelines: Mutex<HashMap<Handle, Arc<AppendPipeline>>>,
+}
+
+/// A ledger's pipeline, which is forgotten once no append uses it and it is idle
+pub struct PipelineRef {
+  pipelines: Arc<AppendPipelines>,
+  handle: Handle,
+  pipeline: Option<Arc<AppendPipeline>>,
+}
+
+impl Deref for PipelineRef {
+  type Target = Arc<AppendPipeline>;
+
+  fn deref(&self) -> &Self::Target {
+    self.pipeline.as_ref().unwrap()
+  }
+}
+
+impl Drop for PipelineRef {
+  fn drop(&mut self) {
+    let mut pipelines = self
+      .pipelines
+      .pipelines
+      .lock()
+      .unwrap_or_else(|e| e.into_inner());
+    drop(self.pipeline.take());
+    // pipelines are only handed out under the lock, so no other append holds an unshared one
+    if let Some(pipeline) = pipelines.get(&self.handle) {
+      if Arc::strong_count(pipeline) == 1 && pipeline.is_idle() {
+        pipelines.remove(&self.handle);
+      }
+    }
+  }
+}
+
+impl AppendPipelines {
+  /// Returns the pipeline of the ledger `handle`
+  pub fn get(self: &Arc<Self>, handle: &Handle) -> PipelineRef {
+    let mut pipelines = self.pipelines.lock().unwrap_or_else(|e| e.into_inner());
+    let pipeline = pipelines
+      .entry(*handle)
+      .or_insert_with(|| Arc::new(AppendPipeline::new()))
+      .clone();
+    PipelineRef {
+      pipelines: self.clone(),
+      handle: *handle,
+      pipeline: Some(pipeline),
+    }
+  }
+
+  #[cfg(test)]
+  fn len(&self) -> usize {
+    self.pipelines.lock().unwrap().len()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    IdSig, Receipt,
+  };
+
+  fn receipts_for(metablock: &MetaBlock) -> Receipts {
+    let sk = PrivateKey::new();
+    let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+    let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+    let mut receipts = Receipts::new();
+    receipts.add(&Receipt::new(
+      NimbleDigest::default(),
+      metablock.clone(),
+      id_sig,
+    ));
+    receipts
+  }
+
+  fn block_hash(height: usize) -> NimbleDigest {
+    NimbleDigest::digest(&height.to_le_bytes())
+  }
+
+  fn enqueue(
+    pipeline: &AppendPipeline,
+    height: usize,
+  ) -> oneshot::Receiver<Result<Receipts, CoordinatorError>> {
+    pipeline.enqueue(height, block_hash(height), Block::new(&[]), Nonces::new())
+  }
+
+  #[test]
+  fn test_append_pipeline() {
+    let pipelines = Arc::new(AppendPipelines::default());
+    let handle = NimbleDigest::digest(b"ledger");
+    let pipeline = pipelines.get(&handle);
+
+    // the first round learns the tail, onto which the appends that queue behind it are chained
+    let mut first = enqueue(&pipeline, 1);
+    let round = pipeline.start_round(8).unwrap();
+    assert!(pipeline.start_round(8).is_none());
+    let mut second = enqueue(&pipeline, 2);
+    let mut third = enqueue(&pipeline, 3);
+    let genesis = MetaBlock::genesis(&block_hash(0));
+    let tail = MetaBlock::new(&genesis.hash(), &block_hash(1), 1);
+    round.finish(Ok(vec![receipts_for(&tail)]));
+    assert!(first.try_recv().unwrap().is_ok());
+
+    // the next round endorses both queued appends, whose receipts match their speculation
+    let round = pipeline.start_round(8).unwrap();
+    assert_eq!(
+      round.appends().iter().map(|a| a.height).collect::<Vec<_>>(),
+      vec![2, 3]
+    );
+    let second_metablock = MetaBlock::new(&tail.hash(), &block_hash(2), 2);
+    let third_metablock = MetaBlock::new(&second_metablock.hash(), &block_hash(3), 3);
+    assert_eq!(round.appends()[1].metablock, Some(third_metablock.clone()));
+    let mut fourth = enqueue(&pipeline, 4);
+    round.finish(Ok(vec![
+      receipts_for(&second_metablock),
+      receipts_for(&third_metablock),
+    ]));
+    assert!(second.try_recv().unwrap().is_ok());
+    assert!(third.try_recv().unwrap().is_ok());
+
+    // receipts for another metablock than the speculated one fail the append
+    let round = pipeline.start_round(8).unwrap();
+    let forked = MetaBlock::new(&NimbleDigest::default(), &block_hash(4), 4);
+    round.finish(Ok(vec![receipts_for(&forked)]));
+    assert_eq!(
+      fourth.try_recv().unwrap().err(),
+      Some(CoordinatorError::EndorsersNotInSync)
+    );
+
+    // a failed round fails its appends and rolls the speculation back
+    let mut fifth = enqueue(&pipeline, 5);
+    let round = pipeline.start_round(8).unwrap();
+    let mut sixth = enqueue(&pipeline, 6);
+    round.finish(Err(CoordinatorError::FailedToAppendLedger));
+    assert_eq!(
+      fifth.try_recv().unwrap().err(),
+      Some(CoordinatorError::FailedToAppendLedger)
+    );
+    let round = pipeline.start_round(8).unwrap();
+    assert_eq!(round.appends()[0].metablock, None);
+
+    // an abandoned round fails its appends and lets the next one start
+    drop(round);
+    assert!(sixth.try_recv().is_err());
+    assert!(pipeline.start_round(8).is_none());
+
+    // an idle pipeline is forgotten once no append holds it
+    assert_eq!(pipelines.len(), 1);
+    drop(pipeline);
+    assert_eq!(pipelines.len(), 0);
+  }
+}
@@ -0,0 +1,739 @@
+//! Authentication of the clients of the gRPC service. A client presents a static API key in the
+//! `x-nimble-api-key` metadata, or a JWT in the `authorization` metadata as `Bearer <token>`, and
+//! each key, and each secret that signs tokens, grants a set of permissions. The keys and secrets
+//! come from a JSON file that the coordinator rereads when it changes:
+//!
+//! ```json
+//! {
+//!   "api_keys": [{ "key": "...", "permissions": ["read", "append"] }],
+//!   "jwt_keys": [{ "kid": "main", "secret": "<base64url>", "issuer": "...", "audience": "...",
+//!                  "permissions": ["create", "append", "read"] }],
+//!   "anonymous": ["read"]
+//! }
+//! ```
+//!
+//! Tokens are signed with HS256 and must carry an `exp` claim; a `scope` claim narrows the
+//! permissions of the secret that signed the token. Requests without credentials have the
+//! `anonymous` permissions, none by default.
+//!
+//! A key or a secret with `"namespaces": ["acme"]` only reaches the ledgers in those namespaces
+//! (see `ledger::namespace`), so that one tenant cannot read or append to another's ledgers; a
+//! `namespace` claim narrows a token to one of the namespaces of its secret. Credentials without
+//! namespaces reach every ledger.
+use crate::errors::CoordinatorError;
+use hmac::{Hmac, Mac};
+use ledger::{
+  namespace::{is_valid_namespace, namespace_of},
+  API_KEY_METADATA,
+};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+  sync::{Arc, RwLock},
+  task::{Context, Poll},
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tonic::{
+  body::BoxBody,
+  codegen::{http, BoxFuture},
+  Status,
+};
+use tower::{Layer, Service};
+
+const AUTHORIZATION_METADATA: &str = "authorization";
+const BEARER_PREFIX: &str = "Bearer ";
+const JWT_ALGORITHM: &str = "HS256";
+
+/// What a client may do
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+  /// create ledgers
+  Create,
+  /// append to ledgers and checkpoint them
+  Append,
+  /// read ledgers and the view ledger
+  Read,
+  /// call the admin service, which reconfigures the endorsers
+  Admin,
+}
+
+impl Permission {
+  // the permission that a call of the gRPC method at `path` needs, if any
+  fn required_by(path: &str) -> Option<Permission> {
+    match path.rsplit_once('/') {
+      Some(("/grpc.health.v1.Health", _method)) => None,
+      Some(("/coordinator_proto.Admin", _method)) => Some(Permission::Admin),
+      Some((_service, "NewLedger")) => Some(Permission::Create),
+      Some((
+        _service,
+        "Append" | "AppendBatch" | "AppendChunked" | "AppendHashOnly" | "Checkpoint",
+      )) => Some(Permission::Append),
+      _ => Some(Permission::Read),
+    }
+  }
+
+  fn from_scope(name: &str) -> Option<Permission> {
+    match name {
+      "create" => Some(Permission::Create),
+      "append" => Some(Permission::Append),
+      "read" => Some(Permission::Read),
+      "admin" => Some(Permission::Admin),
+      _ => None,
+    }
+  }
+}
+
+/// The namespaces whose ledgers a request's credentials reach
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NamespaceScope {
+  /// every ledger, in a namespace or not
+  All,
+  /// the ledgers in these namespaces only
+  Only(HashSet<String>),
+}
+
+impl NamespaceScope {
+  fn from_config(namespaces: Option<HashSet<String>>) -> Result<Self, CoordinatorError> {
+    match namespaces {
+      None => Ok(NamespaceScope::All),
+      Some(namespaces) if namespaces.iter().all(|ns| is_valid_namespace(ns)) => {
+        Ok(NamespaceScope::Only(namespaces))
+      },
+      Some(_namespaces) => Err(CoordinatorError::InvalidAuthConfig),
+    }
+  }
+
+  /// whether the credentials reach the ledger `handle_bytes`
+  pub fn permits(&self, handle_bytes: &[u8]) -> bool {
+    match self {
+      NamespaceScope::All => true,
+      NamespaceScope::Only(namespaces) => {
+        namespace_of(handle_bytes).is_some_and(|namespace| namespaces.contains(namespace))
+      },
+    }
+  }
+
+  /// the namespaces that the credentials reach, or None for every ledger
+  pub fn namespaces(&self) -> Option<&HashSet<String>> {
+    match self {
+      NamespaceScope::All => None,
+      NamespaceScope::Only(namespaces) => Some(namespaces),
+    }
+  }
+
+  // narrows the scope to `namespace`, if the scope reaches it
+  fn narrow(&self, namespace: &str) -> Option<NamespaceScope> {
+    match self {
+      NamespaceScope::Only(namespaces) if !namespaces.contains(namespace) => None,
+      _ => Some(NamespaceScope::Only(HashSet::from([namespace.to_string()]))),
+    }
+  }
+}
+
+// What a key, or a token, grants
+#[derive(Clone)]
+struct Grant {
+  permissions: HashSet<Permission>,
+  namespaces: NamespaceScope,
+}
+
+#[derive(Deserialize)]
+struct ApiKeyConfig {
+  key: String,
+  permissions: HashSet<Permission>,
+  #[serde(default)]
+  namespaces: Option<HashSet<String>>,
+}
+
+#[derive(Deserialize)]
+struct JwtKeyConfig {
+  #[serde(default)]
+  kid: Option<String>,
+  secret: String,
+  #[serde(default)]
+  issuer: Option<String>,
+  #[serde(default)]
+  audience: Option<String>,
+  permissions: HashSet<Permission>,
+  #[serde(default)]
+  namespaces: Option<HashSet<String>>,
+}
+
+#[derive(Deserialize)]
+struct AuthConfig {
+  #[serde(default)]
+  api_keys: Vec<ApiKeyConfig>,
+  #[serde(default)]
+  jwt_keys: Vec<JwtKeyConfig>,
+  #[serde(default)]
+  anonymous: HashSet<Permission>,
+}
+
+struct JwtKey {
+  kid: Option<String>,
+  secret: Vec<u8>,
+  issuer: Option<String>,
+  audience: Option<String>,
+  permissions: HashSet<Permission>,
+  namespaces: NamespaceScope,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+  alg: String,
+  #[serde(default)]
+  kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+  exp: u64,
+  #[serde(default)]
+  nbf: Option<u64>,
+  #[serde(default)]
+  iss: Option<String>,
+  #[serde(default)]
+  aud: Option<serde_json::Value>,
+  #[serde(default)]
+  scope: Option<String>,
+  #[serde(default)]
+  namespace: Option<String>,
+}
+
+/// The credentials that the coordinator accepts and what each permits
+pub struct AuthPolicy {
+  api_keys: HashMap<String, Grant>,
+  jwt_keys: Vec<JwtKey>,
+  anonymous: HashSet<Permission>,
+}
+
+impl AuthPolicy {
+  /// Parses a policy from the JSON of an auth file
+  pub fn from_json(bytes: &[u8]) -> Result<Self, CoordinatorError> {
+    let res = serde_json::from_slice::<AuthConfig>(bytes);
+    if res.is_err() {
+      return Err(CoordinatorError::InvalidAuthConfig);
+    }
+    let config = res.unwrap();
+
+    let mut jwt_keys = Vec::new();
+    for key in config.jwt_keys {
+      let secret = match base64_url::decode(&key.secret) {
+        Ok(secret) if !secret.is_empty() => secret,
+        _ => return Err(CoordinatorError::InvalidAuthConfig),
+      };
+      jwt_keys.push(JwtKey {
+        kid: key.kid,
+        secret,
+        issuer: key.issuer,
+        audience: key.audience,
+        permissions: key.permissions,
+        namespaces: NamespaceScope::from_config(key.namespaces)?,
+      });
+    }
+    let mut api_keys = HashMap::new();
+    for key in config.api_keys {
+      let grant = Grant {
+        permissions: key.permissions,
+        namespaces: NamespaceScope::from_config(key.namespaces)?,
+      };
+      api_keys.insert(key.key, grant);
+    }
+    Ok(AuthPolicy {
+      api_keys,
+      jwt_keys,
+      anonymous: config.anonymous,
+    })
+  }
+
+  /// Checks that the credentials in `headers` permit `permission` at `now` (seconds since the
+  /// epoch), and returns the namespaces that they reach. Credentials that are presented must be
+  /// valid, even for what anonymous clients may do.
+  pub fn authorize(
+    &self,
+    headers: &http::HeaderMap,
+    permission: Permission,
+    now: u64,
+  ) -> Result<NamespaceScope, CoordinatorError> {
+    let api_key = headers.get(API_KEY_METADATA).map(|v| v.to_str());
+    let token = headers
+      .get(AUTHORIZATION_METADATA)
+      .map(|v| v.to_str().map(|v| v.strip_prefix(BEARER_PREFIX)));
+    let grant = match (api_key, token) {
+      (None, None) => {
+        if self.anonymous.contains(&permission) {
+          return Ok(NamespaceScope::All);
+        }
+        return Err(CoordinatorError::Unauthenticated);
+      },
+      (Some(Ok(api_key)), None) => self.api_keys.get(api_key).cloned(),
+      (None, Some(Ok(Some(token)))) => self.verify_token(token, now),
+      _ => None,
+    };
+    match grant {
+      Some(grant) if grant.permissions.contains(&permission) => Ok(grant.namespaces),
+      Some(_grant) => Err(CoordinatorError::PermissionDenied),
+      None => Err(CoordinatorError::Unauthenticated),
+    }
+  }
+
+  // Returns what a token that a known secret signed and that is valid at `now` grants
+  fn verify_token(&self, token: &str, now: u64) -> Option<Grant> {
+    let (signing_input, signature) = token.rsplit_once('.')?;
+    let (header, claims) = signing_input.split_once('.')?;
+    let header: JwtHeader = serde_json::from_slice(&base64_url::decode(header).ok()?).ok()?;
+    if header.alg != JWT_ALGORITHM {
+      return None;
+    }
+    let signature = base64_url::decode(signature).ok()?;
+    let key = self
+      .jwt_keys
+      .iter()
+      .filter(|key| header.kid.is_none() || key.kid == header.kid)
+      .find(|key| {
+        let mut mac = match Hmac::<Sha256>::new_from_slice(&key.secret) {
+          Ok(mac) => mac,
+          Err(_e) => return false,
+        };
+        mac.update(signing_input.as_bytes());
+        mac.verify_slice(&signature).is_ok()
+      })?;
+
+    let claims: JwtClaims = serde_json::from_slice(&base64_url::decode(claims).ok()?).ok()?;
+    if claims.exp <= now || claims.nbf.is_some_and(|nbf| nbf > now) {
+      return None;
+    }
+    if key.issuer.is_some() && key.issuer != claims.iss {
+      return None;
+    }
+    if let Some(audience) = &key.audience {
+      let audiences = match claims.aud {
+        Some(serde_json::Value::String(aud)) => vec![aud],
+        Some(serde_json::Value::Array(auds)) => auds
+          .into_iter()
+          .filter_map(|aud| aud.as_str().map(String::from))
+          .collect(),
+        _ => Vec::new(),
+      };
+      if !audiences.contains(audience) {
+        return None;
+      }
+    }
+
+    let namespaces = match claims.namespace {
+      Some(namespace) => key.namespaces.narrow(&namespace)?,
+      None => key.namespaces.clone(),
+    };
+    let permissions = match claims.scope {
+      Some(scope) => scope
+        .split(' ')
+        .filter_map(Permission::from_scope)
+        .filter(|permission| key.permissions.contains(permission))
+        .collect(),
+      None => key.permissions.clone(),
+    };
+    Some(Grant {
+      permissions,
+      namespaces,
+    })
+  }
+}
+
+/// An auth policy read from a file, which is read again when the file changes
+pub struct Authenticator {
+  path: PathBuf,
+  policy: RwLock<(Arc<AuthPolicy>, Option<SystemTime>)>, // the policy and the file's mtime
+}
+
+impl Authenticator {
+  pub fn from_file(path: &Path) -> Result<Self, CoordinatorError> {
+    let (policy, modified) = Self::read(path)?;
+    Ok(Authenticator {
+      path: path.to_path_buf(),
+      policy: RwLock::new((Arc::new(policy), modified)),
+    })
+  }
+
+  fn read(path: &Path) -> Result<(AuthPolicy, Option<SystemTime>), CoordinatorError> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let res = std::fs::read(path);
+    if res.is_err() {
+      return Err(CoordinatorError::FailedToReadAuthConfig);
+    }
+    Ok((AuthPolicy::from_json(&res.unwrap())?, modified))
+  }
+
+  /// Reads the file again if it changed since it was last read, and returns whether it did. A
+  /// file that fails to parse leaves the policy as it was.
+  pub fn reload(&self) -> Result<bool, CoordinatorError> {
+    let modified = std::fs::metadata(&self.path)
+      .and_then(|m| m.modified())
+      .ok();
+    if modified.is_some() && modified == self.policy()?.1 {
+      return Ok(false);
+    }
+    let (policy, modified) = Self::read(&self.path)?;
+    let res = self.policy.write();
+    if res.is_err() {
+      return Err(CoordinatorError::FailedToAcquireWriteLock);
+    }
+    *res.unwrap() = (Arc::new(policy), modified);
+    Ok(true)
+  }
+
+  fn policy(&self) -> Result<(Arc<AuthPolicy>, Option<SystemTime>), CoordinatorError> {
+    let res = self.policy.read();
+    if res.is_err() {
+      return Err(CoordinatorError::FailedToAcquireReadLock);
+    }
+    Ok(res.unwrap().clone())
+  }
+
+  /// Checks the credentials in `headers` for a call of the gRPC method at `path`, and returns the
+  /// namespaces that they reach, or None if the method needs no permission. Fails with the
+  /// status to fail the call with if they do not permit it.
+  #[allow(clippy::result_large_err)]
+  pub fn check(
+    &self,
+    path: &str,
+    headers: &http::HeaderMap,
+  ) -> Result<Option<NamespaceScope>, Status> {
+    let permission = match Permission::required_by(path) {
+      Some(permission) => permission,
+      None => return Ok(None),
+    };
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |d| d.as_secs());
+    let res = self
+      .policy()
+      .and_then(|(policy, _modified)| policy.authorize(headers, permission, now));
+    match res {
+      Ok(scope) => Ok(Some(scope)),
+      Err(CoordinatorError::Unauthenticated) => Err(Status::unauthenticated(
+        "The request carries no valid API key or bearer token",
+      )),
+      Err(CoordinatorError::PermissionDenied) => Err(Status::permission_denied(format!(
+        "The request's credentials do not grant the {:?} permission",
+        permission
+      ))),
+      Err(error) => Err(Status::internal(format!("{:?}", error))),
+    }
+  }
+
+  // Checks the credentials of a request, returning the status to fail it with if they do not
+  // permit it; otherwise, records the namespaces that they reach in the request's extensions,
+  // against which the handlers check the ledger that the request names
+  fn rejection<B>(&self, req: &mut http::Request<B>) -> Option<Status> {
+    match self.check(req.uri().path(), req.headers()) {
+      Ok(scope) => {
+        if let Some(scope) = scope {
+          req.extensions_mut().insert(scope);
+        }
+        None
+      },
+      Err(status) => Some(status),
+    }
+  }
+}
+
+/// A layer for the gRPC server that turns away requests whose credentials do not permit them
+#[derive(Clone)]
+pub struct AuthLayer {
+  authenticator: Arc<Authenticator>,
+}
+
+impl AuthLayer {
+  pub fn new(authenticator: Arc<Authenticator>) -> Self {
+    AuthLayer { authenticator }
+  }
+}
+
+impl<S> Layer<S> for AuthLayer {
+  type Service = Auth<S>;
+
+  fn layer(&self, inner: S) -> Self::Service {
+    Auth {
+      inner,
+      authenticator: self.authenticator.clone(),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct Auth<S> {
+  inner: S,
+  authenticator: Arc<Authenticator>,
+}
+
+impl<S, B> Service<http::Request<B>> for Auth<S>
+where
+  S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+  S::Future: Send + 'static,
+{
+  type Response = http::Response<BoxBody>;
+  type Error = S::Error;
+  type Future = BoxFuture<Self::Response, Self::Error>;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_ready(cx)
+  }
+
+  fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+    match self.authenticator.rejection(&mut req) {
+      None => Box::pin(self.inner.call(req)),
+      Some(status) => {
+        let response = status.to_http();
+        Box::pin(async move { Ok(response) })
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::namespace::namespaced_handle;
+
+  const SECRET: &[u8] = b"a secret that signs tokens";
+
+  fn token(header: &str, claims: &str, secret: &[u8]) -> String {
+    let signing_input = format!(
+      "{}.{}",
+      base64_url::encode(header),
+      base64_url::encode(claims)
+    );
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(signing_input.as_bytes());
+    let signature = mac.finalize().into_bytes();
+    format!("{}.{}", signing_input, base64_url::encode(&signature))
+  }
+
+  fn headers(name: &str, value: &str) -> http::HeaderMap {
+    let mut headers = http::HeaderMap::new();
+    headers.insert(
+      http::header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+      value.parse().unwrap(),
+    );
+    headers
+  }
+
+  fn policy() -> AuthPolicy {
+    let config = format!(
+      r#"{{
+        "api_keys": [{{ "key": "reader", "permissions": ["read"] }}],
+        "jwt_keys": [{{ "kid": "main", "secret": "{}", "issuer": "nimble", "audience": "coordinator",
+                        "permissions": ["create", "append", "read"] }}]
+      }}"#,
+      base64_url::encode(SECRET)
+    );
+    AuthPolicy::from_json(config.as_bytes()).unwrap()
+  }
+
+  #[test]
+  fn test_api_keys() {
+    let policy = policy();
+    let reader = headers(API_KEY_METADATA, "reader");
+    assert_eq!(
+      policy.authorize(&reader, Permission::Read, 0),
+      Ok(NamespaceScope::All)
+    );
+    assert_eq!(
+      policy.authorize(&reader, Permission::Append, 0),
+      Err(CoordinatorError::PermissionDenied)
+    );
+    assert_eq!(
+      policy.authorize(&headers(API_KEY_METADATA, "writer"), Permission::Read, 0),
+      Err(CoordinatorError::Unauthenticated)
+    );
+    assert_eq!(
+      policy.authorize(&http::HeaderMap::new(), Permission::Read, 0),
+      Err(CoordinatorError::Unauthenticated)
+    );
+
+    assert!(AuthPolicy::from_json(b"{\"api_keys\": [{\"key\": \"k\"}]}").is_err());
+    assert!(AuthPolicy::from_json(b"{\"anonymous\": [\"read\"]}")
+      .unwrap()
+      .authorize(&http::HeaderMap::new(), Permission::Read, 0)
+      .is_ok());
+  }
+
+  #[test]
+  fn test_bearer_tokens() {
+    let policy = policy();
+    let header = r#"{"alg":"HS256","kid":"main"}"#;
+    let claims = r#"{"iss":"nimble","aud":"coordinator","exp":100,"scope":"read append"}"#;
+    let bearer = |token: String| headers(AUTHORIZATION_METADATA, &format!("Bearer {}", token));
+
+    let valid = bearer(token(header, claims, SECRET));
+    assert_eq!(
+      policy.authorize(&valid, Permission::Append, 50),
+      Ok(NamespaceScope::All)
+    );
+    // the scope narrows what the secret grants
+    assert_eq!(
+      policy.authorize(&valid, Permission::Create, 50),
+      Err(CoordinatorError::PermissionDenied)
+    );
+    // expired tokens, tokens signed by another secret or with another algorithm are rejected
+    assert_eq!(
+      policy.authorize(&valid, Permission::Append, 100),
+      Err(CoordinatorError::Unauthenticated)
+    );
+    let forged = bearer(token(header, claims, b"another secret"));
+    assert!(policy.authorize(&forged, Permission::Read, 50).is_err());
+    let none = bearer(token(r#"{"alg":"none"}"#, claims, SECRET));
+    assert!(policy.authorize(&none, Permission::Read, 50).is_err());
+
+    // the issuer and the audience must match those of the secret
+    let other_audience = r#"{"iss":"nimble","aud":["other"],"exp":100}"#;
+    let token_for_other = bearer(token(header, other_audience, SECRET));
+    assert!(policy
+      .authorize(&token_for_other, Permission::Read, 50)
+      .is_err());
+    let audiences = r#"{"iss":"nimble","aud":["other","coordinator"],"exp":100}"#;
+    let token_for_both = bearer(token(header, audiences, SECRET));
+    assert_eq!(
+      policy.authorize(&token_for_both, Permission::Create, 50),
+      Ok(NamespaceScope::All)
+    );
+  }
+
+  #[test]
+  fn test_namespace_scopes() {
+    let config = format!(
+      r#"{{
+        "api_keys": [{{ "key": "acme", "permissions": ["read"], "namespaces": ["acme"] }}],
+        "jwt_keys": [{{ "secret": "{}", "permissions": ["read"], "namespaces": ["acme", "globex"] }}]
+      }}"#,
+      base64_url::encode(SECRET)
+    );
+    let policy = AuthPolicy::from_json(config.as_bytes()).unwrap();
+    let acme = namespaced_handle("acme", b"orders");
+    let globex = namespaced_handle("globex", b"orders");
+
+    // a key reaches the ledgers of its namespaces only
+    let scope = policy
+      .authorize(&headers(API_KEY_METADATA, "acme"), Permission::Read, 0)
+      .unwrap();
+    assert!(scope.permits(&acme));
+    assert!(!scope.permits(&globex));
+    assert!(!scope.permits(b"orders"));
+    assert!(NamespaceScope::All.permits(&globex));
+
+    // a token's namespace claim narrows the namespaces of its secret
+    let bearer = |claims: &str| {
+      let token = token(r#"{"alg":"HS256"}"#, claims, SECRET);
+      headers(AUTHORIZATION_METADATA, &format!("Bearer {}", token))
+    };
+    let scope = policy
+      .authorize(&bearer(r#"{"exp":100}"#), Permission::Read, 50)
+      .unwrap();
+    assert!(scope.permits(&globex));
+    let scope = policy
+      .authorize(
+        &bearer(r#"{"exp":100,"namespace":"globex"}"#),
+        Permission::Read,
+        50,
+      )
+      .unwrap();
+    assert_eq!(
+      scope.namespaces().map(|namespaces| namespaces.len()),
+      Some(1)
+    );
+    assert!(!scope.permits(&acme));
+    assert_eq!(
+      policy.authorize(
+        &bearer(r#"{"exp":100,"namespace":"initech"}"#),
+        Permission::Read,
+        50
+      ),
+      Err(CoordinatorError::Unauthenticated)
+    );
+
+    assert!(AuthPolicy::from_json(
+      br#"{"api_keys": [{"key": "k", "permissions": ["read"], "namespaces": ["Not Valid"]}]}"#
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn test_required_permissions() {
+    let required = |method: &str| Permission::required_by(method);
+    assert_eq!(
+      required("/coordinator_proto.Call/NewLedger"),
+      Some(Permission::Create)
+    );
+    assert_eq!(
+      required("/coordinator_proto.Call/AppendChunked"),
+      Some(Permission::Append)
+    );
+    assert_eq!(
+      required("/coordinator_proto.Call/AppendHashOnly"),
+      Some(Permission::Append)
+    );
+    assert_eq!(
+      required("/coordinator_proto.Call/ReadLatest"),
+      Some(Permission::Read)
+    );
+    assert_eq!(
+      required("/coordinator_proto.Admin/ReplaceEndorsers"),
+      Some(Permission::Admin)
+    );
+    assert_eq!(required("/grpc.health.v1.Health/Check"), None);
+  }
+
+  #[test]
+  fn test_reload() {
+    let path = std::env::temp_dir().join(format!("nimble-auth-{}.json", rand::random::<u64>()));
+    std::fs::write(
+      &path,
+      r#"{"api_keys": [{"key": "k", "permissions": ["read"]}]}"#,
+    )
+    .unwrap();
+    let authenticator = Authenticator::from_file(&path).unwrap();
+    assert_eq!(authenticator.reload(), Ok(false));
+
+    // a change to the file takes effect, and a broken file leaves the policy as it was
+    std::fs::write(
+      &path,
+      r#"{"api_keys": [{"key": "k", "permissions": ["append"]}]}"#,
+    )
+    .unwrap();
+    let later = SystemTime::now() + std::time::Duration::from_secs(10);
+    std::fs::File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(later)
+      .unwrap();
+    assert_eq!(authenticator.reload(), Ok(true));
+    let (policy, _modified) = authenticator.policy().unwrap();
+    let key = headers(API_KEY_METADATA, "k");
+    assert_eq!(
+      policy.authorize(&key, Permission::Append, 0),
+      Ok(NamespaceScope::All)
+    );
+
+    std::fs::write(&path, "not json").unwrap();
+    std::fs::File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(later + std::time::Duration::from_secs(10))
+      .unwrap();
+    assert_eq!(
+      authenticator.reload(),
+      Err(CoordinatorError::InvalidAuthConfig)
+    );
+    let (policy, _modified) = authenticator.policy().unwrap();
+    assert_eq!(
+      policy.authorize(&key, Permission::Append, 0),
+      Ok(NamespaceScope::All)
+    );
+    let _ = std::fs::remove_file(&path);
+  }
+}
@@ -0,0 +1,109 @@
+//! Consistency tokens let a client read its own writes through any coordinator that shares the
+//! ledger store. An append returns a token that names the ledger, the height of the appended
+//! entry, and the hash of its metablock; a read of the ledger's tail that carries the token is
+//! served only once the ledger store holds that entry with its receipts. Clients treat the token
+//! as opaque bytes.
+use ledger::{
+  serde::{CustomSerde, CustomSerdeError, Reader},
+  Handle, MetaBlock, NimbleDigest, NimbleHashTrait,
+};
+use std::convert::TryInto;
+
+const CONSISTENCY_TOKEN_VERSION: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsistencyToken {
+  handle: Handle,
+  height: usize,
+  tail_hash: NimbleDigest,
+}
+
+impl ConsistencyToken {
+  /// Returns the token for the entry of the ledger `handle` whose metablock is `metablock`
+  pub fn new(handle: &Handle, metablock: &MetaBlock) -> Self {
+    ConsistencyToken {
+      handle: *handle,
+      height: metablock.get_height(),
+      tail_hash: metablock.hash(),
+    }
+  }
+
+  pub fn get_handle(&self) -> &Handle {
+    &self.handle
+  }
+
+  pub fn get_height(&self) -> usize {
+    self.height
+  }
+
+  pub fn get_tail_hash(&self) -> &NimbleDigest {
+    &self.tail_hash
+  }
+
+  pub fn num_bytes() -> usize {
+    1 + 2 * NimbleDigest::num_bytes() + std::mem::size_of::<u64>()
+  }
+}
+
+impl CustomSerde for ConsistencyToken {
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = vec![CONSISTENCY_TOKEN_VERSION];
+    bytes.extend(self.handle.to_bytes());
+    bytes.extend((self.height as u64).to_le_bytes());
+    bytes.extend(self.tail_hash.to_bytes());
+    bytes
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    if bytes.len() != ConsistencyToken::num_bytes() {
+      return Err(CustomSerdeError::IncorrectLength);
+    }
+    let mut reader = Reader::new("ConsistencyToken", bytes);
+    if reader.read_u8()? != CONSISTENCY_TOKEN_VERSION {
+      return Err(CustomSerdeError::UnsupportedVersion);
+    }
+    let handle = NimbleDigest::from_bytes(reader.take(NimbleDigest::num_bytes())?)?;
+    let height: usize = reader
+      .read_u64()?
+      .try_into()
+      .map_err(|_| CustomSerdeError::InternalError)?;
+    let tail_hash = NimbleDigest::from_bytes(reader.remaining())?;
+    Ok(ConsistencyToken {
+      handle,
+      height,
+      tail_hash,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_consistency_token() {
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    let metablock = MetaBlock::new(
+      &NimbleDigest::digest("prev".as_bytes()),
+      &NimbleDigest::digest("block".as_bytes()),
+      3,
+    );
+    let token = ConsistencyToken::new(&handle, &metablock);
+    assert_eq!(token.get_height(), 3);
+    assert_eq!(token.get_tail_hash(), &metablock.hash());
+
+    let bytes = token.to_bytes();
+    assert_eq!(bytes.len(), ConsistencyToken::num_bytes());
+    assert_eq!(ConsistencyToken::from_bytes(&bytes).unwrap(), token);
+    assert_eq!(
+      ConsistencyToken::from_bytes(&bytes[1..]),
+      Err(CustomSerdeError::IncorrectLength)
+    );
+    let mut unknown = bytes;
+    unknown[0] += 1;
+    assert_eq!(
+      ConsistencyToken::from_bytes(&unknown),
+      Err(CustomSerdeError::UnsupportedVersion)
+    );
+  }
+}
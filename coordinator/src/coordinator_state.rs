@@ -1,54 +1,345 @@
-use crate::errors::CoordinatorError;
+use crate::{
+  append_pipeline::{AppendPipelines, Round},
+  consistency::ConsistencyToken,
+  endorser_health::{EndorserHealthReport, EndorserHealthTracker},
+  errors::CoordinatorError,
+  history::{find_tail_as_of, reconstruct_checkpoint, views_up_to, Checkpoint},
+  integrity::{check_store, IntegrityReport},
+  ledger_stats::{LedgerStats, LedgerStatsTracker},
+  misbehavior::EquivocationDetector,
+  reconfigure::{carried_over_endorsers, has_quorum, num_initialized_signers, PreparedViewChange},
+  tail_cache::{TailCache, TailCacheStats},
+};
 use ledger::{
+  attestation::{
+    encode_view_config_with_handovers, encode_view_config_with_lease, retrieve_lease_from_config,
+    verify_public_key_attestation, AttestationVerifier, EndorserAttestations,
+  },
   compute_aggregated_block_hash, compute_cut_diffs, compute_max_cut,
   errors::VerificationError,
+  logging::request_with_id,
+  messages::{SignedStatement, ViewChangeAttestation},
+  namespace::namespace_of,
+  produce_hash_of_state, shard_endorsers,
   signature::{PublicKey, PublicKeyTrait},
-  Block, CustomSerde, EndorserHostnames, Handle, MetaBlock, NimbleDigest, NimbleHashTrait, Nonce,
-  Nonces, Receipt, Receipts, VerifierState,
+  AccessPolicy, AccessRequest, BlobReference, Block, BlockValidation, CheckpointProof, CustomSerde,
+  EndorsementPolicy, EndorserHostnames, ExternalBlock, Handle, InclusionProof, KeyHandover,
+  LedgerSnapshot, MetaBlock, MisbehaviorEvidence, NimbleDigest, NimbleHashTrait, Nonce, Nonces,
+  ReadVisibility, Receipt, Receipts, VerifierState, ENDORSER_LOCKED_DETAILS,
 };
 use rand::random;
+use serde::{Deserialize, Serialize};
 use std::{
   collections::{HashMap, HashSet},
-  convert::TryInto,
+  convert::TryFrom,
+  future::Future,
   ops::Deref,
+  pin::Pin,
   sync::{Arc, RwLock},
+  time::{Duration, Instant},
 };
 use store::ledger::{
-  azure_table::TableLedgerStore, filestore::FileStore, in_memory::InMemoryLedgerStore,
-  mongodb_cosmos::MongoCosmosLedgerStore, LedgerEntry, LedgerStore,
+  archive::{export_store, ArchiveReport},
+  current_timestamp, open_ledger_store, AppendRequest, BoxedLedgerStore, LedgerEntry,
+  LedgerSummary, ReceiptCompaction,
+};
+use store::{content::BoxedContentStore, errors::LedgerStoreError, errors::StorageError};
+use tokio::{
+  io::DuplexStream,
+  sync::{broadcast, mpsc, oneshot::error::TryRecvError},
 };
-use store::{errors::LedgerStoreError, errors::StorageError};
-use tokio::sync::mpsc;
 use tonic::{
-  transport::{Channel, Endpoint},
+  transport::{Channel, ClientTlsConfig, Endpoint, Uri},
   Code, Status,
 };
+use tracing::{info_span, warn, Instrument, Span};
 
 use ledger::endorser_proto;
 
 const DEFAULT_NUM_GRPC_CHANNELS: usize = 1; // the default number of GRPC channels
 
+// Errors that the ledger store returns because of the request keep their meaning, so clients can
+// tell them apart from the store failing
+fn ledger_store_error(error: &LedgerStoreError) -> CoordinatorError {
+  match error {
+    LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)
+    | LedgerStoreError::LedgerError(StorageError::InvalidKey) => CoordinatorError::UnknownLedger,
+    LedgerStoreError::LedgerError(StorageError::DuplicateKey) => {
+      CoordinatorError::LedgerAlreadyExists
+    },
+    LedgerStoreError::LedgerError(StorageError::IncorrectConditionalData) => {
+      CoordinatorError::HeightMismatch
+    },
+    LedgerStoreError::LedgerError(StorageError::InvalidIndex) => CoordinatorError::InvalidHeight,
+    _ => CoordinatorError::FailedToCallLedgerStore,
+  }
+}
+
 struct EndorserClients {
   clients: Vec<endorser_proto::endorser_call_client::EndorserCallClient<Channel>>,
   uri: String,
+  incarnation: u64,
+  attestation: Vec<u8>, // the evidence binding the endorser's public key; empty if not attested
+  health: EndorserHealthTracker,
 }
 
 type EndorserConnMap = HashMap<Vec<u8>, EndorserClients>;
 
-type LedgerStoreRef = Arc<Box<dyn LedgerStore + Send + Sync>>;
+pub type LedgerStoreRef = Arc<BoxedLedgerStore>;
+
+/// Opens a connection to the endorser at a URI in place of a network connection, so that the
+/// coordinator can call endorsers that run in the same process
+pub type EndorserConnector = Arc<
+  dyn Fn(Uri) -> Pin<Box<dyn Future<Output = Result<DuplexStream, std::io::Error>> + Send>>
+    + Send
+    + Sync,
+>;
 
 pub struct CoordinatorState {
-  pub(crate) ledger_store: LedgerStoreRef,
+  pub ledger_store: LedgerStoreRef,
   conn_map: Arc<RwLock<EndorserConnMap>>,
   verifier_state: Arc<RwLock<VerifierState>>,
   num_grpc_channels: usize,
+  maintenance_deadline: Arc<RwLock<Option<Instant>>>, // set while appends are rejected
+  endorsement_policies: Arc<RwLock<HashMap<Handle, EndorsementPolicy>>>, // cached from genesis
+  access_policies: Arc<RwLock<HashMap<Handle, AccessPolicy>>>, // cached from genesis
+  ledger_stats: Arc<LedgerStatsTracker>,
+  tail_cache: Arc<TailCache>, // the entries at the tails of recently read ledgers
+  view_changes: broadcast::Sender<ViewChangeNotification>,
+  ledger_appends: broadcast::Sender<LedgerAppendNotification>,
+  admin_ledger_lock: Arc<tokio::sync::Mutex<()>>, // serializes appends to the admin ledger
+  checkpoint_ledger_lock: Arc<tokio::sync::Mutex<()>>, // serializes appends of snapshots
+  draining: Arc<RwLock<HashSet<Vec<u8>>>>, // endorsers being decommissioned; get no new writes
+  checkpoints: Arc<RwLock<HashMap<usize, Arc<Checkpoint>>>>, // verified, keyed by view height
+  endorser_tls: Option<ClientTlsConfig>,   // applied to endorsers with https URIs
+  attestation_verifier: Arc<RwLock<Option<Box<dyn AttestationVerifier>>>>,
+  endorser_allowlist: Arc<RwLock<Option<HashSet<Vec<u8>>>>>, // the keys endorsers may have
+  block_validation: Arc<RwLock<BlockValidation>>,            // the blocks that appends may carry
+  blob_store: Arc<RwLock<Option<BlobStore>>>, // holds the payloads of chunked appends
+  append_request_retention: Arc<RwLock<Duration>>, // how long appends are deduplicated
+  append_pipelines: Arc<AppendPipelines>,
+  append_pipeline_depth: Arc<RwLock<usize>>, // the most appends in a round; 0 disables pipelining
+  attest_timestamps: Arc<RwLock<bool>>,      // whether appends propose timestamps to endorsers
+  endorser_lease: Arc<RwLock<Option<Duration>>>, // how long the endorsers of a view may sign
+  view_lease_expiry: Arc<RwLock<Option<u64>>>, // ms since epoch when the current view's lease ends
+  endorser_connector: Option<EndorserConnector>, // reaches in-process endorsers
+  misbehavior: Arc<RwLock<EquivocationDetector>>, // cross-checks the receipts of endorsers
+  misbehavior_ledger_lock: Arc<tokio::sync::Mutex<()>>, // serializes appends of evidence
+  integrity_report: Arc<RwLock<Option<IntegrityReport>>>, // from the last check of the store
+  integrity_check_lock: Arc<tokio::sync::Mutex<()>>, // runs one check of the store at a time
+  // held shared by the work that must not be cut short when the coordinator exits
+  in_flight: Arc<tokio::sync::RwLock<()>>,
+}
+
+// The blob store of chunked appends, with the largest payload it accepts
+#[derive(Clone)]
+struct BlobStore {
+  store: Arc<BoxedContentStore>,
+  max_blob_size: Option<usize>,
+}
+
+/// The outcome of decommissioning an endorser
+#[derive(Clone, Debug)]
+pub struct DecommissionReport {
+  pub pk: Vec<u8>,
+  pub final_state: Receipt, // the endorser's signed statement of its final state
+  pub view_height: usize,   // the view that no longer includes the endorser
+  pub active_endorsers: usize,
+  pub num_endorsers: usize,
+}
+
+/// The outcome of rotating an endorser's key
+#[derive(Clone, Debug)]
+pub struct KeyRotationReport {
+  pub pk: Vec<u8>,           // the endorser's new public key
+  pub handover: KeyHandover, // the old key's statement that the new key succeeds it
+  pub view_height: usize,    // the view that lists the new key
+}
+
+/// A ledger as listed by `list_ledgers`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerListing {
+  /// the digest of the handle, under which the store keeps the ledger
+  pub handle: Handle,
+  /// untrusted coordinator time (ms since epoch) when the genesis entry was stored
+  pub created_at: Option<u64>,
+  /// the index of the ledger's tail
+  pub height: usize,
+  /// the digest of the genesis block's application bytes, without its policies
+  pub app_bytes_digest: NimbleDigest,
+}
+
+/// An entry appended to a ledger, as delivered to clients subscribed to the ledger
+#[derive(Clone, Debug)]
+pub struct LedgerAppendNotification {
+  pub handle: Handle,
+  pub index: usize,
+  pub block: Block,
+  pub nonces: Nonces,
+  pub receipts: Receipts,
+  pub metablock: MetaBlock,
+}
+
+/// A view change committed by the coordinator, as delivered to clients watching for them
+#[derive(Clone, Debug)]
+pub struct ViewChangeNotification {
+  pub block: Block,
+  pub receipts: Receipts,
+  pub height: usize,
+  pub attestations: Vec<u8>,
 }
 
 const ENDORSER_MPSC_CHANNEL_BUFFER: usize = 8; // limited by the number of endorsers
 const ENDORSER_CONNECT_TIMEOUT: u64 = 10; // seconds: the connect timeout to endorsres
 const ENDORSER_REQUEST_TIMEOUT: u64 = 10; // seconds: the request timeout to endorsers
+const ENDORSER_KEEPALIVE_INTERVAL: u64 = 10; // seconds: between HTTP/2 pings on idle connections
+const ENDORSER_KEEPALIVE_TIMEOUT: u64 = 5; // seconds: a ping unanswered by then closes a connection
+pub const ENDORSER_LOCKED_MAX_RETRIES: usize = 10; // the number of retries while an endorser is locked
+const ENDORSER_LOCKED_RETRY_SLEEP: u64 = 50; // ms: the wait between retries to a locked endorser
+const VIEW_CHANGE_CHANNEL_BUFFER: usize = 16; // view changes buffered for slow watchers
+const LEDGER_APPEND_CHANNEL_BUFFER: usize = 256; // appends buffered for slow subscribers
+const CONSISTENCY_TOKEN_TIMEOUT: u64 = 2000; // ms: how long a read waits for a token's append
+const CONSISTENCY_TOKEN_POLL: u64 = 10; // ms: the wait between checks of the ledger store
+const APPEND_REQUEST_CLAIM_TIMEOUT: u64 = 60_000; // ms: how long an unfinished append holds its ID
+const DEFAULT_APPEND_REQUEST_RETENTION: u64 = 600; // seconds: how long a completed ID is remembered
+const DEFAULT_LIST_LEDGERS_PAGE_SIZE: usize = 100; // ledgers listed when the caller names no size
+pub const MAX_LIST_LEDGERS_PAGE_SIZE: usize = 1000; // the most ledgers listed in one page
+pub const MAX_CLIENT_REQUEST_ID_SIZE: usize = 128; // bytes
+pub const MAX_LEDGER_LABEL_SIZE: usize = 256; // bytes
+
+/// The handle of the ledger in which the coordinator records administrative actions. Clients
+/// cannot create or append to it, but can read and verify it like any other ledger.
+pub const ADMIN_LEDGER_HANDLE: &[u8] = b"nimble-admin-ledger";
+
+/// The handle of the ledger in which the coordinator records the snapshots that the Checkpoint RPC
+/// takes. The entry at index `id` is the snapshot with that id; like the admin ledger, clients can
+/// read and verify it but cannot create or append to it.
+pub const CHECKPOINT_LEDGER_HANDLE: &[u8] = b"nimble-checkpoint-ledger";
+// the genesis block of the checkpoint ledger, so that snapshot ids start at 1
+const CHECKPOINT_LEDGER_GENESIS: &[u8] = b"nimble-checkpoint-ledger-genesis";
+
+/// The handle of the ledger in which the coordinator records evidence of endorsers that
+/// equivocated. Each entry's block is a `MisbehaviorEvidence` in its binary encoding; like the
+/// admin ledger, clients can read and verify it but cannot create or append to it.
+pub const MISBEHAVIOR_LEDGER_HANDLE: &[u8] = b"nimble-misbehavior-ledger";
+
+/// An administrative action recorded in the admin ledger
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdminAction {
+  ReplaceEndorsers {
+    uris: Vec<String>,
+    view_height: usize,
+  },
+  RemoveEndorser {
+    uri: String,
+  },
+  ReadmitEndorser {
+    uri: String,
+  },
+  DecommissionEndorser {
+    uri: String,
+    final_state: String, // base64url encoded receipt over the endorser's final state
+  },
+  RotateEndorserKey {
+    uri: String,
+    handover: String, // base64url encoded handover from the endorser's old key to its new key
+    view_height: usize,
+  },
+  ExpelEndorser {
+    uri: String,
+    evidence_index: usize, // the entry of the misbehavior ledger that holds the evidence
+    view_height: usize,
+  },
+  RenewEndorserLeases {
+    lease_expiry: Option<u64>, // ms since epoch, or None if the lease does not expire
+    view_height: usize,
+  },
+  EnterMaintenance {
+    seconds: u64,
+  },
+  ExitMaintenance,
+}
+
+/// An entry of the admin ledger, stored as JSON in the entry's block
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminEvent {
+  #[serde(rename = "Action")]
+  pub action: AdminAction,
+  #[serde(rename = "Timestamp")]
+  pub timestamp: u64, // coordinator time (ms since epoch) when the action was recorded
+}
+
+pub const ATTESTATION_STR: &str = "THIS IS A PLACE HOLDER FOR ATTESTATION";
+
+// An endorser that is locked for a view change becomes available once the view change
+// completes, so requests to it are retried for a while before routing around it
+fn is_endorser_locked(status: &Status) -> bool {
+  matches!(status.code(), Code::Unavailable | Code::FailedPrecondition)
+    && status.details() == ENDORSER_LOCKED_DETAILS
+}
+
+// Returns the metablock of the view that the entry of the view ledger before `view_ledger_height`
+// holds, which is the default metablock before the first view
+pub(crate) fn view_tail_metablock(
+  view_ledger_entry: &LedgerEntry,
+  view_ledger_height: usize,
+) -> Result<MetaBlock, CoordinatorError> {
+  let view_tail_receipts = view_ledger_entry.get_receipts();
+  if view_tail_receipts.is_empty() {
+    if view_ledger_height != 1 {
+      eprintln!(
+        "cannot get view tail metablock from empty receipts (height = {}",
+        view_ledger_height
+      );
+      return Err(CoordinatorError::UnexpectedError);
+    }
+    Ok(MetaBlock::default())
+  } else {
+    view_tail_receipts.get_metablock().map_err(|_e| {
+      eprintln!("faield to retrieve metablock from view receipts");
+      CoordinatorError::UnexpectedError
+    })
+  }
+}
 
-const ATTESTATION_STR: &str = "THIS IS A PLACE HOLDER FOR ATTESTATION";
+// Cross-checks a receipt that an endorser returned for an append to the ledger `handle` with the
+// receipts it returned before
+fn observe_receipt(misbehavior: &RwLock<EquivocationDetector>, handle: &Handle, receipt: &Receipt) {
+  if let Ok(mut detector) = misbehavior.write() {
+    if let Some(evidence) = detector.observe(handle, receipt) {
+      warn!(
+        endorser = %base64_url::encode(evidence.get_endorser()),
+        height = evidence.get_height(),
+        "an endorser signed conflicting appends at the same height"
+      );
+    }
+  }
+}
+
+// Connects to `endpoint` over the network, or with `connector` if there is one
+async fn connect_endpoint(
+  endpoint: Endpoint,
+  connector: Option<EndorserConnector>,
+) -> Result<Channel, tonic::transport::Error> {
+  match connector {
+    Some(connector) => {
+      endpoint
+        .connect_with_connector(tower::service_fn(move |uri: Uri| connector(uri)))
+        .await
+    },
+    None => endpoint.connect().await,
+  }
+}
+
+// Runs a call to an endorser in `span`, which the caller opens under the span of the request
+// that the call serves, so the call's logs name the endorser and it forwards the request's ID
+fn spawn_endorser_call<F>(span: Span, call: F) -> tokio::task::JoinHandle<F::Output>
+where
+  F: Future + Send + 'static,
+  F::Output: Send + 'static,
+{
+  tokio::spawn(call.instrument(span))
+}
 
 async fn get_public_key_with_retry(
   endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
@@ -56,7 +347,7 @@ async fn get_public_key_with_retry(
 ) -> Result<tonic::Response<endorser_proto::GetPublicKeyResp>, Status> {
   loop {
     let res = endorser_client
-      .get_public_key(tonic::Request::new(request.clone()))
+      .get_public_key(request_with_id(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -80,9 +371,10 @@ async fn new_ledger_with_retry(
   endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
   request: endorser_proto::NewLedgerReq,
 ) -> Result<tonic::Response<endorser_proto::NewLedgerResp>, Status> {
+  let mut locked_retries = 0;
   loop {
     let res = endorser_client
-      .new_ledger(tonic::Request::new(request.clone()))
+      .new_ledger(request_with_id(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -93,6 +385,13 @@ async fn new_ledger_with_retry(
           Code::ResourceExhausted => {
             continue;
           },
+          Code::Unavailable | Code::FailedPrecondition
+            if is_endorser_locked(&status) && locked_retries < ENDORSER_LOCKED_MAX_RETRIES =>
+          {
+            locked_retries += 1;
+            tokio::time::sleep(Duration::from_millis(ENDORSER_LOCKED_RETRY_SLEEP)).await;
+            continue;
+          },
           _ => {
             return Err(status);
           },
@@ -106,9 +405,77 @@ async fn append_with_retry(
   endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
   request: endorser_proto::AppendReq,
 ) -> Result<tonic::Response<endorser_proto::AppendResp>, Status> {
+  let mut locked_retries = 0;
+  loop {
+    let res = endorser_client
+      .append(request_with_id(request.clone()))
+      .await;
+    match res {
+      Ok(resp) => {
+        return Ok(resp);
+      },
+      Err(status) => {
+        match status.code() {
+          Code::ResourceExhausted => {
+            continue;
+          },
+          Code::Unavailable | Code::FailedPrecondition
+            if is_endorser_locked(&status) && locked_retries < ENDORSER_LOCKED_MAX_RETRIES =>
+          {
+            locked_retries += 1;
+            tokio::time::sleep(Duration::from_millis(ENDORSER_LOCKED_RETRY_SLEEP)).await;
+            continue;
+          },
+          _ => {
+            return Err(status);
+          },
+        };
+      },
+    };
+  }
+}
+
+async fn append_batch_with_retry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  request: endorser_proto::AppendBatchReq,
+) -> Result<tonic::Response<endorser_proto::AppendBatchResp>, Status> {
+  let mut locked_retries = 0;
+  loop {
+    let res = endorser_client
+      .append_batch(request_with_id(request.clone()))
+      .await;
+    match res {
+      Ok(resp) => {
+        return Ok(resp);
+      },
+      Err(status) => {
+        match status.code() {
+          Code::ResourceExhausted => {
+            continue;
+          },
+          Code::Unavailable | Code::FailedPrecondition
+            if is_endorser_locked(&status) && locked_retries < ENDORSER_LOCKED_MAX_RETRIES =>
+          {
+            locked_retries += 1;
+            tokio::time::sleep(Duration::from_millis(ENDORSER_LOCKED_RETRY_SLEEP)).await;
+            continue;
+          },
+          _ => {
+            return Err(status);
+          },
+        };
+      },
+    };
+  }
+}
+
+async fn read_view_tail_with_retry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  request: endorser_proto::ReadViewTailReq,
+) -> Result<tonic::Response<endorser_proto::ReadViewTailResp>, Status> {
   loop {
     let res = endorser_client
-      .append(tonic::Request::new(request.clone()))
+      .read_view_tail(request_with_id(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -132,9 +499,10 @@ async fn read_latest_with_retry(
   endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
   request: endorser_proto::ReadLatestReq,
 ) -> Result<tonic::Response<endorser_proto::ReadLatestResp>, Status> {
+  let mut locked_retries = 0;
   loop {
     let res = endorser_client
-      .read_latest(tonic::Request::new(request.clone()))
+      .read_latest(request_with_id(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -145,6 +513,13 @@ async fn read_latest_with_retry(
           Code::ResourceExhausted => {
             continue;
           },
+          Code::Unavailable | Code::FailedPrecondition
+            if is_endorser_locked(&status) && locked_retries < ENDORSER_LOCKED_MAX_RETRIES =>
+          {
+            locked_retries += 1;
+            tokio::time::sleep(Duration::from_millis(ENDORSER_LOCKED_RETRY_SLEEP)).await;
+            continue;
+          },
           _ => {
             return Err(status);
           },
@@ -164,7 +539,7 @@ async fn initialize_state_with_retry(
 ) -> Result<tonic::Response<endorser_proto::InitializeStateResp>, Status> {
   loop {
     let res = endorser_client
-      .initialize_state(tonic::Request::new(endorser_proto::InitializeStateReq {
+      .initialize_state(request_with_id(endorser_proto::InitializeStateReq {
         group_identity: group_identity.clone(),
         ledger_tail_map: ledger_tail_map.deref().clone(),
         view_tail_metablock: view_tail_metablock.clone(),
@@ -196,7 +571,66 @@ async fn finalize_state_with_retry(
 ) -> Result<tonic::Response<endorser_proto::FinalizeStateResp>, Status> {
   loop {
     let res = endorser_client
-      .finalize_state(tonic::Request::new(request.clone()))
+      .finalize_state(request_with_id(request.clone()))
+      .await;
+    match res {
+      Ok(resp) => {
+        return Ok(resp);
+      },
+      Err(status) => {
+        match status.code() {
+          Code::ResourceExhausted => {
+            continue;
+          },
+          _ => {
+            return Err(status);
+          },
+        };
+      },
+    };
+  }
+}
+
+async fn lock_endorser_with_retry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  locked: bool,
+) -> Result<(), Status> {
+  loop {
+    let res = if locked {
+      endorser_client
+        .lock_endorser(request_with_id(endorser_proto::LockEndorserReq {}))
+        .await
+        .map(|_resp| ())
+    } else {
+      endorser_client
+        .unlock_endorser(request_with_id(endorser_proto::UnlockEndorserReq {}))
+        .await
+        .map(|_resp| ())
+    };
+    match res {
+      Ok(()) => {
+        return Ok(());
+      },
+      Err(status) => {
+        match status.code() {
+          Code::ResourceExhausted => {
+            continue;
+          },
+          _ => {
+            return Err(status);
+          },
+        };
+      },
+    };
+  }
+}
+
+async fn rotate_key_with_retry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+) -> Result<tonic::Response<endorser_proto::RotateKeyResp>, Status> {
+  loop {
+    let res = endorser_client
+      .rotate_key(request_with_id(endorser_proto::RotateKeyReq {}))
       .await;
     match res {
       Ok(resp) => {
@@ -222,7 +656,7 @@ async fn read_state_with_retry(
 ) -> Result<tonic::Response<endorser_proto::ReadStateResp>, Status> {
   loop {
     let res = endorser_client
-      .read_state(tonic::Request::new(request.clone()))
+      .read_state(request_with_id(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -252,7 +686,7 @@ async fn activate_with_retry(
 ) -> Result<tonic::Response<endorser_proto::ActivateResp>, Status> {
   loop {
     let res = endorser_client
-      .activate(tonic::Request::new(endorser_proto::ActivateReq {
+      .activate(request_with_id(endorser_proto::ActivateReq {
         old_config: old_config.clone(),
         new_config: new_config.clone(),
         ledger_tail_maps: ledger_tail_maps.deref().clone(),
@@ -280,6 +714,7 @@ async fn activate_with_retry(
 
 async fn update_endorser(
   ledger_store: LedgerStoreRef,
+  tail_cache: &TailCache,
   endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
   handle: NimbleDigest,
   start: usize,
@@ -324,6 +759,8 @@ async fn update_endorser(
           expected_height: idx as u64,
           block: ledger_entry.get_block().to_bytes(),
           nonces: ledger_entry.get_nonces().to_bytes(),
+          // the time of an earlier append is too far from the endorser's clock to be attested
+          timestamp: 0,
         },
       )
       .await?
@@ -332,14 +769,23 @@ async fn update_endorser(
       receipt
     };
 
+    // a receipt without the timestamp that the entry's endorsers attested to is for a different
+    // metablock, which would leave the entry's receipts without one that they agree on
+    if ledger_entry
+      .get_receipts()
+      .get_metablock()
+      .is_ok_and(|metablock| metablock.get_timestamp().is_some())
+    {
+      continue;
+    }
     let res = Receipt::from_bytes(&receipt);
-    if res.is_ok() {
-      let receipt_rs = res.unwrap();
+    if let Ok(receipt_rs) = res {
       let mut receipts = Receipts::new();
       receipts.add(&receipt_rs);
       let res = ledger_store
         .attach_ledger_receipts(&handle, idx, &receipts)
         .await;
+      tail_cache.invalidate(&handle);
       if res.is_err() {
         eprintln!(
           "Failed to attach ledger receipt to the ledger store ({:?})",
@@ -368,6 +814,7 @@ fn process_error(
   handle: Option<&NimbleDigest>,
   status: &Status,
 ) -> CoordinatorAction {
+  warn!(endorser, code = ?status.code(), "{}", status.message());
   match status.code() {
     Code::Aborted => {
       eprintln!("operation aborted to due to ledger store");
@@ -388,6 +835,14 @@ fn process_error(
       eprintln!("endorser {} is locked", endorser);
       CoordinatorAction::DoNothing
     },
+    Code::PermissionDenied => {
+      eprintln!("the lease of endorser {} has expired", endorser);
+      CoordinatorAction::DoNothing
+    },
+    Code::FailedPrecondition if is_endorser_locked(status) => {
+      eprintln!("endorser {} is locked", endorser);
+      CoordinatorAction::DoNothing
+    },
     Code::FailedPrecondition | Code::NotFound => {
       if let Some(h) = handle {
         eprintln!("ledger {:?} lags behind in endorser {}", h, endorser);
@@ -426,7 +881,11 @@ fn process_error(
     },
 
     Code::Unavailable => {
-      eprintln!("the endorser is already finalized");
+      if is_endorser_locked(status) {
+        eprintln!("endorser {} is locked for view change", endorser);
+      } else {
+        eprintln!("the endorser is already finalized");
+      }
       CoordinatorAction::DoNothing
     },
     Code::Unimplemented => {
@@ -447,36 +906,92 @@ impl CoordinatorState {
     ledger_store_type: &str,
     args: &HashMap<String, String>,
     num_grpc_channels_opt: Option<usize>,
+  ) -> Result<CoordinatorState, CoordinatorError> {
+    let ledger_store = match open_ledger_store(ledger_store_type, args).await {
+      Ok(store) => store,
+      Err(e) => {
+        eprintln!(
+          "Failed to open the {} ledger store ({:?})",
+          ledger_store_type, e
+        );
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    };
+    Self::with_store(ledger_store, num_grpc_channels_opt).await
+  }
+
+  /// Creates a coordinator over an already opened ledger store, resuming from its view ledger
+  pub async fn with_store(
+    ledger_store: BoxedLedgerStore,
+    num_grpc_channels_opt: Option<usize>,
+  ) -> Result<CoordinatorState, CoordinatorError> {
+    Self::with_store_and_endorser_tls(ledger_store, num_grpc_channels_opt, None).await
+  }
+
+  /// Like `with_store`, but connects to endorsers with https URIs over TLS with `endorser_tls`,
+  /// which carries the CA that endorser certificates chain to and the coordinator's identity
+  pub async fn with_store_and_endorser_tls(
+    ledger_store: BoxedLedgerStore,
+    num_grpc_channels_opt: Option<usize>,
+    endorser_tls: Option<ClientTlsConfig>,
+  ) -> Result<CoordinatorState, CoordinatorError> {
+    Self::open(ledger_store, num_grpc_channels_opt, endorser_tls, None).await
+  }
+
+  /// Like `with_store`, but connects to endorsers with `connector` instead of over the network,
+  /// including the endorsers of the view it resumes from
+  pub async fn with_store_and_connector(
+    ledger_store: BoxedLedgerStore,
+    connector: EndorserConnector,
+  ) -> Result<CoordinatorState, CoordinatorError> {
+    Self::open(ledger_store, None, None, Some(connector)).await
+  }
+
+  async fn open(
+    ledger_store: BoxedLedgerStore,
+    num_grpc_channels_opt: Option<usize>,
+    endorser_tls: Option<ClientTlsConfig>,
+    endorser_connector: Option<EndorserConnector>,
   ) -> Result<CoordinatorState, CoordinatorError> {
     let num_grpc_channels = match num_grpc_channels_opt {
       Some(n) => n,
       None => DEFAULT_NUM_GRPC_CHANNELS,
     };
-    let coordinator = match ledger_store_type {
-      "mongodb_cosmos" => CoordinatorState {
-        ledger_store: Arc::new(Box::new(MongoCosmosLedgerStore::new(args).await.unwrap())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
-      },
-      "table" => CoordinatorState {
-        ledger_store: Arc::new(Box::new(TableLedgerStore::new(args).await.unwrap())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
-      },
-      "filestore" => CoordinatorState {
-        ledger_store: Arc::new(Box::new(FileStore::new(args).await.unwrap())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
-      },
-      _ => CoordinatorState {
-        ledger_store: Arc::new(Box::new(InMemoryLedgerStore::new())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
-      },
+    let coordinator = CoordinatorState {
+      ledger_store: Arc::new(ledger_store),
+      conn_map: Arc::new(RwLock::new(HashMap::new())),
+      verifier_state: Arc::new(RwLock::new(VerifierState::new())),
+      num_grpc_channels,
+      maintenance_deadline: Arc::new(RwLock::new(None)),
+      endorsement_policies: Arc::new(RwLock::new(HashMap::new())),
+      access_policies: Arc::new(RwLock::new(HashMap::new())),
+      ledger_stats: Arc::new(LedgerStatsTracker::default()),
+      tail_cache: Arc::new(TailCache::default()),
+      view_changes: broadcast::channel(VIEW_CHANGE_CHANNEL_BUFFER).0,
+      ledger_appends: broadcast::channel(LEDGER_APPEND_CHANNEL_BUFFER).0,
+      admin_ledger_lock: Arc::new(tokio::sync::Mutex::new(())),
+      checkpoint_ledger_lock: Arc::new(tokio::sync::Mutex::new(())),
+      draining: Arc::new(RwLock::new(HashSet::new())),
+      checkpoints: Arc::new(RwLock::new(HashMap::new())),
+      endorser_tls,
+      attestation_verifier: Arc::new(RwLock::new(None)),
+      endorser_allowlist: Arc::new(RwLock::new(None)),
+      block_validation: Arc::new(RwLock::new(BlockValidation::default())),
+      blob_store: Arc::new(RwLock::new(None)),
+      append_request_retention: Arc::new(RwLock::new(Duration::from_secs(
+        DEFAULT_APPEND_REQUEST_RETENTION,
+      ))),
+      append_pipelines: Arc::new(AppendPipelines::default()),
+      append_pipeline_depth: Arc::new(RwLock::new(0)),
+      attest_timestamps: Arc::new(RwLock::new(false)),
+      endorser_lease: Arc::new(RwLock::new(None)),
+      view_lease_expiry: Arc::new(RwLock::new(None)),
+      endorser_connector,
+      misbehavior: Arc::new(RwLock::new(EquivocationDetector::default())),
+      misbehavior_ledger_lock: Arc::new(tokio::sync::Mutex::new(())),
+      integrity_report: Arc::new(RwLock::new(None)),
+      integrity_check_lock: Arc::new(tokio::sync::Mutex::new(())),
+      in_flight: Arc::new(tokio::sync::RwLock::new(())),
     };
 
     let res = coordinator.ledger_store.read_view_ledger_tail().await;
@@ -509,6 +1024,8 @@ impl CoordinatorState {
         return Err(CoordinatorError::FailedToAcquireWriteLock);
       }
 
+      coordinator.set_view_lease_expiry(view_ledger_tail.get_block())?;
+
       // Connect to current endorsers
       let curr_endorsers = coordinator
         .connect_to_existing_endorsers(&view_ledger_tail.get_block().to_bytes())
@@ -550,6 +1067,7 @@ impl CoordinatorState {
               &prev_view_ledger_entry,
               view_ledger_tail.get_block(),
               tail_height,
+              None,
             )
             .await;
           if let Err(error) = res {
@@ -610,11 +1128,64 @@ impl CoordinatorState {
       }
     }
 
+    if tail_height > 0 {
+      coordinator.reconcile_endorsers().await?;
+    }
+
     Ok(coordinator)
   }
 
-  async fn connect_to_existing_endorsers(
-    &self,
+  // Brings the endorsers of the recovered view up to the ledger tails in the store. A coordinator
+  // that crashed between appending to the store and collecting receipts leaves entries that the
+  // endorsers never signed; they are endorsed now, and an endorser that cannot catch up is
+  // disconnected until it is readmitted.
+  async fn reconcile_endorsers(&self) -> Result<(), CoordinatorError> {
+    let (tail, _height, _attestations) = self.read_view_tail().await?;
+    let view = match tail.get_receipts().get_metablock() {
+      Ok(metablock) => metablock.hash(),
+      Err(_) => return Err(CoordinatorError::UnexpectedError),
+    };
+    let tails = self.read_ledger_tails().await?;
+
+    let mut lagging = EndorserHostnames::new();
+    for uri in self.get_endorser_uris() {
+      if let Err(error) = self.catch_up_endorser(&uri, &view, &tails).await {
+        eprintln!(
+          "Failed to reconcile the endorser {} with the ledger store ({:?})",
+          uri, error
+        );
+        if let Some(pk) = self.get_endorser_pk(&uri) {
+          lagging.push((pk, uri));
+        }
+      }
+    }
+    if !lagging.is_empty() {
+      self.disconnect_endorsers(&lagging).await;
+    }
+    Ok(())
+  }
+
+  // Returns the handle and the height of the tail of every ledger in the store
+  async fn read_ledger_tails(&self) -> Result<Vec<(Handle, usize)>, CoordinatorError> {
+    let res = self.ledger_store.list_ledgers().await;
+    if let Err(error) = res {
+      eprintln!("Failed to list the ledgers ({:?})", error);
+      return Err(CoordinatorError::FailedToCallLedgerStore);
+    }
+    let mut tails = Vec::new();
+    for handle in res.unwrap() {
+      let res = self.ledger_store.read_ledger_tail(&handle).await;
+      if let Err(error) = res {
+        eprintln!("Failed to read the tail of a ledger ({:?})", error);
+        return Err(ledger_store_error(&error));
+      }
+      tails.push((handle, res.unwrap().1));
+    }
+    Ok(tails)
+  }
+
+  // Returns the endorsers that a block of the view ledger lists
+  fn decode_view_endorsers(
     view_ledger_block: &[u8],
   ) -> Result<EndorserHostnames, CoordinatorError> {
     let res = bincode::deserialize(view_ledger_block);
@@ -625,12 +1196,22 @@ impl CoordinatorState {
       );
       return Err(CoordinatorError::FailedToSerde);
     }
-    let endorser_hostnames: EndorserHostnames = res.unwrap();
+    Ok(res.unwrap())
+  }
+
+  async fn connect_to_existing_endorsers(
+    &self,
+    view_ledger_block: &[u8],
+  ) -> Result<EndorserHostnames, CoordinatorError> {
+    let endorser_hostnames = Self::decode_view_endorsers(view_ledger_block)?;
 
     let mut endorsers = EndorserHostnames::new();
 
+    // the endorser at each URI must still have the key that the view ledger lists for it
     for (pk, uri) in &endorser_hostnames {
-      let pks = self.connect_endorsers(&[uri.clone()]).await;
+      let pks = self
+        .connect_endorsers_with(std::slice::from_ref(uri), |key| key == pk.as_slice())
+        .await;
       if pks.len() == 1 && pks[0].0 == *pk {
         endorsers.push((pk.clone(), uri.clone()));
       }
@@ -666,94 +1247,457 @@ impl CoordinatorState {
 
   pub fn get_endorser_pks(&self) -> Vec<Vec<u8>> {
     if let Ok(conn_map_rd) = self.conn_map.read() {
-      conn_map_rd
-        .iter()
-        .map(|(pk, _endorser)| pk.clone())
-        .collect::<Vec<Vec<u8>>>()
+      conn_map_rd.keys().cloned().collect::<Vec<Vec<u8>>>()
     } else {
       eprintln!("Failed to acquire read lock");
       Vec::new()
     }
   }
 
-  pub fn get_endorser_uris(&self) -> Vec<String> {
-    if let Ok(conn_map_rd) = self.conn_map.read() {
-      conn_map_rd
-        .iter()
-        .map(|(_pk, endorser)| endorser.uri.clone())
-        .collect::<Vec<String>>()
-    } else {
-      eprintln!("Failed to acquire read lock");
-      Vec::new()
-    }
+  /// Returns the endorsers the ledger `handle` is assigned to in the current view, leaving out
+  /// the endorsers that are being decommissioned
+  pub fn get_ledger_endorser_pks(&self, handle: &Handle) -> Vec<Vec<u8>> {
+    let pks = self
+      .get_endorser_pks()
+      .into_iter()
+      .collect::<HashSet<Vec<u8>>>();
+    let shard_size = match self.verifier_state.read() {
+      Ok(vs) => vs.get_shard_size(),
+      Err(_) => 0,
+    };
+    let draining = match self.draining.read() {
+      Ok(draining) => draining.clone(),
+      Err(_) => HashSet::new(),
+    };
+    shard_endorsers(&pks, handle, shard_size)
+      .into_iter()
+      .filter(|pk| !draining.contains(pk))
+      .collect()
   }
 
-  fn get_endorser_hostnames(&self) -> EndorserHostnames {
-    if let Ok(conn_map_rd) = self.conn_map.read() {
-      conn_map_rd
-        .iter()
-        .map(|(pk, endorser)| (pk.clone(), endorser.uri.clone()))
-        .collect::<Vec<(Vec<u8>, String)>>()
-    } else {
-      eprintln!("Failed to acquire read lock");
-      Vec::new()
-    }
+  /// Assigns each ledger to `shard_size` endorsers of a view instead of all of them; verifiers
+  /// must be configured with the same shard size to check the resulting receipts
+  pub fn set_shard_size(&self, shard_size: usize) -> Result<(), CoordinatorError> {
+    let mut vs = self
+      .verifier_state
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    vs.set_shard_size(shard_size);
+    Ok(())
   }
 
-  pub fn get_endorser_pk(&self, hostname: &str) -> Option<Vec<u8>> {
-    if let Ok(conn_map_rd) = self.conn_map.read() {
-      for (pk, endorser) in conn_map_rd.iter() {
-        if endorser.uri == hostname {
-          return Some(pk.clone());
-        }
-      }
-    }
-    None
+  /// Sets the size limit and the content types that appended blocks must satisfy
+  pub fn set_block_validation(&self, validation: BlockValidation) -> Result<(), CoordinatorError> {
+    let mut block_validation = self
+      .block_validation
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *block_validation = validation;
+    Ok(())
   }
 
-  pub async fn connect_endorsers(&self, hostnames: &[String]) -> EndorserHostnames {
-    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
-    for hostname in hostnames {
-      for _idx in 0..self.num_grpc_channels {
-        let tx = mpsc_tx.clone();
-        let endorser = hostname.clone();
+  /// Enables chunked appends, whose payloads of up to `max_blob_size` bytes go to `store`
+  pub fn set_blob_store(
+    &self,
+    store: BoxedContentStore,
+    max_blob_size: Option<usize>,
+  ) -> Result<(), CoordinatorError> {
+    let mut blob_store = self
+      .blob_store
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *blob_store = Some(BlobStore {
+      store: Arc::new(store),
+      max_blob_size,
+    });
+    Ok(())
+  }
 
-        let _job = tokio::spawn(async move {
-          let res = Endpoint::from_shared(endorser.to_string());
-          if let Ok(endorser_endpoint) = res {
-            let endorser_endpoint = endorser_endpoint
-              .connect_timeout(std::time::Duration::from_secs(ENDORSER_CONNECT_TIMEOUT));
-            let endorser_endpoint =
-              endorser_endpoint.timeout(std::time::Duration::from_secs(ENDORSER_REQUEST_TIMEOUT));
-            let res = endorser_endpoint.connect().await;
-            if let Ok(channel) = res {
-              let mut client =
-                endorser_proto::endorser_call_client::EndorserCallClient::new(channel);
-
-              let res =
-                get_public_key_with_retry(&mut client, endorser_proto::GetPublicKeyReq {}).await;
-              if let Ok(resp) = res {
-                let endorser_proto::GetPublicKeyResp { pk } = resp.into_inner();
-                let _ = tx.send((endorser, Ok((client, pk)))).await;
-              } else {
-                eprintln!("Failed to retrieve the public key: {:?}", res);
-                let _ = tx
-                  .send((endorser, Err(CoordinatorError::UnableToRetrievePublicKey)))
-                  .await;
-              }
-            } else {
-              eprintln!("Failed to connect to the endorser {}: {:?}", endorser, res);
+  /// Sets how long the ledger store remembers the client request ID of a completed append, and
+  /// so how late a retry of the append is still recognized as one
+  pub fn set_append_request_retention(&self, retention: Duration) -> Result<(), CoordinatorError> {
+    let mut append_request_retention = self
+      .append_request_retention
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *append_request_retention = retention;
+    Ok(())
+  }
+
+  /// Pipelines the appends to each ledger, with up to `depth` appends endorsed in one round; 0
+  /// endorses each append on its own
+  pub fn set_append_pipeline_depth(&self, depth: usize) -> Result<(), CoordinatorError> {
+    let mut append_pipeline_depth = self
+      .append_pipeline_depth
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *append_pipeline_depth = depth;
+    Ok(())
+  }
+
+  /// Caches the tail entries of up to `capacity` ledgers, each for at most `ttl`, so that reads
+  /// of a ledger's tail that find it where it was do not read the ledger store; 0 disables it
+  pub fn set_tail_cache(&self, capacity: usize, ttl: Option<Duration>) {
+    self.tail_cache.configure(capacity, ttl);
+  }
+
+  pub fn get_tail_cache_stats(&self) -> TailCacheStats {
+    self.tail_cache.stats()
+  }
+
+  /// Proposes the current time to the endorsers with each append, so that the metablocks of the
+  /// appends carry a timestamp that the endorsers attest to
+  pub fn set_attest_timestamps(&self, enabled: bool) -> Result<(), CoordinatorError> {
+    let mut attest_timestamps = self
+      .attest_timestamps
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *attest_timestamps = enabled;
+    Ok(())
+  }
+
+  /// Gives the endorsers of each later view a lease of `lease`, after which they refuse to sign
+  /// until a view change renews it, or no lease with None
+  pub fn set_endorser_lease(&self, lease: Option<Duration>) -> Result<(), CoordinatorError> {
+    let mut endorser_lease = self
+      .endorser_lease
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *endorser_lease = lease;
+    Ok(())
+  }
+
+  pub fn get_endorser_lease(&self) -> Result<Option<Duration>, CoordinatorError> {
+    match self.endorser_lease.read() {
+      Ok(endorser_lease) => Ok(*endorser_lease),
+      Err(_) => Err(CoordinatorError::FailedToAcquireReadLock),
+    }
+  }
+
+  // Packages the endorsers of a new view, their attestations, the key handovers it carries and
+  // the lease of the endorsers, if they get one, into a block of the view ledger
+  fn encode_view_block(
+    &self,
+    endorsers: &EndorserHostnames,
+    handovers: &[KeyHandover],
+  ) -> Result<Block, CoordinatorError> {
+    let attestations = self.get_endorser_attestations(endorsers);
+    let res = match self.get_endorser_lease()? {
+      Some(lease) => encode_view_config_with_lease(
+        endorsers,
+        &attestations,
+        handovers,
+        current_timestamp().saturating_add(lease.as_millis() as u64),
+      ),
+      None => encode_view_config_with_handovers(endorsers, &attestations, handovers),
+    };
+    match res {
+      Ok(block_vec) => Ok(Block::new(&block_vec)),
+      Err(error) => {
+        eprintln!("Failed to serialize endorser hostnames {:?}", error);
+        Err(CoordinatorError::FailedToSerde)
+      },
+    }
+  }
+
+  // the timestamp to propose to the endorsers for an append, where 0 proposes none
+  fn proposed_timestamp(&self) -> u64 {
+    match self.attest_timestamps.read() {
+      Ok(attest_timestamps) if *attest_timestamps => current_timestamp(),
+      _ => 0,
+    }
+  }
+
+  fn get_blob_store(&self) -> Result<BlobStore, CoordinatorError> {
+    match self.blob_store.read() {
+      Ok(blob_store) => blob_store
+        .clone()
+        .ok_or(CoordinatorError::BlobStoreNotConfigured),
+      Err(_) => Err(CoordinatorError::FailedToAcquireReadLock),
+    }
+  }
+
+  /// Returns the largest payload of a chunked append, or None if there is no limit
+  pub fn get_max_blob_size(&self) -> Result<Option<usize>, CoordinatorError> {
+    Ok(self.get_blob_store()?.max_blob_size)
+  }
+
+  fn check_block(&self, block_bytes: &[u8]) -> Result<(), CoordinatorError> {
+    // blob references are appended only by chunked appends, which store the blob first
+    if BlobReference::is_blob_reference(block_bytes) {
+      return Err(CoordinatorError::InvalidBlobReference);
+    }
+    // and external blocks only by hash-only appends
+    if ExternalBlock::is_external_block(block_bytes) {
+      return Err(CoordinatorError::InvalidExternalBlock);
+    }
+    let res = match self.block_validation.read() {
+      Ok(validation) => validation.validate(block_bytes),
+      Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+    };
+    match res {
+      Ok(()) => Ok(()),
+      Err(VerificationError::BlockTooLarge) => Err(CoordinatorError::BlockTooLarge),
+      Err(VerificationError::ContentTypeNotAllowed) => Err(CoordinatorError::ContentTypeNotAllowed),
+      Err(_) => Err(CoordinatorError::InvalidBlockEnvelope),
+    }
+  }
+
+  pub fn get_endorser_uris(&self) -> Vec<String> {
+    if let Ok(conn_map_rd) = self.conn_map.read() {
+      conn_map_rd
+        .values()
+        .map(|endorser| endorser.uri.clone())
+        .collect::<Vec<String>>()
+    } else {
+      eprintln!("Failed to acquire read lock");
+      Vec::new()
+    }
+  }
+
+  fn get_endorser_hostnames(&self) -> EndorserHostnames {
+    if let Ok(conn_map_rd) = self.conn_map.read() {
+      conn_map_rd
+        .iter()
+        .map(|(pk, endorser)| (pk.clone(), endorser.uri.clone()))
+        .collect::<Vec<(Vec<u8>, String)>>()
+    } else {
+      eprintln!("Failed to acquire read lock");
+      Vec::new()
+    }
+  }
+
+  pub fn get_endorser_pk(&self, hostname: &str) -> Option<Vec<u8>> {
+    if let Ok(conn_map_rd) = self.conn_map.read() {
+      for (pk, endorser) in conn_map_rd.iter() {
+        if endorser.uri == hostname {
+          return Some(pk.clone());
+        }
+      }
+    }
+    None
+  }
+
+  /// Asks the endorser at `uri` how many times it restarted from its storage, and returns the
+  /// public key of the endorser and whether it restarted since the coordinator last asked. An
+  /// endorser that restarted kept its key and ledger tails, but its connections were reset.
+  pub async fn check_endorser_restart(
+    &self,
+    uri: &str,
+  ) -> Result<(Vec<u8>, u64, bool), CoordinatorError> {
+    let pk = match self.get_endorser_pk(uri) {
+      Some(pk) => pk,
+      None => return Err(CoordinatorError::InvalidEndorserUri),
+    };
+    let (mut client, _uri) = match self.get_endorser_client(&pk) {
+      Some(client) => client,
+      None => return Err(CoordinatorError::InvalidEndorserUri),
+    };
+
+    let res = client
+      .get_recovery_info(endorser_proto::GetRecoveryInfoReq {})
+      .await;
+    if let Err(status) = res {
+      eprintln!("Failed to query the endorser {} ({:?})", uri, status);
+      return Err(CoordinatorError::FailedToConnectToEndorser);
+    }
+    let incarnation = res.unwrap().into_inner().incarnation;
+
+    if let Ok(mut conn_map_wr) = self.conn_map.write() {
+      match conn_map_wr.get_mut(&pk) {
+        Some(endorser) => {
+          let restarted = endorser.incarnation != incarnation;
+          endorser.incarnation = incarnation;
+          Ok((pk, incarnation, restarted))
+        },
+        None => Err(CoordinatorError::InvalidEndorserUri),
+      }
+    } else {
+      Err(CoordinatorError::FailedToAcquireWriteLock)
+    }
+  }
+
+  /// Requires endorsers to attest their public keys with evidence that `verifier` accepts before
+  /// they are connected, and so before they can join a view
+  pub fn set_attestation_verifier(
+    &self,
+    verifier: Box<dyn AttestationVerifier>,
+  ) -> Result<(), CoordinatorError> {
+    let mut attestation_verifier = self
+      .attestation_verifier
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *attestation_verifier = Some(verifier);
+    Ok(())
+  }
+
+  fn verify_attestation(&self, pk: &[u8], attestation: &[u8]) -> Result<(), CoordinatorError> {
+    let attestation_verifier = self
+      .attestation_verifier
+      .read()
+      .map_err(|_e| CoordinatorError::FailedToAcquireReadLock)?;
+    if let Some(verifier) = attestation_verifier.as_ref() {
+      if verify_public_key_attestation(verifier.as_ref(), pk, attestation).is_err() {
+        return Err(CoordinatorError::InvalidEndorserAttestation);
+      }
+    }
+    Ok(())
+  }
+
+  // Returns the evidence with which the connected `endorsers` attested their public keys
+  fn get_endorser_attestations(&self, endorsers: &EndorserHostnames) -> EndorserAttestations {
+    let mut attestations = EndorserAttestations::new();
+    if let Ok(conn_map_rd) = self.conn_map.read() {
+      for (pk, _uri) in endorsers {
+        if let Some(endorser) = conn_map_rd.get(pk) {
+          if !endorser.attestation.is_empty() {
+            attestations.push((pk.clone(), endorser.attestation.clone()));
+          }
+        }
+      }
+    }
+    attestations
+  }
+
+  // Returns the endpoint of the endorser at `uri`, which is reached over TLS if it is https
+  fn endorser_endpoint(&self, uri: &str) -> Result<Endpoint, CoordinatorError> {
+    let res = Endpoint::from_shared(uri.to_string());
+    if res.is_err() {
+      return Err(CoordinatorError::CannotResolveHostName);
+    }
+    let endorser_endpoint = res
+      .unwrap()
+      .connect_timeout(std::time::Duration::from_secs(ENDORSER_CONNECT_TIMEOUT))
+      .timeout(std::time::Duration::from_secs(ENDORSER_REQUEST_TIMEOUT))
+      // pings detect connections that a network failure broke without closing them
+      .http2_keep_alive_interval(Duration::from_secs(ENDORSER_KEEPALIVE_INTERVAL))
+      .keep_alive_timeout(Duration::from_secs(ENDORSER_KEEPALIVE_TIMEOUT))
+      .keep_alive_while_idle(true)
+      .tcp_keepalive(Some(Duration::from_secs(ENDORSER_KEEPALIVE_INTERVAL)));
+    if !uri.starts_with("https://") {
+      return Ok(endorser_endpoint);
+    }
+    let tls = match &self.endorser_tls {
+      Some(tls) => tls.clone(),
+      None => ClientTlsConfig::new(),
+    };
+    let res = endorser_endpoint.tls_config(tls);
+    if let Err(error) = res {
+      eprintln!(
+        "Failed to configure TLS for the endorser {}: {:?}",
+        uri, error
+      );
+      return Err(CoordinatorError::FailedToConnectToEndorser);
+    }
+    Ok(res.unwrap())
+  }
+
+  /// Limits the endorsers that the coordinator connects to, and so admits to views, to those with
+  /// the public keys in `pks` and those of the current view, whose keys the view ledger vouches for
+  pub async fn set_endorser_allowlist(
+    &self,
+    pks: HashSet<Vec<u8>>,
+  ) -> Result<(), CoordinatorError> {
+    let mut allowlist = pks;
+    if self.get_view_height()? > 0 {
+      let (tail, _height, _attestations) = self.read_view_tail().await?;
+      let endorsers = Self::decode_view_endorsers(&tail.get_block().to_bytes())?;
+      allowlist.extend(endorsers.into_iter().map(|(pk, _uri)| pk));
+    }
+    let mut endorser_allowlist = self
+      .endorser_allowlist
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *endorser_allowlist = Some(allowlist);
+    Ok(())
+  }
+
+  fn is_endorser_allowed(&self, pk: &[u8]) -> bool {
+    match self.endorser_allowlist.read() {
+      Ok(allowlist) => allowlist
+        .as_ref()
+        .is_none_or(|allowlist| allowlist.contains(pk)),
+      Err(_e) => false,
+    }
+  }
+
+  // Adds the key to which an endorser of the view handed over to the allowlist, if there is one
+  fn allow_endorser(&self, pk: &[u8]) -> Result<(), CoordinatorError> {
+    let mut endorser_allowlist = self
+      .endorser_allowlist
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    if let Some(allowlist) = endorser_allowlist.as_mut() {
+      allowlist.insert(pk.to_vec());
+    }
+    Ok(())
+  }
+
+  pub async fn connect_endorsers(&self, hostnames: &[String]) -> EndorserHostnames {
+    self
+      .connect_endorsers_with(hostnames, |pk| self.is_endorser_allowed(pk))
+      .await
+  }
+
+  // Connects to the endorsers at `hostnames` whose public keys `is_expected` accepts
+  async fn connect_endorsers_with<F>(
+    &self,
+    hostnames: &[String],
+    is_expected: F,
+  ) -> EndorserHostnames
+  where
+    F: Fn(&[u8]) -> bool,
+  {
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+    for hostname in hostnames {
+      for _idx in 0..self.num_grpc_channels {
+        let tx = mpsc_tx.clone();
+        let endorser = hostname.clone();
+        let res = self.endorser_endpoint(hostname);
+        let connector = self.endorser_connector.clone();
+
+        let _job = spawn_endorser_call(
+          info_span!("endorser", otel.kind = "client", uri = %endorser),
+          async move {
+            if let Ok(endorser_endpoint) = res {
+              let res = connect_endpoint(endorser_endpoint, connector).await;
+              if let Ok(channel) = res {
+                let mut client =
+                  endorser_proto::endorser_call_client::EndorserCallClient::new(channel);
+
+                let res =
+                  get_public_key_with_retry(&mut client, endorser_proto::GetPublicKeyReq {}).await;
+                if let Ok(resp) = res {
+                  let endorser_proto::GetPublicKeyResp { pk, attestation } = resp.into_inner();
+                  // endorsers that do not persist their state report no restarts
+                  let incarnation = client
+                    .get_recovery_info(endorser_proto::GetRecoveryInfoReq {})
+                    .await
+                    .map(|resp| resp.into_inner().incarnation)
+                    .unwrap_or(0);
+                  let _ = tx
+                    .send((endorser, Ok((client, pk, incarnation, attestation))))
+                    .await;
+                } else {
+                  eprintln!("Failed to retrieve the public key: {:?}", res);
+                  let _ = tx
+                    .send((endorser, Err(CoordinatorError::UnableToRetrievePublicKey)))
+                    .await;
+                }
+              } else {
+                eprintln!("Failed to connect to the endorser {}: {:?}", endorser, res);
+                let _ = tx
+                  .send((endorser, Err(CoordinatorError::FailedToConnectToEndorser)))
+                  .await;
+              }
+            } else {
+              eprintln!("Failed to resolve the endorser host name: {:?}", res);
               let _ = tx
-                .send((endorser, Err(CoordinatorError::FailedToConnectToEndorser)))
+                .send((endorser, Err(CoordinatorError::CannotResolveHostName)))
                 .await;
             }
-          } else {
-            eprintln!("Failed to resolve the endorser host name: {:?}", res);
-            let _ = tx
-              .send((endorser, Err(CoordinatorError::CannotResolveHostName)))
-              .await;
-          }
-        });
+          },
+        );
       }
     }
 
@@ -761,11 +1705,25 @@ impl CoordinatorState {
 
     let mut endorser_hostnames = EndorserHostnames::new();
     while let Some((endorser, res)) = mpsc_rx.recv().await {
-      if let Ok((client, pk)) = res {
+      if let Ok((client, pk, incarnation, attestation)) = res {
         if PublicKey::from_bytes(&pk).is_err() {
           eprintln!("Public key is invalid from endorser {:?}", endorser);
           continue;
         }
+        if let Err(error) = self.verify_attestation(&pk, &attestation) {
+          eprintln!(
+            "Failed to verify the attestation of endorser {:?} ({:?})",
+            endorser, error
+          );
+          continue;
+        }
+        if !is_expected(&pk) {
+          eprintln!(
+            "Endorser {:?} has a public key that is not expected",
+            endorser
+          );
+          continue;
+        }
         if let Ok(mut conn_map_wr) = self.conn_map.write() {
           let e = conn_map_wr.get_mut(&pk);
           match e {
@@ -774,6 +1732,9 @@ impl CoordinatorState {
               let mut endorser_clients = EndorserClients {
                 clients: Vec::new(),
                 uri: endorser,
+                incarnation,
+                attestation,
+                health: EndorserHealthTracker::default(),
               };
               endorser_clients.clients.push(client);
               conn_map_wr.insert(pk, endorser_clients);
@@ -824,11 +1785,14 @@ impl CoordinatorState {
 
       let tx = mpsc_tx.clone();
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
-        let res =
-          read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await;
-        let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res =
+            read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -871,7 +1835,7 @@ impl CoordinatorState {
     Ok(())
   }
 
-  async fn endorser_initialize_state(
+  pub(crate) async fn endorser_initialize_state(
     &self,
     group_identity: &NimbleDigest,
     endorsers: &EndorserHostnames,
@@ -894,18 +1858,21 @@ impl CoordinatorState {
       let block_hash_copy = block_hash.to_bytes();
       let pk_bytes = pk.clone();
       let group_identity_copy = (*group_identity).to_bytes();
-      let _job = tokio::spawn(async move {
-        let res = initialize_state_with_retry(
-          &mut endorser_client,
-          group_identity_copy,
-          ledger_tail_map_arc_copy,
-          view_tail_metablock_bytes,
-          block_hash_copy,
-          expected_height,
-        )
-        .await;
-        let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = initialize_state_with_retry(
+            &mut endorser_client,
+            group_identity_copy,
+            ledger_tail_map_arc_copy,
+            view_tail_metablock_bytes,
+            block_hash_copy,
+            expected_height,
+          )
+          .await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -940,12 +1907,57 @@ impl CoordinatorState {
     receipts
   }
 
+  // Endorsers that answer after a quorum signed are not waited for, but their receipts are still
+  // attached to the entries from `index` on in the background, so that the stored entries keep
+  // the endorsement of every endorser that responded
+  fn attach_straggler_receipts<T: Send + 'static>(
+    &self,
+    handle: Handle,
+    index: usize,
+    mut mpsc_rx: mpsc::Receiver<T>,
+    receipts_of: fn(T) -> Vec<Vec<u8>>,
+  ) {
+    let ledger_store = self.ledger_store.clone();
+    let tail_cache = self.tail_cache.clone();
+    let misbehavior = self.misbehavior.clone();
+    // once the coordinator drains, the receipts are attached only if time remains
+    let in_flight = self.in_flight.clone().try_read_owned().ok();
+    let _job = tokio::spawn(async move {
+      let _in_flight = in_flight;
+      let mut late_receipts = Vec::<Receipts>::new();
+      while let Some(res) = mpsc_rx.recv().await {
+        for (i, receipt) in receipts_of(res).iter().enumerate() {
+          if let Ok(receipt_rs) = Receipt::from_bytes(receipt) {
+            observe_receipt(&misbehavior, &handle, &receipt_rs);
+            if late_receipts.len() <= i {
+              late_receipts.resize(i + 1, Receipts::new());
+            }
+            late_receipts[i].add(&receipt_rs);
+          }
+        }
+      }
+      for (i, receipts) in late_receipts.iter().enumerate() {
+        let res = ledger_store
+          .attach_ledger_receipts(&handle, index + i, receipts)
+          .await;
+        tail_cache.invalidate(&handle);
+        if let Err(error) = res {
+          eprintln!(
+            "Failed to attach late receipts to the ledger store ({:?})",
+            error
+          );
+        }
+      }
+    });
+  }
+
   async fn endorser_create_ledger(
     &self,
     endorsers: &[Vec<u8>],
     ledger_handle: &Handle,
     ledger_block_hash: &NimbleDigest,
     ledger_block: Block,
+    policy: &EndorsementPolicy,
   ) -> Result<Receipts, CoordinatorError> {
     let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
     for pk in endorsers {
@@ -959,18 +1971,21 @@ impl CoordinatorState {
       let block_hash = *ledger_block_hash;
       let block = ledger_block.clone();
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
-        let res = new_ledger_with_retry(
-          &mut endorser_client,
-          endorser_proto::NewLedgerReq {
-            handle: handle.to_bytes(),
-            block_hash: block_hash.to_bytes(),
-            block: block.to_bytes(),
-          },
-        )
-        .await;
-        let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = new_ledger_with_retry(
+            &mut endorser_client,
+            endorser_proto::NewLedgerReq {
+              handle: handle.to_bytes(),
+              block_hash: block_hash.to_bytes(),
+              block: block.to_bytes(),
+            },
+          )
+          .await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -983,9 +1998,16 @@ impl CoordinatorState {
           let res = Receipt::from_bytes(&receipt);
           match res {
             Ok(receipt_rs) => {
+              observe_receipt(&self.misbehavior, ledger_handle, &receipt_rs);
               receipts.add(&receipt_rs);
               if let Ok(vs) = self.verifier_state.read() {
-                if receipts.check_quorum(&vs).is_ok() {
+                if receipts.check_policy(&vs, ledger_handle, policy).is_ok() {
+                  self.attach_straggler_receipts(*ledger_handle, 0, mpsc_rx, |res| {
+                    res
+                      .2
+                      .map(|resp| vec![resp.into_inner().receipt])
+                      .unwrap_or_default()
+                  });
                   return Ok(receipts);
                 }
               }
@@ -1011,9 +2033,11 @@ impl CoordinatorState {
       }
     }
 
+    self.check_endorsement_policy(ledger_handle, &receipts, policy)?;
     Ok(receipts)
   }
 
+  #[allow(clippy::too_many_arguments)]
   pub async fn endorser_append_ledger(
     &self,
     endorsers: &[Vec<u8>],
@@ -1022,8 +2046,11 @@ impl CoordinatorState {
     expected_height: usize,
     block: Block,
     nonces: Nonces,
+    policy: &EndorsementPolicy,
   ) -> Result<Receipts, CoordinatorError> {
     let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+    // every endorser is proposed the same timestamp, so that they sign the same metablock
+    let timestamp = self.proposed_timestamp();
 
     for pk in endorsers {
       let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
@@ -1038,102 +2065,115 @@ impl CoordinatorState {
       let nonces_copy = nonces.clone();
       let pk_bytes = pk.clone();
       let ledger_store = self.ledger_store.clone();
-      let _job = tokio::spawn(async move {
-        loop {
-          let res = append_with_retry(
-            &mut endorser_client,
-            endorser_proto::AppendReq {
-              handle: handle.to_bytes(),
-              block_hash: block_hash_copy.to_bytes(),
-              expected_height: expected_height as u64,
-              block: block_copy.to_bytes(),
-              nonces: nonces_copy.to_bytes(),
-            },
-          )
-          .await;
-          match res {
-            Ok(resp) => {
-              let endorser_proto::AppendResp { receipt } = resp.into_inner();
-              let _ = tx.send((endorser, pk_bytes, Ok(receipt))).await;
-              break;
-            },
-            Err(status) => match process_error(&endorser, Some(&handle), &status) {
-              CoordinatorAction::UpdateEndorser => {
-                let height_to_start = {
-                  if status.code() == Code::NotFound {
-                    0
+      let tail_cache = self.tail_cache.clone();
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          loop {
+            let res = append_with_retry(
+              &mut endorser_client,
+              endorser_proto::AppendReq {
+                handle: handle.to_bytes(),
+                block_hash: block_hash_copy.to_bytes(),
+                expected_height: expected_height as u64,
+                block: block_copy.to_bytes(),
+                nonces: nonces_copy.to_bytes(),
+                timestamp,
+              },
+            )
+            .await;
+            match res {
+              Ok(resp) => {
+                let endorser_proto::AppendResp { receipt } = resp.into_inner();
+                let _ = tx.send((endorser, pk_bytes, Ok(receipt))).await;
+                break;
+              },
+              Err(status) => match process_error(&endorser, Some(&handle), &status) {
+                CoordinatorAction::UpdateEndorser => {
+                  // the endorser reports its height in the details, which are not trusted to hold one
+                  let height_to_start = if status.code() == Code::NotFound {
+                    Some(0)
                   } else {
-                    let bytes = status.details();
-                    let ledger_height = u64::from_le_bytes(bytes[0..].try_into().unwrap()) as usize;
-                    ledger_height.checked_add(1).unwrap()
+                    <[u8; 8]>::try_from(status.details())
+                      .ok()
+                      .and_then(|height| u64::from_le_bytes(height).checked_add(1))
+                      .map(|height| height as usize)
+                  };
+                  if height_to_start.is_none() {
+                    let _ = tx
+                      .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                      .await;
+                    break;
                   }
-                };
-                let height_to_end = expected_height - 1;
-                let res = update_endorser(
-                  ledger_store.clone(),
-                  &mut endorser_client,
-                  handle,
-                  height_to_start,
-                  height_to_end,
-                )
-                .await;
-                match res {
-                  Ok(_resp) => {
-                    continue;
-                  },
-                  Err(status) => match process_error(&endorser, Some(&handle), &status) {
-                    CoordinatorAction::RemoveEndorser => {
-                      let _ = tx
-                        .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
-                        .await;
-                      break;
-                    },
-                    CoordinatorAction::IncrementReceipt => {
+                  let height_to_start = height_to_start.unwrap();
+                  let height_to_end = expected_height - 1;
+                  let res = update_endorser(
+                    ledger_store.clone(),
+                    &tail_cache,
+                    &mut endorser_client,
+                    handle,
+                    height_to_start,
+                    height_to_end,
+                  )
+                  .await;
+                  match res {
+                    Ok(_resp) => {
                       continue;
                     },
-                    _ => {
-                      let _ = tx
-                        .send((
-                          endorser,
-                          pk_bytes,
-                          Err(CoordinatorError::FailedToAppendLedger),
-                        ))
-                        .await;
-                      break;
+                    Err(status) => match process_error(&endorser, Some(&handle), &status) {
+                      CoordinatorAction::RemoveEndorser => {
+                        let _ = tx
+                          .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                          .await;
+                        break;
+                      },
+                      CoordinatorAction::IncrementReceipt => {
+                        continue;
+                      },
+                      _ => {
+                        let _ = tx
+                          .send((
+                            endorser,
+                            pk_bytes,
+                            Err(CoordinatorError::FailedToAppendLedger),
+                          ))
+                          .await;
+                        break;
+                      },
                     },
-                  },
-                }
-              },
-              CoordinatorAction::RemoveEndorser => {
-                let _ = tx
-                  .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
-                  .await;
-                break;
-              },
-              CoordinatorAction::IncrementReceipt => {
-                let _ = tx
-                  .send((
-                    endorser,
-                    pk_bytes,
-                    Err(CoordinatorError::LedgerAlreadyExists),
-                  ))
-                  .await;
-                break;
-              },
-              _ => {
-                let _ = tx
-                  .send((
-                    endorser,
-                    pk_bytes,
-                    Err(CoordinatorError::FailedToAppendLedger),
-                  ))
-                  .await;
-                break;
+                  }
+                },
+                CoordinatorAction::RemoveEndorser => {
+                  let _ = tx
+                    .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                    .await;
+                  break;
+                },
+                CoordinatorAction::IncrementReceipt => {
+                  let _ = tx
+                    .send((
+                      endorser,
+                      pk_bytes,
+                      Err(CoordinatorError::LedgerAlreadyExists),
+                    ))
+                    .await;
+                  break;
+                },
+                _ => {
+                  let _ = tx
+                    .send((
+                      endorser,
+                      pk_bytes,
+                      Err(CoordinatorError::FailedToAppendLedger),
+                    ))
+                    .await;
+                  break;
+                },
               },
-            },
+            }
           }
-        }
-      });
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -1143,9 +2183,13 @@ impl CoordinatorState {
       match res {
         Ok(receipt) => match Receipt::from_bytes(&receipt) {
           Ok(receipt_rs) => {
+            observe_receipt(&self.misbehavior, ledger_handle, &receipt_rs);
             receipts.add(&receipt_rs);
             if let Ok(vs) = self.verifier_state.read() {
-              if receipts.check_quorum(&vs).is_ok() {
+              if receipts.check_policy(&vs, ledger_handle, policy).is_ok() {
+                self.attach_straggler_receipts(*ledger_handle, expected_height, mpsc_rx, |res| {
+                  res.2.map(|receipt| vec![receipt]).unwrap_or_default()
+                });
                 return Ok(receipts);
               }
             }
@@ -1166,16 +2210,206 @@ impl CoordinatorState {
       }
     }
 
+    self.check_endorsement_policy(ledger_handle, &receipts, policy)?;
     Ok(receipts)
   }
 
-  async fn endorser_update_ledger(
+  /// Appends `entries` (the block hash, block, and nonces of each block) to a ledger at
+  /// consecutive heights from `expected_height` with one AppendBatch call per endorser, and
+  /// returns the receipts for each height. An endorser signs the entries of a batch in order, so
+  /// the receipts for the last height have the fewest signers.
+  pub async fn endorser_append_ledger_batch(
     &self,
     endorsers: &[Vec<u8>],
     ledger_handle: &Handle,
-    max_height: usize,
-    endorser_height_map: &HashMap<String, usize>,
-  ) {
+    entries: &[(NimbleDigest, Block, Nonces)],
+    expected_height: usize,
+    policy: &EndorsementPolicy,
+  ) -> Result<Vec<Receipts>, CoordinatorError> {
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+    let timestamp = self.proposed_timestamp();
+    let request = endorser_proto::AppendBatchReq {
+      entries: entries
+        .iter()
+        .enumerate()
+        .map(
+          |(i, (block_hash, block, nonces))| endorser_proto::AppendReq {
+            handle: ledger_handle.to_bytes(),
+            block_hash: block_hash.to_bytes(),
+            expected_height: (expected_height + i) as u64,
+            block: block.to_bytes(),
+            nonces: nonces.to_bytes(),
+            timestamp,
+          },
+        )
+        .collect(),
+    };
+
+    for pk in endorsers {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+
+      let tx = mpsc_tx.clone();
+      let handle = *ledger_handle;
+      let request = request.clone();
+      let pk_bytes = pk.clone();
+      let ledger_store = self.ledger_store.clone();
+      let tail_cache = self.tail_cache.clone();
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          loop {
+            let res = append_batch_with_retry(&mut endorser_client, request.clone()).await;
+            match res {
+              Ok(resp) => {
+                let endorser_proto::AppendBatchResp { receipts } = resp.into_inner();
+                let _ = tx.send((endorser, pk_bytes, Ok(receipts))).await;
+                break;
+              },
+              Err(status) => match process_error(&endorser, Some(&handle), &status) {
+                CoordinatorAction::UpdateEndorser => {
+                  // the endorser reports its height in the details, which are not trusted to hold one
+                  let height_to_start = if status.code() == Code::NotFound {
+                    Some(0)
+                  } else {
+                    <[u8; 8]>::try_from(status.details())
+                      .ok()
+                      .and_then(|height| u64::from_le_bytes(height).checked_add(1))
+                      .map(|height| height as usize)
+                  };
+                  if height_to_start.is_none() {
+                    let _ = tx
+                      .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                      .await;
+                    break;
+                  }
+                  let height_to_start = height_to_start.unwrap();
+                  let height_to_end = expected_height - 1;
+                  let res = update_endorser(
+                    ledger_store.clone(),
+                    &tail_cache,
+                    &mut endorser_client,
+                    handle,
+                    height_to_start,
+                    height_to_end,
+                  )
+                  .await;
+                  match res {
+                    Ok(_resp) => {
+                      continue;
+                    },
+                    Err(status) => match process_error(&endorser, Some(&handle), &status) {
+                      CoordinatorAction::RemoveEndorser => {
+                        let _ = tx
+                          .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                          .await;
+                        break;
+                      },
+                      CoordinatorAction::IncrementReceipt => {
+                        continue;
+                      },
+                      _ => {
+                        let _ = tx
+                          .send((
+                            endorser,
+                            pk_bytes,
+                            Err(CoordinatorError::FailedToAppendLedger),
+                          ))
+                          .await;
+                        break;
+                      },
+                    },
+                  }
+                },
+                CoordinatorAction::RemoveEndorser => {
+                  let _ = tx
+                    .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                    .await;
+                  break;
+                },
+                CoordinatorAction::IncrementReceipt => {
+                  let _ = tx
+                    .send((
+                      endorser,
+                      pk_bytes,
+                      Err(CoordinatorError::LedgerAlreadyExists),
+                    ))
+                    .await;
+                  break;
+                },
+                _ => {
+                  let _ = tx
+                    .send((
+                      endorser,
+                      pk_bytes,
+                      Err(CoordinatorError::FailedToAppendLedger),
+                    ))
+                    .await;
+                  break;
+                },
+              },
+            }
+          }
+        },
+      );
+    }
+
+    drop(mpsc_tx);
+
+    let mut receipts = vec![Receipts::new(); entries.len()];
+    while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
+      match res {
+        Ok(receipts_bytes) => {
+          for (i, receipt) in receipts_bytes.iter().take(entries.len()).enumerate() {
+            match Receipt::from_bytes(receipt) {
+              Ok(receipt_rs) => {
+                observe_receipt(&self.misbehavior, ledger_handle, &receipt_rs);
+                receipts[i].add(&receipt_rs);
+              },
+              Err(error) => {
+                eprintln!("Failed to parse a receipt (err={:?}", error);
+                break;
+              },
+            }
+          }
+          // every endorser that signed the last entry signed the ones before it
+          if let Ok(vs) = self.verifier_state.read() {
+            if receipts[entries.len() - 1]
+              .check_policy(&vs, ledger_handle, policy)
+              .is_ok()
+            {
+              self.attach_straggler_receipts(*ledger_handle, expected_height, mpsc_rx, |res| {
+                res.2.unwrap_or_default()
+              });
+              return Ok(receipts);
+            }
+          }
+        },
+        Err(error) => {
+          if error == CoordinatorError::UnexpectedError {
+            eprintln!(
+              "append_batch from endorser {} received unexpected error {:?}",
+              endorser, error
+            );
+            self.disconnect_endorsers(&vec![(pk_bytes, endorser)]).await;
+          }
+        },
+      }
+    }
+
+    self.check_endorsement_policy(ledger_handle, &receipts[entries.len() - 1], policy)?;
+    Ok(receipts)
+  }
+
+  async fn endorser_update_ledger(
+    &self,
+    endorsers: &[Vec<u8>],
+    ledger_handle: &Handle,
+    max_height: usize,
+    endorser_height_map: &HashMap<String, usize>,
+  ) {
     let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
 
     for pk in endorsers {
@@ -1197,20 +2431,25 @@ impl CoordinatorState {
       }
 
       let ledger_store = self.ledger_store.clone();
+      let tail_cache = self.tail_cache.clone();
       let handle = *ledger_handle;
       let pk_bytes = pk.clone();
       let tx = mpsc_tx.clone();
-      let _job = tokio::spawn(async move {
-        let res = update_endorser(
-          ledger_store,
-          &mut endorser_client,
-          handle,
-          height_to_start,
-          max_height,
-        )
-        .await;
-        let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = update_endorser(
+            ledger_store,
+            &tail_cache,
+            &mut endorser_client,
+            handle,
+            height_to_start,
+            max_height,
+          )
+          .await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -1251,44 +2490,47 @@ impl CoordinatorState {
       let handle = *ledger_handle;
       let nonce = *client_nonce;
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
-        let res = read_latest_with_retry(
-          &mut endorser_client,
-          endorser_proto::ReadLatestReq {
-            handle: handle.to_bytes(),
-            nonce: nonce.to_bytes(),
-          },
-        )
-        .await;
-        match res {
-          Ok(resp) => {
-            let endorser_proto::ReadLatestResp {
-              receipt,
-              block,
-              nonces,
-            } = resp.into_inner();
-            let _ = tx
-              .send((endorser, pk_bytes, Ok((receipt, block, nonces))))
-              .await;
-          },
-          Err(status) => match process_error(&endorser, Some(&handle), &status) {
-            CoordinatorAction::RemoveEndorser => {
-              let _ = tx
-                .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
-                .await;
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = read_latest_with_retry(
+            &mut endorser_client,
+            endorser_proto::ReadLatestReq {
+              handle: handle.to_bytes(),
+              nonce: nonce.to_bytes(),
             },
-            _ => {
+          )
+          .await;
+          match res {
+            Ok(resp) => {
+              let endorser_proto::ReadLatestResp {
+                receipt,
+                block,
+                nonces,
+              } = resp.into_inner();
               let _ = tx
-                .send((
-                  endorser,
-                  pk_bytes,
-                  Err(CoordinatorError::FailedToReadLedger),
-                ))
+                .send((endorser, pk_bytes, Ok((receipt, block, nonces))))
                 .await;
             },
-          },
-        }
-      });
+            Err(status) => match process_error(&endorser, Some(&handle), &status) {
+              CoordinatorAction::RemoveEndorser => {
+                let _ = tx
+                  .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                  .await;
+              },
+              _ => {
+                let _ = tx
+                  .send((
+                    endorser,
+                    pk_bytes,
+                    Err(CoordinatorError::FailedToReadLedger),
+                  ))
+                  .await;
+              },
+            },
+          }
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -1308,7 +2550,9 @@ impl CoordinatorState {
             }
             receipts.add(&receipt_rs);
             if let Ok(vs) = self.verifier_state.read() {
-              if let Ok(_h) = receipts.check_quorum(&vs) {
+              if let Ok(_h) =
+                receipts.check_policy(&vs, ledger_handle, &EndorsementPolicy::Majority)
+              {
                 if let Ok(block_rs) = Block::from_bytes(&block) {
                   if let Ok(nonces_rs) = Nonces::from_bytes(&nonces) {
                     return Ok(LedgerEntry::new(block_rs, receipts, Some(nonces_rs)));
@@ -1341,9 +2585,95 @@ impl CoordinatorState {
     Err(CoordinatorError::FailedToObtainQuorum)
   }
 
+  // Locks or unlocks the endorsers, waits for all of them to answer, and returns those that did.
+  // An endorser that fails to answer is left as it is: locking only narrows the window in which
+  // appends can race a view change, and finalizing the endorser closes it.
+  pub(crate) async fn endorser_set_locked(
+    &self,
+    endorsers: &EndorserHostnames,
+    locked: bool,
+  ) -> EndorserHostnames {
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+
+    for (pk, _uri) in endorsers {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+
+      let tx = mpsc_tx.clone();
+      let pk_bytes = pk.clone();
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = lock_endorser_with_retry(&mut endorser_client, locked).await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
+    }
+
+    drop(mpsc_tx);
+
+    let mut acknowledged = EndorserHostnames::new();
+    while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
+      match res {
+        Ok(_resp) => acknowledged.push((pk_bytes, endorser)),
+        Err(status) => eprintln!(
+          "Failed to {} endorser {} (status={:?})",
+          if locked { "lock" } else { "unlock" },
+          endorser,
+          status
+        ),
+      }
+    }
+    acknowledged
+  }
+
+  // Reads the state of the endorsers, and returns the states of those that answered along with
+  // their keys
+  pub(crate) async fn endorser_read_state(
+    &self,
+    endorsers: &EndorserHostnames,
+  ) -> Vec<(Vec<u8>, endorser_proto::ReadStateResp)> {
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+
+    for (pk, _uri) in endorsers {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+
+      let tx = mpsc_tx.clone();
+      let pk_bytes = pk.clone();
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res =
+            read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
+    }
+
+    drop(mpsc_tx);
+
+    let mut states = Vec::new();
+    while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
+      match res {
+        Ok(resp) => states.push((pk_bytes, resp.into_inner())),
+        Err(status) => eprintln!(
+          "Failed to read the state of endorser {} (status={:?})",
+          endorser, status
+        ),
+      }
+    }
+    states
+  }
+
   async fn endorser_finalize_state(
     &self,
     endorsers: &EndorserHostnames,
+    prev_view_hash: &NimbleDigest,
     block_hash: &NimbleDigest,
     expected_height: usize,
   ) -> (Receipts, Vec<endorser_proto::LedgerTailMap>) {
@@ -1357,18 +2687,23 @@ impl CoordinatorState {
 
       let tx = mpsc_tx.clone();
       let block = *block_hash;
+      let prev_view = *prev_view_hash;
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
-        let res = finalize_state_with_retry(
-          &mut endorser_client,
-          endorser_proto::FinalizeStateReq {
-            block_hash: block.to_bytes(),
-            expected_height: expected_height as u64,
-          },
-        )
-        .await;
-        let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = finalize_state_with_retry(
+            &mut endorser_client,
+            endorser_proto::FinalizeStateReq {
+              block_hash: block.to_bytes(),
+              expected_height: expected_height as u64,
+              prev_view_hash: prev_view.to_bytes(),
+            },
+          )
+          .await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -1442,18 +2777,21 @@ impl CoordinatorState {
       let ledger_tail_maps_arc_copy = ledger_tail_maps_arc.clone();
       let ledger_chunks_copy = ledger_chunks.clone();
       let receipts_copy = receipts.to_bytes();
-      let _job = tokio::spawn(async move {
-        let res = activate_with_retry(
-          &mut endorser_client,
-          old_config_copy.to_bytes(),
-          new_config_copy.to_bytes(),
-          ledger_tail_maps_arc_copy,
-          ledger_chunks_copy,
-          receipts_copy,
-        )
-        .await;
-        let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = activate_with_retry(
+            &mut endorser_client,
+            old_config_copy.to_bytes(),
+            new_config_copy.to_bytes(),
+            ledger_tail_maps_arc_copy,
+            ledger_chunks_copy,
+            receipts_copy,
+          )
+          .await;
+          let _ = tx.send((endorser, pk_bytes, res)).await;
+        },
+      );
     }
 
     drop(mpsc_tx);
@@ -1489,99 +2827,698 @@ impl CoordinatorState {
       return Err(CoordinatorError::NoNewEndorsers);
     }
 
-    // Package the list of endorsers into a genesis block of the view ledger
-    let view_ledger_genesis_block = {
-      let res = bincode::serialize(&new_endorsers);
-      if res.is_err() {
-        eprintln!("Failed to serialize endorser hostnames {:?}", res);
-        return Err(CoordinatorError::FailedToSerde);
-      }
-      let block_vec = res.unwrap();
-      Block::new(&block_vec)
-    };
+    // Package the list of endorsers and their attestations into a genesis block of the view ledger
+    let view_ledger_genesis_block = self.encode_view_block(&new_endorsers, &[])?;
 
-    // Read the current ledger tail
-    let res = self.ledger_store.read_view_ledger_tail().await;
+    let view_ledger_height = self
+      .reconfigure(
+        &existing_endorsers,
+        &new_endorsers,
+        &view_ledger_genesis_block,
+      )
+      .await?;
 
-    if res.is_err() {
-      eprintln!(
-        "Failed to read from the view ledger in the ledger store ({:?})",
-        res.unwrap_err()
-      );
-      return Err(CoordinatorError::FailedToCallLedgerStore);
+    self
+      .record_admin_event(AdminAction::ReplaceEndorsers {
+        uris: new_endorsers.iter().map(|(_pk, uri)| uri.clone()).collect(),
+        view_height: view_ledger_height,
+      })
+      .await
+  }
+
+  /// Returns when the lease of the endorsers of the current view expires (ms since epoch), or
+  /// None if their lease does not expire
+  pub fn get_endorser_lease_expiry(&self) -> Result<Option<u64>, CoordinatorError> {
+    match self.view_lease_expiry.read() {
+      Ok(view_lease_expiry) => Ok(*view_lease_expiry),
+      Err(_) => Err(CoordinatorError::FailedToAcquireReadLock),
     }
+  }
 
-    let (tail, height) = res.unwrap();
+  // Takes the lease of the endorsers from the block of the view ledger that lists them
+  fn set_view_lease_expiry(&self, view_ledger_block: &Block) -> Result<(), CoordinatorError> {
+    let lease_expiry = retrieve_lease_from_config(&view_ledger_block.to_bytes())
+      .map_err(|_e| CoordinatorError::FailedToReadViewLedger)?;
+    let mut view_lease_expiry = self
+      .view_lease_expiry
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *view_lease_expiry = lease_expiry;
+    Ok(())
+  }
 
-    // Store the genesis block of the view ledger in the ledger store
-    let res = self
-      .ledger_store
-      .append_view_ledger(&view_ledger_genesis_block, height + 1)
-      .await;
-    if let Err(e) = res {
-      eprintln!(
-        "Failed to append to the view ledger in the ledger store ({:?})",
-        e,
-      );
-      return Err(CoordinatorError::FailedToCallLedgerStore);
+  // Endorsers whose lease expired sign nothing, so requests that need their signatures are
+  // rejected until a view change renews the lease
+  fn check_endorser_lease(&self) -> Result<(), CoordinatorError> {
+    match self.get_endorser_lease_expiry()? {
+      Some(expiry) if current_timestamp() >= expiry => Err(CoordinatorError::EndorserLeaseExpired),
+      _ => Ok(()),
+    }
+  }
+
+  /// Renews the lease of the endorsers with a view change that keeps them, and returns the
+  /// height of the new view
+  pub async fn renew_endorser_leases(&self) -> Result<usize, CoordinatorError> {
+    let existing_endorsers = self.get_endorser_hostnames();
+    if existing_endorsers.is_empty() {
+      return Err(CoordinatorError::NoNewEndorsers);
     }
 
-    let view_ledger_height = res.unwrap();
+    let view_ledger_block = self.encode_view_block(&existing_endorsers, &[])?;
+    let lease_expiry = retrieve_lease_from_config(&view_ledger_block.to_bytes())
+      .map_err(|_e| CoordinatorError::FailedToSerde)?;
+    let view_ledger_height = self
+      .reconfigure(&existing_endorsers, &existing_endorsers, &view_ledger_block)
+      .await?;
 
     self
-      .apply_view_change(
-        &existing_endorsers,
-        &new_endorsers,
-        &tail,
-        &view_ledger_genesis_block,
-        view_ledger_height,
-      )
-      .await
+      .record_admin_event(AdminAction::RenewEndorserLeases {
+        lease_expiry,
+        view_height: view_ledger_height,
+      })
+      .await?;
+    Ok(view_ledger_height)
   }
 
-  async fn apply_view_change(
+  /// Replaces the key of the endorser at `uri` with a new one without replacing the endorser. The
+  /// endorser generates the key and hands over to it with its current key, and a view change
+  /// lists the new key in place of the old one and carries the handover, so that verifiers accept
+  /// the new key. The other endorsers stay in the view and keep their keys.
+  pub async fn rotate_endorser_key(
     &self,
-    existing_endorsers: &EndorserHostnames,
-    new_endorsers: &EndorserHostnames,
-    view_ledger_entry: &LedgerEntry,
-    view_ledger_genesis_block: &Block,
-    view_ledger_height: usize,
-  ) -> Result<(), CoordinatorError> {
-    // Retrieve the view tail metablock
-    let view_tail_receipts = view_ledger_entry.get_receipts();
-    let view_tail_metablock = if view_tail_receipts.is_empty() {
-      if view_ledger_height != 1 {
-        eprintln!(
-          "cannot get view tail metablock from empty receipts (height = {}",
-          view_ledger_height
-        );
-        return Err(CoordinatorError::UnexpectedError);
-      } else {
-        MetaBlock::default()
+    uri: &str,
+  ) -> Result<KeyRotationReport, CoordinatorError> {
+    let existing_endorsers = self.get_endorser_hostnames();
+    let pk = match self.get_endorser_pk(uri) {
+      Some(pk) => pk,
+      None => return Err(CoordinatorError::InvalidEndorserUri),
+    };
+    let (mut endorser_client, _uri) = match self.get_endorser_client(&pk) {
+      Some(client) => client,
+      None => return Err(CoordinatorError::InvalidEndorserUri),
+    };
+
+    let res = rotate_key_with_retry(&mut endorser_client).await;
+    if let Err(status) = res {
+      eprintln!(
+        "Failed to rotate the key of endorser {} ({:?})",
+        uri, status
+      );
+      return Err(CoordinatorError::FailedToRotateKey);
+    }
+    let endorser_proto::RotateKeyResp {
+      pk: new_pk,
+      attestation,
+      handover,
+    } = res.unwrap().into_inner();
+
+    let handover = match KeyHandover::from_bytes(&handover) {
+      Ok(handover) if *handover.get_old_pk() == pk && *handover.get_new_pk() == new_pk => handover,
+      _ => return Err(CoordinatorError::InvalidKeyHandover),
+    };
+    if let Ok(vs) = self.verifier_state.read() {
+      if vs.verify_key_handover(&handover).is_err() {
+        return Err(CoordinatorError::InvalidKeyHandover);
       }
     } else {
-      let res = view_tail_receipts.get_metablock();
-      match res {
-        Ok(metablock) => metablock,
-        Err(_e) => {
-          eprintln!("faield to retrieve metablock from view receipts");
-          return Err(CoordinatorError::UnexpectedError);
+      return Err(CoordinatorError::FailedToAcquireReadLock);
+    }
+    self.verify_attestation(&new_pk, &attestation)?;
+    // the old key vouches for the new one
+    self.allow_endorser(&new_pk)?;
+
+    // the endorser is reached under its new key over the connections it already has
+    if let Ok(mut conn_map_wr) = self.conn_map.write() {
+      let endorser = match conn_map_wr.get(&pk) {
+        Some(endorser) => EndorserClients {
+          clients: endorser.clients.clone(),
+          uri: endorser.uri.clone(),
+          incarnation: endorser.incarnation,
+          attestation,
+          health: endorser.health.clone(),
         },
-      }
-    };
+        None => return Err(CoordinatorError::InvalidEndorserUri),
+      };
+      conn_map_wr.insert(new_pk.clone(), endorser);
+    } else {
+      return Err(CoordinatorError::FailedToAcquireWriteLock);
+    }
 
-    let (finalize_receipts, ledger_tail_maps) = if existing_endorsers.is_empty() {
-      assert!(view_ledger_height == 1);
+    let new_endorsers = existing_endorsers
+      .iter()
+      .map(|(endorser_pk, endorser_uri)| {
+        if *endorser_pk == pk {
+          (new_pk.clone(), endorser_uri.clone())
+        } else {
+          (endorser_pk.clone(), endorser_uri.clone())
+        }
+      })
+      .collect::<EndorserHostnames>();
+    let view_ledger_block =
+      self.encode_view_block(&new_endorsers, std::slice::from_ref(&handover))?;
 
-      (Receipts::new(), Vec::new())
-    } else {
+    let res = self
+      .reconfigure(&existing_endorsers, &new_endorsers, &view_ledger_block)
+      .await;
+    if res.is_err() {
       self
-        .endorser_finalize_state(
-          existing_endorsers,
+        .disconnect_endorsers(&vec![(new_pk.clone(), uri.to_string())])
+        .await;
+    }
+    let view_ledger_height = res?;
+
+    // the endorser must sign in the new view with its new key
+    let (mut endorser_client, _uri) = match self.get_endorser_client(&new_pk) {
+      Some(client) => client,
+      None => return Err(CoordinatorError::FailedToActivate),
+    };
+    match read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await {
+      Ok(resp) if resp.get_ref().mode == endorser_proto::EndorserMode::Active as i32 => {},
+      res => {
+        eprintln!(
+          "Endorser {} is not active with its new key ({:?})",
+          uri, res
+        );
+        return Err(CoordinatorError::FailedToActivate);
+      },
+    }
+
+    self
+      .record_admin_event(AdminAction::RotateEndorserKey {
+        uri: uri.to_string(),
+        handover: base64_url::encode(&handover.to_bytes()),
+        view_height: view_ledger_height,
+      })
+      .await?;
+    Ok(KeyRotationReport {
+      pk: new_pk,
+      handover,
+      view_height: view_ledger_height,
+    })
+  }
+
+  /// Stops sending requests to the endorsers at `uris` without a view change. The endorsers stay
+  /// in the view, so the endorsers that remain must still form a quorum of it. Returns the number
+  /// of endorsers that remain.
+  pub async fn remove_endorsers(&self, uris: &[String]) -> Result<usize, CoordinatorError> {
+    let endorsers = self.get_endorser_hostnames();
+    let mut removed: EndorserHostnames = Vec::new();
+    for uri in uris {
+      match endorsers
+        .iter()
+        .find(|(_pk, endorser_uri)| endorser_uri == uri)
+      {
+        Some(endorser) if !removed.contains(endorser) => removed.push(endorser.clone()),
+        Some(_) => {},
+        None => return Err(CoordinatorError::InvalidEndorserUri),
+      }
+    }
+
+    let (tail, _height, _attestations) = self.read_view_tail().await?;
+    let view = match tail.get_receipts().get_metablock() {
+      Ok(metablock) => metablock.hash(),
+      Err(_) => return Err(CoordinatorError::UnexpectedError),
+    };
+    let num_endorsers = match self.verifier_state.read() {
+      Ok(vs) => match vs.get_pks_for_view(&view) {
+        Ok(pks) => pks.len(),
+        Err(_) => return Err(CoordinatorError::UnexpectedError),
+      },
+      Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+    };
+    let remaining = endorsers.len() - removed.len();
+    if remaining * 2 <= num_endorsers {
+      return Err(CoordinatorError::FailedToObtainQuorum);
+    }
+
+    self.disconnect_endorsers(&removed).await;
+    for (_pk, uri) in removed {
+      self
+        .record_admin_event(AdminAction::RemoveEndorser { uri })
+        .await?;
+    }
+    Ok(remaining)
+  }
+
+  /// Sends requests again to endorsers of the current view that stopped receiving them, because
+  /// they were removed or disconnected after failing, without a view change. Each endorser is
+  /// first brought up to date with the ledger store and must sign a state that includes every
+  /// ledger's tail. Returns the number of endorsers that serve requests.
+  pub async fn readmit_endorsers(&self, uris: &[String]) -> Result<usize, CoordinatorError> {
+    let (tail, _height, _attestations) = self.read_view_tail().await?;
+    let view = match tail.get_receipts().get_metablock() {
+      Ok(metablock) => metablock.hash(),
+      Err(_) => return Err(CoordinatorError::UnexpectedError),
+    };
+
+    let tails = self.read_ledger_tails().await?;
+
+    let existing_uris = self.get_endorser_uris();
+    for uri in uris {
+      if existing_uris.contains(uri) {
+        continue;
+      }
+      // the endorser receives no requests until it caught up, so it cannot fail them meanwhile
+      self.catch_up_endorser(uri, &view, &tails).await?;
+      if self
+        .connect_endorsers(std::slice::from_ref(uri))
+        .await
+        .is_empty()
+      {
+        return Err(CoordinatorError::FailedToConnectToEndorser);
+      }
+      self
+        .record_admin_event(AdminAction::ReadmitEndorser { uri: uri.clone() })
+        .await?;
+    }
+    Ok(self.get_endorser_pks().len())
+  }
+
+  // Appends to the endorser at `uri` the entries of each ledger that it is missing, up to the
+  // heights in `tails`, and checks that it then signs a state that includes them
+  async fn catch_up_endorser(
+    &self,
+    uri: &str,
+    view: &NimbleDigest,
+    tails: &[(Handle, usize)],
+  ) -> Result<(), CoordinatorError> {
+    let endorser_endpoint = self.endorser_endpoint(uri)?;
+    let res = connect_endpoint(endorser_endpoint, self.endorser_connector.clone()).await;
+    if let Err(error) = res {
+      eprintln!("Failed to connect to the endorser {}: {:?}", uri, error);
+      return Err(CoordinatorError::FailedToConnectToEndorser);
+    }
+    let mut endorser_client =
+      endorser_proto::endorser_call_client::EndorserCallClient::new(res.unwrap());
+
+    let res =
+      get_public_key_with_retry(&mut endorser_client, endorser_proto::GetPublicKeyReq {}).await;
+    if res.is_err() {
+      return Err(CoordinatorError::UnableToRetrievePublicKey);
+    }
+    let pk = res.unwrap().into_inner().pk;
+    let in_view = match self.verifier_state.read() {
+      Ok(vs) => match vs.get_pks_for_view(view) {
+        Ok(pks) => pks.contains(&pk),
+        Err(_) => false,
+      },
+      Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+    };
+    if !in_view {
+      return Err(CoordinatorError::EndorserNotInView);
+    }
+
+    let heights = self
+      .read_signed_endorser_state(&mut endorser_client, &pk, view)
+      .await?;
+    for (handle, tail_height) in tails {
+      let height_to_start = match heights.get(handle) {
+        Some(height) => height + 1,
+        None => 0,
+      };
+      if height_to_start > *tail_height {
+        continue;
+      }
+      let res = update_endorser(
+        self.ledger_store.clone(),
+        &self.tail_cache,
+        &mut endorser_client,
+        *handle,
+        height_to_start,
+        *tail_height,
+      )
+      .await;
+      if let Err(status) = res {
+        eprintln!(
+          "Failed to catch up the endorser {} on a ledger ({:?})",
+          uri, status
+        );
+        return Err(CoordinatorError::FailedToCatchUpEndorser);
+      }
+    }
+
+    let heights = self
+      .read_signed_endorser_state(&mut endorser_client, &pk, view)
+      .await?;
+    for (handle, tail_height) in tails {
+      match heights.get(handle) {
+        Some(height) if height >= tail_height => {},
+        _ => return Err(CoordinatorError::FailedToCatchUpEndorser),
+      }
+    }
+    Ok(())
+  }
+
+  // Reads the state of an active endorser of `view` and checks its signature over it, returning
+  // the height of each ledger the endorser knows about
+  async fn read_signed_endorser_state(
+    &self,
+    endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+    pk: &[u8],
+    view: &NimbleDigest,
+  ) -> Result<HashMap<Handle, usize>, CoordinatorError> {
+    let res = read_state_with_retry(endorser_client, endorser_proto::ReadStateReq {}).await;
+    if let Err(status) = res {
+      eprintln!("Failed to read the state of an endorser ({:?})", status);
+      return Err(CoordinatorError::FailedToReadLatestState);
+    }
+    let endorser_proto::ReadStateResp {
+      receipt,
+      mode,
+      ledger_tail_map,
+    } = res.unwrap().into_inner();
+    if mode != endorser_proto::EndorserMode::Active as i32 {
+      return Err(CoordinatorError::EndorserNotInView);
+    }
+    let receipt = match Receipt::from_bytes(&receipt) {
+      Ok(receipt) if receipt.get_id_sig().get_id() == pk => receipt,
+      _ => return Err(CoordinatorError::InvalidReceipt),
+    };
+    if receipt.get_metablock().hash() != *view {
+      return Err(CoordinatorError::EndorserNotInView);
+    }
+
+    // endorsers sign the hash of their state along with the view ledger's tail
+    let group_identity = match self.verifier_state.read() {
+      Ok(vs) => *vs.get_group_identity(),
+      Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+    };
+    let state_hash = produce_hash_of_state(&ledger_tail_map);
+    let statement = ViewChangeAttestation::new(&group_identity, &state_hash, view);
+    if *receipt.get_view() != state_hash || statement.verify(receipt.get_id_sig()).is_err() {
+      return Err(CoordinatorError::InvalidReceipt);
+    }
+
+    let mut heights = HashMap::new();
+    for entry in ledger_tail_map {
+      match NimbleDigest::from_bytes(&entry.handle) {
+        Ok(handle) => {
+          heights.insert(handle, entry.height as usize);
+        },
+        Err(_) => return Err(CoordinatorError::InvalidReceipt),
+      }
+    }
+    Ok(heights)
+  }
+
+  /// returns the height of the newest view on the view ledger
+  pub fn get_view_height(&self) -> Result<usize, CoordinatorError> {
+    match self.verifier_state.read() {
+      Ok(vs) => Ok(vs.get_view_ledger_height()),
+      Err(_) => Err(CoordinatorError::FailedToAcquireReadLock),
+    }
+  }
+
+  /// Checks that endorsers have been initialized into a view and that a majority of the
+  /// connected endorsers answer, which the coordinator needs to serve requests. The answers
+  /// update the health of the endorsers.
+  pub async fn is_quorum_reachable(&self) -> bool {
+    if self.get_view_height().unwrap_or(0) == 0 {
+      return false;
+    }
+    let endorsers = self.get_endorser_hostnames();
+    let mut jobs = Vec::new();
+    for (pk, uri) in &endorsers {
+      if let Some((mut endorser_client, _endorser)) = self.get_endorser_client(pk) {
+        let job = spawn_endorser_call(
+          info_span!("endorser", otel.kind = "client", uri = %uri),
+          async move {
+            get_public_key_with_retry(&mut endorser_client, endorser_proto::GetPublicKeyReq {})
+              .await
+              .map(|_resp| ())
+          },
+        );
+        jobs.push((pk, job));
+      }
+    }
+    let mut num_reachable = 0;
+    for (pk, job) in jobs {
+      let res = match job.await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(status)) => Err(format!("{:?}: {}", status.code(), status.message())),
+        Err(error) => Err(error.to_string()),
+      };
+      if res.is_ok() {
+        num_reachable += 1;
+      }
+      if let Ok(mut conn_map_wr) = self.conn_map.write() {
+        if let Some(endorser) = conn_map_wr.get_mut(pk) {
+          match res {
+            Ok(()) => endorser.health.record_success(),
+            Err(error) => endorser.health.record_failure(error, Instant::now()),
+          }
+        }
+      }
+    }
+    num_reachable > endorsers.len() / 2
+  }
+
+  /// Replaces the connections to the endorsers that are down with new ones, once their backoff
+  /// elapsed, and returns the URIs of the endorsers that answered on the new connections
+  pub async fn reconnect_endorsers(&self) -> Vec<String> {
+    let now = Instant::now();
+    let due = match self.conn_map.read() {
+      Ok(conn_map_rd) => conn_map_rd
+        .iter()
+        .filter(|(_pk, endorser)| endorser.health.is_reconnect_due(now))
+        .map(|(pk, endorser)| (pk.clone(), endorser.uri.clone()))
+        .collect::<Vec<(Vec<u8>, String)>>(),
+      Err(_) => return Vec::new(),
+    };
+
+    let mut reconnected = Vec::new();
+    for (pk, uri) in due {
+      let res = self.connect_endorser_clients(&uri, &pk).await;
+      if let Ok(mut conn_map_wr) = self.conn_map.write() {
+        // the endorser may have left the view while the coordinator reconnected
+        if let Some(endorser) = conn_map_wr.get_mut(&pk) {
+          match res {
+            Ok(clients) => {
+              endorser.clients = clients;
+              endorser.health.record_reconnect();
+              reconnected.push(uri);
+            },
+            Err(error) => endorser
+              .health
+              .record_failure(format!("failed to reconnect ({:?})", error), Instant::now()),
+          }
+        }
+      }
+    }
+    reconnected
+  }
+
+  // Opens new connections to the endorser at `uri`, on which it must answer with the key `pk`
+  async fn connect_endorser_clients(
+    &self,
+    uri: &str,
+    pk: &[u8],
+  ) -> Result<
+    Vec<endorser_proto::endorser_call_client::EndorserCallClient<Channel>>,
+    CoordinatorError,
+  > {
+    let mut clients = Vec::with_capacity(self.num_grpc_channels);
+    for _idx in 0..self.num_grpc_channels {
+      let endpoint = self.endorser_endpoint(uri)?;
+      let res = connect_endpoint(endpoint, self.endorser_connector.clone()).await;
+      if let Err(error) = res {
+        warn!(
+          endorser = uri,
+          ?error,
+          "failed to reconnect to the endorser"
+        );
+        return Err(CoordinatorError::FailedToConnectToEndorser);
+      }
+      let mut client = endorser_proto::endorser_call_client::EndorserCallClient::new(res.unwrap());
+      let res = get_public_key_with_retry(&mut client, endorser_proto::GetPublicKeyReq {}).await;
+      match res {
+        Ok(resp) if resp.get_ref().pk == pk => clients.push(client),
+        Ok(_resp) => return Err(CoordinatorError::InvalidEndorserPublicKey),
+        Err(_status) => return Err(CoordinatorError::UnableToRetrievePublicKey),
+      }
+    }
+    Ok(clients)
+  }
+
+  /// Returns the health of the connected endorsers, in the order of their URIs
+  pub fn get_endorser_health(&self) -> Vec<EndorserHealthReport> {
+    let mut reports = match self.conn_map.read() {
+      Ok(conn_map_rd) => conn_map_rd
+        .iter()
+        .map(|(pk, endorser)| endorser.health.report(&endorser.uri, pk))
+        .collect::<Vec<EndorserHealthReport>>(),
+      Err(_) => Vec::new(),
+    };
+    reports.sort_by(|a, b| a.uri.cmp(&b.uri));
+    reports
+  }
+
+  /// Removes the endorser at `uri` from service: it stops receiving writes, its signed final
+  /// state is recorded in the admin ledger, and a view change moves the ledgers to the
+  /// `replacements`. Succeeds only once a quorum of the new view is confirmed active.
+  pub async fn decommission_endorser(
+    &self,
+    uri: &str,
+    replacements: &[String],
+  ) -> Result<DecommissionReport, CoordinatorError> {
+    let pk = match self.get_endorser_pk(uri) {
+      Some(pk) => pk,
+      None => return Err(CoordinatorError::InvalidEndorserUri),
+    };
+    let existing_uris = self.get_endorser_uris();
+    if replacements.is_empty() || replacements.iter().any(|r| existing_uris.contains(r)) {
+      // endorsers cannot rejoin a view once they have been finalized
+      return Err(CoordinatorError::NoNewEndorsers);
+    }
+
+    if let Ok(mut draining) = self.draining.write() {
+      draining.insert(pk.clone());
+    } else {
+      return Err(CoordinatorError::FailedToAcquireWriteLock);
+    }
+    let res = self
+      .decommission_drained_endorser(uri, &pk, replacements)
+      .await;
+    if let Ok(mut draining) = self.draining.write() {
+      draining.remove(&pk);
+    }
+    res
+  }
+
+  async fn decommission_drained_endorser(
+    &self,
+    uri: &str,
+    pk: &[u8],
+    replacements: &[String],
+  ) -> Result<DecommissionReport, CoordinatorError> {
+    let (mut endorser_client, _uri) = match self.get_endorser_client(pk) {
+      Some(client) => client,
+      None => return Err(CoordinatorError::InvalidEndorserUri),
+    };
+    let res = read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await;
+    let final_state = match res {
+      Ok(resp) => {
+        let endorser_proto::ReadStateResp { receipt, .. } = resp.into_inner();
+        match Receipt::from_bytes(&receipt) {
+          Ok(receipt) if receipt.get_id_sig().get_id() == pk => receipt,
+          _ => return Err(CoordinatorError::InvalidReceipt),
+        }
+      },
+      Err(status) => {
+        eprintln!(
+          "Failed to read the state of endorser {} ({:?})",
+          uri, status
+        );
+        return Err(CoordinatorError::FailedToReadLatestState);
+      },
+    };
+    self
+      .record_admin_event(AdminAction::DecommissionEndorser {
+        uri: uri.to_string(),
+        final_state: base64_url::encode(&final_state.to_bytes()),
+      })
+      .await?;
+
+    self.replace_endorsers(replacements).await?;
+
+    // confirm that the new view can serve requests before reporting success
+    let endorsers = self.get_endorser_hostnames();
+    let mut active_endorsers = 0;
+    for (pk, uri) in &endorsers {
+      let mut endorser_client = match self.get_endorser_client(pk) {
+        Some((client, _uri)) => client,
+        None => continue,
+      };
+      match read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await {
+        Ok(resp) if resp.get_ref().mode == endorser_proto::EndorserMode::Active as i32 => {
+          active_endorsers += 1;
+        },
+        res => eprintln!(
+          "Endorser {} is not active after the view change ({:?})",
+          uri, res
+        ),
+      }
+    }
+    if active_endorsers * 2 <= endorsers.len() {
+      return Err(CoordinatorError::FailedToObtainQuorum);
+    }
+
+    let view_height = self.get_view_height()?;
+    Ok(DecommissionReport {
+      pk: pk.to_vec(),
+      final_state,
+      view_height,
+      active_endorsers,
+      num_endorsers: endorsers.len(),
+    })
+  }
+
+  // A view change that started runs to its end, which unlocks the endorsers, before the
+  // coordinator exits; one that did not start waits until the coordinator exits
+  pub(crate) async fn enter_view_change(&self) -> tokio::sync::RwLockReadGuard<'_, ()> {
+    self.in_flight.read().await
+  }
+
+  // Returns the identity of the group of endorsers, which is the hash of the first block of the
+  // view ledger
+  pub(crate) fn get_group_identity(
+    &self,
+    view_ledger_block: &Block,
+    view_ledger_height: usize,
+  ) -> Result<NimbleDigest, CoordinatorError> {
+    if view_ledger_height == 1 {
+      Ok(view_ledger_block.hash())
+    } else if let Ok(vs) = self.verifier_state.read() {
+      Ok(*vs.get_group_identity())
+    } else {
+      Err(CoordinatorError::FailedToAcquireReadLock)
+    }
+  }
+
+  // Completes a view change whose block is in the view ledger at `view_ledger_height`: the
+  // existing endorsers are finalized, the new ones initialized and activated, and the receipts
+  // of both attached to the block. A view change that `prepared` holds the existing endorsers
+  // locked already, and initialized the new endorsers that are not among them; otherwise every
+  // step runs here, as when a view change is re-applied after a restart. The view is not
+  // activated unless the receipts of a quorum of the new endorsers verify, and a view change
+  // left incomplete is re-applied when the coordinator restarts.
+  pub(crate) async fn apply_view_change(
+    &self,
+    existing_endorsers: &EndorserHostnames,
+    new_endorsers: &EndorserHostnames,
+    view_ledger_entry: &LedgerEntry,
+    view_ledger_genesis_block: &Block,
+    view_ledger_height: usize,
+    prepared: Option<PreparedViewChange>,
+  ) -> Result<(), CoordinatorError> {
+    let view_tail_metablock = view_tail_metablock(view_ledger_entry, view_ledger_height)?;
+
+    let (finalize_receipts, ledger_tail_maps) = if existing_endorsers.is_empty() {
+      assert!(view_ledger_height == 1);
+
+      (Receipts::new(), Vec::new())
+    } else {
+      // the endorsers are locked first, so that no append lands on some of them after others
+      // are finalized, and unlocked afterwards in case some of them failed to finalize. A
+      // prepared view change finalizes only the endorsers that acknowledged its lock, whose
+      // ledger tails cannot have moved since it read them.
+      let finalized_endorsers = match &prepared {
+        Some(prepared) => prepared.get_locked().clone(),
+        None => {
+          self.endorser_set_locked(existing_endorsers, true).await;
+          existing_endorsers.clone()
+        },
+      };
+      let res = self
+        .endorser_finalize_state(
+          &finalized_endorsers,
+          &view_tail_metablock.hash(),
           &view_ledger_genesis_block.hash(),
           view_ledger_height,
         )
-        .await
+        .await;
+      self.endorser_set_locked(existing_endorsers, false).await;
+      res
     };
 
     // Compute the max cut
@@ -1602,17 +3539,57 @@ impl CoordinatorState {
       return Err(CoordinatorError::FailedToAcquireReadLock);
     };
 
-    // Initialize new endorsers
-    let initialize_receipts = self
+    // Initialize new endorsers; those that a prepared view change initialized already were
+    // handed the cut of its prepare phase, so the new view cannot start with another one
+    let (uninitialized_endorsers, mut initialize_receipts) = match prepared {
+      Some(prepared) => {
+        if *prepared.get_max_cut() != max_cut {
+          eprintln!(
+            "The ledger tails of the endorsers moved after they were locked for view {}",
+            view_ledger_height
+          );
+          return Err(CoordinatorError::FailedToActivate);
+        }
+        (
+          carried_over_endorsers(existing_endorsers, new_endorsers),
+          prepared.into_initialize_receipts(),
+        )
+      },
+      None => (new_endorsers.clone(), Receipts::new()),
+    };
+    let res = self
       .endorser_initialize_state(
         &group_identity,
-        new_endorsers,
-        max_cut,
+        &uninitialized_endorsers,
+        max_cut.clone(),
         &view_tail_metablock,
         &view_ledger_genesis_block.hash(),
         view_ledger_height,
       )
       .await;
+    initialize_receipts.merge_receipts(&res);
+
+    // only the new endorsers whose receipts for the view verify count toward its quorum
+    let new_view_metablock = MetaBlock::new(
+      &view_tail_metablock.hash(),
+      &view_ledger_genesis_block.hash(),
+      view_ledger_height,
+    );
+    let num_initialized = num_initialized_signers(
+      &initialize_receipts,
+      new_endorsers,
+      &group_identity,
+      &max_cut,
+      &new_view_metablock,
+    );
+    if !has_quorum(num_initialized, new_endorsers.len()) {
+      eprintln!(
+        "insufficient initialized endorsers {} * 2 <= {}",
+        num_initialized,
+        new_endorsers.len()
+      );
+      return Err(CoordinatorError::FailedToActivate);
+    }
 
     // Store the receipts in the view ledger
     let mut receipts = Receipts::new();
@@ -1622,10 +3599,10 @@ impl CoordinatorState {
       .ledger_store
       .attach_view_ledger_receipts(view_ledger_height, &receipts)
       .await;
-    if res.is_err() {
+    if let Err(error) = res {
       eprintln!(
         "Failed to attach view ledger receipt in the ledger store ({:?})",
-        res.unwrap_err()
+        error
       );
       return Err(CoordinatorError::FailedToCallLedgerStore);
     }
@@ -1637,14 +3614,10 @@ impl CoordinatorState {
       if cut_diff.low == cut_diff.high {
         continue;
       }
-      let mut block_hashes: Vec<Vec<u8>> =
-        Vec::with_capacity((cut_diff.high - cut_diff.low) as usize);
+      let mut block_hashes: Vec<Vec<u8>> = Vec::with_capacity(cut_diff.high - cut_diff.low);
       let h = NimbleDigest::from_bytes(&cut_diff.handle).unwrap();
       for index in (cut_diff.low + 1)..=cut_diff.high {
-        let res = self
-          .ledger_store
-          .read_ledger_by_index(&h, index as usize)
-          .await;
+        let res = self.ledger_store.read_ledger_by_index(&h, index).await;
         if let Err(e) = res {
           eprintln!("Failed to read the ledger store {:?}", e);
           return Err(CoordinatorError::FailedToCallLedgerStore);
@@ -1694,65 +3667,484 @@ impl CoordinatorState {
     } else {
       return Err(CoordinatorError::FailedToAcquireWriteLock);
     }
-
-    // Disconnect existing endorsers
-    self.disconnect_endorsers(existing_endorsers).await;
+    self.set_view_lease_expiry(view_ledger_genesis_block)?;
+
+    // Disconnect the existing endorsers that are not in the new view
+    let retired_endorsers = existing_endorsers
+      .iter()
+      .filter(|(pk, _uri)| !new_endorsers.iter().any(|(new_pk, _uri)| new_pk == pk))
+      .cloned()
+      .collect::<EndorserHostnames>();
+    self.disconnect_endorsers(&retired_endorsers).await;
+
+    // Notify watchers; failing to send only means that nobody is watching
+    let _ = self.view_changes.send(ViewChangeNotification {
+      block: view_ledger_genesis_block.clone(),
+      receipts,
+      height: view_ledger_height,
+      attestations: ATTESTATION_STR.as_bytes().to_vec(),
+    });
 
     Ok(())
   }
 
-  pub async fn reset_ledger_store(&self) {
-    let res = self.ledger_store.reset_store().await;
-    assert!(res.is_ok());
+  /// Appends `action` to the admin ledger, creating the ledger on first use. Admin events are
+  /// recorded even in maintenance mode, since entering and leaving it are admin actions too.
+  pub async fn record_admin_event(&self, action: AdminAction) -> Result<(), CoordinatorError> {
+    let event = AdminEvent {
+      action,
+      timestamp: current_timestamp(),
+    };
+    let res = serde_json::to_vec(&event);
+    if res.is_err() {
+      eprintln!("Failed to serialize the admin event {:?}", res);
+      return Err(CoordinatorError::FailedToSerde);
+    }
+    let block_bytes = res.unwrap();
+
+    let res = self
+      .append_internal_ledger(&self.admin_ledger_lock, ADMIN_LEDGER_HANDLE, &block_bytes)
+      .await;
+    if let Err(error) = &res {
+      eprintln!("Failed to record the admin event {:?} ({:?})", event, error);
+    }
+    res.map(|_index| ())
   }
 
-  pub async fn create_ledger(
+  // Appends `block_bytes` to the coordinator's own ledger `handle_bytes`, creating the ledger with
+  // it if it does not exist yet, and returns the index of the new entry
+  async fn append_internal_ledger(
     &self,
-    endorsers_opt: Option<Vec<Vec<u8>>>,
+    lock: &tokio::sync::Mutex<()>,
     handle_bytes: &[u8],
     block_bytes: &[u8],
-  ) -> Result<Receipts, CoordinatorError> {
+  ) -> Result<usize, CoordinatorError> {
+    let _guard = lock.lock().await;
     let handle = NimbleDigest::digest(handle_bytes);
-    let genesis_block = Block::new(block_bytes);
-
-    let hash_block = genesis_block.hash();
-    let hash_nonces = Nonces::new().hash();
-    let block_hash = compute_aggregated_block_hash(&hash_block.to_bytes(), &hash_nonces.to_bytes());
+    match self.ledger_store.read_ledger_tail(&handle).await {
+      Ok((_entry, height)) => self
+        .append_ledger_internal(None, handle_bytes, block_bytes, height + 1)
+        .await
+        .map(|_r| height + 1),
+      Err(_e) => self
+        .create_ledger_internal(None, handle_bytes, block_bytes)
+        .await
+        .map(|_r| 0),
+    }
+  }
 
-    let res = self
-      .ledger_store
-      .create_ledger(&handle, genesis_block.clone())
-      .await;
-    if res.is_err() {
-      eprintln!(
-        "Failed to create ledger in the ledger store ({:?})",
-        res.unwrap_err()
-      );
-      return Err(CoordinatorError::FailedToCreateLedger);
+  /// Queues evidence that an endorser equivocated, which a client or an auditor found, for
+  /// `handle_misbehavior`. The evidence must verify under the identity of this service.
+  pub fn report_misbehavior(&self, evidence: MisbehaviorEvidence) -> Result<(), CoordinatorError> {
+    self.verify_misbehavior(&evidence)?;
+    if let Ok(mut detector) = self.misbehavior.write() {
+      detector.add_evidence(evidence);
+      Ok(())
+    } else {
+      Err(CoordinatorError::FailedToAcquireWriteLock)
     }
+  }
 
-    // Make a request to the endorsers for NewLedger using the handle which returns a signature.
-    let receipts = {
-      let endorsers = match endorsers_opt {
-        Some(ref endorsers) => endorsers.clone(),
-        None => self.get_endorser_pks(),
-      };
-      let res = self
-        .endorser_create_ledger(&endorsers, &handle, &block_hash, genesis_block)
-        .await;
-      if res.is_err() {
-        eprintln!("Failed to create ledger in endorsers ({:?})", res);
-        return Err(res.unwrap_err());
+  fn verify_misbehavior(&self, evidence: &MisbehaviorEvidence) -> Result<(), CoordinatorError> {
+    if let Ok(vs) = self.verifier_state.read() {
+      if evidence.verify(vs.get_group_identity()).is_err() {
+        return Err(CoordinatorError::InvalidMisbehaviorEvidence);
       }
-      res.unwrap()
+      Ok(())
+    } else {
+      Err(CoordinatorError::FailedToAcquireReadLock)
+    }
+  }
+
+  /// Acts on the evidence of equivocation found since the last call: each piece of evidence is
+  /// appended to the misbehavior ledger, and its endorser is expelled with a view change that
+  /// keeps the other endorsers. Returns the uris of the expelled endorsers.
+  pub async fn handle_misbehavior(&self) -> Result<Vec<String>, CoordinatorError> {
+    let evidence = if let Ok(mut detector) = self.misbehavior.write() {
+      detector.take_evidence()
+    } else {
+      return Err(CoordinatorError::FailedToAcquireWriteLock);
     };
 
-    // Store the receipt
-    let res = self
-      .ledger_store
-      .attach_ledger_receipts(&handle, 0, &receipts)
-      .await;
-    if res.is_err() {
+    let mut expelled = Vec::new();
+    for evidence in evidence {
+      if let Err(error) = self.verify_misbehavior(&evidence) {
+        eprintln!("Dropping evidence that does not verify ({:?})", error);
+        continue;
+      }
+      let existing_endorsers = self.get_endorser_hostnames();
+      let uri = match existing_endorsers
+        .iter()
+        .find(|(pk, _uri)| pk == evidence.get_endorser())
+      {
+        Some((_pk, uri)) => uri.clone(),
+        None => continue, // the endorser already left the view
+      };
+
+      let evidence_index = self
+        .append_internal_ledger(
+          &self.misbehavior_ledger_lock,
+          MISBEHAVIOR_LEDGER_HANDLE,
+          &evidence.to_bytes(),
+        )
+        .await?;
+
+      let new_endorsers = existing_endorsers
+        .iter()
+        .filter(|(pk, _uri)| pk != evidence.get_endorser())
+        .cloned()
+        .collect::<EndorserHostnames>();
+      let view_ledger_block = self.encode_view_block(&new_endorsers, &[])?;
+      let view_ledger_height = self
+        .reconfigure(&existing_endorsers, &new_endorsers, &view_ledger_block)
+        .await?;
+
+      self
+        .record_admin_event(AdminAction::ExpelEndorser {
+          uri: uri.clone(),
+          evidence_index,
+          view_height: view_ledger_height,
+        })
+        .await?;
+      expelled.push(uri);
+    }
+    Ok(expelled)
+  }
+
+  /// Reads the entry at `index` of the misbehavior ledger
+  pub async fn read_misbehavior_evidence(
+    &self,
+    index: usize,
+  ) -> Result<MisbehaviorEvidence, CoordinatorError> {
+    let entry = self
+      .read_ledger_by_index(MISBEHAVIOR_LEDGER_HANDLE, index)
+      .await?;
+    match MisbehaviorEvidence::from_bytes(&entry.get_block().to_bytes()) {
+      Ok(evidence) => Ok(evidence),
+      Err(_e) => Err(CoordinatorError::InvalidMisbehaviorEvidence),
+    }
+  }
+
+  /// Reads the entry at `index` of the admin ledger
+  pub async fn read_admin_event(&self, index: usize) -> Result<LedgerEntry, CoordinatorError> {
+    self.read_ledger_by_index(ADMIN_LEDGER_HANDLE, index).await
+  }
+
+  /// Takes a snapshot of the ledger `handle_bytes` by reading its tail for a fresh nonce, and
+  /// appends the snapshot to the checkpoint ledger. Returns the id of the snapshot along with it.
+  pub async fn checkpoint_ledger(
+    &self,
+    handle_bytes: &[u8],
+  ) -> Result<(usize, LedgerSnapshot), CoordinatorError> {
+    Self::check_client_handle(handle_bytes)?;
+    self.check_accepts_writes()?;
+
+    let nonce = random::<[u8; 16]>().to_vec();
+    let tail_entry = self.read_ledger_tail(handle_bytes, &nonce).await?;
+    let res = tail_entry.get_receipts().get_metablock();
+    if res.is_err() {
+      eprintln!("The receipts of the tail are not for a single metablock");
+      return Err(CoordinatorError::InvalidReceipt);
+    }
+    let snapshot = LedgerSnapshot::new(
+      &NimbleDigest::digest(handle_bytes),
+      res.unwrap().get_height(),
+      tail_entry.get_block().to_bytes(),
+      tail_entry.get_nonces().to_bytes(),
+      nonce,
+      tail_entry.get_receipts().to_bytes(),
+    );
+
+    let _guard = self.checkpoint_ledger_lock.lock().await;
+    let handle = NimbleDigest::digest(CHECKPOINT_LEDGER_HANDLE);
+    let height = match self.ledger_store.read_ledger_tail(&handle).await {
+      Ok((_entry, height)) => height,
+      Err(_e) => {
+        self
+          .create_ledger_internal(None, CHECKPOINT_LEDGER_HANDLE, CHECKPOINT_LEDGER_GENESIS)
+          .await?;
+        0
+      },
+    };
+    self
+      .append_ledger_internal(
+        None,
+        CHECKPOINT_LEDGER_HANDLE,
+        &snapshot.to_bytes(),
+        height + 1,
+      )
+      .await?;
+    Ok((height + 1, snapshot))
+  }
+
+  /// Reads the snapshot with id `id` from the checkpoint ledger
+  pub async fn read_snapshot(&self, id: usize) -> Result<LedgerSnapshot, CoordinatorError> {
+    if id == 0 {
+      return Err(CoordinatorError::InvalidHeight);
+    }
+    let entry = self
+      .read_ledger_by_index(CHECKPOINT_LEDGER_HANDLE, id)
+      .await?;
+    let res = LedgerSnapshot::from_bytes(&entry.get_block().to_bytes());
+    if res.is_err() {
+      eprintln!("Failed to decode the snapshot {}", id);
+      return Err(CoordinatorError::FailedToSerde);
+    }
+    Ok(res.unwrap())
+  }
+
+  /// Returns a receiver of the view changes committed from now on
+  pub fn subscribe_view_changes(&self) -> broadcast::Receiver<ViewChangeNotification> {
+    self.view_changes.subscribe()
+  }
+
+  /// Returns a receiver of the entries appended to any ledger from now on
+  pub fn subscribe_ledger_appends(&self) -> broadcast::Receiver<LedgerAppendNotification> {
+    self.ledger_appends.subscribe()
+  }
+
+  // Notifies subscribers of an appended entry; failing to send only means that nobody subscribed
+  fn notify_append(
+    &self,
+    handle: &Handle,
+    index: usize,
+    block: Block,
+    nonces: Nonces,
+    receipts: &Receipts,
+  ) {
+    if let Ok(metablock) = receipts.get_metablock() {
+      let _ = self.ledger_appends.send(LedgerAppendNotification {
+        handle: *handle,
+        index,
+        block,
+        nonces,
+        receipts: receipts.clone(),
+        metablock,
+      });
+    }
+  }
+
+  pub async fn reset_ledger_store(&self) {
+    let res = self.ledger_store.reset_store().await;
+    assert!(res.is_ok());
+    self.tail_cache.clear();
+  }
+
+  /// Waits, for at most `timeout`, for the work that outlives the requests that started it: the
+  /// receipts of endorsers that answered after a quorum, which are still to be attached to the
+  /// store, and view changes, which lock the endorsers while they run. It is called once the
+  /// servers stopped accepting requests and their requests finished, before the coordinator
+  /// exits. While the returned guard lives, no view change starts; None means that the work did
+  /// not finish in time.
+  pub async fn drain(&self, timeout: Duration) -> Option<tokio::sync::OwnedRwLockWriteGuard<()>> {
+    tokio::time::timeout(timeout, self.in_flight.clone().write_owned())
+      .await
+      .ok()
+  }
+
+  /// Writes the view ledger and every ledger in the store to `out` as an archive, which
+  /// `nimble-store --import` loads into another store. This is the way out of the memory store,
+  /// which lives only as long as the coordinator.
+  pub async fn export_ledgers(
+    &self,
+    out: &mut dyn std::io::Write,
+  ) -> Result<ArchiveReport, String> {
+    export_store(self.ledger_store.as_ref().as_ref(), out).await
+  }
+
+  /// Puts the coordinator into maintenance mode: new ledgers and appends are rejected while
+  /// reads continue. The mode is left automatically once `duration` elapses, so a crashed
+  /// operator tool cannot leave the service read-only forever.
+  pub fn enter_maintenance(&self, duration: Duration) -> Result<(), CoordinatorError> {
+    if let Ok(mut deadline) = self.maintenance_deadline.write() {
+      *deadline = Some(Instant::now() + duration);
+      Ok(())
+    } else {
+      Err(CoordinatorError::FailedToAcquireWriteLock)
+    }
+  }
+
+  pub fn exit_maintenance(&self) -> Result<(), CoordinatorError> {
+    if let Ok(mut deadline) = self.maintenance_deadline.write() {
+      *deadline = None;
+      Ok(())
+    } else {
+      Err(CoordinatorError::FailedToAcquireWriteLock)
+    }
+  }
+
+  /// Returns the time left in maintenance mode, or None if the coordinator accepts writes
+  pub fn get_maintenance_remaining(&self) -> Option<Duration> {
+    if let Ok(deadline) = self.maintenance_deadline.read() {
+      deadline.and_then(|d| d.checked_duration_since(Instant::now()))
+    } else {
+      None
+    }
+  }
+
+  fn check_accepts_writes(&self) -> Result<(), CoordinatorError> {
+    if self.get_maintenance_remaining().is_some() {
+      Err(CoordinatorError::InMaintenanceMode)
+    } else {
+      self.check_endorser_lease()
+    }
+  }
+
+  fn check_client_handle(handle_bytes: &[u8]) -> Result<(), CoordinatorError> {
+    if handle_bytes == ADMIN_LEDGER_HANDLE
+      || handle_bytes == CHECKPOINT_LEDGER_HANDLE
+      || handle_bytes == MISBEHAVIOR_LEDGER_HANDLE
+    {
+      Err(CoordinatorError::InvalidHandle)
+    } else {
+      Ok(())
+    }
+  }
+
+  pub async fn create_ledger(
+    &self,
+    endorsers_opt: Option<Vec<Vec<u8>>>,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+  ) -> Result<Receipts, CoordinatorError> {
+    self.check_accepts_writes()?;
+    Self::check_client_handle(handle_bytes)?;
+    self
+      .create_ledger_internal(endorsers_opt, handle_bytes, block_bytes)
+      .await
+  }
+
+  /// Creates a ledger like `create_ledger` and binds `label` to it, so that clients can find the
+  /// ledger by the label with `get_ledger_by_label`. The label is taken before the ledger is
+  /// created, and given up again if the creation fails; a retry of a creation that bound the
+  /// label keeps it.
+  pub async fn create_labeled_ledger(
+    &self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    label: &str,
+  ) -> Result<Receipts, CoordinatorError> {
+    self.check_accepts_writes()?;
+    Self::check_client_handle(handle_bytes)?;
+    if label.is_empty() || label.len() > MAX_LEDGER_LABEL_SIZE {
+      return Err(CoordinatorError::InvalidLabel);
+    }
+
+    let res = self
+      .ledger_store
+      .add_ledger_label(label, handle_bytes)
+      .await;
+    let bound = match res {
+      Ok(bound) => bound,
+      Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey)) => {
+        return Err(CoordinatorError::LabelAlreadyTaken);
+      },
+      Err(LedgerStoreError::LedgerError(StorageError::LabelsNotSupported)) => {
+        return Err(CoordinatorError::LabelsNotSupported);
+      },
+      Err(error) => {
+        eprintln!("Failed to bind the label {} ({:?})", label, error);
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    };
+
+    let res = self
+      .create_ledger_internal(None, handle_bytes, block_bytes)
+      .await;
+    if res.is_err() && bound {
+      let released = self
+        .ledger_store
+        .remove_ledger_label(label, handle_bytes)
+        .await;
+      if let Err(error) = released {
+        eprintln!("Failed to release the label {} ({:?})", label, error);
+      }
+    }
+    res
+  }
+
+  /// Returns the handle of the ledger that `label` is bound to
+  pub async fn get_ledger_by_label(&self, label: &str) -> Result<Vec<u8>, CoordinatorError> {
+    let res = self.ledger_store.find_ledger_by_label(label).await;
+    match res {
+      Ok(handle_bytes) => Ok(handle_bytes),
+      Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)) => {
+        Err(CoordinatorError::UnknownLabel)
+      },
+      Err(LedgerStoreError::LedgerError(StorageError::LabelsNotSupported)) => {
+        Err(CoordinatorError::LabelsNotSupported)
+      },
+      Err(error) => {
+        eprintln!("Failed to look up the label {} ({:?})", label, error);
+        Err(CoordinatorError::FailedToCallLedgerStore)
+      },
+    }
+  }
+
+  async fn create_ledger_internal(
+    &self,
+    endorsers_opt: Option<Vec<Vec<u8>>>,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+  ) -> Result<Receipts, CoordinatorError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let genesis_block = Block::new(block_bytes);
+    let (policy, app_bytes) = match EndorsementPolicy::from_genesis_bytes(block_bytes) {
+      Ok((policy, app_bytes)) => (policy, app_bytes),
+      Err(_) => return Err(CoordinatorError::InvalidEndorsementPolicy),
+    };
+    let access_policy = match AccessPolicy::from_genesis_bytes(app_bytes) {
+      Ok((access_policy, _app_bytes)) => access_policy,
+      Err(_) => return Err(CoordinatorError::InvalidAccessPolicy),
+    };
+
+    let hash_block = genesis_block.hash();
+    let hash_nonces = Nonces::new().hash();
+    let block_hash = compute_aggregated_block_hash(&hash_block.to_bytes(), &hash_nonces.to_bytes());
+
+    // a ledger in a namespace is created in the namespace's partition of the store
+    let res = match namespace_of(handle_bytes) {
+      Some(namespace) => {
+        self
+          .ledger_store
+          .create_ledger_in_namespace(namespace, &handle, genesis_block.clone())
+          .await
+      },
+      None => {
+        self
+          .ledger_store
+          .create_ledger(&handle, genesis_block.clone())
+          .await
+      },
+    };
+    if let Err(error) = res {
+      eprintln!("Failed to create ledger in the ledger store ({:?})", error);
+      return Err(ledger_store_error(&error));
+    }
+
+    // Make a request to the endorsers for NewLedger using the handle which returns a signature.
+    let receipts = {
+      let endorsers = match endorsers_opt {
+        Some(ref endorsers) => endorsers.clone(),
+        None => self.get_ledger_endorser_pks(&handle),
+      };
+      let res = self
+        .endorser_create_ledger(&endorsers, &handle, &block_hash, genesis_block, &policy)
+        .await;
+      if let Err(error) = res {
+        eprintln!("Failed to create ledger in endorsers ({:?})", error);
+        return Err(error);
+      }
+      res.unwrap()
+    };
+
+    // Store the receipt
+    let res = self
+      .ledger_store
+      .attach_ledger_receipts(&handle, 0, &receipts)
+      .await;
+    self.tail_cache.invalidate(&handle);
+    if res.is_err() {
       eprintln!(
         "Failed to attach ledger receipt to the ledger store ({:?})",
         res
@@ -1760,6 +4152,16 @@ impl CoordinatorState {
       return Err(CoordinatorError::FailedToAttachReceipt);
     }
 
+    if let Ok(mut policies) = self.endorsement_policies.write() {
+      policies.insert(handle, policy);
+    }
+    if let Ok(mut policies) = self.access_policies.write() {
+      policies.insert(handle, access_policy);
+    }
+    self
+      .ledger_stats
+      .record_write(&handle, handle_bytes, 0, receipts.get_signer_ids().len());
+
     Ok(receipts)
   }
 
@@ -1770,36 +4172,220 @@ impl CoordinatorState {
     block_bytes: &[u8],
     expected_height: usize,
   ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
-    if expected_height == 0 {
-      return Err(CoordinatorError::InvalidHeight);
+    self.check_accepts_writes()?;
+    Self::check_client_handle(handle_bytes)?;
+    self.check_block(block_bytes)?;
+    self
+      .append_ledger_internal(endorsers_opt, handle_bytes, block_bytes, expected_height)
+      .await
+  }
+
+  /// Appends like `append_ledger`, but at most once per `client_request_id`: a retry of an append
+  /// that completed gets the hash of the nonces and the receipts of the original append back,
+  /// and a retry of one still in progress fails with `AppendInProgress`
+  pub async fn append_ledger_once(
+    &self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    expected_height: usize,
+    client_request_id: &[u8],
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
+    self.check_accepts_writes()?;
+    Self::check_client_handle(handle_bytes)?;
+    self.check_block(block_bytes)?;
+    if client_request_id.is_empty() || client_request_id.len() > MAX_CLIENT_REQUEST_ID_SIZE {
+      return Err(CoordinatorError::InvalidClientRequestId);
     }
+    let retention = match self.append_request_retention.read() {
+      Ok(retention) => retention.as_millis() as u64,
+      Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+    };
 
     let handle = NimbleDigest::digest(handle_bytes);
-    let data_block = Block::new(block_bytes);
-
     let res = self
       .ledger_store
-      .append_ledger(&handle, &data_block, expected_height)
+      .claim_append_request(&handle, client_request_id, APPEND_REQUEST_CLAIM_TIMEOUT)
+      .await;
+    match res {
+      Ok(AppendRequest::Claimed) => {},
+      Ok(AppendRequest::InProgress) => return Err(CoordinatorError::AppendInProgress),
+      Ok(AppendRequest::Completed(index)) => {
+        return self.completed_append(&handle, block_bytes, index).await;
+      },
+      Err(LedgerStoreError::LedgerError(StorageError::RequestIdsNotSupported)) => {
+        return Err(CoordinatorError::ClientRequestIdsNotSupported);
+      },
+      Err(error) => {
+        eprintln!("Failed to claim the client request ID {:?}", error);
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    }
+
+    let res = self
+      .append_ledger_internal(None, handle_bytes, block_bytes, expected_height)
       .await;
+    // a failed append may be retried, so it gives up its claim; the ledger store rejects a
+    // retry of one that failed after storing its block, since the height is taken
+    let recorded = if res.is_ok() {
+      self
+        .ledger_store
+        .complete_append_request(&handle, client_request_id, expected_height, retention)
+        .await
+    } else {
+      self
+        .ledger_store
+        .release_append_request(&handle, client_request_id)
+        .await
+    };
+    if let Err(error) = recorded {
+      eprintln!("Failed to record the client request ID {:?}", error);
+    }
+    res
+  }
+
+  // Returns the hash of the nonces and the receipts of an append that completed at `index`
+  async fn completed_append(
+    &self,
+    handle: &Handle,
+    block_bytes: &[u8],
+    index: usize,
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
+    let res = self.ledger_store.read_ledger_by_index(handle, index).await;
+    if let Err(error) = res {
+      eprintln!("Failed to read the entry of a completed append {:?}", error);
+      return Err(ledger_store_error(&error));
+    }
+    let ledger_entry = res.unwrap();
+    if ledger_entry.get_block().to_bytes() != block_bytes {
+      return Err(CoordinatorError::ClientRequestIdReused);
+    }
+    Ok((
+      ledger_entry.get_nonces().hash(),
+      ledger_entry.get_receipts().clone(),
+    ))
+  }
+
+  /// Stores `payload` in the blob store and appends a block that references it. The block is
+  /// exempt from the deployment's block validation, which applies to the blocks clients write.
+  pub async fn append_ledger_blob(
+    &self,
+    handle_bytes: &[u8],
+    payload: &[u8],
+    expected_height: usize,
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
+    self.check_accepts_writes()?;
+    Self::check_client_handle(handle_bytes)?;
+    let blob_store = self.get_blob_store()?;
+    if blob_store
+      .max_blob_size
+      .is_some_and(|max| payload.len() > max)
+    {
+      return Err(CoordinatorError::BlobTooLarge);
+    }
+
+    let res = blob_store.store.put(payload).await;
+    if let Err(error) = res {
+      eprintln!("Failed to put the blob in the blob store {:?}", error);
+      return Err(CoordinatorError::FailedToCallBlobStore);
+    }
+    let block_bytes = BlobReference::new(payload).to_bytes();
+    self
+      .append_ledger_internal(None, handle_bytes, &block_bytes, expected_height)
+      .await
+  }
+
+  /// Appends the hash of a payload that the client stores elsewhere, as an `ExternalBlock`. The
+  /// deployment's limits on blocks do not apply, since the ledger holds no payload.
+  pub async fn append_ledger_hash_only(
+    &self,
+    handle_bytes: &[u8],
+    block_hash_bytes: &[u8],
+    expected_height: usize,
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
+    self.check_accepts_writes()?;
+    Self::check_client_handle(handle_bytes)?;
+    let block =
+      ExternalBlock::new(block_hash_bytes).map_err(|_e| CoordinatorError::InvalidExternalBlock)?;
+    self
+      .append_ledger_internal(None, handle_bytes, &block.to_bytes(), expected_height)
+      .await
+  }
+
+  /// Reads the payload of the entry at `index`, which must have been appended in chunks
+  pub async fn read_blob(
+    &self,
+    handle_bytes: &[u8],
+    index: usize,
+  ) -> Result<Vec<u8>, CoordinatorError> {
+    let blob_store = self.get_blob_store()?;
+    let ledger_entry = self.read_ledger_by_index(handle_bytes, index).await?;
+    let res = BlobReference::from_bytes(&ledger_entry.get_block().to_bytes());
     if res.is_err() {
-      eprintln!(
-        "Failed to append to the ledger in the ledger store {:?}",
-        res.unwrap_err()
-      );
-      return Err(CoordinatorError::FailedToAppendLedger);
+      return Err(CoordinatorError::InvalidBlobReference);
     }
+    let reference = res.unwrap();
 
-    let (actual_height, nonces) = res.unwrap();
-    assert!(actual_height == expected_height);
+    let payload = match blob_store.store.get(reference.get_hash()).await {
+      Ok(payload) => payload,
+      Err(StorageError::KeyDoesNotExist) => return Err(CoordinatorError::BlobNotFound),
+      Err(error) => {
+        eprintln!("Failed to get the blob from the blob store {:?}", error);
+        return Err(CoordinatorError::FailedToCallBlobStore);
+      },
+    };
+    if !reference.matches(&payload) {
+      eprintln!("The blob store holds a corrupt blob {:?}", reference);
+      return Err(CoordinatorError::BlobNotFound);
+    }
+    Ok(payload)
+  }
+
+  async fn append_ledger_internal(
+    &self,
+    endorsers_opt: Option<Vec<Vec<u8>>>,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    expected_height: usize,
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
+    if expected_height == 0 {
+      return Err(CoordinatorError::InvalidHeight);
+    }
+
+    let handle = NimbleDigest::digest(handle_bytes);
+    let data_block = Block::new(block_bytes);
+    let policy = self.get_endorsement_policy(&handle).await?;
+    let pipeline_depth = match self.append_pipeline_depth.read() {
+      Ok(depth) => *depth,
+      Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+    };
+    if endorsers_opt.is_none() && pipeline_depth > 0 {
+      return self
+        .append_ledger_pipelined(
+          handle_bytes,
+          &handle,
+          data_block,
+          expected_height,
+          pipeline_depth,
+        )
+        .await;
+    }
+
+    let nonces = self
+      .store_ledger_append(&handle, &data_block, expected_height)
+      .await?;
+    let actual_height = expected_height;
 
     let hash_block = data_block.hash();
     let hash_nonces = nonces.hash();
     let block_hash = compute_aggregated_block_hash(&hash_block.to_bytes(), &hash_nonces.to_bytes());
+    // the endorsers take the entry, so keep a copy for subscribers if there are any
+    let appended =
+      (self.ledger_appends.receiver_count() > 0).then(|| (data_block.clone(), nonces.clone()));
 
     let receipts = {
       let endorsers = match endorsers_opt {
         Some(endorsers) => endorsers,
-        None => self.get_endorser_pks(),
+        None => self.get_ledger_endorser_pks(&handle),
       };
       let res = self
         .endorser_append_ledger(
@@ -1809,11 +4395,12 @@ impl CoordinatorState {
           actual_height,
           data_block,
           nonces,
+          &policy,
         )
         .await;
-      if res.is_err() {
-        eprintln!("Failed to append to the ledger in endorsers {:?}", res);
-        return Err(res.unwrap_err());
+      if let Err(error) = res {
+        eprintln!("Failed to append to the ledger in endorsers {:?}", error);
+        return Err(error);
       }
       res.unwrap()
     };
@@ -1822,23 +4409,365 @@ impl CoordinatorState {
       .ledger_store
       .attach_ledger_receipts(&handle, expected_height, &receipts)
       .await;
-    if res.is_err() {
+    self.tail_cache.invalidate(&handle);
+    if let Err(error) = res {
       eprintln!(
         "Failed to attach ledger receipt to the ledger store ({:?})",
-        res.unwrap_err()
+        error
       );
       return Err(CoordinatorError::FailedToAttachReceipt);
     }
+    self.ledger_stats.record_write(
+      &handle,
+      handle_bytes,
+      actual_height,
+      receipts.get_signer_ids().len(),
+    );
+    if let Some((block, nonces)) = appended {
+      self.notify_append(&handle, actual_height, block, nonces, &receipts);
+    }
 
     Ok((hash_nonces, receipts))
   }
 
+  // Stores `data_block` at `expected_height` of a ledger and returns the nonces that the store
+  // collected for the height
+  async fn store_ledger_append(
+    &self,
+    handle: &Handle,
+    data_block: &Block,
+    expected_height: usize,
+  ) -> Result<Nonces, CoordinatorError> {
+    let res = self
+      .ledger_store
+      .append_ledger(handle, data_block, expected_height)
+      .await;
+    if let Err(error) = res {
+      eprintln!(
+        "Failed to append to the ledger in the ledger store {:?}",
+        error
+      );
+      return Err(ledger_store_error(&error));
+    }
+
+    // the tail moves on, so the cached one would not answer reads anymore
+    self.tail_cache.invalidate(handle);
+    let (actual_height, nonces) = res.unwrap();
+    if actual_height != expected_height {
+      eprintln!(
+        "The ledger store appended at height {} instead of {}",
+        actual_height, expected_height
+      );
+      return Err(CoordinatorError::HeightMismatch);
+    }
+    Ok(nonces)
+  }
+
+  // Appends through the ledger's pipeline: the block is stored, and the append waits for a round
+  // that endorses it together with the appends that queued while the previous round was in
+  // flight. Whichever waiting append finds no round in flight leads the next one.
+  async fn append_ledger_pipelined(
+    &self,
+    handle_bytes: &[u8],
+    handle: &Handle,
+    data_block: Block,
+    expected_height: usize,
+    pipeline_depth: usize,
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
+    let pipeline = self.append_pipelines.get(handle);
+    let (hash_nonces, mut done) = {
+      let _order = pipeline.lock_order().await;
+      let nonces = self
+        .store_ledger_append(handle, &data_block, expected_height)
+        .await?;
+      let hash_nonces = nonces.hash();
+      let block_hash =
+        compute_aggregated_block_hash(&data_block.hash().to_bytes(), &hash_nonces.to_bytes());
+      let done = pipeline.enqueue(expected_height, block_hash, data_block, nonces);
+      (hash_nonces, done)
+    };
+
+    loop {
+      let mut rounds = pipeline.watch_rounds();
+      match done.try_recv() {
+        Ok(res) => return res.map(|receipts| (hash_nonces, receipts)),
+        Err(TryRecvError::Closed) => return Err(CoordinatorError::FailedToAppendLedger),
+        Err(TryRecvError::Empty) => {},
+      }
+      match pipeline.start_round(pipeline_depth) {
+        Some(round) => self.endorse_round(handle_bytes, handle, round).await,
+        None => {
+          tokio::select! {
+            res = &mut done => {
+              return match res {
+                Ok(res) => res.map(|receipts| (hash_nonces, receipts)),
+                Err(_) => Err(CoordinatorError::FailedToAppendLedger),
+              };
+            },
+            _ = rounds.changed() => {},
+          }
+        },
+      }
+    }
+  }
+
+  // Endorses the appends of a round with one batch and attaches their receipts
+  async fn endorse_round(&self, handle_bytes: &[u8], handle: &Handle, round: Round) {
+    let appends = round.appends();
+    let first_height = appends[0].height;
+    let entries = appends
+      .iter()
+      .map(|append| {
+        (
+          append.block_hash,
+          append.block.clone(),
+          append.nonces.clone(),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    let res = match self.get_endorsement_policy(handle).await {
+      Ok(policy) => {
+        let endorsers = self.get_ledger_endorser_pks(handle);
+        self
+          .endorser_append_ledger_batch(&endorsers, handle, &entries, first_height, &policy)
+          .await
+      },
+      Err(error) => Err(error),
+    };
+    let res = match res {
+      Ok(receipts) => self
+        .commit_batch_receipts(handle_bytes, handle, &entries, first_height, &receipts)
+        .await
+        .map(|_metablocks| receipts),
+      Err(error) => {
+        eprintln!("Failed to append to the ledger in endorsers {:?}", error);
+        Err(error)
+      },
+    };
+    round.finish(res);
+  }
+
+  /// Appends `blocks` to a ledger at consecutive heights from `expected_height` with a single
+  /// round of endorsements, in which each endorser signs all the blocks under one call. Returns
+  /// the hash of the nonces and the metablock of each block, and the receipts for the last block.
+  /// If the ledger store rejects a block, the blocks before it stay in the ledger and are endorsed
+  /// with the next append.
+  pub async fn append_ledger_batch(
+    &self,
+    handle_bytes: &[u8],
+    blocks: &[Vec<u8>],
+    expected_height: usize,
+  ) -> Result<(Vec<(NimbleDigest, MetaBlock)>, Receipts), CoordinatorError> {
+    self.check_accepts_writes()?;
+    Self::check_client_handle(handle_bytes)?;
+    if blocks.is_empty() {
+      return Err(CoordinatorError::EmptyBatch);
+    }
+    for block_bytes in blocks {
+      self.check_block(block_bytes)?;
+    }
+    if expected_height == 0 {
+      return Err(CoordinatorError::InvalidHeight);
+    }
+    let tail_height = match expected_height.checked_add(blocks.len() - 1) {
+      Some(height) => height,
+      None => return Err(CoordinatorError::InvalidHeight),
+    };
+
+    let handle = NimbleDigest::digest(handle_bytes);
+    let policy = self.get_endorsement_policy(&handle).await?;
+
+    let mut hashes = Vec::with_capacity(blocks.len());
+    let mut entries = Vec::with_capacity(blocks.len());
+    for (height, block_bytes) in (expected_height..=tail_height).zip(blocks) {
+      let data_block = Block::new(block_bytes);
+      let res = self
+        .ledger_store
+        .append_ledger(&handle, &data_block, height)
+        .await;
+      if let Err(error) = res {
+        eprintln!(
+          "Failed to append to the ledger in the ledger store {:?}",
+          error
+        );
+        return Err(ledger_store_error(&error));
+      }
+      let (actual_height, nonces) = res.unwrap();
+      if actual_height != height {
+        return Err(CoordinatorError::HeightMismatch);
+      }
+
+      let hash_nonces = nonces.hash();
+      let block_hash =
+        compute_aggregated_block_hash(&data_block.hash().to_bytes(), &hash_nonces.to_bytes());
+      hashes.push(hash_nonces);
+      entries.push((block_hash, data_block, nonces));
+    }
+
+    let endorsers = self.get_ledger_endorser_pks(&handle);
+    let res = self
+      .endorser_append_ledger_batch(&endorsers, &handle, &entries, expected_height, &policy)
+      .await;
+    if let Err(error) = res {
+      eprintln!("Failed to append to the ledger in endorsers {:?}", error);
+      return Err(error);
+    }
+    let mut receipts = res.unwrap();
+
+    let metablocks = self
+      .commit_batch_receipts(handle_bytes, &handle, &entries, expected_height, &receipts)
+      .await?;
+    let result = hashes.into_iter().zip(metablocks).collect();
+    let receipts = receipts.pop().unwrap();
+    Ok((result, receipts))
+  }
+
+  // Attaches the receipts of a batch of appends at consecutive heights from `expected_height` to
+  // the ledger store, checks that they attest to the batch's blocks, and notifies subscribers.
+  // Returns the metablock of each append.
+  async fn commit_batch_receipts(
+    &self,
+    handle_bytes: &[u8],
+    handle: &Handle,
+    entries: &[(NimbleDigest, Block, Nonces)],
+    expected_height: usize,
+    receipts: &[Receipts],
+  ) -> Result<Vec<MetaBlock>, CoordinatorError> {
+    let mut metablocks = Vec::with_capacity(entries.len());
+    for (i, height) in (expected_height..expected_height + entries.len()).enumerate() {
+      let res = self
+        .ledger_store
+        .attach_ledger_receipts(handle, height, &receipts[i])
+        .await;
+      self.tail_cache.invalidate(handle);
+      if let Err(error) = res {
+        eprintln!(
+          "Failed to attach ledger receipt to the ledger store ({:?})",
+          error
+        );
+        return Err(CoordinatorError::FailedToAttachReceipt);
+      }
+
+      let metablock = receipts[i].get_metablock();
+      if metablock.is_err() {
+        return Err(CoordinatorError::EndorsersNotInSync);
+      }
+      let metablock = metablock.unwrap();
+      if *metablock.get_block_hash() != entries[i].0 || metablock.get_height() != height {
+        return Err(CoordinatorError::EndorsersNotInSync);
+      }
+      if self.ledger_appends.receiver_count() > 0 {
+        let (_block_hash, block, nonces) = &entries[i];
+        self.notify_append(handle, height, block.clone(), nonces.clone(), &receipts[i]);
+      }
+      self.ledger_stats.record_write(
+        handle,
+        handle_bytes,
+        height,
+        receipts[i].get_signer_ids().len(),
+      );
+      metablocks.push(metablock);
+    }
+    Ok(metablocks)
+  }
+
+  // Endorsers only enforce the majority quorum, so a stricter policy has to hold before the
+  // coordinator hands out receipts; the majority case keeps the existing best-effort behavior
+  fn check_endorsement_policy(
+    &self,
+    handle: &Handle,
+    receipts: &Receipts,
+    policy: &EndorsementPolicy,
+  ) -> Result<(), CoordinatorError> {
+    if *policy == EndorsementPolicy::Majority {
+      return Ok(());
+    }
+    let vs = self
+      .verifier_state
+      .read()
+      .map_err(|_e| CoordinatorError::FailedToAcquireReadLock)?;
+    match receipts.check_policy(&vs, handle, policy) {
+      Ok(_h) => Ok(()),
+      Err(_) => Err(CoordinatorError::EndorsementPolicyNotSatisfied),
+    }
+  }
+
+  /// Returns the endorsement policy of a ledger, reading its genesis block if not cached
+  pub async fn get_endorsement_policy(
+    &self,
+    handle: &Handle,
+  ) -> Result<EndorsementPolicy, CoordinatorError> {
+    if let Ok(policies) = self.endorsement_policies.read() {
+      if let Some(policy) = policies.get(handle) {
+        return Ok(policy.clone());
+      }
+    }
+
+    let genesis_entry = self.read_ledger_by_index_internal(handle, 0).await?;
+    let policy = match EndorsementPolicy::from_genesis_bytes(&genesis_entry.get_block().to_bytes())
+    {
+      Ok((policy, _app_bytes)) => policy,
+      Err(_) => return Err(CoordinatorError::InvalidEndorsementPolicy),
+    };
+    if let Ok(mut policies) = self.endorsement_policies.write() {
+      policies.insert(*handle, policy.clone());
+    }
+    Ok(policy)
+  }
+
+  pub async fn get_access_policy(&self, handle: &Handle) -> Result<AccessPolicy, CoordinatorError> {
+    if let Ok(policies) = self.access_policies.read() {
+      if let Some(policy) = policies.get(handle) {
+        return Ok(policy.clone());
+      }
+    }
+
+    let genesis_entry = self.read_ledger_by_index_internal(handle, 0).await?;
+    let block_bytes = genesis_entry.get_block().to_bytes();
+    let app_bytes = match EndorsementPolicy::from_genesis_bytes(&block_bytes) {
+      Ok((_policy, app_bytes)) => app_bytes,
+      Err(_) => return Err(CoordinatorError::InvalidEndorsementPolicy),
+    };
+    let policy = match AccessPolicy::from_genesis_bytes(app_bytes) {
+      Ok((policy, _app_bytes)) => policy,
+      Err(_) => return Err(CoordinatorError::InvalidAccessPolicy),
+    };
+    if let Ok(mut policies) = self.access_policies.write() {
+      policies.insert(*handle, policy.clone());
+    }
+    Ok(policy)
+  }
+
+  /// Checks a client request against the access policy committed in the ledger's genesis block.
+  /// `credentials` are the client's public key and its signature over the request message.
+  pub async fn authorize(
+    &self,
+    handle_bytes: &[u8],
+    request: &AccessRequest<'_>,
+    credentials: Option<(&[u8], &[u8])>,
+  ) -> Result<(), CoordinatorError> {
+    if request.is_write() {
+      self.check_accepts_writes()?;
+      Self::check_client_handle(handle_bytes)?;
+    }
+    let handle = NimbleDigest::digest(handle_bytes);
+    let policy = self.get_access_policy(&handle).await?;
+    if policy
+      .authorize(handle_bytes, request, credentials)
+      .is_err()
+    {
+      return Err(CoordinatorError::AccessDenied);
+    }
+    Ok(())
+  }
+
   async fn read_ledger_tail_internal(
     &self,
     handle: &NimbleDigest,
     nonce: &Nonce,
   ) -> Result<LedgerEntry, CoordinatorError> {
-    let endorsers = self.get_endorser_pks();
+    let endorsers = self.get_ledger_endorser_pks(handle);
     self
       .endorser_read_ledger_tail(&endorsers, handle, nonce)
       .await
@@ -1852,12 +4781,103 @@ impl CoordinatorState {
     let res = self.ledger_store.read_ledger_by_index(handle, height).await;
     match res {
       Ok(ledger_entry) => Ok(ledger_entry),
-      Err(error) => match error {
-        LedgerStoreError::LedgerError(StorageError::InvalidIndex) => {
-          Err(CoordinatorError::InvalidHeight)
+      Err(error) => Err(ledger_store_error(&error)),
+    }
+  }
+
+  // Reads the entry at `height` of a ledger from the tail cache, or else from the ledger store,
+  // caching it once its receipts are attached
+  async fn read_tail_entry(
+    &self,
+    handle: &Handle,
+    height: usize,
+  ) -> Result<LedgerEntry, CoordinatorError> {
+    if let Some(ledger_entry) = self.tail_cache.get(handle, height) {
+      return Ok(ledger_entry);
+    }
+    let ledger_entry = self.read_ledger_by_index_internal(handle, height).await?;
+    if ledger_entry.get_receipts().get_metablock().is_ok() {
+      self.tail_cache.insert(handle, height, &ledger_entry);
+    }
+    Ok(ledger_entry)
+  }
+
+  // Endorsers do not know when the coordinator stored an entry, so the (untrusted) timestamp
+  // is looked up in the ledger store. Failing to find it is not an error.
+  async fn attach_stored_timestamp(
+    &self,
+    handle: &Handle,
+    mut ledger_entry: LedgerEntry,
+  ) -> LedgerEntry {
+    if ledger_entry.get_timestamp().is_some() {
+      return ledger_entry;
+    }
+
+    let height = match self.verifier_state.read() {
+      Ok(vs) => ledger_entry
+        .get_receipts()
+        .check_policy(&vs, handle, &EndorsementPolicy::Majority)
+        .ok(),
+      Err(_) => None,
+    };
+    if let Some(height) = height {
+      if let Ok(stored_entry) = self.read_tail_entry(handle, height).await {
+        if let Some(timestamp) = stored_entry.get_timestamp() {
+          ledger_entry.set_timestamp(timestamp);
+        }
+      }
+    }
+    ledger_entry
+  }
+
+  /// Returns the height of a ledger's tail in the ledger store, which may not be endorsed yet
+  pub async fn get_ledger_height(&self, handle_bytes: &[u8]) -> Result<usize, CoordinatorError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    match self.ledger_store.read_ledger_tail(&handle).await {
+      Ok((_entry, height)) => Ok(height),
+      Err(error) => Err(ledger_store_error(&error)),
+    }
+  }
+
+  /// Waits until the ledger store holds the append named by a consistency token, with the
+  /// append's receipts, so that a read of the ledger's tail that follows observes the append
+  pub async fn wait_for_consistency_token(
+    &self,
+    handle_bytes: &[u8],
+    token_bytes: &[u8],
+  ) -> Result<(), CoordinatorError> {
+    let token = {
+      let res = ConsistencyToken::from_bytes(token_bytes);
+      if res.is_err() {
+        return Err(CoordinatorError::InvalidConsistencyToken);
+      }
+      res.unwrap()
+    };
+    let handle = NimbleDigest::digest(handle_bytes);
+    if *token.get_handle() != handle {
+      return Err(CoordinatorError::InvalidConsistencyToken);
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(CONSISTENCY_TOKEN_TIMEOUT);
+    loop {
+      let res = self.read_tail_entry(&handle, token.get_height()).await;
+      match res {
+        Ok(ledger_entry) => {
+          // an entry whose receipts are not attached yet is still being appended
+          if let Ok(metablock) = ledger_entry.get_receipts().get_metablock() {
+            if metablock.hash() != *token.get_tail_hash() {
+              return Err(CoordinatorError::InvalidConsistencyToken);
+            }
+            return Ok(());
+          }
         },
-        _ => Err(CoordinatorError::FailedToCallLedgerStore),
-      },
+        Err(CoordinatorError::InvalidHeight) => {},
+        Err(error) => return Err(error),
+      }
+      if Instant::now() >= deadline {
+        return Err(CoordinatorError::StaleRead);
+      }
+      tokio::time::sleep(Duration::from_millis(CONSISTENCY_TOKEN_POLL)).await;
     }
   }
 
@@ -1876,34 +4896,37 @@ impl CoordinatorState {
     };
 
     let handle = NimbleDigest::digest(handle_bytes);
+    self.check_endorser_lease()?;
 
     let mut nonce_attached = false;
     let mut nonce_attached_height = 0;
 
     loop {
       match self.read_ledger_tail_internal(&handle, &nonce).await {
-        Ok(ledger_entry) => return Ok(ledger_entry),
+        Ok(ledger_entry) => {
+          self.ledger_stats.record_read(&handle, handle_bytes);
+          return Ok(self.attach_stored_timestamp(&handle, ledger_entry).await);
+        },
         Err(error) => match error {
           CoordinatorError::FailedToObtainQuorum => {
             if !nonce_attached {
               let res = self.ledger_store.attach_ledger_nonce(&handle, &nonce).await;
-              if res.is_err() {
+              if let Err(error) = res {
                 eprintln!(
                   "Failed to attach the nonce for reading ledger tail {:?}",
-                  res.unwrap_err()
+                  error
                 );
-                return Err(CoordinatorError::FailedToAttachNonce);
+                return Err(ledger_store_error(&error));
               }
               nonce_attached = true;
               nonce_attached_height = res.unwrap();
             }
-            match self
-              .read_ledger_by_index_internal(&handle, nonce_attached_height)
-              .await
-            {
+            match self.read_tail_entry(&handle, nonce_attached_height).await {
               Ok(ledger_entry) => return Ok(ledger_entry),
               Err(error) => match error {
                 CoordinatorError::FailedToObtainQuorum | CoordinatorError::InvalidHeight => {
+                  // wait for an append to include the nonce without starving other tasks
+                  tokio::task::yield_now().await;
                   continue;
                 },
                 _ => {
@@ -1920,6 +4943,156 @@ impl CoordinatorState {
     }
   }
 
+  /// Returns the identities of the endorsers that signed the receipts and whether the
+  /// signatures form a quorum in the view they were produced in
+  /// Returns the metrics of a ledger, or None if it saw no activity since the coordinator started
+  pub fn get_ledger_stats(&self, handle_bytes: &[u8]) -> Option<LedgerStats> {
+    self.ledger_stats.get(&NimbleDigest::digest(handle_bytes))
+  }
+
+  pub fn get_all_ledger_stats(&self) -> Vec<(Vec<u8>, LedgerStats)> {
+    self.ledger_stats.get_all()
+  }
+
+  /// Lists up to `page_size` ledgers (a default number if it is 0) whose handle digests sort
+  /// after `after`, in sorted order, and returns them with the cursor of the next page, or None
+  /// on the last page. Ledgers that only their writers may read are left out, so a page can hold
+  /// fewer ledgers than asked for before the last page. With `namespaces`, only the ledgers in
+  /// them are listed.
+  pub async fn list_ledgers(
+    &self,
+    after: Option<&Handle>,
+    page_size: usize,
+    namespaces: Option<&HashSet<String>>,
+  ) -> Result<(Vec<LedgerListing>, Option<Handle>), CoordinatorError> {
+    let limit = match page_size {
+      0 => DEFAULT_LIST_LEDGERS_PAGE_SIZE,
+      size => size.min(MAX_LIST_LEDGERS_PAGE_SIZE),
+    };
+    let res = match namespaces {
+      Some(namespaces) => {
+        self
+          .list_namespace_summaries(namespaces, after, limit)
+          .await
+      },
+      None => self.ledger_store.list_ledger_summaries(after, limit).await,
+    };
+    if let Err(error) = res {
+      eprintln!(
+        "Failed to list the ledgers in the ledger store ({:?})",
+        error
+      );
+      if let LedgerStoreError::LedgerError(StorageError::NamespacesNotSupported) = error {
+        return Err(CoordinatorError::NamespacesNotSupported);
+      }
+      return Err(ledger_store_error(&error));
+    }
+    let summaries = res.unwrap();
+    let next = if summaries.len() == limit {
+      summaries.last().map(|summary| summary.handle)
+    } else {
+      None
+    };
+
+    let mut ledgers = Vec::with_capacity(summaries.len());
+    for summary in summaries {
+      let block_bytes = summary.genesis.get_block().to_bytes();
+      let app_bytes = match EndorsementPolicy::from_genesis_bytes(&block_bytes) {
+        Ok((_policy, app_bytes)) => app_bytes,
+        Err(_) => return Err(CoordinatorError::InvalidEndorsementPolicy),
+      };
+      let (policy, app_bytes) = match AccessPolicy::from_genesis_bytes(app_bytes) {
+        Ok(res) => res,
+        Err(_) => return Err(CoordinatorError::InvalidAccessPolicy),
+      };
+      if policy.get_visibility() != ReadVisibility::Public {
+        continue;
+      }
+      ledgers.push(LedgerListing {
+        handle: summary.handle,
+        created_at: summary.genesis.get_timestamp(),
+        height: summary.height,
+        app_bytes_digest: NimbleDigest::digest(app_bytes),
+      });
+    }
+    Ok((ledgers, next))
+  }
+
+  // Returns up to `limit` ledgers of `namespaces` whose handles sort after `after`, in sorted
+  // order, as the store's `list_ledger_summaries` returns those of the store
+  async fn list_namespace_summaries(
+    &self,
+    namespaces: &HashSet<String>,
+    after: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<LedgerSummary>, LedgerStoreError> {
+    let mut handles = Vec::new();
+    for namespace in namespaces {
+      handles.extend(
+        self
+          .ledger_store
+          .list_ledgers_in_namespace(namespace)
+          .await?,
+      );
+    }
+    handles.retain(|handle| after.is_none_or(|after| handle > after));
+    handles.sort();
+    handles.truncate(limit);
+
+    let mut summaries = Vec::with_capacity(handles.len());
+    for handle in handles {
+      let (_tail, height) = self.ledger_store.read_ledger_tail(&handle).await?;
+      let genesis = self.ledger_store.read_ledger_by_index(&handle, 0).await?;
+      summaries.push(LedgerSummary {
+        handle,
+        height,
+        genesis,
+      });
+    }
+    Ok(summaries)
+  }
+
+  /// Returns the signers of `receipts`, whether they form a quorum, and the endorsers of the
+  /// ledger that did not sign
+  pub fn summarize_receipts(
+    &self,
+    handle_bytes: &[u8],
+    receipts: &Receipts,
+  ) -> (Vec<Vec<u8>>, bool, Vec<Vec<u8>>) {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let quorum_verified = match self.verifier_state.read() {
+      Ok(vs) => receipts
+        .check_policy(&vs, &handle, &EndorsementPolicy::Majority)
+        .is_ok(),
+      Err(_) => false,
+    };
+    let signers = receipts.get_signer_ids();
+    let lagging = self
+      .get_ledger_endorser_pks(&handle)
+      .into_iter()
+      .filter(|pk| !signers.contains(pk))
+      .collect();
+    (signers, quorum_verified, lagging)
+  }
+
+  /// Returns the statement that the endorsers signed in the receipts of a read of the latest entry
+  /// with `nonce_bytes`, so that a client can check them without reconstructing it; empty if
+  /// there are no receipts
+  pub fn read_latest_statement(
+    &self,
+    handle_bytes: &[u8],
+    nonce_bytes: &[u8],
+    receipts: &Receipts,
+  ) -> Vec<u8> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    match self.verifier_state.read() {
+      Ok(vs) => receipts
+        .read_latest_statement(vs.get_group_identity(), &handle, nonce_bytes)
+        .unwrap_or_default(),
+      Err(_) => Vec::new(),
+    }
+  }
+
   pub async fn read_ledger_by_index(
     &self,
     handle_bytes: &[u8],
@@ -1934,11 +5107,403 @@ impl CoordinatorState {
           "Failed to read ledger by index from the ledger store {:?}",
           error,
         );
+        Err(ledger_store_error(&error))
+      },
+    }
+  }
+
+  /// Reads the entries of a ledger at indices `start..end`, or up to its tail, each with its
+  /// metablock. `prev` is the metablock of the entry before `start` if the caller knows it, from
+  /// which the metablocks of entries whose receipts were compacted away are chained.
+  pub async fn read_ledger_range(
+    &self,
+    handle_bytes: &[u8],
+    start: usize,
+    end: usize,
+    prev: Option<MetaBlock>,
+  ) -> Result<Vec<(LedgerEntry, MetaBlock)>, CoordinatorError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let res = self
+      .ledger_store
+      .read_ledger_range(&handle, start, end)
+      .await;
+    if let Err(error) = res {
+      eprintln!(
+        "Failed to read a range of the ledger from the ledger store {:?}",
+        error
+      );
+      return Err(ledger_store_error(&error));
+    }
+
+    let mut prev = prev;
+    let mut entries = Vec::new();
+    for (index, ledger_entry) in (start..).zip(res.unwrap()) {
+      let metablock = if !ledger_entry.get_receipts().is_empty() {
+        let res = ledger_entry.get_receipts().get_metablock();
+        if res.is_err() {
+          eprintln!(
+            "The receipts of entry {} are not for a single metablock",
+            index
+          );
+          return Err(CoordinatorError::InvalidReceipt);
+        }
+        res.unwrap()
+      } else if let Some(prev) = &prev {
+        let block_hash = compute_aggregated_block_hash(
+          &ledger_entry.get_block().hash().to_bytes(),
+          &ledger_entry.get_nonces().hash().to_bytes(),
+        );
+        MetaBlock::new(&prev.hash(), &block_hash, index)
+      } else {
+        self
+          .recompute_metablock(handle_bytes, index, &ledger_entry)
+          .await?
+      };
+      prev = Some(metablock.clone());
+      entries.push((ledger_entry, metablock));
+    }
+    Ok(entries)
+  }
+
+  /// Reads the entry at `index` of a ledger along with a proof that links it to a read of the
+  /// ledger's tail for `nonce_bytes`, so that a client can check the entry against endorsers
+  pub async fn read_ledger_by_index_with_proof(
+    &self,
+    handle_bytes: &[u8],
+    index: usize,
+    nonce_bytes: &[u8],
+  ) -> Result<(LedgerEntry, InclusionProof), CoordinatorError> {
+    let tail_entry = self.read_ledger_tail(handle_bytes, nonce_bytes).await?;
+    let res = tail_entry.get_receipts().get_metablock();
+    if res.is_err() {
+      eprintln!("The receipts of the tail are not for a single metablock");
+      return Err(CoordinatorError::InvalidReceipt);
+    }
+    self
+      .inclusion_proof(
+        handle_bytes,
+        index,
+        res.unwrap().get_height(),
+        tail_entry.get_block().to_bytes(),
+        tail_entry.get_nonces().to_bytes(),
+        tail_entry.get_receipts().to_bytes(),
+      )
+      .await
+  }
+
+  /// Reads the entry at `index` of a ledger along with a proof that links it to the tail of the
+  /// snapshot with id `snapshot_id`, which must be a snapshot of the same ledger
+  pub async fn read_ledger_by_index_with_snapshot(
+    &self,
+    handle_bytes: &[u8],
+    index: usize,
+    snapshot_id: usize,
+  ) -> Result<(LedgerEntry, InclusionProof), CoordinatorError> {
+    let snapshot = self.read_snapshot(snapshot_id).await?;
+    if *snapshot.get_handle() != NimbleDigest::digest(handle_bytes) {
+      return Err(CoordinatorError::SnapshotOfAnotherLedger);
+    }
+    self
+      .inclusion_proof(
+        handle_bytes,
+        index,
+        snapshot.get_height(),
+        snapshot.get_block().to_vec(),
+        snapshot.get_nonces().to_vec(),
+        snapshot.get_receipts().to_vec(),
+      )
+      .await
+  }
+
+  // reads the entry at `index` with the block hashes that chain it to the tail at `tail_height`
+  async fn inclusion_proof(
+    &self,
+    handle_bytes: &[u8],
+    index: usize,
+    tail_height: usize,
+    tail_block: Vec<u8>,
+    tail_nonces: Vec<u8>,
+    tail_receipts: Vec<u8>,
+  ) -> Result<(LedgerEntry, InclusionProof), CoordinatorError> {
+    if index > tail_height {
+      return Err(CoordinatorError::InvalidHeight);
+    }
+
+    let ledger_entry = self.read_ledger_by_index(handle_bytes, index).await?;
+    let res = if ledger_entry.get_receipts().is_empty() {
+      self
+        .recompute_metablock(handle_bytes, index, &ledger_entry)
+        .await
+    } else {
+      ledger_entry
+        .get_receipts()
+        .get_metablock()
+        .map_err(|_| CoordinatorError::InvalidReceipt)
+    };
+    if res.is_err() {
+      eprintln!(
+        "The receipts of entry {} are not for a single metablock",
+        index
+      );
+      return Err(CoordinatorError::InvalidReceipt);
+    }
+    let metablock = res.unwrap();
+
+    let mut block_hashes = Vec::with_capacity(tail_height - index);
+    for height in index + 1..=tail_height {
+      let entry = self.read_ledger_by_index(handle_bytes, height).await?;
+      block_hashes.push(compute_aggregated_block_hash(
+        &entry.get_block().hash().to_bytes(),
+        &entry.get_nonces().hash().to_bytes(),
+      ));
+    }
+
+    let proof = InclusionProof::new(
+      metablock,
+      block_hashes,
+      tail_block,
+      tail_nonces,
+      tail_receipts,
+    );
+    Ok((ledger_entry, proof))
+  }
+
+  /// Reads the nearest entry at or before `index` of a ledger that kept its receipts, with the
+  /// block hashes of the entries after it up to `index`, so that a client can check an entry whose
+  /// receipts were compacted away
+  pub async fn read_checkpoint(
+    &self,
+    handle_bytes: &[u8],
+    index: usize,
+  ) -> Result<CheckpointProof, CoordinatorError> {
+    let mut block_hashes = Vec::new();
+    let mut checkpoint = index;
+    loop {
+      let entry = self.read_ledger_by_index(handle_bytes, checkpoint).await?;
+      if !entry.get_receipts().is_empty() {
+        block_hashes.reverse();
+        return Ok(CheckpointProof::new(
+          checkpoint,
+          entry.get_block().to_bytes(),
+          entry.get_nonces().to_bytes(),
+          entry.get_receipts().to_bytes(),
+          block_hashes,
+        ));
+      }
+      if checkpoint == 0 {
+        eprintln!("No entry at or before {} carries receipts", index);
+        return Err(CoordinatorError::InvalidReceipt);
+      }
+      block_hashes.push(compute_aggregated_block_hash(
+        &entry.get_block().hash().to_bytes(),
+        &entry.get_nonces().hash().to_bytes(),
+      ));
+      checkpoint -= 1;
+    }
+  }
+
+  // Returns the metablock of an entry whose receipts were compacted away, recomputed from the
+  // checkpoint before it
+  async fn recompute_metablock(
+    &self,
+    handle_bytes: &[u8],
+    index: usize,
+    ledger_entry: &LedgerEntry,
+  ) -> Result<MetaBlock, CoordinatorError> {
+    let proof = self.read_checkpoint(handle_bytes, index).await?;
+    let res = Receipts::from_bytes(proof.get_receipts());
+    if res.is_err() {
+      return Err(CoordinatorError::InvalidReceipt);
+    }
+    let res = res.unwrap().get_metablock();
+    if res.is_err() {
+      return Err(CoordinatorError::InvalidReceipt);
+    }
+    proof
+      .verify_chain(
+        &res.unwrap(),
+        &ledger_entry.get_block().to_bytes(),
+        &ledger_entry.get_nonces().to_bytes(),
+        index,
+      )
+      .map_err(|_| CoordinatorError::InvalidReceipt)
+  }
+
+  /// Rewrites the receipts of every ledger so they conform to the store's receipt retention
+  /// policy, and returns the number of entries rewritten. `progress` holds the index of each
+  /// ledger from which its next compaction starts.
+  pub async fn compact_receipt_retention(
+    &self,
+    progress: &mut HashMap<Handle, usize>,
+  ) -> Result<usize, CoordinatorError> {
+    let res = self.ledger_store.list_ledgers().await;
+    if let Err(error) = res {
+      eprintln!("Failed to list the ledgers ({:?})", error);
+      return Err(CoordinatorError::FailedToCallLedgerStore);
+    }
+
+    let mut compacted = 0;
+    for handle in res.unwrap() {
+      let from = progress.get(&handle).copied().unwrap_or_default();
+      match self
+        .ledger_store
+        .compact_ledger_receipts(&handle, from)
+        .await
+      {
+        Ok(next) => {
+          self.tail_cache.invalidate(&handle);
+          compacted += next - from;
+          progress.insert(handle, next);
+        },
+        Err(error) => warn!(
+          ?error,
+          "failed to apply the receipt retention policy to a ledger"
+        ),
+      }
+    }
+    Ok(compacted)
+  }
+
+  /// Drops the receipts of the old entries of every ledger that `policy` compacts. `progress`
+  /// holds the index of each ledger from which its next compaction starts.
+  pub async fn compact_ledgers(
+    &self,
+    policy: &ReceiptCompaction,
+    progress: &mut HashMap<Handle, usize>,
+  ) -> Result<usize, CoordinatorError> {
+    let res = self.ledger_store.list_ledgers().await;
+    if let Err(error) = res {
+      eprintln!("Failed to list the ledgers ({:?})", error);
+      return Err(CoordinatorError::FailedToCallLedgerStore);
+    }
+
+    let mut compacted = 0;
+    for handle in res.unwrap() {
+      let from = progress.get(&handle).copied().unwrap_or_default();
+      let res = self
+        .ledger_store
+        .compact_ledger_to_checkpoints(&handle, policy, from)
+        .await;
+      match res {
+        Ok(next) => {
+          self.tail_cache.invalidate(&handle);
+          compacted += next - from;
+          progress.insert(handle, next);
+        },
+        Err(error) => warn!(?error, "failed to compact the receipts of a ledger"),
+      }
+    }
+    Ok(compacted)
+  }
+
+  /// Checks the integrity of every ledger in the store: recomputes the hash chain of each ledger
+  /// and verifies the stored receipts against the view ledger, flagging the entries that fail,
+  /// which points to tampering with the store or to a bug between audits. The report is kept for
+  /// `get_integrity_report`. A check that is already running is waited for rather than repeated.
+  pub async fn check_store_integrity(&self) -> Result<IntegrityReport, CoordinatorError> {
+    if let Ok(_check) = self.integrity_check_lock.try_lock() {
+      return self.run_integrity_check().await;
+    }
+    let _check = self.integrity_check_lock.lock().await;
+    match self.get_integrity_report()? {
+      Some(report) => Ok(report),
+      None => self.run_integrity_check().await,
+    }
+  }
+
+  async fn run_integrity_check(&self) -> Result<IntegrityReport, CoordinatorError> {
+    let report = check_store(
+      self.ledger_store.as_ref().as_ref(),
+      ATTESTATION_STR.as_bytes(),
+    )
+    .await;
+    for issue in &report.issues {
+      warn!(
+        ledger = ?issue.ledger,
+        index = ?issue.index,
+        kind = ?issue.kind,
+        detail = %issue.detail,
+        "the integrity check of the store flagged an entry"
+      );
+    }
+    let mut integrity_report = self
+      .integrity_report
+      .write()
+      .map_err(|_e| CoordinatorError::FailedToAcquireWriteLock)?;
+    *integrity_report = Some(report.clone());
+    Ok(report)
+  }
+
+  /// Returns the report of the last check of the integrity of the store, or None if the store has
+  /// not been checked since the coordinator started
+  pub fn get_integrity_report(&self) -> Result<Option<IntegrityReport>, CoordinatorError> {
+    match self.integrity_report.read() {
+      Ok(integrity_report) => Ok(integrity_report.clone()),
+      Err(_) => Err(CoordinatorError::FailedToAcquireReadLock),
+    }
+  }
+
+  /// Reads the tail of a ledger as of the view at `view_height`: the last entry endorsed in that
+  /// view or an earlier one. Returns the entry, its index, and whether the state of all ledgers at
+  /// the end of the view matches what endorsers committed to in the next view change, which is
+  /// never the case for the current view since it has not ended.
+  pub async fn read_ledger_as_of_view(
+    &self,
+    handle_bytes: &[u8],
+    view_height: usize,
+  ) -> Result<(LedgerEntry, usize, bool), CoordinatorError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let res = self.ledger_store.read_view_ledger_tail().await;
+    if res.is_err() {
+      return Err(CoordinatorError::FailedToReadViewLedger);
+    }
+    let (_tail, current_view_height) = res.unwrap();
+    if view_height == 0 || view_height > current_view_height {
+      return Err(CoordinatorError::InvalidViewHeight);
+    }
+
+    if view_height == current_view_height {
+      let views = views_up_to(&self.ledger_store, view_height).await?;
+      return match find_tail_as_of(&self.ledger_store, &handle, &views).await? {
+        Some((index, ledger_entry, _metablock)) => Ok((ledger_entry, index, false)),
+        None => Err(CoordinatorError::NoEntryAsOfView),
+      };
+    }
+
+    let checkpoint = self.get_checkpoint(view_height).await?;
+    let index = match checkpoint.tails.get(&handle) {
+      Some(index) => *index,
+      None => return Err(CoordinatorError::NoEntryAsOfView),
+    };
+    match self.ledger_store.read_ledger_by_index(&handle, index).await {
+      Ok(ledger_entry) => Ok((ledger_entry, index, checkpoint.verified)),
+      Err(error) => {
+        eprintln!(
+          "Failed to read ledger by index from the ledger store {:?}",
+          error
+        );
         Err(CoordinatorError::FailedToReadLedger)
       },
     }
   }
 
+  // past views never change, so a checkpoint is reconstructed once it verifies
+  async fn get_checkpoint(&self, view_height: usize) -> Result<Arc<Checkpoint>, CoordinatorError> {
+    if let Ok(checkpoints) = self.checkpoints.read() {
+      if let Some(checkpoint) = checkpoints.get(&view_height) {
+        return Ok(checkpoint.clone());
+      }
+    }
+
+    let checkpoint = Arc::new(reconstruct_checkpoint(&self.ledger_store, view_height).await?);
+    if checkpoint.verified {
+      if let Ok(mut checkpoints) = self.checkpoints.write() {
+        checkpoints.insert(view_height, checkpoint.clone());
+      }
+    }
+    Ok(checkpoint)
+  }
+
   pub async fn read_view_by_index(&self, index: usize) -> Result<LedgerEntry, CoordinatorError> {
     let ledger_entry = {
       let res = self.ledger_store.read_view_ledger_by_index(index).await;
@@ -1951,6 +5516,16 @@ impl CoordinatorState {
     Ok(ledger_entry)
   }
 
+  /// Reads every entry of the view ledger, in the order of the views
+  pub async fn read_view_ledger(&self) -> Result<Vec<LedgerEntry>, CoordinatorError> {
+    let (_tail, height, _attestations) = self.read_view_tail().await?;
+    let mut entries = Vec::with_capacity(height);
+    for index in 1..=height {
+      entries.push(self.read_view_by_index(index).await?);
+    }
+    Ok(entries)
+  }
+
   pub async fn read_view_tail(&self) -> Result<(LedgerEntry, usize, Vec<u8>), CoordinatorError> {
     let res = self.ledger_store.read_view_ledger_tail().await;
     if let Err(error) = res {
@@ -1964,4 +5539,79 @@ impl CoordinatorState {
     let (ledger_entry, height) = res.unwrap();
     Ok((ledger_entry, height, ATTESTATION_STR.as_bytes().to_vec()))
   }
+
+  /// Reads the tail of the view ledger with receipts that the endorsers of the current view sign
+  /// together with `nonce_bytes`, so that a client can check that it learns the current view
+  pub async fn read_view_tail_with_nonce(
+    &self,
+    nonce_bytes: &[u8],
+  ) -> Result<(LedgerEntry, usize), CoordinatorError> {
+    if Nonce::new(nonce_bytes).is_err() {
+      return Err(CoordinatorError::InvalidNonce);
+    }
+    let (stored_entry, height, _attestations) = self.read_view_tail().await?;
+    let block = stored_entry.get_block().to_bytes();
+
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+    for pk in self.get_endorser_pks() {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(&pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+
+      let tx = mpsc_tx.clone();
+      let nonce = nonce_bytes.to_vec();
+      let _job = spawn_endorser_call(
+        info_span!("endorser", otel.kind = "client", uri = %endorser),
+        async move {
+          let res = read_view_tail_with_retry(
+            &mut endorser_client,
+            endorser_proto::ReadViewTailReq { nonce },
+          )
+          .await;
+          let _ = tx.send((endorser, res)).await;
+        },
+      );
+    }
+
+    drop(mpsc_tx);
+
+    let mut receipts = Receipts::new();
+    while let Some((endorser, res)) = mpsc_rx.recv().await {
+      match res {
+        Ok(resp) => {
+          let endorser_proto::ReadViewTailResp { receipt } = resp.into_inner();
+          match Receipt::from_bytes(&receipt) {
+            Ok(receipt_rs) => receipts.add(&receipt_rs),
+            Err(error) => {
+              eprintln!("Failed to parse a receipt (err={:?}", error);
+              continue;
+            },
+          }
+          let quorum = match self.verifier_state.read() {
+            Ok(vs) => receipts
+              .verify_read_view_tail(&vs, &block, nonce_bytes)
+              .is_ok(),
+            Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+          };
+          if quorum {
+            let mut ledger_entry =
+              LedgerEntry::new(stored_entry.get_block().clone(), receipts, None);
+            if let Some(timestamp) = stored_entry.get_timestamp() {
+              ledger_entry.set_timestamp(timestamp);
+            }
+            return Ok((ledger_entry, height));
+          }
+        },
+        Err(status) => {
+          eprintln!(
+            "Failed to read the view ledger tail from endorser {} (status={:?})",
+            endorser, status
+          );
+        },
+      }
+    }
+
+    Err(CoordinatorError::FailedToObtainQuorum)
+  }
 }
@@ -0,0 +1,394 @@
+//! Leader election among coordinators that share a ledger store. The coordinators take turns
+//! holding a lease in the store; the holder serves requests, and the others forward the requests
+//! they receive to it until the lease expires and one of them takes it over. A leader that cannot
+//! renew its lease before it expires exits, so that it is restarted as a follower.
+//!
+//! The lease makes one coordinator at a time append to the ledgers, but it is not what keeps the
+//! ledgers consistent: the store appends an entry only at the height that follows the ledger's
+//! tail, and endorsers only sign entries that extend the tails they hold, so a leader whose lease
+//! ran out while a request was in flight fails that request instead of forking the ledger.
+use crate::coordinator_proto::{
+  call_client::CallClient, call_server::Call, AppendBatchReq, AppendBatchResp, AppendChunkReq,
+  AppendHashOnlyReq, AppendReq, AppendResp, CheckpointReq, CheckpointResp, GetLedgerByLabelReq,
+  GetLedgerByLabelResp, GetLedgerStatsReq, GetLedgerStatsResp, ListLedgersReq, ListLedgersResp,
+  NewLedgerReq, NewLedgerResp, ReadAdminLedgerReq, ReadAdminLedgerResp, ReadBlobReq, ReadBlobResp,
+  ReadByIndexReq, ReadByIndexResp, ReadCheckpointReq, ReadCheckpointResp, ReadLatestAsOfViewReq,
+  ReadLatestAsOfViewResp, ReadLatestReq, ReadLatestResp, ReadRangeReq, ReadRangeResp,
+  ReadViewByIndexReq, ReadViewByIndexResp, ReadViewTailReq, ReadViewTailResp, SubscribeReq,
+  WatchViewChangesReq, WatchViewChangesResp,
+};
+use std::{
+  sync::{Arc, RwLock},
+  time::Duration,
+};
+use store::{
+  errors::LedgerStoreError,
+  ledger::{BoxedLedgerStore, Lease},
+};
+use tokio::sync::{oneshot, Mutex};
+use tonic::{
+  codec::Streaming,
+  transport::{Channel, ClientTlsConfig, Endpoint},
+  Extensions, Request, Response, Status,
+};
+use tracing::{info, warn};
+
+/// The name of the lease that the leader holds in the store
+pub const LEADER_LEASE: &str = "coordinator_leader";
+// marks a request that a follower forwarded, which is never forwarded again
+const FORWARDED_METADATA: &str = "x-nimble-forwarded";
+const NO_LEADER_MSG: &str = "No coordinator leads; retry later";
+
+fn no_leader() -> Status {
+  Status::unavailable(NO_LEADER_MSG)
+}
+
+pub struct LeaderElection {
+  store: Arc<BoxedLedgerStore>,
+  id: String, // the URI at which this coordinator serves, which followers forward requests to
+  lease_duration: Duration,
+  lease: RwLock<Option<Lease>>, // the lease as last read from the store
+}
+
+impl LeaderElection {
+  pub fn new(store: Arc<BoxedLedgerStore>, id: &str, lease_duration: Duration) -> Self {
+    LeaderElection {
+      store,
+      id: id.to_string(),
+      lease_duration,
+      lease: RwLock::new(None),
+    }
+  }
+
+  /// Takes the lease if it is free or expired, or renews it if this coordinator holds it, and
+  /// returns whether this coordinator leads
+  pub async fn campaign(&self) -> Result<bool, LedgerStoreError> {
+    let lease = self
+      .store
+      .acquire_lease(
+        LEADER_LEASE,
+        &self.id,
+        self.lease_duration.as_millis() as u64,
+      )
+      .await?;
+    if let Ok(mut cached) = self.lease.write() {
+      *cached = Some(lease);
+    }
+    Ok(self.is_leader())
+  }
+
+  /// Returns whether this coordinator holds an unexpired lease
+  pub fn is_leader(&self) -> bool {
+    match self.lease.read() {
+      Ok(lease) => lease.as_ref().is_some_and(|l| l.is_held_by(&self.id)),
+      Err(_) => false,
+    }
+  }
+
+  /// Returns the URI of the coordinator that holds the lease, unless it expired
+  pub fn get_leader(&self) -> Option<String> {
+    let lease = self.lease.read().ok()?.clone()?;
+    if lease.is_held_by(&lease.holder) {
+      Some(lease.holder)
+    } else {
+      None
+    }
+  }
+
+  /// Campaigns for the lease every third of its duration, and signals `elected` once this
+  /// coordinator takes it. A leader exits when its lease expires without being renewed.
+  pub async fn run(self: Arc<Self>, elected: oneshot::Sender<()>) {
+    let mut elected = Some(elected);
+    loop {
+      if let Err(error) = self.campaign().await {
+        warn!(?error, "failed to campaign for the leader lease");
+      }
+      if self.is_leader() {
+        if let Some(elected) = elected.take() {
+          info!(id = %self.id, "took the leader lease");
+          let _ = elected.send(());
+        }
+      } else if elected.is_none() {
+        eprintln!("Lost the leader lease; exiting so that this coordinator restarts as a follower");
+        std::process::exit(1);
+      }
+      tokio::time::sleep(self.lease_duration / 3).await;
+    }
+  }
+}
+
+/// Serves the coordinator's Call service on a follower by forwarding every request, with its
+/// metadata, to the leader
+pub struct LeaderForwarder {
+  election: Arc<LeaderElection>,
+  tls: Option<ClientTlsConfig>,
+  client: Mutex<Option<(String, CallClient<Channel>)>>, // the leader's URI and a client to it
+}
+
+impl LeaderForwarder {
+  pub fn new(election: Arc<LeaderElection>, tls: Option<ClientTlsConfig>) -> Self {
+    LeaderForwarder {
+      election,
+      tls,
+      client: Mutex::new(None),
+    }
+  }
+
+  async fn leader_client(&self) -> Result<CallClient<Channel>, Status> {
+    let leader = match self.election.get_leader() {
+      Some(leader) if !self.election.is_leader() => leader,
+      _ => return Err(no_leader()),
+    };
+    let mut client = self.client.lock().await;
+    if let Some((uri, c)) = client.as_ref() {
+      if *uri == leader {
+        return Ok(c.clone());
+      }
+    }
+
+    let res = Endpoint::from_shared(leader.clone());
+    if res.is_err() {
+      return Err(no_leader());
+    }
+    let mut endpoint = res.unwrap();
+    if let Some(tls) = &self.tls {
+      endpoint = endpoint.tls_config(tls.clone()).map_err(|_| no_leader())?;
+    }
+    let res = endpoint.connect().await;
+    if let Err(error) = res {
+      warn!(%leader, ?error, "failed to connect to the leader");
+      return Err(no_leader());
+    }
+    let c = CallClient::new(res.unwrap());
+    *client = Some((leader, c.clone()));
+    Ok(c)
+  }
+
+  // Returns the request marked as forwarded, or None if another follower already forwarded it
+  fn forwarded<T>(request: Request<T>) -> Option<Request<T>> {
+    let (mut metadata, _extensions, message) = request.into_parts();
+    if metadata.contains_key(FORWARDED_METADATA) {
+      return None;
+    }
+    metadata.insert(FORWARDED_METADATA, "1".parse().unwrap());
+    Some(Request::from_parts(
+      metadata,
+      Extensions::default(),
+      message,
+    ))
+  }
+}
+
+#[tonic::async_trait]
+impl Call for LeaderForwarder {
+  async fn new_ledger(
+    &self,
+    req: Request<NewLedgerReq>,
+  ) -> Result<Response<NewLedgerResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.new_ledger(req).await
+  }
+
+  async fn append(&self, req: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.append(req).await
+  }
+
+  async fn append_batch(
+    &self,
+    req: Request<AppendBatchReq>,
+  ) -> Result<Response<AppendBatchResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.append_batch(req).await
+  }
+
+  async fn append_chunked(
+    &self,
+    req: Request<Streaming<AppendChunkReq>>,
+  ) -> Result<Response<AppendResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    // the whole payload is received before forwarding, so that a broken stream appends nothing
+    let (metadata, _extensions, mut stream) = req.into_parts();
+    let mut messages = Vec::new();
+    while let Some(message) = stream.message().await? {
+      messages.push(message);
+    }
+    let req = Request::from_parts(
+      metadata,
+      Extensions::default(),
+      tokio_stream::iter(messages),
+    );
+    self.leader_client().await?.append_chunked(req).await
+  }
+
+  async fn append_hash_only(
+    &self,
+    req: Request<AppendHashOnlyReq>,
+  ) -> Result<Response<AppendResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.append_hash_only(req).await
+  }
+
+  type ReadBlobStream = Streaming<ReadBlobResp>;
+
+  async fn read_blob(
+    &self,
+    req: Request<ReadBlobReq>,
+  ) -> Result<Response<Self::ReadBlobStream>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_blob(req).await
+  }
+
+  async fn read_latest(
+    &self,
+    req: Request<ReadLatestReq>,
+  ) -> Result<Response<ReadLatestResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_latest(req).await
+  }
+
+  async fn read_by_index(
+    &self,
+    req: Request<ReadByIndexReq>,
+  ) -> Result<Response<ReadByIndexResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_by_index(req).await
+  }
+
+  type ReadRangeStream = Streaming<ReadRangeResp>;
+
+  async fn read_range(
+    &self,
+    req: Request<ReadRangeReq>,
+  ) -> Result<Response<Self::ReadRangeStream>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_range(req).await
+  }
+
+  type SubscribeStream = Streaming<ReadRangeResp>;
+
+  async fn subscribe(
+    &self,
+    req: Request<SubscribeReq>,
+  ) -> Result<Response<Self::SubscribeStream>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.subscribe(req).await
+  }
+
+  async fn read_checkpoint(
+    &self,
+    req: Request<ReadCheckpointReq>,
+  ) -> Result<Response<ReadCheckpointResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_checkpoint(req).await
+  }
+
+  async fn checkpoint(
+    &self,
+    req: Request<CheckpointReq>,
+  ) -> Result<Response<CheckpointResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.checkpoint(req).await
+  }
+
+  async fn read_view_by_index(
+    &self,
+    req: Request<ReadViewByIndexReq>,
+  ) -> Result<Response<ReadViewByIndexResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_view_by_index(req).await
+  }
+
+  async fn read_view_tail(
+    &self,
+    req: Request<ReadViewTailReq>,
+  ) -> Result<Response<ReadViewTailResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_view_tail(req).await
+  }
+
+  async fn read_admin_ledger(
+    &self,
+    req: Request<ReadAdminLedgerReq>,
+  ) -> Result<Response<ReadAdminLedgerResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.read_admin_ledger(req).await
+  }
+
+  async fn get_ledger_stats(
+    &self,
+    req: Request<GetLedgerStatsReq>,
+  ) -> Result<Response<GetLedgerStatsResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.get_ledger_stats(req).await
+  }
+
+  async fn list_ledgers(
+    &self,
+    req: Request<ListLedgersReq>,
+  ) -> Result<Response<ListLedgersResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.list_ledgers(req).await
+  }
+
+  async fn get_ledger_by_label(
+    &self,
+    req: Request<GetLedgerByLabelReq>,
+  ) -> Result<Response<GetLedgerByLabelResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.get_ledger_by_label(req).await
+  }
+
+  async fn read_latest_as_of_view(
+    &self,
+    req: Request<ReadLatestAsOfViewReq>,
+  ) -> Result<Response<ReadLatestAsOfViewResp>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self
+      .leader_client()
+      .await?
+      .read_latest_as_of_view(req)
+      .await
+  }
+
+  type WatchViewChangesStream = Streaming<WatchViewChangesResp>;
+
+  async fn watch_view_changes(
+    &self,
+    req: Request<WatchViewChangesReq>,
+  ) -> Result<Response<Self::WatchViewChangesStream>, Status> {
+    let req = Self::forwarded(req).ok_or_else(no_leader)?;
+    self.leader_client().await?.watch_view_changes(req).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use store::ledger::in_memory::InMemoryLedgerStore;
+
+  #[tokio::test]
+  async fn test_leader_election() {
+    let store: Arc<BoxedLedgerStore> = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let a = LeaderElection::new(store.clone(), "http://a:8080", Duration::from_secs(60));
+    let b = LeaderElection::new(store.clone(), "http://b:8080", Duration::from_secs(60));
+    assert_eq!(b.get_leader(), None);
+
+    assert!(a.campaign().await.unwrap());
+    assert!(!b.campaign().await.unwrap());
+    assert_eq!(b.get_leader(), Some("http://a:8080".to_string()));
+    assert!(a.campaign().await.unwrap());
+
+    // a follower forwards requests to the leader only once
+    let req = LeaderForwarder::forwarded(Request::new(ReadViewTailReq { nonce: vec![] })).unwrap();
+    assert!(LeaderForwarder::forwarded(req).is_none());
+
+    // once the leader's lease expires, a follower takes it over
+    let store: Arc<BoxedLedgerStore> = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let a = LeaderElection::new(store.clone(), "http://a:8080", Duration::from_millis(0));
+    let b = LeaderElection::new(store, "http://b:8080", Duration::from_secs(60));
+    assert!(!a.campaign().await.unwrap());
+    assert!(b.campaign().await.unwrap());
+    assert_eq!(b.get_leader(), Some("http://b:8080".to_string()));
+  }
+}
@@ -0,0 +1,194 @@
+//! The health of the coordinator's connections to its endorsers. The coordinator probes every
+//! connected endorser periodically: an endorser that fails a probe is degraded, and one that
+//! fails several in a row is down. The connections to a down endorser are replaced with new ones
+//! after a backoff that doubles with each failed attempt, so an endorser that restarts or whose
+//! network recovers is served again without restarting the coordinator.
+use std::time::{Duration, Instant};
+use store::ledger::current_timestamp;
+
+/// the consecutive failed probes after which an endorser is down
+pub const DOWN_AFTER_FAILURES: u32 = 3;
+
+const MIN_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How an endorser answered the coordinator's last probes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EndorserHealth {
+  /// the endorser answered the last probe
+  Healthy,
+  /// the endorser failed the last probes, but fewer than `DOWN_AFTER_FAILURES` of them
+  Degraded,
+  /// the endorser failed `DOWN_AFTER_FAILURES` probes in a row, and the coordinator reconnects
+  /// to it
+  Down,
+}
+
+impl EndorserHealth {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      EndorserHealth::Healthy => "healthy",
+      EndorserHealth::Degraded => "degraded",
+      EndorserHealth::Down => "down",
+    }
+  }
+}
+
+/// The health of an endorser, as reported to operators
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndorserHealthReport {
+  pub uri: String,
+  pub pk: Vec<u8>,
+  pub health: EndorserHealth,
+  /// the failed probes and reconnection attempts since the endorser last answered
+  pub consecutive_failures: u32,
+  /// why the endorser last failed, if it failed since it last answered
+  pub last_error: Option<String>,
+  /// time (ms since epoch) at which the endorser's health last changed
+  pub since: u64,
+  /// the times that the connections to the endorser were replaced
+  pub reconnects: u64,
+}
+
+/// Tracks the health of one endorser from the outcomes of the probes and reconnections
+#[derive(Clone, Debug)]
+pub struct EndorserHealthTracker {
+  health: EndorserHealth,
+  consecutive_failures: u32,
+  last_error: Option<String>,
+  since: u64,
+  reconnects: u64,
+  backoff: Duration,
+  next_reconnect: Option<Instant>, // set while the endorser is down
+}
+
+impl Default for EndorserHealthTracker {
+  fn default() -> Self {
+    EndorserHealthTracker {
+      health: EndorserHealth::Healthy,
+      consecutive_failures: 0,
+      last_error: None,
+      since: current_timestamp(),
+      reconnects: 0,
+      backoff: MIN_RECONNECT_BACKOFF,
+      next_reconnect: None,
+    }
+  }
+}
+
+impl EndorserHealthTracker {
+  pub fn health(&self) -> EndorserHealth {
+    self.health
+  }
+
+  fn set_health(&mut self, health: EndorserHealth) {
+    if self.health != health {
+      self.health = health;
+      self.since = current_timestamp();
+    }
+  }
+
+  /// Records that the endorser answered a probe
+  pub fn record_success(&mut self) {
+    self.consecutive_failures = 0;
+    self.last_error = None;
+    self.backoff = MIN_RECONNECT_BACKOFF;
+    self.next_reconnect = None;
+    self.set_health(EndorserHealth::Healthy);
+  }
+
+  /// Records that the endorser failed a probe or a reconnection attempt. Once it is down, each
+  /// failure doubles the wait before the next reconnection attempt.
+  pub fn record_failure(&mut self, error: String, now: Instant) {
+    self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+    self.last_error = Some(error);
+    if self.consecutive_failures < DOWN_AFTER_FAILURES {
+      self.set_health(EndorserHealth::Degraded);
+      return;
+    }
+    match self.next_reconnect {
+      None => self.next_reconnect = Some(now + self.backoff),
+      Some(next_reconnect) if next_reconnect <= now => {
+        self.backoff = (self.backoff * 2).min(MAX_RECONNECT_BACKOFF);
+        self.next_reconnect = Some(now + self.backoff);
+      },
+      // a probe that fails while the endorser waits for its next attempt does not delay it
+      Some(_next_reconnect) => {},
+    }
+    self.set_health(EndorserHealth::Down);
+  }
+
+  /// Records that the connections to the endorser were replaced with ones on which it answered
+  pub fn record_reconnect(&mut self) {
+    self.reconnects += 1;
+    self.record_success();
+  }
+
+  /// whether the endorser is down and its next reconnection attempt is due at `now`
+  pub fn is_reconnect_due(&self, now: Instant) -> bool {
+    self
+      .next_reconnect
+      .is_some_and(|next_reconnect| next_reconnect <= now)
+  }
+
+  pub fn report(&self, uri: &str, pk: &[u8]) -> EndorserHealthReport {
+    EndorserHealthReport {
+      uri: uri.to_string(),
+      pk: pk.to_vec(),
+      health: self.health,
+      consecutive_failures: self.consecutive_failures,
+      last_error: self.last_error.clone(),
+      since: self.since,
+      reconnects: self.reconnects,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_endorser_health_tracker() {
+    let mut tracker = EndorserHealthTracker::default();
+    let now = Instant::now();
+    assert_eq!(tracker.health(), EndorserHealth::Healthy);
+
+    // an endorser is degraded until it fails enough probes in a row to be down
+    for failures in 1..DOWN_AFTER_FAILURES {
+      tracker.record_failure(String::from("unavailable"), now);
+      assert_eq!(tracker.health(), EndorserHealth::Degraded);
+      assert_eq!(tracker.report("uri", b"pk").consecutive_failures, failures);
+      assert!(!tracker.is_reconnect_due(now + MAX_RECONNECT_BACKOFF));
+    }
+    tracker.record_success();
+    assert_eq!(tracker.health(), EndorserHealth::Healthy);
+    for _ in 0..DOWN_AFTER_FAILURES {
+      tracker.record_failure(String::from("unavailable"), now);
+    }
+    assert_eq!(tracker.health(), EndorserHealth::Down);
+    assert!(!tracker.is_reconnect_due(now));
+    assert!(tracker.is_reconnect_due(now + MIN_RECONNECT_BACKOFF));
+
+    // failed attempts double the backoff up to its maximum, and probes in between do not
+    let mut at = now + MIN_RECONNECT_BACKOFF;
+    tracker.record_failure(String::from("refused"), at);
+    tracker.record_failure(String::from("unavailable"), at + Duration::from_millis(1));
+    assert!(!tracker.is_reconnect_due(at + MIN_RECONNECT_BACKOFF));
+    assert!(tracker.is_reconnect_due(at + 2 * MIN_RECONNECT_BACKOFF));
+    for _ in 0..10 {
+      at += MAX_RECONNECT_BACKOFF;
+      tracker.record_failure(String::from("refused"), at);
+    }
+    assert!(!tracker.is_reconnect_due(at + MAX_RECONNECT_BACKOFF - Duration::from_millis(1)));
+    assert!(tracker.is_reconnect_due(at + MAX_RECONNECT_BACKOFF));
+
+    tracker.record_reconnect();
+    let report = tracker.report("uri", b"pk");
+    assert_eq!(report.health, EndorserHealth::Healthy);
+    assert_eq!(report.consecutive_failures, 0);
+    assert_eq!(report.last_error, None);
+    assert_eq!(report.reconnects, 1);
+    assert!(!tracker.is_reconnect_due(at + MAX_RECONNECT_BACKOFF));
+  }
+}
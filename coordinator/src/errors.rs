@@ -64,4 +64,94 @@ pub enum CoordinatorError {
   FailedToObtainQuorum,
   /// returned if failed to verify view change
   FailedToActivate,
+  /// returned if a write is rejected because the coordinator is in maintenance mode
+  InMaintenanceMode,
+  /// returned if the genesis block carries a malformed endorsement policy
+  InvalidEndorsementPolicy,
+  /// returned if the collected receipts do not satisfy the ledger's endorsement policy
+  EndorsementPolicyNotSatisfied,
+  /// returned if the genesis block carries a malformed access policy
+  InvalidAccessPolicy,
+  /// returned if the ledger's access policy does not permit the request
+  AccessDenied,
+  /// returned if the provided view height is not in the view ledger
+  InvalidViewHeight,
+  /// returned if the ledger had no endorsed entry as of the provided view height
+  NoEntryAsOfView,
+  /// returned if the ledger store has no ledger with the provided handle
+  UnknownLedger,
+  /// returned if the expected height of an append does not follow the tail in the ledger store
+  HeightMismatch,
+  /// returned if a batch of appends carries no blocks
+  EmptyBatch,
+  /// returned if an endorser to readmit is not an active endorser of the current view
+  EndorserNotInView,
+  /// returned if an endorser could not be brought up to date with the ledger store
+  FailedToCatchUpEndorser,
+  /// returned if an endorser's attestation does not verify
+  InvalidEndorserAttestation,
+  /// returned if a consistency token is malformed or names another ledger or entry
+  InvalidConsistencyToken,
+  /// returned if the ledger store does not reach the entry of a consistency token in time
+  StaleRead,
+  /// returned if a snapshot to prove an entry against is a snapshot of another ledger
+  SnapshotOfAnotherLedger,
+  /// returned if an appended block is larger than the deployment allows
+  BlockTooLarge,
+  /// returned if an appended block starts like an envelope but is not a well-formed one
+  InvalidBlockEnvelope,
+  /// returned if an appended block is not an envelope of a content type the deployment allows
+  ContentTypeNotAllowed,
+  /// returned if a chunked append or a blob read reaches a coordinator without a blob store
+  BlobStoreNotConfigured,
+  /// returned if the payload of a chunked append is larger than the blob store accepts
+  BlobTooLarge,
+  /// returned if the blob store fails to store or return a blob
+  FailedToCallBlobStore,
+  /// returned if a plain append carries a blob reference, or a blob read names an entry without one
+  InvalidBlobReference,
+  /// returned if the blob store has no intact copy of a referenced blob
+  BlobNotFound,
+  /// returned if a plain append carries an external block, or a hash-only append a hash that is
+  /// not a digest
+  InvalidExternalBlock,
+  /// returned if an append carries a client request ID but the ledger store cannot deduplicate
+  ClientRequestIdsNotSupported,
+  /// returned if a client request ID is longer than the coordinator accepts
+  InvalidClientRequestId,
+  /// returned if another append with the same client request ID has not completed yet
+  AppendInProgress,
+  /// returned if a client request ID was already used to append a different block
+  ClientRequestIdReused,
+  /// returned if an endorser fails to generate a new key
+  FailedToRotateKey,
+  /// returned if an endorser's key handover is malformed or not signed by its key in the view
+  InvalidKeyHandover,
+  /// returned if misbehavior evidence does not convict an endorser of the current view
+  InvalidMisbehaviorEvidence,
+  /// returned if a ledger label is longer than the coordinator accepts
+  InvalidLabel,
+  /// returned if a ledger label is already bound to another ledger
+  LabelAlreadyTaken,
+  /// returned if no ledger is bound to a label
+  UnknownLabel,
+  /// returned if a ledger is created with a label but the ledger store keeps no labels
+  LabelsNotSupported,
+  /// returned if the ledgers of namespaces are listed but the ledger store cannot list them
+  NamespacesNotSupported,
+  /// returned if the auth file cannot be read
+  FailedToReadAuthConfig,
+  /// returned if the auth file is not a valid auth policy
+  InvalidAuthConfig,
+  /// returned if a request carries no credentials, or credentials that are not valid
+  Unauthenticated,
+  /// returned if a request's credentials do not grant what the request does
+  PermissionDenied,
+  /// returned if a request's credentials do not reach the namespace of the ledger it names
+  NamespaceNotPermitted,
+  /// returned if the lease of the endorsers of the current view has expired and awaits renewal
+  EndorserLeaseExpired,
+  /// returned if a view change was abandoned before its block was appended, leaving the current
+  /// view in place
+  ViewChangeAborted,
 }
@@ -0,0 +1,660 @@
+//! A JSON facade over the coordinator's Call service, for clients that cannot speak gRPC. Each
+//! route calls the gRPC handler of the same name, so access policies, maintenance mode, and
+//! conditional appends behave as they do over gRPC. The JSON encoding is documented with the
+//! messages in coordinator.proto: bytes fields are URL-safe base64 without padding, which is also
+//! how handles appear in paths, and integer fields are JSON numbers. HTTP headers are passed to
+//! the handlers as gRPC metadata, so a request ID or a client's credentials travel in the same
+//! headers as over gRPC. With an auth file, each route needs the permission of its gRPC method,
+//! and reaches the namespaces of the request's credentials only. Creations and appends count
+//! against the same per-client limits as over gRPC.
+use crate::{
+  coordinator_proto::{
+    call_server::Call, AppendReq, AppendResp, InclusionProof, NewLedgerReq, NewLedgerResp,
+    ReadByIndexReq, ReadByIndexResp, ReadLatestReq, ReadLatestResp, ReadViewByIndexReq,
+    ReadViewByIndexResp, ReceiptSummary,
+  },
+  CoordinatorServiceState,
+};
+use axum::{
+  extract::{ConnectInfo, Extension, Path, Query},
+  http::{HeaderMap, StatusCode},
+  response::IntoResponse,
+  routing::get,
+  Json, Router,
+};
+use coordinator::rate_limit::{is_limited, Permit, RateLimiter};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{convert::TryFrom, net::SocketAddr, sync::Arc, time::Instant};
+use tonic::{metadata::MetadataMap, Code, Request, Status};
+
+/// A bytes field that is not valid URL-safe base64; names the field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidEncoding(pub &'static str);
+
+fn encode(bytes: &[u8]) -> String {
+  base64_url::encode(bytes)
+}
+
+fn decode(field: &'static str, value: &str) -> Result<Vec<u8>, InvalidEncoding> {
+  base64_url::decode(value).map_err(|_e| InvalidEncoding(field))
+}
+
+fn encode_all(values: &[Vec<u8>]) -> Vec<String> {
+  values.iter().map(|value| encode(value)).collect()
+}
+
+fn decode_all(field: &'static str, values: &[String]) -> Result<Vec<Vec<u8>>, InvalidEncoding> {
+  values.iter().map(|value| decode(field, value)).collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewLedgerBody {
+  pub block: String,
+  #[serde(default)]
+  pub label: String,
+  #[serde(default)]
+  pub namespace: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendBody {
+  pub block: String,
+  #[serde(default)]
+  pub expected_height: u64,
+  #[serde(default)]
+  pub client_pk: String,
+  #[serde(default)]
+  pub client_signature: String,
+  #[serde(default)]
+  pub client_request_id: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NonceQuery {
+  #[serde(default)]
+  pub nonce: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadLatestQuery {
+  #[serde(default)]
+  pub nonce: String,
+  #[serde(default)]
+  pub consistency_token: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptSummaryJson {
+  pub signers: Vec<String>,
+  pub quorum_verified: bool,
+  pub lagging: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewLedgerJson {
+  pub receipts: String,
+  #[serde(default)]
+  pub handle: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendJson {
+  pub hash_nonces: String,
+  pub receipts: String,
+  pub summary: Option<ReceiptSummaryJson>,
+  pub consistency_token: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadLatestJson {
+  pub block: String,
+  pub nonces: String,
+  pub receipts: String,
+  pub timestamp: u64,
+  pub summary: Option<ReceiptSummaryJson>,
+  pub nonce: String,
+  pub statement: String,
+  pub external: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProofJson {
+  pub metablock: String,
+  pub block_hashes: Vec<String>,
+  pub tail_block: String,
+  pub tail_nonces: String,
+  pub tail_receipts: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadByIndexJson {
+  pub block: String,
+  pub nonces: String,
+  pub receipts: String,
+  pub timestamp: u64,
+  pub proof: Option<InclusionProofJson>,
+  pub external: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadViewByIndexJson {
+  pub block: String,
+  pub receipts: String,
+  pub timestamp: u64,
+}
+
+impl From<ReceiptSummary> for ReceiptSummaryJson {
+  fn from(summary: ReceiptSummary) -> Self {
+    ReceiptSummaryJson {
+      signers: encode_all(&summary.signers),
+      quorum_verified: summary.quorum_verified,
+      lagging: encode_all(&summary.lagging),
+    }
+  }
+}
+
+impl TryFrom<ReceiptSummaryJson> for ReceiptSummary {
+  type Error = InvalidEncoding;
+
+  fn try_from(summary: ReceiptSummaryJson) -> Result<Self, Self::Error> {
+    Ok(ReceiptSummary {
+      signers: decode_all("signers", &summary.signers)?,
+      quorum_verified: summary.quorum_verified,
+      lagging: decode_all("lagging", &summary.lagging)?,
+    })
+  }
+}
+
+impl From<NewLedgerResp> for NewLedgerJson {
+  fn from(resp: NewLedgerResp) -> Self {
+    NewLedgerJson {
+      receipts: encode(&resp.receipts),
+      handle: encode(&resp.handle),
+    }
+  }
+}
+
+impl TryFrom<NewLedgerJson> for NewLedgerResp {
+  type Error = InvalidEncoding;
+
+  fn try_from(resp: NewLedgerJson) -> Result<Self, Self::Error> {
+    Ok(NewLedgerResp {
+      receipts: decode("receipts", &resp.receipts)?,
+      handle: decode("handle", &resp.handle)?,
+    })
+  }
+}
+
+impl From<AppendResp> for AppendJson {
+  fn from(resp: AppendResp) -> Self {
+    AppendJson {
+      hash_nonces: encode(&resp.hash_nonces),
+      receipts: encode(&resp.receipts),
+      summary: resp.summary.map(ReceiptSummaryJson::from),
+      consistency_token: encode(&resp.consistency_token),
+    }
+  }
+}
+
+impl TryFrom<AppendJson> for AppendResp {
+  type Error = InvalidEncoding;
+
+  fn try_from(resp: AppendJson) -> Result<Self, Self::Error> {
+    Ok(AppendResp {
+      hash_nonces: decode("hash_nonces", &resp.hash_nonces)?,
+      receipts: decode("receipts", &resp.receipts)?,
+      summary: resp.summary.map(ReceiptSummary::try_from).transpose()?,
+      consistency_token: decode("consistency_token", &resp.consistency_token)?,
+    })
+  }
+}
+
+impl From<ReadLatestResp> for ReadLatestJson {
+  fn from(resp: ReadLatestResp) -> Self {
+    ReadLatestJson {
+      block: encode(&resp.block),
+      nonces: encode(&resp.nonces),
+      receipts: encode(&resp.receipts),
+      timestamp: resp.timestamp,
+      summary: resp.summary.map(ReceiptSummaryJson::from),
+      nonce: encode(&resp.nonce),
+      statement: encode(&resp.statement),
+      external: resp.external,
+    }
+  }
+}
+
+impl TryFrom<ReadLatestJson> for ReadLatestResp {
+  type Error = InvalidEncoding;
+
+  fn try_from(resp: ReadLatestJson) -> Result<Self, Self::Error> {
+    Ok(ReadLatestResp {
+      block: decode("block", &resp.block)?,
+      nonces: decode("nonces", &resp.nonces)?,
+      receipts: decode("receipts", &resp.receipts)?,
+      timestamp: resp.timestamp,
+      summary: resp.summary.map(ReceiptSummary::try_from).transpose()?,
+      nonce: decode("nonce", &resp.nonce)?,
+      statement: decode("statement", &resp.statement)?,
+      external: resp.external,
+    })
+  }
+}
+
+impl From<InclusionProof> for InclusionProofJson {
+  fn from(proof: InclusionProof) -> Self {
+    InclusionProofJson {
+      metablock: encode(&proof.metablock),
+      block_hashes: encode_all(&proof.block_hashes),
+      tail_block: encode(&proof.tail_block),
+      tail_nonces: encode(&proof.tail_nonces),
+      tail_receipts: encode(&proof.tail_receipts),
+    }
+  }
+}
+
+impl TryFrom<InclusionProofJson> for InclusionProof {
+  type Error = InvalidEncoding;
+
+  fn try_from(proof: InclusionProofJson) -> Result<Self, Self::Error> {
+    Ok(InclusionProof {
+      metablock: decode("metablock", &proof.metablock)?,
+      block_hashes: decode_all("block_hashes", &proof.block_hashes)?,
+      tail_block: decode("tail_block", &proof.tail_block)?,
+      tail_nonces: decode("tail_nonces", &proof.tail_nonces)?,
+      tail_receipts: decode("tail_receipts", &proof.tail_receipts)?,
+    })
+  }
+}
+
+impl From<ReadByIndexResp> for ReadByIndexJson {
+  fn from(resp: ReadByIndexResp) -> Self {
+    ReadByIndexJson {
+      block: encode(&resp.block),
+      nonces: encode(&resp.nonces),
+      receipts: encode(&resp.receipts),
+      timestamp: resp.timestamp,
+      proof: resp.proof.map(InclusionProofJson::from),
+      external: resp.external,
+    }
+  }
+}
+
+impl TryFrom<ReadByIndexJson> for ReadByIndexResp {
+  type Error = InvalidEncoding;
+
+  fn try_from(resp: ReadByIndexJson) -> Result<Self, Self::Error> {
+    Ok(ReadByIndexResp {
+      block: decode("block", &resp.block)?,
+      nonces: decode("nonces", &resp.nonces)?,
+      receipts: decode("receipts", &resp.receipts)?,
+      timestamp: resp.timestamp,
+      proof: resp.proof.map(InclusionProof::try_from).transpose()?,
+      external: resp.external,
+    })
+  }
+}
+
+impl From<ReadViewByIndexResp> for ReadViewByIndexJson {
+  fn from(resp: ReadViewByIndexResp) -> Self {
+    ReadViewByIndexJson {
+      block: encode(&resp.block),
+      receipts: encode(&resp.receipts),
+      timestamp: resp.timestamp,
+    }
+  }
+}
+
+impl TryFrom<ReadViewByIndexJson> for ReadViewByIndexResp {
+  type Error = InvalidEncoding;
+
+  fn try_from(resp: ReadViewByIndexJson) -> Result<Self, Self::Error> {
+    Ok(ReadViewByIndexResp {
+      block: decode("block", &resp.block)?,
+      receipts: decode("receipts", &resp.receipts)?,
+      timestamp: resp.timestamp,
+    })
+  }
+}
+
+// The limits that the gateway's requests count against, shared with the gRPC server
+#[derive(Clone)]
+struct Limits(Option<Arc<RateLimiter>>);
+
+/// Returns the routes of the gateway, which serve requests with `service` and admit them with
+/// `limiter`. The server must be made with the peer address of each connection, so that clients
+/// named by their address are told apart.
+pub fn router(service: Arc<CoordinatorServiceState>, limiter: Option<Arc<RateLimiter>>) -> Router {
+  Router::new()
+    .route(
+      "/ledgers/:handle",
+      get(read_latest).put(new_ledger).post(append),
+    )
+    .route("/ledgers/:handle/entries/:index", get(read_by_index))
+    .route("/views/:index", get(read_view_by_index))
+    .layer(Extension(service))
+    .layer(Extension(Limits(limiter)))
+}
+
+type GatewayResponse = (StatusCode, Json<serde_json::Value>);
+
+fn http_status(code: Code) -> StatusCode {
+  match code {
+    Code::Ok => StatusCode::OK,
+    Code::InvalidArgument | Code::OutOfRange => StatusCode::BAD_REQUEST,
+    Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+    Code::PermissionDenied => StatusCode::FORBIDDEN,
+    Code::NotFound => StatusCode::NOT_FOUND,
+    Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+    Code::FailedPrecondition => StatusCode::PRECONDITION_FAILED,
+    Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+    Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+    Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+    Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+    _ => StatusCode::INTERNAL_SERVER_ERROR,
+  }
+}
+
+fn error_response(status: Status) -> GatewayResponse {
+  let mut error = json!({ "error": status.message() });
+  // a conditional append that lost a race carries the height of the ledger's tail
+  if status.code() == Code::FailedPrecondition && status.details().len() == 8 {
+    let mut height = [0u8; 8];
+    height.copy_from_slice(status.details());
+    error["height"] = json!(u64::from_le_bytes(height));
+  }
+  (http_status(status.code()), Json(error))
+}
+
+fn invalid_encoding(InvalidEncoding(field): InvalidEncoding) -> GatewayResponse {
+  error_response(Status::invalid_argument(format!(
+    "The {} is not URL-safe base64",
+    field
+  )))
+}
+
+fn with_headers<T>(message: T, headers: HeaderMap) -> Request<T> {
+  let mut request = Request::new(message);
+  *request.metadata_mut() = MetadataMap::from_headers(headers);
+  request
+}
+
+// Builds the request for the gRPC handler of `method`, checking it as the gRPC server's layers
+// do: its credentials must permit it, and the namespaces they reach are recorded in the request,
+// and it counts against the limits of its client, named by its headers or by `peer`, while the
+// returned permit is held
+fn admit<T>(
+  service: &CoordinatorServiceState,
+  Limits(limiter): &Limits,
+  peer: Option<ConnectInfo<SocketAddr>>,
+  method: &str,
+  message: T,
+  headers: HeaderMap,
+) -> Result<(Request<T>, Option<Permit>), GatewayResponse> {
+  let scope = match &service.authenticator {
+    Some(authenticator) => authenticator
+      .check(method, &headers)
+      .map_err(error_response)?,
+    None => None,
+  };
+  let permit = match limiter {
+    Some(limiter) if is_limited(method) => {
+      let peer = peer.map(|ConnectInfo(addr)| addr.ip());
+      match limiter.admit(&limiter.client_named(&headers, peer), Instant::now()) {
+        Ok(permit) => Some(permit),
+        Err(rejection) => {
+          let (code, Json(mut error)) = error_response(rejection.to_status());
+          error["retry_after_ms"] = json!(rejection.retry_after().as_millis() as u64);
+          return Err((code, Json(error)));
+        },
+      }
+    },
+    _ => None,
+  };
+  let mut request = with_headers(message, headers);
+  if let Some(scope) = scope {
+    request.extensions_mut().insert(scope);
+  }
+  Ok((request, permit))
+}
+
+fn ok_response<T: Serialize>(resp: T) -> GatewayResponse {
+  (StatusCode::OK, Json(json!(resp)))
+}
+
+async fn new_ledger(
+  Path(handle): Path<String>,
+  headers: HeaderMap,
+  Json(body): Json<NewLedgerBody>,
+  Extension(service): Extension<Arc<CoordinatorServiceState>>,
+  Extension(limits): Extension<Limits>,
+  peer: Option<ConnectInfo<SocketAddr>>,
+) -> impl IntoResponse {
+  let req = match (decode("handle", &handle), decode("block", &body.block)) {
+    (Ok(handle), Ok(block)) => NewLedgerReq {
+      handle,
+      block,
+      label: body.label,
+      namespace: body.namespace,
+      client_nonce: Vec::new(),
+    },
+    (Err(error), _) | (_, Err(error)) => return invalid_encoding(error),
+  };
+  let method = "/coordinator_proto.Call/NewLedger";
+  let (req, _permit) = match admit(&service, &limits, peer, method, req, headers) {
+    Ok(admitted) => admitted,
+    Err(response) => return response,
+  };
+  match service.new_ledger(req).await {
+    Ok(resp) => ok_response(NewLedgerJson::from(resp.into_inner())),
+    Err(status) => error_response(status),
+  }
+}
+
+fn append_req(handle: &str, body: AppendBody) -> Result<AppendReq, InvalidEncoding> {
+  Ok(AppendReq {
+    handle: decode("handle", handle)?,
+    block: decode("block", &body.block)?,
+    expected_height: body.expected_height,
+    client_pk: decode("client_pk", &body.client_pk)?,
+    client_signature: decode("client_signature", &body.client_signature)?,
+    client_request_id: decode("client_request_id", &body.client_request_id)?,
+  })
+}
+
+async fn append(
+  Path(handle): Path<String>,
+  headers: HeaderMap,
+  Json(body): Json<AppendBody>,
+  Extension(service): Extension<Arc<CoordinatorServiceState>>,
+  Extension(limits): Extension<Limits>,
+  peer: Option<ConnectInfo<SocketAddr>>,
+) -> impl IntoResponse {
+  let req = match append_req(&handle, body) {
+    Ok(req) => req,
+    Err(error) => return invalid_encoding(error),
+  };
+  let method = "/coordinator_proto.Call/Append";
+  let (req, _permit) = match admit(&service, &limits, peer, method, req, headers) {
+    Ok(admitted) => admitted,
+    Err(response) => return response,
+  };
+  match service.append(req).await {
+    Ok(resp) => ok_response(AppendJson::from(resp.into_inner())),
+    Err(status) => error_response(status),
+  }
+}
+
+async fn read_latest(
+  Path(handle): Path<String>,
+  Query(query): Query<ReadLatestQuery>,
+  headers: HeaderMap,
+  Extension(service): Extension<Arc<CoordinatorServiceState>>,
+  Extension(limits): Extension<Limits>,
+  peer: Option<ConnectInfo<SocketAddr>>,
+) -> impl IntoResponse {
+  let req = match (
+    decode("handle", &handle),
+    decode("nonce", &query.nonce),
+    decode("consistency_token", &query.consistency_token),
+  ) {
+    (Ok(handle), Ok(nonce), Ok(consistency_token)) => ReadLatestReq {
+      handle,
+      nonce,
+      consistency_token,
+    },
+    (Err(error), ..) | (_, Err(error), _) | (.., Err(error)) => return invalid_encoding(error),
+  };
+  let method = "/coordinator_proto.Call/ReadLatest";
+  let (req, _permit) = match admit(&service, &limits, peer, method, req, headers) {
+    Ok(admitted) => admitted,
+    Err(response) => return response,
+  };
+  match service.read_latest(req).await {
+    Ok(resp) => ok_response(ReadLatestJson::from(resp.into_inner())),
+    Err(status) => error_response(status),
+  }
+}
+
+async fn read_by_index(
+  Path((handle, index)): Path<(String, u64)>,
+  Query(query): Query<NonceQuery>,
+  headers: HeaderMap,
+  Extension(service): Extension<Arc<CoordinatorServiceState>>,
+  Extension(limits): Extension<Limits>,
+  peer: Option<ConnectInfo<SocketAddr>>,
+) -> impl IntoResponse {
+  let req = match (decode("handle", &handle), decode("nonce", &query.nonce)) {
+    (Ok(handle), Ok(nonce)) => ReadByIndexReq {
+      handle,
+      index,
+      nonce,
+      snapshot: 0,
+    },
+    (Err(error), _) | (_, Err(error)) => return invalid_encoding(error),
+  };
+  let method = "/coordinator_proto.Call/ReadByIndex";
+  let (req, _permit) = match admit(&service, &limits, peer, method, req, headers) {
+    Ok(admitted) => admitted,
+    Err(response) => return response,
+  };
+  match service.read_by_index(req).await {
+    Ok(resp) => ok_response(ReadByIndexJson::from(resp.into_inner())),
+    Err(status) => error_response(status),
+  }
+}
+
+async fn read_view_by_index(
+  Path(index): Path<u64>,
+  headers: HeaderMap,
+  Extension(service): Extension<Arc<CoordinatorServiceState>>,
+  Extension(limits): Extension<Limits>,
+  peer: Option<ConnectInfo<SocketAddr>>,
+) -> impl IntoResponse {
+  let req = ReadViewByIndexReq { index };
+  let method = "/coordinator_proto.Call/ReadViewByIndex";
+  let (req, _permit) = match admit(&service, &limits, peer, method, req, headers) {
+    Ok(admitted) => admitted,
+    Err(response) => return response,
+  };
+  match service.read_view_by_index(req).await {
+    Ok(resp) => ok_response(ReadViewByIndexJson::from(resp.into_inner())),
+    Err(status) => error_response(status),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde::de::DeserializeOwned;
+
+  // converts a response to JSON text and back
+  fn round_trip<P, J>(resp: P) -> P
+  where
+    J: From<P> + Serialize + DeserializeOwned,
+    P: TryFrom<J, Error = InvalidEncoding>,
+  {
+    let text = serde_json::to_string(&J::from(resp)).unwrap();
+    P::try_from(serde_json::from_str::<J>(&text).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn test_json_round_trip() {
+    let summary = ReceiptSummary {
+      signers: vec![vec![1u8; 33], vec![2u8; 33]],
+      quorum_verified: true,
+      lagging: vec![vec![3u8; 33]],
+    };
+    let resp = NewLedgerResp {
+      receipts: vec![0xfb, 0xff, 0x00],
+      handle: vec![1, 2],
+    };
+    assert_eq!(round_trip::<_, NewLedgerJson>(resp.clone()), resp);
+    let resp = AppendResp {
+      hash_nonces: vec![4u8; 32],
+      receipts: vec![5u8; 100],
+      summary: Some(summary.clone()),
+      consistency_token: vec![7u8; 73],
+    };
+    assert_eq!(round_trip::<_, AppendJson>(resp.clone()), resp);
+    let resp = ReadLatestResp {
+      block: b"block".to_vec(),
+      nonces: vec![],
+      receipts: vec![6u8; 10],
+      timestamp: 1_650_000_000_000,
+      summary: Some(summary),
+      nonce: vec![9u8; 16],
+      statement: vec![2u8; 145],
+      external: false,
+    };
+    assert_eq!(round_trip::<_, ReadLatestJson>(resp.clone()), resp);
+    let resp = ReadByIndexResp {
+      block: b"block".to_vec(),
+      nonces: vec![7u8; 16],
+      receipts: vec![8u8; 10],
+      timestamp: 0,
+      proof: Some(InclusionProof {
+        metablock: vec![9u8; 72],
+        block_hashes: vec![vec![10u8; 32], vec![11u8; 32]],
+        tail_block: b"tail".to_vec(),
+        tail_nonces: vec![],
+        tail_receipts: vec![12u8; 10],
+      }),
+      external: true,
+    };
+    assert_eq!(round_trip::<_, ReadByIndexJson>(resp.clone()), resp);
+    let resp = ReadViewByIndexResp {
+      block: b"view".to_vec(),
+      receipts: vec![13u8; 10],
+      timestamp: 7,
+    };
+    assert_eq!(round_trip::<_, ReadViewByIndexJson>(resp.clone()), resp);
+
+    // bytes are URL-safe base64 without padding, and other encodings are rejected
+    let json = serde_json::to_value(NewLedgerJson::from(NewLedgerResp {
+      receipts: vec![0xfb, 0xff],
+      handle: vec![0xfb],
+    }))
+    .unwrap();
+    assert_eq!(json, json!({ "receipts": "-_8", "handle": "-w" }));
+    let invalid = NewLedgerJson {
+      receipts: "not base64!".to_string(),
+      handle: String::new(),
+    };
+    assert_eq!(
+      NewLedgerResp::try_from(invalid),
+      Err(InvalidEncoding("receipts"))
+    );
+
+    // a lost race reports the height of the tail
+    let status = Status::with_details(
+      Code::FailedPrecondition,
+      "conflict",
+      bytes::Bytes::copy_from_slice(&5u64.to_le_bytes()),
+    );
+    let (code, Json(error)) = error_response(status);
+    assert_eq!(code, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(error["height"], json!(5));
+  }
+}
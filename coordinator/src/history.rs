@@ -0,0 +1,231 @@
+use crate::errors::CoordinatorError;
+use ledger::{
+  endorser_proto::LedgerTailMapEntry, produce_hash_of_state, CustomSerde, Handle, MetaBlock,
+  NimbleDigest, NimbleHashTrait, Receipts,
+};
+use std::collections::{HashMap, HashSet};
+use store::ledger::{BoxedLedgerStore, LedgerEntry};
+
+/// The state of every ledger at the end of a view, reconstructed from the ledger store
+#[derive(Debug, Default)]
+pub struct Checkpoint {
+  /// the height of each ledger's last entry endorsed in the view or an earlier one
+  pub tails: HashMap<Handle, usize>,
+  /// whether the reconstructed state matches a commitment that endorsers signed in the view
+  /// change that ended the view
+  pub verified: bool,
+}
+
+/// Returns the ids of views 1..=`view_height`, which are the hashes of the view ledger's
+/// metablocks; a view change that never completed carries no receipts, so it has no id
+pub async fn views_up_to(
+  store: &BoxedLedgerStore,
+  view_height: usize,
+) -> Result<HashSet<NimbleDigest>, CoordinatorError> {
+  let mut views = HashSet::new();
+  for index in 1..=view_height {
+    let res = store.read_view_ledger_by_index(index).await;
+    if let Err(error) = res {
+      eprintln!("Failed to read view {} ({:?})", index, error);
+      return Err(CoordinatorError::FailedToReadViewLedger);
+    }
+    if let Ok(metablock) = res.unwrap().get_receipts().get_metablock() {
+      views.insert(metablock.hash());
+    }
+  }
+  Ok(views)
+}
+
+// returns the metablock that endorsers signed for an entry in one of `views`
+fn endorsed_metablock(receipts: &Receipts, views: &HashSet<NimbleDigest>) -> Option<MetaBlock> {
+  receipts
+    .get()
+    .keys()
+    .find(|ex_meta_block| views.contains(ex_meta_block.get_view()))
+    .map(|ex_meta_block| ex_meta_block.get_metablock().clone())
+}
+
+/// Returns the index, contents, and metablock of the last entry of a ledger that was endorsed in
+/// one of `views`, or None if the ledger had no such entry
+pub async fn find_tail_as_of(
+  store: &BoxedLedgerStore,
+  handle: &Handle,
+  views: &HashSet<NimbleDigest>,
+) -> Result<Option<(usize, LedgerEntry, MetaBlock)>, CoordinatorError> {
+  let res = store.read_ledger_tail(handle).await;
+  if let Err(error) = res {
+    eprintln!("Failed to read the tail of a ledger ({:?})", error);
+    return Err(CoordinatorError::FailedToReadLedger);
+  }
+  let (tail, height) = res.unwrap();
+
+  // entries are endorsed in the order of their indices, so the newest match is the tail
+  let mut entry = tail;
+  for index in (0..=height).rev() {
+    if index < height {
+      let res = store.read_ledger_by_index(handle, index).await;
+      if let Err(error) = res {
+        eprintln!("Failed to read entry {} of a ledger ({:?})", index, error);
+        return Err(CoordinatorError::FailedToReadLedger);
+      }
+      entry = res.unwrap();
+    }
+    if let Some(metablock) = endorsed_metablock(entry.get_receipts(), views) {
+      return Ok(Some((index, entry, metablock)));
+    }
+  }
+  Ok(None)
+}
+
+/// Reconstructs the tails of all ledgers at the end of the view at `view_height` and checks them
+/// against the view change that followed it: endorsers sign the hash of their ledger tails when
+/// they finalize a view, and new endorsers sign the hash of the tails they are initialized with
+pub async fn reconstruct_checkpoint(
+  store: &BoxedLedgerStore,
+  view_height: usize,
+) -> Result<Checkpoint, CoordinatorError> {
+  let views = views_up_to(store, view_height).await?;
+  let res = store.read_view_ledger_by_index(view_height + 1).await;
+  if let Err(error) = res {
+    eprintln!("Failed to read view {} ({:?})", view_height + 1, error);
+    return Err(CoordinatorError::FailedToReadViewLedger);
+  }
+  let commitments = res
+    .unwrap()
+    .get_receipts()
+    .get()
+    .keys()
+    .map(|ex_meta_block| *ex_meta_block.get_view())
+    .collect::<HashSet<NimbleDigest>>();
+
+  let res = store.list_ledgers().await;
+  if let Err(error) = res {
+    eprintln!("Failed to list the ledgers ({:?})", error);
+    return Err(CoordinatorError::FailedToCallLedgerStore);
+  }
+
+  // the handles are sorted, as producing the hash of the state requires
+  let mut checkpoint = Checkpoint::default();
+  let mut ledger_tail_map = Vec::new();
+  for handle in res.unwrap() {
+    if let Some((index, _entry, metablock)) = find_tail_as_of(store, &handle, &views).await? {
+      ledger_tail_map.push(LedgerTailMapEntry {
+        handle: handle.to_bytes(),
+        height: index as u64,
+        metablock: metablock.to_bytes(),
+        block: Vec::new(),
+        nonces: Vec::new(),
+      });
+      checkpoint.tails.insert(handle, index);
+    }
+  }
+  checkpoint.verified = commitments.contains(&produce_hash_of_state(&ledger_tail_map));
+  Ok(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    Block, IdSig, Receipt,
+  };
+  use store::ledger::in_memory::InMemoryLedgerStore;
+
+  fn receipts(view: &NimbleDigest, metablock: &MetaBlock) -> Receipts {
+    let sk = PrivateKey::new();
+    let signature = sk.sign(&metablock.hash().to_bytes()).unwrap();
+    let id_sig = IdSig::new(sk.get_public_key().unwrap(), signature);
+    let mut receipts = Receipts::new();
+    receipts.add(&Receipt::new(*view, metablock.clone(), id_sig));
+    receipts
+  }
+
+  #[tokio::test]
+  async fn test_reconstruct_checkpoint() {
+    let store: BoxedLedgerStore = Box::new(InMemoryLedgerStore::new());
+
+    // view 1
+    let config = Block::new(&[1]);
+    store.append_view_ledger(&config, 1).await.unwrap();
+    let view1 = MetaBlock::new(&NimbleDigest::default(), &config.hash(), 1);
+    let r = receipts(&NimbleDigest::default(), &view1);
+    store.attach_view_ledger_receipts(1, &r).await.unwrap();
+
+    // a ledger with two entries endorsed in view 1 and one that was never endorsed
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    store
+      .create_ledger(&handle, Block::new(&[0]))
+      .await
+      .unwrap();
+    let genesis = MetaBlock::genesis(&Block::new(&[0]).hash());
+    let r = receipts(&view1.hash(), &genesis);
+    store.attach_ledger_receipts(&handle, 0, &r).await.unwrap();
+    store
+      .append_ledger(&handle, &Block::new(&[1]), 1)
+      .await
+      .unwrap();
+    let entry1 = MetaBlock::new(&genesis.hash(), &Block::new(&[1]).hash(), 1);
+    let r = receipts(&view1.hash(), &entry1);
+    store.attach_ledger_receipts(&handle, 1, &r).await.unwrap();
+    store
+      .append_ledger(&handle, &Block::new(&[2]), 2)
+      .await
+      .unwrap();
+
+    // view 2, whose view change commits to the tails at the end of view 1
+    let config = Block::new(&[2]);
+    store.append_view_ledger(&config, 2).await.unwrap();
+    let view2 = MetaBlock::new(&view1.hash(), &config.hash(), 2);
+    let tail_map = vec![LedgerTailMapEntry {
+      handle: handle.to_bytes(),
+      height: 1,
+      metablock: entry1.to_bytes(),
+      block: Vec::new(),
+      nonces: Vec::new(),
+    }];
+    let r = receipts(&produce_hash_of_state(&tail_map), &view2);
+    store.attach_view_ledger_receipts(2, &r).await.unwrap();
+
+    // the unendorsed entry is endorsed in view 2
+    let entry2 = MetaBlock::new(&entry1.hash(), &Block::new(&[2]).hash(), 2);
+    let r = receipts(&view2.hash(), &entry2);
+    store.attach_ledger_receipts(&handle, 2, &r).await.unwrap();
+
+    let views = views_up_to(&store, 1).await.unwrap();
+    assert_eq!(views.len(), 1);
+    let (index, entry, metablock) = find_tail_as_of(&store, &handle, &views)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(entry.get_block().to_bytes(), vec![1]);
+    assert_eq!(metablock, entry1);
+    let views = views_up_to(&store, 2).await.unwrap();
+    let (index, _entry, _metablock) = find_tail_as_of(&store, &handle, &views)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(index, 2);
+    assert!(find_tail_as_of(&store, &handle, &HashSet::new())
+      .await
+      .unwrap()
+      .is_none());
+
+    let checkpoint = reconstruct_checkpoint(&store, 1).await.unwrap();
+    assert!(checkpoint.verified);
+    assert_eq!(checkpoint.tails.get(&handle), Some(&1));
+
+    // a ledger the endorsers did not know about at the end of view 1 breaks the match
+    let other = NimbleDigest::digest("other".as_bytes());
+    store.create_ledger(&other, Block::new(&[0])).await.unwrap();
+    let r = receipts(&view1.hash(), &genesis);
+    store.attach_ledger_receipts(&other, 0, &r).await.unwrap();
+    let checkpoint = reconstruct_checkpoint(&store, 1).await.unwrap();
+    assert!(!checkpoint.verified);
+    assert_eq!(checkpoint.tails.len(), 2);
+
+    // there is no view change after the last view yet
+    assert!(reconstruct_checkpoint(&store, 2).await.is_err());
+  }
+}
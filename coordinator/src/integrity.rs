@@ -0,0 +1,110 @@
+use serde::Serialize;
+use std::time::Instant;
+use store::ledger::{
+  current_timestamp,
+  verify::{audit_store, AuditIssue},
+  LedgerStore,
+};
+
+/// What a check of the integrity of the ledger store found
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct IntegrityReport {
+  /// time (ms since epoch) when the check started
+  pub started: u64,
+  /// how long the check took, in ms
+  pub duration_ms: u64,
+  /// the number of views in the view ledger
+  pub views: usize,
+  /// the number of ledgers checked
+  pub ledgers: usize,
+  /// the number of entries checked across the ledgers
+  pub entries: usize,
+  /// the entries, ledgers, and views flagged as corrupted, in the view ledger first and then
+  /// ledger by ledger
+  pub issues: Vec<AuditIssue>,
+}
+
+impl IntegrityReport {
+  pub fn passed(&self) -> bool {
+    self.issues.is_empty()
+  }
+}
+
+/// Checks every ledger in `store` the way an offline audit does: the view ledger is replayed from
+/// the tail vouched for by `attestations`, the metablock of each entry must extend the one before
+/// it, and the receipts of each endorsed entry must verify against the views. The check goes on
+/// past the entries it flags, so the report holds every issue.
+pub async fn check_store(
+  store: &(dyn LedgerStore + Send + Sync),
+  attestations: &[u8],
+) -> IntegrityReport {
+  let started = current_timestamp();
+  let start = Instant::now();
+  let audit = audit_store(store, attestations, None).await;
+  IntegrityReport {
+    started,
+    duration_ms: start.elapsed().as_millis() as u64,
+    views: audit.views,
+    ledgers: audit.ledgers.len(),
+    entries: audit
+      .ledgers
+      .iter()
+      .map(|ledger| ledger.report.height + 1)
+      .sum(),
+    issues: audit.issues,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    Block, IdSig, MetaBlock, NimbleDigest, NimbleHashTrait, Receipt, Receipts,
+  };
+  use store::ledger::{in_memory::InMemoryLedgerStore, verify::IssueKind};
+
+  #[tokio::test]
+  async fn test_check_store() {
+    let store = InMemoryLedgerStore::new();
+    let report = check_store(&store, &[]).await;
+    assert_eq!((report.views, report.ledgers, report.entries), (0, 0, 0));
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, IssueKind::EmptyViewLedger);
+
+    let genesis_block = Block::new(&[1, 2, 3]);
+    let handle = genesis_block.hash();
+    store.create_ledger(&handle, genesis_block).await.unwrap();
+    store
+      .append_ledger(&handle, &Block::new(&[1]), 1)
+      .await
+      .unwrap();
+
+    // a receipt on the tail that no view vouches for, as if the store had been tampered with
+    let sk = PrivateKey::new();
+    let entry = store.read_ledger_by_index(&handle, 1).await.unwrap();
+    let metablock = MetaBlock::new(&NimbleDigest::default(), &entry.get_block().hash(), 1);
+    let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+    let mut receipts = Receipts::new();
+    receipts.add(&Receipt::new(
+      NimbleDigest::digest("view".as_bytes()),
+      metablock,
+      IdSig::new(sk.get_public_key().unwrap(), sig),
+    ));
+    store
+      .attach_ledger_receipts(&handle, 1, &receipts)
+      .await
+      .unwrap();
+
+    let report = check_store(&store, &[]).await;
+    assert!(!report.passed());
+    assert_eq!((report.ledgers, report.entries), (1, 2));
+    let flagged = report
+      .issues
+      .iter()
+      .filter(|issue| issue.ledger.is_some())
+      .map(|issue| (issue.index, issue.kind))
+      .collect::<Vec<_>>();
+    assert_eq!(flagged, vec![(Some(1), IssueKind::InvalidReceipts)]);
+  }
+}
@@ -0,0 +1,179 @@
+use ledger::Handle;
+use std::{
+  collections::HashMap,
+  sync::RwLock,
+  time::{Duration, Instant},
+};
+use store::ledger::current_timestamp;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Operational metrics of a ledger, tracked in memory since the coordinator started
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LedgerStats {
+  /// height of the ledger after the last append (0 after creation)
+  pub height: usize,
+  /// number of appends since the coordinator started
+  pub appends: u64,
+  /// appends per second over the last minute
+  pub append_rate: f64,
+  /// number of endorser signatures in the last receipt the coordinator assembled
+  pub last_quorum_size: usize,
+  /// time (ms since epoch) of the last create, append, or read of the tail
+  pub last_activity: u64,
+}
+
+struct LedgerStatsEntry {
+  handle_bytes: Vec<u8>,
+  stats: LedgerStats,
+  // sliding window counter: appends in the current window and in the one before it
+  window_start: Instant,
+  window_count: u64,
+  prev_window_count: u64,
+}
+
+impl LedgerStatsEntry {
+  fn new(handle_bytes: &[u8], now: Instant) -> Self {
+    LedgerStatsEntry {
+      handle_bytes: handle_bytes.to_vec(),
+      stats: LedgerStats::default(),
+      window_start: now,
+      window_count: 0,
+      prev_window_count: 0,
+    }
+  }
+
+  fn advance_window(&mut self, now: Instant) {
+    let elapsed = now.saturating_duration_since(self.window_start);
+    if elapsed < RATE_WINDOW {
+      return;
+    }
+    self.prev_window_count = if elapsed < 2 * RATE_WINDOW {
+      self.window_count
+    } else {
+      0
+    };
+    self.window_count = 0;
+    let windows = (elapsed.as_secs_f64() / RATE_WINDOW.as_secs_f64()).floor();
+    self.window_start += RATE_WINDOW.mul_f64(windows);
+  }
+
+  fn snapshot(&mut self, now: Instant) -> LedgerStats {
+    self.advance_window(now);
+    let elapsed = now
+      .saturating_duration_since(self.window_start)
+      .as_secs_f64();
+    let window = RATE_WINDOW.as_secs_f64();
+    let weight = 1.0 - elapsed / window;
+    let mut stats = self.stats.clone();
+    stats.append_rate =
+      (self.prev_window_count as f64 * weight + self.window_count as f64) / window;
+    stats
+  }
+}
+
+/// Tracks per-ledger metrics so that operators can spot stuck or abnormally hot ledgers
+#[derive(Default)]
+pub struct LedgerStatsTracker {
+  ledgers: RwLock<HashMap<Handle, LedgerStatsEntry>>,
+}
+
+impl LedgerStatsTracker {
+  /// records that the ledger reached `height` with a receipt signed by `quorum_size` endorsers
+  pub fn record_write(
+    &self,
+    handle: &Handle,
+    handle_bytes: &[u8],
+    height: usize,
+    quorum_size: usize,
+  ) {
+    let now = Instant::now();
+    if let Ok(mut ledgers) = self.ledgers.write() {
+      let entry = ledgers
+        .entry(*handle)
+        .or_insert_with(|| LedgerStatsEntry::new(handle_bytes, now));
+      if height > 0 {
+        entry.advance_window(now);
+        entry.window_count += 1;
+        entry.stats.appends += 1;
+      }
+      if height >= entry.stats.height {
+        entry.stats.height = height;
+      }
+      entry.stats.last_quorum_size = quorum_size;
+      entry.stats.last_activity = current_timestamp();
+    }
+  }
+
+  pub fn record_read(&self, handle: &Handle, handle_bytes: &[u8]) {
+    let now = Instant::now();
+    if let Ok(mut ledgers) = self.ledgers.write() {
+      let entry = ledgers
+        .entry(*handle)
+        .or_insert_with(|| LedgerStatsEntry::new(handle_bytes, now));
+      entry.stats.last_activity = current_timestamp();
+    }
+  }
+
+  pub fn get(&self, handle: &Handle) -> Option<LedgerStats> {
+    let now = Instant::now();
+    let mut ledgers = self.ledgers.write().ok()?;
+    ledgers.get_mut(handle).map(|entry| entry.snapshot(now))
+  }
+
+  /// returns the metrics of every ledger that saw activity, keyed by the client's handle bytes
+  pub fn get_all(&self) -> Vec<(Vec<u8>, LedgerStats)> {
+    let now = Instant::now();
+    match self.ledgers.write() {
+      Ok(mut ledgers) => ledgers
+        .values_mut()
+        .map(|entry| (entry.handle_bytes.clone(), entry.snapshot(now)))
+        .collect(),
+      Err(_) => Vec::new(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::NimbleDigest;
+
+  #[test]
+  fn test_ledger_stats() {
+    let tracker = LedgerStatsTracker::default();
+    let handle_bytes = "handle".as_bytes();
+    let handle = NimbleDigest::digest(handle_bytes);
+    assert!(tracker.get(&handle).is_none());
+
+    tracker.record_write(&handle, handle_bytes, 0, 3);
+    let stats = tracker.get(&handle).unwrap();
+    assert_eq!(stats.height, 0);
+    assert_eq!(stats.appends, 0);
+    assert_eq!(stats.last_quorum_size, 3);
+    assert!(stats.last_activity > 0);
+
+    for height in 1..=30 {
+      tracker.record_write(&handle, handle_bytes, height, 2);
+    }
+    let stats = tracker.get(&handle).unwrap();
+    assert_eq!(stats.height, 30);
+    assert_eq!(stats.appends, 30);
+    assert_eq!(stats.last_quorum_size, 2);
+    assert!((stats.append_rate - 0.5).abs() < 1e-9);
+
+    // the rate decays as appends leave the window
+    {
+      let mut ledgers = tracker.ledgers.write().unwrap();
+      let entry = ledgers.get_mut(&handle).unwrap();
+      let later = entry.window_start + RATE_WINDOW + RATE_WINDOW / 2;
+      assert!((entry.snapshot(later).append_rate - 0.25).abs() < 1e-9);
+      let much_later = entry.window_start + 3 * RATE_WINDOW;
+      assert_eq!(entry.snapshot(much_later).append_rate, 0.0);
+    }
+
+    let all = tracker.get_all();
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].0, handle_bytes.to_vec());
+  }
+}
@@ -0,0 +1,16 @@
+//! The coordinator's state and its protocol with the endorsers, as a library for tools that run a
+//! coordinator in-process, such as the test harness in `nimble-testkit`. The coordinator binary
+//! serves it over gRPC and HTTP.
+pub mod append_pipeline;
+pub mod auth;
+pub mod consistency;
+pub mod coordinator_state;
+pub mod endorser_health;
+pub mod errors;
+mod history;
+pub mod integrity;
+pub mod ledger_stats;
+pub mod misbehavior;
+pub mod rate_limit;
+pub mod reconfigure;
+pub mod tail_cache;
@@ -1,42 +1,219 @@
-mod coordinator_state;
-mod errors;
-
-use crate::coordinator_state::CoordinatorState;
-use ledger::CustomSerde;
-use std::{collections::HashMap, sync::Arc};
-use tonic::{transport::Server, Request, Response, Status};
+mod election;
+mod gateway;
+mod replication;
+#[cfg(feature = "soak")]
+mod soak;
+
+use crate::{
+  election::{LeaderElection, LeaderForwarder},
+  replication::{replication_proto::replication_server::ReplicationServer, StandbyState},
+};
+use coordinator::{
+  auth::{AuthLayer, Authenticator, NamespaceScope},
+  consistency::ConsistencyToken,
+  coordinator_state::{
+    AdminAction, CoordinatorState, LedgerAppendNotification, ViewChangeNotification,
+    ADMIN_LEDGER_HANDLE,
+  },
+  endorser_health::{EndorserHealth, EndorserHealthReport},
+  errors::CoordinatorError,
+  ledger_stats::LedgerStats,
+  rate_limit::{ClientKey, RateLimitLayer, RateLimits},
+};
+use ledger::{
+  attestation::verifier_from_name,
+  config::args_with_config_file,
+  derive_ledger_handle,
+  errors::SecretError,
+  health::{HealthReporter, HealthServer},
+  logging::{self, request_id_from_metadata, short_id},
+  namespace::{is_valid_namespace, namespaced_handle},
+  reflection::{
+    ReflectionService, ServerReflectionServer, HEALTH_DESCRIPTOR_SET, REFLECTION_DESCRIPTOR_SET,
+  },
+  secrets::secret_provider_from_uri,
+  shutdown::{shutdown_signal, Shutdown},
+  signature::{PublicKey, PublicKeyTrait},
+  telemetry::{grpc_request_span, OtlpExporter},
+  AccessRequest, BlobReference, BlockValidation, CustomSerde, ExternalBlock, MetaBlock,
+  MisbehaviorEvidence, NimbleDigest, Receipts, CLIENT_PUBLIC_KEY_METADATA,
+  CLIENT_SIGNATURE_METADATA,
+};
+use std::{
+  collections::{HashMap, HashSet},
+  path::PathBuf,
+  sync::Arc,
+  time::Duration,
+};
+use store::{
+  content::open_content_store,
+  errors::{LedgerStoreError, StorageError},
+  ledger::{
+    current_timestamp, in_memory::InMemoryLedgerStore, open_ledger_store,
+    traced::TracedLedgerStore, BoxedLedgerStore, LedgerEntry, ReceiptCompaction, ReceiptRetention,
+  },
+};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{
+  metadata::MetadataMap,
+  transport::{Certificate, ClientTlsConfig, Identity, NamedService, Server, ServerTlsConfig},
+  Code, Request, Response, Status, Streaming,
+};
 
 #[allow(clippy::derive_partial_eq_without_eq)]
 pub mod coordinator_proto {
   tonic::include_proto!("coordinator_proto");
 }
 
+/// The descriptors of the coordinator's services, which it serves with gRPC reflection
+pub const COORDINATOR_DESCRIPTOR_SET: &[u8] =
+  tonic::include_file_descriptor_set!("coordinator_descriptor");
+
 use clap::{App, Arg};
 use coordinator_proto::{
+  admin_server::{Admin, AdminServer},
   call_server::{Call, CallServer},
-  AppendReq, AppendResp, NewLedgerReq, NewLedgerResp, ReadByIndexReq, ReadByIndexResp,
-  ReadLatestReq, ReadLatestResp, ReadViewByIndexReq, ReadViewByIndexResp, ReadViewTailReq,
-  ReadViewTailResp,
+  AppendBatchReq, AppendBatchResp, AppendChunkReq, AppendHashOnlyReq, AppendReq, AppendResp,
+  CheckpointReq, CheckpointResp, DumpViewLedgerReq, DumpViewLedgerResp, EndorserHealthEntry,
+  EndorserHealthState, EnterMaintenanceReq, ExitMaintenanceReq, GetEndorserHealthReq,
+  GetEndorserHealthResp, GetLedgerByLabelReq, GetLedgerByLabelResp, GetLedgerStatsReq,
+  GetLedgerStatsResp, InclusionProof, LedgerInfo, ListLedgersReq, ListLedgersResp, MaintenanceResp,
+  NewLedgerReq, NewLedgerResp, ReadAdminLedgerReq, ReadAdminLedgerResp, ReadBlobReq, ReadBlobResp,
+  ReadByIndexReq, ReadByIndexResp, ReadCheckpointReq, ReadCheckpointResp, ReadLatestAsOfViewReq,
+  ReadLatestAsOfViewResp, ReadLatestReq, ReadLatestResp, ReadRangeReq, ReadRangeResp,
+  ReadViewByIndexReq, ReadViewByIndexResp, ReadViewTailReq, ReadViewTailResp, ReadmitEndorsersReq,
+  ReadmitEndorsersResp, ReceiptSummary, RemoveEndorsersReq, RemoveEndorsersResp,
+  ReplaceEndorsersReq, ReplaceEndorsersResp, RotateEndorserKeyReq, RotateEndorserKeyResp,
+  SubscribeReq, ViewLedgerEntry, WatchViewChangesReq, WatchViewChangesResp,
 };
 
 use axum::{
   extract::{Extension, Path},
   http::StatusCode,
   response::IntoResponse,
-  routing::get,
+  routing::{get, put},
   Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tower::ServiceBuilder;
+use tracing::{debug, info, instrument, warn};
+
+const STORE_SECRETS: [&str; 4] = [
+  "COSMOS_URL",
+  "STORAGE_ACCOUNT",
+  "STORAGE_MASTER_KEY",
+  "STORAGE_CONNECTION_STRING",
+];
+const WATCH_CHANNEL_BUFFER: usize = 4; // view changes buffered per watching client
+const BLOB_CHUNK_SIZE: usize = 1 << 20; // bytes: the chunks in which blobs are read
+const BLOB_CHANNEL_BUFFER: usize = 4; // chunks buffered per blob read
+const RANGE_PAGE_SIZE: usize = 64; // entries read from the ledger store at a time for a range
+const RANGE_CHANNEL_BUFFER: usize = 64; // entries buffered per range read
+const HEALTH_CHECK_INTERVAL: u64 = 5; // seconds between checks that a quorum of endorsers answers
+const COMPACTION_INTERVAL: u64 = 60; // seconds between compactions of the ledgers' receipts
+const MISBEHAVIOR_CHECK_INTERVAL: u64 = 5; // seconds between expulsions of equivocating endorsers
+const AUTH_RELOAD_INTERVAL: u64 = 5; // seconds between checks that the auth file changed
+const LEASE_RENEWAL_RETRY_INTERVAL: u64 = 5; // seconds before a failed lease renewal is retried
+const MAINTENANCE_MODE_MSG: &str = "The coordinator is in maintenance mode; retry later";
+const ENDORSER_LEASE_MSG: &str = "The endorsers' lease is being renewed; retry later";
+const ENDORSEMENT_POLICY_MSG: &str = "The endorsers required by the ledger's policy did not sign";
+const ACCESS_DENIED_MSG: &str = "The ledger's access policy does not permit the request";
 
 pub struct CoordinatorServiceState {
   state: Arc<CoordinatorState>,
+  // the credentials that requests which do not pass through the gRPC server's auth layer, such as
+  // those of the JSON gateway, are checked against; with them, requests whose credentials were
+  // not checked reach no namespace
+  authenticator: Option<Arc<Authenticator>>,
 }
 
 impl CoordinatorServiceState {
   pub fn new(coordinator: Arc<CoordinatorState>) -> Self {
-    CoordinatorServiceState { state: coordinator }
+    CoordinatorServiceState {
+      state: coordinator,
+      authenticator: None,
+    }
+  }
+
+  pub fn with_authenticator(mut self, authenticator: Option<Arc<Authenticator>>) -> Self {
+    self.authenticator = authenticator;
+    self
+  }
+
+  // Returns the namespaces that the request's credentials reach, as the auth layer or the gateway
+  // recorded them; without an auth file, every namespace
+  fn namespace_scope<T>(&self, request: &Request<T>) -> NamespaceScope {
+    match request.extensions().get::<NamespaceScope>() {
+      Some(scope) => scope.clone(),
+      None if self.authenticator.is_none() => NamespaceScope::All,
+      None => NamespaceScope::Only(HashSet::new()),
+    }
+  }
+
+  fn receipt_summary(&self, handle_bytes: &[u8], receipts: &Receipts) -> ReceiptSummary {
+    let (signers, quorum_verified, lagging) = self.state.summarize_receipts(handle_bytes, receipts);
+    ReceiptSummary {
+      signers,
+      quorum_verified,
+      lagging,
+    }
+  }
+
+  // A conditional append that lost a race carries the ledger's tail height, so the client can
+  // retry without reading the ledger first
+  fn append_reply(
+    &self,
+    handle_bytes: &[u8],
+    hash_nonces: &NimbleDigest,
+    receipts: &Receipts,
+  ) -> AppendResp {
+    let consistency_token = match receipts.get_metablock() {
+      Ok(metablock) => {
+        ConsistencyToken::new(&NimbleDigest::digest(handle_bytes), &metablock).to_bytes()
+      },
+      Err(_) => Vec::new(),
+    };
+    AppendResp {
+      hash_nonces: hash_nonces.to_bytes(),
+      receipts: receipts.to_bytes(),
+      summary: Some(self.receipt_summary(handle_bytes, receipts)),
+      consistency_token,
+    }
+  }
+
+  async fn append_status(
+    &self,
+    handle_bytes: &[u8],
+    error: CoordinatorError,
+    failure_msg: &str,
+  ) -> Status {
+    if error == CoordinatorError::HeightMismatch {
+      if let Ok(height) = self.state.get_ledger_height(handle_bytes).await {
+        return Status::with_details(
+          Code::FailedPrecondition,
+          "Concurrent append conflict: the expected height does not follow the ledger's tail",
+          bytes::Bytes::copy_from_slice(&(height as u64).to_le_bytes()),
+        );
+      }
+    }
+    ledger_status(error, failure_msg)
+  }
+
+  // A ledger that exists already is reported with the receipts of its genesis block, so that a
+  // client can tell whether it is the ledger it meant to create
+  async fn new_ledger_status(&self, handle_bytes: &[u8], error: CoordinatorError) -> Status {
+    if error == CoordinatorError::LedgerAlreadyExists {
+      if let Ok(entry) = self.state.read_ledger_by_index(handle_bytes, 0).await {
+        return Status::with_details(
+          Code::AlreadyExists,
+          "The ledger already exists",
+          bytes::Bytes::from(entry.get_receipts().to_bytes()),
+        );
+      }
+    }
+    ledger_status(error, "Failed to create a new ledger")
   }
 
   #[cfg(test)]
@@ -45,159 +222,1637 @@ impl CoordinatorServiceState {
   }
 }
 
+// An entry of a ledger as ReadRange and Subscribe stream it
+fn range_resp(ledger_entry: &LedgerEntry, metablock: &MetaBlock) -> ReadRangeResp {
+  ReadRangeResp {
+    index: metablock.get_height() as u64,
+    block: ledger_entry.get_block().to_bytes(),
+    nonces: ledger_entry.get_nonces().to_bytes(),
+    receipts: ledger_entry.get_receipts().to_bytes(),
+    timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+    metablock: metablock.to_bytes(),
+    external: ExternalBlock::is_external_block(&ledger_entry.get_block().to_bytes()),
+  }
+}
+
+// The public key and signature a client attaches to act under a ledger's access policy
+// Returns the ID that the client gave the request, or a new one
+fn request_id<T>(request: &Request<T>) -> String {
+  request_id_from_metadata(request.metadata()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn check_namespace(scope: &NamespaceScope, handle_bytes: &[u8]) -> Result<(), CoordinatorError> {
+  if scope.permits(handle_bytes) {
+    Ok(())
+  } else {
+    Err(CoordinatorError::NamespaceNotPermitted)
+  }
+}
+
+fn client_credentials(metadata: &MetadataMap) -> Option<(Vec<u8>, Vec<u8>)> {
+  let pk = metadata
+    .get_bin(CLIENT_PUBLIC_KEY_METADATA)?
+    .to_bytes()
+    .ok()?;
+  let sig = metadata
+    .get_bin(CLIENT_SIGNATURE_METADATA)?
+    .to_bytes()
+    .ok()?;
+  Some((pk.to_vec(), sig.to_vec()))
+}
+
+impl CoordinatorServiceState {
+  async fn authorize(
+    &self,
+    metadata: &MetadataMap,
+    handle_bytes: &[u8],
+    request: &AccessRequest<'_>,
+  ) -> Result<(), Status> {
+    self
+      .authorize_with_credentials(client_credentials(metadata), handle_bytes, request)
+      .await
+  }
+
+  async fn authorize_with_credentials(
+    &self,
+    credentials: Option<(Vec<u8>, Vec<u8>)>,
+    handle_bytes: &[u8],
+    request: &AccessRequest<'_>,
+  ) -> Result<(), Status> {
+    let res = self
+      .state
+      .authorize(
+        handle_bytes,
+        request,
+        credentials
+          .as_ref()
+          .map(|(pk, sig)| (pk.as_slice(), sig.as_slice())),
+      )
+      .await;
+    res.map_err(|error| ledger_status(error, "Failed to read the ledger's access policy"))
+  }
+}
+
+// Maps an error of a ledger operation to the status returned to the client: errors caused by the
+// request get a status that tells the client what to fix, and a failing ledger store is reported
+// as unavailable so that the client can retry
+fn ledger_status(error: CoordinatorError, failure_msg: &str) -> Status {
+  warn!(?error, "{}", failure_msg);
+  match error {
+    CoordinatorError::InMaintenanceMode => Status::unavailable(MAINTENANCE_MODE_MSG),
+    CoordinatorError::EndorserLeaseExpired => Status::unavailable(ENDORSER_LEASE_MSG),
+    CoordinatorError::EndorsementPolicyNotSatisfied => Status::unavailable(ENDORSEMENT_POLICY_MSG),
+    CoordinatorError::FailedToCallLedgerStore => {
+      Status::unavailable("The ledger store is unavailable")
+    },
+    CoordinatorError::InvalidEndorsementPolicy => {
+      Status::invalid_argument("Invalid endorsement policy")
+    },
+    CoordinatorError::InvalidAccessPolicy => Status::invalid_argument("Invalid access policy"),
+    CoordinatorError::InvalidHandle => Status::invalid_argument("The handle is reserved"),
+    CoordinatorError::EmptyBatch => Status::invalid_argument("The batch carries no blocks"),
+    CoordinatorError::InvalidNonce => Status::invalid_argument("The nonce is invalid"),
+    CoordinatorError::AccessDenied => Status::permission_denied(ACCESS_DENIED_MSG),
+    CoordinatorError::UnknownLedger => Status::not_found("The ledger does not exist"),
+    CoordinatorError::LedgerAlreadyExists => Status::already_exists("The ledger already exists"),
+    CoordinatorError::HeightMismatch => {
+      Status::failed_precondition("The expected height does not follow the ledger's tail")
+    },
+    CoordinatorError::InvalidHeight => Status::out_of_range("The height is not in the ledger"),
+    CoordinatorError::InvalidConsistencyToken => {
+      Status::invalid_argument("The consistency token is invalid")
+    },
+    CoordinatorError::BlockTooLarge => {
+      Status::resource_exhausted("The block is too large; append it in chunks")
+    },
+    CoordinatorError::BlobTooLarge => Status::resource_exhausted("The payload is too large"),
+    CoordinatorError::BlobStoreNotConfigured => {
+      Status::unimplemented("The coordinator has no blob store for chunked appends")
+    },
+    CoordinatorError::FailedToCallBlobStore => Status::unavailable("The blob store is unavailable"),
+    CoordinatorError::InvalidBlobReference => {
+      Status::invalid_argument("Blob references are only appended and read in chunks")
+    },
+    CoordinatorError::BlobNotFound => Status::not_found("The blob store does not hold the blob"),
+    CoordinatorError::InvalidExternalBlock => Status::invalid_argument(
+      "External blocks are only appended by the hash of a payload, which must be a digest",
+    ),
+    CoordinatorError::InvalidLabel => Status::invalid_argument("The label is too long"),
+    CoordinatorError::LabelAlreadyTaken => {
+      Status::already_exists("The label is bound to another ledger")
+    },
+    CoordinatorError::UnknownLabel => Status::not_found("No ledger has the label"),
+    CoordinatorError::LabelsNotSupported => {
+      Status::unimplemented("The ledger store keeps no ledger labels")
+    },
+    CoordinatorError::NamespaceNotPermitted => {
+      Status::permission_denied("The request's credentials do not reach the ledger's namespace")
+    },
+    CoordinatorError::NamespacesNotSupported => {
+      Status::unimplemented("The ledger store cannot list the ledgers of a namespace")
+    },
+    CoordinatorError::ClientRequestIdsNotSupported => {
+      Status::unimplemented("The ledger store cannot deduplicate appends by client request ID")
+    },
+    CoordinatorError::InvalidClientRequestId => {
+      Status::invalid_argument("The client request ID is empty or too long")
+    },
+    CoordinatorError::AppendInProgress => {
+      Status::aborted("An append with the client request ID is in progress")
+    },
+    CoordinatorError::ClientRequestIdReused => {
+      Status::invalid_argument("The client request ID was used to append another block")
+    },
+    CoordinatorError::InvalidBlockEnvelope => {
+      Status::invalid_argument("The block envelope is malformed")
+    },
+    CoordinatorError::ContentTypeNotAllowed => {
+      Status::invalid_argument("The block's content type is not allowed")
+    },
+    CoordinatorError::SnapshotOfAnotherLedger => {
+      Status::invalid_argument("The snapshot is of another ledger")
+    },
+    CoordinatorError::StaleRead => {
+      Status::unavailable("The ledger store has not caught up to the consistency token")
+    },
+    _ => Status::aborted(failure_msg),
+  }
+}
+
 #[tonic::async_trait]
 impl Call for CoordinatorServiceState {
+  #[instrument(
+    name = "NewLedger",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      handle = %short_id(&req.get_ref().handle)
+    )
+  )]
   async fn new_ledger(
     &self,
     req: Request<NewLedgerReq>,
   ) -> Result<Response<NewLedgerResp>, Status> {
+    let scope = self.namespace_scope(&req);
     let NewLedgerReq {
-      handle: handle_bytes,
+      handle,
       block: block_bytes,
+      label,
+      namespace,
+      client_nonce,
     } = req.into_inner();
+    if !namespace.is_empty() && !is_valid_namespace(&namespace) {
+      return Err(Status::invalid_argument("The namespace is invalid"));
+    }
+    let handle = match (handle.is_empty(), client_nonce.is_empty()) {
+      (_, true) => handle,
+      (true, false) => derive_ledger_handle(&client_nonce, &block_bytes)
+        .map_err(|_e| Status::invalid_argument("The genesis block carries a malformed policy"))?,
+      (false, false) => {
+        return Err(Status::invalid_argument(
+          "A ledger is created with a handle or with a client nonce, not both",
+        ))
+      },
+    };
+    let handle_bytes = namespaced_handle(&namespace, &handle);
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
 
-    let res = self
-      .state
-      .create_ledger(None, &handle_bytes, &block_bytes)
-      .await;
-    if res.is_err() {
-      return Err(Status::aborted("Failed to create a new ledger"));
+    let res = if label.is_empty() {
+      self
+        .state
+        .create_ledger(None, &handle_bytes, &block_bytes)
+        .await
+    } else {
+      self
+        .state
+        .create_labeled_ledger(&handle_bytes, &block_bytes, &label)
+        .await
+    };
+    if let Err(error) = res {
+      return Err(self.new_ledger_status(&handle_bytes, error).await);
     }
 
     let receipts = res.unwrap();
+    info!("created the ledger");
     let reply = NewLedgerResp {
       receipts: receipts.to_bytes(),
+      handle,
     };
     Ok(Response::new(reply))
   }
 
+  #[instrument(
+    name = "Append",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      height = request.get_ref().expected_height
+    )
+  )]
   async fn append(&self, request: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
     let AppendReq {
       handle: handle_bytes,
       block: block_bytes,
       expected_height,
+      client_pk,
+      client_signature,
+      client_request_id,
     } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    let access_request = AccessRequest::Append {
+      block: &block_bytes,
+      expected_height: expected_height as usize,
+    };
+    let credentials = client_credentials(&metadata).or(if client_pk.is_empty() {
+      None
+    } else {
+      Some((client_pk, client_signature))
+    });
+    self
+      .authorize_with_credentials(credentials, &handle_bytes, &access_request)
+      .await?;
+
+    let res = if client_request_id.is_empty() {
+      self
+        .state
+        .append_ledger(None, &handle_bytes, &block_bytes, expected_height as usize)
+        .await
+    } else {
+      self
+        .state
+        .append_ledger_once(
+          &handle_bytes,
+          &block_bytes,
+          expected_height as usize,
+          &client_request_id,
+        )
+        .await
+    };
+    if let Err(error) = res {
+      return Err(
+        self
+          .append_status(&handle_bytes, error, "Failed to append to a ledger")
+          .await,
+      );
+    }
+
+    let (hash_nonces, receipts) = res.unwrap();
+    info!(
+      signers = receipts.get_signer_ids().len(),
+      "appended to the ledger"
+    );
+    Ok(Response::new(self.append_reply(
+      &handle_bytes,
+      &hash_nonces,
+      &receipts,
+    )))
+  }
+
+  #[instrument(
+    name = "AppendChunked",
+    skip_all,
+    fields(request_id = %request_id(&request))
+  )]
+  async fn append_chunked(
+    &self,
+    request: Request<Streaming<AppendChunkReq>>,
+  ) -> Result<Response<AppendResp>, Status> {
+    let res = self.state.get_max_blob_size();
+    if let Err(error) = res {
+      return Err(ledger_status(error, "Failed to append in chunks"));
+    }
+    let max_blob_size = res.unwrap();
+
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let mut stream = request.into_inner();
+    let AppendChunkReq {
+      handle: handle_bytes,
+      expected_height,
+      client_pk,
+      client_signature,
+      chunk: mut payload,
+    } = match stream.message().await? {
+      Some(req) => req,
+      None => return Err(Status::invalid_argument("The stream carries no chunks")),
+    };
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+    while let Some(req) = stream.message().await? {
+      payload.extend_from_slice(&req.chunk);
+      if max_blob_size.is_some_and(|max| payload.len() > max) {
+        return Err(ledger_status(
+          CoordinatorError::BlobTooLarge,
+          "Failed to append in chunks",
+        ));
+      }
+    }
+
+    // the client signs the append of the block that references the payload
+    let block_bytes = BlobReference::new(&payload).to_bytes();
+    let access_request = AccessRequest::Append {
+      block: &block_bytes,
+      expected_height: expected_height as usize,
+    };
+    let credentials = client_credentials(&metadata).or(if client_pk.is_empty() {
+      None
+    } else {
+      Some((client_pk, client_signature))
+    });
+    self
+      .authorize_with_credentials(credentials, &handle_bytes, &access_request)
+      .await?;
 
     let res = self
       .state
-      .append_ledger(None, &handle_bytes, &block_bytes, expected_height as usize)
+      .append_ledger_blob(&handle_bytes, &payload, expected_height as usize)
       .await;
-    if res.is_err() {
-      return Err(Status::aborted("Failed to append to a ledger"));
+    if let Err(error) = res {
+      return Err(
+        self
+          .append_status(&handle_bytes, error, "Failed to append in chunks")
+          .await,
+      );
     }
 
     let (hash_nonces, receipts) = res.unwrap();
-    let reply = AppendResp {
-      hash_nonces: hash_nonces.to_bytes(),
+    info!(
+      size = payload.len(),
+      signers = receipts.get_signer_ids().len(),
+      "appended a blob to the ledger"
+    );
+    Ok(Response::new(self.append_reply(
+      &handle_bytes,
+      &hash_nonces,
+      &receipts,
+    )))
+  }
+
+  #[instrument(
+    name = "ReadBlob",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      index = request.get_ref().index
+    )
+  )]
+  async fn read_blob(
+    &self,
+    request: Request<ReadBlobReq>,
+  ) -> Result<Response<Self::ReadBlobStream>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let ReadBlobReq {
+      handle: handle_bytes,
+      index,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    let access_request = AccessRequest::ReadByIndex {
+      index: index as usize,
+    };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    let res = self.state.read_blob(&handle_bytes, index as usize).await;
+    if let Err(error) = res {
+      return Err(ledger_status(error, "Failed to read a blob"));
+    }
+
+    let payload = res.unwrap();
+    let (tx, rx) = mpsc::channel(BLOB_CHANNEL_BUFFER);
+    tokio::spawn(async move {
+      for chunk in payload.chunks(BLOB_CHUNK_SIZE) {
+        let resp = ReadBlobResp {
+          chunk: chunk.to_vec(),
+        };
+        if tx.send(Ok(resp)).await.is_err() {
+          break;
+        }
+      }
+    });
+    Ok(Response::new(ReceiverStream::new(rx)))
+  }
+
+  #[instrument(
+    name = "AppendHashOnly",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      height = request.get_ref().expected_height
+    )
+  )]
+  async fn append_hash_only(
+    &self,
+    request: Request<AppendHashOnlyReq>,
+  ) -> Result<Response<AppendResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let AppendHashOnlyReq {
+      handle: handle_bytes,
+      block_hash,
+      expected_height,
+      client_pk,
+      client_signature,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    // the client signs the append of the block that holds the hash
+    let block_bytes = match ExternalBlock::new(&block_hash) {
+      Ok(block) => block.to_bytes(),
+      Err(_) => {
+        return Err(ledger_status(
+          CoordinatorError::InvalidExternalBlock,
+          "Failed to append a hash",
+        ))
+      },
+    };
+    let access_request = AccessRequest::Append {
+      block: &block_bytes,
+      expected_height: expected_height as usize,
+    };
+    let credentials = client_credentials(&metadata).or(if client_pk.is_empty() {
+      None
+    } else {
+      Some((client_pk, client_signature))
+    });
+    self
+      .authorize_with_credentials(credentials, &handle_bytes, &access_request)
+      .await?;
+
+    let res = self
+      .state
+      .append_ledger_hash_only(&handle_bytes, &block_hash, expected_height as usize)
+      .await;
+    if let Err(error) = res {
+      return Err(
+        self
+          .append_status(&handle_bytes, error, "Failed to append a hash")
+          .await,
+      );
+    }
+
+    let (hash_nonces, receipts) = res.unwrap();
+    info!(
+      signers = receipts.get_signer_ids().len(),
+      "appended the hash of an external payload to the ledger"
+    );
+    Ok(Response::new(self.append_reply(
+      &handle_bytes,
+      &hash_nonces,
+      &receipts,
+    )))
+  }
+
+  #[instrument(
+    name = "AppendBatch",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      height = request.get_ref().expected_height,
+      blocks = request.get_ref().blocks.len()
+    )
+  )]
+  async fn append_batch(
+    &self,
+    request: Request<AppendBatchReq>,
+  ) -> Result<Response<AppendBatchResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let AppendBatchReq {
+      handle: handle_bytes,
+      blocks,
+      expected_height,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    let access_request = AccessRequest::AppendBatch {
+      blocks: &blocks,
+      expected_height: expected_height as usize,
+    };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    let res = self
+      .state
+      .append_ledger_batch(&handle_bytes, &blocks, expected_height as usize)
+      .await;
+    if let Err(error) = res {
+      return Err(
+        self
+          .append_status(&handle_bytes, error, "Failed to append a batch to a ledger")
+          .await,
+      );
+    }
+
+    let (entries, receipts) = res.unwrap();
+    info!(
+      signers = receipts.get_signer_ids().len(),
+      "appended a batch to the ledger"
+    );
+    let reply = AppendBatchResp {
+      hash_nonces: entries
+        .iter()
+        .map(|(hash_nonces, _metablock)| hash_nonces.to_bytes())
+        .collect(),
+      metablocks: entries
+        .iter()
+        .map(|(_hash_nonces, metablock)| metablock.to_bytes())
+        .collect(),
       receipts: receipts.to_bytes(),
+      summary: Some(self.receipt_summary(&handle_bytes, &receipts)),
     };
 
     Ok(Response::new(reply))
   }
 
+  #[instrument(
+    name = "ReadLatest",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle)
+    )
+  )]
   async fn read_latest(
     &self,
     request: Request<ReadLatestReq>,
   ) -> Result<Response<ReadLatestResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
     let ReadLatestReq {
       handle: handle_bytes,
       nonce: nonce_bytes,
+      consistency_token,
     } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    let access_request = AccessRequest::ReadLatest {
+      nonce: &nonce_bytes,
+    };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    if !consistency_token.is_empty() {
+      let res = self
+        .state
+        .wait_for_consistency_token(&handle_bytes, &consistency_token)
+        .await;
+      if let Err(error) = res {
+        return Err(ledger_status(error, "Failed to read a ledger tail"));
+      }
+    }
 
     let res = self
       .state
       .read_ledger_tail(&handle_bytes, &nonce_bytes)
       .await;
-    if res.is_err() {
-      return Err(Status::aborted("Failed to read a ledger tail"));
+    if let Err(error) = res {
+      return Err(ledger_status(error, "Failed to read a ledger tail"));
     }
 
     let ledger_entry = res.unwrap();
+    let statement =
+      self
+        .state
+        .read_latest_statement(&handle_bytes, &nonce_bytes, ledger_entry.get_receipts());
     let reply = ReadLatestResp {
       block: ledger_entry.get_block().to_bytes(),
       nonces: ledger_entry.get_nonces().to_bytes(),
       receipts: ledger_entry.get_receipts().to_bytes(),
+      timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+      summary: Some(self.receipt_summary(&handle_bytes, ledger_entry.get_receipts())),
+      nonce: nonce_bytes,
+      statement,
+      external: ExternalBlock::is_external_block(&ledger_entry.get_block().to_bytes()),
     };
 
     Ok(Response::new(reply))
   }
 
+  #[instrument(
+    name = "ReadByIndex",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      index = request.get_ref().index
+    )
+  )]
   async fn read_by_index(
     &self,
     request: Request<ReadByIndexReq>,
   ) -> Result<Response<ReadByIndexResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
     let ReadByIndexReq {
       handle: handle_bytes,
       index,
+      nonce: nonce_bytes,
+      snapshot,
     } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
 
-    match self
-      .state
-      .read_ledger_by_index(&handle_bytes, index as usize)
-      .await
-    {
-      Ok(ledger_entry) => {
+    let access_request = AccessRequest::ReadByIndex {
+      index: index as usize,
+    };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    let res = if snapshot != 0 {
+      self
+        .state
+        .read_ledger_by_index_with_snapshot(&handle_bytes, index as usize, snapshot as usize)
+        .await
+        .map(|(ledger_entry, proof)| (ledger_entry, Some(proof)))
+    } else if nonce_bytes.is_empty() {
+      self
+        .state
+        .read_ledger_by_index(&handle_bytes, index as usize)
+        .await
+        .map(|ledger_entry| (ledger_entry, None))
+    } else {
+      self
+        .state
+        .read_ledger_by_index_with_proof(&handle_bytes, index as usize, &nonce_bytes)
+        .await
+        .map(|(ledger_entry, proof)| (ledger_entry, Some(proof)))
+    };
+
+    match res {
+      Ok((ledger_entry, proof)) => {
         let reply = ReadByIndexResp {
           block: ledger_entry.get_block().to_bytes(),
           nonces: ledger_entry.get_nonces().to_bytes(),
           receipts: ledger_entry.get_receipts().to_bytes(),
+          timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+          proof: proof.map(|proof| InclusionProof {
+            metablock: proof.get_metablock().to_bytes(),
+            block_hashes: proof
+              .get_block_hashes()
+              .iter()
+              .map(|block_hash| block_hash.to_bytes())
+              .collect(),
+            tail_block: proof.get_tail_block().to_vec(),
+            tail_nonces: proof.get_tail_nonces().to_vec(),
+            tail_receipts: proof.get_tail_receipts().to_vec(),
+          }),
+          external: ExternalBlock::is_external_block(&ledger_entry.get_block().to_bytes()),
         };
         Ok(Response::new(reply))
       },
-      Err(_) => return Err(Status::aborted("Failed to read a ledger")),
+      Err(error) => Err(ledger_status(error, "Failed to read a ledger")),
     }
   }
 
-  async fn read_view_by_index(
+  #[instrument(
+    name = "ReadRange",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      start = request.get_ref().start,
+      end = request.get_ref().end
+    )
+  )]
+  async fn read_range(
     &self,
-    request: Request<ReadViewByIndexReq>,
-  ) -> Result<Response<ReadViewByIndexResp>, Status> {
-    let ReadViewByIndexReq { index } = request.into_inner();
+    request: Request<ReadRangeReq>,
+  ) -> Result<Response<Self::ReadRangeStream>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let ReadRangeReq {
+      handle: handle_bytes,
+      start,
+      end,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
 
-    let res = self.state.read_view_by_index(index as usize).await;
-    if res.is_err() {
-      return Err(Status::aborted("Failed to read the view ledger"));
+    let access_request = AccessRequest::ReadByIndex {
+      index: start as usize,
+    };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    // the range ends at the tail as of the request, even if the ledger grows while it streams
+    let res = self.state.get_ledger_height(&handle_bytes).await;
+    if let Err(error) = res {
+      return Err(ledger_status(error, "Failed to read a range of a ledger"));
     }
-
-    let ledger_entry = res.unwrap();
-    let reply = ReadViewByIndexResp {
-      block: ledger_entry.get_block().to_bytes(),
-      receipts: ledger_entry.get_receipts().to_bytes(),
+    let tail_end = res.unwrap() + 1;
+    let start = start as usize;
+    let end = if end == 0 {
+      tail_end
+    } else {
+      (end as usize).min(tail_end)
     };
+    if start >= tail_end {
+      return Err(ledger_status(
+        CoordinatorError::InvalidHeight,
+        "Failed to read a range of a ledger",
+      ));
+    }
+    if start >= end {
+      return Err(Status::invalid_argument("The range is empty"));
+    }
 
-    Ok(Response::new(reply))
+    // the next page is read only once the client has taken enough of this one to make room
+    let state = self.state.clone();
+    let (tx, rx) = mpsc::channel(RANGE_CHANNEL_BUFFER);
+    tokio::spawn(async move {
+      let mut next = start;
+      let mut prev = None;
+      while next < end {
+        let page_end = end.min(next + RANGE_PAGE_SIZE);
+        let res = state
+          .read_ledger_range(&handle_bytes, next, page_end, prev.take())
+          .await;
+        let entries = match res {
+          Ok(entries) if !entries.is_empty() => entries,
+          Ok(_) => break,
+          Err(error) => {
+            let status = ledger_status(error, "Failed to read a range of a ledger");
+            let _ = tx.send(Err(status)).await;
+            break;
+          },
+        };
+        for (ledger_entry, metablock) in entries {
+          if tx
+            .send(Ok(range_resp(&ledger_entry, &metablock)))
+            .await
+            .is_err()
+          {
+            return;
+          }
+          next = metablock.get_height() + 1;
+          prev = Some(metablock);
+        }
+      }
+    });
+    Ok(Response::new(ReceiverStream::new(rx)))
   }
 
-  async fn read_view_tail(
+  #[instrument(
+    name = "Subscribe",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      from_height = request.get_ref().from_height
+    )
+  )]
+  async fn subscribe(
     &self,
-    _request: Request<ReadViewTailReq>,
-  ) -> Result<Response<ReadViewTailResp>, Status> {
-    let res = self.state.read_view_tail().await;
-    if res.is_err() {
-      return Err(Status::aborted("Failed to read the view ledger tail"));
-    }
+    request: Request<SubscribeReq>,
+  ) -> Result<Response<Self::SubscribeStream>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let SubscribeReq {
+      handle: handle_bytes,
+      from_height,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
 
-    let (ledger_entry, height, attestation_reports) = res.unwrap();
-    let reply = ReadViewTailResp {
-      block: ledger_entry.get_block().to_bytes(),
-      receipts: ledger_entry.get_receipts().to_bytes(),
-      height: height as u64,
-      attestations: attestation_reports,
+    let access_request = AccessRequest::ReadByIndex {
+      index: from_height as usize,
     };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    // subscribe before catching up, so that no append falls between the two
+    let mut appends = self.state.subscribe_ledger_appends();
+    let res = self.state.get_ledger_height(&handle_bytes).await;
+    if let Err(error) = res {
+      return Err(ledger_status(error, "Failed to subscribe to a ledger"));
+    }
 
-    Ok(Response::new(reply))
+    let state = self.state.clone();
+    let (tx, rx) = mpsc::channel(RANGE_CHANNEL_BUFFER);
+    tokio::spawn(async move {
+      let handle = NimbleDigest::digest(&handle_bytes);
+      let mut next = from_height as usize;
+      let mut prev = None;
+      loop {
+        // catch up from the ledger store through its tail
+        loop {
+          let res = state
+            .read_ledger_range(&handle_bytes, next, next + RANGE_PAGE_SIZE, prev.take())
+            .await;
+          let entries = match res {
+            Ok(entries) => entries,
+            Err(CoordinatorError::InvalidHeight) => break, // already at the tail
+            Err(error) => {
+              let status = ledger_status(error, "Failed to subscribe to a ledger");
+              let _ = tx.send(Err(status)).await;
+              return;
+            },
+          };
+          let at_tail = entries.len() < RANGE_PAGE_SIZE;
+          let num_entries = entries.len();
+          for (i, (ledger_entry, metablock)) in entries.into_iter().enumerate() {
+            // the tail may still await its receipts; its append notification delivers it
+            if at_tail && i + 1 == num_entries && ledger_entry.get_receipts().is_empty() {
+              break;
+            }
+            if tx
+              .send(Ok(range_resp(&ledger_entry, &metablock)))
+              .await
+              .is_err()
+            {
+              return;
+            }
+            next = metablock.get_height() + 1;
+            prev = Some(metablock);
+          }
+          if at_tail {
+            break;
+          }
+        }
+
+        // then follow the appends, until one is missed and has to be read from the store
+        loop {
+          let notification = match appends.recv().await {
+            Ok(notification) => notification,
+            Err(RecvError::Lagged(_)) => break,
+            Err(RecvError::Closed) => return,
+          };
+          if notification.handle != handle || notification.index < next {
+            continue;
+          }
+          if notification.index > next {
+            break;
+          }
+          let LedgerAppendNotification {
+            block,
+            nonces,
+            receipts,
+            metablock,
+            ..
+          } = notification;
+          let resp = range_resp(&LedgerEntry::new(block, receipts, Some(nonces)), &metablock);
+          if tx.send(Ok(resp)).await.is_err() {
+            return;
+          }
+          next += 1;
+          prev = Some(metablock);
+        }
+      }
+    });
+    Ok(Response::new(ReceiverStream::new(rx)))
   }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct EndorserOpResponse {
-  #[serde(rename = "PublicKey")]
-  pub pk: String,
-}
 
-async fn get_endorser(
-  Path(uri): Path<String>,
-  Extension(state): Extension<Arc<CoordinatorState>>,
+  #[instrument(
+    name = "ReadCheckpoint",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      index = request.get_ref().index
+    )
+  )]
+  async fn read_checkpoint(
+    &self,
+    request: Request<ReadCheckpointReq>,
+  ) -> Result<Response<ReadCheckpointResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let ReadCheckpointReq {
+      handle: handle_bytes,
+      index,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    let access_request = AccessRequest::ReadByIndex {
+      index: index as usize,
+    };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    let res = self
+      .state
+      .read_checkpoint(&handle_bytes, index as usize)
+      .await;
+    if let Err(error) = res {
+      return Err(ledger_status(error, "Failed to read a checkpoint"));
+    }
+
+    let proof = res.unwrap();
+    let reply = ReadCheckpointResp {
+      index: proof.get_index() as u64,
+      block: proof.get_block().to_vec(),
+      nonces: proof.get_nonces().to_vec(),
+      receipts: proof.get_receipts().to_vec(),
+      block_hashes: proof
+        .get_block_hashes()
+        .iter()
+        .map(|block_hash| block_hash.to_bytes())
+        .collect(),
+    };
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(
+    name = "Checkpoint",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle)
+    )
+  )]
+  async fn checkpoint(
+    &self,
+    request: Request<CheckpointReq>,
+  ) -> Result<Response<CheckpointResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let CheckpointReq {
+      handle: handle_bytes,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    let access_request = AccessRequest::ReadLatest { nonce: &[] };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    let res = self.state.checkpoint_ledger(&handle_bytes).await;
+    if let Err(error) = res {
+      return Err(ledger_status(error, "Failed to checkpoint a ledger"));
+    }
+
+    let (id, snapshot) = res.unwrap();
+    let reply = CheckpointResp {
+      id: id as u64,
+      snapshot: snapshot.to_bytes(),
+    };
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(
+    name = "ReadViewByIndex",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      index = request.get_ref().index
+    )
+  )]
+  async fn read_view_by_index(
+    &self,
+    request: Request<ReadViewByIndexReq>,
+  ) -> Result<Response<ReadViewByIndexResp>, Status> {
+    let ReadViewByIndexReq { index } = request.into_inner();
+
+    let res = self.state.read_view_by_index(index as usize).await;
+    if res.is_err() {
+      return Err(Status::aborted("Failed to read the view ledger"));
+    }
+
+    let ledger_entry = res.unwrap();
+    let reply = ReadViewByIndexResp {
+      block: ledger_entry.get_block().to_bytes(),
+      receipts: ledger_entry.get_receipts().to_bytes(),
+      timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+    };
+
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(name = "ReadViewTail", skip_all, fields(request_id = %request_id(&request)))]
+  async fn read_view_tail(
+    &self,
+    request: Request<ReadViewTailReq>,
+  ) -> Result<Response<ReadViewTailResp>, Status> {
+    let ReadViewTailReq { nonce } = request.into_inner();
+    let res = self.state.read_view_tail().await;
+    if res.is_err() {
+      return Err(Status::aborted("Failed to read the view ledger tail"));
+    }
+    let (ledger_entry, height, attestation_reports) = res.unwrap();
+
+    let ledger_entry = if nonce.is_empty() {
+      ledger_entry
+    } else {
+      match self.state.read_view_tail_with_nonce(&nonce).await {
+        Ok((ledger_entry, _height)) => ledger_entry,
+        Err(CoordinatorError::InvalidNonce) => {
+          return Err(Status::invalid_argument("Invalid nonce"))
+        },
+        Err(CoordinatorError::FailedToObtainQuorum) => {
+          return Err(Status::unavailable(
+            "The endorsers of the current view did not sign the view ledger tail",
+          ))
+        },
+        Err(_) => return Err(Status::aborted("Failed to read the view ledger tail")),
+      }
+    };
+
+    // a view change that has not completed has no receipts, and so no metablock yet
+    let metablock = match ledger_entry.get_receipts().get_metablock() {
+      Ok(metablock) => metablock.to_bytes(),
+      Err(_) => Vec::new(),
+    };
+    let reply = ReadViewTailResp {
+      block: ledger_entry.get_block().to_bytes(),
+      receipts: ledger_entry.get_receipts().to_bytes(),
+      height: height as u64,
+      attestations: attestation_reports,
+      timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+      metablock,
+    };
+
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(
+    name = "ReadAdminLedger",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      index = request.get_ref().index
+    )
+  )]
+  async fn read_admin_ledger(
+    &self,
+    request: Request<ReadAdminLedgerReq>,
+  ) -> Result<Response<ReadAdminLedgerResp>, Status> {
+    let ReadAdminLedgerReq { index } = request.into_inner();
+
+    let res = self.state.read_admin_event(index as usize).await;
+    if res.is_err() {
+      return Err(Status::not_found("Failed to read the admin ledger"));
+    }
+
+    let ledger_entry = res.unwrap();
+    let reply = ReadAdminLedgerResp {
+      handle: ADMIN_LEDGER_HANDLE.to_vec(),
+      block: ledger_entry.get_block().to_bytes(),
+      nonces: ledger_entry.get_nonces().to_bytes(),
+      receipts: ledger_entry.get_receipts().to_bytes(),
+      timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+    };
+
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(
+    name = "GetLedgerStats",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle)
+    )
+  )]
+  async fn get_ledger_stats(
+    &self,
+    request: Request<GetLedgerStatsReq>,
+  ) -> Result<Response<GetLedgerStatsResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let GetLedgerStatsReq {
+      handle: handle_bytes,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    self
+      .authorize(&metadata, &handle_bytes, &AccessRequest::ReadStats)
+      .await?;
+
+    let stats = self.state.get_ledger_stats(&handle_bytes);
+    if stats.is_none() {
+      return Err(Status::not_found(
+        "No activity on the ledger since the coordinator started",
+      ));
+    }
+
+    let stats = stats.unwrap();
+    let reply = GetLedgerStatsResp {
+      height: stats.height as u64,
+      appends: stats.appends,
+      append_rate: stats.append_rate,
+      last_quorum_size: stats.last_quorum_size as u64,
+      last_activity: stats.last_activity,
+    };
+
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(
+    name = "ListLedgers",
+    skip_all,
+    fields(request_id = %request_id(&request), page_size = request.get_ref().page_size)
+  )]
+  async fn list_ledgers(
+    &self,
+    request: Request<ListLedgersReq>,
+  ) -> Result<Response<ListLedgersResp>, Status> {
+    let scope = self.namespace_scope(&request);
+    let ListLedgersReq { cursor, page_size } = request.into_inner();
+
+    let after = if cursor.is_empty() {
+      None
+    } else {
+      match NimbleDigest::from_bytes(&cursor) {
+        Ok(handle) => Some(handle),
+        Err(_) => return Err(Status::invalid_argument("The cursor is invalid")),
+      }
+    };
+    let res = self
+      .state
+      .list_ledgers(after.as_ref(), page_size as usize, scope.namespaces())
+      .await;
+    let (ledgers, next) =
+      res.map_err(|error| ledger_status(error, "Failed to list the ledgers"))?;
+
+    let reply = ListLedgersResp {
+      ledgers: ledgers
+        .into_iter()
+        .map(|ledger| LedgerInfo {
+          handle: ledger.handle.to_bytes(),
+          created_at: ledger.created_at.unwrap_or_default(),
+          height: ledger.height as u64,
+          app_bytes_digest: ledger.app_bytes_digest.to_bytes(),
+        })
+        .collect(),
+      next_cursor: next.map(|handle| handle.to_bytes()).unwrap_or_default(),
+    };
+
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(name = "GetLedgerByLabel", skip_all, fields(request_id = %request_id(&request)))]
+  async fn get_ledger_by_label(
+    &self,
+    request: Request<GetLedgerByLabelReq>,
+  ) -> Result<Response<GetLedgerByLabelResp>, Status> {
+    let scope = self.namespace_scope(&request);
+    let GetLedgerByLabelReq { label } = request.into_inner();
+
+    let res = self.state.get_ledger_by_label(&label).await;
+    let handle = res.map_err(|error| ledger_status(error, "Failed to look up the label"))?;
+    check_namespace(&scope, &handle)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    Ok(Response::new(GetLedgerByLabelResp { handle }))
+  }
+
+  #[instrument(
+    name = "ReadLatestAsOfView",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      handle = %short_id(&request.get_ref().handle),
+      view_height = request.get_ref().view_height
+    )
+  )]
+  async fn read_latest_as_of_view(
+    &self,
+    request: Request<ReadLatestAsOfViewReq>,
+  ) -> Result<Response<ReadLatestAsOfViewResp>, Status> {
+    let metadata = request.metadata().clone();
+    let scope = self.namespace_scope(&request);
+    let ReadLatestAsOfViewReq {
+      handle: handle_bytes,
+      view_height,
+    } = request.into_inner();
+    check_namespace(&scope, &handle_bytes)
+      .map_err(|error| ledger_status(error, "Failed to reach the ledger"))?;
+
+    let access_request = AccessRequest::ReadAsOfView {
+      view_height: view_height as usize,
+    };
+    self
+      .authorize(&metadata, &handle_bytes, &access_request)
+      .await?;
+
+    let res = self
+      .state
+      .read_ledger_as_of_view(&handle_bytes, view_height as usize)
+      .await;
+    let (ledger_entry, height, checkpoint_verified) = match res {
+      Ok(res) => res,
+      Err(CoordinatorError::InvalidViewHeight) => {
+        return Err(Status::out_of_range(
+          "The view height is not in the view ledger",
+        ))
+      },
+      Err(CoordinatorError::NoEntryAsOfView) => {
+        return Err(Status::not_found(
+          "The ledger had no endorsed entry as of the view",
+        ))
+      },
+      Err(_) => return Err(Status::aborted("Failed to read a ledger as of the view")),
+    };
+
+    let reply = ReadLatestAsOfViewResp {
+      block: ledger_entry.get_block().to_bytes(),
+      nonces: ledger_entry.get_nonces().to_bytes(),
+      receipts: ledger_entry.get_receipts().to_bytes(),
+      height: height as u64,
+      checkpoint_verified,
+      timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+    };
+
+    Ok(Response::new(reply))
+  }
+
+  type WatchViewChangesStream = ReceiverStream<Result<WatchViewChangesResp, Status>>;
+  type ReadBlobStream = ReceiverStream<Result<ReadBlobResp, Status>>;
+  type ReadRangeStream = ReceiverStream<Result<ReadRangeResp, Status>>;
+  type SubscribeStream = ReceiverStream<Result<ReadRangeResp, Status>>;
+
+  async fn watch_view_changes(
+    &self,
+    _request: Request<WatchViewChangesReq>,
+  ) -> Result<Response<Self::WatchViewChangesStream>, Status> {
+    let mut view_changes = self.state.subscribe_view_changes();
+    let (tx, rx) = mpsc::channel(WATCH_CHANNEL_BUFFER);
+    tokio::spawn(async move {
+      loop {
+        let res = match view_changes.recv().await {
+          Ok(ViewChangeNotification {
+            block,
+            receipts,
+            height,
+            attestations,
+          }) => Ok(WatchViewChangesResp {
+            block: block.to_bytes(),
+            receipts: receipts.to_bytes(),
+            height: height as u64,
+            attestations,
+          }),
+          // the client missed view changes and has to catch up by reading the view ledger
+          Err(RecvError::Lagged(_)) => Err(Status::data_loss(
+            "Missed view changes; read the view ledger and watch again",
+          )),
+          Err(RecvError::Closed) => break,
+        };
+        let is_err = res.is_err();
+        if tx.send(res).await.is_err() || is_err {
+          break;
+        }
+      }
+    });
+
+    Ok(Response::new(ReceiverStream::new(rx)))
+  }
+}
+
+#[tonic::async_trait]
+impl Admin for CoordinatorServiceState {
+  #[instrument(
+    name = "ReplaceEndorsers",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      uris = ?request.get_ref().uris
+    )
+  )]
+  async fn replace_endorsers(
+    &self,
+    request: Request<ReplaceEndorsersReq>,
+  ) -> Result<Response<ReplaceEndorsersResp>, Status> {
+    let ReplaceEndorsersReq { uris } = request.into_inner();
+
+    let res = self.state.replace_endorsers(&uris).await;
+    match res {
+      Ok(()) => {},
+      Err(CoordinatorError::NoNewEndorsers) => {
+        return Err(Status::invalid_argument(
+          "None of the endorsers could be connected to",
+        ))
+      },
+      Err(CoordinatorError::ViewChangeAborted) => {
+        return Err(Status::unavailable(
+          "The view change was aborted and the current view kept",
+        ))
+      },
+      Err(error) => {
+        eprintln!("Failed to replace the endorsers ({:?})", error);
+        return Err(Status::aborted("Failed to change the view"));
+      },
+    }
+
+    let res = self.state.get_view_height();
+    if res.is_err() {
+      return Err(Status::internal("Failed to read the view height"));
+    }
+    let reply = ReplaceEndorsersResp {
+      view_height: res.unwrap() as u64,
+      endorsers: self.state.get_endorser_pks(),
+    };
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(
+    name = "RemoveEndorsers",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      uris = ?request.get_ref().uris
+    )
+  )]
+  async fn remove_endorsers(
+    &self,
+    request: Request<RemoveEndorsersReq>,
+  ) -> Result<Response<RemoveEndorsersResp>, Status> {
+    let RemoveEndorsersReq { uris } = request.into_inner();
+
+    let res = self.state.remove_endorsers(&uris).await;
+    match res {
+      Ok(num_endorsers) => Ok(Response::new(RemoveEndorsersResp {
+        num_endorsers: num_endorsers as u64,
+      })),
+      Err(CoordinatorError::InvalidEndorserUri) => {
+        Err(Status::not_found("An endorser is not part of the view"))
+      },
+      Err(CoordinatorError::FailedToObtainQuorum) => Err(Status::failed_precondition(
+        "The remaining endorsers would not form a quorum of the view",
+      )),
+      Err(error) => {
+        eprintln!("Failed to remove the endorsers ({:?})", error);
+        Err(Status::aborted("Failed to remove the endorsers"))
+      },
+    }
+  }
+
+  #[instrument(
+    name = "ReadmitEndorsers",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      uris = ?request.get_ref().uris
+    )
+  )]
+  async fn readmit_endorsers(
+    &self,
+    request: Request<ReadmitEndorsersReq>,
+  ) -> Result<Response<ReadmitEndorsersResp>, Status> {
+    let ReadmitEndorsersReq { uris } = request.into_inner();
+
+    let res = self.state.readmit_endorsers(&uris).await;
+    match res {
+      Ok(num_endorsers) => Ok(Response::new(ReadmitEndorsersResp {
+        num_endorsers: num_endorsers as u64,
+      })),
+      Err(CoordinatorError::EndorserNotInView) => Err(Status::failed_precondition(
+        "An endorser is not an active endorser of the view",
+      )),
+      Err(CoordinatorError::CannotResolveHostName)
+      | Err(CoordinatorError::FailedToConnectToEndorser)
+      | Err(CoordinatorError::UnableToRetrievePublicKey) => {
+        Err(Status::unavailable("Failed to reach an endorser"))
+      },
+      Err(error) => {
+        eprintln!("Failed to readmit the endorsers ({:?})", error);
+        Err(Status::aborted("Failed to readmit the endorsers"))
+      },
+    }
+  }
+
+  #[instrument(
+    name = "RotateEndorserKey",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      uri = %request.get_ref().uri
+    )
+  )]
+  async fn rotate_endorser_key(
+    &self,
+    request: Request<RotateEndorserKeyReq>,
+  ) -> Result<Response<RotateEndorserKeyResp>, Status> {
+    let RotateEndorserKeyReq { uri } = request.into_inner();
+
+    let res = self.state.rotate_endorser_key(&uri).await;
+    match res {
+      Ok(report) => Ok(Response::new(RotateEndorserKeyResp {
+        pk: report.pk,
+        handover: report.handover.to_bytes(),
+        view_height: report.view_height as u64,
+      })),
+      Err(CoordinatorError::InvalidEndorserUri) => {
+        Err(Status::not_found("The endorser is not part of the view"))
+      },
+      Err(CoordinatorError::FailedToRotateKey) => Err(Status::failed_precondition(
+        "The endorser failed to generate a new key",
+      )),
+      Err(CoordinatorError::InvalidKeyHandover)
+      | Err(CoordinatorError::InvalidEndorserAttestation) => Err(Status::failed_precondition(
+        "The endorser's new key is not handed over or attested",
+      )),
+      Err(CoordinatorError::ViewChangeAborted) => Err(Status::unavailable(
+        "The view change was aborted and the current view kept",
+      )),
+      Err(error) => {
+        eprintln!("Failed to rotate the key of the endorser ({:?})", error);
+        Err(Status::aborted("Failed to change the view"))
+      },
+    }
+  }
+
+  #[instrument(name = "GetEndorserHealth", skip_all, fields(request_id = %request_id(&request)))]
+  async fn get_endorser_health(
+    &self,
+    request: Request<GetEndorserHealthReq>,
+  ) -> Result<Response<GetEndorserHealthResp>, Status> {
+    let GetEndorserHealthReq {} = request.into_inner();
+
+    let endorsers = self
+      .state
+      .get_endorser_health()
+      .into_iter()
+      .map(|report| EndorserHealthEntry {
+        uri: report.uri,
+        pk: report.pk,
+        health: match report.health {
+          EndorserHealth::Healthy => EndorserHealthState::Healthy,
+          EndorserHealth::Degraded => EndorserHealthState::Degraded,
+          EndorserHealth::Down => EndorserHealthState::Down,
+        } as i32,
+        consecutive_failures: report.consecutive_failures,
+        last_error: report.last_error.unwrap_or_default(),
+        since: report.since,
+        reconnects: report.reconnects,
+      })
+      .collect();
+    Ok(Response::new(GetEndorserHealthResp { endorsers }))
+  }
+
+  #[instrument(
+    name = "EnterMaintenance",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      seconds = request.get_ref().seconds
+    )
+  )]
+  async fn enter_maintenance(
+    &self,
+    request: Request<EnterMaintenanceReq>,
+  ) -> Result<Response<MaintenanceResp>, Status> {
+    let EnterMaintenanceReq { seconds } = request.into_inner();
+    if seconds == 0 {
+      return Err(Status::invalid_argument(
+        "Maintenance mode requires a non-zero duration",
+      ));
+    }
+
+    if let Err(error) = self.state.enter_maintenance(Duration::from_secs(seconds)) {
+      eprintln!("Failed to enter maintenance mode ({:?})", error);
+      return Err(Status::internal("Failed to enter maintenance mode"));
+    }
+    if self
+      .state
+      .record_admin_event(AdminAction::EnterMaintenance { seconds })
+      .await
+      .is_err()
+    {
+      return Err(Status::internal("Failed to record the admin event"));
+    }
+    Ok(Response::new(maintenance_resp(&self.state)))
+  }
+
+  #[instrument(name = "ExitMaintenance", skip_all, fields(request_id = %request_id(&request)))]
+  async fn exit_maintenance(
+    &self,
+    request: Request<ExitMaintenanceReq>,
+  ) -> Result<Response<MaintenanceResp>, Status> {
+    let ExitMaintenanceReq {} = request.into_inner();
+
+    if let Err(error) = self.state.exit_maintenance() {
+      eprintln!("Failed to exit maintenance mode ({:?})", error);
+      return Err(Status::internal("Failed to exit maintenance mode"));
+    }
+    if self
+      .state
+      .record_admin_event(AdminAction::ExitMaintenance)
+      .await
+      .is_err()
+    {
+      return Err(Status::internal("Failed to record the admin event"));
+    }
+    Ok(Response::new(maintenance_resp(&self.state)))
+  }
+
+  #[instrument(name = "DumpViewLedger", skip_all, fields(request_id = %request_id(&request)))]
+  async fn dump_view_ledger(
+    &self,
+    request: Request<DumpViewLedgerReq>,
+  ) -> Result<Response<DumpViewLedgerResp>, Status> {
+    let DumpViewLedgerReq {} = request.into_inner();
+
+    let res = self.state.read_view_ledger().await;
+    if res.is_err() {
+      return Err(Status::aborted("Failed to read the view ledger"));
+    }
+    let entries = res
+      .unwrap()
+      .iter()
+      .map(|ledger_entry| ViewLedgerEntry {
+        block: ledger_entry.get_block().to_bytes(),
+        receipts: ledger_entry.get_receipts().to_bytes(),
+        timestamp: ledger_entry.get_timestamp().unwrap_or_default(),
+      })
+      .collect();
+    Ok(Response::new(DumpViewLedgerResp { entries }))
+  }
+}
+
+fn maintenance_resp(state: &CoordinatorState) -> MaintenanceResp {
+  let remaining = state.get_maintenance_remaining();
+  MaintenanceResp {
+    enabled: remaining.is_some(),
+    remaining_seconds: remaining.map(|d| d.as_secs()).unwrap_or_default(),
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserOpResponse {
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserStatusResponse {
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "Incarnation")]
+  pub incarnation: u64,
+  #[serde(rename = "Restarted")]
+  pub restarted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserHealthResponse {
+  #[serde(rename = "Uri")]
+  pub uri: String,
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "Health")]
+  pub health: String,
+  #[serde(rename = "ConsecutiveFailures")]
+  pub consecutive_failures: u32,
+  #[serde(rename = "LastError")]
+  pub last_error: Option<String>,
+  #[serde(rename = "Since")]
+  pub since: u64,
+  #[serde(rename = "Reconnects")]
+  pub reconnects: u64,
+}
+
+impl From<EndorserHealthReport> for EndorserHealthResponse {
+  fn from(report: EndorserHealthReport) -> Self {
+    EndorserHealthResponse {
+      uri: report.uri,
+      pk: base64_url::encode(&report.pk),
+      health: report.health.as_str().to_string(),
+      consecutive_failures: report.consecutive_failures,
+      last_error: report.last_error,
+      since: report.since,
+      reconnects: report.reconnects,
+    }
+  }
+}
+
+// The health of the connections to every endorser in the view
+async fn get_endorsers(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let endorsers = state
+    .get_endorser_health()
+    .into_iter()
+    .map(EndorserHealthResponse::from)
+    .collect::<Vec<_>>();
+  (StatusCode::OK, Json(json!(endorsers)))
+}
+
+async fn get_endorser(
+  Path(uri): Path<String>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
 ) -> impl IntoResponse {
   let res = base64_url::decode(&uri);
   if res.is_err() {
@@ -216,18 +1871,20 @@ async fn get_endorser(
   }
   let endorser_uri_str = res.unwrap();
 
-  let res = state.get_endorser_pk(endorser_uri_str);
+  let res = state.check_endorser_restart(endorser_uri_str).await;
   match res {
-    None => {
+    Err(error) => {
       eprintln!(
-        "failed to delete the endorser {} ({:?})",
-        endorser_uri_str, res
+        "failed to get the endorser {} ({:?})",
+        endorser_uri_str, error
       );
       (StatusCode::BAD_REQUEST, Json(json!({})))
     },
-    Some(pk) => {
-      let resp = EndorserOpResponse {
+    Ok((pk, incarnation, restarted)) => {
+      let resp = EndorserStatusResponse {
         pk: base64_url::encode(&pk),
+        incarnation,
+        restarted,
       };
       (StatusCode::OK, Json(json!(resp)))
     },
@@ -319,15 +1976,493 @@ async fn delete_endorser(
     .disconnect_endorsers(&vec![(pk, endorser_uri_str.to_string())])
     .await;
 
+  let action = AdminAction::RemoveEndorser {
+    uri: endorser_uri_str.to_string(),
+  };
+  if state.record_admin_event(action).await.is_err() {
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+
   (StatusCode::OK, Json(json!(resp)))
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-  let config = App::new("coordinator")
-    .arg(
-      Arg::with_name("nimbledb")
-        .short("n")
+#[derive(Debug, Serialize, Deserialize)]
+struct DecommissionResponse {
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "FinalState")]
+  pub final_state: String,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: usize,
+  #[serde(rename = "ActiveEndorsers")]
+  pub active_endorsers: usize,
+  #[serde(rename = "NumEndorsers")]
+  pub num_endorsers: usize,
+}
+
+async fn decommission_endorser(
+  Path((uri, replacements)): Path<(String, String)>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  let decoded = base64_url::decode(&uri)
+    .ok()
+    .and_then(|u| String::from_utf8(u).ok())
+    .zip(
+      base64_url::decode(&replacements)
+        .ok()
+        .and_then(|r| String::from_utf8(r).ok()),
+    );
+  let (endorser_uri, replacements) = match decoded {
+    Some(decoded) => decoded,
+    None => {
+      eprintln!(
+        "received a bad endorser uri {} or replacements {}",
+        uri, replacements
+      );
+      return (StatusCode::BAD_REQUEST, Json(json!({})));
+    },
+  };
+  let replacements = replacements
+    .split(';')
+    .filter(|e| !e.is_empty())
+    .map(|e| e.to_string())
+    .collect::<Vec<String>>();
+
+  let res = state
+    .decommission_endorser(&endorser_uri, &replacements)
+    .await;
+  match res {
+    Ok(report) => {
+      let resp = DecommissionResponse {
+        pk: base64_url::encode(&report.pk),
+        final_state: base64_url::encode(&report.final_state.to_bytes()),
+        view_height: report.view_height,
+        active_endorsers: report.active_endorsers,
+        num_endorsers: report.num_endorsers,
+      };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(error) => {
+      eprintln!(
+        "failed to decommission the endorser {} ({:?})",
+        endorser_uri, error
+      );
+      let status = match error {
+        CoordinatorError::InvalidEndorserUri | CoordinatorError::NoNewEndorsers => {
+          StatusCode::BAD_REQUEST
+        },
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+      };
+      (status, Json(json!({})))
+    },
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyRotationResponse {
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "Handover")]
+  pub handover: String,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: usize,
+}
+
+async fn rotate_endorser_key(
+  Path(uri): Path<String>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  let endorser_uri = match base64_url::decode(&uri)
+    .ok()
+    .and_then(|u| String::from_utf8(u).ok())
+  {
+    Some(endorser_uri) => endorser_uri,
+    None => {
+      eprintln!("received a bad endorser uri {}", uri);
+      return (StatusCode::BAD_REQUEST, Json(json!({})));
+    },
+  };
+
+  let res = state.rotate_endorser_key(&endorser_uri).await;
+  match res {
+    Ok(report) => {
+      let resp = KeyRotationResponse {
+        pk: base64_url::encode(&report.pk),
+        handover: base64_url::encode(&report.handover.to_bytes()),
+        view_height: report.view_height,
+      };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(error) => {
+      eprintln!(
+        "failed to rotate the key of the endorser {} ({:?})",
+        endorser_uri, error
+      );
+      let status = match error {
+        CoordinatorError::InvalidEndorserUri => StatusCode::BAD_REQUEST,
+        CoordinatorError::ViewChangeAborted => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+      };
+      (status, Json(json!({})))
+    },
+  }
+}
+
+async fn report_misbehavior(
+  Path(evidence): Path<String>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  let evidence = match base64_url::decode(&evidence)
+    .ok()
+    .and_then(|e| MisbehaviorEvidence::from_bytes(&e).ok())
+  {
+    Some(evidence) => evidence,
+    None => {
+      eprintln!("received malformed misbehavior evidence");
+      return (StatusCode::BAD_REQUEST, Json(json!({})));
+    },
+  };
+
+  match state.report_misbehavior(evidence) {
+    Ok(()) => (StatusCode::OK, Json(json!({}))),
+    Err(CoordinatorError::InvalidMisbehaviorEvidence) => (StatusCode::BAD_REQUEST, Json(json!({}))),
+    Err(error) => {
+      eprintln!("failed to report misbehavior ({:?})", error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MaintenanceResponse {
+  #[serde(rename = "Maintenance")]
+  pub enabled: bool,
+  #[serde(rename = "RemainingSeconds")]
+  pub remaining_secs: u64,
+}
+
+fn maintenance_response(state: &CoordinatorState) -> MaintenanceResponse {
+  let remaining = state.get_maintenance_remaining();
+  MaintenanceResponse {
+    enabled: remaining.is_some(),
+    remaining_secs: remaining.map(|d| d.as_secs()).unwrap_or_default(),
+  }
+}
+
+async fn get_maintenance(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let resp = maintenance_response(&state);
+  (StatusCode::OK, Json(json!(resp)))
+}
+
+async fn enter_maintenance(
+  Path(seconds): Path<u64>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  if seconds == 0 {
+    eprintln!("maintenance mode requires a non-zero duration");
+    return (StatusCode::BAD_REQUEST, Json(json!({})));
+  }
+
+  if let Err(error) = state.enter_maintenance(Duration::from_secs(seconds)) {
+    eprintln!("failed to enter maintenance mode ({:?})", error);
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+  if state
+    .record_admin_event(AdminAction::EnterMaintenance { seconds })
+    .await
+    .is_err()
+  {
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+
+  let resp = maintenance_response(&state);
+  (StatusCode::OK, Json(json!(resp)))
+}
+
+async fn exit_maintenance(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  if let Err(error) = state.exit_maintenance() {
+    eprintln!("failed to exit maintenance mode ({:?})", error);
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+  if state
+    .record_admin_event(AdminAction::ExitMaintenance)
+    .await
+    .is_err()
+  {
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+
+  let resp = maintenance_response(&state);
+  (StatusCode::OK, Json(json!(resp)))
+}
+
+async fn get_integrity(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  match state.get_integrity_report() {
+    Ok(Some(report)) => (StatusCode::OK, Json(json!(report))),
+    Ok(None) => (StatusCode::NOT_FOUND, Json(json!({}))),
+    Err(error) => {
+      eprintln!("failed to read the integrity report ({:?})", error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
+
+async fn check_integrity(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  match state.check_store_integrity().await {
+    Ok(report) => (StatusCode::OK, Json(json!(report))),
+    Err(error) => {
+      eprintln!("failed to check the integrity of the store ({:?})", error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewEntry {
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewLedgerResponse {
+  #[serde(rename = "Views")]
+  pub views: Vec<ViewEntry>,
+  #[serde(rename = "Attestations")]
+  pub attestations: String,
+}
+
+async fn get_views(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let res = state.read_view_tail().await;
+  if res.is_err() {
+    eprintln!("failed to read the view ledger tail ({:?})", res);
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+  let (_tail, _height, attestations) = res.unwrap();
+
+  let res = state.read_view_ledger().await;
+  if let Err(error) = res {
+    eprintln!("failed to read the view ledger ({:?})", error);
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+  let views = res
+    .unwrap()
+    .iter()
+    .map(|ledger_entry| ViewEntry {
+      block: base64_url::encode(&ledger_entry.get_block().to_bytes()),
+      receipts: base64_url::encode(&ledger_entry.get_receipts().to_bytes()),
+    })
+    .collect::<Vec<_>>();
+
+  let resp = ViewLedgerResponse {
+    views,
+    attestations: base64_url::encode(&attestations),
+  };
+  (StatusCode::OK, Json(json!(resp)))
+}
+
+type LedgerMetric = (
+  &'static str,
+  &'static str,
+  &'static str,
+  fn(&LedgerStats) -> f64,
+);
+
+type EndorserMetric = (
+  &'static str,
+  &'static str,
+  &'static str,
+  fn(&EndorserHealthReport) -> f64,
+);
+
+// Per-ledger metrics in the Prometheus text exposition format, labelled by base64url handle, and
+// per-endorser health metrics, labelled by URI
+async fn get_metrics(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let ledgers = state.get_all_ledger_stats();
+  let metrics: [LedgerMetric; 5] = [
+    (
+      "nimble_ledger_height",
+      "gauge",
+      "Height of the ledger after the last append",
+      |s| s.height as f64,
+    ),
+    (
+      "nimble_ledger_appends_total",
+      "counter",
+      "Appends since the coordinator started",
+      |s| s.appends as f64,
+    ),
+    (
+      "nimble_ledger_append_rate",
+      "gauge",
+      "Appends per second over the last minute",
+      |s| s.append_rate,
+    ),
+    (
+      "nimble_ledger_last_quorum_size",
+      "gauge",
+      "Endorser signatures in the last receipt assembled",
+      |s| s.last_quorum_size as f64,
+    ),
+    (
+      "nimble_ledger_last_activity_timestamp_ms",
+      "gauge",
+      "Time of the last create, append, or read of the tail",
+      |s| s.last_activity as f64,
+    ),
+  ];
+
+  let mut body = String::new();
+  for (name, kind, help, value) in metrics {
+    body.push_str(&format!(
+      "# HELP {} {}\n# TYPE {} {}\n",
+      name, help, name, kind
+    ));
+    for (handle_bytes, stats) in &ledgers {
+      body.push_str(&format!(
+        "{}{{handle=\"{}\"}} {}\n",
+        name,
+        base64_url::encode(handle_bytes),
+        value(stats)
+      ));
+    }
+  }
+
+  let endorsers = state.get_endorser_health();
+  let metrics: [EndorserMetric; 3] = [
+    (
+      "nimble_endorser_up",
+      "gauge",
+      "Whether the endorser answered the last probe",
+      |r| (r.health == EndorserHealth::Healthy) as u64 as f64,
+    ),
+    (
+      "nimble_endorser_consecutive_failures",
+      "gauge",
+      "Failed probes and reconnection attempts since the endorser last answered",
+      |r| r.consecutive_failures as f64,
+    ),
+    (
+      "nimble_endorser_reconnects_total",
+      "counter",
+      "Times the connections to the endorser were replaced",
+      |r| r.reconnects as f64,
+    ),
+  ];
+  for (name, kind, help, value) in metrics {
+    body.push_str(&format!(
+      "# HELP {} {}\n# TYPE {} {}\n",
+      name, help, name, kind
+    ));
+    for report in &endorsers {
+      body.push_str(&format!(
+        "{}{{endorser=\"{}\"}} {}\n",
+        name,
+        report.uri,
+        value(report)
+      ));
+    }
+  }
+  let name = "nimble_endorser_health_state";
+  body.push_str(&format!(
+    "# HELP {} Whether the endorser is in the health state\n# TYPE {} gauge\n",
+    name, name
+  ));
+  for report in &endorsers {
+    for health in [
+      EndorserHealth::Healthy,
+      EndorserHealth::Degraded,
+      EndorserHealth::Down,
+    ] {
+      body.push_str(&format!(
+        "{}{{endorser=\"{}\",state=\"{}\"}} {}\n",
+        name,
+        report.uri,
+        health.as_str(),
+        (report.health == health) as u64
+      ));
+    }
+  }
+
+  let cache = state.get_tail_cache_stats();
+  let metrics: [(&str, &str, &str, f64); 5] = [
+    (
+      "nimble_tail_cache_hits_total",
+      "counter",
+      "Reads of a ledger's tail entry that the tail cache answered",
+      cache.hits as f64,
+    ),
+    (
+      "nimble_tail_cache_misses_total",
+      "counter",
+      "Reads of a ledger's tail entry that went to the ledger store",
+      cache.misses as f64,
+    ),
+    (
+      "nimble_tail_cache_evictions_total",
+      "counter",
+      "Cached tail entries dropped to make room for others",
+      cache.evictions as f64,
+    ),
+    (
+      "nimble_tail_cache_expirations_total",
+      "counter",
+      "Cached tail entries dropped because they outlived the TTL",
+      cache.expirations as f64,
+    ),
+    (
+      "nimble_tail_cache_entries",
+      "gauge",
+      "Ledgers whose tail entry is cached",
+      cache.entries as f64,
+    ),
+  ];
+  for (name, kind, help, value) in metrics {
+    body.push_str(&format!(
+      "# HELP {} {}\n# TYPE {} {}\n{} {}\n",
+      name, help, name, kind, name, value
+    ));
+  }
+
+  // the store has not been checked yet if there is no report
+  if let Ok(Some(report)) = state.get_integrity_report() {
+    let metrics: [(&str, &str, &str, f64); 2] = [
+      (
+        "nimble_integrity_issues",
+        "gauge",
+        "Entries, ledgers, and views that the last integrity check of the store flagged",
+        report.issues.len() as f64,
+      ),
+      (
+        "nimble_integrity_last_check_timestamp_ms",
+        "gauge",
+        "Time when the last integrity check of the store started",
+        report.started as f64,
+      ),
+    ];
+    for (name, kind, help, value) in metrics {
+      body.push_str(&format!(
+        "# HELP {} {}\n# TYPE {} {}\n{} {}\n",
+        name, help, name, kind, name, value
+      ));
+    }
+  }
+  (StatusCode::OK, body)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  let config = App::new("coordinator")
+    .arg(
+      Arg::with_name("config")
+        .long("config")
+        .takes_value(true)
+        .help("A JSON file of options, keyed by their long names; options given here override it"),
+    )
+    .arg(
+      Arg::with_name("nimbledb")
+        .short("n")
         .long("nimbledb")
         .help("The database name")
         .default_value("nimble_cosmosdb"),
@@ -353,11 +2488,158 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .takes_value(true)
         .help("The storage master key"),
     )
+    .arg(
+      Arg::with_name("tail_read_consistency")
+        .long("tail_read_consistency")
+        .takes_value(true)
+        .possible_values(&["strong", "eventual"])
+        .help("The consistency level for reads of a ledger's tail (cosmosdb only)"),
+    )
+    .arg(
+      Arg::with_name("historical_read_consistency")
+        .long("historical_read_consistency")
+        .takes_value(true)
+        .possible_values(&["strong", "eventual"])
+        .help("The consistency level for reads of a ledger by index (cosmosdb only)"),
+    )
+    .arg(
+      Arg::with_name("receipt_retention")
+        .long("receipt_retention")
+        .takes_value(true)
+        .help("Which receipts to keep per ledger entry: all, strongest, or bounded:<n>"),
+    )
+    .arg(
+      Arg::with_name("receipt_compaction")
+        .long("receipt_compaction")
+        .takes_value(true)
+        .help("Which old entries keep their receipts: none, or every:<n>[,age:<secs>] to keep them only at checkpoints n entries apart"),
+    )
+    .arg(
+      Arg::with_name("shard_size")
+        .long("shard_size")
+        .takes_value(true)
+        .help("The number of endorsers each ledger is assigned to (0 assigns all endorsers)"),
+    )
+    .arg(
+      Arg::with_name("max_block_size")
+        .long("max_block_size")
+        .takes_value(true)
+        .help("The largest block in bytes that appends may carry"),
+    )
+    .arg(
+      Arg::with_name("block_content_types")
+        .long("block_content_types")
+        .takes_value(true)
+        .help("Comma-separated content types; appended blocks must be envelopes of one of them"),
+    )
+    .arg(
+      Arg::with_name("blob_store")
+        .long("blob_store")
+        .takes_value(true)
+        .possible_values(&store::content::CONTENT_STORE_TYPES)
+        .help("The store of the payloads of chunked appends; without one, chunked appends fail"),
+    )
+    .arg(
+      Arg::with_name("blob_store_dir")
+        .long("blob_store_dir")
+        .takes_value(true)
+        .help("The directory of the filestore blob store"),
+    )
+    .arg(
+      Arg::with_name("max_blob_size")
+        .long("max_blob_size")
+        .takes_value(true)
+        .requires("blob_store")
+        .help("The largest payload in bytes that a chunked append may carry"),
+    )
+    .arg(
+      Arg::with_name("append_request_retention")
+        .long("append_request_retention")
+        .takes_value(true)
+        .help("How long in seconds a retry of an append with a client request ID is deduplicated (default 600)"),
+    )
+    .arg(
+      Arg::with_name("append_pipeline_depth")
+        .long("append_pipeline_depth")
+        .takes_value(true)
+        .help("The most appends to a ledger that one round of endorsements carries; appends queue while a round is in flight (default 0, which endorses each append on its own)"),
+    )
+    .arg(
+      Arg::with_name("tail_cache_size")
+        .long("tail_cache_size")
+        .takes_value(true)
+        .help("The most ledgers whose tail entries are cached, so that reads of their tails skip the ledger store (default 0, which disables the cache)"),
+    )
+    .arg(
+      Arg::with_name("tail_cache_ttl")
+        .long("tail_cache_ttl")
+        .takes_value(true)
+        .requires("tail_cache_size")
+        .help("How long in milliseconds a cached tail entry is served before it is read from the ledger store again (default until the ledger is written to)"),
+    )
+    .arg(
+      Arg::with_name("attest_timestamps")
+        .long("attest_timestamps")
+        .help("Proposes the time of each append to the endorsers, which attest to it if it is close to their clocks, so that the entry's metablock carries it"),
+    )
+    .arg(
+      Arg::with_name("endorser_lease")
+        .long("endorser_lease")
+        .takes_value(true)
+        .help("How long in seconds the endorsers of a view may sign before they refuse to until a view change renews their lease; the coordinator renews it once two thirds of it have passed (default no lease)"),
+    )
+    .arg(
+      Arg::with_name("integrity_check_interval")
+        .long("integrity_check_interval")
+        .takes_value(true)
+        .help("How often in seconds to check the integrity of every ledger in the store, recomputing hash chains and verifying receipts against the view ledger (default never)"),
+    )
+    .arg(
+      Arg::with_name("rate_limit")
+        .long("rate_limit")
+        .takes_value(true)
+        .help("The appends and ledger creations per second that each client may make on average"),
+    )
+    .arg(
+      Arg::with_name("rate_limit_burst")
+        .long("rate_limit_burst")
+        .takes_value(true)
+        .requires("rate_limit")
+        .help("The appends and ledger creations that an idle client may make at once (default the rate, and at least 1)"),
+    )
+    .arg(
+      Arg::with_name("max_concurrent_requests")
+        .long("max_concurrent_requests")
+        .takes_value(true)
+        .help("The appends and ledger creations that each client may have in progress at once"),
+    )
+    .arg(
+      Arg::with_name("rate_limit_by")
+        .long("rate_limit_by")
+        .takes_value(true)
+        .possible_values(&["peer", "api_key"])
+        .default_value("peer")
+        .help("Whether clients are told apart by their IP address or by the API key in their requests"),
+    )
+    .arg(
+      Arg::with_name("soak")
+        .long("soak")
+        .takes_value(true)
+        .help("Run synthetic load against this coordinator for the given number of seconds (requires the soak feature)"),
+    )
+    .arg(
+      Arg::with_name("soak_error_budget")
+        .long("soak_error_budget")
+        .takes_value(true)
+        .default_value("0.001")
+        .help("The fraction of failed operations tolerated by the soak test"),
+    )
     .arg(
       Arg::with_name("store")
         .short("s")
         .long("store")
         .help("The type of store used by the service.")
+        .possible_values(&store::ledger::LEDGER_STORE_TYPES)
         .default_value("memory"),
     )
     .arg(
@@ -381,6 +2663,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .help("The port number to run the coordinator control service on.")
         .default_value("8090"),
     )
+    .arg(
+      Arg::with_name("http_port")
+        .long("http-port")
+        .takes_value(true)
+        .help("The port number to serve the JSON gateway to the coordinator service on"),
+    )
+    .arg(
+      Arg::with_name("admin_port")
+        .long("admin-port")
+        .takes_value(true)
+        .help("The port number to serve the admin service on, instead of the coordinator service's port"),
+    )
     .arg(
       Arg::with_name("endorser")
         .short("e")
@@ -389,67 +2683,804 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .use_delimiter(true)
         .default_value("http://[::1]:9090"),
     )
+    .arg(
+      Arg::with_name("secrets")
+        .long("secrets")
+        .takes_value(true)
+        .help("Where to read store credentials that are not passed as flags: env, env:<prefix>, or file:<dir>, optionally with ;ttl=<secs>"),
+    )
+    .arg(
+      Arg::with_name("replicate_to")
+        .long("replicate_to")
+        .takes_value(true)
+        .conflicts_with("standby")
+        .help("Stream the in-memory store to a warm standby coordinator at this URL"),
+    )
+    .arg(
+      Arg::with_name("standby")
+        .long("standby")
+        .help("Run as a warm standby that only accepts replication on the service port until it is promoted"),
+    )
+    .arg(
+      Arg::with_name("leader_lease")
+        .long("leader-lease")
+        .takes_value(true)
+        .conflicts_with_all(&["standby", "replicate_to"])
+        .help("Elect a leader among the coordinators that share the store, with a lease of this many seconds; followers forward requests to the leader"),
+    )
+    .arg(
+      Arg::with_name("advertise_uri")
+        .long("advertise-uri")
+        .takes_value(true)
+        .requires("leader_lease")
+        .help("The URI at which other coordinators reach this one's gRPC service (http://HOST:PORT by default)"),
+    )
     .arg(
       Arg::with_name("channels")
         .short("l")
         .long("channels")
         .takes_value(true)
         .help("The number of grpc channels"),
-    );
-
-  let cli_matches = config.get_matches();
-  let hostname = cli_matches.value_of("host").unwrap();
-  let port_number = cli_matches.value_of("port").unwrap();
-  let ctrl_port = cli_matches.value_of("ctrl").unwrap();
-  let store = cli_matches.value_of("store").unwrap();
-  let addr = format!("{}:{}", hostname, port_number).parse()?;
-  let str_vec: Vec<&str> = cli_matches.values_of("endorser").unwrap().collect();
-  let endorser_hostnames = str_vec
-    .iter()
-    .filter(|e| !e.is_empty())
-    .map(|e| e.to_string())
-    .collect::<Vec<String>>();
-
-  let mut ledger_store_args = HashMap::<String, String>::new();
-  if let Some(x) = cli_matches.value_of("cosmosurl") {
-    ledger_store_args.insert(String::from("COSMOS_URL"), x.to_string());
-  }
-  if let Some(x) = cli_matches.value_of("nimbledb") {
-    ledger_store_args.insert(String::from("NIMBLE_DB"), x.to_string());
-  }
-  if let Some(x) = cli_matches.value_of("storage_account") {
-    ledger_store_args.insert(String::from("STORAGE_ACCOUNT"), x.to_string());
-  }
-  if let Some(x) = cli_matches.value_of("storage_master_key") {
-    ledger_store_args.insert(String::from("STORAGE_MASTER_KEY"), x.to_string());
-  }
-  let num_grpc_channels: Option<usize> = if let Some(x) = cli_matches.value_of("channels") {
-    match x.to_string().parse() {
-      Ok(v) => Some(v),
+    )
+    .arg(
+      Arg::with_name("attestation")
+        .long("attestation")
+        .takes_value(true)
+        .possible_values(&["mock"])
+        .help("The trusted execution environment whose attestation endorsers must present to join a view"),
+    )
+    .arg(
+      Arg::with_name("endorser_allowlist")
+        .long("endorser_allowlist")
+        .takes_value(true)
+        .help("The base64url public keys, separated by commas, of the endorsers that may join a view besides those of the current view"),
+    )
+    .arg(
+      Arg::with_name("tls_cert")
+        .long("tls-cert")
+        .takes_value(true)
+        .requires("tls_key")
+        .help("The PEM certificate with which the coordinator serves TLS and authenticates to endorsers"),
+    )
+    .arg(
+      Arg::with_name("tls_key")
+        .long("tls-key")
+        .takes_value(true)
+        .requires("tls_cert")
+        .help("The PEM private key of the TLS certificate"),
+    )
+    .arg(
+      Arg::with_name("tls_ca")
+        .long("tls-ca")
+        .takes_value(true)
+        .help("The PEM CA that the certificates of endorsers with https URIs chain to"),
+    )
+    .arg(
+      Arg::with_name("auth_file")
+        .long("auth-file")
+        .takes_value(true)
+        .help("A JSON file of the API keys and JWT secrets that clients authenticate with, reread when it changes"),
+    )
+    .arg(
+      Arg::with_name("log_level")
+        .long("log-level")
+        .takes_value(true)
+        .possible_values(&["error", "warn", "info", "debug", "trace"])
+        .default_value("info")
+        .help("The least severe level of the events to log"),
+    )
+    .arg(
+      Arg::with_name("shutdown_timeout")
+        .long("shutdown-timeout")
+        .takes_value(true)
+        .help("The seconds to wait on SIGTERM for the requests in flight to finish before exiting")
+        .default_value("30"),
+    )
+    .arg(
+      Arg::with_name("export_on_shutdown")
+        .long("export-on-shutdown")
+        .takes_value(true)
+        .help("Exports the ledgers to an archive file on SIGTERM, for nimble-store --import"),
+    )
+    .arg(
+      Arg::with_name("snapshot_path")
+        .long("snapshot-path")
+        .takes_value(true)
+        .help("Loads the memory store from a snapshot file on startup, if there is one, and snapshots it to the file periodically and on SIGTERM, for development setups"),
+    )
+    .arg(
+      Arg::with_name("snapshot_interval")
+        .long("snapshot-interval")
+        .takes_value(true)
+        .help("How often in seconds to snapshot the memory store with --snapshot-path")
+        .default_value("10"),
+    )
+    .arg(
+      Arg::with_name("log_json")
+        .long("log-json")
+        .help("Logs events as JSON lines instead of text"),
+    )
+    .arg(
+      Arg::with_name("otlp_endpoint")
+        .long("otlp-endpoint")
+        .takes_value(true)
+        .help("Exports traces to an OpenTelemetry collector at this OTLP/HTTP URL, e.g., http://localhost:4318"),
+    )
+    .arg(
+      Arg::with_name("enable_reflection")
+        .long("enable-reflection")
+        .help("Serves gRPC reflection, with which tools such as grpcurl discover the services"),
+    );
+
+  // options on the command line override the ones from the configuration file
+  let given = config.clone().get_matches();
+  let args = match args_with_config_file(std::env::args_os(), |name| given.occurrences_of(name) > 0)
+  {
+    Ok(args) => args,
+    Err(error) => panic!("Failed to read the configuration file ({:?})", error),
+  };
+  let cli_matches = config.get_matches_from(args);
+  let log_level = cli_matches.value_of("log_level").unwrap();
+  let exporter = cli_matches
+    .value_of("otlp_endpoint")
+    .map(|endpoint| OtlpExporter::start(endpoint, "nimble-coordinator"))
+    .transpose()
+    .unwrap_or_else(|error| panic!("Failed to start exporting traces ({:?})", error));
+  let tracing_enabled = exporter.is_some();
+  if let Err(error) =
+    logging::init_with_exporter(log_level, cli_matches.is_present("log_json"), exporter)
+  {
+    panic!("Failed to initialize logging ({:?})", error);
+  }
+  let hostname = cli_matches.value_of("host").unwrap();
+  let port_number = cli_matches.value_of("port").unwrap();
+  let ctrl_port = cli_matches.value_of("ctrl").unwrap();
+  let store = cli_matches.value_of("store").unwrap();
+  let addr = format!("{}:{}", hostname, port_number).parse()?;
+  let str_vec: Vec<&str> = cli_matches.values_of("endorser").unwrap().collect();
+  let endorser_hostnames = str_vec
+    .iter()
+    .filter(|e| !e.is_empty())
+    .map(|e| e.to_string())
+    .collect::<Vec<String>>();
+
+  let mut ledger_store_args = HashMap::<String, String>::new();
+  if let Some(x) = cli_matches.value_of("cosmosurl") {
+    ledger_store_args.insert(String::from("COSMOS_URL"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("nimbledb") {
+    ledger_store_args.insert(String::from("NIMBLE_DB"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("storage_account") {
+    ledger_store_args.insert(String::from("STORAGE_ACCOUNT"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("storage_master_key") {
+    ledger_store_args.insert(String::from("STORAGE_MASTER_KEY"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("tail_read_consistency") {
+    ledger_store_args.insert(String::from("TAIL_READ_CONSISTENCY"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("historical_read_consistency") {
+    ledger_store_args.insert(String::from("HISTORICAL_READ_CONSISTENCY"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("receipt_retention") {
+    ledger_store_args.insert(String::from("RECEIPT_RETENTION"), x.to_string());
+  }
+  let receipt_retention = ReceiptRetention::from_args(&ledger_store_args);
+  let receipt_compaction = match cli_matches.value_of("receipt_compaction") {
+    Some(x) => {
+      let mut args = HashMap::new();
+      args.insert(String::from("RECEIPT_COMPACTION"), x.to_string());
+      match ReceiptCompaction::from_args(&args) {
+        Ok(receipt_compaction) => receipt_compaction,
+        Err(error) => panic!("Invalid receipt compaction ({:?})", error),
+      }
+    },
+    None => ReceiptCompaction::default(),
+  };
+  if let Some(uri) = cli_matches.value_of("secrets") {
+    let secrets = match secret_provider_from_uri(uri) {
+      Ok(secrets) => secrets,
+      Err(error) => panic!("Invalid secret provider {} ({:?})", uri, error),
+    };
+    for name in STORE_SECRETS {
+      if ledger_store_args.contains_key(name) {
+        continue;
+      }
+      match secrets.get_secret(name) {
+        Ok(value) => {
+          ledger_store_args.insert(name.to_string(), value);
+        },
+        Err(SecretError::NotFound) => {},
+        Err(error) => panic!("Failed to read the secret {} ({:?})", name, error),
+      }
+    }
+  }
+  let num_grpc_channels: Option<usize> = if let Some(x) = cli_matches.value_of("channels") {
+    match x.to_string().parse() {
+      Ok(v) => Some(v),
       Err(_) => panic!("Failed to parse the number of grpc channels"),
     }
   } else {
     None
   };
-  let res = CoordinatorState::new(store, &ledger_store_args, num_grpc_channels).await;
+  let tls_identity = match (
+    cli_matches.value_of("tls_cert"),
+    cli_matches.value_of("tls_key"),
+  ) {
+    (Some(cert), Some(key)) => Some(Identity::from_pem(
+      std::fs::read(cert)?,
+      std::fs::read(key)?,
+    )),
+    _ => None,
+  };
+  let endorser_tls = if tls_identity.is_some() || cli_matches.is_present("tls_ca") {
+    let mut tls = ClientTlsConfig::new();
+    if let Some(ca) = cli_matches.value_of("tls_ca") {
+      tls = tls.ca_certificate(Certificate::from_pem(std::fs::read(ca)?));
+    }
+    if let Some(identity) = &tls_identity {
+      tls = tls.identity(identity.clone());
+    }
+    Some(tls)
+  } else {
+    None
+  };
+  // a follower forwards the requests it receives to the leader until it takes the leader lease,
+  // and then recovers the view and ledgers from the store like a restarted coordinator
+  if let Some(x) = cli_matches.value_of("leader_lease") {
+    if store == "memory" {
+      panic!("Leader election needs a store that the coordinators share");
+    }
+    let lease_duration = match x.parse::<u64>() {
+      Ok(secs) if secs > 0 => Duration::from_secs(secs),
+      _ => panic!("Failed to parse the leader lease duration"),
+    };
+    let advertise_uri = match cli_matches.value_of("advertise_uri") {
+      Some(uri) => uri.to_string(),
+      None => format!("http://{}:{}", hostname, port_number),
+    };
+    let election_store = match open_ledger_store(store, &ledger_store_args).await {
+      Ok(ledger_store) => ledger_store,
+      Err(error) => panic!("Failed to open the {} ledger store ({:?})", store, error),
+    };
+    let election = Arc::new(LeaderElection::new(
+      Arc::new(election_store),
+      &advertise_uri,
+      lease_duration,
+    ));
+    if let Err(LedgerStoreError::LedgerError(StorageError::LeasesNotSupported)) =
+      election.campaign().await
+    {
+      panic!("The {} store does not support leader election", store);
+    }
+    let (elected_tx, elected_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(election.clone().run(elected_tx));
+
+    println!(
+      "Forwarding requests to the leader at {:?} until elected",
+      addr
+    );
+    let mut builder = Server::builder();
+    if let Some(identity) = tls_identity.clone() {
+      builder = builder.tls_config(ServerTlsConfig::new().identity(identity))?;
+    }
+    let res = builder
+      .add_service(CallServer::new(LeaderForwarder::new(
+        election,
+        endorser_tls.clone(),
+      )))
+      .serve_with_shutdown(addr, async {
+        let _ = elected_rx.await;
+      })
+      .await;
+    if let Err(error) = res {
+      panic!("The follower service failed ({:?})", error);
+    }
+    info!(%advertise_uri, "elected the leader");
+  }
+
+  // with tracing, every store operation runs in a span of its own
+  let traced = |ledger_store: BoxedLedgerStore| -> BoxedLedgerStore {
+    if tracing_enabled {
+      Box::new(TracedLedgerStore::new(ledger_store))
+    } else {
+      ledger_store
+    }
+  };
+  let standby = cli_matches.is_present("standby");
+  if (standby || cli_matches.is_present("replicate_to")) && store != "memory" {
+    panic!("Replication is only supported for the memory store");
+  }
+  let snapshot_path = cli_matches.value_of("snapshot_path").map(PathBuf::from);
+  if snapshot_path.is_some() && store != "memory" {
+    panic!("Snapshots are only supported for the memory store");
+  }
+  let snapshot_interval = match cli_matches
+    .value_of("snapshot_interval")
+    .unwrap()
+    .parse::<u64>()
+  {
+    Ok(secs) if secs > 0 => Duration::from_secs(secs),
+    _ => panic!("Failed to parse the snapshot interval"),
+  };
+  let mut snapshot_store = None;
+  let res = if standby || cli_matches.is_present("replicate_to") || snapshot_path.is_some() {
+    let in_memory_store = match InMemoryLedgerStore::from_args(&ledger_store_args) {
+      Ok(in_memory_store) => in_memory_store,
+      Err(error) => panic!("Failed to open the {} ledger store ({:?})", store, error),
+    };
+    if let Some(path) = &snapshot_path {
+      match in_memory_store.load_snapshot(path) {
+        Ok(entries) => info!(path = %path.display(), entries, "loaded the snapshot of the store"),
+        Err(error) => panic!("Failed to load the snapshot {:?} ({:?})", path, error),
+      }
+      snapshot_store = Some(in_memory_store.clone());
+    }
+    let in_memory_store = if standby {
+      let (standby_state, promoted) = StandbyState::new(in_memory_store.clone());
+      println!("Running the standby replication service at {:?}", addr);
+      let res = Server::builder()
+        .add_service(ReplicationServer::new(standby_state))
+        .serve_with_shutdown(addr, async {
+          let _ = promoted.await;
+        })
+        .await;
+      if let Err(error) = res {
+        panic!("The standby replication service failed ({:?})", error);
+      }
+      in_memory_store
+    } else if let Some(standby_uri) = cli_matches.value_of("replicate_to") {
+      let standby_uri = standby_uri.to_string();
+      let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+      let in_memory_store = in_memory_store.with_replication(sender);
+      tokio::spawn(replication::run_replication(
+        in_memory_store.clone(),
+        receiver,
+        standby_uri,
+      ));
+      in_memory_store
+    } else {
+      in_memory_store
+    };
+    CoordinatorState::with_store_and_endorser_tls(
+      traced(Box::new(in_memory_store)),
+      num_grpc_channels,
+      endorser_tls,
+    )
+    .await
+  } else {
+    let ledger_store = match open_ledger_store(store, &ledger_store_args).await {
+      Ok(ledger_store) => ledger_store,
+      Err(error) => panic!("Failed to open the {} ledger store ({:?})", store, error),
+    };
+    CoordinatorState::with_store_and_endorser_tls(
+      traced(ledger_store),
+      num_grpc_channels,
+      endorser_tls,
+    )
+    .await
+  };
   assert!(res.is_ok());
   let coordinator = res.unwrap();
 
-  if !endorser_hostnames.is_empty() {
-    let _ = coordinator.replace_endorsers(&endorser_hostnames).await;
+  if let Some(x) = cli_matches.value_of("shard_size") {
+    match x.parse::<usize>() {
+      Ok(shard_size) => coordinator.set_shard_size(shard_size).unwrap(),
+      Err(_) => panic!("Failed to parse the shard size"),
+    }
+  }
+
+  let max_block_size = cli_matches.value_of("max_block_size").map(|x| {
+    x.parse::<usize>()
+      .unwrap_or_else(|_| panic!("Failed to parse the maximum block size"))
+  });
+  let content_types = cli_matches.value_of("block_content_types").map(|x| {
+    x.split(',')
+      .map(|content_type| content_type.trim().to_string())
+      .collect::<HashSet<String>>()
+  });
+  coordinator
+    .set_block_validation(BlockValidation {
+      max_block_size,
+      content_types,
+    })
+    .unwrap();
+
+  if let Some(blob_store_type) = cli_matches.value_of("blob_store") {
+    let mut args = HashMap::new();
+    if let Some(x) = cli_matches.value_of("blob_store_dir") {
+      args.insert(String::from("NIMBLE_CONTENT_DIR"), x.to_string());
+    }
+    let blob_store = match open_content_store(blob_store_type, &args) {
+      Ok(blob_store) => blob_store,
+      Err(error) => panic!("Failed to open the blob store ({:?})", error),
+    };
+    let max_blob_size = cli_matches.value_of("max_blob_size").map(|x| {
+      x.parse::<usize>()
+        .unwrap_or_else(|_| panic!("Failed to parse the maximum blob size"))
+    });
+    coordinator
+      .set_blob_store(blob_store, max_blob_size)
+      .unwrap();
   }
-  if coordinator.get_endorser_pks().is_empty() {
-    panic!("No endorsers are available!");
+
+  if let Some(x) = cli_matches.value_of("append_request_retention") {
+    match x.parse::<u64>() {
+      Ok(secs) => coordinator
+        .set_append_request_retention(Duration::from_secs(secs))
+        .unwrap(),
+      Err(_) => panic!("Failed to parse the append request retention"),
+    }
+  }
+
+  if let Some(x) = cli_matches.value_of("append_pipeline_depth") {
+    match x.parse::<usize>() {
+      Ok(depth) => coordinator.set_append_pipeline_depth(depth).unwrap(),
+      Err(_) => panic!("Failed to parse the append pipeline depth"),
+    }
+  }
+
+  if let Some(x) = cli_matches.value_of("tail_cache_size") {
+    let ttl = cli_matches
+      .value_of("tail_cache_ttl")
+      .map(|x| match x.parse::<u64>() {
+        Ok(ms) => Duration::from_millis(ms),
+        Err(_) => panic!("Failed to parse the tail cache TTL"),
+      });
+    match x.parse::<usize>() {
+      Ok(capacity) => coordinator.set_tail_cache(capacity, ttl),
+      Err(_) => panic!("Failed to parse the tail cache size"),
+    }
+  }
+
+  if cli_matches.is_present("attest_timestamps") {
+    coordinator.set_attest_timestamps(true).unwrap();
+  }
+
+  let endorser_lease = cli_matches
+    .value_of("endorser_lease")
+    .map(|x| match x.parse::<u64>() {
+      Ok(secs) if secs > 0 => Duration::from_secs(secs),
+      _ => panic!("Failed to parse the endorser lease"),
+    });
+  coordinator.set_endorser_lease(endorser_lease).unwrap();
+
+  let integrity_check_interval = cli_matches
+    .value_of("integrity_check_interval")
+    .map(|x| match x.parse::<u64>() {
+      Ok(secs) if secs > 0 => Duration::from_secs(secs),
+      _ => panic!("Failed to parse the integrity check interval"),
+    });
+
+  if let Some(name) = cli_matches.value_of("attestation") {
+    match verifier_from_name(name) {
+      Ok(verifier) => coordinator.set_attestation_verifier(verifier).unwrap(),
+      Err(error) => panic!("Unsupported attestation {} ({:?})", name, error),
+    }
+  }
+
+  if let Some(x) = cli_matches.value_of("endorser_allowlist") {
+    let pks = x
+      .split(',')
+      .map(|pk| match base64_url::decode(pk.trim()) {
+        Ok(pk) if PublicKey::from_bytes(&pk).is_ok() => pk,
+        _ => panic!("Invalid public key {} in the endorser allowlist", pk),
+      })
+      .collect::<HashSet<Vec<u8>>>();
+    coordinator.set_endorser_allowlist(pks).await.unwrap();
   }
-  println!("Endorser URIs: {:?}", coordinator.get_endorser_uris());
+
+  let rate = cli_matches
+    .value_of("rate_limit")
+    .map_or(0.0, |x| match x.parse::<f64>() {
+      Ok(rate) if rate >= 0.0 => rate,
+      _ => panic!("Failed to parse the rate limit"),
+    });
+  let burst = cli_matches
+    .value_of("rate_limit_burst")
+    .map_or(rate.max(1.0), |x| match x.parse::<f64>() {
+      Ok(burst) if burst >= 1.0 => burst,
+      _ => panic!("Failed to parse the rate limit burst"),
+    });
+  let max_concurrent = cli_matches
+    .value_of("max_concurrent_requests")
+    .map_or(0, |x| match x.parse::<usize>() {
+      Ok(max_concurrent) => max_concurrent,
+      Err(_) => panic!("Failed to parse the maximum concurrent requests"),
+    });
+  let rate_limits = RateLimits {
+    rate,
+    burst,
+    max_concurrent,
+    key: match cli_matches.value_of("rate_limit_by") {
+      Some("api_key") => ClientKey::ApiKey,
+      _ => ClientKey::Peer,
+    },
+  };
+
+  let authenticator =
+    cli_matches.value_of("auth_file").map(|path| {
+      match Authenticator::from_file(std::path::Path::new(path)) {
+        Ok(authenticator) => Arc::new(authenticator),
+        Err(error) => panic!("Failed to load the auth file {} ({:?})", path, error),
+      }
+    });
+
+  // the gRPC servers and the JSON gateway count the requests of each client together
+  let rate_limit_layer = rate_limits
+    .is_enabled()
+    .then(|| RateLimitLayer::new(rate_limits));
 
   let coordinator_ref = Arc::new(coordinator);
 
-  let server = CoordinatorServiceState::new(coordinator_ref.clone());
+  let server =
+    CoordinatorServiceState::new(coordinator_ref.clone()).with_authenticator(authenticator.clone());
+  let admin_server =
+    CoordinatorServiceState::new(coordinator_ref.clone()).with_authenticator(authenticator.clone());
+  // operators can keep the admin service off the port that clients reach
+  let admin_addr: Option<std::net::SocketAddr> = match cli_matches.value_of("admin_port") {
+    Some(admin_port) => Some(format!("{}:{}", hostname, admin_port).parse()?),
+    None => None,
+  };
+
+  // the gRPC server starts before the endorsers are initialized, reporting itself as not serving
+  // until they are, so that probes can tell a listening coordinator from a ready one
+  let (health_reporter, health_service) = HealthReporter::new(&[
+    CallServer::<CoordinatorServiceState>::NAME,
+    AdminServer::<CoordinatorServiceState>::NAME,
+  ]);
+  let reflection_service = if cli_matches.is_present("enable_reflection") {
+    let service = ReflectionService::new(&[
+      COORDINATOR_DESCRIPTOR_SET,
+      HEALTH_DESCRIPTOR_SET,
+      REFLECTION_DESCRIPTOR_SET,
+    ])?;
+    Some(ServerReflectionServer::new(service))
+  } else {
+    None
+  };
+  let new_builder = || -> Result<_, tonic::transport::Error> {
+    let mut builder = Server::builder();
+    if let Some(identity) = tls_identity.clone() {
+      builder = builder.tls_config(ServerTlsConfig::new().identity(identity))?;
+    }
+    // a request that carries a trace context continues the caller's trace
+    if tracing_enabled {
+      builder = builder.trace_fn(grpc_request_span);
+    }
+    // requests are authenticated before they count against their client's limits
+    Ok(
+      builder
+        .layer(tower::util::option_layer(
+          authenticator.clone().map(AuthLayer::new),
+        ))
+        .layer(tower::util::option_layer(rate_limit_layer.clone())),
+    )
+  };
+  let shutdown_timeout = match cli_matches
+    .value_of("shutdown_timeout")
+    .unwrap()
+    .parse::<u64>()
+  {
+    Ok(secs) => Duration::from_secs(secs),
+    Err(_) => panic!("Failed to parse the shutdown timeout"),
+  };
+  let export_on_shutdown = cli_matches.value_of("export_on_shutdown").map(String::from);
+  // every server stops accepting requests once the coordinator shuts down
+  let shutdown = Shutdown::default();
+  let mut servers = Vec::new();
+  let mut builder = new_builder()?;
+  if let Some(admin_addr) = admin_addr {
+    let mut admin_builder = new_builder()?;
+    let service = CoordinatorServiceState::new(coordinator_ref.clone())
+      .with_authenticator(authenticator.clone());
+    let health_service = health_service.clone();
+    let stopped = shutdown.started();
+    servers.push(tokio::spawn(async move {
+      println!("Running the admin service at {}", admin_addr);
+      let _ = admin_builder
+        .add_service(AdminServer::new(service))
+        .add_service(HealthServer::new(health_service))
+        .serve_with_shutdown(admin_addr, stopped)
+        .await;
+    }));
+  }
+  info!(%addr, "starting the coordinator");
+  let stopped = shutdown.started();
+  let mut job2 = tokio::spawn(async move {
+    println!(
+      "Running gRPC Coordinator Service at {:?} ({} digests)",
+      addr,
+      NimbleDigest::algorithm()
+    );
+    let _ = builder
+      .add_service(CallServer::new(server))
+      .add_optional_service(admin_addr.is_none().then(|| AdminServer::new(admin_server)))
+      .add_service(HealthServer::new(health_service))
+      .add_optional_service(reflection_service)
+      .serve_with_shutdown(addr, stopped)
+      .await;
+  });
+
+  // a promoted standby keeps the endorsers of the replicated view ledger unless told otherwise
+  let keep_endorsers = standby && cli_matches.occurrences_of("endorser") == 0;
+  // a coordinator restarted on its store resumes the recovered view when it names the same
+  // endorsers, instead of moving the ledgers through a view change
+  let connected_uris = coordinator_ref.get_endorser_uris();
+  let keep_endorsers = keep_endorsers
+    || (coordinator_ref.get_view_height().unwrap() > 0
+      && endorser_hostnames
+        .iter()
+        .all(|uri| connected_uris.contains(uri)));
+  if !endorser_hostnames.is_empty() && !keep_endorsers {
+    let _ = coordinator_ref.replace_endorsers(&endorser_hostnames).await;
+  }
+  if coordinator_ref.get_endorser_pks().is_empty() {
+    panic!("No endorsers are available!");
+  }
+  println!("Endorser URIs: {:?}", coordinator_ref.get_endorser_uris());
+
+  info!(endorsers = ?coordinator_ref.get_endorser_uris(), "initialized the endorsers");
+
+  // the coordinator serves while a majority of its endorsers answer
+  let coordinator = coordinator_ref.clone();
+  let shutdown_health_reporter = health_reporter.clone();
+  let health_job = tokio::spawn(async move {
+    loop {
+      health_reporter.set_all(coordinator.is_quorum_reachable().await);
+      // endorsers that stopped answering get new connections, so a restarted endorser or a
+      // recovered network does not need the coordinator to restart
+      for uri in coordinator.reconnect_endorsers().await {
+        info!(endorser = %uri, "reconnected to the endorser");
+      }
+      tokio::time::sleep(Duration::from_secs(HEALTH_CHECK_INTERVAL)).await;
+    }
+  });
+
+  // endorsers caught signing conflicting appends are recorded and leave the view
+  let coordinator = coordinator_ref.clone();
+  let _misbehavior_job = tokio::spawn(async move {
+    loop {
+      tokio::time::sleep(Duration::from_secs(MISBEHAVIOR_CHECK_INTERVAL)).await;
+      match coordinator.handle_misbehavior().await {
+        Ok(expelled) if !expelled.is_empty() => {
+          warn!(?expelled, "expelled endorsers that equivocated")
+        },
+        Ok(_expelled) => {},
+        Err(error) => warn!(?error, "failed to expel endorsers that equivocated"),
+      }
+    }
+  });
+
+  // the endorsers' lease is renewed before it expires, by a view change that keeps them unless
+  // another view change renewed it first
+  if let Some(lease) = endorser_lease {
+    let coordinator = coordinator_ref.clone();
+    let _lease_job = tokio::spawn(async move {
+      loop {
+        let remaining = match coordinator.get_endorser_lease_expiry() {
+          Ok(Some(expiry)) => Duration::from_millis(expiry.saturating_sub(current_timestamp())),
+          Ok(None) => Duration::ZERO,
+          Err(error) => {
+            warn!(?error, "failed to read the lease of the endorsers");
+            tokio::time::sleep(Duration::from_secs(LEASE_RENEWAL_RETRY_INTERVAL)).await;
+            continue;
+          },
+        };
+        if remaining > lease / 3 {
+          tokio::time::sleep(remaining - lease / 3).await;
+          continue;
+        }
+        match coordinator.renew_endorser_leases().await {
+          Ok(view_height) => info!(view_height, "renewed the lease of the endorsers"),
+          Err(error) => {
+            warn!(?error, "failed to renew the lease of the endorsers");
+            tokio::time::sleep(Duration::from_secs(LEASE_RENEWAL_RETRY_INTERVAL)).await;
+          },
+        }
+      }
+    });
+  }
+
+  // entries corrupted by tampering with the store or by a bug are flagged between audits
+  if let Some(interval) = integrity_check_interval {
+    let coordinator = coordinator_ref.clone();
+    let _integrity_job = tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(interval).await;
+        match coordinator.check_store_integrity().await {
+          Ok(report) if report.passed() => info!(
+            ledgers = report.ledgers,
+            entries = report.entries,
+            "checked the integrity of the store"
+          ),
+          Ok(report) => warn!(
+            issues = report.issues.len(),
+            "the integrity check of the store flagged corrupted entries"
+          ),
+          Err(error) => warn!(?error, "failed to check the integrity of the store"),
+        }
+      }
+    });
+  }
+
+  // a development setup with the memory store keeps its ledgers across restarts; appends made
+  // since the last snapshot are lost if the coordinator crashes
+  if let (Some(store), Some(path)) = (snapshot_store.clone(), snapshot_path.clone()) {
+    let _snapshot_job = tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(snapshot_interval).await;
+        let (store, path) = (store.clone(), path.clone());
+        match tokio::task::spawn_blocking(move || store.save_snapshot(&path)).await {
+          Ok(Ok(entries)) => debug!(entries, "snapshotted the store"),
+          Ok(Err(error)) => warn!(?error, "failed to snapshot the store"),
+          Err(error) => warn!(?error, "the snapshot of the store panicked"),
+        }
+      }
+    });
+  }
+
+  // edits to the auth file take effect without a restart
+  if let Some(authenticator) = authenticator.clone() {
+    let _auth_reload_job = tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(Duration::from_secs(AUTH_RELOAD_INTERVAL)).await;
+        match authenticator.reload() {
+          Ok(true) => info!("reloaded the auth file"),
+          Ok(false) => {},
+          Err(error) => warn!(?error, "failed to reload the auth file"),
+        }
+      }
+    });
+  }
+
+  // entries stored before the retention policy was set keep their superseded receipts until
+  // they are compacted; later entries conform as soon as their receipts are attached
+  if !matches!(receipt_retention, Ok(ReceiptRetention::All)) {
+    let coordinator = coordinator_ref.clone();
+    let _retention_job = tokio::spawn(async move {
+      let mut progress = HashMap::new();
+      loop {
+        match coordinator.compact_receipt_retention(&mut progress).await {
+          Ok(compacted) => info!(compacted, "applied the receipt retention policy"),
+          Err(error) => warn!(?error, "failed to apply the receipt retention policy"),
+        }
+        tokio::time::sleep(Duration::from_secs(COMPACTION_INTERVAL)).await;
+      }
+    });
+  }
+
+  // old entries keep only their blocks and nonces, apart from checkpoints
+  if receipt_compaction.is_enabled() {
+    let coordinator = coordinator_ref.clone();
+    let _compaction_job = tokio::spawn(async move {
+      let mut progress = HashMap::new();
+      loop {
+        tokio::time::sleep(Duration::from_secs(COMPACTION_INTERVAL)).await;
+        match coordinator
+          .compact_ledgers(&receipt_compaction, &mut progress)
+          .await
+        {
+          Ok(compacted) => info!(compacted, "compacted the receipts of old entries"),
+          Err(error) => warn!(?error, "failed to compact the receipts of old entries"),
+        }
+      }
+    });
+  }
 
   // Start the REST server for management
   let control_server = Router::new()
+      .route("/endorsers", get(get_endorsers))
       .route("/endorsers/:uri", get(get_endorser).put(new_endorser).delete(delete_endorser))
+      .route("/endorsers/:uri/decommission/:replacements", put(decommission_endorser))
+      .route("/endorsers/:uri/rotate", put(rotate_endorser_key))
+      .route("/misbehavior/:evidence", put(report_misbehavior))
+      .route("/maintenance", get(get_maintenance).delete(exit_maintenance))
+      .route("/maintenance/:seconds", put(enter_maintenance))
+      .route("/views", get(get_views))
+      .route("/integrity", get(get_integrity).put(check_integrity))
+      .route("/metrics", get(get_metrics))
       // Add middleware to all routes
       .layer(
           ServiceBuilder::new()
@@ -459,44 +3490,201 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
       );
 
   let ctrl_addr = format!("{}:{}", hostname, ctrl_port).parse()?;
-  let _job = tokio::spawn(async move {
+  let stopped = shutdown.started();
+  servers.push(tokio::spawn(async move {
     println!("Running control service at {}", ctrl_addr);
     let _res = axum::Server::bind(&ctrl_addr)
       .serve(control_server.into_make_service())
+      .with_graceful_shutdown(stopped)
       .await;
-  });
+  }));
+
+  if let Some(http_port) = cli_matches.value_of("http_port") {
+    let http_addr = format!("{}:{}", hostname, http_port).parse()?;
+    // the gateway's requests do not pass through the gRPC server's layers, so the gateway
+    // checks their credentials and their clients' limits itself
+    let service =
+      CoordinatorServiceState::new(coordinator_ref.clone()).with_authenticator(authenticator);
+    let limiter = rate_limit_layer.as_ref().map(RateLimitLayer::limiter);
+    let gateway = gateway::router(Arc::new(service), limiter);
+    let stopped = shutdown.started();
+    servers.push(tokio::spawn(async move {
+      println!("Running the JSON gateway at {}", http_addr);
+      let _res = axum::Server::bind(&http_addr)
+        .serve(gateway.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .with_graceful_shutdown(stopped)
+        .await;
+    }));
+  }
 
-  let job2 = tokio::spawn(async move {
-    println!("Running gRPC Coordinator Service at {:?}", addr);
-    let _ = Server::builder()
-      .add_service(CallServer::new(server))
-      .serve(addr)
-      .await;
-  });
+  if let Some(x) = cli_matches.value_of("soak") {
+    let duration = match x.parse::<u64>() {
+      Ok(secs) => Duration::from_secs(secs),
+      Err(_) => panic!("Failed to parse the soak duration"),
+    };
+    let error_budget = match cli_matches
+      .value_of("soak_error_budget")
+      .unwrap()
+      .parse::<f64>()
+    {
+      Ok(budget) => budget,
+      Err(_) => panic!("Failed to parse the soak error budget"),
+    };
+    run_soak(coordinator_ref.clone(), duration, error_budget).await;
+  }
 
-  job2.await?;
+  let signal = tokio::select! {
+    res = &mut job2 => return res.map_err(|e| e.into()),
+    signal = shutdown_signal() => signal,
+  };
+
+  // the servers stop accepting requests and probes report the coordinator as not serving, so
+  // that load balancers move clients elsewhere; the requests in flight, and the receipts and
+  // view changes they started, finish before the coordinator exits, unless they take too long
+  info!(signal, timeout = ?shutdown_timeout, "shutting down");
+  let deadline = tokio::time::Instant::now() + shutdown_timeout;
+  health_job.abort();
+  shutdown_health_reporter.set_all(false);
+  shutdown.start();
+  servers.push(job2);
+  let mut timed_out = false;
+  for server in servers {
+    if tokio::time::timeout_at(deadline, server).await.is_err() {
+      warn!("requests were still in flight when the shutdown timed out");
+      timed_out = true;
+      break;
+    }
+  }
+  // while the guard lives, no view change starts, so the export below sees a settled store
+  let _guard = if timed_out {
+    None
+  } else {
+    let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+    let guard = coordinator_ref.drain(remaining).await;
+    match guard {
+      Some(_) => info!("finished the requests in flight"),
+      None => warn!("receipts or view changes were still in flight when the shutdown timed out"),
+    }
+    guard
+  };
+
+  // the export runs even if the shutdown timed out, since every entry in the store is complete
+  if let Some(path) = export_on_shutdown {
+    let res = match std::fs::File::create(&path) {
+      Ok(file) => {
+        let mut out = std::io::BufWriter::new(file);
+        match coordinator_ref.export_ledgers(&mut out).await {
+          Ok(report) => std::io::Write::flush(&mut out)
+            .map(|()| report)
+            .map_err(|e| e.to_string()),
+          Err(error) => Err(error),
+        }
+      },
+      Err(error) => Err(error.to_string()),
+    };
+    match res {
+      Ok(report) => info!(
+        path,
+        views = report.views,
+        ledgers = report.ledgers,
+        entries = report.entries,
+        "exported the ledgers"
+      ),
+      Err(error) => warn!(path, error, "failed to export the ledgers"),
+    }
+  }
+
+  if let (Some(store), Some(path)) = (snapshot_store, snapshot_path) {
+    match store.save_snapshot(&path) {
+      Ok(entries) => info!(path = %path.display(), entries, "snapshotted the store"),
+      Err(error) => warn!(path = %path.display(), ?error, "failed to snapshot the store"),
+    }
+  }
 
   Ok(())
 }
 
+#[cfg(feature = "soak")]
+async fn run_soak(coordinator: Arc<CoordinatorState>, duration: Duration, error_budget: f64) {
+  let config = soak::SoakConfig {
+    duration,
+    error_budget,
+    appends_per_ledger: 10,
+  };
+  let stats = soak::run(coordinator, config).await;
+  println!("soak: finished {}", stats.report());
+  std::process::exit(if stats.within_budget(error_budget) {
+    0
+  } else {
+    1
+  });
+}
+
+#[cfg(not(feature = "soak"))]
+async fn run_soak(_coordinator: Arc<CoordinatorState>, _duration: Duration, _error_budget: f64) {
+  panic!("The coordinator was built without the soak feature");
+}
+
 #[cfg(test)]
 mod tests {
   use crate::{
     coordinator_proto::{
-      call_server::Call, AppendReq, AppendResp, NewLedgerReq, NewLedgerResp, ReadByIndexReq,
-      ReadByIndexResp, ReadLatestReq, ReadLatestResp, ReadViewTailReq, ReadViewTailResp,
+      admin_server::Admin, call_server::Call, AppendBatchReq, AppendBatchResp, AppendHashOnlyReq,
+      AppendReq, AppendResp, CheckpointReq, DumpViewLedgerReq, EndorserHealthState,
+      EnterMaintenanceReq, ExitMaintenanceReq, GetEndorserHealthReq, GetLedgerStatsReq,
+      ListLedgersReq, NewLedgerReq, NewLedgerResp, ReadAdminLedgerReq, ReadAdminLedgerResp,
+      ReadBlobReq, ReadByIndexReq, ReadByIndexResp, ReadCheckpointReq, ReadLatestReq,
+      ReadLatestResp, ReadRangeReq, ReadViewByIndexReq, ReadViewTailReq, ReadViewTailResp,
+      ReadmitEndorsersReq, RemoveEndorsersReq, ReplaceEndorsersReq, RotateEndorserKeyReq,
+      SubscribeReq,
+    },
+    gateway, ledger_status,
+    replication::verify_replica,
+    CoordinatorServiceState, CoordinatorState, RANGE_PAGE_SIZE,
+  };
+  use axum::{extract::ConnectInfo, http::StatusCode};
+  use coordinator::{
+    auth::{Authenticator, NamespaceScope},
+    consistency::ConsistencyToken,
+    coordinator_state::{
+      AdminAction, AdminEvent, ADMIN_LEDGER_HANDLE, ATTESTATION_STR, CHECKPOINT_LEDGER_HANDLE,
+      MAX_CLIENT_REQUEST_ID_SIZE,
+    },
+    errors::CoordinatorError,
+    rate_limit::{ClientKey, RateLimiter, RateLimits},
+  };
+  use ledger::{
+    attestation::{
+      retrieve_attestations_from_config, verifier_from_name, verify_config_attestations,
+    },
+    derive_ledger_handle,
+    health::{
+      health_proto::{health_client::HealthClient, HealthCheckRequest, HealthCheckResponse},
+      ServingStatus,
     },
-    CoordinatorServiceState, CoordinatorState,
+    namespace::namespaced_handle,
+    signature::{PrivateKey, PrivateKeyTrait, PublicKeyTrait, SignatureTrait},
+    AccessPolicy, AccessRequest, BlobReference, Block, BlockEnvelope, BlockValidation,
+    CheckpointProof, CustomSerde, ExternalBlock, InclusionProof, LedgerSnapshot, MetaBlock,
+    NimbleDigest, NimbleHashTrait, Nonce, ReadVisibility, Receipts, VerifierState,
+    API_KEY_METADATA, CLIENT_PUBLIC_KEY_METADATA, CLIENT_SIGNATURE_METADATA,
   };
-  use ledger::{Block, CustomSerde, NimbleDigest, VerifierState};
   use rand::Rng;
+  use serde_json::json;
   use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ffi::OsString,
     io::{BufRead, BufReader},
     process::{Child, Command, Stdio},
     sync::Arc,
+    time::Duration,
   };
+  use store::{
+    content::open_content_store,
+    ledger::{in_memory::InMemoryLedgerStore, AppendRequest, LedgerStore, ReceiptCompaction},
+  };
+  use tokio_stream::StreamExt;
+  use tonic::{metadata::MetadataValue, Code, Request};
 
   struct BoxChild {
     pub child: Child,
@@ -589,10 +3777,20 @@ mod tests {
       );
     }
 
-    if std::env::var_os("NIMBLE_DB").is_some() {
+    if std::env::var_os("STORAGE_CONNECTION_STRING").is_some() {
       ledger_store_args.insert(
-        String::from("NIMBLE_DB"),
-        std::env::var_os("NIMBLE_DB")
+        String::from("STORAGE_CONNECTION_STRING"),
+        std::env::var_os("STORAGE_CONNECTION_STRING")
+          .unwrap()
+          .into_string()
+          .unwrap(),
+      );
+    }
+
+    if std::env::var_os("NIMBLE_DB").is_some() {
+      ledger_store_args.insert(
+        String::from("NIMBLE_DB"),
+        std::env::var_os("NIMBLE_DB")
           .unwrap()
           .into_string()
           .unwrap(),
@@ -629,7 +3827,7 @@ mod tests {
     // Initialization: Fetch view ledger to build VerifierState
     let mut vs = VerifierState::new();
 
-    let req = tonic::Request::new(ReadViewTailReq {});
+    let req = tonic::Request::new(ReadViewTailReq { nonce: vec![] });
     let res = server.read_view_tail(req).await;
     assert!(res.is_ok());
     let ReadViewTailResp {
@@ -637,6 +3835,7 @@ mod tests {
       receipts,
       height: view_height,
       attestations,
+      ..
     } = res.unwrap().into_inner();
 
     assert!(view_height == 1);
@@ -645,6 +3844,25 @@ mod tests {
     let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
     assert!(res.is_ok());
 
+    // The view change is recorded in the admin ledger
+    let req = tonic::Request::new(ReadAdminLedgerReq { index: 0 });
+    let ReadAdminLedgerResp {
+      handle,
+      block,
+      receipts,
+      ..
+    } = server.read_admin_ledger(req).await.unwrap().into_inner();
+    let event: AdminEvent = serde_json::from_slice(&block).unwrap();
+    assert_eq!(
+      event.action,
+      AdminAction::ReplaceEndorsers {
+        uris: vec!["http://[::1]:9090".to_string()],
+        view_height: 1,
+      }
+    );
+    let res = vs.verify_new_ledger(&handle, &block, &receipts);
+    assert!(res.is_ok());
+
     // Step 0: Create some app data
     let block_bytes: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
 
@@ -653,8 +3871,11 @@ mod tests {
     let request = tonic::Request::new(NewLedgerReq {
       handle: handle_bytes.to_vec(),
       block: block_bytes.to_vec(),
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
     });
-    let NewLedgerResp { receipts } = server.new_ledger(request).await.unwrap().into_inner();
+    let NewLedgerResp { receipts, .. } = server.new_ledger(request).await.unwrap().into_inner();
     let res = vs.verify_new_ledger(&handle_bytes, block_bytes.as_ref(), &receipts);
     println!("NewLedger (WithAppData) : {:?}", res);
     assert!(res.is_ok());
@@ -665,29 +3886,36 @@ mod tests {
     let req = tonic::Request::new(ReadByIndexReq {
       handle: handle.clone(),
       index: 0,
+      nonce: vec![],
+      snapshot: 0,
     });
 
     let ReadByIndexResp {
       block,
       nonces,
       receipts,
+      timestamp,
+      ..
     } = server.read_by_index(req).await.unwrap().into_inner();
 
     let res = vs.verify_read_by_index(&handle, &block, &nonces, 0, &receipts);
     println!("ReadByIndex: {:?}", res.is_ok());
     assert!(res.is_ok());
+    assert!(timestamp > 0);
 
     // Step 3: Read Latest with the Nonce generated
     let nonce = rand::thread_rng().gen::<[u8; 16]>();
     let req = tonic::Request::new(ReadLatestReq {
       handle: handle.clone(),
       nonce: nonce.to_vec(),
+      consistency_token: vec![],
     });
 
     let ReadLatestResp {
       block,
       nonces,
       receipts,
+      ..
     } = server.read_latest(req).await.unwrap().into_inner();
 
     let res = vs.verify_read_latest(&handle, &block, &nonces, nonce.as_ref(), &receipts);
@@ -698,7 +3926,7 @@ mod tests {
     let b1: Vec<u8> = "data_block_example_1".as_bytes().to_vec();
     let b2: Vec<u8> = "data_block_example_2".as_bytes().to_vec();
     let b3: Vec<u8> = "data_block_example_3".as_bytes().to_vec();
-    let blocks = vec![&b1, &b2, &b3].to_vec();
+    let blocks = [&b1, &b2, &b3].to_vec();
 
     let mut expected_height = 0;
     for block_to_append in blocks {
@@ -707,11 +3935,16 @@ mod tests {
         handle: handle.clone(),
         block: block_to_append.to_vec(),
         expected_height: expected_height as u64,
+        client_pk: vec![],
+        client_signature: vec![],
+        client_request_id: vec![],
       });
 
       let AppendResp {
         hash_nonces,
         receipts,
+        summary,
+        consistency_token: _,
       } = server.append(req).await.unwrap().into_inner();
 
       let res = vs.verify_append(
@@ -723,6 +3956,10 @@ mod tests {
       );
       println!("Append verification: {:?} {:?}", block_to_append, res);
       assert!(res.is_ok());
+
+      let summary = summary.unwrap();
+      assert!(summary.quorum_verified);
+      assert!(!summary.signers.is_empty());
     }
 
     // Step 4: Read Latest with the Nonce generated and check for new data
@@ -730,12 +3967,14 @@ mod tests {
     let latest_state_query = tonic::Request::new(ReadLatestReq {
       handle: handle.clone(),
       nonce: nonce.to_vec(),
+      consistency_token: vec![],
     });
 
     let ReadLatestResp {
       block,
       nonces,
       receipts,
+      ..
     } = server
       .read_latest(latest_state_query)
       .await
@@ -755,12 +3994,15 @@ mod tests {
     let req = tonic::Request::new(ReadByIndexReq {
       handle: handle.clone(),
       index: 1,
+      nonce: vec![],
+      snapshot: 0,
     });
 
     let ReadByIndexResp {
       block,
       nonces,
       receipts,
+      ..
     } = server.read_by_index(req).await.unwrap().into_inner();
     assert_eq!(block, b1.clone());
 
@@ -768,12 +4010,79 @@ mod tests {
     println!("Verifying ReadByIndex Response: {:?}", res.is_ok());
     assert!(res.is_ok());
 
+    let req = tonic::Request::new(GetLedgerStatsReq {
+      handle: handle.clone(),
+    });
+    let stats = server.get_ledger_stats(req).await.unwrap().into_inner();
+    assert_eq!(stats.height, 3);
+    assert_eq!(stats.appends, 3);
+    assert_eq!(stats.last_quorum_size, 1);
+
+    // Step 5b: a ledger whose genesis block restricts writes and reads to one client
+    let writer = PrivateKey::new();
+    let acl_handle = "acl-handle".as_bytes().to_vec();
+    let acl_policy = AccessPolicy::new(
+      vec![writer.get_public_key().unwrap().to_bytes()],
+      ReadVisibility::Writers,
+    );
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: acl_handle.clone(),
+      block: acl_policy.to_genesis_bytes(&block_bytes),
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
+    });
+    assert!(server.new_ledger(req).await.is_ok());
+
+    let signed = |req: AppendReq, sk: &PrivateKey| {
+      let access_request = AccessRequest::Append {
+        block: &req.block,
+        expected_height: req.expected_height as usize,
+      };
+      let sig = sk.sign(&access_request.message(&req.handle)).unwrap();
+      let mut req = tonic::Request::new(req);
+      req.metadata_mut().insert_bin(
+        CLIENT_PUBLIC_KEY_METADATA,
+        MetadataValue::from_bytes(&sk.get_public_key().unwrap().to_bytes()),
+      );
+      req.metadata_mut().insert_bin(
+        CLIENT_SIGNATURE_METADATA,
+        MetadataValue::from_bytes(&sig.to_bytes()),
+      );
+      req
+    };
+    let acl_append = AppendReq {
+      handle: acl_handle.clone(),
+      block: b1.clone(),
+      expected_height: 1,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
+    };
+    let res = server.append(tonic::Request::new(acl_append.clone())).await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::PermissionDenied);
+    let res = server
+      .append(signed(acl_append.clone(), &PrivateKey::new()))
+      .await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::PermissionDenied);
+    assert!(server.append(signed(acl_append, &writer)).await.is_ok());
+
+    let req = tonic::Request::new(ReadByIndexReq {
+      handle: acl_handle.clone(),
+      index: 1,
+      nonce: vec![],
+      snapshot: 0,
+    });
+    let res = server.read_by_index(req).await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::PermissionDenied);
+
     // Step 6: change the view by adding two new endorsers
     let endorser_args2 = endorser_args.clone() + " -p 9092";
     let endorser2 = launch_endorser(&endorser_cmd, endorser_args2);
     let endorser_args3 = endorser_args.clone() + " -p 9093";
     let endorser3 = launch_endorser(&endorser_cmd, endorser_args3);
 
+    let mut view_changes = server.get_state().subscribe_view_changes();
     let res = server
       .get_state()
       .replace_endorsers(&[
@@ -784,16 +4093,22 @@ mod tests {
     println!("new config with 2 endorsers: {:?}", res);
     assert!(res.is_ok());
 
-    let req = tonic::Request::new(ReadViewTailReq {});
+    let req = tonic::Request::new(ReadViewTailReq { nonce: vec![] });
     let res = server.read_view_tail(req).await;
     assert!(res.is_ok());
     let ReadViewTailResp {
       block,
       receipts,
-      height: _view_height,
+      height: view_height,
       attestations,
+      ..
     } = res.unwrap().into_inner();
 
+    // watchers are notified of the same view change
+    let view_change = view_changes.try_recv().unwrap();
+    assert_eq!(view_change.block.to_bytes(), block);
+    assert_eq!(view_change.height, view_height as usize);
+
     let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
     println!("Applying ReadViewByIndexResp Response: {:?}", res);
     assert!(res.is_ok());
@@ -806,11 +4121,15 @@ mod tests {
       handle: handle.clone(),
       block: message.to_vec(),
       expected_height: expected_height as u64,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
     });
 
     let AppendResp {
       hash_nonces,
       receipts,
+      ..
     } = server.append(req).await.unwrap().into_inner();
 
     let res = vs.verify_append(&handle, message, &hash_nonces, expected_height, &receipts);
@@ -822,12 +4141,14 @@ mod tests {
     let latest_state_query = tonic::Request::new(ReadLatestReq {
       handle: handle.clone(),
       nonce: nonce.to_vec(),
+      consistency_token: vec![],
     });
 
     let ReadLatestResp {
       block,
       nonces,
       receipts,
+      ..
     } = server
       .read_latest(latest_state_query)
       .await
@@ -975,7 +4296,7 @@ mod tests {
     println!("new config with 3 endorsers: {:?}", res);
     assert!(res.is_ok());
 
-    let req = tonic::Request::new(ReadViewTailReq {});
+    let req = tonic::Request::new(ReadViewTailReq { nonce: vec![] });
     let res = server.read_view_tail(req).await;
     assert!(res.is_ok());
     let ReadViewTailResp {
@@ -983,6 +4304,7 @@ mod tests {
       receipts,
       height: _view_height,
       attestations,
+      ..
     } = res.unwrap().into_inner();
 
     let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
@@ -994,12 +4316,14 @@ mod tests {
     let latest_state_query = tonic::Request::new(ReadLatestReq {
       handle: new_handle.clone(),
       nonce: nonce.to_vec(),
+      consistency_token: vec![],
     });
 
     let ReadLatestResp {
       block,
       nonces,
       receipts,
+      ..
     } = server
       .read_latest(latest_state_query)
       .await
@@ -1018,11 +4342,15 @@ mod tests {
       handle: new_handle.clone(),
       block: message.to_vec(),
       expected_height: 2_u64,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
     });
 
     let AppendResp {
       hash_nonces,
       receipts,
+      ..
     } = server.append(req).await.unwrap().into_inner();
 
     let res = vs.verify_append(&new_handle, message, &hash_nonces, 2, &receipts);
@@ -1128,7 +4456,7 @@ mod tests {
       let server2 = CoordinatorServiceState::new(coordinator2);
       println!("Started a new coordinator");
 
-      let req = tonic::Request::new(ReadViewTailReq {});
+      let req = tonic::Request::new(ReadViewTailReq { nonce: vec![] });
       let res = server2.read_view_tail(req).await;
       assert!(res.is_ok());
       let ReadViewTailResp {
@@ -1136,6 +4464,7 @@ mod tests {
         receipts,
         height: _view_height,
         attestations,
+        ..
       } = res.unwrap().into_inner();
 
       let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
@@ -1148,11 +4477,15 @@ mod tests {
         handle: new_handle.clone(),
         block: message.to_vec(),
         expected_height: 2_u64,
+        client_pk: vec![],
+        client_signature: vec![],
+        client_request_id: vec![],
       });
 
       let AppendResp {
         hash_nonces,
         receipts,
+        ..
       } = server2.append(req).await.unwrap().into_inner();
       let res = vs.verify_append(&new_handle, message, &hash_nonces, 2, &receipts);
       println!("Append verification: {:?}", res.is_ok());
@@ -1164,11 +4497,15 @@ mod tests {
         handle: new_handle2.clone(),
         block: message.to_vec(),
         expected_height: 2_u64,
+        client_pk: vec![],
+        client_signature: vec![],
+        client_request_id: vec![],
       });
 
       let AppendResp {
         hash_nonces,
         receipts,
+        ..
       } = server2.append(req).await.unwrap().into_inner();
       let res = vs.verify_append(&new_handle2, message, &hash_nonces, 2, &receipts);
       println!("Append verification: {:?}", res.is_ok());
@@ -1190,4 +4527,2255 @@ mod tests {
     println!("endorser5 process ID is {}", endorser5.child.id());
     println!("endorser6 process ID is {}", endorser6.child.id());
   }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_standby_promotion() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9094".to_string());
+    // the endorser announces itself just before it starts accepting connections
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let primary_store = InMemoryLedgerStore::new().with_replication(sender);
+    let primary = CoordinatorState::with_store(Box::new(primary_store), None)
+      .await
+      .unwrap();
+    primary
+      .replace_endorsers(&["http://[::1]:9094".to_string()])
+      .await
+      .unwrap();
+    let handle_bytes = "standby-handle".as_bytes();
+    primary
+      .create_ledger(None, handle_bytes, &[1, 2, 3])
+      .await
+      .unwrap();
+    for height in 1..=3 {
+      primary
+        .append_ledger(None, handle_bytes, &[height as u8], height)
+        .await
+        .unwrap();
+    }
+
+    let standby_store = InMemoryLedgerStore::new();
+    while let Ok(event) = receiver.try_recv() {
+      standby_store.apply_replication_event(&event).unwrap();
+    }
+    let summary = verify_replica(&standby_store).await.unwrap();
+    assert_eq!(summary.views, 1);
+    assert_eq!(summary.unendorsed, 0);
+    // the client ledger and the admin ledger
+    assert_eq!(summary.ledgers, 2);
+    drop(primary);
+
+    // the promoted standby serves the replicated ledgers through the same endorsers
+    let promoted = CoordinatorState::with_store(Box::new(standby_store), None)
+      .await
+      .unwrap();
+    let entry = promoted
+      .read_ledger_by_index(handle_bytes, 3)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_block().to_bytes(), vec![3]);
+    let res = promoted.append_ledger(None, handle_bytes, &[4], 4).await;
+    assert!(res.is_ok());
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_crash_recovery() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9108".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9109".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let store = InMemoryLedgerStore::new();
+    let coordinator = CoordinatorState::with_store(Box::new(store.clone()), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&[
+        "http://[::1]:9108".to_string(),
+        "http://[::1]:9109".to_string(),
+      ])
+      .await
+      .unwrap();
+    let handle_bytes = "recovery-handle".as_bytes();
+    coordinator
+      .create_ledger(None, handle_bytes, &[1, 2, 3])
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, handle_bytes, &[1], 1)
+      .await
+      .unwrap();
+
+    // the coordinator crashes after it appended to the store but before the endorsers signed
+    let handle = NimbleDigest::digest(handle_bytes);
+    store
+      .append_ledger(&handle, &Block::new(&[2]), 2)
+      .await
+      .unwrap();
+    drop(coordinator);
+
+    // the restarted coordinator keeps the view and has the endorsers sign the missing entry
+    let recovered = CoordinatorState::with_store(Box::new(store.clone()), None)
+      .await
+      .unwrap();
+    assert_eq!(recovered.get_view_height().unwrap(), 1);
+    assert_eq!(recovered.get_endorser_pks().len(), 2);
+    let entry = store.read_ledger_by_index(&handle, 2).await.unwrap();
+    assert_eq!(entry.get_receipts().get_signer_ids().len(), 2);
+    let res = recovered.append_ledger(None, handle_bytes, &[3], 3).await;
+    assert!(res.is_ok());
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_health() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let endorser = launch_endorser(&endorser_cmd, "-p 9110".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // a listening endorser is not ready to endorse until it is active in a view
+    let mut health = HealthClient::connect("http://[::1]:9110").await.unwrap();
+    let check = |service: &str| HealthCheckRequest {
+      service: service.to_string(),
+    };
+    let status = |resp: tonic::Response<HealthCheckResponse>| resp.into_inner().status;
+    let endorser_call = "endorser_proto.EndorserCall";
+    let res = health.check(check("")).await.unwrap();
+    assert_eq!(status(res), ServingStatus::Serving as i32);
+    let res = health.check(check(endorser_call)).await.unwrap();
+    assert_eq!(status(res), ServingStatus::NotServing as i32);
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    assert!(!coordinator.is_quorum_reachable().await);
+    coordinator
+      .replace_endorsers(&["http://[::1]:9110".to_string()])
+      .await
+      .unwrap();
+    assert!(coordinator.is_quorum_reachable().await);
+    let res = health.check(check(endorser_call)).await.unwrap();
+    assert_eq!(status(res), ServingStatus::Serving as i32);
+
+    drop(endorser);
+    assert!(!coordinator.is_quorum_reachable().await);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_gateway() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9112".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9112".to_string()])
+      .await
+      .unwrap();
+    let service = Arc::new(CoordinatorServiceState::new(Arc::new(coordinator)));
+    let addr = "[::1]:8192".parse().unwrap();
+    let _server = tokio::spawn(async move {
+      let _ = axum::Server::bind(&addr)
+        .serve(gateway::router(service, None).into_make_service())
+        .await;
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = hyper::Client::new();
+    let call = |method: &str, path: &str, body: serde_json::Value| {
+      let request = hyper::Request::builder()
+        .method(method)
+        .uri(format!("http://[::1]:8192{}", path))
+        .header("content-type", "application/json")
+        .body(hyper::Body::from(body.to_string()))
+        .unwrap();
+      let resp = client.request(request);
+      async move {
+        let resp = resp.await.unwrap();
+        let status = resp.status();
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        (
+          status,
+          serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        )
+      }
+    };
+
+    let handle = base64_url::encode("gateway-handle".as_bytes());
+    let path = format!("/ledgers/{}", handle);
+    let block = base64_url::encode("genesis".as_bytes());
+    let (status, resp) = call("PUT", &path, json!({ "block": block })).await;
+    assert_eq!(status, StatusCode::OK);
+    let receipts = base64_url::decode(resp["receipts"].as_str().unwrap()).unwrap();
+    assert!(Receipts::from_bytes(&receipts).is_ok());
+
+    let block = base64_url::encode("first".as_bytes());
+    let body = json!({ "block": block, "expected_height": 1 });
+    let (status, resp) = call("POST", &path, body.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["summary"]["quorum_verified"], json!(true));
+    // a conditional append that lost the race reports the tail
+    let (status, resp) = call("POST", &path, body).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(resp["height"], json!(1));
+
+    let nonce = base64_url::encode(&[7u8; 16]);
+    let latest = format!("{}?nonce={}", path, nonce);
+    let (status, resp) = call("GET", &latest, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["block"], json!(block));
+    let (status, resp) = call("GET", &format!("{}/entries/1", path), json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["block"], json!(block));
+    assert_eq!(resp["proof"], json!(null));
+    let (status, resp) = call("GET", "/views/1", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!resp["receipts"].as_str().unwrap().is_empty());
+
+    let (status, _resp) = call("GET", "/ledgers/not*base64", json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let missing = format!("/ledgers/{}", base64_url::encode("missing".as_bytes()));
+    let (status, _resp) = call("GET", &missing, json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+  }
+
+  #[tokio::test]
+  async fn test_gateway_auth() {
+    use tower::ServiceExt;
+
+    let path = std::env::temp_dir().join(format!(
+      "nimble-gateway-auth-{}.json",
+      rand::random::<u64>()
+    ));
+    std::fs::write(
+      &path,
+      r#"{"api_keys": [{"key": "reader", "permissions": ["read"]},
+                       {"key": "writer", "permissions": ["create", "read"]},
+                       {"key": "acme", "permissions": ["create", "read"], "namespaces": ["acme"]}]}"#,
+    )
+    .unwrap();
+    let authenticator = Arc::new(Authenticator::from_file(&path).unwrap());
+    let _ = std::fs::remove_file(&path);
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let service =
+      CoordinatorServiceState::new(Arc::new(coordinator)).with_authenticator(Some(authenticator));
+    let service = Arc::new(service);
+    let router = gateway::router(service.clone(), None);
+    let call_with = |method: &str, path: &str, api_key: Option<&str>, body: serde_json::Value| {
+      let mut request = axum::http::Request::builder()
+        .method(method)
+        .uri(path)
+        .header("content-type", "application/json");
+      if let Some(api_key) = api_key {
+        request = request.header(API_KEY_METADATA, api_key);
+      }
+      let request = request
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+      let response = router.clone().oneshot(request);
+      async move { response.await.unwrap().status() }
+    };
+    let genesis = base64_url::encode("genesis".as_bytes());
+    let call = |method: &str, path: &str, api_key: Option<&str>| {
+      call_with(method, path, api_key, json!({ "block": genesis }))
+    };
+
+    // the gateway needs the permissions that the same calls need over gRPC
+    let handle = format!("/ledgers/{}", base64_url::encode("gateway-auth".as_bytes()));
+    assert_eq!(call("GET", &handle, None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+      call("GET", &handle, Some("unknown")).await,
+      StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+      call("PUT", &handle, Some("reader")).await,
+      StatusCode::FORBIDDEN
+    );
+    assert_eq!(call("PUT", &handle, Some("writer")).await, StatusCode::OK);
+    assert_eq!(
+      call("POST", &handle, Some("writer")).await,
+      StatusCode::FORBIDDEN
+    );
+    let entry = format!("{}/entries/0", handle);
+    assert_eq!(call("GET", &entry, Some("reader")).await, StatusCode::OK);
+
+    // and reaches the namespaces of the request's credentials only
+    let orders = format!("/ledgers/{}", base64_url::encode("orders".as_bytes()));
+    let in_namespace = |namespace: &str| json!({ "block": genesis, "namespace": namespace });
+    assert_eq!(
+      call_with("PUT", &orders, Some("acme"), in_namespace("globex")).await,
+      StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+      call_with("PUT", &orders, Some("acme"), in_namespace("acme")).await,
+      StatusCode::OK
+    );
+    assert_eq!(
+      call("GET", &entry, Some("acme")).await,
+      StatusCode::FORBIDDEN
+    );
+
+    // with an auth file, a request whose credentials were not checked reaches no ledger
+    let req = ReadByIndexReq {
+      handle: "gateway-auth".as_bytes().to_vec(),
+      index: 0,
+      nonce: vec![],
+      snapshot: 0,
+    };
+    let status = service.read_by_index(Request::new(req)).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+  }
+
+  #[tokio::test]
+  async fn test_gateway_rate_limits() {
+    use tower::ServiceExt;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let service = Arc::new(CoordinatorServiceState::new(Arc::new(coordinator)));
+    let limiter = Arc::new(RateLimiter::new(RateLimits {
+      rate: 1.0,
+      burst: 1.0,
+      max_concurrent: 0,
+      key: ClientKey::Peer,
+    }));
+    let router = gateway::router(service, Some(limiter));
+    let call = |method: &str, handle: &str| {
+      let path = format!("/ledgers/{}", base64_url::encode(handle.as_bytes()));
+      let body = json!({ "block": base64_url::encode("genesis".as_bytes()) });
+      let mut request = axum::http::Request::builder()
+        .method(method)
+        .uri(path)
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+      let peer: std::net::SocketAddr = "192.0.2.1:443".parse().unwrap();
+      request.extensions_mut().insert(ConnectInfo(peer));
+      let response = router.clone().oneshot(request);
+      async move {
+        let response = response.await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (
+          status,
+          serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        )
+      }
+    };
+
+    // creations through the gateway count against the same limits as over gRPC
+    let (status, _resp) = call("PUT", "limited-1").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, resp) = call("PUT", "limited-2").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp["retry_after_ms"].as_u64().unwrap() > 0);
+    // reads are not limited
+    let (status, _resp) = call("GET", "limited-1").await;
+    assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_read_as_of_view() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9095".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9096".to_string());
+    // the endorsers announce themselves just before they start accepting connections
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9095".to_string()])
+      .await
+      .unwrap();
+    let handle_bytes = "as-of-view-handle".as_bytes();
+    coordinator
+      .create_ledger(None, handle_bytes, &[0])
+      .await
+      .unwrap();
+    for height in 1..=2 {
+      coordinator
+        .append_ledger(None, handle_bytes, &[height as u8], height)
+        .await
+        .unwrap();
+    }
+
+    coordinator
+      .replace_endorsers(&["http://[::1]:9096".to_string()])
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, handle_bytes, &[3], 3)
+      .await
+      .unwrap();
+
+    // the end of view 1 is reconstructed and matches what the endorsers committed to
+    let (entry, height, checkpoint_verified) = coordinator
+      .read_ledger_as_of_view(handle_bytes, 1)
+      .await
+      .unwrap();
+    assert_eq!(height, 2);
+    assert_eq!(entry.get_block().to_bytes(), vec![2]);
+    assert!(checkpoint_verified);
+
+    // the current view has not ended, so there is nothing to check against yet
+    let (entry, height, checkpoint_verified) = coordinator
+      .read_ledger_as_of_view(handle_bytes, 2)
+      .await
+      .unwrap();
+    assert_eq!(height, 3);
+    assert_eq!(entry.get_block().to_bytes(), vec![3]);
+    assert!(!checkpoint_verified);
+
+    let res = coordinator.read_ledger_as_of_view(handle_bytes, 3).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidViewHeight);
+    let res = coordinator.read_ledger_as_of_view(handle_bytes, 0).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidViewHeight);
+
+    // an entry endorsed in view 1 is linked to a fresh read of the tail in view 2
+    let nonce = rand::thread_rng().gen::<[u8; 16]>();
+    let (entry, proof) = coordinator
+      .read_ledger_by_index_with_proof(handle_bytes, 1, &nonce)
+      .await
+      .unwrap();
+    let tail = proof
+      .verify_chain(
+        &entry.get_block().to_bytes(),
+        &entry.get_nonces().to_bytes(),
+        1,
+      )
+      .unwrap();
+    assert_eq!(tail.get_height(), 3);
+    let receipts = Receipts::from_bytes(proof.get_tail_receipts()).unwrap();
+    assert_eq!(receipts.get_metablock().unwrap(), tail);
+    let res = coordinator
+      .read_ledger_by_index_with_proof(handle_bytes, 4, &nonce)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidHeight);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_append_batch() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9101".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9102".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&[
+        "http://[::1]:9101".to_string(),
+        "http://[::1]:9102".to_string(),
+      ])
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    let mut vs = VerifierState::new();
+    let ReadViewTailResp {
+      block,
+      receipts,
+      attestations,
+      ..
+    } = server
+      .read_view_tail(Request::new(ReadViewTailReq { nonce: vec![] }))
+      .await
+      .unwrap()
+      .into_inner();
+    vs.set_group_identity(NimbleDigest::digest(&block));
+    vs.apply_view_change(&block, &receipts, Some(&attestations))
+      .unwrap();
+
+    // the endorsers of the current view sign the view ledger's tail with a client's nonce
+    let nonce = rand::thread_rng().gen::<[u8; 16]>().to_vec();
+    let ReadViewTailResp {
+      block: tail_block,
+      receipts: tail_receipts,
+      height,
+      metablock,
+      ..
+    } = server
+      .read_view_tail(Request::new(ReadViewTailReq {
+        nonce: nonce.clone(),
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(tail_block, block);
+    assert_eq!(
+      vs.verify_read_view_tail(&tail_block, &nonce, &tail_receipts),
+      Ok(height as usize)
+    );
+    assert_eq!(MetaBlock::from_bytes(&metablock).unwrap().get_height(), 1);
+    assert!(vs
+      .verify_read_view_tail(&tail_block, &[0u8; 16], &tail_receipts)
+      .is_err());
+
+    let handle = b"batch-handle".to_vec();
+    let req = Request::new(NewLedgerReq {
+      handle: handle.clone(),
+      block: vec![0],
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
+    });
+    server.new_ledger(req).await.unwrap();
+
+    let blocks = vec![vec![1], vec![2], vec![3]];
+    let req = Request::new(AppendBatchReq {
+      handle: handle.clone(),
+      blocks: blocks.clone(),
+      expected_height: 1,
+    });
+    let AppendBatchResp {
+      hash_nonces,
+      metablocks,
+      receipts,
+      ..
+    } = server.append_batch(req).await.unwrap().into_inner();
+    assert_eq!(metablocks.len(), 3);
+    vs.verify_append(&handle, &blocks[2], &hash_nonces[2], 3, &receipts)
+      .unwrap();
+
+    // the metablocks chain from the first block to the endorsed tail
+    let metablocks = metablocks
+      .iter()
+      .map(|bytes| MetaBlock::from_bytes(bytes).unwrap())
+      .collect::<Vec<MetaBlock>>();
+    for (i, metablock) in metablocks.iter().enumerate() {
+      assert_eq!(metablock.get_height(), i + 1);
+      if i > 0 {
+        assert_eq!(*metablock.get_prev(), metablocks[i - 1].hash());
+      }
+    }
+    let tail = Receipts::from_bytes(&receipts).unwrap();
+    assert_eq!(tail.get_metablock().unwrap(), metablocks[2]);
+
+    // the blocks before the tail were endorsed in the same batch
+    let req = Request::new(ReadByIndexReq {
+      handle: handle.clone(),
+      index: 2,
+      nonce: vec![],
+      snapshot: 0,
+    });
+    let ReadByIndexResp {
+      block,
+      nonces,
+      receipts,
+      ..
+    } = server.read_by_index(req).await.unwrap().into_inner();
+    vs.verify_read_by_index(&handle, &block, &nonces, 2, &receipts)
+      .unwrap();
+
+    let req = Request::new(AppendBatchReq {
+      handle: handle.clone(),
+      blocks: vec![vec![4]],
+      expected_height: 3,
+    });
+    let status = server.append_batch(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    let req = Request::new(AppendBatchReq {
+      handle,
+      blocks: Vec::new(),
+      expected_height: 4,
+    });
+    let status = server.append_batch(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_receipt_compaction() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9114".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9114".to_string()])
+      .await
+      .unwrap();
+    let handle = b"compaction-handle".to_vec();
+    coordinator
+      .create_ledger(None, &handle, &[0])
+      .await
+      .unwrap();
+    for height in 1..=6 {
+      coordinator
+        .append_ledger(None, &handle, &[height as u8], height)
+        .await
+        .unwrap();
+    }
+
+    // with checkpoints every 3 entries, entries 1 and 2 lose their receipts
+    let policy = ReceiptCompaction {
+      checkpoint_interval: 3,
+      min_age_ms: 0,
+    };
+    let mut progress = HashMap::new();
+    coordinator
+      .compact_ledgers(&policy, &mut progress)
+      .await
+      .unwrap();
+    assert_eq!(progress[&NimbleDigest::digest(&handle)], 4);
+    let entry = coordinator.read_ledger_by_index(&handle, 2).await.unwrap();
+    assert!(entry.get_receipts().is_empty());
+
+    // the entry is proven by the hash chain from the checkpoint before it
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+    let resp = server
+      .read_checkpoint(Request::new(ReadCheckpointReq {
+        handle: handle.clone(),
+        index: 2,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.index, 0);
+    assert_eq!(resp.block_hashes.len(), 2);
+    let proof = CheckpointProof::new(
+      resp.index as usize,
+      resp.block,
+      resp.nonces,
+      resp.receipts.clone(),
+      resp
+        .block_hashes
+        .iter()
+        .map(|h| NimbleDigest::from_bytes(h).unwrap())
+        .collect(),
+    );
+    let checkpoint = Receipts::from_bytes(&resp.receipts)
+      .unwrap()
+      .get_metablock()
+      .unwrap();
+    let metablock = proof
+      .verify_chain(
+        &checkpoint,
+        &entry.get_block().to_bytes(),
+        &entry.get_nonces().to_bytes(),
+        2,
+      )
+      .unwrap();
+    let next = server
+      .get_state()
+      .read_ledger_by_index(&handle, 3)
+      .await
+      .unwrap();
+    assert_eq!(
+      *next.get_receipts().get_metablock().unwrap().get_prev(),
+      metablock.hash()
+    );
+
+    // reads with a proof to the tail still work for compacted entries
+    let resp = server
+      .read_by_index(Request::new(ReadByIndexReq {
+        handle: handle.clone(),
+        index: 1,
+        nonce: vec![7u8; 16],
+        snapshot: 0,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(resp.proof.is_some());
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_ledger_snapshots() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9115".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9115".to_string()])
+      .await
+      .unwrap();
+    let handle = b"snapshot-handle".to_vec();
+    let other_handle = b"other-snapshot-handle".to_vec();
+    for h in [&handle, &other_handle] {
+      coordinator.create_ledger(None, h, &[0]).await.unwrap();
+    }
+    for height in 1..=3 {
+      coordinator
+        .append_ledger(None, &handle, &[height as u8], height)
+        .await
+        .unwrap();
+    }
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    // snapshots get increasing ids, starting at 1
+    let resp = server
+      .checkpoint(Request::new(CheckpointReq {
+        handle: handle.clone(),
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.id, 1);
+    let snapshot = LedgerSnapshot::from_bytes(&resp.snapshot).unwrap();
+    assert_eq!(*snapshot.get_handle(), NimbleDigest::digest(&handle));
+    assert_eq!(snapshot.get_height(), 3);
+    let tail = Receipts::from_bytes(snapshot.get_receipts())
+      .unwrap()
+      .get_metablock()
+      .unwrap();
+    assert_eq!(tail.get_height(), 3);
+    let resp = server
+      .checkpoint(Request::new(CheckpointReq {
+        handle: other_handle.clone(),
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.id, 2);
+
+    // an entry is proven against the snapshot even after the ledger grows
+    server
+      .get_state()
+      .append_ledger(None, &handle, &[4], 4)
+      .await
+      .unwrap();
+    let resp = server
+      .read_by_index(Request::new(ReadByIndexReq {
+        handle: handle.clone(),
+        index: 1,
+        nonce: vec![],
+        snapshot: 1,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    let proof = resp.proof.unwrap();
+    assert_eq!(proof.block_hashes.len(), 2);
+    assert_eq!(proof.tail_receipts, snapshot.get_receipts());
+    let proof = InclusionProof::new(
+      MetaBlock::from_bytes(&proof.metablock).unwrap(),
+      proof
+        .block_hashes
+        .iter()
+        .map(|h| NimbleDigest::from_bytes(h).unwrap())
+        .collect(),
+      proof.tail_block,
+      proof.tail_nonces,
+      proof.tail_receipts,
+    );
+    assert_eq!(
+      proof.verify_chain(&resp.block, &resp.nonces, 1).unwrap(),
+      tail
+    );
+
+    // a snapshot of another ledger, or an entry past the snapshot, proves nothing
+    let res = server
+      .read_by_index(Request::new(ReadByIndexReq {
+        handle: handle.clone(),
+        index: 1,
+        nonce: vec![],
+        snapshot: 2,
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::InvalidArgument);
+    let res = server
+      .read_by_index(Request::new(ReadByIndexReq {
+        handle: handle.clone(),
+        index: 4,
+        nonce: vec![],
+        snapshot: 1,
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::OutOfRange);
+
+    // clients can read the checkpoint ledger, but cannot append to it
+    let entry = server
+      .get_state()
+      .read_ledger_by_index(CHECKPOINT_LEDGER_HANDLE, 1)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_block().to_bytes(), snapshot.to_bytes());
+    let res = server
+      .get_state()
+      .append_ledger(None, CHECKPOINT_LEDGER_HANDLE, &[5], 3)
+      .await;
+    assert!(matches!(res, Err(CoordinatorError::InvalidHandle)));
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_chunked_append() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9116".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9116".to_string()])
+      .await
+      .unwrap();
+    coordinator
+      .set_blob_store(
+        open_content_store("memory", &HashMap::new()).unwrap(),
+        Some(3 << 20),
+      )
+      .unwrap();
+    coordinator
+      .set_block_validation(BlockValidation {
+        max_block_size: Some(1024),
+        content_types: None,
+      })
+      .unwrap();
+    let handle = b"chunked-handle".to_vec();
+    coordinator
+      .create_ledger(None, &handle, &[0])
+      .await
+      .unwrap();
+
+    // a payload too large for a block goes to the blob store, and the ledger holds its reference
+    let payload = (0..(2 << 20)).map(|i| i as u8).collect::<Vec<u8>>();
+    let res = coordinator.append_ledger(None, &handle, &payload, 1).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::BlockTooLarge);
+    coordinator
+      .append_ledger_blob(&handle, &payload, 1)
+      .await
+      .unwrap();
+    let entry = coordinator.read_ledger_by_index(&handle, 1).await.unwrap();
+    let reference = BlobReference::from_bytes(&entry.get_block().to_bytes()).unwrap();
+    assert!(reference.matches(&payload));
+    let res = coordinator
+      .append_ledger_blob(&handle, &vec![0u8; (3 << 20) + 1], 2)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::BlobTooLarge);
+
+    // the payload streams back in chunks
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+    let mut stream = server
+      .read_blob(Request::new(ReadBlobReq {
+        handle: handle.clone(),
+        index: 1,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    let mut read = Vec::new();
+    let mut chunks = 0;
+    while let Some(resp) = stream.next().await {
+      read.extend_from_slice(&resp.unwrap().chunk);
+      chunks += 1;
+    }
+    assert_eq!(chunks, 2);
+    assert_eq!(read, payload);
+
+    // entries that are not blob references have no blob
+    let res = server
+      .read_blob(Request::new(ReadBlobReq {
+        handle: handle.clone(),
+        index: 0,
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::InvalidArgument);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_hash_only_append() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9127".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9127".to_string()])
+      .await
+      .unwrap();
+    let handle = b"hash-only-handle".to_vec();
+    coordinator
+      .create_ledger(None, &handle, &[0])
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    // the client sends only the hash of a payload it stores elsewhere
+    let payload = b"a payload kept outside the ledger".to_vec();
+    let block_hash = NimbleDigest::digest(&payload).to_bytes();
+    let resp = server
+      .append_hash_only(Request::new(AppendHashOnlyReq {
+        handle: handle.clone(),
+        block_hash: block_hash.clone(),
+        expected_height: 1,
+        client_pk: vec![],
+        client_signature: vec![],
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(!Receipts::from_bytes(&resp.receipts).unwrap().is_empty());
+    let res = server
+      .append_hash_only(Request::new(AppendHashOnlyReq {
+        handle: handle.clone(),
+        block_hash: block_hash[1..].to_vec(),
+        expected_height: 2,
+        client_pk: vec![],
+        client_signature: vec![],
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::InvalidArgument);
+
+    // reads mark the entry as external, and its block holds the hash of the payload
+    let resp = server
+      .read_by_index(Request::new(ReadByIndexReq {
+        handle: handle.clone(),
+        index: 1,
+        nonce: vec![],
+        snapshot: 0,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(resp.external);
+    assert!(ExternalBlock::from_bytes(&resp.block)
+      .unwrap()
+      .matches(&payload));
+    let resp = server
+      .read_latest(Request::new(ReadLatestReq {
+        handle: handle.clone(),
+        nonce: Nonce::random().to_bytes(),
+        consistency_token: vec![],
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(resp.external);
+    assert_eq!(
+      resp.block,
+      ExternalBlock::new(&block_hash).unwrap().to_bytes()
+    );
+    let resp = server
+      .read_by_index(Request::new(ReadByIndexReq {
+        handle: handle.clone(),
+        index: 0,
+        nonce: vec![],
+        snapshot: 0,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(!resp.external);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_read_range() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9117".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9117".to_string()])
+      .await
+      .unwrap();
+    let handle = b"range-handle".to_vec();
+    coordinator
+      .create_ledger(None, &handle, &[0])
+      .await
+      .unwrap();
+    let num_entries = RANGE_PAGE_SIZE + 10;
+    for height in 1..num_entries {
+      coordinator
+        .append_ledger(None, &handle, &(height as u64).to_le_bytes(), height)
+        .await
+        .unwrap();
+    }
+    // entries without receipts still stream with their metablocks
+    let policy = ReceiptCompaction {
+      checkpoint_interval: 16,
+      min_age_ms: 0,
+    };
+    coordinator
+      .compact_ledgers(&policy, &mut HashMap::new())
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    // a range from the middle through the tail spans several pages of the ledger store
+    let mut stream = server
+      .read_range(Request::new(ReadRangeReq {
+        handle: handle.clone(),
+        start: 3,
+        end: 0,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    let mut prev: Option<MetaBlock> = None;
+    let mut index = 3;
+    while let Some(resp) = stream.next().await {
+      let resp = resp.unwrap();
+      assert_eq!(resp.index, index as u64);
+      assert_eq!(resp.block, (index as u64).to_le_bytes());
+      let metablock = MetaBlock::from_bytes(&resp.metablock).unwrap();
+      if !resp.receipts.is_empty() {
+        let receipts = Receipts::from_bytes(&resp.receipts).unwrap();
+        assert_eq!(receipts.get_metablock().unwrap(), metablock);
+      }
+      if let Some(prev) = prev {
+        assert_eq!(*metablock.get_prev(), prev.hash());
+      }
+      prev = Some(metablock);
+      index += 1;
+    }
+    assert_eq!(index, num_entries);
+
+    // a bounded range stops at its end, and a range must start within the ledger
+    let stream = server
+      .read_range(Request::new(ReadRangeReq {
+        handle: handle.clone(),
+        start: 1,
+        end: 4,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(stream.collect::<Vec<_>>().await.len(), 3);
+    let res = server
+      .read_range(Request::new(ReadRangeReq {
+        handle: handle.clone(),
+        start: num_entries as u64,
+        end: 0,
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::OutOfRange);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_idempotent_append() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9119".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9119".to_string()])
+      .await
+      .unwrap();
+    let handle = b"idempotent-handle".to_vec();
+    coordinator
+      .create_ledger(None, &handle, &[0])
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    let append = |block: &[u8], expected_height: u64, client_request_id: &[u8]| {
+      Request::new(AppendReq {
+        handle: handle.clone(),
+        block: block.to_vec(),
+        expected_height,
+        client_pk: vec![],
+        client_signature: vec![],
+        client_request_id: client_request_id.to_vec(),
+      })
+    };
+    let first = server
+      .append(append(b"once", 1, b"request-1"))
+      .await
+      .unwrap()
+      .into_inner();
+
+    // a retry gets the original response back without appending again
+    let retry = server
+      .append(append(b"once", 1, b"request-1"))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(retry.hash_nonces, first.hash_nonces);
+    assert_eq!(retry.receipts, first.receipts);
+    let (_tail, height) = server
+      .get_state()
+      .ledger_store
+      .read_ledger_tail(&NimbleDigest::digest(&handle))
+      .await
+      .unwrap();
+    assert_eq!(height, 1);
+
+    // the request ID cannot be reused for another block, but another ID appends as usual
+    let res = server.append(append(b"other", 2, b"request-1")).await;
+    assert_eq!(res.unwrap_err().code(), Code::InvalidArgument);
+    server
+      .append(append(b"other", 2, b"request-2"))
+      .await
+      .unwrap();
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_subscribe() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9118".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9118".to_string()])
+      .await
+      .unwrap();
+    let handle = b"subscribe-handle".to_vec();
+    let other_handle = b"other-subscribe-handle".to_vec();
+    for h in [&handle, &other_handle] {
+      coordinator.create_ledger(None, h, &[0]).await.unwrap();
+    }
+    for height in 1..=2 {
+      coordinator
+        .append_ledger(None, &handle, &[height as u8], height)
+        .await
+        .unwrap();
+    }
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    // the subscriber first catches up on the entries already in the ledger
+    let mut stream = server
+      .subscribe(Request::new(SubscribeReq {
+        handle: handle.clone(),
+        from_height: 1,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    for index in 1..=2u64 {
+      let resp = stream.next().await.unwrap().unwrap();
+      assert_eq!(resp.index, index);
+    }
+
+    // then receives appends as they happen, including batches, but not those of other ledgers
+    server
+      .get_state()
+      .append_ledger(None, &other_handle, &[9], 1)
+      .await
+      .unwrap();
+    server
+      .get_state()
+      .append_ledger(None, &handle, &[3], 3)
+      .await
+      .unwrap();
+    server
+      .get_state()
+      .append_ledger_batch(&handle, &[vec![4], vec![5]], 4)
+      .await
+      .unwrap();
+    let mut prev: Option<MetaBlock> = None;
+    for index in 3..=5u64 {
+      let resp = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+      assert_eq!(resp.index, index);
+      assert_eq!(resp.block, vec![index as u8]);
+      let metablock = MetaBlock::from_bytes(&resp.metablock).unwrap();
+      let receipts = Receipts::from_bytes(&resp.receipts).unwrap();
+      assert_eq!(receipts.get_metablock().unwrap(), metablock);
+      if let Some(prev) = prev {
+        assert_eq!(*metablock.get_prev(), prev.hash());
+      }
+      prev = Some(metablock);
+    }
+
+    let res = server
+      .subscribe(Request::new(SubscribeReq {
+        handle: b"unknown-handle".to_vec(),
+        from_height: 0,
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::NotFound);
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_straggler_receipts() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9103".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9104".to_string());
+    let _endorser3 = launch_endorser(&endorser_cmd, "-p 9105".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&[
+        "http://[::1]:9103".to_string(),
+        "http://[::1]:9104".to_string(),
+        "http://[::1]:9105".to_string(),
+      ])
+      .await
+      .unwrap();
+
+    // a quorum of two is enough to answer, and the third receipt is attached once it arrives
+    let handle = b"straggler-handle".to_vec();
+    coordinator
+      .create_ledger(None, &handle, &[0])
+      .await
+      .unwrap();
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(None, &handle, &[1], 1)
+      .await
+      .unwrap();
+    let (signers, quorum_verified, lagging) = coordinator.summarize_receipts(&handle, &receipts);
+    assert!(quorum_verified);
+    assert!(signers.len() >= 2);
+    assert_eq!(signers.len() + lagging.len(), 3);
+    assert!(lagging.iter().all(|pk| !signers.contains(pk)));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    for index in 0..=1 {
+      let entry = coordinator
+        .ledger_store
+        .read_ledger_by_index(&NimbleDigest::digest(&handle), index)
+        .await
+        .unwrap();
+      assert_eq!(entry.get_receipts().get_signer_ids().len(), 3);
+    }
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_endorser_attestation() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9106 --attestation mock".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9107".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .set_attestation_verifier(verifier_from_name("mock").unwrap())
+      .unwrap();
+
+    // the endorser without evidence is not admitted to the view
+    coordinator
+      .replace_endorsers(&[
+        "http://[::1]:9106".to_string(),
+        "http://[::1]:9107".to_string(),
+      ])
+      .await
+      .unwrap();
+    assert_eq!(
+      coordinator.get_endorser_uris(),
+      vec!["http://[::1]:9106".to_string()]
+    );
+
+    // the view ledger block carries the evidence of the endorser it lists
+    let (tail, _height) = coordinator
+      .ledger_store
+      .read_view_ledger_tail()
+      .await
+      .unwrap();
+    let config = tail.get_block().to_bytes();
+    let attestations = retrieve_attestations_from_config(&config).unwrap();
+    assert_eq!(attestations.len(), 1);
+    assert_eq!(attestations[0].0, coordinator.get_endorser_pks()[0]);
+    assert!(
+      verify_config_attestations(verifier_from_name("mock").unwrap().as_ref(), &config).is_ok()
+    );
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_tail_cache() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser = launch_endorser(&endorser_cmd, "-p 9123".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .replace_endorsers(&["http://[::1]:9123".to_string()])
+      .await
+      .unwrap();
+    coordinator.set_tail_cache(16, None);
+
+    let handle_bytes = "tail-cache-handle".as_bytes();
+    coordinator
+      .create_ledger(None, handle_bytes, &[0])
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, handle_bytes, &[1], 1)
+      .await
+      .unwrap();
+
+    // the first read of the tail caches it, and the reads after it are answered from the cache
+    // with the same entry
+    let first = coordinator
+      .read_ledger_tail(handle_bytes, &rand::random::<[u8; 16]>())
+      .await
+      .unwrap();
+    let second = coordinator
+      .read_ledger_tail(handle_bytes, &rand::random::<[u8; 16]>())
+      .await
+      .unwrap();
+    assert_eq!(second.get_block().to_bytes(), vec![1]);
+    assert_eq!(first.get_timestamp(), second.get_timestamp());
+    assert!(second.get_timestamp().is_some());
+    let stats = coordinator.get_tail_cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+    // an append moves the tail, and the read after it finds the new entry in the store
+    coordinator
+      .append_ledger(None, handle_bytes, &[2], 2)
+      .await
+      .unwrap();
+    let third = coordinator
+      .read_ledger_tail(handle_bytes, &rand::random::<[u8; 16]>())
+      .await
+      .unwrap();
+    assert_eq!(third.get_block().to_bytes(), vec![2]);
+    let stats = coordinator.get_tail_cache_stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_endorser_lease() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9124".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9125".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .set_endorser_lease(Some(Duration::from_secs(2)))
+      .unwrap();
+    coordinator
+      .replace_endorsers(&[
+        "http://[::1]:9124".to_string(),
+        "http://[::1]:9125".to_string(),
+      ])
+      .await
+      .unwrap();
+    let expiry = coordinator.get_endorser_lease_expiry().unwrap();
+    assert!(expiry.is_some());
+
+    let handle_bytes = "lease-handle".as_bytes();
+    coordinator
+      .create_ledger(None, handle_bytes, &[0])
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, handle_bytes, &[1], 1)
+      .await
+      .unwrap();
+
+    // endorsers whose lease expired sign nothing, so the coordinator asks them for nothing
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let res = coordinator
+      .read_ledger_tail(handle_bytes, &rand::random::<[u8; 16]>())
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::EndorserLeaseExpired);
+    let res = coordinator.append_ledger(None, handle_bytes, &[2], 2).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::EndorserLeaseExpired);
+
+    // a view change that keeps the endorsers renews their lease, and they sign again
+    assert_eq!(coordinator.renew_endorser_leases().await.unwrap(), 2);
+    assert!(coordinator.get_endorser_lease_expiry().unwrap() > expiry);
+    let res = coordinator
+      .read_ledger_tail(handle_bytes, &rand::random::<[u8; 16]>())
+      .await
+      .unwrap();
+    assert_eq!(res.get_block().to_bytes(), vec![1]);
+    coordinator
+      .append_ledger(None, handle_bytes, &[2], 2)
+      .await
+      .unwrap();
+
+    let event = coordinator.read_admin_event(1).await.unwrap();
+    let event = serde_json::from_slice::<AdminEvent>(&event.get_block().to_bytes()).unwrap();
+    assert!(matches!(
+      event.action,
+      AdminAction::RenewEndorserLeases { view_height: 2, .. }
+    ));
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_reconfigure_endorsers() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    // endorsers with different signature schemes serve the same view
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9097".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9098 -s ed25519".to_string());
+    let _endorser3 = launch_endorser(&endorser_cmd, "-p 9099 -s secp256k1".to_string());
+    let _endorser4 = launch_endorser(&endorser_cmd, "-p 9100".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+    let resp = server
+      .replace_endorsers(Request::new(ReplaceEndorsersReq {
+        uris: vec!["http://[::1]:9097".to_string()],
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.view_height, 1);
+    assert_eq!(resp.endorsers.len(), 1);
+
+    let handle_bytes = "reconfigure-handle".as_bytes();
+    let state = server.get_state();
+    state.create_ledger(None, handle_bytes, &[0]).await.unwrap();
+    state
+      .append_ledger(None, handle_bytes, &[1], 1)
+      .await
+      .unwrap();
+
+    let resp = server
+      .replace_endorsers(Request::new(ReplaceEndorsersReq {
+        uris: vec![
+          "http://[::1]:9098".to_string(),
+          "http://[::1]:9099".to_string(),
+          "http://[::1]:9100".to_string(),
+        ],
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.view_height, 2);
+    assert_eq!(resp.endorsers.len(), 3);
+    state
+      .append_ledger(None, handle_bytes, &[2], 2)
+      .await
+      .unwrap();
+
+    // one endorser can leave, since the other two still form a quorum of the view
+    let resp = server
+      .remove_endorsers(Request::new(RemoveEndorsersReq {
+        uris: vec!["http://[::1]:9100".to_string()],
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.num_endorsers, 2);
+    state
+      .append_ledger(None, handle_bytes, &[3], 3)
+      .await
+      .unwrap();
+    let entry = state.read_ledger_by_index(handle_bytes, 3).await.unwrap();
+    assert_eq!(entry.get_receipts().get_signer_ids().len(), 2);
+
+    // the removed endorser catches up on the append it missed and serves requests again
+    let resp = server
+      .readmit_endorsers(Request::new(ReadmitEndorsersReq {
+        uris: vec!["http://[::1]:9100".to_string()],
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.num_endorsers, 3);
+    let entry = state.read_ledger_by_index(handle_bytes, 3).await.unwrap();
+    assert_eq!(entry.get_receipts().get_signer_ids().len(), 3);
+    let res = server
+      .readmit_endorsers(Request::new(ReadmitEndorsersReq {
+        uris: vec!["http://[::1]:9097".to_string()],
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::FailedPrecondition);
+    let resp = server
+      .remove_endorsers(Request::new(RemoveEndorsersReq {
+        uris: vec!["http://[::1]:9100".to_string()],
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.num_endorsers, 2);
+
+    let res = server
+      .remove_endorsers(Request::new(RemoveEndorsersReq {
+        uris: vec!["http://[::1]:9099".to_string()],
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::FailedPrecondition);
+    let res = server
+      .remove_endorsers(Request::new(RemoveEndorsersReq {
+        uris: vec!["http://[::1]:9100".to_string()],
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::NotFound);
+    let res = server
+      .replace_endorsers(Request::new(ReplaceEndorsersReq {
+        uris: vec!["http://[::1]:9101".to_string()],
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::InvalidArgument);
+
+    // with one of the two endorsers that serve the view gone, a quorum of them cannot be locked,
+    // so the view change aborts: the new endorser is dropped and the other endorser unlocked
+    drop(_endorser3);
+    let _endorser5 = launch_endorser(&endorser_cmd, "-p 9128".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let res = server
+      .replace_endorsers(Request::new(ReplaceEndorsersReq {
+        uris: vec!["http://[::1]:9128".to_string()],
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::Unavailable);
+    assert_eq!(state.get_view_height().unwrap(), 2);
+    assert!(!state
+      .get_endorser_uris()
+      .contains(&"http://[::1]:9128".to_string()));
+    server
+      .readmit_endorsers(Request::new(ReadmitEndorsersReq {
+        uris: vec!["http://[::1]:9100".to_string()],
+      }))
+      .await
+      .unwrap();
+    state
+      .append_ledger(None, handle_bytes, &[4], 4)
+      .await
+      .unwrap();
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_rotate_endorser_key() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9120".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9121 -s ed25519".to_string());
+    let _endorser3 = launch_endorser(&endorser_cmd, "-p 9122".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let uris = vec![
+      "http://[::1]:9120".to_string(),
+      "http://[::1]:9121".to_string(),
+      "http://[::1]:9122".to_string(),
+    ];
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator.replace_endorsers(&uris).await.unwrap();
+    let handle_bytes = "rotation-handle".as_bytes();
+    coordinator
+      .create_ledger(None, handle_bytes, &[0])
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+    let state = server.get_state();
+
+    let mut vs = VerifierState::new();
+    let view = state.read_view_by_index(1).await.unwrap();
+    vs.set_group_identity(view.get_block().hash());
+    vs.apply_view_change(
+      &view.get_block().to_bytes(),
+      &view.get_receipts().to_bytes(),
+      Some(ATTESTATION_STR.as_bytes()),
+    )
+    .unwrap();
+
+    // each rotation replaces one key, and the other endorsers keep theirs and stay in the view
+    for (idx, uri) in uris[..2].iter().enumerate() {
+      let old_pk = state.get_endorser_pk(uri).unwrap();
+      let others = state
+        .get_endorser_pks()
+        .into_iter()
+        .filter(|pk| *pk != old_pk)
+        .collect::<HashSet<Vec<u8>>>();
+      let resp = server
+        .rotate_endorser_key(Request::new(RotateEndorserKeyReq { uri: uri.clone() }))
+        .await
+        .unwrap()
+        .into_inner();
+      assert_eq!(resp.view_height as usize, idx + 2);
+      assert_ne!(resp.pk, old_pk);
+      assert_eq!(state.get_endorser_pk(uri).unwrap(), resp.pk);
+      let pks = state
+        .get_endorser_pks()
+        .into_iter()
+        .collect::<HashSet<Vec<u8>>>();
+      assert_eq!(pks.len(), 3);
+      assert!(pks.is_superset(&others));
+
+      // verifiers accept the new key on the old key's handover
+      let view = state.read_view_by_index(idx + 2).await.unwrap();
+      let config = view.get_block().to_bytes();
+      let receipts = view.get_receipts().to_bytes();
+      assert!(vs.apply_view_change(&config, &receipts, None).is_err());
+      vs.apply_key_rotation(&config, &receipts).unwrap();
+
+      // all three endorsers sign appends in the new view, the last after the quorum
+      state
+        .append_ledger(None, handle_bytes, &[idx as u8 + 1], idx + 1)
+        .await
+        .unwrap();
+      tokio::time::sleep(Duration::from_millis(500)).await;
+      let entry = state
+        .read_ledger_by_index(handle_bytes, idx + 1)
+        .await
+        .unwrap();
+      let signers = entry.get_receipts().get_signer_ids();
+      assert_eq!(signers.len(), 3);
+      assert!(signers.contains(&resp.pk));
+      assert!(!signers.contains(&old_pk));
+    }
+
+    let res = server
+      .rotate_endorser_key(Request::new(RotateEndorserKeyReq {
+        uri: "http://[::1]:9123".to_string(),
+      }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_admin_ledger_is_reserved() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: ADMIN_LEDGER_HANDLE.to_vec(),
+      block: vec![],
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
+    });
+    let res = server.new_ledger(req).await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+    let req = tonic::Request::new(AppendReq {
+      handle: ADMIN_LEDGER_HANDLE.to_vec(),
+      block: vec![],
+      expected_height: 1,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
+    });
+    let res = server.append(req).await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+    let req = tonic::Request::new(ReadAdminLedgerReq { index: 0 });
+    assert!(server.read_admin_ledger(req).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_decommission_unknown_endorser() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let res = coordinator
+      .decommission_endorser("http://[::1]:9090", &["http://[::1]:9091".to_string()])
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidEndorserUri);
+  }
+
+  #[tokio::test]
+  async fn test_maintenance_mode() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    server
+      .get_state()
+      .enter_maintenance(Duration::from_secs(60))
+      .unwrap();
+    assert!(server.get_state().get_maintenance_remaining().is_some());
+
+    let req = tonic::Request::new(AppendReq {
+      handle: vec![1, 2, 3],
+      block: vec![4, 5, 6],
+      expected_height: 1,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
+    });
+    let status = server.append(req).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+
+    // the deadline ends maintenance mode even without an explicit exit
+    server
+      .get_state()
+      .enter_maintenance(Duration::from_millis(1))
+      .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(server.get_state().get_maintenance_remaining().is_none());
+
+    server
+      .get_state()
+      .enter_maintenance(Duration::from_secs(60))
+      .unwrap();
+    server.get_state().exit_maintenance().unwrap();
+    assert!(server.get_state().get_maintenance_remaining().is_none());
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_admin_service() {
+    let endorser_cmd = match std::env::var_os("ENDORSER_CMD") {
+      None => panic!("The ENDORSER_CMD environment variable is not specified"),
+      Some(o) => o,
+    };
+    let _endorser1 = launch_endorser(&endorser_cmd, "-p 9111".to_string());
+    let _endorser2 = launch_endorser(&endorser_cmd, "-p 9112".to_string());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+    for uri in ["http://[::1]:9111", "http://[::1]:9112"] {
+      server
+        .replace_endorsers(Request::new(ReplaceEndorsersReq {
+          uris: vec![uri.to_string()],
+        }))
+        .await
+        .unwrap();
+    }
+
+    // the view ledger lists both views, the current one last
+    let resp = server
+      .dump_view_ledger(Request::new(DumpViewLedgerReq {}))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.entries.len(), 2);
+    let view_tail = server.get_state().read_view_by_index(2).await.unwrap();
+    assert_eq!(resp.entries[1].block, view_tail.get_block().to_bytes());
+
+    let resp = server
+      .get_endorser_health(Request::new(GetEndorserHealthReq {}))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(resp.endorsers.len(), 1);
+    assert_eq!(resp.endorsers[0].uri, "http://[::1]:9112");
+    assert_eq!(
+      resp.endorsers[0].health,
+      EndorserHealthState::Healthy as i32
+    );
+
+    // appends are paused until maintenance mode ends
+    let handle_bytes = "admin-service-handle".as_bytes();
+    let state = server.get_state();
+    state.create_ledger(None, handle_bytes, &[0]).await.unwrap();
+    let res = server
+      .enter_maintenance(Request::new(EnterMaintenanceReq { seconds: 0 }))
+      .await;
+    assert_eq!(res.unwrap_err().code(), Code::InvalidArgument);
+    let resp = server
+      .enter_maintenance(Request::new(EnterMaintenanceReq { seconds: 60 }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(resp.enabled);
+    assert!(resp.remaining_seconds > 0);
+    assert!(state
+      .append_ledger(None, handle_bytes, &[1], 1)
+      .await
+      .is_err());
+    let resp = server
+      .exit_maintenance(Request::new(ExitMaintenanceReq {}))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(!resp.enabled);
+    state
+      .append_ledger(None, handle_bytes, &[1], 1)
+      .await
+      .unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_block_validation() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    coordinator
+      .set_block_validation(BlockValidation {
+        max_block_size: Some(64),
+        content_types: Some(HashSet::from(["application/json".to_string()])),
+      })
+      .unwrap();
+
+    // blocks are checked before anything is appended, so no endorsers are needed
+    let handle = b"envelope-handle".to_vec();
+    let res = coordinator
+      .append_ledger(None, &handle, &[0u8; 65], 1)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::BlockTooLarge);
+    let res = coordinator.append_ledger(None, &handle, b"plain", 1).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::ContentTypeNotAllowed);
+    let mut malformed = BlockEnvelope::new("application/json", b"c", 0, b"{}")
+      .to_bytes()
+      .unwrap();
+    malformed.push(0);
+    let res = coordinator
+      .append_ledger_batch(&handle, &[malformed], 1)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidBlockEnvelope);
+
+    // an allowed envelope passes validation and fails only for the missing ledger
+    let envelope = BlockEnvelope::new("application/json", b"c", 0, b"{}")
+      .to_bytes()
+      .unwrap();
+    let res = coordinator.append_ledger(None, &handle, &envelope, 1).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::UnknownLedger);
+
+    // only chunked appends write blob references, and only with a blob store
+    let reference = BlobReference::new(b"payload").to_bytes();
+    let res = coordinator
+      .append_ledger(None, &handle, &reference, 1)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidBlobReference);
+    let res = coordinator.append_ledger_blob(&handle, b"payload", 1).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::BlobStoreNotConfigured);
+    let status = ledger_status(CoordinatorError::BlockTooLarge, "Failed to append");
+    assert_eq!(status.code(), Code::ResourceExhausted);
+
+    // only hash-only appends write external blocks, which must hold a digest, and the limits on
+    // blocks do not apply to them
+    let external = ExternalBlock::for_payload(b"payload");
+    let res = coordinator
+      .append_ledger(None, &handle, &external.to_bytes(), 1)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidExternalBlock);
+    let res = coordinator
+      .append_ledger_hash_only(&handle, &[1u8; 31], 1)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidExternalBlock);
+    let res = coordinator
+      .append_ledger_hash_only(&handle, &external.get_hash().to_bytes(), 1)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::UnknownLedger);
+    let status = ledger_status(CoordinatorError::InvalidExternalBlock, "Failed to append");
+    assert_eq!(status.code(), Code::InvalidArgument);
+  }
+
+  #[tokio::test]
+  async fn test_client_request_ids() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let handle = b"request-id-handle".to_vec();
+    let long_id = vec![0u8; MAX_CLIENT_REQUEST_ID_SIZE + 1];
+    let res = coordinator
+      .append_ledger_once(&handle, b"block", 1, &long_id)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidClientRequestId);
+
+    // a retry while the original append holds the request ID is told to back off
+    let digest = NimbleDigest::digest(&handle);
+    coordinator
+      .ledger_store
+      .claim_append_request(&digest, b"in-flight", 60_000)
+      .await
+      .unwrap();
+    let res = coordinator
+      .append_ledger_once(&handle, b"block", 1, b"in-flight")
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::AppendInProgress);
+    let status = ledger_status(CoordinatorError::AppendInProgress, "Failed to append");
+    assert_eq!(status.code(), Code::Aborted);
+
+    // a failed append gives up its claim so that it can be retried
+    let res = coordinator
+      .append_ledger_once(&handle, b"block", 1, b"failed")
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::UnknownLedger);
+    let claim = coordinator
+      .ledger_store
+      .claim_append_request(&digest, b"failed", 60_000)
+      .await;
+    assert_eq!(claim.unwrap(), AppendRequest::Claimed);
+  }
+
+  #[tokio::test]
+  async fn test_append_credentials_in_request() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    let writer = PrivateKey::new();
+    let handle = b"acl-ledger".to_vec();
+    let policy = AccessPolicy::new(
+      vec![writer.get_public_key().unwrap().to_bytes()],
+      ReadVisibility::Public,
+    );
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: handle.clone(),
+      block: policy.to_genesis_bytes(&[]),
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
+    });
+    assert!(server.new_ledger(req).await.is_ok());
+
+    let append = |sk: &PrivateKey| {
+      let access_request = AccessRequest::Append {
+        block: &[1],
+        expected_height: 1,
+      };
+      let sig = sk.sign(&access_request.message(&handle)).unwrap();
+      AppendReq {
+        handle: handle.clone(),
+        block: vec![1],
+        expected_height: 1,
+        client_pk: sk.get_public_key().unwrap().to_bytes(),
+        client_signature: sig.to_bytes(),
+        client_request_id: vec![],
+      }
+    };
+
+    // a client that cannot set metadata signs the append in the request itself
+    let mut req = append(&writer);
+    req.client_signature = vec![];
+    let status = server.append(tonic::Request::new(req)).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    let req = append(&PrivateKey::new());
+    let status = server.append(tonic::Request::new(req)).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    assert!(server
+      .append(tonic::Request::new(append(&writer)))
+      .await
+      .is_ok());
+  }
+
+  #[tokio::test]
+  async fn test_ledger_namespaces() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+    // the auth layer records the namespaces that a request's credentials reach
+    fn scoped<T>(message: T) -> Request<T> {
+      let mut req = Request::new(message);
+      req
+        .extensions_mut()
+        .insert(NamespaceScope::Only(HashSet::from([String::from("acme")])));
+      req
+    }
+    let new_ledger = |namespace: &str| NewLedgerReq {
+      handle: b"orders".to_vec(),
+      block: vec![],
+      label: String::new(),
+      namespace: namespace.to_string(),
+      client_nonce: Vec::new(),
+    };
+
+    assert!(server.new_ledger(scoped(new_ledger("acme"))).await.is_ok());
+    let status = server
+      .new_ledger(scoped(new_ledger("globex")))
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    let status = server.new_ledger(scoped(new_ledger(""))).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    let status = server
+      .new_ledger(tonic::Request::new(new_ledger("Not Valid")))
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert!(server
+      .new_ledger(tonic::Request::new(new_ledger("globex")))
+      .await
+      .is_ok());
+
+    // one tenant cannot read another's ledger, even with the same handle
+    let read = |namespace: &str| ReadByIndexReq {
+      handle: namespaced_handle(namespace, b"orders"),
+      index: 0,
+      nonce: vec![],
+      snapshot: 0,
+    };
+    assert!(server.read_by_index(scoped(read("acme"))).await.is_ok());
+    let status = server
+      .read_by_index(scoped(read("globex")))
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    let req = ListLedgersReq {
+      cursor: vec![],
+      page_size: 0,
+    };
+    let ledgers = server.list_ledgers(scoped(req)).await.unwrap().into_inner();
+    assert_eq!(ledgers.ledgers.len(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_ledger_store_errors() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+    let handle = b"ledger".to_vec();
+
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: handle.clone(),
+      block: vec![],
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
+    });
+    let resp = server.new_ledger(req).await.unwrap().into_inner();
+    assert_eq!(resp.handle, handle);
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: handle.clone(),
+      block: vec![],
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
+    });
+    let status = server.new_ledger(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::AlreadyExists);
+    // the status carries the receipts of the genesis block of the ledger that exists
+    assert_eq!(status.details(), resp.receipts.as_slice());
+
+    // the handle of a ledger can be derived from a nonce of the client's instead
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: Vec::new(),
+      block: vec![1],
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: vec![7],
+    });
+    let resp = server.new_ledger(req).await.unwrap().into_inner();
+    assert_eq!(resp.handle, derive_ledger_handle(&[7], &[1]).unwrap());
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: handle.clone(),
+      block: vec![1],
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: vec![7],
+    });
+    let status = server.new_ledger(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    // a conditional append that does not follow the tail is rejected without touching the ledger
+    let req = tonic::Request::new(AppendReq {
+      handle: handle.clone(),
+      block: vec![1],
+      expected_height: 2,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
+    });
+    let status = server.append(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    // the status carries the height of the tail, so the client can retry on top of it
+    assert_eq!(status.details(), &0u64.to_le_bytes());
+
+    let req = tonic::Request::new(ReadByIndexReq {
+      handle: handle.clone(),
+      index: 1,
+      nonce: vec![],
+      snapshot: 0,
+    });
+    let status = server.read_by_index(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+
+    let req = tonic::Request::new(ReadLatestReq {
+      handle: handle.clone(),
+      nonce: vec![1],
+      consistency_token: vec![],
+    });
+    let status = server.read_latest(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    // a consistency token must name the ledger, and the read waits for the entry it names
+    let metablock = MetaBlock::new(&NimbleDigest::default(), &NimbleDigest::default(), 1);
+    let tokens = [
+      (vec![1u8; 3], Code::InvalidArgument),
+      (
+        ConsistencyToken::new(&NimbleDigest::digest(b"other"), &metablock).to_bytes(),
+        Code::InvalidArgument,
+      ),
+      (
+        ConsistencyToken::new(&NimbleDigest::digest(&handle), &metablock).to_bytes(),
+        Code::Unavailable,
+      ),
+    ];
+    for (consistency_token, code) in tokens {
+      let req = tonic::Request::new(ReadLatestReq {
+        handle: handle.clone(),
+        nonce: vec![0; 16],
+        consistency_token,
+      });
+      assert_eq!(server.read_latest(req).await.unwrap_err().code(), code);
+    }
+
+    // requests for a ledger that was never created
+    let req = tonic::Request::new(AppendReq {
+      handle: b"unknown".to_vec(),
+      block: vec![1],
+      expected_height: 1,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
+    });
+    assert_eq!(server.append(req).await.unwrap_err().code(), Code::NotFound);
+    let req = tonic::Request::new(ReadLatestReq {
+      handle: b"unknown".to_vec(),
+      nonce: vec![0; 16],
+      consistency_token: vec![],
+    });
+    let status = server.read_latest(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    let req = tonic::Request::new(ReadByIndexReq {
+      handle: b"unknown".to_vec(),
+      index: 0,
+      nonce: vec![],
+      snapshot: 0,
+    });
+    let status = server.read_by_index(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    // a failing store is reported as unavailable, so that clients retry
+    let status = ledger_status(CoordinatorError::FailedToCallLedgerStore, "Failed");
+    assert_eq!(status.code(), Code::Unavailable);
+    let status = ledger_status(CoordinatorError::FailedToConnectToEndorser, "Failed");
+    assert_eq!(status.code(), Code::Aborted);
+  }
+
+  // requests that panicked or hung the coordinator before its handlers checked their inputs; the
+  // fuzz target in `fuzz/` drives the coordinator with arbitrary requests
+  #[tokio::test]
+  async fn test_malformed_requests() {
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(Arc::new(coordinator));
+
+    let req = AppendReq {
+      handle: vec![1u8; 16],
+      block: vec![],
+      expected_height: 0,
+      client_pk: vec![],
+      client_signature: vec![],
+      client_request_id: vec![],
+    };
+    assert!(server.append(tonic::Request::new(req)).await.is_err());
+    let req = ReadLatestReq {
+      handle: vec![1u8; 16],
+      nonce: vec![1u8; 3],
+      consistency_token: vec![2u8; 5],
+    };
+    assert!(server.read_latest(tonic::Request::new(req)).await.is_err());
+    let req = ReadViewByIndexReq { index: u64::MAX };
+    assert!(server
+      .read_view_by_index(tonic::Request::new(req))
+      .await
+      .is_err());
+
+    // without endorsers a read of an existing ledger waits for the next append, yielding to the
+    // other tasks of the runtime in the meantime
+    let req = NewLedgerReq {
+      handle: vec![2u8; 32],
+      block: vec![],
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
+    };
+    let _ = server.new_ledger(tonic::Request::new(req)).await;
+    let req = ReadLatestReq {
+      handle: vec![2u8; 32],
+      nonce: vec![3u8; 16],
+      consistency_token: vec![],
+    };
+    let _ = tokio::time::timeout(
+      Duration::from_millis(1),
+      server.read_latest(tonic::Request::new(req)),
+    )
+    .await;
+  }
 }
@@ -0,0 +1,154 @@
+//! Detection of endorsers that equivocate. The coordinator remembers the receipt that each
+//! endorser returned for the heights of the ledgers it appended to recently, and an endorser that
+//! returns a receipt for a different metablock at a height it already signed is caught: the two
+//! receipts form `MisbehaviorEvidence`, which the coordinator records in the misbehavior ledger
+//! before it removes the endorser from the view.
+use ledger::{Handle, MisbehaviorEvidence, Receipt};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const DEFAULT_TRACKED_HEIGHTS: usize = 65536; // (ledger, height) pairs whose signers are kept
+
+/// Cross-checks the receipts that endorsers return for the same height of a ledger
+pub struct EquivocationDetector {
+  signed: HashMap<(Handle, usize), HashMap<Vec<u8>, Receipt>>,
+  order: VecDeque<(Handle, usize)>, // the tracked heights, oldest first
+  capacity: usize,
+  convicted: HashSet<Vec<u8>>, // endorsers with evidence against them, which is not repeated
+  pending: Vec<MisbehaviorEvidence>,
+}
+
+impl Default for EquivocationDetector {
+  fn default() -> Self {
+    EquivocationDetector::with_capacity(DEFAULT_TRACKED_HEIGHTS)
+  }
+}
+
+impl EquivocationDetector {
+  /// Returns a detector that keeps the signers of the last `capacity` heights it saw
+  pub fn with_capacity(capacity: usize) -> Self {
+    EquivocationDetector {
+      signed: HashMap::new(),
+      order: VecDeque::new(),
+      capacity,
+      convicted: HashSet::new(),
+      pending: Vec::new(),
+    }
+  }
+
+  /// Records that the endorser that signed `receipt` attested to an append to the ledger
+  /// `handle`, and returns evidence if the endorser signed a different metablock at the same
+  /// height before. The evidence is also kept until `take_evidence` is called.
+  pub fn observe(&mut self, handle: &Handle, receipt: &Receipt) -> Option<MisbehaviorEvidence> {
+    let id = receipt.get_id_sig().get_id();
+    if self.convicted.contains(id) {
+      return None;
+    }
+
+    let key = (*handle, receipt.get_height());
+    if !self.signed.contains_key(&key) {
+      if self.order.len() == self.capacity {
+        if let Some(oldest) = self.order.pop_front() {
+          self.signed.remove(&oldest);
+        }
+      }
+      self.order.push_back(key);
+    }
+    let signers = self.signed.entry(key).or_default();
+    let evidence = match signers.get(id) {
+      Some(signed) => MisbehaviorEvidence::new(handle, signed, receipt),
+      None => {
+        signers.insert(id.clone(), receipt.clone());
+        None
+      },
+    };
+
+    if let Some(evidence) = &evidence {
+      self.convicted.insert(id.clone());
+      self.pending.push(evidence.clone());
+    }
+    evidence
+  }
+
+  /// Queues evidence that was found elsewhere, unless there is evidence against its endorser
+  pub fn add_evidence(&mut self, evidence: MisbehaviorEvidence) {
+    if self.convicted.insert(evidence.get_endorser().clone()) {
+      self.pending.push(evidence);
+    }
+  }
+
+  /// Returns the evidence found since the last call
+  pub fn take_evidence(&mut self) -> Vec<MisbehaviorEvidence> {
+    std::mem::take(&mut self.pending)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    messages::{AppendAttestation, SignedStatement},
+    signature::{PrivateKey, PrivateKeyTrait, PublicKeyTrait},
+    IdSig, MetaBlock, NimbleDigest, NimbleHashTrait,
+  };
+
+  #[test]
+  fn test_equivocation_detector() {
+    let group_identity = NimbleDigest::digest("group".as_bytes());
+    let view = NimbleDigest::digest("view".as_bytes());
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    let prev = NimbleDigest::digest("prev".as_bytes());
+    let receipt = |sk: &PrivateKey, block: &str, height: usize| {
+      let metablock = MetaBlock::new(&prev, &NimbleDigest::digest(block.as_bytes()), height);
+      let message = AppendAttestation::new(&group_identity, &view, &handle, &metablock.hash());
+      let sig = sk.sign(&message.message().to_bytes()).unwrap();
+      Receipt::new(
+        view,
+        metablock,
+        IdSig::new(sk.get_public_key().unwrap(), sig),
+      )
+    };
+    let honest = PrivateKey::new();
+    let faulty = PrivateKey::new();
+
+    let mut detector = EquivocationDetector::with_capacity(2);
+    // signing the same metablock again, or another height, is not equivocation
+    assert!(detector
+      .observe(&handle, &receipt(&honest, "a", 1))
+      .is_none());
+    assert!(detector
+      .observe(&handle, &receipt(&honest, "a", 1))
+      .is_none());
+    assert!(detector
+      .observe(&handle, &receipt(&faulty, "a", 1))
+      .is_none());
+    assert!(detector
+      .observe(&handle, &receipt(&faulty, "b", 2))
+      .is_none());
+
+    let evidence = detector
+      .observe(&handle, &receipt(&faulty, "c", 1))
+      .unwrap();
+    assert_eq!(
+      *evidence.get_endorser(),
+      faulty.get_public_key().unwrap().to_bytes()
+    );
+    assert_eq!(evidence.get_height(), 1);
+    assert!(evidence.verify(&group_identity).is_ok());
+    assert!(evidence.verify(&view).is_err());
+    assert_eq!(detector.take_evidence().len(), 1);
+    assert!(detector.take_evidence().is_empty());
+
+    // an endorser is convicted once
+    assert!(detector
+      .observe(&handle, &receipt(&faulty, "d", 2))
+      .is_none());
+
+    // the signers of the oldest height are forgotten once the detector tracks too many
+    assert!(detector
+      .observe(&handle, &receipt(&honest, "a", 3))
+      .is_none());
+    assert!(detector
+      .observe(&handle, &receipt(&honest, "b", 1))
+      .is_none());
+  }
+}
@@ -0,0 +1,398 @@
+//! Per-client limits on the requests that make endorsers sign. Each client, named by its peer
+//! address or by the API key it presents, has a token bucket that bounds the rate of its appends
+//! and ledger creations, and a cap on how many of them it has in progress at once, so that one
+//! client cannot take the endorsers' signing capacity from the others. A request over a limit
+//! fails with `RESOURCE_EXHAUSTED` and carries how long the client should wait before retrying.
+//! The JSON gateway admits its requests with the same limiter as the gRPC server.
+use ledger::{API_KEY_METADATA, RETRY_AFTER_METADATA};
+use std::{
+  collections::HashMap,
+  net::IpAddr,
+  sync::{Arc, Mutex},
+  task::{Context, Poll},
+  time::{Duration, Instant},
+};
+use tonic::{
+  body::BoxBody,
+  codegen::{http, BoxFuture},
+  metadata::MetadataValue,
+  transport::server::TcpConnectInfo,
+  Status,
+};
+use tower::{Layer, Service};
+
+// the methods whose requests are signed by the endorsers
+const LIMITED_METHODS: [&str; 6] = [
+  "/coordinator_proto.Call/NewLedger",
+  "/coordinator_proto.Call/Append",
+  "/coordinator_proto.Call/AppendBatch",
+  "/coordinator_proto.Call/AppendChunked",
+  "/coordinator_proto.Call/AppendHashOnly",
+  "/coordinator_proto.Call/Checkpoint",
+];
+const MAX_TRACKED_CLIENTS: usize = 65536; // clients tracked before the idle ones are forgotten
+const CONCURRENCY_RETRY_AFTER: Duration = Duration::from_millis(100);
+
+/// whether the requests of the gRPC method at `path` count against the limits
+pub fn is_limited(path: &str) -> bool {
+  LIMITED_METHODS.contains(&path)
+}
+
+/// What names the client that a request counts against
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClientKey {
+  /// the IP address the request came from
+  Peer,
+  /// the API key in the request's metadata, or the IP address of a request without one
+  ApiKey,
+}
+
+/// The limits that apply to each client
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimits {
+  /// the requests per second a client may make on average; 0 disables the rate limit
+  pub rate: f64,
+  /// the requests a client that was idle may make at once
+  pub burst: f64,
+  /// the requests a client may have in progress at once; 0 disables the cap
+  pub max_concurrent: usize,
+  pub key: ClientKey,
+}
+
+impl RateLimits {
+  pub fn is_enabled(&self) -> bool {
+    self.rate > 0.0 || self.max_concurrent > 0
+  }
+}
+
+/// Why a request was refused
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rejection {
+  /// the client made requests faster than its rate; it may retry after the given wait
+  RateLimited(Duration),
+  /// the client has as many requests in progress as it may
+  TooManyConcurrent,
+}
+
+impl Rejection {
+  pub fn retry_after(&self) -> Duration {
+    match self {
+      Rejection::RateLimited(wait) => *wait,
+      Rejection::TooManyConcurrent => CONCURRENCY_RETRY_AFTER,
+    }
+  }
+
+  pub fn to_status(self) -> Status {
+    let mut status = Status::resource_exhausted(match self {
+      Rejection::RateLimited(_) => "The client exceeded its request rate; retry later",
+      Rejection::TooManyConcurrent => "The client has too many requests in progress; retry later",
+    });
+    let retry_after_ms = self.retry_after().as_millis().max(1) as u64;
+    status
+      .metadata_mut()
+      .insert(RETRY_AFTER_METADATA, MetadataValue::from(retry_after_ms));
+    status
+  }
+}
+
+struct Bucket {
+  tokens: f64,
+  updated: Instant,
+  in_flight: usize,
+}
+
+impl Bucket {
+  // adds the tokens earned since the last update
+  fn refill(&mut self, limits: &RateLimits, now: Instant) {
+    let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+    self.tokens = (self.tokens + elapsed * limits.rate).min(limits.burst);
+    self.updated = now;
+  }
+}
+
+/// Tracks the requests of each client against the limits
+pub struct RateLimiter {
+  limits: RateLimits,
+  clients: Mutex<HashMap<String, Bucket>>,
+}
+
+/// A request that was admitted, which counts as in progress until it is dropped
+pub struct Permit {
+  limiter: Arc<RateLimiter>,
+  client: String,
+}
+
+impl Drop for Permit {
+  fn drop(&mut self) {
+    let mut clients = self
+      .limiter
+      .clients
+      .lock()
+      .unwrap_or_else(|e| e.into_inner());
+    if let Some(bucket) = clients.get_mut(&self.client) {
+      bucket.in_flight = bucket.in_flight.saturating_sub(1);
+    }
+  }
+}
+
+impl RateLimiter {
+  pub fn new(limits: RateLimits) -> Self {
+    RateLimiter {
+      limits,
+      clients: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Admits a request of `client` made at `now`, or tells why the client must wait
+  pub fn admit(self: &Arc<Self>, client: &str, now: Instant) -> Result<Permit, Rejection> {
+    let limits = self.limits;
+    // a poisoned lock only means a thread panicked while it counted requests
+    let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+    if !clients.contains_key(client) && clients.len() >= MAX_TRACKED_CLIENTS {
+      // a client whose bucket refilled and that has nothing in progress is as good as new
+      clients.retain(|_client, bucket| {
+        bucket.refill(&limits, now);
+        bucket.in_flight > 0 || bucket.tokens < limits.burst
+      });
+    }
+    let bucket = clients.entry(client.to_string()).or_insert(Bucket {
+      tokens: limits.burst,
+      updated: now,
+      in_flight: 0,
+    });
+
+    if limits.max_concurrent > 0 && bucket.in_flight >= limits.max_concurrent {
+      return Err(Rejection::TooManyConcurrent);
+    }
+    if limits.rate > 0.0 {
+      bucket.refill(&limits, now);
+      if bucket.tokens < 1.0 {
+        let wait = (1.0 - bucket.tokens) / limits.rate;
+        return Err(Rejection::RateLimited(Duration::from_secs_f64(wait)));
+      }
+      bucket.tokens -= 1.0;
+    }
+    bucket.in_flight += 1;
+
+    Ok(Permit {
+      limiter: self.clone(),
+      client: client.to_string(),
+    })
+  }
+
+  /// Names the client that made a request with `headers` from the address `peer`; an API key
+  /// and an address never name the same client
+  pub fn client_named(&self, headers: &http::HeaderMap, peer: Option<IpAddr>) -> String {
+    if self.limits.key == ClientKey::ApiKey {
+      if let Some(Ok(api_key)) = headers.get(API_KEY_METADATA).map(|v| v.to_str()) {
+        return format!("key:{}", api_key);
+      }
+    }
+    match peer {
+      Some(ip) => format!("peer:{}", ip),
+      None => String::from("peer:unknown"),
+    }
+  }
+
+  fn client_of<B>(&self, req: &http::Request<B>) -> String {
+    let peer = req
+      .extensions()
+      .get::<TcpConnectInfo>()
+      .and_then(|info| info.remote_addr())
+      .map(|addr| addr.ip());
+    self.client_named(req.headers(), peer)
+  }
+}
+
+/// A layer for the gRPC server that applies the limits to the requests of each client
+#[derive(Clone)]
+pub struct RateLimitLayer {
+  limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+  pub fn new(limits: RateLimits) -> Self {
+    RateLimitLayer {
+      limiter: Arc::new(RateLimiter::new(limits)),
+    }
+  }
+
+  /// the limiter that the layer admits requests with, for servers that share its limits
+  pub fn limiter(&self) -> Arc<RateLimiter> {
+    self.limiter.clone()
+  }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+  type Service = RateLimit<S>;
+
+  fn layer(&self, inner: S) -> Self::Service {
+    RateLimit {
+      inner,
+      limiter: self.limiter.clone(),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+  inner: S,
+  limiter: Arc<RateLimiter>,
+}
+
+impl<S, B> Service<http::Request<B>> for RateLimit<S>
+where
+  S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+  S::Future: Send + 'static,
+{
+  type Response = http::Response<BoxBody>;
+  type Error = S::Error;
+  type Future = BoxFuture<Self::Response, Self::Error>;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_ready(cx)
+  }
+
+  fn call(&mut self, req: http::Request<B>) -> Self::Future {
+    if !is_limited(req.uri().path()) {
+      return Box::pin(self.inner.call(req));
+    }
+    let client = self.limiter.client_of(&req);
+    match self.limiter.admit(&client, Instant::now()) {
+      Ok(permit) => {
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+          let res = fut.await;
+          drop(permit);
+          res
+        })
+      },
+      Err(rejection) => {
+        let response = rejection.to_status().to_http();
+        Box::pin(async move { Ok(response) })
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rate_limiter() {
+    let limiter = Arc::new(RateLimiter::new(RateLimits {
+      rate: 10.0,
+      burst: 2.0,
+      max_concurrent: 0,
+      key: ClientKey::Peer,
+    }));
+    let start = Instant::now();
+
+    // a client may make a burst of requests, and then one per 100ms
+    assert!(limiter.admit("a", start).is_ok());
+    assert!(limiter.admit("a", start).is_ok());
+    match limiter.admit("a", start) {
+      Err(Rejection::RateLimited(wait)) => assert_eq!(wait, Duration::from_millis(100)),
+      res => panic!("expected a rate limit, got {:?}", res.err()),
+    }
+    // another client has a bucket of its own
+    assert!(limiter.admit("b", start).is_ok());
+    assert!(limiter
+      .admit("a", start + Duration::from_millis(50))
+      .is_err());
+    assert!(limiter
+      .admit("a", start + Duration::from_millis(100))
+      .is_ok());
+    assert!(limiter
+      .admit("a", start + Duration::from_millis(150))
+      .is_err());
+  }
+
+  #[test]
+  fn test_concurrency_cap() {
+    let limiter = Arc::new(RateLimiter::new(RateLimits {
+      rate: 0.0,
+      burst: 0.0,
+      max_concurrent: 2,
+      key: ClientKey::Peer,
+    }));
+    let now = Instant::now();
+
+    let first = limiter.admit("a", now).unwrap();
+    let _second = limiter.admit("a", now).unwrap();
+    assert_eq!(
+      limiter.admit("a", now).err(),
+      Some(Rejection::TooManyConcurrent)
+    );
+    assert!(limiter.admit("b", now).is_ok());
+
+    // a request that completes makes room for another
+    drop(first);
+    assert!(limiter.admit("a", now).is_ok());
+  }
+
+  #[tokio::test]
+  async fn test_rate_limit_layer() {
+    use tower::ServiceExt;
+
+    let layer = RateLimitLayer::new(RateLimits {
+      rate: 1.0,
+      burst: 1.0,
+      max_concurrent: 0,
+      key: ClientKey::ApiKey,
+    });
+    let service = layer.layer(tower::service_fn(|_req: http::Request<()>| async {
+      Ok::<_, Status>(http::Response::new(tonic::codegen::empty_body()))
+    }));
+    let request = |path: &str, api_key: &str| {
+      http::Request::builder()
+        .uri(path)
+        .header(API_KEY_METADATA, api_key)
+        .body(())
+        .unwrap()
+    };
+    let grpc_status = |response: &http::Response<BoxBody>| {
+      response
+        .headers()
+        .get("grpc-status")
+        .map(|status| status.to_str().unwrap().to_string())
+    };
+
+    let append = "/coordinator_proto.Call/Append";
+    let response = service.clone().oneshot(request(append, "a")).await.unwrap();
+    assert_eq!(grpc_status(&response), None);
+    let response = service.clone().oneshot(request(append, "a")).await.unwrap();
+    assert_eq!(grpc_status(&response), Some(String::from("8")));
+    assert!(response.headers().contains_key(RETRY_AFTER_METADATA));
+    let append_hash_only = "/coordinator_proto.Call/AppendHashOnly";
+    let response = service
+      .clone()
+      .oneshot(request(append_hash_only, "a"))
+      .await
+      .unwrap();
+    assert_eq!(grpc_status(&response), Some(String::from("8")));
+
+    let checkpoint = "/coordinator_proto.Call/Checkpoint";
+    let response = service
+      .clone()
+      .oneshot(request(checkpoint, "a"))
+      .await
+      .unwrap();
+    assert_eq!(grpc_status(&response), Some(String::from("8")));
+
+    // reads are not limited, and neither are the requests of other clients
+    let read = "/coordinator_proto.Call/ReadLatest";
+    let response = service.clone().oneshot(request(read, "a")).await.unwrap();
+    assert_eq!(grpc_status(&response), None);
+    let response = service.oneshot(request(append, "b")).await.unwrap();
+    assert_eq!(grpc_status(&response), None);
+  }
+
+  #[test]
+  fn test_rejection_status() {
+    let status = Rejection::RateLimited(Duration::from_millis(250)).to_status();
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    assert_eq!(status.metadata().get(RETRY_AFTER_METADATA).unwrap(), "250");
+  }
+}
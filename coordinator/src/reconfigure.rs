@@ -0,0 +1,457 @@
+//! Reconfiguration of the endorsers as a two-phase protocol. A view change replaces the endorsers
+//! of the current view with those of a new one, and it must neither lose an append that a quorum
+//! of the current endorsers signed nor leave the current view unusable if it fails midway.
+//!
+//! In the prepare phase, the coordinator locks the current endorsers, so that they sign no more
+//! appends, and reads the tails of the ledgers from a quorum of them. In the commit phase, it
+//! computes the max cut of those tails, initializes the endorsers that are new to the view with
+//! it, and appends the block of the new view to the view ledger. Until that append, the view
+//! change can be abandoned: the current endorsers are unlocked and carry on, and the new ones are
+//! disconnected. Once the block is in the view ledger, the current endorsers are finalized and
+//! the new view activated; a coordinator that fails from then on completes the view change when
+//! it restarts.
+use crate::{
+  coordinator_state::{view_tail_metablock, CoordinatorState},
+  errors::CoordinatorError,
+};
+use ledger::{
+  compute_max_cut, endorser_proto,
+  messages::{SignedStatement, ViewChangeAttestation},
+  produce_hash_of_state,
+  signature::{PublicKey, PublicKeyTrait},
+  Block, EndorserHostnames, MetaBlock, NimbleDigest, NimbleHashTrait, Receipts,
+};
+use std::{collections::HashSet, fmt};
+
+/// The phases of a view change
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReconfigurationPhase {
+  /// the current endorsers are locked and their ledger tails collected
+  Prepare,
+  /// the new endorsers are initialized and the block of the new view appended
+  Commit,
+}
+
+impl fmt::Display for ReconfigurationPhase {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ReconfigurationPhase::Prepare => write!(f, "prepare"),
+      ReconfigurationPhase::Commit => write!(f, "commit"),
+    }
+  }
+}
+
+/// What the prepare phase of a view change collected: the current endorsers it locked and the max
+/// cut of their ledger tails, along with the receipts of the new endorsers initialized with it
+#[derive(Debug, Default)]
+pub struct PreparedViewChange {
+  locked: EndorserHostnames,
+  max_cut: Vec<endorser_proto::LedgerTailMapEntry>,
+  initialize_receipts: Receipts,
+}
+
+impl PreparedViewChange {
+  pub fn get_locked(&self) -> &EndorserHostnames {
+    &self.locked
+  }
+
+  pub fn get_max_cut(&self) -> &Vec<endorser_proto::LedgerTailMapEntry> {
+    &self.max_cut
+  }
+
+  pub fn into_initialize_receipts(self) -> Receipts {
+    self.initialize_receipts
+  }
+}
+
+/// Returns whether `acknowledged` endorsers form a quorum of a view of `num_endorsers`
+pub fn has_quorum(acknowledged: usize, num_endorsers: usize) -> bool {
+  acknowledged * 2 > num_endorsers
+}
+
+// An endorser carries over into the new view if it keeps its key or its URI, since an endorser
+// that rotated its key is still the endorser of the current view under its old key
+fn is_carried_over(existing_endorsers: &EndorserHostnames, pk: &[u8], uri: &str) -> bool {
+  existing_endorsers
+    .iter()
+    .any(|(old_pk, old_uri)| old_pk == pk || old_uri == uri)
+}
+
+/// Returns the endorsers of `new_endorsers` that are not in `existing_endorsers`
+pub fn fresh_endorsers(
+  existing_endorsers: &EndorserHostnames,
+  new_endorsers: &EndorserHostnames,
+) -> EndorserHostnames {
+  new_endorsers
+    .iter()
+    .filter(|(pk, uri)| !is_carried_over(existing_endorsers, pk, uri))
+    .cloned()
+    .collect()
+}
+
+/// Returns the endorsers of `new_endorsers` that are in `existing_endorsers` too, under the same
+/// key or URI. They can only be initialized for the new view once they are finalized for the
+/// current one.
+pub fn carried_over_endorsers(
+  existing_endorsers: &EndorserHostnames,
+  new_endorsers: &EndorserHostnames,
+) -> EndorserHostnames {
+  new_endorsers
+    .iter()
+    .filter(|(pk, uri)| is_carried_over(existing_endorsers, pk, uri))
+    .cloned()
+    .collect()
+}
+
+/// Returns how many of `endorsers` have a receipt in `receipts` that initializes them with
+/// `max_cut` in the view whose metablock is `metablock`, and whose signature verifies
+pub fn num_initialized_signers(
+  receipts: &Receipts,
+  endorsers: &EndorserHostnames,
+  group_identity: &NimbleDigest,
+  max_cut: &[endorser_proto::LedgerTailMapEntry],
+  metablock: &MetaBlock,
+) -> usize {
+  // receipts name their signers by the tagged encoding of their keys
+  let pks = endorsers
+    .iter()
+    .filter_map(|(pk, _uri)| PublicKey::from_bytes(pk).ok())
+    .map(|pk| pk.to_bytes())
+    .collect::<HashSet<Vec<u8>>>();
+  let state_hash = produce_hash_of_state(max_cut);
+  let statement = ViewChangeAttestation::new(group_identity, &state_hash, &metablock.hash());
+  let mut signers = HashSet::new();
+  for (ex_meta_block, id_sigs) in receipts.get() {
+    if *ex_meta_block.get_view() != state_hash
+      || ex_meta_block.get_metablock().hash() != metablock.hash()
+    {
+      continue;
+    }
+    for id_sig in id_sigs {
+      if pks.contains(id_sig.get_id()) && statement.verify(id_sig).is_ok() {
+        signers.insert(id_sig.get_id().clone());
+      }
+    }
+  }
+  signers.len()
+}
+
+/// Returns the distinct ledger tail maps among the states that endorsers reported, counting only
+/// the endorsers that are active in the current view
+pub fn active_ledger_tail_maps(
+  states: &[(Vec<u8>, endorser_proto::ReadStateResp)],
+) -> (usize, Vec<endorser_proto::LedgerTailMap>) {
+  let mut num_active = 0;
+  let mut ledger_tail_maps = Vec::new();
+  for (_pk, state) in states {
+    if state.mode != endorser_proto::EndorserMode::Active as i32 {
+      continue;
+    }
+    num_active += 1;
+    let ledger_tail_map = endorser_proto::LedgerTailMap {
+      entries: state.ledger_tail_map.clone(),
+    };
+    if !ledger_tail_maps.contains(&ledger_tail_map) {
+      ledger_tail_maps.push(ledger_tail_map);
+    }
+  }
+  (num_active, ledger_tail_maps)
+}
+
+impl CoordinatorState {
+  /// Changes the view from `existing_endorsers` to `new_endorsers`, whose configuration
+  /// `view_ledger_block` holds, and returns the height of the new view. The view change is
+  /// abandoned with `ViewChangeAborted` if a quorum of the current endorsers cannot be locked, if
+  /// too few of the new endorsers can be initialized to form a quorum, or if the block cannot be
+  /// appended to the view ledger; the current view then stays in place.
+  pub async fn reconfigure(
+    &self,
+    existing_endorsers: &EndorserHostnames,
+    new_endorsers: &EndorserHostnames,
+    view_ledger_block: &Block,
+  ) -> Result<usize, CoordinatorError> {
+    let _in_flight = self.enter_view_change().await;
+    let fresh = fresh_endorsers(existing_endorsers, new_endorsers);
+
+    let mut prepared = match self.prepare_view_change(existing_endorsers).await {
+      Some(prepared) => prepared,
+      None => {
+        return Err(
+          self
+            .abort_view_change(ReconfigurationPhase::Prepare, &Vec::new(), &fresh)
+            .await,
+        )
+      },
+    };
+
+    let (tail, height) = match self.ledger_store.read_view_ledger_tail().await {
+      Ok(res) => res,
+      Err(error) => {
+        eprintln!(
+          "Failed to read from the view ledger in the ledger store ({:?})",
+          error
+        );
+        return Err(
+          self
+            .abort_view_change(ReconfigurationPhase::Commit, &prepared.locked, &fresh)
+            .await,
+        );
+      },
+    };
+    let view_ledger_height = height + 1;
+    let view_tail_metablock = match view_tail_metablock(&tail, view_ledger_height) {
+      Ok(metablock) => metablock,
+      Err(_e) => {
+        return Err(
+          self
+            .abort_view_change(ReconfigurationPhase::Commit, &prepared.locked, &fresh)
+            .await,
+        )
+      },
+    };
+    let group_identity = match self.get_group_identity(view_ledger_block, view_ledger_height) {
+      Ok(group_identity) => group_identity,
+      Err(_e) => {
+        return Err(
+          self
+            .abort_view_change(ReconfigurationPhase::Commit, &prepared.locked, &fresh)
+            .await,
+        )
+      },
+    };
+
+    prepared.initialize_receipts = self
+      .endorser_initialize_state(
+        &group_identity,
+        &fresh,
+        prepared.max_cut.clone(),
+        &view_tail_metablock,
+        &view_ledger_block.hash(),
+        view_ledger_height,
+      )
+      .await;
+    // a fresh endorser counts once its receipt for the new view verifies. An endorser that carries
+    // over is only initialized once it is finalized, which the view change cannot take back, so
+    // here it counts if it acknowledged the lock and so is held at the max cut; the view is only
+    // activated once the receipts of a quorum of the new endorsers verify.
+    let new_view_metablock = MetaBlock::new(
+      &view_tail_metablock.hash(),
+      &view_ledger_block.hash(),
+      view_ledger_height,
+    );
+    let initialized = num_initialized_signers(
+      &prepared.initialize_receipts,
+      &fresh,
+      &group_identity,
+      &prepared.max_cut,
+      &new_view_metablock,
+    ) + carried_over_endorsers(existing_endorsers, new_endorsers)
+      .iter()
+      .filter(|(pk, uri)| is_carried_over(&prepared.locked, pk, uri))
+      .count();
+    if !has_quorum(initialized, new_endorsers.len()) {
+      eprintln!(
+        "insufficient initialized endorsers {} * 2 <= {}",
+        initialized,
+        new_endorsers.len()
+      );
+      return Err(
+        self
+          .abort_view_change(ReconfigurationPhase::Commit, &prepared.locked, &fresh)
+          .await,
+      );
+    }
+
+    // the view change commits once its block is in the view ledger
+    if let Err(error) = self
+      .ledger_store
+      .append_view_ledger(view_ledger_block, view_ledger_height)
+      .await
+    {
+      eprintln!(
+        "Failed to append to the view ledger in the ledger store ({:?})",
+        error,
+      );
+      return Err(
+        self
+          .abort_view_change(ReconfigurationPhase::Commit, &prepared.locked, &fresh)
+          .await,
+      );
+    }
+
+    self
+      .apply_view_change(
+        existing_endorsers,
+        new_endorsers,
+        &tail,
+        view_ledger_block,
+        view_ledger_height,
+        Some(prepared),
+      )
+      .await?;
+    Ok(view_ledger_height)
+  }
+
+  // Locks the existing endorsers and collects the max cut of their ledger tails, or unlocks them
+  // and returns None if a quorum of them does not answer. There is nothing to lock before the
+  // first view.
+  async fn prepare_view_change(
+    &self,
+    existing_endorsers: &EndorserHostnames,
+  ) -> Option<PreparedViewChange> {
+    if existing_endorsers.is_empty() {
+      return Some(PreparedViewChange::default());
+    }
+
+    let locked = self.endorser_set_locked(existing_endorsers, true).await;
+    let states = self.endorser_read_state(&locked).await;
+    let (num_active, ledger_tail_maps) = active_ledger_tail_maps(&states);
+    if !has_quorum(num_active, existing_endorsers.len()) {
+      eprintln!(
+        "insufficient locked endorsers {} * 2 <= {}",
+        num_active,
+        existing_endorsers.len()
+      );
+      self.endorser_set_locked(&locked, false).await;
+      return None;
+    }
+
+    Some(PreparedViewChange {
+      locked,
+      max_cut: compute_max_cut(&ledger_tail_maps),
+      initialize_receipts: Receipts::new(),
+    })
+  }
+
+  // Takes back a view change whose block is not in the view ledger: the existing endorsers it
+  // locked sign appends again, and the endorsers new to the view are disconnected, since those it
+  // initialized cannot be initialized for another view
+  async fn abort_view_change(
+    &self,
+    phase: ReconfigurationPhase,
+    locked_endorsers: &EndorserHostnames,
+    fresh_endorsers: &EndorserHostnames,
+  ) -> CoordinatorError {
+    eprintln!("Aborting the view change in the {} phase", phase);
+    self.endorser_set_locked(locked_endorsers, false).await;
+    self.disconnect_endorsers(fresh_endorsers).await;
+    CoordinatorError::ViewChangeAborted
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    CustomSerde, IdSig, Receipt,
+  };
+
+  fn state(mode: endorser_proto::EndorserMode, height: u64) -> endorser_proto::ReadStateResp {
+    endorser_proto::ReadStateResp {
+      mode: mode as i32,
+      ledger_tail_map: vec![endorser_proto::LedgerTailMapEntry {
+        handle: vec![1; 32],
+        height,
+        ..Default::default()
+      }],
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_reconfiguration_quorums() {
+    assert!(!has_quorum(0, 1));
+    assert!(has_quorum(1, 1));
+    assert!(!has_quorum(1, 2));
+    assert!(has_quorum(2, 3));
+    assert!(!has_quorum(2, 4));
+
+    let a = (vec![1], "a".to_string());
+    let b = (vec![2], "b".to_string());
+    let c = (vec![3], "c".to_string());
+    let existing = vec![a.clone(), b.clone()];
+    let new = vec![b.clone(), c.clone()];
+    assert_eq!(fresh_endorsers(&existing, &new), vec![c.clone()]);
+    assert_eq!(carried_over_endorsers(&existing, &new), vec![b]);
+    assert_eq!(fresh_endorsers(&Vec::new(), &new), new);
+    assert!(carried_over_endorsers(&Vec::new(), &new).is_empty());
+    // an endorser that rotated its key carries over under its new key
+    let rotated = vec![(vec![6], "a".to_string()), c.clone()];
+    assert_eq!(
+      carried_over_endorsers(&existing, &rotated),
+      vec![rotated[0].clone()]
+    );
+    assert_eq!(fresh_endorsers(&existing, &rotated), vec![c.clone()]);
+
+    // only active endorsers count, and endorsers that agree report one tail map between them
+    let states = vec![
+      (a.0, state(endorser_proto::EndorserMode::Active, 2)),
+      (vec![4], state(endorser_proto::EndorserMode::Active, 2)),
+      (c.0, state(endorser_proto::EndorserMode::Active, 3)),
+      (vec![5], state(endorser_proto::EndorserMode::Finalized, 4)),
+    ];
+    let (num_active, ledger_tail_maps) = active_ledger_tail_maps(&states);
+    assert_eq!((num_active, ledger_tail_maps.len()), (3, 2));
+    assert_eq!(compute_max_cut(&ledger_tail_maps)[0].height, 3);
+  }
+
+  #[test]
+  fn test_initialized_signers_verify() {
+    let group_identity = NimbleDigest::digest(b"group");
+    let metablock = MetaBlock::new(
+      &NimbleDigest::digest(b"tail"),
+      &NimbleDigest::digest(b"view"),
+      2,
+    );
+    let max_cut = state(endorser_proto::EndorserMode::Active, 3).ledger_tail_map;
+    let mut other_cut = max_cut.clone();
+    other_cut[0].handle = vec![2; 32];
+    let sks = (0..3)
+      .map(|_| PrivateKey::new())
+      .collect::<Vec<PrivateKey>>();
+    let endorsers = sks[..2]
+      .iter()
+      .enumerate()
+      .map(|(i, sk)| (sk.get_public_key().unwrap().to_bytes(), i.to_string()))
+      .collect::<EndorserHostnames>();
+    let receipt = |sk: &PrivateKey, cut: &[endorser_proto::LedgerTailMapEntry]| {
+      let state_hash = produce_hash_of_state(cut);
+      let message = ViewChangeAttestation::new(&group_identity, &state_hash, &metablock.hash())
+        .message()
+        .to_bytes();
+      let id_sig = IdSig::new(sk.get_public_key().unwrap(), sk.sign(&message).unwrap());
+      Receipt::new(state_hash, metablock.clone(), id_sig)
+    };
+    let count = |receipts: &Receipts| {
+      num_initialized_signers(receipts, &endorsers, &group_identity, &max_cut, &metablock)
+    };
+
+    let mut receipts = Receipts::new();
+    receipts.add(&receipt(&sks[0], &max_cut));
+    assert_eq!(count(&receipts), 1);
+    // a key outside the view, and a receipt for another cut, do not count
+    receipts.add(&receipt(&sks[2], &max_cut));
+    receipts.add(&receipt(&sks[1], &other_cut));
+    assert_eq!(count(&receipts), 1);
+    // nor does a signature that names an endorser of the view but was made by another key
+    let forged = receipt(&sks[2], &max_cut);
+    let forged_id_sig = IdSig::from_bytes(
+      &[
+        endorsers[1].0.clone(),
+        forged.get_id_sig().to_bytes()[endorsers[1].0.len()..].to_vec(),
+      ]
+      .concat(),
+    )
+    .unwrap();
+    let mut forged_receipts = receipts.clone();
+    forged_receipts.add(&Receipt::new(
+      *forged.get_view(),
+      metablock.clone(),
+      forged_id_sig,
+    ));
+    assert_eq!(count(&forged_receipts), 1);
+    receipts.add(&receipt(&sks[1], &max_cut));
+    assert_eq!(count(&receipts), 2);
+  }
+}
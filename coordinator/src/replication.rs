@@ -0,0 +1,300 @@
+use coordinator::coordinator_state::ATTESTATION_STR;
+use ledger::{CustomSerde, NimbleDigest, Nonces, Receipts};
+use std::{sync::Mutex, time::Duration};
+use store::ledger::{
+  in_memory::{InMemoryLedgerStore, ReplicationEvent},
+  verify::{replay_view_ledger, verify_ledger},
+  LedgerEntry,
+};
+use tokio::sync::{mpsc::UnboundedReceiver, oneshot};
+use tonic::{Request, Response, Status};
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+pub mod replication_proto {
+  tonic::include_proto!("replication_proto");
+}
+
+use replication_proto::{
+  replication_client::ReplicationClient, replication_server::Replication, PromoteReq, PromoteResp,
+  ReplicateReq, ReplicateResp, ReplicatedEntry,
+};
+
+const MAX_BATCH_ENTRIES: usize = 1000;
+const MAX_BATCH_BYTES: usize = 1 << 20;
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+fn to_proto(event: &ReplicationEvent) -> ReplicatedEntry {
+  ReplicatedEntry {
+    view_ledger: event.handle.is_none(),
+    handle: event.handle.map(|h| h.to_bytes()).unwrap_or_default(),
+    index: event.index as u64,
+    block: event.entry.get_block().to_bytes(),
+    nonces: event.entry.get_nonces().to_bytes(),
+    receipts: event.entry.get_receipts().to_bytes(),
+    timestamp: event.entry.get_timestamp().unwrap_or_default(),
+  }
+}
+
+fn from_proto(entry: &ReplicatedEntry) -> Option<ReplicationEvent> {
+  let handle = if entry.view_ledger {
+    None
+  } else {
+    Some(NimbleDigest::from_bytes(&entry.handle).ok()?)
+  };
+  let block = ledger::Block::from_bytes(&entry.block);
+  let nonces = Nonces::from_bytes(&entry.nonces);
+  let receipts = Receipts::from_bytes(&entry.receipts);
+  if block.is_err() || nonces.is_err() || receipts.is_err() {
+    return None;
+  }
+
+  let mut ledger_entry = LedgerEntry::new(block.unwrap(), receipts.unwrap(), Some(nonces.unwrap()));
+  if entry.timestamp > 0 {
+    ledger_entry.set_timestamp(entry.timestamp);
+  }
+  Some(ReplicationEvent {
+    handle,
+    index: entry.index as usize,
+    entry: ledger_entry,
+  })
+}
+
+async fn send_events(
+  client: &mut ReplicationClient<tonic::transport::Channel>,
+  events: &[ReplicationEvent],
+) -> Result<(), Status> {
+  let mut entries = Vec::new();
+  let mut num_bytes = 0;
+  for event in events {
+    let entry = to_proto(event);
+    num_bytes += entry.block.len() + entry.nonces.len() + entry.receipts.len();
+    entries.push(entry);
+    if entries.len() >= MAX_BATCH_ENTRIES || num_bytes >= MAX_BATCH_BYTES {
+      let req = ReplicateReq {
+        entries: std::mem::take(&mut entries),
+      };
+      client.replicate(req).await?;
+      num_bytes = 0;
+    }
+  }
+  if !entries.is_empty() {
+    client.replicate(ReplicateReq { entries }).await?;
+  }
+  Ok(())
+}
+
+/// Streams the changes of a primary's in-memory store to the standby at `standby_uri`. After
+/// every (re)connection the standby first catches up from a snapshot of the store, which covers
+/// any change that was lost while it was unreachable.
+pub async fn run_replication(
+  store: InMemoryLedgerStore,
+  mut receiver: UnboundedReceiver<ReplicationEvent>,
+  standby_uri: String,
+) {
+  loop {
+    let res = ReplicationClient::connect(standby_uri.clone()).await;
+    if let Err(error) = res {
+      eprintln!(
+        "Failed to connect to the standby at {} ({:?})",
+        standby_uri, error
+      );
+      tokio::time::sleep(RECONNECT_BACKOFF).await;
+      continue;
+    }
+    let mut client = res.unwrap();
+
+    let snapshot = match store.snapshot() {
+      Ok(snapshot) => snapshot,
+      Err(error) => {
+        eprintln!("Failed to snapshot the store ({:?})", error);
+        tokio::time::sleep(RECONNECT_BACKOFF).await;
+        continue;
+      },
+    };
+    if let Err(status) = send_events(&mut client, &snapshot).await {
+      eprintln!(
+        "Failed to send a snapshot to the standby at {} ({:?})",
+        standby_uri, status
+      );
+      tokio::time::sleep(RECONNECT_BACKOFF).await;
+      continue;
+    }
+    println!("Replicating the store to the standby at {}", standby_uri);
+
+    loop {
+      let mut events = match receiver.recv().await {
+        Some(event) => vec![event],
+        None => return,
+      };
+      while events.len() < MAX_BATCH_ENTRIES {
+        match receiver.try_recv() {
+          Ok(event) => events.push(event),
+          Err(_) => break,
+        }
+      }
+      if let Err(status) = send_events(&mut client, &events).await {
+        eprintln!(
+          "Failed to replicate to the standby at {} ({:?})",
+          standby_uri, status
+        );
+        tokio::time::sleep(RECONNECT_BACKOFF).await;
+        break;
+      }
+    }
+  }
+}
+
+/// What promoting a standby verified about its copy of the store
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReplicaSummary {
+  pub views: usize,
+  pub ledgers: usize,
+  pub entries: usize,
+  pub unendorsed: usize,
+}
+
+/// Verifies a replicated store the way a client would: the view ledger is replayed, and every
+/// endorsed ledger entry must carry a valid quorum receipt that extends the previous entry
+pub async fn verify_replica(store: &InMemoryLedgerStore) -> Result<ReplicaSummary, String> {
+  let (vs, views) = replay_view_ledger(store, ATTESTATION_STR.as_bytes()).await?;
+  let mut summary = ReplicaSummary {
+    views,
+    ..Default::default()
+  };
+
+  for handle in store.get_handles() {
+    let report = verify_ledger(store, &vs, &handle)
+      .await
+      .map_err(|e| format!("ledger {}: {}", base64_url::encode(&handle.to_bytes()), e))?;
+    summary.ledgers += 1;
+    summary.entries += report.endorsed;
+    summary.unendorsed += report.unendorsed;
+  }
+
+  Ok(summary)
+}
+
+/// The replication service of a standby, which applies the primary's changes to its copy of the
+/// store until it is promoted
+pub struct StandbyState {
+  store: InMemoryLedgerStore,
+  promote: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl StandbyState {
+  /// returns the service and a receiver that completes once the standby is promoted
+  pub fn new(store: InMemoryLedgerStore) -> (Self, oneshot::Receiver<()>) {
+    let (sender, receiver) = oneshot::channel();
+    let standby = StandbyState {
+      store,
+      promote: Mutex::new(Some(sender)),
+    };
+    (standby, receiver)
+  }
+
+  fn is_promoted(&self) -> bool {
+    match self.promote.lock() {
+      Ok(promote) => promote.is_none(),
+      Err(_) => true,
+    }
+  }
+}
+
+#[tonic::async_trait]
+impl Replication for StandbyState {
+  async fn replicate(
+    &self,
+    request: Request<ReplicateReq>,
+  ) -> Result<Response<ReplicateResp>, Status> {
+    if self.is_promoted() {
+      return Err(Status::failed_precondition("The standby was promoted"));
+    }
+
+    let ReplicateReq { entries } = request.into_inner();
+    for entry in &entries {
+      let event = match from_proto(entry) {
+        Some(event) => event,
+        None => return Err(Status::invalid_argument("Invalid replicated entry")),
+      };
+      if let Err(error) = self.store.apply_replication_event(&event) {
+        eprintln!("Failed to apply a replicated entry ({:?})", error);
+        return Err(Status::aborted("Failed to apply a replicated entry"));
+      }
+    }
+    Ok(Response::new(ReplicateResp {}))
+  }
+
+  async fn promote(&self, _request: Request<PromoteReq>) -> Result<Response<PromoteResp>, Status> {
+    let summary = match verify_replica(&self.store).await {
+      Ok(summary) => summary,
+      Err(error) => {
+        return Err(Status::failed_precondition(format!(
+          "The replica failed verification: {}",
+          error
+        )))
+      },
+    };
+
+    let sender = match self.promote.lock() {
+      Ok(mut promote) => promote.take(),
+      Err(_) => None,
+    };
+    match sender {
+      Some(sender) => {
+        let _ = sender.send(());
+      },
+      None => return Err(Status::failed_precondition("The standby was promoted")),
+    }
+
+    println!("Promoted the standby ({:?})", summary);
+    let reply = PromoteResp {
+      views: summary.views as u64,
+      ledgers: summary.ledgers as u64,
+      entries: summary.entries as u64,
+      unendorsed: summary.unendorsed as u64,
+    };
+    Ok(Response::new(reply))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::Block;
+  use store::ledger::LedgerStore;
+
+  #[tokio::test]
+  async fn test_standby_replication() {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let primary = InMemoryLedgerStore::new().with_replication(sender);
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    primary
+      .create_ledger(&handle, Block::new(&[1, 2, 3]))
+      .await
+      .unwrap();
+    primary
+      .append_ledger(&handle, &Block::new(&[4, 5, 6]), 1)
+      .await
+      .unwrap();
+
+    let (standby, mut promoted) = StandbyState::new(InMemoryLedgerStore::new());
+    let mut entries = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+      entries.push(to_proto(&event));
+    }
+    let req = Request::new(ReplicateReq { entries });
+    assert!(standby.replicate(req).await.is_ok());
+    let entry = standby
+      .store
+      .read_ledger_by_index(&handle, 1)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_block().to_bytes(), vec![4, 5, 6]);
+
+    // without an endorsed view ledger the replica cannot be verified, so it is not promoted
+    let res = standby.promote(Request::new(PromoteReq {})).await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::FailedPrecondition);
+    assert!(promoted.try_recv().is_err());
+    assert!(!standby.is_promoted());
+  }
+}
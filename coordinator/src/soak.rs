@@ -0,0 +1,139 @@
+//! A self-test that drives synthetic ledgers and appends against the coordinator's own state,
+//! for long-running stability validation in staging environments.
+
+use coordinator::coordinator_state::CoordinatorState;
+use rand::random;
+use std::{
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+const SOAK_REPORT_INTERVAL: u64 = 60; // seconds: how often progress is printed
+const SOAK_MIN_OPS_FOR_BUDGET: u64 = 1000; // the error budget is not enforced before this many ops
+const SOAK_READ_TIMEOUT: u64 = 10; // seconds: reads taking longer count as errors
+
+pub struct SoakConfig {
+  pub duration: Duration,
+  pub error_budget: f64, // the tolerated fraction of failed operations
+  pub appends_per_ledger: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct SoakStats {
+  pub ledgers: u64,
+  pub ops: u64,
+  pub errors: u64,
+  pub initial_rss_kb: Option<u64>,
+  pub peak_rss_kb: Option<u64>,
+}
+
+impl SoakStats {
+  pub fn error_rate(&self) -> f64 {
+    if self.ops == 0 {
+      0.0
+    } else {
+      self.errors as f64 / self.ops as f64
+    }
+  }
+
+  pub fn within_budget(&self, error_budget: f64) -> bool {
+    self.ops < SOAK_MIN_OPS_FOR_BUDGET || self.error_rate() <= error_budget
+  }
+
+  fn record<T, E>(&mut self, res: Result<T, E>) -> bool {
+    self.ops += 1;
+    if res.is_err() {
+      self.errors += 1;
+    }
+    res.is_ok()
+  }
+
+  fn sample_memory(&mut self) {
+    if let Some(rss) = resident_set_kb() {
+      self.initial_rss_kb.get_or_insert(rss);
+      self.peak_rss_kb = Some(self.peak_rss_kb.map_or(rss, |peak| peak.max(rss)));
+    }
+  }
+
+  pub fn report(&self) -> String {
+    format!(
+      "ledgers={} ops={} errors={} error_rate={:.6} initial_rss_kb={:?} peak_rss_kb={:?}",
+      self.ledgers,
+      self.ops,
+      self.errors,
+      self.error_rate(),
+      self.initial_rss_kb,
+      self.peak_rss_kb
+    )
+  }
+}
+
+// Only available on Linux; memory growth is not tracked elsewhere
+fn resident_set_kb() -> Option<u64> {
+  let status = std::fs::read_to_string("/proc/self/status").ok()?;
+  let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+  line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Creates ledgers, appends to them, and reads their tails until the duration elapses or the
+/// error budget is exhausted, and returns the statistics of the run
+pub async fn run(state: Arc<CoordinatorState>, config: SoakConfig) -> SoakStats {
+  let mut stats = SoakStats::default();
+  stats.sample_memory();
+
+  let start = Instant::now();
+  let mut last_report = Instant::now();
+  while start.elapsed() < config.duration && stats.within_budget(config.error_budget) {
+    let handle = random::<[u8; 16]>();
+    if !stats.record(state.create_ledger(None, &handle, b"soak genesis").await) {
+      continue;
+    }
+    stats.ledgers += 1;
+
+    for height in 1..=config.appends_per_ledger {
+      let block = format!("soak block {}", height);
+      if !stats.record(
+        state
+          .append_ledger(None, &handle, block.as_bytes(), height)
+          .await,
+      ) {
+        break;
+      }
+    }
+
+    // a read without a quorum waits for the next append, which this loop would never issue
+    let nonce = random::<[u8; 16]>();
+    let res = tokio::time::timeout(
+      Duration::from_secs(SOAK_READ_TIMEOUT),
+      state.read_ledger_tail(&handle, &nonce),
+    )
+    .await;
+    stats.record(res.map_err(|_elapsed| ()).and_then(|r| r.map_err(|_e| ())));
+
+    if last_report.elapsed() >= Duration::from_secs(SOAK_REPORT_INTERVAL) {
+      stats.sample_memory();
+      println!("soak: {}", stats.report());
+      last_report = Instant::now();
+    }
+  }
+
+  stats.sample_memory();
+  stats
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_soak_error_budget() {
+    let mut stats = SoakStats::default();
+    for i in 0..SOAK_MIN_OPS_FOR_BUDGET {
+      stats.record(if i % 100 == 0 { Err(()) } else { Ok(()) });
+    }
+    assert_eq!(stats.ops, SOAK_MIN_OPS_FOR_BUDGET);
+    assert_eq!(stats.errors, SOAK_MIN_OPS_FOR_BUDGET / 100);
+    assert!(stats.within_budget(0.01));
+    assert!(!stats.within_budget(0.001));
+  }
+}
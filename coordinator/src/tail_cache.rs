@@ -0,0 +1,253 @@
+//! An in-memory cache of the entries at the tails of ledgers. Reading a ledger's tail asks the
+//! endorsers for a quorum of receipts and then reads the entry they endorsed from the ledger store
+//! for its block and its timestamp; the cache keeps that entry for the ledgers read most recently,
+//! so that reads that find the tail where it was skip the store. An entry is cached under the
+//! height it is at and only answers reads of that height, and the coordinator drops it whenever
+//! it writes to the ledger, so the cache never answers with an entry that the store changed.
+use ledger::Handle;
+use std::{
+  collections::{BTreeMap, HashMap},
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+use store::ledger::LedgerEntry;
+
+/// Counters of the cache's lookups since the coordinator started
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TailCacheStats {
+  /// lookups that the cache answered
+  pub hits: u64,
+  /// lookups that went to the ledger store
+  pub misses: u64,
+  /// entries dropped to make room for others
+  pub evictions: u64,
+  /// entries dropped because they were older than the time to live
+  pub expirations: u64,
+  /// entries in the cache
+  pub entries: usize,
+}
+
+struct CachedTail {
+  height: usize,
+  entry: LedgerEntry,
+  cached_at: Instant,
+  last_used: u64,
+}
+
+#[derive(Default)]
+struct TailCacheInner {
+  capacity: usize,
+  ttl: Option<Duration>,
+  tails: HashMap<Handle, CachedTail>,
+  // the ledgers by the time of their last use, from the least recently used
+  recency: BTreeMap<u64, Handle>,
+  clock: u64,
+  stats: TailCacheStats,
+}
+
+impl TailCacheInner {
+  fn remove(&mut self, handle: &Handle) -> Option<CachedTail> {
+    let tail = self.tails.remove(handle)?;
+    self.recency.remove(&tail.last_used);
+    Some(tail)
+  }
+
+  fn tick(&mut self) -> u64 {
+    self.clock += 1;
+    self.clock
+  }
+}
+
+/// Caches the tail entry of up to a number of ledgers, least recently used first out; a cache
+/// with no room is disabled and misses every lookup without counting it
+#[derive(Default)]
+pub struct TailCache {
+  inner: Mutex<TailCacheInner>,
+}
+
+impl TailCache {
+  pub fn new(capacity: usize, ttl: Option<Duration>) -> Self {
+    let cache = TailCache::default();
+    cache.configure(capacity, ttl);
+    cache
+  }
+
+  /// Sets the most ledgers whose tails are cached, and how long a tail is served before it is
+  /// read from the store again (None for as long as it is the tail); drops the cached tails
+  pub fn configure(&self, capacity: usize, ttl: Option<Duration>) {
+    if let Ok(mut inner) = self.inner.lock() {
+      inner.capacity = capacity;
+      inner.ttl = ttl;
+      inner.tails.clear();
+      inner.recency.clear();
+    }
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    match self.inner.lock() {
+      Ok(inner) => inner.capacity > 0,
+      Err(_) => false,
+    }
+  }
+
+  /// Returns the cached entry at `height` of the ledger, if `height` is where its tail was cached
+  pub fn get(&self, handle: &Handle, height: usize) -> Option<LedgerEntry> {
+    let mut inner = self.inner.lock().ok()?;
+    if inner.capacity == 0 {
+      return None;
+    }
+    let expired = match (inner.tails.get(handle), inner.ttl) {
+      (Some(tail), Some(ttl)) => tail.cached_at.elapsed() >= ttl,
+      _ => false,
+    };
+    if expired {
+      inner.remove(handle);
+      inner.stats.expirations += 1;
+    }
+    match inner.tails.get(handle) {
+      Some(tail) if tail.height == height => {
+        let entry = tail.entry.clone();
+        let last_used = tail.last_used;
+        let now = inner.tick();
+        inner.recency.remove(&last_used);
+        inner.recency.insert(now, *handle);
+        if let Some(tail) = inner.tails.get_mut(handle) {
+          tail.last_used = now;
+        }
+        inner.stats.hits += 1;
+        Some(entry)
+      },
+      _ => {
+        inner.stats.misses += 1;
+        None
+      },
+    }
+  }
+
+  /// Caches the entry at `height` of the ledger, unless a higher entry of it is cached already
+  pub fn insert(&self, handle: &Handle, height: usize, entry: &LedgerEntry) {
+    let mut inner = match self.inner.lock() {
+      Ok(inner) => inner,
+      Err(_) => return,
+    };
+    if inner.capacity == 0 {
+      return;
+    }
+    if let Some(tail) = inner.tails.get(handle) {
+      if tail.height > height {
+        return;
+      }
+    }
+    inner.remove(handle);
+    while inner.tails.len() >= inner.capacity {
+      let lru = match inner.recency.iter().next() {
+        Some((_last_used, lru)) => *lru,
+        None => break,
+      };
+      inner.remove(&lru);
+      inner.stats.evictions += 1;
+    }
+    let now = inner.tick();
+    inner.recency.insert(now, *handle);
+    inner.tails.insert(
+      *handle,
+      CachedTail {
+        height,
+        entry: entry.clone(),
+        cached_at: Instant::now(),
+        last_used: now,
+      },
+    );
+  }
+
+  /// Drops the cached tail of a ledger that is being written to
+  pub fn invalidate(&self, handle: &Handle) {
+    if let Ok(mut inner) = self.inner.lock() {
+      inner.remove(handle);
+    }
+  }
+
+  /// Drops every cached tail
+  pub fn clear(&self) {
+    if let Ok(mut inner) = self.inner.lock() {
+      inner.tails.clear();
+      inner.recency.clear();
+    }
+  }
+
+  pub fn stats(&self) -> TailCacheStats {
+    match self.inner.lock() {
+      Ok(inner) => TailCacheStats {
+        entries: inner.tails.len(),
+        ..inner.stats
+      },
+      Err(_) => TailCacheStats::default(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{Block, CustomSerde, NimbleDigest, Receipts};
+
+  fn entry(byte: u8) -> LedgerEntry {
+    LedgerEntry::new(Block::new(&[byte]), Receipts::new(), None)
+  }
+
+  #[test]
+  fn test_tail_cache() {
+    let cache = TailCache::new(2, None);
+    let (a, b, c) = (
+      NimbleDigest::digest(b"a"),
+      NimbleDigest::digest(b"b"),
+      NimbleDigest::digest(b"c"),
+    );
+
+    // a tail answers reads of its height only
+    assert!(cache.get(&a, 0).is_none());
+    cache.insert(&a, 3, &entry(3));
+    assert_eq!(cache.get(&a, 3).unwrap().get_block().to_bytes(), vec![3]);
+    assert!(cache.get(&a, 4).is_none());
+
+    // a lower entry does not replace the tail, and a higher one does
+    cache.insert(&a, 2, &entry(2));
+    assert!(cache.get(&a, 2).is_none());
+    cache.insert(&a, 4, &entry(4));
+    assert!(cache.get(&a, 3).is_none());
+    assert!(cache.get(&a, 4).is_some());
+
+    // the least recently used tail makes room for a new one
+    cache.insert(&b, 1, &entry(1));
+    assert!(cache.get(&a, 4).is_some());
+    cache.insert(&c, 1, &entry(1));
+    assert!(cache.get(&b, 1).is_none());
+    assert!(cache.get(&a, 4).is_some());
+    assert!(cache.get(&c, 1).is_some());
+
+    cache.invalidate(&a);
+    assert!(cache.get(&a, 4).is_none());
+
+    assert_eq!(
+      cache.stats(),
+      TailCacheStats {
+        hits: 5,
+        misses: 6,
+        evictions: 1,
+        expirations: 0,
+        entries: 1,
+      }
+    );
+
+    // tails expire, and a disabled cache neither caches nor counts
+    let cache = TailCache::new(2, Some(Duration::from_millis(0)));
+    cache.insert(&a, 1, &entry(1));
+    assert!(cache.get(&a, 1).is_none());
+    assert_eq!(cache.stats().expirations, 1);
+    cache.configure(0, None);
+    assert!(!cache.is_enabled());
+    cache.insert(&a, 1, &entry(1));
+    assert!(cache.get(&a, 1).is_none());
+    assert_eq!(cache.stats().misses, 1);
+  }
+}
@@ -1,3 +1,5 @@
+mod verify;
+
 use clap::{App, Arg};
 
 use serde::{Deserialize, Serialize};
@@ -9,6 +11,38 @@ struct EndorserOpResponse {
   pub pk: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct DecommissionResponse {
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "FinalState")]
+  pub final_state: String,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: usize,
+  #[serde(rename = "ActiveEndorsers")]
+  pub active_endorsers: usize,
+  #[serde(rename = "NumEndorsers")]
+  pub num_endorsers: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyRotationResponse {
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "Handover")]
+  pub handover: String,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MaintenanceResponse {
+  #[serde(rename = "Maintenance")]
+  pub enabled: bool,
+  #[serde(rename = "RemainingSeconds")]
+  pub remaining_secs: u64,
+}
+
 #[tokio::main]
 async fn main() {
   let config = App::new("client")
@@ -33,12 +67,63 @@ async fn main() {
         .takes_value(true)
         .help("Endorser to delete"),
     )
+    .arg(
+      Arg::with_name("decommission")
+        .long("decommission")
+        .takes_value(true)
+        .requires("replacements")
+        .help("Endorser to take out of service with a view change"),
+    )
+    .arg(
+      Arg::with_name("replacements")
+        .long("replacements")
+        .takes_value(true)
+        .help("Semicolon separated endorsers that form the view after a decommission"),
+    )
+    .arg(
+      Arg::with_name("rotate")
+        .long("rotate")
+        .takes_value(true)
+        .help("Endorser whose signing key to replace with a view change"),
+    )
     .arg(
       Arg::with_name("get")
         .short("g")
         .long("get")
         .takes_value(true)
         .help("Endorser to read"),
+    )
+    .arg(
+      Arg::with_name("maintenance")
+        .short("m")
+        .long("maintenance")
+        .takes_value(true)
+        .help("Reject writes for the given number of seconds (maintenance mode)"),
+    )
+    .arg(
+      Arg::with_name("resume")
+        .short("r")
+        .long("resume")
+        .help("Leave maintenance mode and accept writes again"),
+    )
+    .arg(
+      Arg::with_name("verify_receipt")
+        .long("verify_receipt")
+        .takes_value(true)
+        .help("Verify an exported JSON receipt and report the outcome of each check"),
+    )
+    .arg(
+      Arg::with_name("check_integrity")
+        .long("check_integrity")
+        .help("Check the integrity of every ledger in the coordinator's store and report the entries flagged"),
+    )
+    .arg(
+      Arg::with_name("views")
+        .long("views")
+        .takes_value(true)
+        .help(
+          "A JSON file or coordinator URL to read the view ledger from (default: --coordinator)",
+        ),
     );
   let cli_matches = config.get_matches();
   let coordinator_addr = cli_matches.value_of("coordinator").unwrap();
@@ -83,6 +168,76 @@ async fn main() {
       },
     }
   }
+  if let Some(x) = cli_matches.value_of("decommission") {
+    let replacements = cli_matches.value_of("replacements").unwrap();
+    let endorser_url = reqwest::Url::parse(&format!(
+      "{}/endorsers/{}/decommission/{}",
+      coordinator_addr,
+      base64_url::encode(&x),
+      base64_url::encode(&replacements)
+    ))
+    .unwrap();
+
+    let now = Instant::now();
+    let res = client.put(endorser_url).send().await;
+    println!("Decommission time: {} ms", now.elapsed().as_millis());
+
+    match res {
+      Ok(resp) if resp.status() == reqwest::StatusCode::OK => {
+        let decommission_resp: DecommissionResponse = resp.json().await.unwrap();
+        println!(
+          "decommission_endorser: {} {:?}",
+          x,
+          base64_url::decode(&decommission_resp.pk).unwrap()
+        );
+        println!("final state: {}", decommission_resp.final_state);
+        println!(
+          "view {}: {} of {} endorsers active",
+          decommission_resp.view_height,
+          decommission_resp.active_endorsers,
+          decommission_resp.num_endorsers
+        );
+      },
+      Ok(resp) => {
+        eprintln!("decommission_endorser failed: {}", resp.status());
+        std::process::exit(1);
+      },
+      Err(error) => {
+        eprintln!("decommission_endorser failed: {:?}", error);
+        std::process::exit(1);
+      },
+    }
+  }
+  if let Some(x) = cli_matches.value_of("rotate") {
+    let endorser_url = reqwest::Url::parse(&format!(
+      "{}/endorsers/{}/rotate",
+      coordinator_addr,
+      base64_url::encode(&x)
+    ))
+    .unwrap();
+
+    let res = client.put(endorser_url).send().await;
+    match res {
+      Ok(resp) if resp.status() == reqwest::StatusCode::OK => {
+        let rotation_resp: KeyRotationResponse = resp.json().await.unwrap();
+        println!(
+          "rotate_endorser_key: {} {:?}",
+          x,
+          base64_url::decode(&rotation_resp.pk).unwrap()
+        );
+        println!("handover: {}", rotation_resp.handover);
+        println!("view {}", rotation_resp.view_height);
+      },
+      Ok(resp) => {
+        eprintln!("rotate_endorser_key failed: {}", resp.status());
+        std::process::exit(1);
+      },
+      Err(error) => {
+        eprintln!("rotate_endorser_key failed: {:?}", error);
+        std::process::exit(1);
+      },
+    }
+  }
   if let Some(x) = cli_matches.value_of("get") {
     let uri = base64_url::encode(&x);
     let endorser_url =
@@ -100,4 +255,103 @@ async fn main() {
       },
     }
   }
+  if let Some(x) = cli_matches.value_of("maintenance") {
+    let maintenance_url =
+      reqwest::Url::parse(&format!("{}/maintenance/{}", coordinator_addr, x)).unwrap();
+    let res = client.put(maintenance_url).send().await;
+    match res {
+      Ok(resp) => {
+        assert!(resp.status() == reqwest::StatusCode::OK);
+        let maintenance_resp: MaintenanceResponse = resp.json().await.unwrap();
+        println!(
+          "maintenance: {} (remaining {} s)",
+          maintenance_resp.enabled, maintenance_resp.remaining_secs
+        );
+      },
+      Err(error) => {
+        eprintln!("maintenance failed: {:?}", error);
+      },
+    }
+  }
+  if let Some(x) = cli_matches.value_of("verify_receipt") {
+    let views_source = cli_matches.value_of("views").unwrap_or(coordinator_addr);
+    let res = verify::load_views(&client, views_source).await;
+    let views = match res {
+      Ok(views) => views,
+      Err(error) => {
+        eprintln!("verify_receipt failed to load views: {}", error);
+        std::process::exit(1);
+      },
+    };
+    let res = std::fs::read_to_string(x)
+      .map_err(|e| e.to_string())
+      .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()));
+    let receipt = match res {
+      Ok(receipt) => receipt,
+      Err(error) => {
+        eprintln!("verify_receipt failed to read {}: {}", x, error);
+        std::process::exit(1);
+      },
+    };
+
+    let report = verify::verify_receipt(&views, &receipt);
+    for check in &report {
+      println!(
+        "[{}] {}",
+        if check.passed { "PASS" } else { "FAIL" },
+        check.description
+      );
+    }
+    // individual checks can fail for receipts that do not count towards the quorum, so the
+    // outcome is that of the final check, which verifies the receipt as a whole
+    if !report.last().map(|check| check.passed).unwrap_or(false) {
+      std::process::exit(1);
+    }
+  }
+  if cli_matches.is_present("check_integrity") {
+    let integrity_url = reqwest::Url::parse(&format!("{}/integrity", coordinator_addr)).unwrap();
+    let res = client.put(integrity_url).send().await;
+    match res {
+      Ok(resp) if resp.status() == reqwest::StatusCode::OK => {
+        let report: serde_json::Value = resp.json().await.unwrap();
+        println!(
+          "check_integrity: {} views, {} ledgers, {} entries in {} ms",
+          report["views"], report["ledgers"], report["entries"], report["duration_ms"]
+        );
+        let issues = report["issues"].as_array().cloned().unwrap_or_default();
+        for issue in &issues {
+          println!(
+            "[FLAGGED] ledger {} entry {}: {} ({})",
+            issue["ledger"], issue["index"], issue["kind"], issue["detail"]
+          );
+        }
+        if !issues.is_empty() {
+          std::process::exit(1);
+        }
+      },
+      Ok(resp) => {
+        eprintln!("check_integrity failed: {}", resp.status());
+        std::process::exit(1);
+      },
+      Err(error) => {
+        eprintln!("check_integrity failed: {:?}", error);
+        std::process::exit(1);
+      },
+    }
+  }
+  if cli_matches.is_present("resume") {
+    let maintenance_url =
+      reqwest::Url::parse(&format!("{}/maintenance", coordinator_addr)).unwrap();
+    let res = client.delete(maintenance_url).send().await;
+    match res {
+      Ok(resp) => {
+        assert!(resp.status() == reqwest::StatusCode::OK);
+        let maintenance_resp: MaintenanceResponse = resp.json().await.unwrap();
+        println!("maintenance: {}", maintenance_resp.enabled);
+      },
+      Err(error) => {
+        eprintln!("resume failed: {:?}", error);
+      },
+    }
+  }
 }
@@ -0,0 +1,264 @@
+use ledger::{
+  compute_aggregated_block_hash, Block, CustomSerde, NimbleDigest, NimbleHashTrait, Receipts,
+  VerifierState,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ViewEntry {
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+}
+
+/// The view ledger as served by the coordinator's `/views` endpoint
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ViewLedgerResponse {
+  #[serde(rename = "Views")]
+  pub views: Vec<ViewEntry>,
+  #[serde(rename = "Attestations")]
+  pub attestations: String,
+}
+
+/// An exported receipt. All byte fields are base64url encoded. `Height` is the expected height
+/// of the entry (0 for a genesis block) and `Nonce` is the client nonce of a read of the tail.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedReceipt {
+  #[serde(rename = "Handle")]
+  pub handle: String,
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Nonces", default)]
+  pub nonces: String,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+  #[serde(rename = "Height", default)]
+  pub height: Option<usize>,
+  #[serde(rename = "Nonce", default)]
+  pub nonce: Option<String>,
+}
+
+pub struct Check {
+  pub description: String,
+  pub passed: bool,
+}
+
+impl Check {
+  fn new(passed: bool, description: String) -> Self {
+    Check {
+      description,
+      passed,
+    }
+  }
+}
+
+/// Reads the view ledger from a coordinator URL or from a file holding a `/views` response
+pub async fn load_views(
+  client: &reqwest::Client,
+  source: &str,
+) -> Result<ViewLedgerResponse, String> {
+  if source.starts_with("http://") || source.starts_with("https://") {
+    let url = reqwest::Url::parse(&format!("{}/views", source)).map_err(|e| e.to_string())?;
+    let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if resp.status() != reqwest::StatusCode::OK {
+      return Err(format!("the coordinator returned {}", resp.status()));
+    }
+    resp.json().await.map_err(|e| e.to_string())
+  } else {
+    let s = std::fs::read_to_string(source).map_err(|e| e.to_string())?;
+    serde_json::from_str(&s).map_err(|e| e.to_string())
+  }
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, Check> {
+  base64_url::decode(value).map_err(|e| Check::new(false, format!("decode {} ({})", field, e)))
+}
+
+// Replays the view ledger the same way the endpoint does: the tail is vouched for by the
+// attestations and every earlier view by the view change that follows it
+fn build_verifier_state(views: &ViewLedgerResponse) -> Result<VerifierState, Check> {
+  let mut vs = VerifierState::default();
+  let mut entries = Vec::with_capacity(views.views.len());
+  for (i, entry) in views.views.iter().enumerate() {
+    let block = decode(&format!("view block {}", i + 1), &entry.block)?;
+    let receipts = decode(&format!("view receipts {}", i + 1), &entry.receipts)?;
+    entries.push((block, receipts));
+  }
+  if entries.is_empty() {
+    return Err(Check::new(false, "the view ledger is empty".to_string()));
+  }
+  let attestations = decode("attestations", &views.attestations)?;
+
+  let genesis = Block::from_bytes(&entries[0].0)
+    .map_err(|e| Check::new(false, format!("decode view genesis block ({:?})", e)))?;
+  vs.set_group_identity(genesis.hash());
+
+  for (i, (block, receipts)) in entries.iter().enumerate().rev() {
+    let attestations_opt = if i + 1 == entries.len() {
+      Some(attestations.as_slice())
+    } else {
+      None
+    };
+    vs.apply_view_change(block, receipts, attestations_opt)
+      .map_err(|e| Check::new(false, format!("apply view change {} ({:?})", i + 1, e)))?;
+  }
+
+  Ok(vs)
+}
+
+/// Verifies an exported receipt against the view ledger and reports the outcome of each check
+pub fn verify_receipt(views: &ViewLedgerResponse, receipt: &ExportedReceipt) -> Vec<Check> {
+  let mut report = Vec::new();
+
+  let vs = match build_verifier_state(views) {
+    Ok(vs) => vs,
+    Err(check) => {
+      report.push(check);
+      return report;
+    },
+  };
+  report.push(Check::new(
+    true,
+    format!("replayed {} views", views.views.len()),
+  ));
+
+  let decoded = (|| {
+    let handle = decode("handle", &receipt.handle)?;
+    let block = decode("block", &receipt.block)?;
+    let nonces = decode("nonces", &receipt.nonces)?;
+    let receipts_bytes = decode("receipts", &receipt.receipts)?;
+    let nonce = match &receipt.nonce {
+      Some(nonce) => Some(decode("nonce", nonce)?),
+      None => None,
+    };
+    let receipts = Receipts::from_bytes(&receipts_bytes)
+      .map_err(|e| Check::new(false, format!("decode receipts ({:?})", e)))?;
+    Ok((handle, block, nonces, receipts_bytes, nonce, receipts))
+  })();
+  let (handle, block, nonces, receipts_bytes, nonce, receipts) = match decoded {
+    Ok(decoded) => decoded,
+    Err(check) => {
+      report.push(check);
+      return report;
+    },
+  };
+  report.push(Check::new(
+    true,
+    format!("decoded receipts for {} metablocks", receipts.get().len()),
+  ));
+
+  // the genesis block of a ledger is endorsed without nonces
+  let hash_nonces = if receipt.height == Some(0) {
+    NimbleDigest::default()
+  } else {
+    NimbleDigest::digest(&nonces)
+  };
+  let block_hash = compute_aggregated_block_hash(
+    &NimbleDigest::digest(&block).to_bytes(),
+    &hash_nonces.to_bytes(),
+  );
+  let handle_digest = NimbleDigest::digest(&handle);
+
+  for (ex_meta_block, id_sigs) in receipts.get() {
+    let view = ex_meta_block.get_view();
+    let metablock = ex_meta_block.get_metablock();
+    let prefix = format!(
+      "view {} height {}:",
+      base64_url::encode(&view.to_bytes()),
+      metablock.get_height()
+    );
+
+    let pks = match vs.get_pks_for_ledger(view, &handle_digest) {
+      Ok(pks) => {
+        report.push(Check::new(
+          true,
+          format!("{} view is known ({} endorsers)", prefix, pks.len()),
+        ));
+        pks
+      },
+      Err(e) => {
+        report.push(Check::new(
+          false,
+          format!("{} view is known ({:?})", prefix, e),
+        ));
+        continue;
+      },
+    };
+
+    report.push(Check::new(
+      *metablock.get_block_hash() == block_hash,
+      format!("{} block and nonces match the metablock", prefix),
+    ));
+    if let Some(h) = receipt.height {
+      report.push(Check::new(
+        metablock.get_height() == h,
+        format!("{} height matches the expected height {}", prefix, h),
+      ));
+    }
+
+    let tail_hash = metablock.hash();
+    let mut messages = vec![tail_hash];
+    if let Some(n) = &nonce {
+      messages.push(tail_hash.digest_with_bytes(n));
+    }
+    let messages = messages
+      .iter()
+      .map(|h| {
+        vs.get_group_identity()
+          .digest_with(&view.digest_with(&handle_digest.digest_with(h)))
+          .to_bytes()
+      })
+      .collect::<Vec<Vec<u8>>>();
+
+    let mut num_valid = 0;
+    for id_sig in id_sigs {
+      let id = base64_url::encode(id_sig.get_id());
+      let in_view = pks.contains(id_sig.get_id());
+      let valid = messages.iter().any(|m| id_sig.verify(m).is_ok());
+      report.push(Check::new(
+        in_view,
+        format!("{} endorser {} belongs to the view", prefix, id),
+      ));
+      report.push(Check::new(
+        valid,
+        format!("{} endorser {} signature is valid", prefix, id),
+      ));
+      if in_view && valid {
+        num_valid += 1;
+      }
+    }
+    report.push(Check::new(
+      num_valid > pks.len() / 2,
+      format!(
+        "{} {} of {} endorsers signed (quorum is {})",
+        prefix,
+        num_valid,
+        pks.len(),
+        pks.len() / 2 + 1
+      ),
+    ));
+  }
+
+  let res = match (receipt.height, &nonce) {
+    (_, Some(n)) => vs
+      .verify_read_latest(&handle, &block, &nonces, n, &receipts_bytes)
+      .map(|_h| ()),
+    (Some(0), None) => vs.verify_new_ledger(&handle, &block, &receipts_bytes),
+    (Some(h), None) => {
+      vs.verify_append(&handle, &block, &hash_nonces.to_bytes(), h, &receipts_bytes)
+    },
+    (None, None) => receipts
+      .verify(&vs, &handle, &block, &hash_nonces.to_bytes(), None, None)
+      .map(|_h| ()),
+  };
+  report.push(Check::new(
+    res.is_ok(),
+    match res {
+      Ok(()) => "receipt verifies".to_string(),
+      Err(e) => format!("receipt verifies ({:?})", e),
+    },
+  ));
+
+  report
+}
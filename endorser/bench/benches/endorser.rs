@@ -0,0 +1,145 @@
+//! Benchmarks of an endorser's appends to many ledgers from concurrent threads, as the coordinator
+//! sends them when clients append to different ledgers. Each thread appends to ledgers of its own,
+//! so the threads contend only on the state that the endorser shares between ledgers. A second
+//! group also creates ledgers from another thread during the appends. Run with `cargo bench` in
+//! this directory.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use endorser::endorser_state::EndorserState;
+use ledger::{
+  signature::PublicKeyTrait, Block, CustomSerde, EndorserHostnames, Handle, MetaBlock,
+  NimbleDigest, NimbleHashTrait, Nonces, Receipts,
+};
+use std::{
+  sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+  thread,
+  time::{Duration, Instant},
+};
+
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+const LEDGERS_PER_THREAD: usize = 16;
+
+// Returns an endorser that is active in the first view, which lists only it
+fn active_endorser() -> EndorserState {
+  let endorser = EndorserState::new();
+  let hostnames: EndorserHostnames = vec![(
+    endorser.get_public_key().unwrap().to_bytes(),
+    "bench://endorser".to_string(),
+  )];
+  let config = Block::new(&bincode::serialize(&hostnames).unwrap());
+  let receipt = endorser
+    .initialize_state(
+      &config.hash(),
+      &[],
+      &MetaBlock::default(),
+      &config.hash(),
+      1,
+    )
+    .unwrap();
+  let mut receipts = Receipts::new();
+  receipts.add(&receipt);
+  endorser
+    .activate(&[], &config.to_bytes(), &Vec::new(), &[], &receipts)
+    .unwrap();
+  endorser
+}
+
+fn create_ledger(endorser: &EndorserState, id: usize) -> Handle {
+  let block = Block::new(&id.to_le_bytes());
+  let handle = NimbleDigest::digest(&block.to_bytes());
+  endorser.new_ledger(&handle, &block.hash(), &block).unwrap();
+  handle
+}
+
+// Appends `iters` blocks to the ledgers of `num_threads` threads and returns how long it took.
+// With `created`, another thread creates ledgers until the appends are done, counting them there.
+fn append_concurrently(
+  endorser: &EndorserState,
+  ledgers: &[Vec<(Handle, AtomicUsize)>],
+  num_threads: usize,
+  iters: u64,
+  created: Option<&AtomicUsize>,
+) -> Duration {
+  let block = Block::new(&[0xab; 64]);
+  let block_hash = block.hash();
+  let done = AtomicBool::new(false);
+  thread::scope(|scope| {
+    if let Some(created) = created {
+      let done = &done;
+      scope.spawn(move || {
+        while !done.load(Ordering::SeqCst) {
+          create_ledger(
+            endorser,
+            usize::MAX - created.fetch_add(1, Ordering::SeqCst),
+          );
+        }
+      });
+    }
+    let start = Instant::now();
+    let appenders = ledgers[..num_threads]
+      .iter()
+      .enumerate()
+      .map(|(thread, own)| {
+        let (block, block_hash) = (&block, &block_hash);
+        // the appends are split evenly between the threads
+        let appends = (iters as usize + num_threads - 1 - thread) / num_threads;
+        scope.spawn(move || {
+          for i in 0..appends {
+            let (handle, height) = &own[i % own.len()];
+            let next = height.fetch_add(1, Ordering::SeqCst) + 1;
+            endorser
+              .append(handle, block_hash, next, block, &Nonces::new())
+              .unwrap();
+          }
+        })
+      })
+      .collect::<Vec<_>>();
+    for appender in appenders {
+      appender.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    done.store(true, Ordering::SeqCst);
+    elapsed
+  })
+}
+
+fn bench_appends(c: &mut Criterion) {
+  for (name, create) in [("append", false), ("append_while_creating", true)] {
+    let endorser = active_endorser();
+    let created = AtomicUsize::new(0);
+    let max_threads = THREADS[THREADS.len() - 1];
+    let ledgers = (0..max_threads)
+      .map(|thread| {
+        (0..LEDGERS_PER_THREAD)
+          .map(|i| {
+            let handle = create_ledger(&endorser, thread * LEDGERS_PER_THREAD + i);
+            (handle, AtomicUsize::new(0))
+          })
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(1));
+    for num_threads in THREADS {
+      group.bench_with_input(
+        BenchmarkId::new("threads", num_threads),
+        &num_threads,
+        |b, &num_threads| {
+          b.iter_custom(|iters| {
+            append_concurrently(
+              &endorser,
+              &ledgers,
+              num_threads,
+              iters,
+              create.then_some(&created),
+            )
+          })
+        },
+      );
+    }
+    group.finish();
+  }
+}
+
+criterion_group!(benches, bench_appends);
+criterion_main!(benches);
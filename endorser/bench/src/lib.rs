@@ -0,0 +1 @@
+//! Criterion benchmarks of the endorser crate, in `benches/`
@@ -1,21 +1,34 @@
-use crate::errors::EndorserError;
-
-use itertools::Itertools;
+use crate::{
+  errors::EndorserError,
+  persistence::{LogRecord, StateLog},
+  signer::{Signer, SoftwareSigner},
+};
 
 use ledger::endorser_proto::{EndorserMode, LedgerChunkEntry, LedgerTailMap, LedgerTailMapEntry};
 
 use ledger::{
+  attestation::retrieve_lease_from_config,
+  messages::{
+    AppendAttestation, KeyHandoverAttestation, ReadAttestation, SignedStatement,
+    ViewChangeAttestation, ViewTailAttestation,
+  },
   produce_hash_of_state,
-  signature::{PrivateKey, PrivateKeyTrait, PublicKey},
-  Block, CustomSerde, Handle, IdSig, MetaBlock, NimbleDigest, NimbleHashTrait, Nonces, Receipt,
-  Receipts,
+  signature::{PublicKey, PublicKeyTrait, SignatureScheme},
+  Block, CustomSerde, Handle, IdSig, KeyHandover, MetaBlock, NimbleDigest, NimbleHashTrait, Nonces,
+  Receipt, Receipts,
 };
 use std::{
   collections::{hash_map, HashMap},
   ops::{Deref, DerefMut},
-  sync::{Arc, RwLock},
+  path::Path,
+  sync::{Arc, Mutex, RwLock},
+  time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+/// how far the timestamp that the coordinator proposes for an append may be from the endorser's
+/// clock, unless the endorser is configured otherwise
+pub const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(30);
+
 struct ViewLedgerState {
   view_ledger_tail_metablock: MetaBlock,
 
@@ -28,84 +41,605 @@ struct ViewLedgerState {
 
   /// Endorser's group identity
   group_identity: NimbleDigest,
+
+  /// whether the coordinator locked the endorser, which then signs no appends to ledgers
+  is_locked: bool,
+
+  /// when the lease recorded in the view that activated the endorser expires (ms since epoch),
+  /// after which it signs nothing for clients until a later view renews it
+  lease_expiry: Option<u64>,
+}
+
+struct EndorserKeys {
+  /// a key pair in a digital signature scheme
+  signer: Box<dyn Signer>,
+  public_key: PublicKey,
+
+  /// the key that replaces the key pair once the endorser is initialized into the view that
+  /// lists it
+  next_key: Option<Box<dyn Signer>>,
+}
+
+impl EndorserKeys {
+  fn new(signer: Box<dyn Signer>) -> Self {
+    let public_key = signer.get_public_key();
+    EndorserKeys {
+      signer,
+      public_key,
+      next_key: None,
+    }
+  }
 }
 
 type ProtectedMetaBlock = Arc<RwLock<(MetaBlock, Block, Nonces)>>;
 
+// the number of shards of the ledger tail map
+const NUM_SHARDS: usize = 64;
+
+/// A map from handles to the locks of the ledgers' tails, split into shards that are each behind
+/// their own lock. The lock of a shard is only held to look up or insert a ledger, so an append
+/// that holds its ledger's lock while it signs and persists the new tail stalls neither the
+/// appends to other ledgers nor the creation of ledgers.
+struct LedgerTails {
+  shards: Vec<RwLock<HashMap<Handle, ProtectedMetaBlock>>>,
+}
+
+impl LedgerTails {
+  fn new() -> Self {
+    LedgerTails {
+      shards: (0..NUM_SHARDS)
+        .map(|_| RwLock::new(HashMap::new()))
+        .collect(),
+    }
+  }
+
+  fn shard(&self, handle: &Handle) -> &RwLock<HashMap<Handle, ProtectedMetaBlock>> {
+    // handles are digests, so their first byte spreads them evenly over the shards
+    &self.shards[handle.to_bytes()[0] as usize % self.shards.len()]
+  }
+
+  // returns the lock of the tail of `handle`, after releasing the lock of its shard
+  fn get(&self, handle: &Handle) -> Result<ProtectedMetaBlock, EndorserError> {
+    if let Ok(shard) = self.shard(handle).read() {
+      match shard.get(handle) {
+        Some(protected_metablock) => Ok(protected_metablock.clone()),
+        None => Err(EndorserError::InvalidLedgerName),
+      }
+    } else {
+      Err(EndorserError::FailedToAcquireLedgerMapReadLock)
+    }
+  }
+
+  fn insert(&self, handle: Handle, tail: (MetaBlock, Block, Nonces)) -> Result<(), EndorserError> {
+    if let Ok(mut shard) = self.shard(&handle).write() {
+      shard.insert(handle, Arc::new(RwLock::new(tail)));
+      Ok(())
+    } else {
+      Err(EndorserError::FailedToAcquireLedgerMapWriteLock)
+    }
+  }
+
+  // returns every ledger in the order of their handles, locking one shard at a time
+  fn entries(&self) -> Result<Vec<(Handle, ProtectedMetaBlock)>, EndorserError> {
+    let mut entries = Vec::new();
+    for shard in &self.shards {
+      if let Ok(shard) = shard.read() {
+        entries.extend(
+          shard
+            .iter()
+            .map(|(handle, protected_metablock)| (*handle, protected_metablock.clone())),
+        );
+      } else {
+        return Err(EndorserError::FailedToAcquireLedgerMapReadLock);
+      }
+    }
+    entries.sort_by_key(|(handle, _protected_metablock)| *handle);
+    Ok(entries)
+  }
+}
+
 /// Endorser's internal state
 pub struct EndorserState {
-  /// a key pair in a digital signature scheme
-  private_key: PrivateKey,
-  public_key: PublicKey,
+  /// the signing keys, which are locked after the view ledger state when both are
+  keys: RwLock<EndorserKeys>,
 
   /// a map from fixed-sized labels to a tail hash and a counter
-  ledger_tail_map: Arc<RwLock<HashMap<Handle, ProtectedMetaBlock>>>,
+  ledger_tail_map: LedgerTails,
 
+  /// the state of the view ledger, which every operation on a ledger reads while it runs, so that
+  /// changes of the view, which write it, exclude them all
   view_ledger_state: Arc<RwLock<ViewLedgerState>>,
+
+  /// the log to which changes to the state are written before they are signed, if the endorser
+  /// persists its state
+  log: Option<Mutex<StateLog>>,
+
+  /// the number of times the endorser restarted from its storage
+  incarnation: u64,
+
+  /// how far a timestamp that the endorser attests to may be from its clock
+  max_clock_skew: Duration,
+}
+
+/// An append in a batch: the handle, the block hash, the expected height, the block, the nonces,
+/// and the proposed timestamp, if any
+pub type BatchEntry = (Handle, NimbleDigest, usize, Block, Nonces, Option<u64>);
+
+// returns the tail of a ledger after appending `block_hash` at `expected_height` to `metablock`
+fn next_metablock(
+  metablock: &MetaBlock,
+  block_hash: &NimbleDigest,
+  expected_height: usize,
+) -> Result<MetaBlock, EndorserError> {
+  let next = metablock
+    .next(block_hash)
+    .ok_or(EndorserError::LedgerHeightOverflow)?;
+
+  if expected_height < next.get_height() {
+    return Err(EndorserError::LedgerExists);
+  }
+
+  if expected_height > next.get_height() {
+    return Err(EndorserError::OutOfOrder);
+  }
+
+  Ok(next)
+}
+
+// returns the tail of the view ledger after appending the view `block_hash` at `expected_height`
+// to `tail`, which must be the view `prev_view_hash` names if the coordinator names one, so that a
+// coordinator can neither fork the endorser's view ledger nor skip or repeat a view
+fn next_view_metablock(
+  tail: &MetaBlock,
+  prev_view_hash: Option<&NimbleDigest>,
+  block_hash: &NimbleDigest,
+  expected_height: usize,
+) -> Result<MetaBlock, EndorserError> {
+  let tail_hash = tail.hash();
+  let height_plus_one = tail
+    .get_height()
+    .checked_add(1)
+    .ok_or(EndorserError::LedgerHeightOverflow)?;
+
+  if expected_height < height_plus_one {
+    return Err(EndorserError::StaleViewHeight);
+  }
+
+  if expected_height > height_plus_one {
+    return Err(EndorserError::ViewHeightGap);
+  }
+
+  if let Some(prev_view_hash) = prev_view_hash {
+    if *prev_view_hash != tail_hash {
+      return Err(EndorserError::ViewTailMismatch);
+    }
+  }
+
+  Ok(MetaBlock::new(&tail_hash, block_hash, height_plus_one))
+}
+
+fn view_record(view_ledger_state: &ViewLedgerState) -> LogRecord {
+  LogRecord::View {
+    mode: view_ledger_state.endorser_mode as i32,
+    group_identity: view_ledger_state.group_identity.to_bytes(),
+    tail_metablock: view_ledger_state.view_ledger_tail_metablock.to_bytes(),
+    prev_metablock: view_ledger_state.view_ledger_prev_metablock.to_bytes(),
+  }
+}
+
+// the milliseconds since epoch on the endorser's clock
+fn now_millis() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or(0)
+}
+
+// checks that the lease of the endorser in the current view has not expired
+fn check_lease(view_ledger_state: &ViewLedgerState) -> Result<(), EndorserError> {
+  match view_ledger_state.lease_expiry {
+    Some(expiry) if now_millis() >= expiry => Err(EndorserError::LeaseExpired),
+    _ => Ok(()),
+  }
+}
+
+fn ledger_record(
+  handle: &Handle,
+  metablock: &MetaBlock,
+  block: &Block,
+  nonces: &Nonces,
+) -> LogRecord {
+  LogRecord::Ledger {
+    handle: handle.to_bytes(),
+    metablock: metablock.to_bytes(),
+    block: block.to_bytes(),
+    nonces: nonces.to_bytes(),
+  }
+}
+
+impl Default for EndorserState {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl EndorserState {
   pub fn new() -> Self {
-    let private_key = PrivateKey::new();
-    let public_key = private_key.get_public_key().unwrap();
+    EndorserState::with_scheme(SignatureScheme::P256)
+  }
+
+  /// creates an endorser that signs with a fresh key of `scheme`
+  pub fn with_scheme(scheme: SignatureScheme) -> Self {
+    EndorserState::with_signer(Box::new(SoftwareSigner::generate(scheme)))
+  }
+
+  /// creates an endorser that signs with `signer`
+  pub fn with_signer(signer: Box<dyn Signer>) -> Self {
     EndorserState {
-      private_key,
-      public_key,
-      ledger_tail_map: Arc::new(RwLock::new(HashMap::new())),
+      keys: RwLock::new(EndorserKeys::new(signer)),
+      ledger_tail_map: LedgerTails::new(),
       view_ledger_state: Arc::new(RwLock::new(ViewLedgerState {
         view_ledger_tail_metablock: MetaBlock::default(),
         view_ledger_tail_hash: MetaBlock::default().hash(),
         view_ledger_prev_metablock: MetaBlock::default(),
         endorser_mode: EndorserMode::Uninitialized,
         group_identity: NimbleDigest::default(),
+        is_locked: false,
+        lease_expiry: None,
       })),
+      log: None,
+      incarnation: 0,
+      max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+    }
+  }
+
+  /// Sets how far the timestamp proposed for an append may be from the endorser's clock for the
+  /// endorser to attest to it
+  pub fn set_max_clock_skew(&mut self, max_clock_skew: Duration) {
+    self.max_clock_skew = max_clock_skew;
+  }
+
+  // checks that a timestamp (ms since epoch) proposed for an append is close to the local clock
+  fn check_timestamp(&self, timestamp: Option<u64>) -> Result<(), EndorserError> {
+    if let Some(timestamp) = timestamp {
+      if now_millis().abs_diff(timestamp) > self.max_clock_skew.as_millis() as u64 {
+        return Err(EndorserError::TimestampOutOfBounds);
+      }
+    }
+    Ok(())
+  }
+
+  /// Creates an endorser that persists its key and state in `dir`, restoring them if the
+  /// directory holds the state of an earlier run. A new key uses `scheme`.
+  pub fn with_storage(dir: &Path, scheme: SignatureScheme) -> Result<Self, EndorserError> {
+    let (log, private_key, records) = StateLog::open(dir)?;
+    let signer = match private_key {
+      Some(private_key) => SoftwareSigner::new(private_key),
+      None => {
+        let signer = SoftwareSigner::generate(scheme);
+        log.store_key(&signer.to_pem().unwrap())?;
+        signer
+      },
+    };
+    EndorserState::restore(log, Box::new(signer), records)
+  }
+
+  /// Creates an endorser that signs with `signer` and persists its state in `dir`, restoring it
+  /// if the directory holds the state of an earlier run. A key stored in the directory is not
+  /// used, and a key that cannot leave `signer` is not stored there.
+  pub fn with_storage_and_signer(
+    dir: &Path,
+    signer: Box<dyn Signer>,
+  ) -> Result<Self, EndorserError> {
+    let (log, _, records) = StateLog::open(dir)?;
+    EndorserState::restore(log, signer, records)
+  }
+
+  // replays the log of an earlier run, if there was one, and starts a new log
+  fn restore(
+    log: StateLog,
+    signer: Box<dyn Signer>,
+    records: Vec<LogRecord>,
+  ) -> Result<Self, EndorserError> {
+    let mut endorser_state = EndorserState::with_signer(signer);
+
+    let mut restarted = false;
+    for record in records {
+      restarted = true;
+      endorser_state.replay(record)?;
+    }
+    if restarted {
+      endorser_state.incarnation += 1;
+    }
+
+    // start a new log from the recovered state, which also drops a record torn by a crash
+    let snapshot = endorser_state.snapshot()?;
+    endorser_state.log = Some(Mutex::new(log));
+    endorser_state.persist(&snapshot, true)?;
+    Ok(endorser_state)
+  }
+
+  // applies a record of the log to the state
+  fn replay(&mut self, record: LogRecord) -> Result<(), EndorserError> {
+    match record {
+      LogRecord::Incarnation(incarnation) => self.incarnation = incarnation,
+      LogRecord::View {
+        mode,
+        group_identity,
+        tail_metablock,
+        prev_metablock,
+      } => {
+        let res = (
+          EndorserMode::from_i32(mode),
+          NimbleDigest::from_bytes(&group_identity),
+          MetaBlock::from_bytes(&tail_metablock),
+          MetaBlock::from_bytes(&prev_metablock),
+        );
+        let view_ledger_state = self.view_ledger_state.write();
+        match (res, view_ledger_state) {
+          ((Some(mode), Ok(group_identity), Ok(tail), Ok(prev)), Ok(mut view_ledger_state)) => {
+            view_ledger_state.endorser_mode = mode;
+            view_ledger_state.group_identity = group_identity;
+            view_ledger_state.view_ledger_tail_hash = tail.hash();
+            view_ledger_state.view_ledger_tail_metablock = tail;
+            view_ledger_state.view_ledger_prev_metablock = prev;
+          },
+          _ => return Err(EndorserError::FailedToAccessStorage),
+        }
+      },
+      LogRecord::Lease(expiry) => match self.view_ledger_state.write() {
+        Ok(mut view_ledger_state) => view_ledger_state.lease_expiry = expiry,
+        Err(_) => return Err(EndorserError::FailedToAccessStorage),
+      },
+      LogRecord::Ledger {
+        handle,
+        metablock,
+        block,
+        nonces,
+      } => {
+        let res = (
+          NimbleDigest::from_bytes(&handle),
+          MetaBlock::from_bytes(&metablock),
+          Block::from_bytes(&block),
+          Nonces::from_bytes(&nonces),
+        );
+        match res {
+          (Ok(handle), Ok(metablock), Ok(block), Ok(nonces)) => {
+            self
+              .ledger_tail_map
+              .insert(handle, (metablock, block, nonces))
+              .map_err(|_e| EndorserError::FailedToAccessStorage)?;
+          },
+          _ => return Err(EndorserError::FailedToAccessStorage),
+        }
+      },
+    }
+    Ok(())
+  }
+
+  // the records that describe the whole state
+  fn snapshot(&self) -> Result<Vec<LogRecord>, EndorserError> {
+    let mut records = vec![LogRecord::Incarnation(self.incarnation)];
+    if let Ok(view_ledger_state) = self.view_ledger_state.read() {
+      records.push(view_record(view_ledger_state.deref()));
+      records.push(LogRecord::Lease(view_ledger_state.lease_expiry));
+    } else {
+      return Err(EndorserError::FailedToAcquireViewLedgerReadLock);
+    }
+    for (handle, value) in self.ledger_tail_map.entries()? {
+      if let Ok(e) = value.read() {
+        records.push(ledger_record(&handle, &e.0, &e.1, &e.2));
+      } else {
+        return Err(EndorserError::FailedToAcquireLedgerEntryReadLock);
+      }
+    }
+    Ok(records)
+  }
+
+  // writes `records` to the log, if the endorser persists its state, replacing the log with them
+  // if they are a snapshot
+  fn persist(&self, records: &[LogRecord], snapshot: bool) -> Result<(), EndorserError> {
+    match &self.log {
+      None => Ok(()),
+      Some(log) => match log.lock() {
+        Ok(mut log) if snapshot => log.compact(records),
+        Ok(mut log) => log.append(records),
+        Err(_) => Err(EndorserError::FailedToAccessStorage),
+      },
+    }
+  }
+
+  /// returns the number of times the endorser restarted from its storage, and whether it has any
+  pub fn get_incarnation(&self) -> (u64, bool) {
+    (self.incarnation, self.log.is_some())
+  }
+
+  pub fn get_mode(&self) -> Result<EndorserMode, EndorserError> {
+    match self.view_ledger_state.read() {
+      Ok(view_ledger_state) => Ok(view_ledger_state.endorser_mode),
+      Err(_) => Err(EndorserError::FailedToAcquireViewLedgerReadLock),
     }
   }
 
   pub fn initialize_state(
     &self,
     group_identity: &NimbleDigest,
-    ledger_tail_map: &Vec<LedgerTailMapEntry>,
+    ledger_tail_map: &[LedgerTailMapEntry],
     view_ledger_tail_metablock: &MetaBlock,
     block_hash: &NimbleDigest,
     expected_height: usize,
   ) -> Result<Receipt, EndorserError> {
     if let Ok(mut view_ledger_state) = self.view_ledger_state.write() {
-      if view_ledger_state.endorser_mode != EndorserMode::Uninitialized {
+      // a finalized endorser that stays in the next view, as when another endorser rotates its
+      // key, is initialized again into the view ledger entry that it was finalized into
+      let carries_over = view_ledger_state.endorser_mode == EndorserMode::Finalized
+        && view_ledger_state.group_identity == *group_identity
+        && view_ledger_state.view_ledger_prev_metablock == *view_ledger_tail_metablock
+        && view_ledger_state.view_ledger_tail_metablock
+          == MetaBlock::new(
+            &view_ledger_tail_metablock.hash(),
+            block_hash,
+            expected_height,
+          );
+      if view_ledger_state.endorser_mode != EndorserMode::Uninitialized && !carries_over {
         return Err(EndorserError::AlreadyInitialized);
       }
+      if !carries_over {
+        next_view_metablock(
+          view_ledger_tail_metablock,
+          None,
+          block_hash,
+          expected_height,
+        )?;
+      }
 
-      if let Ok(mut ledger_tail_map_wr) = self.ledger_tail_map.write() {
-        for entry in ledger_tail_map {
-          ledger_tail_map_wr.insert(
-            NimbleDigest::from_bytes(&entry.handle).unwrap(),
-            Arc::new(RwLock::new((
-              MetaBlock::from_bytes(&entry.metablock).unwrap(),
-              Block::from_bytes(&entry.block).unwrap(),
-              Nonces::from_bytes(&entry.nonces).unwrap(),
-            ))),
-          );
+      // decode every entry before changing any state
+      let mut entries = Vec::with_capacity(ledger_tail_map.len());
+      for entry in ledger_tail_map {
+        match (
+          NimbleDigest::from_bytes(&entry.handle),
+          MetaBlock::from_bytes(&entry.metablock),
+          Block::from_bytes(&entry.block),
+          Nonces::from_bytes(&entry.nonces),
+        ) {
+          (Ok(handle), Ok(metablock), Ok(block), Ok(nonces)) => {
+            entries.push((handle, (metablock, block, nonces)))
+          },
+          _ => return Err(EndorserError::InvalidLedgerTailMap),
         }
       }
 
-      view_ledger_state.view_ledger_prev_metablock =
-        view_ledger_state.view_ledger_tail_metablock.clone();
-      view_ledger_state.view_ledger_tail_metablock = view_ledger_tail_metablock.clone();
-      view_ledger_state.view_ledger_tail_hash = view_ledger_state.view_ledger_tail_metablock.hash();
-      view_ledger_state.endorser_mode = EndorserMode::Initialized;
-      view_ledger_state.group_identity = *group_identity;
+      let receipt = if carries_over {
+        // the new view may move the endorser's ledgers forward, but it cannot drop any of them
+        for (handle, value) in self.ledger_tail_map.entries()? {
+          let height = if let Ok(e) = value.read() {
+            e.0.get_height()
+          } else {
+            return Err(EndorserError::FailedToAcquireLedgerEntryReadLock);
+          };
+          if !entries
+            .iter()
+            .any(|(h, (metablock, ..))| *h == handle && metablock.get_height() >= height)
+          {
+            return Err(EndorserError::InvalidLedgerTailMap);
+          }
+        }
 
-      self.append_view_ledger(
-        view_ledger_state.deref_mut(),
-        ledger_tail_map,
-        block_hash,
-        expected_height,
-      )
+        self.take_next_key()?;
+        self.insert_ledger_tails(entries)?;
+        view_ledger_state.endorser_mode = EndorserMode::Initialized;
+        self.sign_view_ledger(view_ledger_state.deref(), ledger_tail_map)?
+      } else {
+        self.insert_ledger_tails(entries)?;
+
+        view_ledger_state.view_ledger_prev_metablock =
+          view_ledger_state.view_ledger_tail_metablock.clone();
+        view_ledger_state.view_ledger_tail_metablock = view_ledger_tail_metablock.clone();
+        view_ledger_state.view_ledger_tail_hash =
+          view_ledger_state.view_ledger_tail_metablock.hash();
+        view_ledger_state.endorser_mode = EndorserMode::Initialized;
+        view_ledger_state.group_identity = *group_identity;
+
+        self.append_view_ledger(
+          view_ledger_state.deref_mut(),
+          ledger_tail_map,
+          block_hash,
+          expected_height,
+        )?
+      };
+
+      let mut records = ledger_tail_map
+        .iter()
+        .map(|entry| LogRecord::Ledger {
+          handle: entry.handle.clone(),
+          metablock: entry.metablock.clone(),
+          block: entry.block.clone(),
+          nonces: entry.nonces.clone(),
+        })
+        .collect::<Vec<LogRecord>>();
+      records.push(view_record(view_ledger_state.deref()));
+      self.persist(&records, false)?;
+      Ok(receipt)
     } else {
       Err(EndorserError::FailedToAcquireViewLedgerWriteLock)
     }
   }
 
+  fn insert_ledger_tails(
+    &self,
+    entries: Vec<(Handle, (MetaBlock, Block, Nonces))>,
+  ) -> Result<(), EndorserError> {
+    // the caller holds the view ledger state's write lock, so no operation on a ledger sees the
+    // tails half inserted
+    for (handle, tail) in entries {
+      self.ledger_tail_map.insert(handle, tail)?;
+    }
+    Ok(())
+  }
+
+  // replaces the endorser's key with the key generated by its last rotation, if there is one,
+  // storing the new key first if the endorser persists its state
+  fn take_next_key(&self) -> Result<(), EndorserError> {
+    if let Ok(mut keys) = self.keys.write() {
+      if let Some(next_key) = &keys.next_key {
+        if let (Some(log), Some(pem)) = (&self.log, next_key.to_pem()) {
+          match log.lock() {
+            Ok(log) => log.store_key(&pem)?,
+            Err(_) => return Err(EndorserError::FailedToAccessStorage),
+          }
+        }
+        let next_key = keys.next_key.take().unwrap();
+        next_key.take_over()?;
+        *keys = EndorserKeys::new(next_key);
+      }
+      Ok(())
+    } else {
+      Err(EndorserError::FailedToAcquireKeyLock)
+    }
+  }
+
+  /// Generates the key that succeeds the endorser's key and signs a handover to it with the
+  /// current key in the current view. The endorser keeps signing with its current key until it
+  /// is finalized and initialized again into the next view, and a later rotation replaces a key
+  /// that was not taken into use yet.
+  pub fn rotate_key(&self) -> Result<(PublicKey, KeyHandover), EndorserError> {
+    if let Ok(view_ledger_state) = self.view_ledger_state.read() {
+      match view_ledger_state.endorser_mode {
+        EndorserMode::Uninitialized | EndorserMode::Initialized => {
+          return Err(EndorserError::NotActive);
+        },
+        EndorserMode::Finalized => {
+          return Err(EndorserError::AlreadyFinalized);
+        },
+        _ => {},
+      }
+
+      if let Ok(mut keys) = self.keys.write() {
+        let next_key = keys.signer.generate_next()?;
+        let next_public_key = next_key.get_public_key();
+        let view = view_ledger_state.view_ledger_tail_hash;
+        let message = KeyHandoverAttestation::new(
+          &view_ledger_state.group_identity,
+          &view,
+          &NimbleDigest::digest(&next_public_key.to_bytes()),
+        )
+        .message();
+        let signature = keys.signer.sign(&message.to_bytes())?;
+        let handover = KeyHandover::new(
+          &view,
+          &next_public_key,
+          IdSig::new(keys.public_key.clone(), signature),
+        );
+        keys.next_key = Some(next_key);
+        Ok((next_public_key, handover))
+      } else {
+        Err(EndorserError::FailedToAcquireKeyLock)
+      }
+    } else {
+      Err(EndorserError::FailedToAcquireViewLedgerReadLock)
+    }
+  }
+
   pub fn new_ledger(
     &self,
     handle: &NimbleDigest,
@@ -122,28 +656,34 @@ impl EndorserState {
         },
         _ => {},
       }
+      check_lease(&view_ledger_state)?;
+      if view_ledger_state.is_locked {
+        return Err(EndorserError::Locked);
+      }
 
       // create a genesis metablock that embeds the current tail of the view/membership ledger
       let view = view_ledger_state.view_ledger_tail_hash;
       let metablock = MetaBlock::genesis(block_hash);
-      let message = view_ledger_state
-        .group_identity
-        .digest_with(&view.digest_with(&handle.digest_with(&metablock.hash())));
-      let signature = self.private_key.sign(&message.to_bytes()).unwrap();
+      let message = AppendAttestation::new(
+        &view_ledger_state.group_identity,
+        &view,
+        handle,
+        &metablock.hash(),
+      )
+      .message();
+      let id_sig = self.sign(&message.to_bytes())?;
 
       // check if the handle already exists, if so, return an error
-      if let Ok(mut ledger_tail_map) = self.ledger_tail_map.write() {
-        if let hash_map::Entry::Vacant(e) = ledger_tail_map.entry(*handle) {
+      if let Ok(mut ledger_tail_shard) = self.ledger_tail_map.shard(handle).write() {
+        if let hash_map::Entry::Vacant(e) = ledger_tail_shard.entry(*handle) {
+          let record = ledger_record(handle, &metablock, block, &Nonces::new());
+          self.persist(&[record], false)?;
           e.insert(Arc::new(RwLock::new((
             metablock.clone(),
             block.clone(),
             Nonces::new(),
           ))));
-          Ok(Receipt::new(
-            view,
-            metablock,
-            IdSig::new(self.public_key.clone(), signature),
-          ))
+          Ok(Receipt::new(view, metablock, id_sig))
         } else {
           Err(EndorserError::LedgerExists)
         }
@@ -170,37 +710,29 @@ impl EndorserState {
         },
         _ => {},
       }
+      check_lease(&view_ledger_state)?;
 
-      if let Ok(ledger_tail_map) = self.ledger_tail_map.read() {
-        match ledger_tail_map.get(handle) {
-          None => Err(EndorserError::InvalidLedgerName),
-          Some(protected_metablock) => {
-            if let Ok(e) = protected_metablock.read() {
-              let view = view_ledger_state.view_ledger_tail_hash;
-              let metablock = &e.0;
-              let tail_hash = metablock.hash();
-              let message = view_ledger_state.group_identity.digest_with(
-                &view.digest_with(&handle.digest_with(&tail_hash.digest_with_bytes(nonce))),
-              );
-              let signature = self.private_key.sign(&message.to_bytes()).unwrap();
-
-              Ok((
-                Receipt::new(
-                  view,
-                  metablock.clone(),
-                  IdSig::new(self.public_key.clone(), signature),
-                ),
-                e.1.clone(),
-                e.2.clone(),
-              ))
-            } else {
-              Err(EndorserError::FailedToAcquireLedgerEntryReadLock)
-            }
-          },
-        }
-      } else {
-        Err(EndorserError::FailedToAcquireLedgerMapReadLock)
-      }
+      let protected_metablock = self.ledger_tail_map.get(handle)?;
+      let e = protected_metablock
+        .read()
+        .map_err(|_e| EndorserError::FailedToAcquireLedgerEntryReadLock)?;
+      let view = view_ledger_state.view_ledger_tail_hash;
+      let metablock = &e.0;
+      let message = ReadAttestation::new(
+        &view_ledger_state.group_identity,
+        &view,
+        handle,
+        &metablock.hash(),
+        nonce,
+      )
+      .message();
+      let id_sig = self.sign(&message.to_bytes())?;
+
+      Ok((
+        Receipt::new(view, metablock.clone(), id_sig),
+        e.1.clone(),
+        e.2.clone(),
+      ))
     } else {
       Err(EndorserError::FailedToAcquireViewLedgerReadLock)
     }
@@ -218,20 +750,11 @@ impl EndorserState {
         _ => {},
       }
 
-      if let Ok(ledger_tail_map) = self.ledger_tail_map.read() {
-        match ledger_tail_map.get(handle) {
-          None => Err(EndorserError::InvalidLedgerName),
-          Some(protected_metablock) => {
-            if let Ok(e) = protected_metablock.read() {
-              Ok(e.0.get_height())
-            } else {
-              Err(EndorserError::FailedToAcquireLedgerEntryReadLock)
-            }
-          },
-        }
-      } else {
-        Err(EndorserError::FailedToAcquireLedgerMapReadLock)
-      }
+      let protected_metablock = self.ledger_tail_map.get(handle)?;
+      let e = protected_metablock
+        .read()
+        .map_err(|_e| EndorserError::FailedToAcquireLedgerEntryReadLock)?;
+      Ok(e.0.get_height())
     } else {
       Err(EndorserError::FailedToAcquireViewLedgerReadLock)
     }
@@ -244,7 +767,9 @@ impl EndorserState {
     expected_height: usize,
     block: &Block,
     nonces: &Nonces,
+    timestamp: Option<u64>,
   ) -> Result<Receipt, EndorserError> {
+    self.check_timestamp(timestamp)?;
     if let Ok(view_ledger_state) = self.view_ledger_state.read() {
       match view_ledger_state.endorser_mode {
         EndorserMode::Uninitialized | EndorserMode::Initialized => {
@@ -255,92 +780,175 @@ impl EndorserState {
         },
         _ => {},
       }
+      check_lease(&view_ledger_state)?;
+      if view_ledger_state.is_locked {
+        return Err(EndorserError::Locked);
+      }
+
+      let protected_metablock = self.ledger_tail_map.get(handle)?;
+      let mut e = protected_metablock
+        .write()
+        .map_err(|_e| EndorserError::FailedToAcquireLedgerEntryWriteLock)?;
+      let new_metablock =
+        next_metablock(&e.0, block_hash, expected_height)?.with_timestamp(timestamp);
+      let receipt = self.sign_ledger_entry(&view_ledger_state, handle, new_metablock)?;
+
+      self.persist(
+        &[ledger_record(
+          handle,
+          receipt.get_metablock(),
+          block,
+          nonces,
+        )],
+        false,
+      )?;
+      *e = (
+        receipt.get_metablock().clone(),
+        block.clone(),
+        nonces.clone(),
+      );
+      Ok(receipt)
+    } else {
+      Err(EndorserError::FailedToAcquireViewLedgerReadLock)
+    }
+  }
 
-      if let Ok(ledger_tail_map) = self.ledger_tail_map.read() {
-        match ledger_tail_map.get(handle) {
-          None => Err(EndorserError::InvalidLedgerName),
-          Some(protected_metablock) => {
-            if let Ok(mut e) = protected_metablock.write() {
-              let metablock = &e.0;
-              // increment height and returning an error in case of overflow
-              let height_plus_one = {
-                let res = metablock.get_height().checked_add(1);
-                if res.is_none() {
-                  return Err(EndorserError::LedgerHeightOverflow);
-                }
-                res.unwrap()
-              };
-
-              if expected_height < height_plus_one {
-                return Err(EndorserError::LedgerExists);
-              }
-
-              if expected_height > height_plus_one {
-                return Err(EndorserError::OutOfOrder);
-              }
-
-              let new_metablock = MetaBlock::new(&metablock.hash(), block_hash, height_plus_one);
-
-              let view = view_ledger_state.view_ledger_tail_hash;
-              let message = view_ledger_state
-                .group_identity
-                .digest_with(&view.digest_with(&handle.digest_with(&new_metablock.hash())));
-
-              let signature = self.private_key.sign(&message.to_bytes()).unwrap();
-
-              *e = (new_metablock.clone(), block.clone(), nonces.clone());
-              Ok(Receipt::new(
-                view,
-                new_metablock,
-                IdSig::new(self.public_key.clone(), signature),
-              ))
-            } else {
-              Err(EndorserError::FailedToAcquireLedgerEntryWriteLock)
-            }
+  // signs the new tail of a ledger in the current view
+  fn sign_ledger_entry(
+    &self,
+    view_ledger_state: &ViewLedgerState,
+    handle: &NimbleDigest,
+    metablock: MetaBlock,
+  ) -> Result<Receipt, EndorserError> {
+    let view = view_ledger_state.view_ledger_tail_hash;
+    let message = AppendAttestation::for_metablock(
+      &view_ledger_state.group_identity,
+      &view,
+      handle,
+      &metablock,
+    )
+    .message();
+    let id_sig = self.sign(&message.to_bytes())?;
+    Ok(Receipt::new(view, metablock, id_sig))
+  }
+
+  // signs `message` with the endorser's current key
+  fn sign(&self, message: &[u8]) -> Result<IdSig, EndorserError> {
+    if let Ok(keys) = self.keys.read() {
+      let signature = keys.signer.sign(message)?;
+      Ok(IdSig::new(keys.public_key.clone(), signature))
+    } else {
+      Err(EndorserError::FailedToAcquireKeyLock)
+    }
+  }
+
+  /// Appends a sequence of blocks, to one ledger or several, locking each ledger once and writing
+  /// to the log once. Entries are applied in order until one fails: its error is returned if it
+  /// is the first entry, and otherwise the receipts of the entries before it are returned.
+  pub fn append_batch(&self, entries: &[BatchEntry]) -> Result<Vec<Receipt>, EndorserError> {
+    if let Ok(view_ledger_state) = self.view_ledger_state.read() {
+      match view_ledger_state.endorser_mode {
+        EndorserMode::Uninitialized | EndorserMode::Initialized => {
+          return Err(EndorserError::NotActive);
+        },
+        EndorserMode::Finalized => {
+          return Err(EndorserError::AlreadyFinalized);
+        },
+        _ => {},
+      }
+      check_lease(&view_ledger_state)?;
+      if view_ledger_state.is_locked {
+        return Err(EndorserError::Locked);
+      }
+
+      // ledgers are locked in a fixed order, so concurrent batches cannot deadlock
+      let mut handles = entries
+        .iter()
+        .map(|(handle, ..)| *handle)
+        .collect::<Vec<Handle>>();
+      handles.sort_by_key(|handle| handle.to_bytes());
+      handles.dedup();
+      let mut ledgers = Vec::new();
+      for handle in handles {
+        match self.ledger_tail_map.get(&handle) {
+          Ok(protected_metablock) => ledgers.push((handle, protected_metablock)),
+          Err(EndorserError::InvalidLedgerName) => {},
+          Err(error) => return Err(error),
+        }
+      }
+      let mut guards = HashMap::new();
+      for (handle, protected_metablock) in &ledgers {
+        match protected_metablock.write() {
+          Ok(guard) => {
+            guards.insert(*handle, guard);
           },
+          Err(_) => return Err(EndorserError::FailedToAcquireLedgerEntryWriteLock),
         }
-      } else {
-        Err(EndorserError::FailedToAcquireLedgerMapReadLock)
       }
+
+      // entries are checked against the tails left by the entries before them
+      let mut tails = HashMap::<Handle, MetaBlock>::new();
+      let mut receipts = Vec::new();
+      let mut records = Vec::new();
+      for (handle, block_hash, expected_height, block, nonces, timestamp) in entries {
+        let res = match (tails.get(handle), guards.get(handle)) {
+          (Some(tail), _) => next_metablock(tail, block_hash, *expected_height),
+          (None, Some(e)) => next_metablock(&e.0, block_hash, *expected_height),
+          (None, None) => Err(EndorserError::InvalidLedgerName),
+        };
+        let res = self
+          .check_timestamp(*timestamp)
+          .and(res.map(|metablock| metablock.with_timestamp(*timestamp)));
+        let new_metablock = match res {
+          Ok(metablock) => metablock,
+          Err(error) if receipts.is_empty() => return Err(error),
+          Err(_) => break,
+        };
+        records.push(ledger_record(handle, &new_metablock, block, nonces));
+        tails.insert(*handle, new_metablock.clone());
+        receipts.push(self.sign_ledger_entry(&view_ledger_state, handle, new_metablock)?);
+      }
+
+      self.persist(&records, false)?;
+      for ((handle, _block_hash, _height, block, nonces, _timestamp), receipt) in
+        entries.iter().zip(&receipts)
+      {
+        if let Some(e) = guards.get_mut(handle) {
+          **e = (
+            receipt.get_metablock().clone(),
+            block.clone(),
+            nonces.clone(),
+          );
+        }
+      }
+      Ok(receipts)
     } else {
       Err(EndorserError::FailedToAcquireViewLedgerReadLock)
     }
   }
 
-  pub fn get_public_key(&self) -> PublicKey {
-    self.public_key.clone()
+  pub fn get_public_key(&self) -> Result<PublicKey, EndorserError> {
+    if let Ok(keys) = self.keys.read() {
+      Ok(keys.public_key.clone())
+    } else {
+      Err(EndorserError::FailedToAcquireKeyLock)
+    }
   }
 
   fn append_view_ledger(
     &self,
     view_ledger_state: &mut ViewLedgerState,
-    ledger_tail_map: &Vec<LedgerTailMapEntry>,
+    ledger_tail_map: &[LedgerTailMapEntry],
     block_hash: &NimbleDigest,
     expected_height: usize,
   ) -> Result<Receipt, EndorserError> {
-    let metablock = &view_ledger_state.view_ledger_tail_metablock;
-
-    // perform a checked addition of height with 1
-    let height_plus_one = {
-      let res = metablock.get_height().checked_add(1);
-      if res.is_none() {
-        return Err(EndorserError::LedgerHeightOverflow);
-      }
-      res.unwrap()
-    };
-
-    assert!(expected_height != 0);
-    if expected_height < height_plus_one {
-      return Err(EndorserError::InvalidTailHeight);
-    }
-
-    if expected_height > height_plus_one {
-      return Err(EndorserError::OutOfOrder);
-    }
-
     // formulate a metablock for the new entry on the view ledger; and hash it to get the updated tail hash
-    let prev = view_ledger_state.view_ledger_tail_hash;
-    let new_metablock = MetaBlock::new(&prev, block_hash, height_plus_one);
+    let new_metablock = next_view_metablock(
+      &view_ledger_state.view_ledger_tail_metablock,
+      None,
+      block_hash,
+      expected_height,
+    )?;
 
     // update the internal state
     view_ledger_state.view_ledger_prev_metablock =
@@ -348,55 +956,57 @@ impl EndorserState {
     view_ledger_state.view_ledger_tail_metablock = new_metablock;
     view_ledger_state.view_ledger_tail_hash = view_ledger_state.view_ledger_tail_metablock.hash();
 
-    Ok(self.sign_view_ledger(view_ledger_state, ledger_tail_map))
+    self.sign_view_ledger(view_ledger_state, ledger_tail_map)
   }
 
   fn sign_view_ledger(
     &self,
     view_ledger_state: &ViewLedgerState,
-    ledger_tail_map: &Vec<LedgerTailMapEntry>,
-  ) -> Receipt {
+    ledger_tail_map: &[LedgerTailMapEntry],
+  ) -> Result<Receipt, EndorserError> {
     // the view embedded in the view ledger is the hash of the current state of the endorser
     let view = produce_hash_of_state(ledger_tail_map);
-    let message = view_ledger_state
-      .group_identity
-      .digest_with(&view.digest_with(&view_ledger_state.view_ledger_tail_hash));
-    let signature = self.private_key.sign(&message.to_bytes()).unwrap();
+    let message = ViewChangeAttestation::new(
+      &view_ledger_state.group_identity,
+      &view,
+      &view_ledger_state.view_ledger_tail_hash,
+    )
+    .message();
+    let id_sig = self.sign(&message.to_bytes())?;
 
-    Receipt::new(
+    Ok(Receipt::new(
       view,
       view_ledger_state.view_ledger_tail_metablock.clone(),
-      IdSig::new(self.public_key.clone(), signature),
-    )
+      id_sig,
+    ))
   }
 
   fn construct_ledger_tail_map(&self) -> Result<Vec<LedgerTailMapEntry>, EndorserError> {
     let mut ledger_tail_map = Vec::new();
-    if let Ok(ledger_tail_map_rd) = self.ledger_tail_map.read() {
-      for (handle, value) in ledger_tail_map_rd.deref().iter().sorted_by_key(|x| x.0) {
-        if let Ok(e) = value.read() {
-          ledger_tail_map.push(LedgerTailMapEntry {
-            handle: handle.to_bytes(),
-            height: e.0.get_height() as u64,
-            metablock: e.0.to_bytes(),
-            block: e.1.to_bytes(),
-            nonces: e.2.to_bytes(),
-          });
-        } else {
-          return Err(EndorserError::FailedToAcquireLedgerEntryReadLock);
-        }
+    for (handle, value) in self.ledger_tail_map.entries()? {
+      if let Ok(e) = value.read() {
+        ledger_tail_map.push(LedgerTailMapEntry {
+          handle: handle.to_bytes(),
+          height: e.0.get_height() as u64,
+          metablock: e.0.to_bytes(),
+          block: e.1.to_bytes(),
+          nonces: e.2.to_bytes(),
+        });
+      } else {
+        return Err(EndorserError::FailedToAcquireLedgerEntryReadLock);
       }
-    } else {
-      return Err(EndorserError::FailedToAcquireLedgerMapReadLock);
     }
 
     Ok(ledger_tail_map)
   }
 
+  /// Finalizes the endorser into the view `block_hash` at `expected_height`, which must be the
+  /// view right after the endorser's and, if `prev_view_hash` is given, extend the view it names
   pub fn finalize_state(
     &self,
     block_hash: &NimbleDigest,
     expected_height: usize,
+    prev_view_hash: Option<&NimbleDigest>,
   ) -> Result<(Receipt, Vec<LedgerTailMapEntry>), EndorserError> {
     if let Ok(mut view_ledger_state) = self.view_ledger_state.write() {
       if view_ledger_state.endorser_mode == EndorserMode::Uninitialized
@@ -408,16 +1018,25 @@ impl EndorserState {
       let ledger_tail_map = self.construct_ledger_tail_map()?;
 
       let receipt = if view_ledger_state.endorser_mode == EndorserMode::Finalized {
-        self.sign_view_ledger(view_ledger_state.deref(), &ledger_tail_map)
+        self.sign_view_ledger(view_ledger_state.deref(), &ledger_tail_map)?
       } else {
+        // the view is checked before the endorser is finalized, which it cannot take back
+        next_view_metablock(
+          &view_ledger_state.view_ledger_tail_metablock,
+          prev_view_hash,
+          block_hash,
+          expected_height,
+        )?;
         view_ledger_state.endorser_mode = EndorserMode::Finalized;
 
-        self.append_view_ledger(
+        let receipt = self.append_view_ledger(
           view_ledger_state.deref_mut(),
           &ledger_tail_map,
           block_hash,
           expected_height,
-        )?
+        )?;
+        self.persist(&[view_record(view_ledger_state.deref())], false)?;
+        receipt
       };
 
       Ok((receipt, ledger_tail_map))
@@ -426,6 +1045,36 @@ impl EndorserState {
     }
   }
 
+  /// Signs the tail of the view ledger together with a client's nonce, so that the client can
+  /// check that it learns the current view
+  pub fn read_view_tail(&self, nonce: &[u8]) -> Result<Receipt, EndorserError> {
+    if let Ok(view_ledger_state) = self.view_ledger_state.read() {
+      match view_ledger_state.endorser_mode {
+        EndorserMode::Uninitialized | EndorserMode::Initialized => {
+          return Err(EndorserError::NotActive);
+        },
+        EndorserMode::Finalized => {
+          return Err(EndorserError::AlreadyFinalized);
+        },
+        _ => {},
+      }
+      check_lease(&view_ledger_state)?;
+
+      let view = view_ledger_state.view_ledger_tail_hash;
+      let message =
+        ViewTailAttestation::new(&view_ledger_state.group_identity, &view, nonce).message();
+      let id_sig = self.sign(&message.to_bytes())?;
+
+      Ok(Receipt::new(
+        view,
+        view_ledger_state.view_ledger_tail_metablock.clone(),
+        id_sig,
+      ))
+    } else {
+      Err(EndorserError::FailedToAcquireViewLedgerReadLock)
+    }
+  }
+
   pub fn read_state(
     &self,
   ) -> Result<(Receipt, EndorserMode, Vec<LedgerTailMapEntry>), EndorserError> {
@@ -433,7 +1082,7 @@ impl EndorserState {
       let ledger_tail_map = self.construct_ledger_tail_map()?;
 
       Ok((
-        self.sign_view_ledger(view_ledger_state.deref(), &ledger_tail_map),
+        self.sign_view_ledger(view_ledger_state.deref(), &ledger_tail_map)?,
         view_ledger_state.endorser_mode,
         ledger_tail_map,
       ))
@@ -442,12 +1091,32 @@ impl EndorserState {
     }
   }
 
+  /// Stops the endorser from signing appends to ledgers until it is unlocked, so that the
+  /// ledger tails it reports during a view change cannot move
+  pub fn lock(&self) -> Result<(), EndorserError> {
+    if let Ok(mut view_ledger_state) = self.view_ledger_state.write() {
+      view_ledger_state.is_locked = true;
+      Ok(())
+    } else {
+      Err(EndorserError::FailedToAcquireViewLedgerWriteLock)
+    }
+  }
+
+  pub fn unlock(&self) -> Result<(), EndorserError> {
+    if let Ok(mut view_ledger_state) = self.view_ledger_state.write() {
+      view_ledger_state.is_locked = false;
+      Ok(())
+    } else {
+      Err(EndorserError::FailedToAcquireViewLedgerWriteLock)
+    }
+  }
+
   pub fn activate(
     &self,
     old_config: &[u8],
     new_config: &[u8],
     ledger_tail_maps: &Vec<LedgerTailMap>,
-    ledger_chunks: &Vec<LedgerChunkEntry>,
+    ledger_chunks: &[LedgerChunkEntry],
     receipts: &Receipts,
   ) -> Result<(), EndorserError> {
     if let Ok(mut view_ledger_state) = self.view_ledger_state.write() {
@@ -467,7 +1136,7 @@ impl EndorserState {
       let res = receipts.verify_view_change(
         old_config,
         new_config,
-        &self.public_key,
+        &self.get_public_key()?,
         &view_ledger_state.group_identity,
         &view_ledger_state.view_ledger_prev_metablock,
         &view_ledger_state.view_ledger_tail_metablock,
@@ -475,12 +1144,21 @@ impl EndorserState {
         ledger_chunks,
       );
 
-      if let Err(_e) = res {
-        Err(EndorserError::FailedToActivate)
-      } else {
-        view_ledger_state.endorser_mode = EndorserMode::Active;
-        Ok(())
-      }
+      // the view that activates the endorser also sets its lease, renewing or dropping the
+      // lease of the view before
+      let lease_expiry = match (res, retrieve_lease_from_config(new_config)) {
+        (Ok(()), Ok(lease_expiry)) => lease_expiry,
+        _ => return Err(EndorserError::FailedToActivate),
+      };
+      view_ledger_state.endorser_mode = EndorserMode::Active;
+      view_ledger_state.lease_expiry = lease_expiry;
+      self.persist(
+        &[
+          view_record(view_ledger_state.deref()),
+          LogRecord::Lease(lease_expiry),
+        ],
+        false,
+      )
     } else {
       Err(EndorserError::FailedToAcquireViewLedgerWriteLock)
     }
@@ -490,6 +1168,7 @@ impl EndorserState {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use ledger::signature::PublicKeyTrait;
   use rand::Rng;
 
   #[test]
@@ -564,7 +1243,7 @@ mod tests {
     assert!(receipt
       .get_id_sig()
       .verify_with_id(
-        &endorser_state.public_key,
+        &endorser_state.get_public_key().unwrap(),
         &view_block_hash
           .digest_with(
             &receipt
@@ -579,14 +1258,28 @@ mod tests {
     let tail_result = endorser_state.read_latest(&handle, &[0]);
     assert!(tail_result.is_ok());
 
-    let ledger_tail_map = endorser_state.ledger_tail_map.read().expect("failed");
+    // The view ledger tail is signed together with the client's nonce
+    let receipt = endorser_state.read_view_tail(&[1]).unwrap();
+    let view = *receipt.get_view();
+    assert_eq!(receipt.get_metablock().hash(), view);
+    assert!(receipt
+      .get_id_sig()
+      .verify_with_id(
+        &endorser_state.get_public_key().unwrap(),
+        &view_block_hash
+          .digest_with(&view.digest_with(&view.digest_with_bytes(&[1])))
+          .to_bytes(),
+      )
+      .is_ok());
 
-    let metablock = &ledger_tail_map
+    let metablock = endorser_state
+      .ledger_tail_map
       .get(&handle)
       .unwrap()
       .read()
       .expect("failed")
-      .0;
+      .0
+      .clone();
     assert_eq!(metablock.get_height(), 0usize);
     assert_eq!(metablock.hash(), genesis_tail_hash);
   }
@@ -644,8 +1337,6 @@ mod tests {
     // Fetch the value currently in the tail.
     let prev_tail = endorser_state
       .ledger_tail_map
-      .read()
-      .expect("failed")
       .get(&handle)
       .unwrap()
       .read()
@@ -658,8 +1349,6 @@ mod tests {
     let height_plus_one = {
       let height = endorser_state
         .ledger_tail_map
-        .read()
-        .expect("failed")
         .get(&handle)
         .unwrap()
         .read()
@@ -680,12 +1369,11 @@ mod tests {
         height_plus_one,
         &block_hash_to_append_data,
         &Nonces::new(),
+        None,
       )
       .unwrap();
     let new_ledger_height = endorser_state
       .ledger_tail_map
-      .read()
-      .expect("failed")
       .get(&handle)
       .unwrap()
       .read()
@@ -708,7 +1396,7 @@ mod tests {
     let endorser_tail_expectation = metadata.hash();
     let message = handle.digest_with(&endorser_tail_expectation);
     let tail_signature_verification = receipt.get_id_sig().verify_with_id(
-      &endorser_state.public_key,
+      &endorser_state.get_public_key().unwrap(),
       &view_block_hash
         .digest_with(&receipt.get_view().digest_with_bytes(&message.to_bytes()))
         .to_bytes(),
@@ -716,8 +1404,8 @@ mod tests {
 
     if tail_signature_verification.is_ok() {
       println!("Verification Passed. Checking Updated Tail");
-      let ledger_tail_map = endorser_state.ledger_tail_map.read().expect("failed");
-      let metablock_hash = ledger_tail_map
+      let metablock_hash = endorser_state
+        .ledger_tail_map
         .get(&handle)
         .unwrap()
         .read()
@@ -729,4 +1417,545 @@ mod tests {
       panic!("Signature verification failed when it should not have failed");
     }
   }
+
+  #[test]
+  pub fn check_endorser_append_batch() {
+    let endorser_state = EndorserState::new();
+    let view_block_hash = NimbleDigest::digest(&[1]);
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = ledger::endorser_proto::EndorserMode::Active;
+
+    let handles = [NimbleDigest::digest(&[2]), NimbleDigest::digest(&[3])];
+    for handle in &handles {
+      let block = Block::new(&handle.to_bytes());
+      assert!(endorser_state
+        .new_ledger(handle, &block.hash(), &block)
+        .is_ok());
+    }
+
+    // two appends to the first ledger and one to the second, then one that is out of order
+    let entry = |handle: &Handle, height: usize| {
+      let block = Block::new(&[height as u8]);
+      (*handle, block.hash(), height, block, Nonces::new(), None)
+    };
+    let batch = vec![
+      entry(&handles[0], 1),
+      entry(&handles[1], 1),
+      entry(&handles[0], 2),
+      entry(&handles[1], 3),
+    ];
+    let receipts = endorser_state.append_batch(&batch).unwrap();
+    assert_eq!(receipts.len(), 3);
+    assert_eq!(endorser_state.get_height(&handles[0]).unwrap(), 2);
+    assert_eq!(endorser_state.get_height(&handles[1]).unwrap(), 1);
+
+    // the receipts chain the entries of each ledger and sign them in the current view
+    let (tail, _block, _nonces) = endorser_state.read_latest(&handles[0], &[0]).unwrap();
+    assert_eq!(receipts[2].get_metablock(), tail.get_metablock());
+    assert_eq!(
+      receipts[2].get_metablock().get_prev(),
+      &receipts[0].get_metablock().hash()
+    );
+    for ((handle, ..), receipt) in batch.iter().zip(&receipts) {
+      let message = view_block_hash.digest_with(
+        &receipt
+          .get_view()
+          .digest_with(&handle.digest_with(&receipt.get_metablock().hash())),
+      );
+      assert!(receipt.get_id_sig().verify(&message.to_bytes()).is_ok());
+    }
+
+    // the first entry's error is returned
+    let res = endorser_state.append_batch(&batch[..1]);
+    assert!(matches!(res, Err(EndorserError::LedgerExists)));
+    let res = endorser_state.append_batch(&[entry(&NimbleDigest::digest(&[4]), 1)]);
+    assert!(matches!(res, Err(EndorserError::InvalidLedgerName)));
+  }
+
+  #[test]
+  pub fn check_endorser_ledgers_lock_independently() {
+    let endorser_state = EndorserState::new();
+    let view_block_hash = NimbleDigest::digest(&[1]);
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = ledger::endorser_proto::EndorserMode::Active;
+
+    let handles = [NimbleDigest::digest(&[2]), NimbleDigest::digest(&[3])];
+    for handle in &handles {
+      let block = Block::new(&handle.to_bytes());
+      assert!(endorser_state
+        .new_ledger(handle, &block.hash(), &block)
+        .is_ok());
+    }
+
+    // an append to the first ledger waits for its lock, which the test holds, while the second
+    // ledger is appended to and read, and a new ledger is created
+    let protected_metablock = endorser_state.ledger_tail_map.get(&handles[0]).unwrap();
+    let held = protected_metablock.write().unwrap();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+      let stalled = scope.spawn(|| {
+        let block = Block::new(&[1]);
+        endorser_state.append(&handles[0], &block.hash(), 1, &block, &Nonces::new(), None)
+      });
+      std::thread::sleep(std::time::Duration::from_millis(100));
+      scope.spawn(|| {
+        let block = Block::new(&[1]);
+        let res = endorser_state
+          .append(&handles[1], &block.hash(), 1, &block, &Nonces::new(), None)
+          .and_then(|_receipt| endorser_state.read_latest(&handles[1], &[0]))
+          .and_then(|_tail| {
+            let handle = NimbleDigest::digest(&[4]);
+            endorser_state.new_ledger(&handle, &block.hash(), &block)
+          });
+        sender.send(res.is_ok()).unwrap();
+      });
+      let res = receiver.recv_timeout(std::time::Duration::from_secs(10));
+      drop(held);
+      assert_eq!(res, Ok(true));
+      assert!(stalled.join().unwrap().is_ok());
+    });
+
+    assert_eq!(endorser_state.get_height(&handles[0]).unwrap(), 1);
+    assert_eq!(endorser_state.read_state().unwrap().2.len(), 3);
+  }
+
+  #[test]
+  pub fn check_endorser_rejects_appends_while_locked() {
+    let endorser_state = EndorserState::new();
+    let view_block_hash = NimbleDigest::digest(&[1]);
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = ledger::endorser_proto::EndorserMode::Active;
+
+    let handle = NimbleDigest::digest(&[2]);
+    let block = Block::new(&[2]);
+    assert!(endorser_state
+      .new_ledger(&handle, &block.hash(), &block)
+      .is_ok());
+
+    assert!(endorser_state.lock().is_ok());
+    let other = NimbleDigest::digest(&[3]);
+    let res = endorser_state.new_ledger(&other, &block.hash(), &block);
+    assert!(matches!(res, Err(EndorserError::Locked)));
+    let res = endorser_state.append(&handle, &block.hash(), 1, &block, &Nonces::new(), None);
+    assert!(matches!(res, Err(EndorserError::Locked)));
+    let res =
+      endorser_state.append_batch(&[(handle, block.hash(), 1, block.clone(), Nonces::new(), None)]);
+    assert!(matches!(res, Err(EndorserError::Locked)));
+    assert_eq!(endorser_state.get_height(&handle).unwrap(), 0);
+
+    // reads and the view change still go through while the endorser is locked
+    assert!(endorser_state.read_latest(&handle, &[0]).is_ok());
+    assert!(endorser_state.read_state().is_ok());
+
+    assert!(endorser_state.unlock().is_ok());
+    assert!(endorser_state
+      .append(&handle, &block.hash(), 1, &block, &Nonces::new(), None)
+      .is_ok());
+    assert_eq!(endorser_state.get_height(&handle).unwrap(), 1);
+  }
+
+  #[test]
+  pub fn check_endorser_rejects_views_that_do_not_extend_its_own() {
+    let endorser_state = EndorserState::new();
+    let view_block_hash = NimbleDigest::digest(&[1]);
+
+    // a first view must follow the empty view ledger
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      2,
+    );
+    assert!(matches!(res, Err(EndorserError::ViewHeightGap)));
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      0,
+    );
+    assert!(matches!(res, Err(EndorserError::StaleViewHeight)));
+    assert_eq!(
+      endorser_state.get_mode().unwrap(),
+      EndorserMode::Uninitialized
+    );
+
+    let receipt = endorser_state
+      .initialize_state(
+        &view_block_hash,
+        &Vec::new(),
+        &MetaBlock::default(),
+        &view_block_hash,
+        1,
+      )
+      .unwrap();
+    let view = receipt.get_metablock().hash();
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = EndorserMode::Active;
+
+    // the next view must come right after the endorser's view and extend it, and a rejected view
+    // leaves the endorser active in its view
+    let next_block_hash = NimbleDigest::digest(&[2]);
+    let res = endorser_state.finalize_state(&next_block_hash, 1, Some(&view));
+    assert!(matches!(res, Err(EndorserError::StaleViewHeight)));
+    let res = endorser_state.finalize_state(&next_block_hash, 3, Some(&view));
+    assert!(matches!(res, Err(EndorserError::ViewHeightGap)));
+    let res = endorser_state.finalize_state(&next_block_hash, 2, Some(&next_block_hash));
+    assert!(matches!(res, Err(EndorserError::ViewTailMismatch)));
+    assert_eq!(endorser_state.get_mode().unwrap(), EndorserMode::Active);
+
+    let (receipt, _ledger_tail_map) = endorser_state
+      .finalize_state(&next_block_hash, 2, Some(&view))
+      .unwrap();
+    assert_eq!(
+      receipt.get_metablock(),
+      &MetaBlock::new(&view, &next_block_hash, 2)
+    );
+    assert_eq!(endorser_state.get_mode().unwrap(), EndorserMode::Finalized);
+  }
+
+  #[test]
+  pub fn check_endorser_refuses_to_sign_after_its_lease() {
+    let dir = std::env::temp_dir().join(format!(
+      "nimble-endorser-{}",
+      rand::thread_rng().gen::<u64>()
+    ));
+    let endorser_state = EndorserState::with_storage(&dir, SignatureScheme::P256).unwrap();
+    let view_block_hash = NimbleDigest::digest(&[1]);
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    {
+      let mut view_ledger_state = endorser_state.view_ledger_state.write().unwrap();
+      view_ledger_state.endorser_mode = EndorserMode::Active;
+      view_ledger_state.lease_expiry = Some(now_millis() + 60_000);
+      let res = endorser_state.persist(
+        &[
+          view_record(view_ledger_state.deref()),
+          LogRecord::Lease(view_ledger_state.lease_expiry),
+        ],
+        false,
+      );
+      assert!(res.is_ok());
+    }
+
+    let handle = NimbleDigest::digest(&[2]);
+    let block = Block::new(&[2]);
+    assert!(endorser_state
+      .new_ledger(&handle, &block.hash(), &block)
+      .is_ok());
+
+    // once the lease expires the endorser signs nothing for clients
+    endorser_state
+      .view_ledger_state
+      .write()
+      .unwrap()
+      .lease_expiry = Some(now_millis() - 1);
+    let res = endorser_state.append(&handle, &block.hash(), 1, &block, &Nonces::new(), None);
+    assert!(matches!(res, Err(EndorserError::LeaseExpired)));
+    let res =
+      endorser_state.append_batch(&[(handle, block.hash(), 1, block.clone(), Nonces::new(), None)]);
+    assert!(matches!(res, Err(EndorserError::LeaseExpired)));
+    let res = endorser_state.read_latest(&handle, &[0]);
+    assert!(matches!(res, Err(EndorserError::LeaseExpired)));
+    let res = endorser_state.read_view_tail(&[0]);
+    assert!(matches!(res, Err(EndorserError::LeaseExpired)));
+
+    // but it still takes part in the view change that renews its lease
+    assert!(endorser_state.read_state().is_ok());
+    assert!(endorser_state
+      .finalize_state(&NimbleDigest::digest(&[3]), 2, None)
+      .is_ok());
+
+    // the lease survives a restart
+    drop(endorser_state);
+    let recovered = EndorserState::with_storage(&dir, SignatureScheme::P256).unwrap();
+    assert!(recovered
+      .view_ledger_state
+      .read()
+      .unwrap()
+      .lease_expiry
+      .is_some());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  pub fn check_endorser_attests_timestamps() {
+    let mut endorser_state = EndorserState::new();
+    endorser_state.set_max_clock_skew(Duration::from_secs(1));
+    let view_block_hash = NimbleDigest::digest(&[1]);
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = ledger::endorser_proto::EndorserMode::Active;
+
+    let handle = NimbleDigest::digest(&[2]);
+    let block = Block::new(&[2]);
+    assert!(endorser_state
+      .new_ledger(&handle, &block.hash(), &block)
+      .is_ok());
+
+    // a timestamp too far from the endorser's clock is not attested to, in either direction
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap()
+      .as_millis() as u64;
+    for timestamp in [now - 60_000, now + 60_000] {
+      let res = endorser_state.append(
+        &handle,
+        &block.hash(),
+        1,
+        &block,
+        &Nonces::new(),
+        Some(timestamp),
+      );
+      assert!(matches!(res, Err(EndorserError::TimestampOutOfBounds)));
+      let res = endorser_state.append_batch(&[(
+        handle,
+        block.hash(),
+        1,
+        block.clone(),
+        Nonces::new(),
+        Some(timestamp),
+      )]);
+      assert!(matches!(res, Err(EndorserError::TimestampOutOfBounds)));
+    }
+    assert_eq!(endorser_state.get_height(&handle).unwrap(), 0);
+
+    // a close one is signed with the metablock, which chains as if it had none
+    let receipt = endorser_state
+      .append(&handle, &block.hash(), 1, &block, &Nonces::new(), Some(now))
+      .unwrap();
+    let metablock = receipt.get_metablock();
+    assert_eq!(metablock.get_timestamp(), Some(now));
+    let message =
+      AppendAttestation::for_metablock(&view_block_hash, receipt.get_view(), &handle, metablock);
+    assert!(message.verify(receipt.get_id_sig()).is_ok());
+    let untimestamped = AppendAttestation::new(
+      &view_block_hash,
+      receipt.get_view(),
+      &handle,
+      &metablock.hash(),
+    );
+    assert!(untimestamped.verify(receipt.get_id_sig()).is_err());
+    let receipt = endorser_state
+      .append(&handle, &block.hash(), 2, &block, &Nonces::new(), None)
+      .unwrap();
+    assert_eq!(receipt.get_metablock().get_prev(), &metablock.hash());
+  }
+
+  #[test]
+  pub fn check_endorser_recovers_from_storage() {
+    let dir = std::env::temp_dir().join(format!(
+      "nimble-endorser-{}",
+      rand::thread_rng().gen::<u64>()
+    ));
+    let endorser_state = EndorserState::with_storage(&dir, SignatureScheme::P256).unwrap();
+    assert_eq!(endorser_state.get_incarnation(), (0, true));
+
+    let view_block_hash = NimbleDigest::digest(&[1]);
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    {
+      let mut view_ledger_state = endorser_state.view_ledger_state.write().unwrap();
+      view_ledger_state.endorser_mode = EndorserMode::Active;
+      let res = endorser_state.persist(&[view_record(view_ledger_state.deref())], false);
+      assert!(res.is_ok());
+    }
+
+    let handle = NimbleDigest::digest(&[2]);
+    let block = Block::new(&[3]);
+    assert!(endorser_state
+      .new_ledger(&handle, &block.hash(), &block)
+      .is_ok());
+    let block = Block::new(&[4]);
+    assert!(endorser_state
+      .append(&handle, &block.hash(), 1, &block, &Nonces::new(), None)
+      .is_ok());
+    let (receipt, _block, _nonces) = endorser_state.read_latest(&handle, &[0]).unwrap();
+    drop(endorser_state);
+
+    // the restarted endorser keeps its key and the tails it signed, so it cannot sign a different
+    // entry at the same height
+    let recovered = EndorserState::with_storage(&dir, SignatureScheme::Ed25519).unwrap();
+    assert_eq!(recovered.get_incarnation(), (1, true));
+    assert_eq!(
+      recovered.get_public_key().unwrap().to_bytes(),
+      *receipt.get_id_sig().get_id()
+    );
+    assert_eq!(recovered.get_height(&handle).unwrap(), 1);
+    let (recovered_receipt, recovered_block, _nonces) =
+      recovered.read_latest(&handle, &[0]).unwrap();
+    assert_eq!(recovered_receipt.get_metablock(), receipt.get_metablock());
+    assert_eq!(recovered_receipt.get_view(), receipt.get_view());
+    assert_eq!(recovered_block.to_bytes(), block.to_bytes());
+    let other = Block::new(&[5]);
+    let res = recovered.append(&handle, &other.hash(), 1, &other, &Nonces::new(), None);
+    assert!(matches!(res, Err(EndorserError::LedgerExists)));
+    drop(recovered);
+
+    let recovered = EndorserState::with_storage(&dir, SignatureScheme::P256).unwrap();
+    assert_eq!(recovered.get_incarnation(), (2, true));
+    assert_eq!(recovered.get_height(&handle).unwrap(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  pub fn check_endorser_rotates_key() {
+    let dir = std::env::temp_dir().join(format!(
+      "nimble-endorser-{}",
+      rand::thread_rng().gen::<u64>()
+    ));
+    let endorser_state = EndorserState::with_storage(&dir, SignatureScheme::P256).unwrap();
+    let old_pk = endorser_state.get_public_key().unwrap();
+
+    let view_block_hash = NimbleDigest::digest(&[1]);
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    let res = endorser_state.rotate_key();
+    assert!(matches!(res, Err(EndorserError::NotActive)));
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = EndorserMode::Active;
+
+    let handle = NimbleDigest::digest(&[2]);
+    let block = Block::new(&[3]);
+    assert!(endorser_state
+      .new_ledger(&handle, &block.hash(), &block)
+      .is_ok());
+
+    // the old key hands over to the new one, which is not used yet; a second rotation replaces
+    // the first new key
+    let (_pk, _handover) = endorser_state.rotate_key().unwrap();
+    let (new_pk, handover) = endorser_state.rotate_key().unwrap();
+    assert_eq!(handover.get_old_pk(), &old_pk.to_bytes());
+    assert_eq!(handover.get_new_pk(), &new_pk.to_bytes());
+    assert!(handover.verify(&view_block_hash).is_ok());
+    assert!(handover.verify(&NimbleDigest::digest(&[4])).is_err());
+    assert_eq!(
+      endorser_state.get_public_key().unwrap().to_bytes(),
+      old_pk.to_bytes()
+    );
+
+    // the endorser is finalized with the old key into the next view ledger entry
+    let config_hash = NimbleDigest::digest(&[5]);
+    let (finalize_receipt, ledger_tail_map) = endorser_state
+      .finalize_state(&config_hash, 2, None)
+      .unwrap();
+    assert_eq!(finalize_receipt.get_id_sig().get_id(), &old_pk.to_bytes());
+    let view_tail_metablock = MetaBlock::new(&MetaBlock::default().hash(), &view_block_hash, 1);
+
+    // it carries over only into that entry and only with all of its ledgers
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &ledger_tail_map,
+      &MetaBlock::default(),
+      &config_hash,
+      2,
+    );
+    assert!(matches!(res, Err(EndorserError::AlreadyInitialized)));
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &view_tail_metablock,
+      &config_hash,
+      2,
+    );
+    assert!(matches!(res, Err(EndorserError::InvalidLedgerTailMap)));
+
+    // and then signs with the new key, which it keeps across restarts
+    let receipt = endorser_state
+      .initialize_state(
+        &view_block_hash,
+        &ledger_tail_map,
+        &view_tail_metablock,
+        &config_hash,
+        2,
+      )
+      .unwrap();
+    assert_eq!(receipt.get_id_sig().get_id(), &new_pk.to_bytes());
+    assert_eq!(receipt.get_metablock(), finalize_receipt.get_metablock());
+    assert_eq!(
+      endorser_state.get_mode().unwrap(),
+      EndorserMode::Initialized
+    );
+    assert_eq!(
+      endorser_state.get_public_key().unwrap().to_bytes(),
+      new_pk.to_bytes()
+    );
+    drop(endorser_state);
+
+    let recovered = EndorserState::with_storage(&dir, SignatureScheme::P256).unwrap();
+    assert_eq!(
+      recovered.get_public_key().unwrap().to_bytes(),
+      new_pk.to_bytes()
+    );
+    assert_eq!(recovered.get_mode().unwrap(), EndorserMode::Initialized);
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
 }
@@ -34,4 +34,32 @@ pub enum EndorserError {
   NotActive,
   /// returned if the endorser is already activated
   AlreadyActivated,
+  /// returned if an entry of a ledger tail map cannot be decoded
+  InvalidLedgerTailMap,
+  /// returned if the endorser's storage cannot be read or written
+  FailedToAccessStorage,
+  /// returned if the endorser's public key cannot be attested
+  FailedToAttest,
+  /// returned if the endorser is locked and so signs no appends
+  Locked,
+  /// returned if failed to acquire the lock on the endorser's keys
+  FailedToAcquireKeyLock,
+  /// returned if the endorser's key fails to sign, such as when the module holding it is
+  /// unavailable
+  FailedToSign,
+  /// returned if the endorser's key cannot be found, generated, or taken into use in the module
+  /// holding it
+  FailedToAccessKey,
+  /// returned if the timestamp proposed for an append is further from the endorser's clock than
+  /// its skew bound
+  TimestampOutOfBounds,
+  /// returned if the lease of the endorser in the current view has expired, so that it signs
+  /// nothing for clients until a view change renews it
+  LeaseExpired,
+  /// returned if a new view is not past the endorser's view
+  StaleViewHeight,
+  /// returned if a new view skips views after the endorser's view
+  ViewHeightGap,
+  /// returned if a new view extends a view other than the endorser's
+  ViewTailMismatch,
 }
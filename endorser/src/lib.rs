@@ -0,0 +1,11 @@
+//! The endorser's state and gRPC service, as a library for tools that run endorsers in-process,
+//! such as the test harness in `nimble-testkit`. The endorser binary serves it over the network.
+pub mod endorser_state;
+pub mod errors;
+mod persistence;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+pub mod service;
+pub mod signer;
+#[cfg(test)]
+mod simulation;
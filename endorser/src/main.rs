@@ -1,371 +1,38 @@
-use crate::{endorser_state::EndorserState, errors::EndorserError};
 use clap::{App, Arg};
+use endorser::{service::EndorserServiceState, signer::Signer};
 use ledger::{
-  signature::PublicKeyTrait, Block, CustomSerde, MetaBlock, NimbleDigest, Nonces, Receipts,
+  attestation::attester_from_name,
+  config::args_with_config_file,
+  health::{HealthReporter, HealthServer, ServingStatus, SERVER_STATUS},
+  logging,
+  reflection::{
+    ReflectionService, ServerReflectionServer, ENDORSER_DESCRIPTOR_SET, HEALTH_DESCRIPTOR_SET,
+    REFLECTION_DESCRIPTOR_SET,
+  },
+  shutdown::{shutdown_signal, Shutdown},
+  signature::SignatureScheme,
+  telemetry::{grpc_request_span, OtlpExporter},
+  NimbleDigest,
 };
-use tonic::{transport::Server, Code, Request, Response, Status};
+use std::{fs, path::Path, time::Duration};
+use tonic::transport::{Certificate, Identity, NamedService, Server, ServerTlsConfig};
+use tracing::{info, warn};
 
-mod endorser_state;
-mod errors;
-
-use ledger::endorser_proto::{
-  endorser_call_server::{EndorserCall, EndorserCallServer},
-  ActivateReq, ActivateResp, AppendReq, AppendResp, FinalizeStateReq, FinalizeStateResp,
-  GetPublicKeyReq, GetPublicKeyResp, InitializeStateReq, InitializeStateResp, NewLedgerReq,
-  NewLedgerResp, ReadLatestReq, ReadLatestResp, ReadStateReq, ReadStateResp,
-};
-
-pub struct EndorserServiceState {
-  state: EndorserState,
-}
-
-impl EndorserServiceState {
-  pub fn new() -> Self {
-    EndorserServiceState {
-      state: EndorserState::new(),
-    }
-  }
-
-  fn process_error(
-    &self,
-    error: EndorserError,
-    handle: Option<&NimbleDigest>,
-    default_msg: impl Into<String>,
-  ) -> Status {
-    match error {
-      EndorserError::OutOfOrder => {
-        if let Some(h) = handle {
-          let height = self.state.get_height(h).unwrap();
-          Status::with_details(
-            Code::FailedPrecondition,
-            "Out of order",
-            bytes::Bytes::copy_from_slice(&(height as u64).to_le_bytes()),
-          )
-        } else {
-          Status::failed_precondition("View ledger height is out of order")
-        }
-      },
-      EndorserError::LedgerExists => Status::already_exists("Ledger exists"),
-      EndorserError::InvalidLedgerName => Status::not_found("Ledger handle not found"),
-      EndorserError::LedgerHeightOverflow => Status::out_of_range("Ledger height overflow"),
-      EndorserError::InvalidTailHeight => Status::invalid_argument("Invalid ledger height"),
-      EndorserError::AlreadyInitialized => {
-        Status::already_exists("Enodrser is already initialized")
-      },
-      EndorserError::NotInitialized => Status::unimplemented("Endorser is not initialized"),
-      EndorserError::AlreadyFinalized => Status::unavailable("Endorser is already finalized"),
-      _ => Status::internal(default_msg),
-    }
-  }
-}
-
-impl Default for EndorserServiceState {
-  fn default() -> Self {
-    Self::new()
-  }
-}
-
-#[tonic::async_trait]
-impl EndorserCall for EndorserServiceState {
-  async fn get_public_key(
-    &self,
-    _req: Request<GetPublicKeyReq>,
-  ) -> Result<Response<GetPublicKeyResp>, Status> {
-    let pk = self.state.get_public_key();
-
-    let reply = GetPublicKeyResp {
-      pk: pk.to_bytes().to_vec(),
-    };
-
-    Ok(Response::new(reply))
-  }
-
-  async fn new_ledger(
-    &self,
-    req: Request<NewLedgerReq>,
-  ) -> Result<Response<NewLedgerResp>, Status> {
-    let NewLedgerReq {
-      handle,
-      block_hash,
-      block,
-    } = req.into_inner();
-    let handle = {
-      let res = NimbleDigest::from_bytes(&handle);
-      if res.is_err() {
-        return Err(Status::invalid_argument("Handle size is invalid"));
-      }
-      res.unwrap()
-    };
-
-    let block_hash = {
-      let res = NimbleDigest::from_bytes(&block_hash);
-      if res.is_err() {
-        return Err(Status::invalid_argument("Block hash size is invalid"));
-      }
-      res.unwrap()
-    };
-
-    let block = {
-      let res = Block::from_bytes(&block);
-      if res.is_err() {
-        return Err(Status::invalid_argument("Block is invalid"));
-      }
-      res.unwrap()
-    };
-
-    let res = self.state.new_ledger(&handle, &block_hash, &block);
-
-    match res {
-      Ok(receipt) => {
-        let reply = NewLedgerResp {
-          receipt: receipt.to_bytes().to_vec(),
-        };
-        Ok(Response::new(reply))
-      },
-      Err(error) => {
-        let status = self.process_error(
-          error,
-          None,
-          "Failed to create a new ledger due to an internal error",
-        );
-        Err(status)
-      },
-    }
-  }
-
-  async fn append(&self, req: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
-    let AppendReq {
-      handle,
-      block_hash,
-      expected_height,
-      block,
-      nonces,
-    } = req.into_inner();
-
-    let handle_instance = NimbleDigest::from_bytes(&handle);
-    let block_hash_instance = NimbleDigest::from_bytes(&block_hash);
-    let block_instance = Block::from_bytes(&block);
-    let nonces_instance = Nonces::from_bytes(&nonces);
-
-    if handle_instance.is_err()
-      || block_hash_instance.is_err()
-      || block_instance.is_err()
-      || nonces_instance.is_err()
-    {
-      return Err(Status::invalid_argument("Invalid input sizes"));
-    }
-
-    if expected_height == 0 {
-      return Err(Status::invalid_argument("Invalid expected height"));
-    }
-
-    let handle = handle_instance.unwrap();
-    let block_hash = block_hash_instance.unwrap();
-    let block = block_instance.unwrap();
-    let nonces = nonces_instance.unwrap();
-
-    let res = self.state.append(
-      &handle,
-      &block_hash,
-      expected_height as usize,
-      &block,
-      &nonces,
-    );
-
-    match res {
-      Ok(receipt) => {
-        let reply = AppendResp {
-          receipt: receipt.to_bytes().to_vec(),
-        };
-        Ok(Response::new(reply))
-      },
-
-      Err(error) => {
-        let status = self.process_error(
-          error,
-          Some(&handle),
-          "Failed to append to a ledger due to an internal error",
-        );
-        Err(status)
-      },
-    }
-  }
-
-  async fn read_latest(
-    &self,
-    request: Request<ReadLatestReq>,
-  ) -> Result<Response<ReadLatestResp>, Status> {
-    let ReadLatestReq { handle, nonce } = request.into_inner();
-    let handle = {
-      let res = NimbleDigest::from_bytes(&handle);
-      if res.is_err() {
-        return Err(Status::invalid_argument("Invalid handle size"));
-      }
-      res.unwrap()
-    };
-    let res = self.state.read_latest(&handle, &nonce);
-
-    match res {
-      Ok((receipt, block, nonces)) => {
-        let reply = ReadLatestResp {
-          receipt: receipt.to_bytes().to_vec(),
-          block: block.to_bytes().to_vec(),
-          nonces: nonces.to_bytes().to_vec(),
-        };
-        Ok(Response::new(reply))
-      },
-      Err(error) => {
-        let status = self.process_error(
-          error,
-          Some(&handle),
-          "Failed to read a ledger due to an internal error",
-        );
-        Err(status)
-      },
-    }
-  }
-
-  async fn finalize_state(
-    &self,
-    req: Request<FinalizeStateReq>,
-  ) -> Result<Response<FinalizeStateResp>, Status> {
-    let FinalizeStateReq {
-      block_hash,
-      expected_height,
-    } = req.into_inner();
-
-    let block_hash_instance = NimbleDigest::from_bytes(&block_hash);
-
-    if block_hash_instance.is_err() {
-      return Err(Status::invalid_argument("Invalid input sizes"));
-    }
-
-    let res = self
-      .state
-      .finalize_state(&block_hash_instance.unwrap(), expected_height as usize);
-
-    match res {
-      Ok((receipt, ledger_tail_map)) => {
-        let reply = FinalizeStateResp {
-          receipt: receipt.to_bytes().to_vec(),
-          ledger_tail_map,
-        };
-        Ok(Response::new(reply))
-      },
-      Err(error) => {
-        let status = self.process_error(
-          error,
-          None,
-          "Failed to finalize the endorser due to an internal error",
-        );
-        Err(status)
-      },
-    }
-  }
-
-  async fn initialize_state(
-    &self,
-    req: Request<InitializeStateReq>,
-  ) -> Result<Response<InitializeStateResp>, Status> {
-    let InitializeStateReq {
-      group_identity,
-      ledger_tail_map,
-      view_tail_metablock,
-      block_hash,
-      expected_height,
-    } = req.into_inner();
-    let group_identity_rs = NimbleDigest::from_bytes(&group_identity).unwrap();
-    let view_tail_metablock_rs = MetaBlock::from_bytes(&view_tail_metablock).unwrap();
-    let block_hash_rs = NimbleDigest::from_bytes(&block_hash).unwrap();
-    let res = self.state.initialize_state(
-      &group_identity_rs,
-      &ledger_tail_map,
-      &view_tail_metablock_rs,
-      &block_hash_rs,
-      expected_height as usize,
-    );
-
-    match res {
-      Ok(receipt) => {
-        let reply = InitializeStateResp {
-          receipt: receipt.to_bytes().to_vec(),
-        };
-        Ok(Response::new(reply))
-      },
-      Err(error) => {
-        let status = self.process_error(
-          error,
-          None,
-          "Failed to initialize an endorser due to an internal error",
-        );
-        Err(status)
-      },
-    }
-  }
-
-  async fn read_state(
-    &self,
-    _req: Request<ReadStateReq>,
-  ) -> Result<Response<ReadStateResp>, Status> {
-    let res = self.state.read_state();
-
-    match res {
-      Ok((receipt, endorser_mode, ledger_tail_map)) => {
-        let reply = ReadStateResp {
-          receipt: receipt.to_bytes().to_vec(),
-          mode: endorser_mode as i32,
-          ledger_tail_map,
-        };
-        Ok(Response::new(reply))
-      },
-      Err(error) => {
-        let status = self.process_error(
-          error,
-          None,
-          "Failed to finalize the endorser due to an internal error",
-        );
-        Err(status)
-      },
-    }
-  }
-
-  async fn activate(&self, req: Request<ActivateReq>) -> Result<Response<ActivateResp>, Status> {
-    let ActivateReq {
-      old_config,
-      new_config,
-      ledger_tail_maps,
-      ledger_chunks,
-      receipts,
-    } = req.into_inner();
-    let receipts_rs = Receipts::from_bytes(&receipts).unwrap();
-    let res = self.state.activate(
-      &old_config,
-      &new_config,
-      &ledger_tail_maps,
-      &ledger_chunks,
-      &receipts_rs,
-    );
-
-    match res {
-      Ok(()) => {
-        let reply = ActivateResp {};
-        Ok(Response::new(reply))
-      },
-      Err(error) => {
-        let status = self.process_error(
-          error,
-          None,
-          "Failed to verify the view change due to an internal error",
-        );
-        Err(status)
-      },
-    }
-  }
-}
+use ledger::endorser_proto::endorser_call_server::EndorserCallServer;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  let schemes = SignatureScheme::all()
+    .iter()
+    .map(|scheme| scheme.get_name())
+    .collect::<Vec<&str>>();
   let config = App::new("endorser")
+    .arg(
+      Arg::with_name("config")
+        .long("config")
+        .takes_value(true)
+        .help("A JSON file of options, keyed by their long names; options given here override it"),
+    )
     .arg(
       Arg::with_name("host")
         .short("t")
@@ -379,23 +46,262 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .long("port")
         .help("The port number to run the Service On. Default: 9096")
         .default_value("9090"),
+    )
+    .arg(
+      Arg::with_name("scheme")
+        .short("s")
+        .long("scheme")
+        .help("The signature scheme of the endorser's key")
+        .possible_values(&schemes)
+        .default_value("p256"),
+    )
+    .arg(
+      Arg::with_name("storage_path")
+        .long("storage-path")
+        .help("The directory in which the endorser persists its key and state")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("attestation")
+        .long("attestation")
+        .help("The trusted execution environment that attests the endorser's public key")
+        .possible_values(&["mock"])
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("tls_cert")
+        .long("tls-cert")
+        .help("The PEM certificate with which the endorser serves TLS")
+        .takes_value(true)
+        .requires("tls_key"),
+    )
+    .arg(
+      Arg::with_name("tls_key")
+        .long("tls-key")
+        .help("The PEM private key of the TLS certificate")
+        .takes_value(true)
+        .requires("tls_cert"),
+    )
+    .arg(
+      Arg::with_name("tls_ca")
+        .long("tls-ca")
+        .help("The PEM CA that client certificates must chain to; only clients holding one, such as the coordinator, are served")
+        .takes_value(true)
+        .requires("tls_cert"),
+    )
+    .arg(
+      Arg::with_name("log_level")
+        .long("log-level")
+        .help("The least severe level of the events to log")
+        .possible_values(&["error", "warn", "info", "debug", "trace"])
+        .default_value("info")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("shutdown_timeout")
+        .long("shutdown-timeout")
+        .takes_value(true)
+        .help("The seconds to wait on SIGTERM for the requests in flight to finish before exiting")
+        .default_value("30"),
+    )
+    .arg(
+      Arg::with_name("max_clock_skew")
+        .long("max-clock-skew")
+        .takes_value(true)
+        .help("The milliseconds by which a timestamp proposed for an append may differ from the endorser's clock for the endorser to attest to it")
+        .default_value("30000"),
+    )
+    .arg(
+      Arg::with_name("log_json")
+        .long("log-json")
+        .help("Logs events as JSON lines instead of text"),
+    )
+    .arg(
+      Arg::with_name("otlp_endpoint")
+        .long("otlp-endpoint")
+        .takes_value(true)
+        .help("Exports traces to an OpenTelemetry collector at this OTLP/HTTP URL, e.g., http://localhost:4318"),
+    )
+    .arg(
+      Arg::with_name("enable_reflection")
+        .long("enable-reflection")
+        .help("Serves gRPC reflection, with which tools such as grpcurl discover the services"),
+    );
+  #[cfg(feature = "pkcs11")]
+  let config = config
+    .arg(
+      Arg::with_name("pkcs11_module")
+        .long("pkcs11-module")
+        .help(
+          "The PKCS#11 library of the hardware security module that holds the endorser's P-256 key",
+        )
+        .takes_value(true)
+        .conflicts_with("scheme")
+        .requires("pkcs11_pin"),
+    )
+    .arg(
+      Arg::with_name("pkcs11_slot")
+        .long("pkcs11-slot")
+        .help("The slot of the token that holds the key")
+        .default_value("0")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("pkcs11_pin")
+        .long("pkcs11-pin")
+        .help("The user PIN of the token")
+        .takes_value(true)
+        .requires("pkcs11_module"),
+    )
+    .arg(
+      Arg::with_name("pkcs11_key_label")
+        .long("pkcs11-key-label")
+        .help("The label of the key pair on the token, which is generated if the token has none")
+        .default_value("nimble-endorser")
+        .takes_value(true),
     );
-  let cli_matches = config.get_matches();
+  // options on the command line override the ones from the configuration file
+  let given = config.clone().get_matches();
+  let args = match args_with_config_file(std::env::args_os(), |name| given.occurrences_of(name) > 0)
+  {
+    Ok(args) => args,
+    Err(error) => panic!("Failed to read the configuration file ({:?})", error),
+  };
+  let cli_matches = config.get_matches_from(args);
+  let log_level = cli_matches.value_of("log_level").unwrap();
+  let exporter = cli_matches
+    .value_of("otlp_endpoint")
+    .map(|endpoint| OtlpExporter::start(endpoint, "nimble-endorser"))
+    .transpose()
+    .unwrap_or_else(|error| panic!("Failed to start exporting traces ({:?})", error));
+  let tracing_enabled = exporter.is_some();
+  if let Err(error) =
+    logging::init_with_exporter(log_level, cli_matches.is_present("log_json"), exporter)
+  {
+    panic!("Failed to initialize logging ({:?})", error);
+  }
   let hostname = cli_matches.value_of("host").unwrap();
   let port_number = cli_matches.value_of("port").unwrap();
   let addr = format!("{}:{}", hostname, port_number).parse()?;
-  let server = EndorserServiceState::new();
+  let scheme = SignatureScheme::from_name(cli_matches.value_of("scheme").unwrap()).unwrap();
+  let signer: Option<Box<dyn Signer>> = None;
+  #[cfg(feature = "pkcs11")]
+  let signer = match cli_matches.value_of("pkcs11_module") {
+    Some(module) => {
+      let slot = cli_matches.value_of("pkcs11_slot").unwrap().parse()?;
+      let pin = cli_matches.value_of("pkcs11_pin").unwrap();
+      let label = cli_matches.value_of("pkcs11_key_label").unwrap();
+      let signer = endorser::pkcs11::Pkcs11Signer::open(module, slot, pin, label)
+        .map_err(|e| format!("Failed to open the key {} in {} ({:?})", label, module, e))?;
+      Some(Box::new(signer) as Box<dyn Signer>)
+    },
+    None => signer,
+  };
+  let storage = cli_matches.value_of("storage_path");
+  let server = match (signer, storage) {
+    (Some(signer), _) => EndorserServiceState::with_signer(signer, storage.map(Path::new))
+      .map_err(|e| {
+        format!(
+          "Failed to restore the endorser from {:?} ({:?})",
+          storage, e
+        )
+      })?,
+    (None, Some(dir)) => EndorserServiceState::with_storage(Path::new(dir), scheme)
+      .map_err(|e| format!("Failed to restore the endorser from {} ({:?})", dir, e))?,
+    (None, None) => EndorserServiceState::with_scheme(scheme),
+  };
+  let server = match cli_matches.value_of("attestation") {
+    Some(name) => {
+      let attester = attester_from_name(name)
+        .map_err(|e| format!("Unsupported attestation {} ({:?})", name, e))?;
+      server
+        .with_attester(attester)
+        .map_err(|e| format!("Failed to attest the endorser ({:?})", e))?
+    },
+    None => server,
+  };
+  let server = match cli_matches
+    .value_of("max_clock_skew")
+    .unwrap()
+    .parse::<u64>()
+  {
+    Ok(ms) => server.with_max_clock_skew(Duration::from_millis(ms)),
+    Err(_) => panic!("Failed to parse the maximum clock skew"),
+  };
+  // the server is reported as serving once it listens, and the endorser call service once the
+  // endorser is active in a view
+  let (health_reporter, health_service) =
+    HealthReporter::new(&[EndorserCallServer::<EndorserServiceState>::NAME]);
+  health_reporter.set_status(SERVER_STATUS, ServingStatus::Serving);
+  let server = server.with_health(health_reporter.clone());
+
+  let reflection_service = if cli_matches.is_present("enable_reflection") {
+    let service = ReflectionService::new(&[
+      ENDORSER_DESCRIPTOR_SET,
+      HEALTH_DESCRIPTOR_SET,
+      REFLECTION_DESCRIPTOR_SET,
+    ])?;
+    Some(ServerReflectionServer::new(service))
+  } else {
+    None
+  };
+
+  let mut builder = Server::builder();
+  if let Some(cert) = cli_matches.value_of("tls_cert") {
+    let key = cli_matches.value_of("tls_key").unwrap();
+    let identity = Identity::from_pem(fs::read(cert)?, fs::read(key)?);
+    let mut tls = ServerTlsConfig::new().identity(identity);
+    if let Some(ca) = cli_matches.value_of("tls_ca") {
+      tls = tls.client_ca_root(Certificate::from_pem(fs::read(ca)?));
+    }
+    builder = builder.tls_config(tls)?;
+  }
+  // a call that carries the coordinator's trace context continues its trace
+  if tracing_enabled {
+    builder = builder.trace_fn(grpc_request_span);
+  }
 
-  let job = tokio::spawn(async move {
-    println!("Endorser host listening on {:?}", addr);
+  let shutdown_timeout = match cli_matches
+    .value_of("shutdown_timeout")
+    .unwrap()
+    .parse::<u64>()
+  {
+    Ok(secs) => Duration::from_secs(secs),
+    Err(_) => panic!("Failed to parse the shutdown timeout"),
+  };
+  let shutdown = Shutdown::default();
+  let stopped = shutdown.started();
+
+  info!(endorser = %server.log_id(), %addr, "starting the endorser");
+  let mut job = tokio::spawn(async move {
+    println!(
+      "Endorser host listening on {:?} ({} digests)",
+      addr,
+      NimbleDigest::algorithm()
+    );
 
-    let _ = Server::builder()
+    let _ = builder
       .add_service(EndorserCallServer::new(server))
-      .serve(addr)
+      .add_service(HealthServer::new(health_service))
+      .add_optional_service(reflection_service)
+      .serve_with_shutdown(addr, stopped)
       .await;
   });
 
-  job.await?;
+  let signal = tokio::select! {
+    res = &mut job => return res.map_err(|e| e.into()),
+    signal = shutdown_signal() => signal,
+  };
+
+  // every change to the endorser's state is durable before it answers, so the requests in flight
+  // are all that a shutdown waits for
+  info!(signal, timeout = ?shutdown_timeout, "shutting down");
+  health_reporter.set_all(false);
+  shutdown.start();
+  match tokio::time::timeout(shutdown_timeout, job).await {
+    Ok(res) => res?,
+    Err(_elapsed) => warn!("requests were still in flight when the shutdown timed out"),
+  }
 
   Ok(())
 }
@@ -0,0 +1,212 @@
+//! Durable storage of an endorser's state, so that an endorser that restarts keeps its identity
+//! and its ledger tails instead of dropping out of the quorum. The signing key is kept in a PEM
+//! file and every change to the state is appended to a log before the endorser signs anything
+//! that depends on it: an endorser that forgot a signed entry could later sign a different entry
+//! at the same height.
+
+use crate::errors::EndorserError;
+use ledger::{signature::PrivateKey, zeroize::Zeroizing};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+  fs::{self, File, OpenOptions},
+  io::{Read, Write},
+  path::{Path, PathBuf},
+};
+
+const KEY_FILE: &str = "endorser_key.pem";
+const LOG_FILE: &str = "endorser_state.log";
+const TMP_FILE: &str = "endorser_state.tmp";
+const CHECKSUM_BYTES: usize = 32;
+
+/// A change to the state of an endorser
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogRecord {
+  /// the number of times the endorser restarted from its storage
+  Incarnation(u64),
+  /// the state of the view ledger after a change
+  View {
+    mode: i32,
+    group_identity: Vec<u8>,
+    tail_metablock: Vec<u8>,
+    prev_metablock: Vec<u8>,
+  },
+  /// the tail of a ledger after a change
+  Ledger {
+    handle: Vec<u8>,
+    metablock: Vec<u8>,
+    block: Vec<u8>,
+    nonces: Vec<u8>,
+  },
+  /// when the endorser's lease expires (ms since epoch), if it has one, after a view change
+  Lease(Option<u64>),
+}
+
+// each record is framed by its length and checksum, so a write torn by a crash is detected
+fn encode_record(record: &LogRecord) -> Vec<u8> {
+  let payload = bincode::serialize(record).unwrap();
+  let mut bytes = (payload.len() as u32).to_le_bytes().to_vec();
+  bytes.extend_from_slice(&Sha256::digest(&payload));
+  bytes.extend_from_slice(&payload);
+  bytes
+}
+
+// decodes records up to the first one that is incomplete or corrupt
+fn decode_records(bytes: &[u8]) -> Vec<LogRecord> {
+  let mut records = Vec::new();
+  let mut pos = 0;
+  while pos + 4 + CHECKSUM_BYTES <= bytes.len() {
+    let len = u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]);
+    let start = pos + 4 + CHECKSUM_BYTES;
+    let end = start + len as usize;
+    if end > bytes.len() {
+      break;
+    }
+    let payload = &bytes[start..end];
+    if Sha256::digest(payload).as_slice() != &bytes[pos + 4..start] {
+      break;
+    }
+    match bincode::deserialize(payload) {
+      Ok(record) => records.push(record),
+      Err(_) => break,
+    }
+    pos = end;
+  }
+  records
+}
+
+/// The files in which an endorser keeps its signing key and the log of its state
+pub struct StateLog {
+  dir: PathBuf,
+  file: File,
+}
+
+impl StateLog {
+  /// Opens the storage in `dir`, creating it if needed, and returns it with the signing key, if
+  /// one is stored, and the records of the log
+  pub fn open(dir: &Path) -> Result<(StateLog, Option<PrivateKey>, Vec<LogRecord>), EndorserError> {
+    if let Err(error) = fs::create_dir_all(dir) {
+      eprintln!(
+        "Failed to create the storage directory {:?} ({:?})",
+        dir, error
+      );
+      return Err(EndorserError::FailedToAccessStorage);
+    }
+
+    let key_path = dir.join(KEY_FILE);
+    let private_key = if key_path.exists() {
+      let res = fs::read(&key_path);
+      if res.is_err() {
+        return Err(EndorserError::FailedToAccessStorage);
+      }
+      let res = PrivateKey::from_pem(&Zeroizing::new(res.unwrap()));
+      if res.is_err() {
+        eprintln!("The key in {:?} is invalid", key_path);
+        return Err(EndorserError::FailedToAccessStorage);
+      }
+      Some(res.unwrap())
+    } else {
+      None
+    };
+
+    let log_path = dir.join(LOG_FILE);
+    let mut bytes = Vec::new();
+    if log_path.exists() {
+      let res = File::open(&log_path).and_then(|mut f| f.read_to_end(&mut bytes));
+      if res.is_err() {
+        return Err(EndorserError::FailedToAccessStorage);
+      }
+    }
+    let records = decode_records(&bytes);
+
+    let res = OpenOptions::new().create(true).append(true).open(&log_path);
+    if res.is_err() {
+      return Err(EndorserError::FailedToAccessStorage);
+    }
+    let log = StateLog {
+      dir: dir.to_path_buf(),
+      file: res.unwrap(),
+    };
+    Ok((log, private_key, records))
+  }
+
+  /// replaces the signing key with the key encoded by `pem` and returns once it is on disk
+  pub fn store_key(&self, pem: &[u8]) -> Result<(), EndorserError> {
+    write_durably(&self.dir, &self.dir.join(KEY_FILE), pem)
+  }
+
+  /// appends `records` to the log and returns once they are on disk
+  pub fn append(&mut self, records: &[LogRecord]) -> Result<(), EndorserError> {
+    let bytes = records.iter().flat_map(encode_record).collect::<Vec<u8>>();
+    let res = self
+      .file
+      .write_all(&bytes)
+      .and_then(|_| self.file.sync_data());
+    if let Err(error) = res {
+      eprintln!("Failed to append to the endorser's log ({:?})", error);
+      return Err(EndorserError::FailedToAccessStorage);
+    }
+    Ok(())
+  }
+
+  /// replaces the log with `records`, which describe the whole state, dropping superseded records
+  pub fn compact(&mut self, records: &[LogRecord]) -> Result<(), EndorserError> {
+    let bytes = records.iter().flat_map(encode_record).collect::<Vec<u8>>();
+    let log_path = self.dir.join(LOG_FILE);
+    write_durably(&self.dir, &log_path, &bytes)?;
+
+    let res = OpenOptions::new().append(true).open(&log_path);
+    if res.is_err() {
+      return Err(EndorserError::FailedToAccessStorage);
+    }
+    self.file = res.unwrap();
+    Ok(())
+  }
+}
+
+// writes a file through a temporary one, so the file is either old or new after a crash
+fn write_durably(dir: &Path, path: &Path, bytes: &[u8]) -> Result<(), EndorserError> {
+  let tmp_path = dir.join(TMP_FILE);
+  let mut options = OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+  // the key file holds the endorser's signing key, so only its owner may read it
+  #[cfg(unix)]
+  std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+  let res = options
+    .open(&tmp_path)
+    .and_then(|mut f| f.write_all(bytes).and_then(|_| f.sync_all()))
+    .and_then(|_| fs::rename(&tmp_path, path))
+    .and_then(|_| File::open(dir))
+    .and_then(|d| d.sync_all());
+  if let Err(error) = res {
+    eprintln!("Failed to write {:?} ({:?})", path, error);
+    return Err(EndorserError::FailedToAccessStorage);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_torn_records_are_dropped() {
+    let records = vec![
+      LogRecord::Incarnation(1),
+      LogRecord::Ledger {
+        handle: vec![1; 32],
+        metablock: vec![2; 72],
+        block: vec![3],
+        nonces: Vec::new(),
+      },
+    ];
+    let mut bytes = records.iter().flat_map(encode_record).collect::<Vec<u8>>();
+    assert_eq!(decode_records(&bytes), records);
+
+    // a record cut short by a crash, or corrupted, ends the log
+    let len = bytes.len();
+    assert_eq!(decode_records(&bytes[..len - 1]), records[..1].to_vec());
+    bytes[len - 1] ^= 1;
+    assert_eq!(decode_records(&bytes), records[..1].to_vec());
+  }
+}
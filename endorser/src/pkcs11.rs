@@ -0,0 +1,556 @@
+//! Keys that live in a hardware security module, which the endorser reaches through the module's
+//! PKCS#11 library. The key is a P-256 key pair on the module's token, found by its label, and
+//! the module signs the digests the endorser signs with raw ECDSA, whose output is already the
+//! (r, s) encoding of a signature. A key generated by a rotation is labelled with the suffix
+//! `.next` until it replaces the key under the label, so an endorser that stops in between finds
+//! it again.
+
+use crate::{errors::EndorserError, signer::Signer};
+use ledger::signature::{PublicKey, PublicKeyTrait, Signature, SignatureScheme, SignatureTrait};
+use std::{
+  ffi::CString,
+  os::raw::{c_ulong, c_void},
+  ptr,
+  sync::{Arc, Mutex},
+};
+
+type CkUlong = c_ulong;
+type CkRv = CkUlong;
+type CkSessionHandle = CkUlong;
+type CkObjectHandle = CkUlong;
+
+const CKR_OK: CkRv = 0;
+const CKR_USER_ALREADY_LOGGED_IN: CkRv = 0x100;
+const CKR_CRYPTOKI_ALREADY_INITIALIZED: CkRv = 0x191;
+
+const CKF_OS_LOCKING_OK: CkUlong = 0x2;
+const CKF_RW_SESSION: CkUlong = 0x2;
+const CKF_SERIAL_SESSION: CkUlong = 0x4;
+const CKU_USER: CkUlong = 1;
+
+const CKA_CLASS: CkUlong = 0x0;
+const CKA_TOKEN: CkUlong = 0x1;
+const CKA_PRIVATE: CkUlong = 0x2;
+const CKA_LABEL: CkUlong = 0x3;
+const CKA_KEY_TYPE: CkUlong = 0x100;
+const CKA_SENSITIVE: CkUlong = 0x103;
+const CKA_SIGN: CkUlong = 0x108;
+const CKA_VERIFY: CkUlong = 0x10a;
+const CKA_EXTRACTABLE: CkUlong = 0x162;
+const CKA_EC_PARAMS: CkUlong = 0x180;
+const CKA_EC_POINT: CkUlong = 0x181;
+
+const CKO_PUBLIC_KEY: CkUlong = 2;
+const CKO_PRIVATE_KEY: CkUlong = 3;
+const CKK_EC: CkUlong = 3;
+const CKM_EC_KEY_PAIR_GEN: CkUlong = 0x1040;
+const CKM_ECDSA: CkUlong = 0x1041;
+
+// the DER encoding of the OID of P-256, which names the curve of a key pair
+const P256_PARAMS: [u8; 10] = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+// the length of an uncompressed P-256 point and of an ECDSA signature over P-256
+const P256_POINT_BYTES: usize = 65;
+const P256_SIGNATURE_BYTES: usize = 64;
+const NEXT_SUFFIX: &str = ".next";
+
+#[repr(C)]
+struct CkAttribute {
+  kind: CkUlong,
+  value: *mut c_void,
+  len: CkUlong,
+}
+
+#[repr(C)]
+struct CkMechanism {
+  mechanism: CkUlong,
+  parameter: *mut c_void,
+  len: CkUlong,
+}
+
+#[repr(C)]
+struct CkInitializeArgs {
+  create_mutex: *mut c_void,
+  destroy_mutex: *mut c_void,
+  lock_mutex: *mut c_void,
+  unlock_mutex: *mut c_void,
+  flags: CkUlong,
+  reserved: *mut c_void,
+}
+
+type Unused = Option<unsafe extern "C" fn()>;
+
+// the functions of a PKCS#11 library, in the order of the standard; the ones the endorser does
+// not call are left untyped
+#[repr(C)]
+struct CkFunctionList {
+  version: [u8; 2],
+  initialize: unsafe extern "C" fn(*mut c_void) -> CkRv,
+  finalize: Unused,
+  get_info: Unused,
+  get_function_list: Unused,
+  get_slot_list: Unused,
+  get_slot_info: Unused,
+  get_token_info: Unused,
+  get_mechanism_list: Unused,
+  get_mechanism_info: Unused,
+  init_token: Unused,
+  init_pin: Unused,
+  set_pin: Unused,
+  open_session:
+    unsafe extern "C" fn(CkUlong, CkUlong, *mut c_void, *mut c_void, *mut CkSessionHandle) -> CkRv,
+  close_session: Unused,
+  close_all_sessions: Unused,
+  get_session_info: Unused,
+  get_operation_state: Unused,
+  set_operation_state: Unused,
+  login: unsafe extern "C" fn(CkSessionHandle, CkUlong, *const u8, CkUlong) -> CkRv,
+  logout: Unused,
+  create_object: Unused,
+  copy_object: Unused,
+  destroy_object: unsafe extern "C" fn(CkSessionHandle, CkObjectHandle) -> CkRv,
+  get_object_size: Unused,
+  get_attribute_value:
+    unsafe extern "C" fn(CkSessionHandle, CkObjectHandle, *mut CkAttribute, CkUlong) -> CkRv,
+  set_attribute_value:
+    unsafe extern "C" fn(CkSessionHandle, CkObjectHandle, *mut CkAttribute, CkUlong) -> CkRv,
+  find_objects_init: unsafe extern "C" fn(CkSessionHandle, *mut CkAttribute, CkUlong) -> CkRv,
+  find_objects:
+    unsafe extern "C" fn(CkSessionHandle, *mut CkObjectHandle, CkUlong, *mut CkUlong) -> CkRv,
+  find_objects_final: unsafe extern "C" fn(CkSessionHandle) -> CkRv,
+  encrypt_init: Unused,
+  encrypt: Unused,
+  encrypt_update: Unused,
+  encrypt_final: Unused,
+  decrypt_init: Unused,
+  decrypt: Unused,
+  decrypt_update: Unused,
+  decrypt_final: Unused,
+  digest_init: Unused,
+  digest: Unused,
+  digest_update: Unused,
+  digest_key: Unused,
+  digest_final: Unused,
+  sign_init: unsafe extern "C" fn(CkSessionHandle, *mut CkMechanism, CkObjectHandle) -> CkRv,
+  sign: unsafe extern "C" fn(CkSessionHandle, *const u8, CkUlong, *mut u8, *mut CkUlong) -> CkRv,
+  sign_update: Unused,
+  sign_final: Unused,
+  sign_recover_init: Unused,
+  sign_recover: Unused,
+  verify_init: Unused,
+  verify: Unused,
+  verify_update: Unused,
+  verify_final: Unused,
+  verify_recover_init: Unused,
+  verify_recover: Unused,
+  digest_encrypt_update: Unused,
+  decrypt_digest_update: Unused,
+  sign_encrypt_update: Unused,
+  decrypt_verify_update: Unused,
+  generate_key: Unused,
+  generate_key_pair: unsafe extern "C" fn(
+    CkSessionHandle,
+    *mut CkMechanism,
+    *mut CkAttribute,
+    CkUlong,
+    *mut CkAttribute,
+    CkUlong,
+    *mut CkObjectHandle,
+    *mut CkObjectHandle,
+  ) -> CkRv,
+}
+
+// an attribute that points into `value`, which must outlive the call it is passed to
+fn attribute(kind: CkUlong, value: &[u8]) -> CkAttribute {
+  CkAttribute {
+    kind,
+    value: value.as_ptr() as *mut c_void,
+    len: value.len() as CkUlong,
+  }
+}
+
+fn ulong_bytes(value: CkUlong) -> Vec<u8> {
+  value.to_ne_bytes().to_vec()
+}
+
+// returns an error naming `call` unless the library returned CKR_OK
+fn check(call: &str, rv: CkRv) -> Result<(), EndorserError> {
+  if rv != CKR_OK {
+    eprintln!("PKCS#11 {} failed ({:#x})", call, rv);
+    return Err(EndorserError::FailedToAccessKey);
+  }
+  Ok(())
+}
+
+/// A session with a token of a PKCS#11 library, shared by the keys on the token
+struct Session {
+  functions: &'static CkFunctionList,
+  // the session handle, which only one thread uses at a time
+  handle: Mutex<CkSessionHandle>,
+}
+
+// the library is initialized to lock with the operating system's primitives and every use of the
+// session is serialized, so the session can be shared across threads
+unsafe impl Send for Session {}
+unsafe impl Sync for Session {}
+
+impl Session {
+  // loads the library at `module`, opens a session with the token in `slot`, and logs in
+  fn open(module: &str, slot: u64, pin: &str) -> Result<Session, EndorserError> {
+    let functions = unsafe {
+      let path = CString::new(module).map_err(|_| EndorserError::FailedToAccessKey)?;
+      let library = libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+      if library.is_null() {
+        eprintln!("Failed to load the PKCS#11 library {}", module);
+        return Err(EndorserError::FailedToAccessKey);
+      }
+      // the library stays loaded for as long as the endorser runs
+      let symbol = libc::dlsym(library, b"C_GetFunctionList\0".as_ptr() as *const _);
+      if symbol.is_null() {
+        eprintln!("{} is not a PKCS#11 library", module);
+        return Err(EndorserError::FailedToAccessKey);
+      }
+      let get_function_list: unsafe extern "C" fn(*mut *const CkFunctionList) -> CkRv =
+        std::mem::transmute(symbol);
+      let mut functions: *const CkFunctionList = ptr::null();
+      check("C_GetFunctionList", get_function_list(&mut functions))?;
+      &*functions
+    };
+
+    let mut args = CkInitializeArgs {
+      create_mutex: ptr::null_mut(),
+      destroy_mutex: ptr::null_mut(),
+      lock_mutex: ptr::null_mut(),
+      unlock_mutex: ptr::null_mut(),
+      flags: CKF_OS_LOCKING_OK,
+      reserved: ptr::null_mut(),
+    };
+    let rv = unsafe { (functions.initialize)(&mut args as *mut _ as *mut c_void) };
+    if rv != CKR_CRYPTOKI_ALREADY_INITIALIZED {
+      check("C_Initialize", rv)?;
+    }
+
+    let mut handle: CkSessionHandle = 0;
+    check("C_OpenSession", unsafe {
+      (functions.open_session)(
+        slot as CkUlong,
+        CKF_SERIAL_SESSION | CKF_RW_SESSION,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        &mut handle,
+      )
+    })?;
+    let rv = unsafe { (functions.login)(handle, CKU_USER, pin.as_ptr(), pin.len() as CkUlong) };
+    if rv != CKR_USER_ALREADY_LOGGED_IN {
+      check("C_Login", rv)?;
+    }
+
+    Ok(Session {
+      functions,
+      handle: Mutex::new(handle),
+    })
+  }
+
+  fn lock(&self) -> Result<std::sync::MutexGuard<'_, CkSessionHandle>, EndorserError> {
+    self
+      .handle
+      .lock()
+      .map_err(|_| EndorserError::FailedToAccessKey)
+  }
+
+  // returns the objects of `class` labelled `label`
+  fn find(&self, class: CkUlong, label: &str) -> Result<Vec<CkObjectHandle>, EndorserError> {
+    let class = ulong_bytes(class);
+    let mut template = [
+      attribute(CKA_CLASS, &class),
+      attribute(CKA_LABEL, label.as_bytes()),
+    ];
+    let session = self.lock()?;
+    let f = self.functions;
+    check("C_FindObjectsInit", unsafe {
+      (f.find_objects_init)(*session, template.as_mut_ptr(), template.len() as CkUlong)
+    })?;
+    let mut objects = Vec::new();
+    let res = loop {
+      let mut batch = [0 as CkObjectHandle; 16];
+      let mut count: CkUlong = 0;
+      let rv = unsafe {
+        (f.find_objects)(
+          *session,
+          batch.as_mut_ptr(),
+          batch.len() as CkUlong,
+          &mut count,
+        )
+      };
+      if rv != CKR_OK || count == 0 {
+        break check("C_FindObjects", rv);
+      }
+      objects.extend_from_slice(&batch[..count as usize]);
+    };
+    check("C_FindObjectsFinal", unsafe {
+      (f.find_objects_final)(*session)
+    })?;
+    res.map(|_| objects)
+  }
+
+  // returns the value of the attribute `kind` of `object`
+  fn get_attribute(&self, object: CkObjectHandle, kind: CkUlong) -> Result<Vec<u8>, EndorserError> {
+    let session = self.lock()?;
+    let f = self.functions;
+    let mut template = [CkAttribute {
+      kind,
+      value: ptr::null_mut(),
+      len: 0,
+    }];
+    check("C_GetAttributeValue", unsafe {
+      (f.get_attribute_value)(*session, object, template.as_mut_ptr(), 1)
+    })?;
+    let mut value = vec![0u8; template[0].len as usize];
+    template[0].value = value.as_mut_ptr() as *mut c_void;
+    check("C_GetAttributeValue", unsafe {
+      (f.get_attribute_value)(*session, object, template.as_mut_ptr(), 1)
+    })?;
+    value.truncate(template[0].len as usize);
+    Ok(value)
+  }
+
+  fn set_label(&self, object: CkObjectHandle, label: &str) -> Result<(), EndorserError> {
+    let session = self.lock()?;
+    let mut template = [attribute(CKA_LABEL, label.as_bytes())];
+    check("C_SetAttributeValue", unsafe {
+      (self.functions.set_attribute_value)(*session, object, template.as_mut_ptr(), 1)
+    })
+  }
+
+  fn destroy(&self, object: CkObjectHandle) -> Result<(), EndorserError> {
+    let session = self.lock()?;
+    check("C_DestroyObject", unsafe {
+      (self.functions.destroy_object)(*session, object)
+    })
+  }
+
+  // generates a P-256 key pair on the token and returns its public and private key
+  fn generate(&self, label: &str) -> Result<(CkObjectHandle, CkObjectHandle), EndorserError> {
+    let yes = [1u8];
+    let no = [0u8];
+    let key_type = ulong_bytes(CKK_EC);
+    let mut public_template = [
+      attribute(CKA_TOKEN, &yes),
+      attribute(CKA_LABEL, label.as_bytes()),
+      attribute(CKA_KEY_TYPE, &key_type),
+      attribute(CKA_VERIFY, &yes),
+      attribute(CKA_EC_PARAMS, &P256_PARAMS),
+    ];
+    let mut private_template = [
+      attribute(CKA_TOKEN, &yes),
+      attribute(CKA_PRIVATE, &yes),
+      attribute(CKA_LABEL, label.as_bytes()),
+      attribute(CKA_KEY_TYPE, &key_type),
+      attribute(CKA_SIGN, &yes),
+      attribute(CKA_SENSITIVE, &yes),
+      attribute(CKA_EXTRACTABLE, &no),
+    ];
+    let mut mechanism = CkMechanism {
+      mechanism: CKM_EC_KEY_PAIR_GEN,
+      parameter: ptr::null_mut(),
+      len: 0,
+    };
+    let (mut public_key, mut private_key) = (0, 0);
+    let session = self.lock()?;
+    check("C_GenerateKeyPair", unsafe {
+      (self.functions.generate_key_pair)(
+        *session,
+        &mut mechanism,
+        public_template.as_mut_ptr(),
+        public_template.len() as CkUlong,
+        private_template.as_mut_ptr(),
+        private_template.len() as CkUlong,
+        &mut public_key,
+        &mut private_key,
+      )
+    })?;
+    Ok((public_key, private_key))
+  }
+
+  // signs `message` as it is, without hashing it first, with ECDSA
+  fn sign(&self, key: CkObjectHandle, message: &[u8]) -> Result<Vec<u8>, EndorserError> {
+    let session = self.lock()?;
+    let f = self.functions;
+    let mut mechanism = CkMechanism {
+      mechanism: CKM_ECDSA,
+      parameter: ptr::null_mut(),
+      len: 0,
+    };
+    let rv = unsafe { (f.sign_init)(*session, &mut mechanism, key) };
+    if rv != CKR_OK {
+      eprintln!("PKCS#11 C_SignInit failed ({:#x})", rv);
+      return Err(EndorserError::FailedToSign);
+    }
+    let mut signature = vec![0u8; P256_SIGNATURE_BYTES];
+    let mut len = signature.len() as CkUlong;
+    let rv = unsafe {
+      (f.sign)(
+        *session,
+        message.as_ptr(),
+        message.len() as CkUlong,
+        signature.as_mut_ptr(),
+        &mut len,
+      )
+    };
+    if rv != CKR_OK || len as usize != P256_SIGNATURE_BYTES {
+      eprintln!("PKCS#11 C_Sign failed ({:#x})", rv);
+      return Err(EndorserError::FailedToSign);
+    }
+    Ok(signature)
+  }
+}
+
+// converts the value of CKA_EC_POINT, an uncompressed point that most modules wrap in a DER
+// octet string, to a public key
+fn public_key_from_ec_point(value: &[u8]) -> Result<PublicKey, EndorserError> {
+  let point = if value.len() == P256_POINT_BYTES + 2 && value[0] == 0x04 {
+    &value[2..]
+  } else {
+    value
+  };
+  if point.len() != P256_POINT_BYTES || point[0] != 0x04 {
+    eprintln!("The PKCS#11 key is not an uncompressed P-256 point");
+    return Err(EndorserError::FailedToAccessKey);
+  }
+  let (x, y) = point[1..].split_at(P256_POINT_BYTES / 2);
+  let mut bytes = vec![SignatureScheme::P256.get_id(), 0x02 | (y[y.len() - 1] & 1)];
+  bytes.extend_from_slice(x);
+  PublicKey::from_bytes(&bytes).map_err(|_| EndorserError::FailedToAccessKey)
+}
+
+/// A P-256 key pair on the token of a hardware security module
+pub struct Pkcs11Signer {
+  session: Arc<Session>,
+  /// the label of the endorser's key, under which the key pair is found
+  label: String,
+  /// the label of this key pair, which differs from `label` until the key pair takes over
+  object_label: String,
+  public_object: CkObjectHandle,
+  private_object: CkObjectHandle,
+  public_key: PublicKey,
+}
+
+impl Pkcs11Signer {
+  /// Opens the key labelled `label` on the token in `slot` of the PKCS#11 library at `module`,
+  /// logging in with `pin`. A key generated by a rotation that did not take over yet takes over
+  /// now, and a new key pair is generated if the token holds no key under the label.
+  pub fn open(module: &str, slot: u64, pin: &str, label: &str) -> Result<Self, EndorserError> {
+    let session = Arc::new(Session::open(module, slot, pin)?);
+    let next_label = format!("{}{}", label, NEXT_SUFFIX);
+    let signer = if !session.find(CKO_PRIVATE_KEY, label)?.is_empty() {
+      // a take-over that stopped after relabelling the private key is completed
+      if session.find(CKO_PUBLIC_KEY, label)?.is_empty() {
+        for object in session.find(CKO_PUBLIC_KEY, &next_label)? {
+          session.set_label(object, label)?;
+        }
+      }
+      Pkcs11Signer::find(session, label, label)?
+    } else if !session.find(CKO_PRIVATE_KEY, &next_label)?.is_empty() {
+      let mut signer = Pkcs11Signer::find(session, label, &next_label)?;
+      signer.take_over()?;
+      signer.object_label = label.to_string();
+      signer
+    } else {
+      let (public_object, private_object) = session.generate(label)?;
+      Pkcs11Signer::new(session, label, label, public_object, private_object)?
+    };
+    Ok(signer)
+  }
+
+  fn new(
+    session: Arc<Session>,
+    label: &str,
+    object_label: &str,
+    public_object: CkObjectHandle,
+    private_object: CkObjectHandle,
+  ) -> Result<Self, EndorserError> {
+    let public_key =
+      public_key_from_ec_point(&session.get_attribute(public_object, CKA_EC_POINT)?)?;
+    Ok(Pkcs11Signer {
+      session,
+      label: label.to_string(),
+      object_label: object_label.to_string(),
+      public_object,
+      private_object,
+      public_key,
+    })
+  }
+
+  // opens the one key pair labelled `object_label`
+  fn find(session: Arc<Session>, label: &str, object_label: &str) -> Result<Self, EndorserError> {
+    let private_objects = session.find(CKO_PRIVATE_KEY, object_label)?;
+    let public_objects = session.find(CKO_PUBLIC_KEY, object_label)?;
+    if private_objects.len() != 1 || public_objects.len() != 1 {
+      eprintln!(
+        "The PKCS#11 token holds {} private and {} public keys labelled {}",
+        private_objects.len(),
+        public_objects.len(),
+        object_label
+      );
+      return Err(EndorserError::FailedToAccessKey);
+    }
+    Pkcs11Signer::new(
+      session,
+      label,
+      object_label,
+      public_objects[0],
+      private_objects[0],
+    )
+  }
+
+  // destroys the key pairs labelled `object_label`
+  fn destroy(&self, object_label: &str) -> Result<(), EndorserError> {
+    for class in [CKO_PRIVATE_KEY, CKO_PUBLIC_KEY] {
+      for object in self.session.find(class, object_label)? {
+        self.session.destroy(object)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Signer for Pkcs11Signer {
+  fn get_scheme(&self) -> SignatureScheme {
+    SignatureScheme::P256
+  }
+
+  fn get_public_key(&self) -> PublicKey {
+    self.public_key.clone()
+  }
+
+  fn sign(&self, message: &[u8]) -> Result<Signature, EndorserError> {
+    let signature = self.session.sign(self.private_object, message)?;
+    Signature::from_bytes(&signature).map_err(|_| EndorserError::FailedToSign)
+  }
+
+  /// generates a key pair labelled with the suffix `.next`, replacing one generated by an earlier
+  /// rotation that did not take over
+  fn generate_next(&self) -> Result<Box<dyn Signer>, EndorserError> {
+    let next_label = format!("{}{}", self.label, NEXT_SUFFIX);
+    self.destroy(&next_label)?;
+    let (public_object, private_object) = self.session.generate(&next_label)?;
+    Ok(Box::new(Pkcs11Signer::new(
+      self.session.clone(),
+      &self.label,
+      &next_label,
+      public_object,
+      private_object,
+    )?))
+  }
+
+  /// destroys the key pair under the label and labels this key pair with it, the private key
+  /// first; the old key pair is destroyed first, so the token never holds two key pairs under
+  /// the label
+  fn take_over(&self) -> Result<(), EndorserError> {
+    if self.object_label != self.label {
+      self.destroy(&self.label)?;
+      self.session.set_label(self.private_object, &self.label)?;
+      self.session.set_label(self.public_object, &self.label)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,896 @@
+//! The endorser's gRPC service, which serves the calls of the coordinator from the endorser's
+//! state and reports the endorser's readiness to the health service.
+use crate::{endorser_state::EndorserState, errors::EndorserError, signer::Signer};
+use ledger::{
+  attestation::{attest_public_key, Attester},
+  health::{HealthReporter, ServingStatus},
+  logging::{request_id_from_metadata, short_id},
+  signature::{PublicKey, PublicKeyTrait, SignatureScheme},
+  Block, CustomSerde, MetaBlock, NimbleDigest, Nonces, Receipts, ENDORSER_LOCKED_DETAILS,
+};
+use std::{path::Path, time::Duration};
+use tonic::{transport::NamedService, Code, Request, Response, Status};
+use tracing::{debug, info, instrument, warn};
+
+use ledger::endorser_proto::{
+  endorser_call_server::{EndorserCall, EndorserCallServer},
+  ActivateReq, ActivateResp, AppendBatchReq, AppendBatchResp, AppendReq, AppendResp, EndorserMode,
+  FinalizeStateReq, FinalizeStateResp, GetPublicKeyReq, GetPublicKeyResp, GetRecoveryInfoReq,
+  GetRecoveryInfoResp, InitializeStateReq, InitializeStateResp, LockEndorserReq, LockEndorserResp,
+  NewLedgerReq, NewLedgerResp, ReadLatestReq, ReadLatestResp, ReadStateReq, ReadStateResp,
+  ReadViewTailReq, ReadViewTailResp, RotateKeyReq, RotateKeyResp, UnlockEndorserReq,
+  UnlockEndorserResp,
+};
+
+pub struct EndorserServiceState {
+  state: EndorserState,
+  attester: Option<Box<dyn Attester>>, // attests the endorser's public keys, if the endorser runs in a TEE
+  health: Option<HealthReporter>,      // told whether the endorser serves a view
+}
+
+impl EndorserServiceState {
+  pub fn new() -> Self {
+    EndorserServiceState {
+      state: EndorserState::new(),
+      attester: None,
+      health: None,
+    }
+  }
+
+  pub fn with_scheme(scheme: SignatureScheme) -> Self {
+    EndorserServiceState {
+      state: EndorserState::with_scheme(scheme),
+      attester: None,
+      health: None,
+    }
+  }
+
+  pub fn with_storage(dir: &Path, scheme: SignatureScheme) -> Result<Self, EndorserError> {
+    Ok(EndorserServiceState {
+      state: EndorserState::with_storage(dir, scheme)?,
+      attester: None,
+      health: None,
+    })
+  }
+
+  /// Creates an endorser that signs with `signer`, persisting its state in `dir` if there is one
+  pub fn with_signer(signer: Box<dyn Signer>, dir: Option<&Path>) -> Result<Self, EndorserError> {
+    let state = match dir {
+      Some(dir) => EndorserState::with_storage_and_signer(dir, signer)?,
+      None => EndorserState::with_signer(signer),
+    };
+    Ok(EndorserServiceState {
+      state,
+      attester: None,
+      health: None,
+    })
+  }
+
+  /// Attests the endorser's public keys with `attester`, so that they are returned with the keys
+  pub fn with_attester(mut self, attester: Box<dyn Attester>) -> Result<Self, EndorserError> {
+    self.attester = Some(attester);
+    self.attest(&self.state.get_public_key()?)?;
+    Ok(self)
+  }
+
+  // returns the evidence binding `pk`, which is empty if the endorser is not attested
+  fn attest(&self, pk: &PublicKey) -> Result<Vec<u8>, EndorserError> {
+    match &self.attester {
+      None => Ok(Vec::new()),
+      Some(attester) => {
+        let res = attest_public_key(attester.as_ref(), &pk.to_bytes());
+        if res.is_err() {
+          return Err(EndorserError::FailedToAttest);
+        }
+        Ok(res.unwrap())
+      },
+    }
+  }
+
+  /// Attests to the timestamps proposed for appends only if they are within `max_clock_skew` of
+  /// the endorser's clock
+  pub fn with_max_clock_skew(mut self, max_clock_skew: Duration) -> Self {
+    self.state.set_max_clock_skew(max_clock_skew);
+    self
+  }
+
+  /// Reports the endorser's readiness to `health`, which it updates as the endorser is
+  /// initialized into views and finalized
+  pub fn with_health(mut self, health: HealthReporter) -> Self {
+    self.health = Some(health);
+    self.report_health();
+    self
+  }
+
+  // the endorser call service is ready while the endorser is active in a view
+  fn report_health(&self) {
+    if let Some(health) = &self.health {
+      let status = match self.state.get_mode() {
+        Ok(EndorserMode::Active) => ServingStatus::Serving,
+        _ => ServingStatus::NotServing,
+      };
+      health.set_status(EndorserCallServer::<EndorserServiceState>::NAME, status);
+    }
+  }
+
+  /// the start of the endorser's public key, which names it in logs
+  pub fn log_id(&self) -> String {
+    match self.state.get_public_key() {
+      Ok(pk) => short_id(&pk.to_bytes()),
+      Err(_) => String::new(),
+    }
+  }
+
+  fn process_error(
+    &self,
+    error: EndorserError,
+    handle: Option<&NimbleDigest>,
+    default_msg: impl Into<String>,
+  ) -> Status {
+    warn!(?error, "the request failed");
+    match error {
+      EndorserError::OutOfOrder => {
+        if let Some(h) = handle {
+          let height = self.state.get_height(h).unwrap();
+          Status::with_details(
+            Code::FailedPrecondition,
+            "Out of order",
+            bytes::Bytes::copy_from_slice(&(height as u64).to_le_bytes()),
+          )
+        } else {
+          Status::failed_precondition("View ledger height is out of order")
+        }
+      },
+      EndorserError::LedgerExists => Status::already_exists("Ledger exists"),
+      EndorserError::InvalidLedgerName => Status::not_found("Ledger handle not found"),
+      EndorserError::LedgerHeightOverflow => Status::out_of_range("Ledger height overflow"),
+      EndorserError::InvalidTailHeight => Status::invalid_argument("Invalid ledger height"),
+      EndorserError::InvalidLedgerTailMap => Status::invalid_argument("Invalid ledger tail map"),
+      EndorserError::AlreadyInitialized => {
+        Status::already_exists("Enodrser is already initialized")
+      },
+      EndorserError::NotInitialized => Status::unimplemented("Endorser is not initialized"),
+      EndorserError::AlreadyFinalized => Status::unavailable("Endorser is already finalized"),
+      EndorserError::NotActive => Status::with_details(
+        Code::Unavailable,
+        "Endorser is locked for view change",
+        bytes::Bytes::from_static(ENDORSER_LOCKED_DETAILS),
+      ),
+      EndorserError::FailedToAccessStorage => {
+        Status::unavailable("Endorser failed to persist its state")
+      },
+      EndorserError::FailedToSign => Status::unavailable("Endorser failed to sign"),
+      EndorserError::TimestampOutOfBounds => {
+        Status::out_of_range("Timestamp is too far from the endorser's clock")
+      },
+      EndorserError::LeaseExpired => Status::permission_denied("Endorser's lease has expired"),
+      EndorserError::StaleViewHeight => {
+        Status::invalid_argument("View ledger height is not past the endorser's view")
+      },
+      EndorserError::ViewHeightGap => {
+        Status::failed_precondition("View ledger height skips views after the endorser's view")
+      },
+      EndorserError::ViewTailMismatch => {
+        Status::failed_precondition("View does not extend the endorser's view")
+      },
+      EndorserError::Locked => Status::with_details(
+        Code::FailedPrecondition,
+        "Endorser is locked",
+        bytes::Bytes::from_static(ENDORSER_LOCKED_DETAILS),
+      ),
+      _ => Status::internal(default_msg),
+    }
+  }
+}
+
+// the ID of the coordinator's request that the call serves, if the coordinator sent one
+fn request_id<T>(request: &Request<T>) -> String {
+  request_id_from_metadata(request.metadata()).unwrap_or_default()
+}
+
+impl Default for EndorserServiceState {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[tonic::async_trait]
+impl EndorserCall for EndorserServiceState {
+  async fn get_public_key(
+    &self,
+    _req: Request<GetPublicKeyReq>,
+  ) -> Result<Response<GetPublicKeyResp>, Status> {
+    let res = self
+      .state
+      .get_public_key()
+      .and_then(|pk| Ok((self.attest(&pk)?, pk)));
+
+    match res {
+      Ok((attestation, pk)) => {
+        let reply = GetPublicKeyResp {
+          pk: pk.to_bytes().to_vec(),
+          attestation,
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to read the public key due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  async fn get_recovery_info(
+    &self,
+    _req: Request<GetRecoveryInfoReq>,
+  ) -> Result<Response<GetRecoveryInfoResp>, Status> {
+    let (incarnation, persistent) = self.state.get_incarnation();
+
+    let reply = GetRecoveryInfoResp {
+      incarnation,
+      persistent,
+    };
+
+    Ok(Response::new(reply))
+  }
+
+  #[instrument(
+    name = "LockEndorser",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn lock_endorser(
+    &self,
+    req: Request<LockEndorserReq>,
+  ) -> Result<Response<LockEndorserResp>, Status> {
+    let LockEndorserReq {} = req.into_inner();
+    let res = self.state.lock();
+
+    match res {
+      Ok(()) => {
+        info!("locked the endorser");
+        Ok(Response::new(LockEndorserResp {}))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to lock the endorser due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "UnlockEndorser",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn unlock_endorser(
+    &self,
+    req: Request<UnlockEndorserReq>,
+  ) -> Result<Response<UnlockEndorserResp>, Status> {
+    let UnlockEndorserReq {} = req.into_inner();
+    let res = self.state.unlock();
+
+    match res {
+      Ok(()) => {
+        info!("unlocked the endorser");
+        Ok(Response::new(UnlockEndorserResp {}))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to unlock the endorser due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "NewLedger",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id(),
+      handle = %short_id(&req.get_ref().handle)
+    )
+  )]
+  async fn new_ledger(
+    &self,
+    req: Request<NewLedgerReq>,
+  ) -> Result<Response<NewLedgerResp>, Status> {
+    let NewLedgerReq {
+      handle,
+      block_hash,
+      block,
+    } = req.into_inner();
+    let handle = {
+      let res = NimbleDigest::from_bytes(&handle);
+      if res.is_err() {
+        return Err(Status::invalid_argument("Handle size is invalid"));
+      }
+      res.unwrap()
+    };
+
+    let block_hash = {
+      let res = NimbleDigest::from_bytes(&block_hash);
+      if res.is_err() {
+        return Err(Status::invalid_argument("Block hash size is invalid"));
+      }
+      res.unwrap()
+    };
+
+    let block = {
+      let res = Block::from_bytes(&block);
+      if res.is_err() {
+        return Err(Status::invalid_argument("Block is invalid"));
+      }
+      res.unwrap()
+    };
+
+    let res = self.state.new_ledger(&handle, &block_hash, &block);
+
+    match res {
+      Ok(receipt) => {
+        debug!("signed the genesis of the ledger");
+        let reply = NewLedgerResp {
+          receipt: receipt.to_bytes().to_vec(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to create a new ledger due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "Append",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id(),
+      handle = %short_id(&req.get_ref().handle),
+      height = req.get_ref().expected_height
+    )
+  )]
+  async fn append(&self, req: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+    let AppendReq {
+      handle,
+      block_hash,
+      expected_height,
+      block,
+      nonces,
+      timestamp,
+    } = req.into_inner();
+
+    let handle_instance = NimbleDigest::from_bytes(&handle);
+    let block_hash_instance = NimbleDigest::from_bytes(&block_hash);
+    let block_instance = Block::from_bytes(&block);
+    let nonces_instance = Nonces::from_bytes(&nonces);
+
+    if handle_instance.is_err()
+      || block_hash_instance.is_err()
+      || block_instance.is_err()
+      || nonces_instance.is_err()
+    {
+      return Err(Status::invalid_argument("Invalid input sizes"));
+    }
+
+    if expected_height == 0 {
+      return Err(Status::invalid_argument("Invalid expected height"));
+    }
+
+    let handle = handle_instance.unwrap();
+    let block_hash = block_hash_instance.unwrap();
+    let block = block_instance.unwrap();
+    let nonces = nonces_instance.unwrap();
+
+    let res = self.state.append(
+      &handle,
+      &block_hash,
+      expected_height as usize,
+      &block,
+      &nonces,
+      (timestamp != 0).then_some(timestamp),
+    );
+
+    match res {
+      Ok(receipt) => {
+        debug!("signed the ledger's new tail");
+        let reply = AppendResp {
+          receipt: receipt.to_bytes().to_vec(),
+        };
+        Ok(Response::new(reply))
+      },
+
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          Some(&handle),
+          "Failed to append to a ledger due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "AppendBatch",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id(),
+      entries = req.get_ref().entries.len()
+    )
+  )]
+  async fn append_batch(
+    &self,
+    req: Request<AppendBatchReq>,
+  ) -> Result<Response<AppendBatchResp>, Status> {
+    let AppendBatchReq { entries } = req.into_inner();
+    if entries.is_empty() {
+      return Err(Status::invalid_argument("Empty batch"));
+    }
+
+    let mut batch = Vec::with_capacity(entries.len());
+    for entry in entries {
+      let handle_instance = NimbleDigest::from_bytes(&entry.handle);
+      let block_hash_instance = NimbleDigest::from_bytes(&entry.block_hash);
+      let block_instance = Block::from_bytes(&entry.block);
+      let nonces_instance = Nonces::from_bytes(&entry.nonces);
+
+      if handle_instance.is_err()
+        || block_hash_instance.is_err()
+        || block_instance.is_err()
+        || nonces_instance.is_err()
+      {
+        return Err(Status::invalid_argument("Invalid input sizes"));
+      }
+
+      if entry.expected_height == 0 {
+        return Err(Status::invalid_argument("Invalid expected height"));
+      }
+
+      batch.push((
+        handle_instance.unwrap(),
+        block_hash_instance.unwrap(),
+        entry.expected_height as usize,
+        block_instance.unwrap(),
+        nonces_instance.unwrap(),
+        (entry.timestamp != 0).then_some(entry.timestamp),
+      ));
+    }
+
+    match self.state.append_batch(&batch) {
+      Ok(receipts) => {
+        let reply = AppendBatchResp {
+          receipts: receipts
+            .iter()
+            .map(|receipt| receipt.to_bytes().to_vec())
+            .collect(),
+        };
+        Ok(Response::new(reply))
+      },
+
+      // only the first entry's error is returned, the others end the batch
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          Some(&batch[0].0),
+          "Failed to append a batch to ledgers due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "ReadLatest",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      endorser = %self.log_id(),
+      handle = %short_id(&request.get_ref().handle)
+    )
+  )]
+  async fn read_latest(
+    &self,
+    request: Request<ReadLatestReq>,
+  ) -> Result<Response<ReadLatestResp>, Status> {
+    let ReadLatestReq { handle, nonce } = request.into_inner();
+    let handle = {
+      let res = NimbleDigest::from_bytes(&handle);
+      if res.is_err() {
+        return Err(Status::invalid_argument("Invalid handle size"));
+      }
+      res.unwrap()
+    };
+    let res = self.state.read_latest(&handle, &nonce);
+
+    match res {
+      Ok((receipt, block, nonces)) => {
+        let reply = ReadLatestResp {
+          receipt: receipt.to_bytes().to_vec(),
+          block: block.to_bytes().to_vec(),
+          nonces: nonces.to_bytes().to_vec(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          Some(&handle),
+          "Failed to read a ledger due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "FinalizeState",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn finalize_state(
+    &self,
+    req: Request<FinalizeStateReq>,
+  ) -> Result<Response<FinalizeStateResp>, Status> {
+    let FinalizeStateReq {
+      block_hash,
+      expected_height,
+      prev_view_hash,
+    } = req.into_inner();
+
+    let block_hash_instance = NimbleDigest::from_bytes(&block_hash);
+
+    if block_hash_instance.is_err() {
+      return Err(Status::invalid_argument("Invalid input sizes"));
+    }
+
+    let prev_view_hash = if prev_view_hash.is_empty() {
+      None
+    } else {
+      match NimbleDigest::from_bytes(&prev_view_hash) {
+        Ok(hash) => Some(hash),
+        Err(_) => return Err(Status::invalid_argument("Invalid input sizes")),
+      }
+    };
+
+    if expected_height == 0 {
+      return Err(Status::invalid_argument("Invalid expected height"));
+    }
+
+    let res = self.state.finalize_state(
+      &block_hash_instance.unwrap(),
+      expected_height as usize,
+      prev_view_hash.as_ref(),
+    );
+
+    self.report_health();
+    match res {
+      Ok((receipt, ledger_tail_map)) => {
+        let reply = FinalizeStateResp {
+          receipt: receipt.to_bytes().to_vec(),
+          ledger_tail_map,
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to finalize the endorser due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "InitializeState",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn initialize_state(
+    &self,
+    req: Request<InitializeStateReq>,
+  ) -> Result<Response<InitializeStateResp>, Status> {
+    let InitializeStateReq {
+      group_identity,
+      ledger_tail_map,
+      view_tail_metablock,
+      block_hash,
+      expected_height,
+    } = req.into_inner();
+    let group_identity_instance = NimbleDigest::from_bytes(&group_identity);
+    let view_tail_metablock_instance = MetaBlock::from_bytes(&view_tail_metablock);
+    let block_hash_instance = NimbleDigest::from_bytes(&block_hash);
+
+    if group_identity_instance.is_err()
+      || view_tail_metablock_instance.is_err()
+      || block_hash_instance.is_err()
+    {
+      return Err(Status::invalid_argument("Invalid input sizes"));
+    }
+
+    if expected_height == 0 {
+      return Err(Status::invalid_argument("Invalid expected height"));
+    }
+
+    let group_identity_rs = group_identity_instance.unwrap();
+    let view_tail_metablock_rs = view_tail_metablock_instance.unwrap();
+    let block_hash_rs = block_hash_instance.unwrap();
+    let res = self.state.initialize_state(
+      &group_identity_rs,
+      &ledger_tail_map,
+      &view_tail_metablock_rs,
+      &block_hash_rs,
+      expected_height as usize,
+    );
+
+    self.report_health();
+    match res {
+      Ok(receipt) => {
+        let reply = InitializeStateResp {
+          receipt: receipt.to_bytes().to_vec(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to initialize an endorser due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "ReadState",
+    skip_all,
+    fields(
+      request_id = %request_id(&_req),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn read_state(
+    &self,
+    _req: Request<ReadStateReq>,
+  ) -> Result<Response<ReadStateResp>, Status> {
+    let res = self.state.read_state();
+
+    match res {
+      Ok((receipt, endorser_mode, ledger_tail_map)) => {
+        let reply = ReadStateResp {
+          receipt: receipt.to_bytes().to_vec(),
+          mode: endorser_mode as i32,
+          ledger_tail_map,
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to finalize the endorser due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "ReadViewTail",
+    skip_all,
+    fields(
+      request_id = %request_id(&request),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn read_view_tail(
+    &self,
+    request: Request<ReadViewTailReq>,
+  ) -> Result<Response<ReadViewTailResp>, Status> {
+    let ReadViewTailReq { nonce } = request.into_inner();
+    let res = self.state.read_view_tail(&nonce);
+
+    match res {
+      Ok(receipt) => {
+        let reply = ReadViewTailResp {
+          receipt: receipt.to_bytes().to_vec(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to read the view ledger tail due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "Activate",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn activate(&self, req: Request<ActivateReq>) -> Result<Response<ActivateResp>, Status> {
+    let ActivateReq {
+      old_config,
+      new_config,
+      ledger_tail_maps,
+      ledger_chunks,
+      receipts,
+    } = req.into_inner();
+    let receipts_rs = {
+      let res = Receipts::from_bytes(&receipts);
+      if res.is_err() {
+        return Err(Status::invalid_argument("Receipts are invalid"));
+      }
+      res.unwrap()
+    };
+    let res = self.state.activate(
+      &old_config,
+      &new_config,
+      &ledger_tail_maps,
+      &ledger_chunks,
+      &receipts_rs,
+    );
+
+    self.report_health();
+    match res {
+      Ok(()) => {
+        let reply = ActivateResp {};
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to verify the view change due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
+  #[instrument(
+    name = "RotateKey",
+    skip_all,
+    fields(
+      request_id = %request_id(&req),
+      endorser = %self.log_id()
+    )
+  )]
+  async fn rotate_key(
+    &self,
+    req: Request<RotateKeyReq>,
+  ) -> Result<Response<RotateKeyResp>, Status> {
+    let RotateKeyReq {} = req.into_inner();
+    let res = self
+      .state
+      .rotate_key()
+      .and_then(|(pk, handover)| Ok((self.attest(&pk)?, pk, handover)));
+
+    match res {
+      Ok((attestation, pk, handover)) => {
+        info!(new_key = %short_id(&pk.to_bytes()), "generated the next key");
+        let reply = RotateKeyResp {
+          pk: pk.to_bytes().to_vec(),
+          attestation,
+          handover: handover.to_bytes(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to rotate the key due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::endorser_proto::LedgerTailMapEntry;
+
+  // requests that panicked the endorser before its handlers checked their inputs; the fuzz target
+  // in `fuzz/` drives the handlers with arbitrary requests
+  #[tokio::test]
+  async fn test_malformed_requests() {
+    let endorser = EndorserServiceState::new();
+    let digest = NimbleDigest::default().to_bytes();
+    let metablock = MetaBlock::default().to_bytes();
+
+    let req = FinalizeStateReq {
+      block_hash: digest.clone(),
+      expected_height: 0,
+      prev_view_hash: vec![],
+    };
+    let status = endorser
+      .finalize_state(Request::new(req))
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let initialize = |ledger_tail_map, expected_height| InitializeStateReq {
+      group_identity: digest.clone(),
+      ledger_tail_map,
+      view_tail_metablock: metablock.clone(),
+      block_hash: digest.clone(),
+      expected_height,
+    };
+    let req = initialize(vec![], 0);
+    let status = endorser
+      .initialize_state(Request::new(req))
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    let entry = LedgerTailMapEntry {
+      handle: vec![1u8; 31],
+      height: 1,
+      metablock: metablock.clone(),
+      block: vec![],
+      nonces: vec![],
+    };
+    let req = initialize(vec![entry], 1);
+    let status = endorser
+      .initialize_state(Request::new(req))
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    // the rejected tail map left the endorser uninitialized
+    let req = initialize(vec![], 1);
+    assert!(endorser.initialize_state(Request::new(req)).await.is_ok());
+
+    let req = ActivateReq {
+      old_config: vec![],
+      new_config: vec![],
+      ledger_tail_maps: vec![],
+      ledger_chunks: vec![],
+      receipts: vec![1u8; 7],
+    };
+    let status = endorser.activate(Request::new(req)).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+  }
+}
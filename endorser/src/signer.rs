@@ -0,0 +1,113 @@
+//! The keys with which an endorser signs. A key either lives in the endorser's memory, and in its
+//! storage if the endorser persists its state, or in a hardware security module that signs on
+//! the endorser's behalf and never reveals the key.
+
+use crate::errors::EndorserError;
+use ledger::{
+  signature::{PrivateKey, PrivateKeyTrait, PublicKey, Signature, SignatureScheme},
+  zeroize::Zeroizing,
+};
+
+/// A signing key and the means to generate the key that succeeds it
+pub trait Signer: Send + Sync {
+  fn get_scheme(&self) -> SignatureScheme;
+
+  fn get_public_key(&self) -> PublicKey;
+
+  fn sign(&self, message: &[u8]) -> Result<Signature, EndorserError>;
+
+  /// generates a key of the same scheme, in the same place, to succeed this one
+  fn generate_next(&self) -> Result<Box<dyn Signer>, EndorserError>;
+
+  /// the PEM encoding of the key, which the endorser stores with its state; None for a key that
+  /// cannot leave the place it lives in
+  fn to_pem(&self) -> Option<Zeroizing<Vec<u8>>> {
+    None
+  }
+
+  /// called once the key replaced its predecessor, which is not used again
+  fn take_over(&self) -> Result<(), EndorserError> {
+    Ok(())
+  }
+}
+
+/// A key held in the endorser's memory
+pub struct SoftwareSigner {
+  private_key: PrivateKey,
+  public_key: PublicKey,
+}
+
+impl SoftwareSigner {
+  pub fn new(private_key: PrivateKey) -> Self {
+    let public_key = private_key.get_public_key().unwrap();
+    SoftwareSigner {
+      private_key,
+      public_key,
+    }
+  }
+
+  pub fn generate(scheme: SignatureScheme) -> Self {
+    SoftwareSigner::new(PrivateKey::generate(scheme))
+  }
+}
+
+impl Signer for SoftwareSigner {
+  fn get_scheme(&self) -> SignatureScheme {
+    self.private_key.get_scheme()
+  }
+
+  fn get_public_key(&self) -> PublicKey {
+    self.public_key.clone()
+  }
+
+  fn sign(&self, message: &[u8]) -> Result<Signature, EndorserError> {
+    let res = self.private_key.sign(message);
+    if res.is_err() {
+      return Err(EndorserError::FailedToSign);
+    }
+    Ok(res.unwrap())
+  }
+
+  fn generate_next(&self) -> Result<Box<dyn Signer>, EndorserError> {
+    Ok(Box::new(SoftwareSigner::generate(self.get_scheme())))
+  }
+
+  fn to_pem(&self) -> Option<Zeroizing<Vec<u8>>> {
+    Some(self.private_key.to_pem())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::signature::{PublicKeyTrait, SignatureTrait};
+
+  #[test]
+  pub fn check_software_signer() {
+    for scheme in SignatureScheme::all() {
+      let signer = SoftwareSigner::generate(scheme);
+      let message = b"a message to sign";
+      let signature = signer.sign(message).unwrap();
+      assert_eq!(signature.get_scheme(), scheme);
+      assert!(signature.verify(&signer.get_public_key(), message).is_ok());
+
+      // the next key has the same scheme but is a different key
+      let next = signer.generate_next().unwrap();
+      assert_eq!(next.get_scheme(), scheme);
+      assert_ne!(
+        next.get_public_key().to_bytes(),
+        signer.get_public_key().to_bytes()
+      );
+      assert!(signature.verify(&next.get_public_key(), message).is_err());
+
+      // the key survives a round trip through its PEM encoding
+      let pem = signer.to_pem().unwrap();
+      let restored = SoftwareSigner::new(PrivateKey::from_pem(&pem).unwrap());
+      assert_eq!(
+        restored.get_public_key().to_bytes(),
+        signer.get_public_key().to_bytes()
+      );
+      assert!(signer.take_over().is_ok());
+    }
+  }
+}
@@ -0,0 +1,709 @@
+//! Deterministic simulation of the coordination protocol.
+//!
+//! A seeded scheduler plays the coordinator against in-process endorsers over a simulated
+//! network that loses, delays and reorders messages, crashes endorsers and replaces them with
+//! view changes. The protocol invariants are checked after every step, and since every choice
+//! comes from the seed, a failing seed replays the exact same schedule
+//! (`NIMBLE_SIM_SEED=<seed> cargo test -p endorser simulation`).
+use crate::{endorser_state::EndorserState, errors::EndorserError};
+use ledger::{
+  compute_aggregated_block_hash, compute_cut_diffs, compute_max_cut,
+  endorser_proto::{LedgerChunkEntry, LedgerTailMap},
+  signature::PublicKeyTrait,
+  Block, CustomSerde, EndorserHostnames, Handle, MetaBlock, NimbleDigest, NimbleHashTrait, Nonce,
+  Nonces, Receipt, Receipts, VerifierState,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+
+const ATTESTATION_STR: &str = "THIS IS A PLACE HOLDER FOR ATTESTATION";
+
+/// number of times the coordinator retries a request before giving up on a quorum
+const MAX_ROUNDS: usize = 8;
+
+/// number of times a view change retries an endorser before the schedule is declared stuck
+const MAX_VIEW_CHANGE_ROUNDS: usize = 1000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimConfig {
+  pub num_endorsers: usize,
+  pub num_ledgers: usize,
+  pub steps: usize,
+  /// probability that a message to an endorser is lost
+  pub drop_rate: f64,
+  /// probability that a write is held back and delivered after later messages
+  pub delay_rate: f64,
+  /// probability that a step crashes an endorser of the current view
+  pub crash_rate: f64,
+  /// probability that a step replaces the endorsers with a view change
+  pub view_change_rate: f64,
+}
+
+impl Default for SimConfig {
+  fn default() -> Self {
+    SimConfig {
+      num_endorsers: 3,
+      num_ledgers: 3,
+      steps: 60,
+      drop_rate: 0.1,
+      delay_rate: 0.1,
+      crash_rate: 0.03,
+      view_change_rate: 0.03,
+    }
+  }
+}
+
+#[derive(Clone)]
+enum Message {
+  NewLedger {
+    handle: Handle,
+    block_hash: NimbleDigest,
+    block: Block,
+  },
+  Append {
+    handle: Handle,
+    block_hash: NimbleDigest,
+    height: usize,
+    block: Block,
+    nonces: Nonces,
+  },
+  ReadLatest {
+    handle: Handle,
+    nonce: Vec<u8>,
+  },
+  GetHeight {
+    handle: Handle,
+  },
+}
+
+enum Response {
+  Receipt(Receipt),
+  Height(usize),
+}
+
+enum Fate {
+  Deliver,
+  Drop,
+  Delay,
+}
+
+struct SimLedger {
+  handle_bytes: Vec<u8>,
+  handle: Handle,
+  /// the entries in the ledger store, which the coordinator writes before the endorsers
+  entries: Vec<(Block, Nonces)>,
+  /// the highest height whose receipts were handed out to a client
+  committed: Option<usize>,
+}
+
+pub struct Simulation {
+  seed: u64,
+  config: SimConfig,
+  rng: StdRng,
+  step: usize,
+  trace: Vec<String>,
+  /// every endorser ever started; a crashed endorser has lost its state
+  endorsers: Vec<Option<EndorserState>>,
+  /// the endorsers of the current view
+  view: Vec<usize>,
+  /// the configurations of the view ledger and their receipts
+  view_ledger: Vec<(Block, Receipts)>,
+  /// writes that the network holds back, delivered later in random order
+  in_flight: Vec<(usize, Message)>,
+  ledgers: Vec<SimLedger>,
+  verifier: VerifierState,
+  /// the metablock each endorser signed for a view, handle and height
+  signed: HashMap<(NimbleDigest, Handle, usize), HashMap<usize, NimbleDigest>>,
+}
+
+impl Simulation {
+  pub fn new(seed: u64, config: SimConfig) -> Self {
+    let mut sim = Simulation {
+      seed,
+      config,
+      rng: StdRng::seed_from_u64(seed),
+      step: 0,
+      trace: Vec::new(),
+      endorsers: Vec::new(),
+      view: Vec::new(),
+      view_ledger: Vec::new(),
+      in_flight: Vec::new(),
+      ledgers: Vec::new(),
+      verifier: VerifierState::new(),
+      signed: HashMap::new(),
+    };
+    sim.view_change();
+    sim
+  }
+
+  /// Runs the schedule to completion and returns its trace
+  pub fn run(mut self) -> Vec<String> {
+    for step in 1..=self.config.steps {
+      self.step = step;
+      let r = self.rng.gen::<f64>();
+      let mut p = self.config.crash_rate;
+      if r < p {
+        self.crash();
+        continue;
+      }
+      p += self.config.view_change_rate;
+      if r < p {
+        self.view_change();
+        continue;
+      }
+      p += 0.1;
+      if self.ledgers.is_empty() || (r < p && self.ledgers.len() < self.config.num_ledgers) {
+        self.create_ledger();
+        continue;
+      }
+      p += 0.1;
+      if r < p {
+        self.deliver_delayed();
+        continue;
+      }
+      p += 0.3;
+      let ledger = self.rng.gen_range(0, self.ledgers.len());
+      if r < p {
+        self.read_latest(ledger);
+      } else {
+        self.append(ledger);
+      }
+    }
+    self.trace
+  }
+
+  fn log(&mut self, event: String) {
+    self.trace.push(format!("{}: {}", self.step, event));
+  }
+
+  fn fail(&self, msg: &str) -> ! {
+    let start = self.trace.len().saturating_sub(20);
+    panic!(
+      "simulation seed {} failed at step {}: {}\n{}",
+      self.seed,
+      self.step,
+      msg,
+      self.trace[start..].join("\n")
+    );
+  }
+
+  fn quorum(&self) -> usize {
+    self.view.len() / 2 + 1
+  }
+
+  fn is_alive(&self, idx: usize) -> bool {
+    self.endorsers[idx].is_some()
+  }
+
+  fn reachable(&mut self) -> bool {
+    !self.rng.gen_bool(self.config.drop_rate)
+  }
+
+  fn fate(&mut self, can_delay: bool) -> Fate {
+    if self.rng.gen_bool(self.config.drop_rate) {
+      Fate::Drop
+    } else if can_delay && self.rng.gen_bool(self.config.delay_rate) {
+      Fate::Delay
+    } else {
+      Fate::Deliver
+    }
+  }
+
+  fn send(&mut self, idx: usize, msg: Message) -> Option<Result<Response, EndorserError>> {
+    let can_delay = matches!(msg, Message::NewLedger { .. } | Message::Append { .. });
+    let res = match self.fate(can_delay) {
+      Fate::Deliver => self.deliver(idx, &msg),
+      Fate::Drop => None,
+      Fate::Delay => {
+        self.in_flight.push((idx, msg));
+        None
+      },
+    };
+    // let a held back message overtake the ones sent after it
+    if !self.in_flight.is_empty() && self.rng.gen_bool(self.config.delay_rate) {
+      self.deliver_delayed();
+    }
+    res
+  }
+
+  fn deliver_delayed(&mut self) {
+    if self.in_flight.is_empty() {
+      return;
+    }
+    let i = self.rng.gen_range(0, self.in_flight.len());
+    let (idx, msg) = self.in_flight.swap_remove(i);
+    // the coordinator stopped waiting for the response long ago
+    let _ = self.deliver(idx, &msg);
+    self.log(format!("delivered a delayed write to endorser {}", idx));
+  }
+
+  fn deliver(&mut self, idx: usize, msg: &Message) -> Option<Result<Response, EndorserError>> {
+    let (handle, res) = {
+      let state = self.endorsers[idx].as_ref()?;
+      match msg {
+        Message::NewLedger {
+          handle,
+          block_hash,
+          block,
+        } => (
+          handle,
+          state
+            .new_ledger(handle, block_hash, block)
+            .map(Response::Receipt),
+        ),
+        Message::Append {
+          handle,
+          block_hash,
+          height,
+          block,
+          nonces,
+        } => (
+          handle,
+          state
+            .append(handle, block_hash, *height, block, nonces, None)
+            .map(Response::Receipt),
+        ),
+        Message::ReadLatest { handle, nonce } => (
+          handle,
+          state
+            .read_latest(handle, nonce)
+            .map(|(receipt, _block, _nonces)| Response::Receipt(receipt)),
+        ),
+        Message::GetHeight { handle } => (handle, state.get_height(handle).map(Response::Height)),
+      }
+    };
+    if let Ok(Response::Receipt(receipt)) = &res {
+      self.observe(idx, handle, receipt);
+    }
+    Some(res)
+  }
+
+  // An honest endorser never signs two different metablocks at the same position of a ledger
+  fn observe(&mut self, idx: usize, handle: &Handle, receipt: &Receipt) {
+    let key = (*receipt.get_view(), *handle, receipt.get_height());
+    let metablock = receipt.get_metablock_hash();
+    let prev = self.signed.entry(key).or_default().insert(idx, metablock);
+    if matches!(prev, Some(prev) if prev != metablock) {
+      self.fail(&format!(
+        "endorser {} signed two metablocks at height {}",
+        idx,
+        receipt.get_height()
+      ));
+    }
+  }
+
+  fn write_message(&self, ledger: usize, height: usize) -> Message {
+    let l = &self.ledgers[ledger];
+    let (block, nonces) = &l.entries[height];
+    if height == 0 {
+      Message::NewLedger {
+        handle: l.handle,
+        block_hash: compute_aggregated_block_hash(
+          &block.hash().to_bytes(),
+          &nonces.hash().to_bytes(),
+        ),
+        block: block.clone(),
+      }
+    } else {
+      Message::Append {
+        handle: l.handle,
+        block_hash: compute_aggregated_block_hash(
+          &block.hash().to_bytes(),
+          &nonces.hash().to_bytes(),
+        ),
+        height,
+        block: block.clone(),
+        nonces: nonces.clone(),
+      }
+    }
+  }
+
+  fn create_ledger(&mut self) {
+    let handle_bytes = self.rng.gen::<[u8; 16]>().to_vec();
+    let block = Block::new(&self.rng.gen::<[u8; 32]>());
+    self.ledgers.push(SimLedger {
+      handle: NimbleDigest::digest(&handle_bytes),
+      handle_bytes,
+      entries: vec![(block, Nonces::new())],
+      committed: None,
+    });
+    self.replicate(self.ledgers.len() - 1, 0);
+  }
+
+  fn append(&mut self, ledger: usize) {
+    let block = Block::new(&self.rng.gen::<[u8; 32]>());
+    let mut nonces = Nonces::new();
+    if self.rng.gen_bool(0.5) {
+      nonces.add(Nonce::new(&self.rng.gen::<[u8; 16]>()).unwrap());
+    }
+    self.ledgers[ledger].entries.push((block, nonces));
+    let height = self.ledgers[ledger].entries.len() - 1;
+    self.replicate(ledger, height);
+  }
+
+  // Sends the entry at `height` to the endorsers of the view and hands out the receipts once a
+  // quorum signed it, the way the coordinator does after writing the entry to the ledger store
+  fn replicate(&mut self, ledger: usize, height: usize) {
+    let msg = self.write_message(ledger, height);
+    let mut receipts = Receipts::new();
+    let mut acked = HashSet::new();
+    for _round in 0..MAX_ROUNDS {
+      let mut view = self.view.clone();
+      view.shuffle(&mut self.rng);
+      for idx in view {
+        if acked.contains(&idx) {
+          continue;
+        }
+        match self.send(idx, msg.clone()) {
+          Some(Ok(Response::Receipt(receipt))) => {
+            receipts.add(&receipt);
+            acked.insert(idx);
+          },
+          Some(Err(EndorserError::OutOfOrder)) | Some(Err(EndorserError::InvalidLedgerName)) => {
+            self.catch_up(idx, ledger, height)
+          },
+          _ => {},
+        }
+      }
+      if acked.len() >= self.quorum() {
+        break;
+      }
+    }
+
+    if acked.len() < self.quorum() {
+      self.log(format!(
+        "write of height {} to ledger {} has {} of {} receipts",
+        height,
+        ledger,
+        acked.len(),
+        self.view.len()
+      ));
+      return;
+    }
+
+    let l = &self.ledgers[ledger];
+    let (block, nonces) = &l.entries[height];
+    let res = if height == 0 {
+      self
+        .verifier
+        .verify_new_ledger(&l.handle_bytes, &block.to_bytes(), &receipts.to_bytes())
+    } else {
+      self.verifier.verify_append(
+        &l.handle_bytes,
+        &block.to_bytes(),
+        &nonces.hash().to_bytes(),
+        height,
+        &receipts.to_bytes(),
+      )
+    };
+    if let Err(e) = res {
+      self.fail(&format!(
+        "receipts of height {} of ledger {} do not verify ({:?})",
+        height, ledger, e
+      ));
+    }
+    let l = &mut self.ledgers[ledger];
+    l.committed = Some(l.committed.map_or(height, |h| h.max(height)));
+    self.log(format!(
+      "committed height {} of ledger {} with {} receipts",
+      height,
+      ledger,
+      acked.len()
+    ));
+  }
+
+  // Brings an endorser that missed earlier writes up to `height - 1` from the ledger store
+  fn catch_up(&mut self, idx: usize, ledger: usize, height: usize) {
+    let handle = self.ledgers[ledger].handle;
+    let current = match self.send(idx, Message::GetHeight { handle }) {
+      Some(Ok(Response::Height(h))) => h,
+      Some(Err(EndorserError::InvalidLedgerName)) => {
+        let msg = self.write_message(ledger, 0);
+        match self.send(idx, msg) {
+          Some(Ok(_)) | Some(Err(EndorserError::LedgerExists)) => 0,
+          _ => return,
+        }
+      },
+      _ => return,
+    };
+    for h in (current + 1)..height {
+      let msg = self.write_message(ledger, h);
+      match self.send(idx, msg) {
+        Some(Ok(_)) | Some(Err(EndorserError::LedgerExists)) => {},
+        _ => return,
+      }
+    }
+  }
+
+  fn read_latest(&mut self, ledger: usize) {
+    let handle = self.ledgers[ledger].handle;
+    let tail = self.ledgers[ledger].entries.len() - 1;
+    let nonce = self.rng.gen::<[u8; 16]>().to_vec();
+    for _round in 0..MAX_ROUNDS {
+      let mut receipts = Receipts::new();
+      let mut lagging = Vec::new();
+      for idx in self.view.clone() {
+        let msg = Message::ReadLatest {
+          handle,
+          nonce: nonce.clone(),
+        };
+        match self.send(idx, msg) {
+          Some(Ok(Response::Receipt(receipt))) => {
+            if receipt.get_height() < tail {
+              lagging.push(idx);
+            }
+            receipts.add(&receipt);
+          },
+          Some(Err(EndorserError::InvalidLedgerName)) => lagging.push(idx),
+          _ => {},
+        }
+      }
+
+      if let Ok(height) = receipts.check_quorum(&self.verifier) {
+        let l = &self.ledgers[ledger];
+        if matches!(l.committed, Some(c) if height < c) {
+          self.fail(&format!(
+            "read of ledger {} returned height {} after height {} was committed",
+            ledger,
+            height,
+            l.committed.unwrap()
+          ));
+        }
+        let (block, nonces) = &l.entries[height];
+        let res = self.verifier.verify_read_latest(
+          &l.handle_bytes,
+          &block.to_bytes(),
+          &nonces.to_bytes(),
+          &nonce,
+          &receipts.to_bytes(),
+        );
+        if let Err(e) = res {
+          self.fail(&format!(
+            "receipts of a read of ledger {} do not verify ({:?})",
+            ledger, e
+          ));
+        }
+        self.log(format!("read height {} of ledger {}", height, ledger));
+        return;
+      }
+
+      for idx in lagging {
+        self.catch_up(idx, ledger, tail + 1);
+      }
+    }
+    self.log(format!("read of ledger {} found no quorum", ledger));
+  }
+
+  fn crash(&mut self) {
+    let alive = self
+      .view
+      .iter()
+      .copied()
+      .filter(|idx| self.is_alive(*idx))
+      .collect::<Vec<usize>>();
+    // a view change needs a quorum of the old endorsers to finalize their state
+    if alive.len() <= self.quorum() {
+      return;
+    }
+    let idx = *alive.choose(&mut self.rng).unwrap();
+    self.endorsers[idx] = None;
+    self.log(format!("crashed endorser {}", idx));
+  }
+
+  fn config_block(&self, endorsers: &[usize]) -> Block {
+    let hostnames = endorsers
+      .iter()
+      .map(|idx| {
+        let pk = self.endorsers[*idx]
+          .as_ref()
+          .unwrap()
+          .get_public_key()
+          .unwrap();
+        (pk.to_bytes(), format!("sim://endorser-{}", idx))
+      })
+      .collect::<EndorserHostnames>();
+    Block::new(&bincode::serialize(&hostnames).unwrap())
+  }
+
+  // Replaces the endorsers of the view with fresh ones the way the coordinator does: finalize
+  // the old endorsers, initialize the new ones with the max cut of the finalized tails, and
+  // prove the view change to them
+  fn view_change(&mut self) {
+    let old = self.view.clone();
+    let new = (0..self.config.num_endorsers)
+      .map(|_| {
+        self.endorsers.push(Some(EndorserState::new()));
+        self.endorsers.len() - 1
+      })
+      .collect::<Vec<usize>>();
+    let config = self.config_block(&new);
+    let view_height = self.view_ledger.len() + 1;
+    let (old_config, view_tail_metablock) = match self.view_ledger.last() {
+      Some((block, view_receipts)) => match view_receipts.get_metablock() {
+        Ok(metablock) => (block.clone(), metablock),
+        Err(e) => self.fail(&format!("view receipts have no metablock ({:?})", e)),
+      },
+      None => (Block::new(&[]), MetaBlock::default()),
+    };
+
+    let mut receipts = Receipts::new();
+    let mut ledger_tail_maps = Vec::new();
+    if !old.is_empty() {
+      let mut finalized = HashSet::new();
+      let mut rounds = 0;
+      while finalized.len() < self.quorum() {
+        rounds += 1;
+        if rounds > MAX_VIEW_CHANGE_ROUNDS {
+          self.fail("could not finalize a quorum of the old endorsers");
+        }
+        for idx in old.iter().copied() {
+          if finalized.contains(&idx) || !self.is_alive(idx) || !self.reachable() {
+            continue;
+          }
+          let res = self.endorsers[idx].as_ref().unwrap().finalize_state(
+            &config.hash(),
+            view_height,
+            Some(&view_tail_metablock.hash()),
+          );
+          match res {
+            Ok((receipt, entries)) => {
+              receipts.add(&receipt);
+              ledger_tail_maps.push(LedgerTailMap { entries });
+              finalized.insert(idx);
+            },
+            Err(e) => self.fail(&format!("endorser {} failed to finalize ({:?})", idx, e)),
+          }
+        }
+      }
+    }
+
+    let max_cut = compute_max_cut(&ledger_tail_maps);
+    if view_height == 1 {
+      self.verifier.set_group_identity(config.hash());
+    }
+    let group_identity = *self.verifier.get_group_identity();
+
+    for idx in new.iter().copied() {
+      self.until_reachable();
+      let res = self.endorsers[idx].as_ref().unwrap().initialize_state(
+        &group_identity,
+        &max_cut,
+        &view_tail_metablock,
+        &config.hash(),
+        view_height,
+      );
+      match res {
+        Ok(receipt) => receipts.add(&receipt),
+        Err(e) => self.fail(&format!("endorser {} failed to initialize ({:?})", idx, e)),
+      }
+    }
+
+    let mut ledger_chunks = Vec::new();
+    for cut_diff in compute_cut_diffs(&ledger_tail_maps) {
+      if cut_diff.low == cut_diff.high {
+        continue;
+      }
+      let l = match self
+        .ledgers
+        .iter()
+        .find(|l| l.handle.to_bytes() == cut_diff.handle)
+      {
+        Some(l) => l,
+        None => self.fail("an endorser holds a ledger that is not in the ledger store"),
+      };
+      let block_hashes = ((cut_diff.low + 1)..=cut_diff.high)
+        .map(|h| {
+          let (block, nonces) = &l.entries[h];
+          compute_aggregated_block_hash(&block.hash().to_bytes(), &nonces.hash().to_bytes())
+            .to_bytes()
+        })
+        .collect();
+      ledger_chunks.push(LedgerChunkEntry {
+        handle: cut_diff.handle.clone(),
+        hash: cut_diff.hash.to_bytes(),
+        height: cut_diff.low as u64,
+        block_hashes,
+      });
+    }
+
+    for idx in new.iter().copied() {
+      self.until_reachable();
+      let res = self.endorsers[idx].as_ref().unwrap().activate(
+        &old_config.to_bytes(),
+        &config.to_bytes(),
+        &ledger_tail_maps,
+        &ledger_chunks,
+        &receipts,
+      );
+      if let Err(e) = res {
+        self.fail(&format!(
+          "endorser {} rejected the view change ({:?})",
+          idx, e
+        ));
+      }
+    }
+
+    if let Err(e) = self.verifier.apply_view_change(
+      &config.to_bytes(),
+      &receipts.to_bytes(),
+      Some(ATTESTATION_STR.as_bytes()),
+    ) {
+      self.fail(&format!("the verifier rejected the view change ({:?})", e));
+    }
+
+    self.view_ledger.push((config, receipts));
+    self.view = new;
+    self.log(format!(
+      "view change {} replaced endorsers {:?} with {:?}",
+      view_height, old, self.view
+    ));
+  }
+
+  fn until_reachable(&mut self) {
+    let mut rounds = 0;
+    while !self.reachable() {
+      rounds += 1;
+      if rounds > MAX_VIEW_CHANGE_ROUNDS {
+        self.fail("an endorser stayed unreachable during a view change");
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  pub fn test_simulated_schedules() {
+    // NIMBLE_SIM_SEED replays a single schedule
+    let seeds = match std::env::var("NIMBLE_SIM_SEED") {
+      Ok(seed) => vec![seed
+        .parse::<u64>()
+        .expect("NIMBLE_SIM_SEED must be a number")],
+      Err(_) => (0..16).collect(),
+    };
+    for seed in seeds {
+      let trace = Simulation::new(seed, SimConfig::default()).run();
+      assert!(!trace.is_empty());
+    }
+
+    let lossy = SimConfig {
+      drop_rate: 0.3,
+      delay_rate: 0.3,
+      crash_rate: 0.1,
+      view_change_rate: 0.1,
+      ..SimConfig::default()
+    };
+    for seed in 0..4 {
+      Simulation::new(seed, lossy).run();
+    }
+  }
+
+  #[test]
+  pub fn test_simulation_is_deterministic() {
+    let first = Simulation::new(42, SimConfig::default()).run();
+    let second = Simulation::new(42, SimConfig::default()).run();
+    assert_eq!(first, second);
+  }
+}
@@ -0,0 +1,226 @@
+//! Client-side envelope encryption of blocks, so that confidential data can be appended to a
+//! ledger without the coordinator, endorsers, or store seeing plaintext. Every block is encrypted
+//! with a fresh data key under AES-256-GCM, and the data key is wrapped (RFC 3394) with a key
+//! encryption key that only clients hold. The handle and height of the block are bound as
+//! additional authenticated data, so a ciphertext moved to another ledger or index fails to
+//! decrypt.
+
+use crate::errors::EndpointError;
+use ledger::NimbleDigest;
+use openssl::{
+  aes::{unwrap_key, wrap_key, AesKey},
+  rand::rand_bytes,
+  symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+
+const ENVELOPE_MAGIC: &[u8] = b"NIMBLE-ENC";
+const ENVELOPE_VERSION: u8 = 1;
+const KEY_BYTES: usize = 32;
+const WRAPPED_KEY_BYTES: usize = KEY_BYTES + 8; // RFC 3394 adds one 64-bit block
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+const MAX_KEY_ID_BYTES: usize = u8::MAX as usize;
+
+/// A key held by clients that wraps the data key of every block they encrypt. The id is stored in
+/// each envelope so that readers can pick the right key after keys are rotated.
+#[derive(Clone)]
+pub struct KeyEncryptionKey {
+  id: Vec<u8>,
+  key: Vec<u8>,
+}
+
+impl std::fmt::Debug for KeyEncryptionKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("KeyEncryptionKey")
+      .field("id", &self.id)
+      .finish()
+  }
+}
+
+impl KeyEncryptionKey {
+  /// creates a key from 32 bytes of key material, named by an id of at most 255 bytes
+  pub fn new(id: &[u8], key: &[u8]) -> Result<Self, EndpointError> {
+    if key.len() != KEY_BYTES || id.len() > MAX_KEY_ID_BYTES {
+      return Err(EndpointError::InvalidEncryptionKey);
+    }
+    Ok(KeyEncryptionKey {
+      id: id.to_vec(),
+      key: key.to_vec(),
+    })
+  }
+
+  /// creates a random key named by `id`
+  pub fn generate(id: &[u8]) -> Result<Self, EndpointError> {
+    let mut key = vec![0u8; KEY_BYTES];
+    if rand_bytes(&mut key).is_err() {
+      return Err(EndpointError::FailedToEncryptBlock);
+    }
+    Self::new(id, &key)
+  }
+
+  pub fn get_id(&self) -> &[u8] {
+    &self.id
+  }
+
+  pub fn get_key(&self) -> &[u8] {
+    &self.key
+  }
+}
+
+// the additional authenticated data covers the envelope's header and the block's position
+fn additional_data(header: &[u8], handle: &[u8], height: usize) -> Vec<u8> {
+  let mut aad = header.to_vec();
+  aad.extend_from_slice(&NimbleDigest::digest(handle).to_bytes());
+  aad.extend_from_slice(&(height as u64).to_le_bytes());
+  aad
+}
+
+/// Returns true if `block` is an envelope produced by `encrypt_block`
+pub fn is_encrypted_block(block: &[u8]) -> bool {
+  block.len() > ENVELOPE_MAGIC.len() && block.starts_with(ENVELOPE_MAGIC)
+}
+
+/// Encrypts `plaintext` for the block at `height` of the ledger `handle`: the genesis block is at
+/// height 0, and an append is at its expected height
+pub fn encrypt_block(
+  kek: &KeyEncryptionKey,
+  handle: &[u8],
+  height: usize,
+  plaintext: &[u8],
+) -> Result<Vec<u8>, EndpointError> {
+  let mut data_key = [0u8; KEY_BYTES];
+  let mut nonce = [0u8; NONCE_BYTES];
+  if rand_bytes(&mut data_key).is_err() || rand_bytes(&mut nonce).is_err() {
+    return Err(EndpointError::FailedToEncryptBlock);
+  }
+
+  let mut wrapped_key = [0u8; WRAPPED_KEY_BYTES];
+  let res = AesKey::new_encrypt(&kek.key)
+    .and_then(|wrapping_key| wrap_key(&wrapping_key, None, &mut wrapped_key, &data_key));
+  if res.is_err() {
+    return Err(EndpointError::FailedToEncryptBlock);
+  }
+
+  // header: magic, version, key id (length-prefixed), wrapped data key, nonce
+  let mut envelope = ENVELOPE_MAGIC.to_vec();
+  envelope.push(ENVELOPE_VERSION);
+  envelope.push(kek.id.len() as u8);
+  envelope.extend_from_slice(&kek.id);
+  envelope.extend_from_slice(&wrapped_key);
+  envelope.extend_from_slice(&nonce);
+
+  let aad = additional_data(&envelope, handle, height);
+  let mut tag = [0u8; TAG_BYTES];
+  let res = encrypt_aead(
+    Cipher::aes_256_gcm(),
+    &data_key,
+    Some(&nonce),
+    &aad,
+    plaintext,
+    &mut tag,
+  );
+  if res.is_err() {
+    return Err(EndpointError::FailedToEncryptBlock);
+  }
+
+  envelope.extend_from_slice(&tag);
+  envelope.extend_from_slice(&res.unwrap());
+  Ok(envelope)
+}
+
+/// Decrypts an envelope read from the block at `height` of the ledger `handle`, using whichever
+/// of `keks` it was encrypted under
+pub fn decrypt_block(
+  keks: &[KeyEncryptionKey],
+  handle: &[u8],
+  height: usize,
+  envelope: &[u8],
+) -> Result<Vec<u8>, EndpointError> {
+  if !is_encrypted_block(envelope) {
+    return Err(EndpointError::FailedToDecryptBlock);
+  }
+  let mut pos = ENVELOPE_MAGIC.len();
+  let mut take = |n: usize| -> Result<&[u8], EndpointError> {
+    if envelope.len() < pos + n {
+      return Err(EndpointError::FailedToDecryptBlock);
+    }
+    pos += n;
+    Ok(&envelope[pos - n..pos])
+  };
+
+  if take(1)?[0] != ENVELOPE_VERSION {
+    return Err(EndpointError::FailedToDecryptBlock);
+  }
+  let id_len = take(1)?[0] as usize;
+  let id = take(id_len)?;
+  let wrapped_key = take(WRAPPED_KEY_BYTES)?;
+  let nonce = take(NONCE_BYTES)?;
+  let header_len = ENVELOPE_MAGIC.len() + 2 + id_len + WRAPPED_KEY_BYTES + NONCE_BYTES;
+  let tag = take(TAG_BYTES)?;
+  let ciphertext = &envelope[header_len + TAG_BYTES..];
+
+  let kek = match keks.iter().find(|kek| kek.id == id) {
+    Some(kek) => kek,
+    None => return Err(EndpointError::UnknownEncryptionKey),
+  };
+  let mut data_key = [0u8; KEY_BYTES];
+  let res = AesKey::new_decrypt(&kek.key)
+    .and_then(|wrapping_key| unwrap_key(&wrapping_key, None, &mut data_key, wrapped_key));
+  if res.is_err() {
+    return Err(EndpointError::FailedToDecryptBlock);
+  }
+
+  let aad = additional_data(&envelope[..header_len], handle, height);
+  decrypt_aead(
+    Cipher::aes_256_gcm(),
+    &data_key,
+    Some(nonce),
+    &aad,
+    ciphertext,
+    tag,
+  )
+  .map_err(|_e| EndpointError::FailedToDecryptBlock)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_block_encryption() {
+    let kek = KeyEncryptionKey::generate(b"key-1").unwrap();
+    let handle = b"handle";
+    let plaintext = b"confidential";
+
+    let envelope = encrypt_block(&kek, handle, 3, plaintext).unwrap();
+    assert!(is_encrypted_block(&envelope));
+    assert!(!envelope.windows(plaintext.len()).any(|w| w == plaintext));
+    let keks = vec![KeyEncryptionKey::generate(b"key-0").unwrap(), kek.clone()];
+    assert_eq!(
+      decrypt_block(&keks, handle, 3, &envelope).unwrap(),
+      plaintext.to_vec()
+    );
+
+    // the envelope is bound to the ledger and the height
+    assert!(decrypt_block(&keks, b"other", 3, &envelope).is_err());
+    assert!(decrypt_block(&keks, handle, 4, &envelope).is_err());
+
+    // tampering with the header or the ciphertext is detected
+    let mut tampered = envelope.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(decrypt_block(&keks, handle, 3, &tampered).is_err());
+    let mut tampered = envelope.clone();
+    tampered[ENVELOPE_MAGIC.len() + 2 + 5] ^= 1;
+    assert!(decrypt_block(&keks, handle, 3, &tampered).is_err());
+
+    // a reader without the key cannot decrypt
+    let other = KeyEncryptionKey::new(b"key-1", &[7u8; 32]).unwrap();
+    assert!(decrypt_block(&[other], handle, 3, &envelope).is_err());
+    assert_eq!(
+      decrypt_block(&keks[..1], handle, 3, &envelope),
+      Err(EndpointError::UnknownEncryptionKey)
+    );
+    assert!(KeyEncryptionKey::new(b"key", &[0u8; 16]).is_err());
+  }
+}
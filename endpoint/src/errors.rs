@@ -18,6 +18,9 @@ pub enum EndpointError {
   FailedToReadCounter,
   /// returned if the endpoint fails to verify the read counter
   FaieldToVerifyReadCounter,
+  /// returned if the receipts of a counter do not satisfy the endorsement policy that its genesis
+  /// block declares, or the policy cannot be read
+  FailedToVerifyEndorsementPolicy,
   /// returned if the endpoint fails to read the view ledger
   FailedToReadViewLedger,
   /// returned if the endpoint fails to acquire the read lock
@@ -26,4 +29,15 @@ pub enum EndpointError {
   FailedToAcquireWriteLock,
   /// returned if the endpoint fails to apply view change
   FailedToApplyViewChange,
+  /// returned if a key encryption key is malformed
+  InvalidEncryptionKey,
+  /// returned if a block cannot be encrypted
+  FailedToEncryptBlock,
+  /// returned if a block is not a valid envelope or fails to authenticate
+  FailedToDecryptBlock,
+  /// returned if none of the provided keys encrypted the block
+  UnknownEncryptionKey,
+  /// returned if a conditional append lost a race with another append; carries the height of the
+  /// ledger's tail at the time of the conflict
+  AppendConflict(u64),
 }
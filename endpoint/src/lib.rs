@@ -1,8 +1,9 @@
+pub mod encryption;
 mod errors;
 
 use tonic::{
   transport::{Channel, Endpoint},
-  Request,
+  Code, Request, Streaming,
 };
 
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -10,21 +11,25 @@ pub mod coordinator_proto {
   tonic::include_proto!("coordinator_proto");
 }
 
-use crate::errors::EndpointError;
+pub use crate::errors::EndpointError;
 use coordinator_proto::{
-  call_client::CallClient, AppendReq, AppendResp, NewLedgerReq, NewLedgerResp, ReadLatestReq,
-  ReadLatestResp, ReadViewByIndexReq, ReadViewByIndexResp, ReadViewTailReq, ReadViewTailResp,
+  call_client::CallClient, AppendReq, AppendResp, NewLedgerReq, NewLedgerResp, ReadByIndexReq,
+  ReadByIndexResp, ReadLatestReq, ReadLatestResp, ReadViewByIndexReq, ReadViewByIndexResp,
+  ReadViewTailReq, ReadViewTailResp, WatchViewChangesReq, WatchViewChangesResp,
 };
 use ledger::{
   errors::VerificationError,
   signature::{PrivateKey, PrivateKeyTrait, PublicKey, PublicKeyTrait, Signature, SignatureTrait},
-  Block, CustomSerde, NimbleDigest, NimbleHashTrait, VerifierState,
+  AccessRequest, Block, CustomSerde, EndorsementPolicy, NimbleDigest, NimbleHashTrait,
+  VerifierState,
 };
 use rand::random;
 use std::{
+  collections::HashMap,
   convert::TryFrom,
   sync::{Arc, RwLock},
 };
+use tokio::sync::Mutex;
 
 #[allow(dead_code)]
 enum MessageType {
@@ -74,8 +79,11 @@ impl Connection {
     let req = Request::new(NewLedgerReq {
       handle: handle.to_vec(),
       block: block.to_vec(),
+      label: String::new(),
+      namespace: String::new(),
+      client_nonce: Vec::new(),
     });
-    let NewLedgerResp { receipts } = self.clients[random::<usize>() % self.num_grpc_channels]
+    let NewLedgerResp { receipts, .. } = self.clients[random::<usize>() % self.num_grpc_channels]
       .clone()
       .new_ledger(req)
       .await
@@ -87,25 +95,48 @@ impl Connection {
     Ok(receipts)
   }
 
+  /// Appends `block` to a ledger, acting under the ledger's access policy with `sk` if given
   pub async fn append(
     &self,
     handle: &[u8],
     block: &[u8],
     expected_height: u64,
+    sk: Option<&PrivateKey>,
   ) -> Result<(Vec<u8>, Vec<u8>), EndpointError> {
+    let (client_pk, client_signature) = match sk {
+      Some(sk) => {
+        let access_request = AccessRequest::Append {
+          block,
+          expected_height: expected_height as usize,
+        };
+        let sig = sk.sign(&access_request.message(handle)).unwrap();
+        (sk.get_public_key().unwrap().to_bytes(), sig.to_bytes())
+      },
+      None => (Vec::new(), Vec::new()),
+    };
     let req = Request::new(AppendReq {
       handle: handle.to_vec(),
       block: block.to_vec(),
       expected_height,
+      client_pk,
+      client_signature,
+      client_request_id: vec![],
     });
     let AppendResp {
       hash_nonces,
       receipts,
+      ..
     } = self.clients[random::<usize>() % self.num_grpc_channels]
       .clone()
       .append(req)
       .await
       .map_err(|e| {
+        // the coordinator reports the tail's height when a conditional append conflicts
+        if e.code() == Code::FailedPrecondition && e.details().len() == 8 {
+          let mut height = [0u8; 8];
+          height.copy_from_slice(e.details());
+          return EndpointError::AppendConflict(u64::from_le_bytes(height));
+        }
         eprintln!("Failed to append to a ledger {:?}", e);
         EndpointError::FailedToIncrementCounter
       })?
@@ -122,11 +153,13 @@ impl Connection {
       block,
       nonces,
       receipts,
+      ..
     } = self.clients[random::<usize>() % self.num_grpc_channels]
       .clone()
       .read_latest(ReadLatestReq {
         handle: handle.to_vec(),
         nonce: nonce.to_vec(),
+        consistency_token: vec![],
       })
       .await
       .map_err(|e| {
@@ -137,12 +170,40 @@ impl Connection {
     Ok((block, nonces, receipts))
   }
 
+  pub async fn read_by_index(
+    &self,
+    handle: &[u8],
+    index: usize,
+  ) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), EndpointError> {
+    let ReadByIndexResp {
+      block,
+      nonces,
+      receipts,
+      ..
+    } = self.clients[random::<usize>() % self.num_grpc_channels]
+      .clone()
+      .read_by_index(ReadByIndexReq {
+        handle: handle.to_vec(),
+        index: index as u64,
+        nonce: vec![],
+        snapshot: 0,
+      })
+      .await
+      .map_err(|e| {
+        eprintln!("Failed to read a ledger by index {:?}", e);
+        EndpointError::FailedToReadCounter
+      })?
+      .into_inner();
+    Ok((block, nonces, receipts))
+  }
+
   pub async fn read_view_by_index(
     &self,
     index: usize,
   ) -> Result<(Vec<u8>, Vec<u8>), EndpointError> {
-    let ReadViewByIndexResp { block, receipts } = self.clients
-      [random::<usize>() % self.num_grpc_channels]
+    let ReadViewByIndexResp {
+      block, receipts, ..
+    } = self.clients[random::<usize>() % self.num_grpc_channels]
       .clone()
       .read_view_by_index(ReadViewByIndexReq {
         index: index as u64,
@@ -159,14 +220,25 @@ impl Connection {
       receipts,
       height,
       attestations,
+      ..
     } = self.clients[random::<usize>() % self.num_grpc_channels]
       .clone()
-      .read_view_tail(ReadViewTailReq {})
+      .read_view_tail(ReadViewTailReq { nonce: vec![] })
       .await
       .map_err(|_e| EndpointError::FailedToReadViewLedger)?
       .into_inner();
     Ok((block, receipts, height as usize, attestations))
   }
+
+  pub async fn watch_view_changes(&self) -> Result<Streaming<WatchViewChangesResp>, EndpointError> {
+    let stream = self.clients[random::<usize>() % self.num_grpc_channels]
+      .clone()
+      .watch_view_changes(WatchViewChangesReq {})
+      .await
+      .map_err(|_e| EndpointError::FailedToReadViewLedger)?
+      .into_inner();
+    Ok(stream)
+  }
 }
 
 pub struct EndpointState {
@@ -175,6 +247,8 @@ pub struct EndpointState {
   sk: PrivateKey,
   pk: PublicKey,
   vs: Arc<RwLock<VerifierState>>,
+  view_refresh: Mutex<()>, // serializes fetching the view ledger
+  policies: RwLock<HashMap<Vec<u8>, EndorsementPolicy>>, // the endorsement policy of each counter
 }
 
 #[derive(Debug)]
@@ -250,6 +324,8 @@ impl EndpointState {
       sk,
       pk,
       vs: Arc::new(RwLock::new(vs)),
+      view_refresh: Mutex::new(()),
+      policies: RwLock::new(HashMap::new()),
     })
   }
 
@@ -261,13 +337,43 @@ impl EndpointState {
     Ok((
       self.id.to_bytes(),
       match pkformat {
-        PublicKeyFormat::COMPRESSED => public_key.to_bytes(),
+        PublicKeyFormat::COMPRESSED => public_key.to_raw(),
         PublicKeyFormat::DER => public_key.to_der(),
         _ => public_key.to_uncompressed(),
       },
     ))
   }
 
+  /// Sets the number of endorsers each ledger is assigned to, which must match the coordinator
+  pub fn set_shard_size(&self, shard_size: usize) -> Result<(), EndpointError> {
+    if let Ok(mut vs_wr) = self.vs.write() {
+      vs_wr.set_shard_size(shard_size);
+      Ok(())
+    } else {
+      Err(EndpointError::FailedToAcquireWriteLock)
+    }
+  }
+
+  /// Applies view changes as the coordinator commits them, so that verification keeps working
+  /// across reconfigurations; returns when the coordinator closes the stream or an error occurs
+  pub async fn follow_view_changes(&self) -> Result<(), EndpointError> {
+    let mut stream = self.conn.watch_view_changes().await?;
+    // catch up with view changes committed before the stream was opened
+    self.refresh_view().await?;
+    loop {
+      match stream.message().await {
+        Ok(Some(_view_change)) => self.refresh_view().await?,
+        Ok(None) => return Ok(()),
+        Err(_e) => return Err(EndpointError::FailedToReadViewLedger),
+      }
+    }
+  }
+
+  async fn refresh_view(&self) -> Result<(), EndpointError> {
+    let _guard = self.view_refresh.lock().await;
+    self.update_view().await
+  }
+
   async fn update_view(&self) -> Result<(), EndpointError> {
     let start_height = {
       if let Ok(vs_rd) = self.vs.read() {
@@ -277,7 +383,7 @@ impl EndpointState {
       }
     };
 
-    let (block, receipts, height, attestations) = self.conn.read_view_tail().await.unwrap();
+    let (block, receipts, height, attestations) = self.conn.read_view_tail().await?;
     if let Ok(mut vs_wr) = self.vs.write() {
       let res = vs_wr.apply_view_change(&block, &receipts, Some(&attestations));
       if res.is_err() {
@@ -288,7 +394,7 @@ impl EndpointState {
     }
 
     for index in (start_height..height).rev() {
-      let (block, receipts) = self.conn.read_view_by_index(index).await.unwrap();
+      let (block, receipts) = self.conn.read_view_by_index(index).await?;
       if let Ok(mut vs_wr) = self.vs.write() {
         let res = vs_wr.apply_view_change(&block, &receipts, None);
         if res.is_err() {
@@ -302,6 +408,108 @@ impl EndpointState {
     Ok(())
   }
 
+  fn verify<T>(
+    &self,
+    verify: &impl Fn(&VerifierState) -> Result<T, VerificationError>,
+  ) -> Result<Result<T, VerificationError>, EndpointError> {
+    if let Ok(vs_rd) = self.vs.read() {
+      Ok(verify(&vs_rd))
+    } else {
+      Err(EndpointError::FailedToAcquireReadLock)
+    }
+  }
+
+  // Runs `verify` against the verifier state. Receipts from a view this endpoint has not seen yet
+  // (e.g., after a reconfiguration) make it fetch and verify the missing suffix of the view
+  // ledger and run `verify` once more, so callers only see verification failures that persist.
+  async fn verify_with_view_refresh<T>(
+    &self,
+    verify: impl Fn(&VerifierState) -> Result<T, VerificationError>,
+  ) -> Result<Result<T, VerificationError>, EndpointError> {
+    match self.verify(&verify)? {
+      Err(VerificationError::ViewNotFound) => {},
+      res => return Ok(res),
+    }
+
+    {
+      let _guard = self.view_refresh.lock().await;
+      // another request may have fetched the missing views while this one waited
+      if let Err(VerificationError::ViewNotFound) = self.verify(&verify)? {
+        self.update_view().await?;
+      }
+    }
+
+    self.verify(&verify)
+  }
+
+  // Returns the endorsement policy that the genesis block of the counter `handle` declares, which
+  // is read and checked against the policy on first use
+  async fn get_policy(&self, handle: &[u8]) -> Result<EndorsementPolicy, EndpointError> {
+    if let Some(policy) = self
+      .policies
+      .read()
+      .map_err(|_e| EndpointError::FailedToAcquireReadLock)?
+      .get(handle)
+    {
+      return Ok(policy.clone());
+    }
+
+    let (block, nonces, receipts) = self.conn.read_by_index(handle, 0).await?;
+    let res = self
+      .verify_with_view_refresh(|vs| vs.verify_read_by_index(handle, &block, &nonces, 0, &receipts))
+      .await?;
+    if res.is_err() {
+      return Err(EndpointError::FailedToVerifyEndorsementPolicy);
+    }
+    let policy = match EndorsementPolicy::from_genesis_bytes(&block) {
+      Ok((policy, _app_bytes)) => policy,
+      Err(_) => return Err(EndpointError::FailedToVerifyEndorsementPolicy),
+    };
+    let hash_nonces = NimbleDigest::digest(&nonces).to_bytes();
+    self
+      .verify_policy(&policy, handle, &block, &hash_nonces, 0, None, &receipts)
+      .await?;
+
+    self
+      .policies
+      .write()
+      .map_err(|_e| EndpointError::FailedToAcquireWriteLock)?
+      .insert(handle.to_vec(), policy.clone());
+    Ok(policy)
+  }
+
+  // Checks that the receipts of the entry at `height` of the counter `handle` satisfy `policy`.
+  // The receipts are checked for a majority quorum already, which is all the default policy asks
+  // for.
+  #[allow(clippy::too_many_arguments)]
+  async fn verify_policy(
+    &self,
+    policy: &EndorsementPolicy,
+    handle: &[u8],
+    block: &[u8],
+    hash_nonces: &[u8],
+    height: usize,
+    nonce: Option<&[u8]>,
+    receipts: &[u8],
+  ) -> Result<(), EndpointError> {
+    if *policy == EndorsementPolicy::Majority {
+      return Ok(());
+    }
+    let res = self
+      .verify_with_view_refresh(|vs| {
+        vs.verify_endorsement_policy(handle, block, hash_nonces, height, nonce, receipts, policy)
+      })
+      .await?;
+    if res.is_err() {
+      eprintln!(
+        "failed to verify the endorsement policy of a counter {:?}",
+        res
+      );
+      return Err(EndpointError::FailedToVerifyEndorsementPolicy);
+    }
+    Ok(())
+  }
+
   pub async fn new_counter(
     &self,
     handle: &[u8],
@@ -337,36 +545,27 @@ impl EndpointState {
       res.unwrap()
     };
 
-    // verify the response received from the coordinator;
-    let res = {
-      if let Ok(vs_rd) = self.vs.read() {
-        vs_rd.verify_new_ledger(handle, &block, &receipts)
-      } else {
-        return Err(EndpointError::FailedToAcquireReadLock);
-      }
-    };
-
+    // verify the response received from the coordinator
+    let res = self
+      .verify_with_view_refresh(|vs| vs.verify_new_ledger(handle, &block, &receipts))
+      .await?;
     if res.is_err() {
-      if res.unwrap_err() != VerificationError::ViewNotFound {
-        return Err(EndpointError::FailedToVerifyNewCounter);
-      } else {
-        let res = self.update_view().await;
-        if res.is_err() {
-          return Err(EndpointError::FailedToVerifyNewCounter);
-        }
-        let res = {
-          if let Ok(vs_rd) = self.vs.read() {
-            vs_rd.verify_new_ledger(handle, &block, &receipts)
-          } else {
-            return Err(EndpointError::FailedToAcquireReadLock);
-          }
-        };
-        if res.is_err() {
-          eprintln!("failed to create a new counter {:?}", res);
-          return Err(EndpointError::FailedToVerifyNewCounter);
-        }
-      }
+      eprintln!("failed to create a new counter {:?}", res);
+      return Err(EndpointError::FailedToVerifyNewCounter);
     }
+    let policy = match EndorsementPolicy::from_genesis_bytes(&block) {
+      Ok((policy, _app_bytes)) => policy,
+      Err(_) => return Err(EndpointError::FailedToVerifyEndorsementPolicy),
+    };
+    let hash_nonces = NimbleDigest::default().to_bytes();
+    self
+      .verify_policy(&policy, handle, &block, &hash_nonces, 0, None, &receipts)
+      .await?;
+    self
+      .policies
+      .write()
+      .map_err(|_e| EndpointError::FailedToAcquireWriteLock)?
+      .insert(handle.to_vec(), policy);
 
     // sign a message that unequivocally identifies the counter and tag
     let msg = {
@@ -383,7 +582,7 @@ impl EndpointState {
     let sig = self.sk.sign(&msg.to_bytes()).unwrap();
     let signature = match sigformat {
       SignatureFormat::DER => sig.to_der(),
-      _ => sig.to_bytes(),
+      _ => sig.to_raw(),
     };
 
     Ok(signature)
@@ -426,43 +625,42 @@ impl EndpointState {
 
     // issue a request to the coordinator and receive a response
     let (hash_nonces, receipts) = {
-      let res = self.conn.append(handle, &block, expected_counter).await;
+      let res = self
+        .conn
+        .append(handle, &block, expected_counter, Some(&self.sk))
+        .await;
 
-      if res.is_err() {
-        return Err(EndpointError::FailedToIncrementCounter);
+      match res {
+        Ok(resp) => resp,
+        Err(EndpointError::AppendConflict(height)) => {
+          return Err(EndpointError::AppendConflict(height));
+        },
+        Err(_) => return Err(EndpointError::FailedToIncrementCounter),
       }
-      res.unwrap()
     };
 
-    // verify the response received from the coordinator; TODO: handle the case where vs does not have the returned view hash
-    let res = {
-      if let Ok(vs_rd) = self.vs.read() {
-        vs_rd.verify_append(handle, &block, &hash_nonces, expected_height, &receipts)
-      } else {
-        return Err(EndpointError::FailedToAcquireReadLock);
-      }
-    };
+    // verify the response received from the coordinator
+    let res = self
+      .verify_with_view_refresh(|vs| {
+        vs.verify_append(handle, &block, &hash_nonces, expected_height, &receipts)
+      })
+      .await?;
     if res.is_err() {
-      if res.unwrap_err() != VerificationError::ViewNotFound {
-        return Err(EndpointError::FailedToVerifyIncrementedCounter);
-      } else {
-        let res = self.update_view().await;
-        if res.is_err() {
-          return Err(EndpointError::FailedToVerifyIncrementedCounter);
-        }
-        let res = {
-          if let Ok(vs_rd) = self.vs.read() {
-            vs_rd.verify_append(handle, &block, &hash_nonces, expected_height, &receipts)
-          } else {
-            return Err(EndpointError::FailedToAcquireReadLock);
-          }
-        };
-        if res.is_err() {
-          eprintln!("failed to increment a counter {:?}", res);
-          return Err(EndpointError::FailedToVerifyIncrementedCounter);
-        }
-      }
+      eprintln!("failed to increment a counter {:?}", res);
+      return Err(EndpointError::FailedToVerifyIncrementedCounter);
     }
+    let policy = self.get_policy(handle).await?;
+    self
+      .verify_policy(
+        &policy,
+        handle,
+        &block,
+        &hash_nonces,
+        expected_height,
+        None,
+        &receipts,
+      )
+      .await?;
 
     // sign a message that unequivocally identifies the counter and tag
     let msg = {
@@ -479,7 +677,7 @@ impl EndpointState {
     let sig = self.sk.sign(&msg.to_bytes()).unwrap();
     let signature = match sigformat {
       SignatureFormat::DER => sig.to_der(),
-      _ => sig.to_bytes(),
+      _ => sig.to_raw(),
     };
 
     Ok(signature)
@@ -502,39 +700,28 @@ impl EndpointState {
     };
 
     // verify the response received from the coordinator
-    let res = {
-      if let Ok(vs_rd) = self.vs.read() {
-        vs_rd.verify_read_latest(handle, &block, &nonces, nonce, &receipts)
-      } else {
-        return Err(EndpointError::FailedToAcquireReadLock);
-      }
-    };
-    let counter = {
-      if res.is_err() {
-        if res.unwrap_err() != VerificationError::ViewNotFound {
-          return Err(EndpointError::FaieldToVerifyReadCounter);
-        } else {
-          let res = self.update_view().await;
-          if res.is_err() {
-            return Err(EndpointError::FaieldToVerifyReadCounter);
-          }
-          let res = {
-            if let Ok(vs_rd) = self.vs.read() {
-              vs_rd.verify_read_latest(handle, &block, &nonces, nonce, &receipts)
-            } else {
-              return Err(EndpointError::FailedToAcquireReadLock);
-            }
-          };
-          if res.is_err() {
-            return Err(EndpointError::FaieldToVerifyReadCounter);
-          } else {
-            res.unwrap()
-          }
-        }
-      } else {
-        res.unwrap()
-      }
+    let res = self
+      .verify_with_view_refresh(|vs| {
+        vs.verify_read_latest(handle, &block, &nonces, nonce, &receipts)
+      })
+      .await?;
+    let counter = match res {
+      Ok(counter) => counter,
+      Err(_) => return Err(EndpointError::FaieldToVerifyReadCounter),
     };
+    let policy = self.get_policy(handle).await?;
+    let hash_nonces = NimbleDigest::digest(&nonces).to_bytes();
+    self
+      .verify_policy(
+        &policy,
+        handle,
+        &block,
+        &hash_nonces,
+        counter,
+        Some(nonce),
+        &receipts,
+      )
+      .await?;
 
     // verify the integrity of the coordinator's response by checking the signature
     if block.len() < Signature::num_bytes() {
@@ -583,7 +770,7 @@ impl EndpointState {
     let sig = self.sk.sign(&msg.to_bytes()).unwrap();
     let signature = match sigformat {
       SignatureFormat::DER => sig.to_der(),
-      _ => sig.to_bytes(),
+      _ => sig.to_raw(),
     };
 
     // respond to the light client
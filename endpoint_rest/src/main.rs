@@ -1,4 +1,4 @@
-use endpoint::{EndpointState, PublicKeyFormat, SignatureFormat};
+use endpoint::{EndpointError, EndpointState, PublicKeyFormat, SignatureFormat};
 
 use axum::{
   extract::{Extension, Path, Query},
@@ -67,6 +67,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .long("channels")
         .takes_value(true)
         .help("The number of grpc channels"),
+    )
+    .arg(
+      Arg::with_name("shard_size")
+        .long("shard_size")
+        .takes_value(true)
+        .help("The number of endorsers each ledger is assigned to (0 assigns all endorsers)"),
     );
   let cli_matches = config.get_matches();
   let hostname = cli_matches.value_of("host").unwrap();
@@ -93,6 +99,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
       .await
       .unwrap(),
   );
+  if let Some(x) = cli_matches.value_of("shard_size") {
+    match x.to_string().parse() {
+      Ok(v) => endpoint_state.set_shard_size(v).unwrap(),
+      Err(_) => panic!("Failed to parse the shard size"),
+    }
+  }
+  {
+    let endpoint_state = endpoint_state.clone();
+    tokio::spawn(async move {
+      if let Err(e) = endpoint_state.follow_view_changes().await {
+        eprintln!("Stopped following view changes: {:?}", e);
+      }
+    });
+  }
 
   // Build our application by composing routes
   let app = Router::new()
@@ -167,6 +187,12 @@ struct IncrementCounterResponse {
   pub signature: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct CounterConflictResponse {
+  #[serde(rename = "Counter")]
+  pub counter: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ReadCounterResponse {
   #[serde(rename = "Tag")]
@@ -326,6 +352,13 @@ async fn increment_counter(
   let res = state
     .increment_counter(&handle, &tag, req.expected_counter, sigformat)
     .await;
+  if let Err(EndpointError::AppendConflict(counter)) = res {
+    // the client can retry with the next counter
+    return (
+      StatusCode::CONFLICT,
+      Json(json!(CounterConflictResponse { counter })),
+    );
+  }
   if res.is_err() {
     eprintln!("failed to increment a counter {:?}", res);
     return (StatusCode::CONFLICT, Json(json!({})));
@@ -0,0 +1,75 @@
+#![no_main]
+use coordinator::coordinator_state::CoordinatorState;
+use libfuzzer_sys::{arbitrary::Result, fuzz_target};
+use nimble_fuzz::Input;
+use nimble_testkit::Testkit;
+use std::time::Duration;
+
+// The gRPC service of the coordinator lives in its binary and hands the fields of each request to
+// `CoordinatorState`, which is driven here as the service drives it. The coordinator has
+// in-process endorsers, so well-formed creates and appends succeed and the requests that follow
+// them reach the handlers' checks of existing ledgers, blocks, and heights.
+fuzz_target!(|data: &[u8]| {
+  let pool = vec![vec![1u8; 16], vec![2u8; 32], Vec::new()];
+  let sizes = vec![16, 32];
+  let mut input = Input::new(data, pool, sizes);
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .enable_time()
+    .build()
+    .unwrap();
+  runtime.block_on(async {
+    let testkit = Testkit::new(NUM_ENDORSERS).await.unwrap();
+    while !input.is_empty() {
+      if call(testkit.coordinator(), &mut input).await.is_err() {
+        break;
+      }
+    }
+  });
+});
+
+const NUM_ENDORSERS: usize = 3;
+
+// a read waits for the next append while its nonce lacks a quorum, which no later request may send
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+// every request must fail with an error instead of panicking
+async fn call(coordinator: &CoordinatorState, input: &mut Input<'_>) -> Result<()> {
+  match input.choice(7)? {
+    0 => {
+      let (handle, block) = (input.bytes()?, input.bytes()?);
+      let _ = coordinator.create_ledger(None, &handle, &block).await;
+    },
+    1 => {
+      let (handle, block, label) = (input.bytes()?, input.bytes()?, input.bytes()?);
+      let label = String::from_utf8_lossy(&label);
+      let _ = coordinator
+        .create_labeled_ledger(&handle, &block, &label)
+        .await;
+    },
+    2 => {
+      let (handle, block, height) = (input.bytes()?, input.bytes()?, input.height()?);
+      let _ = coordinator
+        .append_ledger(None, &handle, &block, height as usize)
+        .await;
+    },
+    3 => {
+      let (handle, nonce) = (input.bytes()?, input.bytes()?);
+      let _ =
+        tokio::time::timeout(READ_TIMEOUT, coordinator.read_ledger_tail(&handle, &nonce)).await;
+    },
+    4 => {
+      let (handle, index) = (input.bytes()?, input.height()?);
+      let _ = coordinator
+        .read_ledger_by_index(&handle, index as usize)
+        .await;
+    },
+    5 => {
+      let index = input.height()?;
+      let _ = coordinator.read_view_by_index(index as usize).await;
+    },
+    _ => {
+      let _ = coordinator.read_view_tail().await;
+    },
+  }
+  Ok(())
+}
@@ -0,0 +1,168 @@
+#![no_main]
+use endorser::service::EndorserServiceState;
+use ledger::{
+  endorser_proto::{
+    endorser_call_server::EndorserCall, ActivateReq, AppendBatchReq, AppendReq, FinalizeStateReq,
+    GetPublicKeyReq, InitializeStateReq, LedgerChunkEntry, LedgerTailMap, LedgerTailMapEntry,
+    LockEndorserReq, NewLedgerReq, ReadLatestReq, ReadStateReq, ReadViewTailReq, RotateKeyReq,
+    UnlockEndorserReq,
+  },
+  CustomSerde, MetaBlock,
+};
+use libfuzzer_sys::{arbitrary::Result, fuzz_target};
+use nimble_fuzz::Input;
+use tonic::Request;
+
+fuzz_target!(|data: &[u8]| {
+  let pool = vec![
+    vec![1u8; 32],
+    vec![2u8; 32],
+    MetaBlock::default().to_bytes(),
+    Vec::new(),
+  ];
+  let sizes = vec![31, 32, 33, MetaBlock::num_bytes()];
+  let mut input = Input::new(data, pool, sizes);
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .enable_time()
+    .build()
+    .unwrap();
+  runtime.block_on(async {
+    let endorser = EndorserServiceState::new();
+    while !input.is_empty() {
+      if call(&endorser, &mut input).await.is_err() {
+        break;
+      }
+    }
+  });
+});
+
+fn tail_map(input: &mut Input) -> Result<Vec<LedgerTailMapEntry>> {
+  (0..input.choice(4)?)
+    .map(|_| {
+      Ok(LedgerTailMapEntry {
+        handle: input.bytes()?,
+        height: input.height()?,
+        metablock: input.bytes()?,
+        block: input.bytes()?,
+        nonces: input.bytes()?,
+      })
+    })
+    .collect()
+}
+
+fn append_req(input: &mut Input) -> Result<AppendReq> {
+  Ok(AppendReq {
+    handle: input.bytes()?,
+    block_hash: input.bytes()?,
+    expected_height: input.height()?,
+    block: input.bytes()?,
+    nonces: input.bytes()?,
+    timestamp: input.height()?,
+  })
+}
+
+// every handler must turn malformed input into an error status instead of panicking
+async fn call(endorser: &EndorserServiceState, input: &mut Input<'_>) -> Result<()> {
+  match input.choice(13)? {
+    0 => {
+      let _ = endorser
+        .get_public_key(Request::new(GetPublicKeyReq {}))
+        .await;
+    },
+    1 => {
+      let req = NewLedgerReq {
+        handle: input.bytes()?,
+        block_hash: input.bytes()?,
+        block: input.bytes()?,
+      };
+      let _ = endorser.new_ledger(Request::new(req)).await;
+    },
+    2 => {
+      let req = append_req(input)?;
+      let _ = endorser.append(Request::new(req)).await;
+    },
+    3 => {
+      let entries = (0..input.choice(4)?)
+        .map(|_| append_req(input))
+        .collect::<Result<Vec<AppendReq>>>()?;
+      let _ = endorser
+        .append_batch(Request::new(AppendBatchReq { entries }))
+        .await;
+    },
+    4 => {
+      let req = ReadLatestReq {
+        handle: input.bytes()?,
+        nonce: input.bytes()?,
+      };
+      let _ = endorser.read_latest(Request::new(req)).await;
+    },
+    5 => {
+      let req = FinalizeStateReq {
+        block_hash: input.bytes()?,
+        expected_height: input.height()?,
+        prev_view_hash: input.bytes()?,
+      };
+      let _ = endorser.finalize_state(Request::new(req)).await;
+    },
+    6 => {
+      let req = InitializeStateReq {
+        group_identity: input.bytes()?,
+        ledger_tail_map: tail_map(input)?,
+        view_tail_metablock: input.bytes()?,
+        block_hash: input.bytes()?,
+        expected_height: input.height()?,
+      };
+      let _ = endorser.initialize_state(Request::new(req)).await;
+    },
+    7 => {
+      let _ = endorser.read_state(Request::new(ReadStateReq {})).await;
+    },
+    8 => {
+      let req = ReadViewTailReq {
+        nonce: input.bytes()?,
+      };
+      let _ = endorser.read_view_tail(Request::new(req)).await;
+    },
+    9 => {
+      let _ = endorser
+        .lock_endorser(Request::new(LockEndorserReq {}))
+        .await;
+    },
+    10 => {
+      let _ = endorser
+        .unlock_endorser(Request::new(UnlockEndorserReq {}))
+        .await;
+    },
+    11 => {
+      let _ = endorser.rotate_key(Request::new(RotateKeyReq {})).await;
+    },
+    _ => {
+      let mut ledger_tail_maps = Vec::new();
+      for _ in 0..input.choice(3)? {
+        ledger_tail_maps.push(LedgerTailMap {
+          entries: tail_map(input)?,
+        });
+      }
+      let mut ledger_chunks = Vec::new();
+      for _ in 0..input.choice(3)? {
+        ledger_chunks.push(LedgerChunkEntry {
+          handle: input.bytes()?,
+          hash: input.bytes()?,
+          height: input.height()?,
+          block_hashes: (0..input.choice(3)?)
+            .map(|_| input.bytes())
+            .collect::<Result<Vec<Vec<u8>>>>()?,
+        });
+      }
+      let req = ActivateReq {
+        old_config: input.bytes()?,
+        new_config: input.bytes()?,
+        ledger_tail_maps,
+        ledger_chunks,
+        receipts: input.bytes()?,
+      };
+      let _ = endorser.activate(Request::new(req)).await;
+    },
+  }
+  Ok(())
+}
@@ -0,0 +1,56 @@
+//! The input of the fuzz targets of the services' request handlers, which read the fuzzer's bytes
+//! as a sequence of requests and check that malformed requests fail with an error status rather
+//! than a panic.
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+
+/// Reads the fields of requests from the fuzzer's bytes. Byte strings are biased towards the
+/// sizes the decoders expect, and are drawn partly from a small pool so that requests refer to
+/// the same ledgers often enough to reach deeper states.
+pub struct Input<'a> {
+  data: Unstructured<'a>,
+  pool: Vec<Vec<u8>>,
+  sizes: Vec<usize>,
+}
+
+impl<'a> Input<'a> {
+  /// `pool` holds the byte strings that fields reuse and `sizes` the lengths the decoders expect
+  pub fn new(data: &'a [u8], pool: Vec<Vec<u8>>, sizes: Vec<usize>) -> Self {
+    Input {
+      data: Unstructured::new(data),
+      pool,
+      sizes,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.data.is_empty()
+  }
+
+  /// picks one of `n` alternatives, such as the handler that the next request goes to
+  pub fn choice(&mut self, n: usize) -> Result<usize> {
+    self.data.int_in_range(0..=n - 1)
+  }
+
+  pub fn bytes(&mut self) -> Result<Vec<u8>> {
+    let len = match self.choice(self.sizes.len() + 3)? {
+      0 => 0,
+      1 => {
+        let i = self.choice(self.pool.len())?;
+        return Ok(self.pool[i].clone());
+      },
+      2 => self.data.int_in_range(0..=255)?,
+      i => self.sizes[i - 3],
+    };
+    Ok(self.data.bytes(len)?.to_vec())
+  }
+
+  /// a ledger height, biased towards the heights that handlers treat specially
+  pub fn height(&mut self) -> Result<u64> {
+    match self.choice(4)? {
+      0 => Ok(0),
+      1 => Ok(1),
+      2 => Ok(u64::MAX),
+      _ => self.data.int_in_range(0..=7),
+    }
+  }
+}
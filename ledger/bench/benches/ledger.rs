@@ -0,0 +1,120 @@
+//! Benchmarks of the computations that every append and every verified read pays for: hashing
+//! blocks and metablocks, and checking the endorsers' signatures in the receipts. A verifier
+//! checks one signature per endorser in the quorum, so receipts are measured at several quorum
+//! sizes and with each signature scheme. Run with `cargo bench` in this directory.
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ledger::{
+  signature::{PrivateKey, PrivateKeyTrait, SignatureScheme},
+  CustomSerde, IdSig, MetaBlock, NimbleDigest, NimbleHashTrait, Receipt, Receipts,
+};
+
+const BLOCK_SIZES: [usize; 4] = [64, 1024, 16 * 1024, 256 * 1024];
+const QUORUM_SIZES: [usize; 4] = [1, 3, 5, 9];
+const LARGE_QUORUM_SIZES: [usize; 4] = [7, 15, 31, 63];
+
+fn bench_digest(c: &mut Criterion) {
+  let mut group = c.benchmark_group(format!("digest_{}", NimbleDigest::algorithm()));
+  for size in BLOCK_SIZES {
+    let block = vec![0xabu8; size];
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_with_input(BenchmarkId::from_parameter(size), &block, |b, block| {
+      b.iter(|| NimbleDigest::digest(black_box(block)))
+    });
+  }
+  group.finish();
+
+  let left = NimbleDigest::digest(b"left");
+  let right = NimbleDigest::digest(b"right");
+  c.bench_function("digest_with", |b| {
+    b.iter(|| black_box(&left).digest_with(black_box(&right)))
+  });
+}
+
+fn bench_metablock(c: &mut Criterion) {
+  let prev = NimbleDigest::digest(b"prev");
+  let block_hash = NimbleDigest::digest(b"block");
+  let metablock = MetaBlock::new(&prev, &block_hash, 42);
+  c.bench_function("metablock_hash", |b| {
+    b.iter(|| black_box(&metablock).hash())
+  });
+}
+
+// Returns the receipts of `quorum` endorsers for one append, as a client receives them
+fn receipts(scheme: SignatureScheme, quorum: usize) -> (Receipts, Vec<u8>) {
+  let view = NimbleDigest::digest(b"view");
+  let metablock = MetaBlock::new(
+    &NimbleDigest::digest(b"prev"),
+    &NimbleDigest::digest(b"block"),
+    1,
+  );
+  let message = metablock.hash().to_bytes();
+  let mut receipts = Receipts::new();
+  for _ in 0..quorum {
+    let sk = PrivateKey::generate(scheme);
+    let sig = sk.sign(&message).unwrap();
+    let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+    receipts.add(&Receipt::new(view, metablock.clone(), id_sig));
+  }
+  (receipts, message)
+}
+
+fn bench_receipts(c: &mut Criterion) {
+  for scheme in SignatureScheme::all() {
+    let mut group = c.benchmark_group(format!("receipt_verify_{}", scheme.get_name()));
+    for quorum in QUORUM_SIZES {
+      let (receipts, message) = receipts(scheme, quorum);
+      let bytes = receipts.to_bytes();
+      group.throughput(Throughput::Elements(quorum as u64));
+      // decoding the receipts and checking the signature of each endorser in the quorum
+      group.bench_with_input(BenchmarkId::from_parameter(quorum), &bytes, |b, bytes| {
+        b.iter(|| {
+          let receipts = Receipts::from_bytes(black_box(bytes)).unwrap();
+          for id_sigs in receipts.get().values() {
+            for id_sig in id_sigs {
+              id_sig.verify(&message).unwrap();
+            }
+          }
+        })
+      });
+    }
+    group.finish();
+  }
+}
+
+// Checking the signatures of large quorums one after another and with `IdSig::verify_all`, which
+// checks them in parallel when the benchmarks are run with `--features parallel`
+fn bench_large_quorums(c: &mut Criterion) {
+  let scheme = SignatureScheme::P256;
+  let mut group = c.benchmark_group(format!("quorum_verify_{}", scheme.get_name()));
+  for quorum in LARGE_QUORUM_SIZES {
+    let (receipts, message) = receipts(scheme, quorum);
+    let id_sigs = receipts.get().values().next().unwrap().clone();
+    group.throughput(Throughput::Elements(quorum as u64));
+    group.bench_with_input(
+      BenchmarkId::new("sequential", quorum),
+      &id_sigs,
+      |b, id_sigs| {
+        b.iter(|| {
+          for id_sig in black_box(id_sigs) {
+            id_sig.verify(&message).unwrap();
+          }
+        })
+      },
+    );
+    group.bench_with_input(
+      BenchmarkId::new("verify_all", quorum),
+      &id_sigs,
+      |b, id_sigs| b.iter(|| IdSig::verify_all(black_box(id_sigs), &message).unwrap()),
+    );
+  }
+  group.finish();
+}
+
+criterion_group!(
+  benches,
+  bench_digest,
+  bench_metablock,
+  bench_receipts,
+  bench_large_quorums
+);
+criterion_main!(benches);
@@ -0,0 +1 @@
+//! Criterion benchmarks of the ledger crate, in `benches/`
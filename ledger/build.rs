@@ -1,4 +1,16 @@
+use std::{env, path::PathBuf};
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-  tonic_build::compile_protos("../proto/endorser.proto")?;
+  // the descriptors of each file are embedded for the reflection service
+  let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+  for (proto, descriptor) in [
+    ("endorser", "endorser_descriptor.bin"),
+    ("health", "health_descriptor.bin"),
+    ("reflection", "reflection_descriptor.bin"),
+  ] {
+    tonic_build::configure()
+      .file_descriptor_set_path(out_dir.join(descriptor))
+      .compile(&[format!("../proto/{}.proto", proto)], &["../proto"])?;
+  }
   Ok(())
 }
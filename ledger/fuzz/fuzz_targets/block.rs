@@ -0,0 +1,21 @@
+#![no_main]
+use ledger::{
+  AccessPolicy, BlobReference, BlockEnvelope, BlockValidation, EndorsementPolicy, ExternalBlock,
+};
+use libfuzzer_sys::fuzz_target;
+
+// the parsers of the structures that a block's bytes may carry
+fuzz_target!(|data: &[u8]| {
+  if let Ok(envelope) = BlockEnvelope::from_bytes(data) {
+    assert_eq!(envelope.to_bytes().unwrap(), data);
+  }
+  if let Ok(reference) = BlobReference::from_bytes(data) {
+    assert_eq!(reference.to_bytes(), data);
+  }
+  if let Ok(block) = ExternalBlock::from_bytes(data) {
+    assert_eq!(block.to_bytes(), data);
+  }
+  let _ = EndorsementPolicy::from_genesis_bytes(data);
+  let _ = AccessPolicy::from_genesis_bytes(data);
+  let _ = BlockValidation::default().validate(data);
+});
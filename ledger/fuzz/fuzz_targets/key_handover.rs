@@ -0,0 +1,7 @@
+#![no_main]
+use ledger::{CustomSerde, KeyHandover};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  let _ = KeyHandover::from_bytes(data);
+});
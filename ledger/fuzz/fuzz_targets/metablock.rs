@@ -0,0 +1,12 @@
+#![no_main]
+use ledger::{CustomSerde, MetaBlock, NimbleDigest, Nonce, Nonces};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  if let Ok(metablock) = MetaBlock::from_bytes(data) {
+    assert_eq!(MetaBlock::from_bytes(&metablock.to_bytes()).unwrap(), metablock);
+  }
+  let _ = NimbleDigest::from_bytes(data);
+  let _ = Nonce::from_bytes(data);
+  let _ = Nonces::from_bytes(data);
+});
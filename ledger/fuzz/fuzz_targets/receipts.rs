@@ -0,0 +1,14 @@
+#![no_main]
+use ledger::{CustomSerde, IdSig, MisbehaviorEvidence, Receipt, Receipts};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  if let Ok(receipt) = Receipt::from_bytes(data) {
+    let _ = Receipt::from_bytes(&receipt.to_bytes()).unwrap();
+  }
+  let _ = IdSig::from_bytes(data);
+  let _ = Receipts::from_bytes(data);
+  if let Ok(evidence) = MisbehaviorEvidence::from_bytes(data) {
+    assert_eq!(evidence.to_bytes(), data);
+  }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+use ledger::signature::{PublicKey, PublicKeyTrait, Signature, SignatureTrait};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  let _ = PublicKey::from_bytes(data);
+  let _ = Signature::from_der(data);
+  if let Ok(signature) = Signature::from_bytes(data) {
+    if let Ok(pk) = PublicKey::from_bytes(data) {
+      let _ = signature.verify(&pk, data);
+    }
+  }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+use ledger::{
+  messages::{
+    AppendAttestation, KeyHandoverAttestation, ReadAttestation, ViewChangeAttestation,
+    ViewTailAttestation,
+  },
+  CustomSerde,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  let _ = AppendAttestation::from_bytes(data);
+  let _ = ReadAttestation::from_bytes(data);
+  let _ = ViewChangeAttestation::from_bytes(data);
+  let _ = ViewTailAttestation::from_bytes(data);
+  let _ = KeyHandoverAttestation::from_bytes(data);
+});
@@ -0,0 +1,303 @@
+//! Attestation of endorsers. An endorser that runs in a trusted execution environment proves it
+//! by returning, along with its public key, a report signed by the hardware (an SGX or SEV-SNP
+//! quote) whose report data is the hash of that key. The coordinator checks the report before it
+//! admits the endorser to a view, and keeps it in the view ledger block so that clients can check
+//! it as well.
+use crate::{errors::VerificationError, CustomSerde, EndorserHostnames, KeyHandover, NimbleDigest};
+use std::{collections::HashSet, convert::TryInto};
+
+/// The attestation evidence of endorsers, each given with its public key
+pub type EndorserAttestations = Vec<(Vec<u8>, Vec<u8>)>;
+
+pub trait Attester: Send + Sync {
+  /// Returns evidence that the caller runs in the trusted execution environment, binding
+  /// `report_data` into it
+  fn attest(&self, report_data: &NimbleDigest) -> Result<Vec<u8>, VerificationError>;
+}
+
+pub trait AttestationVerifier: Send + Sync {
+  /// Checks that `evidence` comes from a trusted execution environment and binds `report_data`
+  fn verify(&self, evidence: &[u8], report_data: &NimbleDigest) -> Result<(), VerificationError>;
+}
+
+const MOCK_QUOTE_MAGIC: &[u8] = b"NIMBLE-MOCK-QUOTE";
+
+/// Produces quotes that carry the report data without any hardware signature, for tests and for
+/// deployments without trusted hardware
+pub struct MockAttester;
+
+impl Attester for MockAttester {
+  fn attest(&self, report_data: &NimbleDigest) -> Result<Vec<u8>, VerificationError> {
+    let mut quote = MOCK_QUOTE_MAGIC.to_vec();
+    quote.extend_from_slice(&report_data.to_bytes());
+    Ok(quote)
+  }
+}
+
+/// Accepts the quotes of `MockAttester`
+pub struct MockVerifier;
+
+impl AttestationVerifier for MockVerifier {
+  fn verify(&self, evidence: &[u8], report_data: &NimbleDigest) -> Result<(), VerificationError> {
+    let mut quote = MOCK_QUOTE_MAGIC.to_vec();
+    quote.extend_from_slice(&report_data.to_bytes());
+    if evidence != quote.as_slice() {
+      return Err(VerificationError::InvalidEndorserAttestation);
+    }
+    Ok(())
+  }
+}
+
+/// Returns the attester called `name`; only "mock" is available in this build
+pub fn attester_from_name(name: &str) -> Result<Box<dyn Attester>, VerificationError> {
+  match name {
+    "mock" => Ok(Box::new(MockAttester)),
+    _ => Err(VerificationError::InvalidEndorserAttestation),
+  }
+}
+
+/// Returns the verifier of the attester called `name`
+pub fn verifier_from_name(name: &str) -> Result<Box<dyn AttestationVerifier>, VerificationError> {
+  match name {
+    "mock" => Ok(Box::new(MockVerifier)),
+    _ => Err(VerificationError::InvalidEndorserAttestation),
+  }
+}
+
+/// Returns evidence that binds the endorser's public key `pk`
+pub fn attest_public_key(attester: &dyn Attester, pk: &[u8]) -> Result<Vec<u8>, VerificationError> {
+  attester.attest(&NimbleDigest::digest(pk))
+}
+
+/// Checks that `evidence` binds the endorser's public key `pk`
+pub fn verify_public_key_attestation(
+  verifier: &dyn AttestationVerifier,
+  pk: &[u8],
+  evidence: &[u8],
+) -> Result<(), VerificationError> {
+  verifier.verify(evidence, &NimbleDigest::digest(pk))
+}
+
+/// Serializes the block of the view ledger that lists `endorsers`. Their attestation evidence, if
+/// any, follows the list, so blocks without evidence keep the format that readers already parse.
+pub fn encode_view_config(
+  endorsers: &EndorserHostnames,
+  attestations: &EndorserAttestations,
+) -> Result<Vec<u8>, VerificationError> {
+  let mut config = bincode::serialize(endorsers).map_err(|_e| VerificationError::InvalidConfig)?;
+  if !attestations.is_empty() {
+    let evidence =
+      bincode::serialize(attestations).map_err(|_e| VerificationError::InvalidConfig)?;
+    config.extend_from_slice(&evidence);
+  }
+  Ok(config)
+}
+
+/// Returns the attestation evidence stored in a block of the view ledger
+pub fn retrieve_attestations_from_config(
+  config: &[u8],
+) -> Result<EndorserAttestations, VerificationError> {
+  let endorsers: EndorserHostnames =
+    bincode::deserialize(config).map_err(|_e| VerificationError::InvalidGenesisBlock)?;
+  let len = bincode::serialized_size(&endorsers).map_err(|_e| VerificationError::InvalidConfig)?;
+  let evidence = &config[len as usize..];
+  if evidence.is_empty() {
+    return Ok(EndorserAttestations::new());
+  }
+  bincode::deserialize(evidence).map_err(|_e| VerificationError::InvalidEndorserAttestation)
+}
+
+/// Serializes the block of the view ledger that rotates the keys of the endorsers that signed
+/// `handovers`. The handovers follow the attestation evidence, which is then written even if
+/// there is none, so that readers of the evidence still find it right after the list.
+pub fn encode_view_config_with_handovers(
+  endorsers: &EndorserHostnames,
+  attestations: &EndorserAttestations,
+  handovers: &[KeyHandover],
+) -> Result<Vec<u8>, VerificationError> {
+  let mut config = encode_view_config(endorsers, attestations)?;
+  if handovers.is_empty() {
+    return Ok(config);
+  }
+  if attestations.is_empty() {
+    let evidence =
+      bincode::serialize(attestations).map_err(|_e| VerificationError::InvalidConfig)?;
+    config.extend_from_slice(&evidence);
+  }
+  let handovers = handovers
+    .iter()
+    .map(|handover| handover.to_bytes())
+    .collect::<Vec<Vec<u8>>>();
+  let handovers = bincode::serialize(&handovers).map_err(|_e| VerificationError::InvalidConfig)?;
+  config.extend_from_slice(&handovers);
+  Ok(config)
+}
+
+/// Serializes the block of the view ledger whose endorsers may sign until `lease_expiry` (ms since
+/// epoch), after which they refuse to until a later view renews their lease. The lease follows
+/// the handovers, which are then written even if there are none.
+pub fn encode_view_config_with_lease(
+  endorsers: &EndorserHostnames,
+  attestations: &EndorserAttestations,
+  handovers: &[KeyHandover],
+  lease_expiry: u64,
+) -> Result<Vec<u8>, VerificationError> {
+  let mut config = bincode::serialize(endorsers).map_err(|_e| VerificationError::InvalidConfig)?;
+  let evidence = bincode::serialize(attestations).map_err(|_e| VerificationError::InvalidConfig)?;
+  config.extend_from_slice(&evidence);
+  let handovers = handovers
+    .iter()
+    .map(|handover| handover.to_bytes())
+    .collect::<Vec<Vec<u8>>>();
+  let handovers = bincode::serialize(&handovers).map_err(|_e| VerificationError::InvalidConfig)?;
+  config.extend_from_slice(&handovers);
+  config.extend_from_slice(&lease_expiry.to_le_bytes());
+  Ok(config)
+}
+
+/// Returns when the lease of the endorsers listed in a block of the view ledger expires (ms since
+/// epoch), or None if their lease does not expire
+pub fn retrieve_lease_from_config(config: &[u8]) -> Result<Option<u64>, VerificationError> {
+  let endorsers: EndorserHostnames =
+    bincode::deserialize(config).map_err(|_e| VerificationError::InvalidGenesisBlock)?;
+  let len = bincode::serialized_size(&endorsers).map_err(|_e| VerificationError::InvalidConfig)?;
+  let rest = &config[len as usize..];
+  if rest.is_empty() {
+    return Ok(None);
+  }
+  let attestations: EndorserAttestations =
+    bincode::deserialize(rest).map_err(|_e| VerificationError::InvalidEndorserAttestation)?;
+  let len =
+    bincode::serialized_size(&attestations).map_err(|_e| VerificationError::InvalidConfig)?;
+  let rest = &rest[len as usize..];
+  if rest.is_empty() {
+    return Ok(None);
+  }
+  let handovers: Vec<Vec<u8>> =
+    bincode::deserialize(rest).map_err(|_e| VerificationError::InvalidKeyHandover)?;
+  let len = bincode::serialized_size(&handovers).map_err(|_e| VerificationError::InvalidConfig)?;
+  let rest = &rest[len as usize..];
+  if rest.is_empty() {
+    return Ok(None);
+  }
+  match rest.try_into() {
+    Ok(bytes) => Ok(Some(u64::from_le_bytes(bytes))),
+    Err(_) => Err(VerificationError::InvalidConfig),
+  }
+}
+
+/// Returns the key handovers stored in a block of the view ledger
+pub fn retrieve_key_handovers_from_config(
+  config: &[u8],
+) -> Result<Vec<KeyHandover>, VerificationError> {
+  let endorsers: EndorserHostnames =
+    bincode::deserialize(config).map_err(|_e| VerificationError::InvalidGenesisBlock)?;
+  let len = bincode::serialized_size(&endorsers).map_err(|_e| VerificationError::InvalidConfig)?;
+  let rest = &config[len as usize..];
+  if rest.is_empty() {
+    return Ok(Vec::new());
+  }
+  let attestations: EndorserAttestations =
+    bincode::deserialize(rest).map_err(|_e| VerificationError::InvalidEndorserAttestation)?;
+  let len =
+    bincode::serialized_size(&attestations).map_err(|_e| VerificationError::InvalidConfig)?;
+  let rest = &rest[len as usize..];
+  if rest.is_empty() {
+    return Ok(Vec::new());
+  }
+  let handovers: Vec<Vec<u8>> =
+    bincode::deserialize(rest).map_err(|_e| VerificationError::InvalidKeyHandover)?;
+  handovers
+    .iter()
+    .map(|bytes| KeyHandover::from_bytes(bytes).map_err(|_e| VerificationError::InvalidKeyHandover))
+    .collect()
+}
+
+/// Checks that every endorser listed in a block of the view ledger comes with valid evidence
+pub fn verify_config_attestations(
+  verifier: &dyn AttestationVerifier,
+  config: &[u8],
+) -> Result<(), VerificationError> {
+  let endorsers: EndorserHostnames =
+    bincode::deserialize(config).map_err(|_e| VerificationError::InvalidGenesisBlock)?;
+  let mut attested = HashSet::new();
+  for (pk, evidence) in retrieve_attestations_from_config(config)? {
+    verify_public_key_attestation(verifier, &pk, &evidence)?;
+    attested.insert(pk);
+  }
+  if endorsers.iter().any(|(pk, _uri)| !attested.contains(pk)) {
+    return Err(VerificationError::InvalidEndorserAttestation);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::retrieve_public_keys_from_config;
+  use crate::signature::{PrivateKey, PrivateKeyTrait, PublicKeyTrait};
+
+  #[test]
+  fn test_view_config_attestations() {
+    let pks = (0..2)
+      .map(|_| PrivateKey::new().get_public_key().unwrap().to_bytes())
+      .collect::<Vec<Vec<u8>>>();
+    let endorsers = pks
+      .iter()
+      .map(|pk| (pk.clone(), "http://[::1]:9090".to_string()))
+      .collect::<EndorserHostnames>();
+    let attestations = pks
+      .iter()
+      .map(|pk| (pk.clone(), attest_public_key(&MockAttester, pk).unwrap()))
+      .collect::<EndorserAttestations>();
+
+    // a block without evidence is the plain list of endorsers
+    let config = encode_view_config(&endorsers, &Vec::new()).unwrap();
+    assert_eq!(config, bincode::serialize(&endorsers).unwrap());
+    assert!(retrieve_attestations_from_config(&config)
+      .unwrap()
+      .is_empty());
+    assert!(verify_config_attestations(&MockVerifier, &config).is_err());
+
+    // the evidence does not change the endorsers that readers find in the block
+    let config = encode_view_config(&endorsers, &attestations).unwrap();
+    assert_eq!(retrieve_public_keys_from_config(&config).unwrap().len(), 2);
+    assert_eq!(
+      retrieve_attestations_from_config(&config).unwrap(),
+      attestations
+    );
+    assert!(verify_config_attestations(&MockVerifier, &config).is_ok());
+
+    // evidence must bind the key it comes with, and every endorser needs evidence
+    let swapped = vec![
+      (pks[0].clone(), attestations[1].1.clone()),
+      attestations[1].clone(),
+    ];
+    let config = encode_view_config(&endorsers, &swapped).unwrap();
+    assert!(verify_config_attestations(&MockVerifier, &config).is_err());
+    let config = encode_view_config(&endorsers, &attestations[..1].to_vec()).unwrap();
+    assert!(verify_config_attestations(&MockVerifier, &config).is_err());
+    assert!(attester_from_name("sgx").is_err());
+  }
+
+  #[test]
+  fn test_view_config_lease() {
+    let pk = PrivateKey::new().get_public_key().unwrap().to_bytes();
+    let endorsers = vec![(pk, "http://[::1]:9090".to_string())];
+
+    // blocks written before leases, or without a lease, have none
+    let config = encode_view_config(&endorsers, &Vec::new()).unwrap();
+    assert_eq!(retrieve_lease_from_config(&config).unwrap(), None);
+    let config = encode_view_config_with_handovers(&endorsers, &Vec::new(), &[]).unwrap();
+    assert_eq!(retrieve_lease_from_config(&config).unwrap(), None);
+
+    // the lease does not change what readers find in the block
+    let config = encode_view_config_with_lease(&endorsers, &Vec::new(), &[], 1234).unwrap();
+    assert_eq!(retrieve_lease_from_config(&config).unwrap(), Some(1234));
+    assert_eq!(retrieve_public_keys_from_config(&config).unwrap().len(), 1);
+    assert!(retrieve_key_handovers_from_config(&config)
+      .unwrap()
+      .is_empty());
+    assert!(retrieve_lease_from_config(&config[..config.len() - 1]).is_err());
+  }
+}
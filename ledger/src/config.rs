@@ -0,0 +1,224 @@
+//! Configuration files for the Nimble services. A configuration file is a JSON object that maps
+//! the long names of a service's command-line options to their values, e.g.,
+//! `{"port": 8080, "endorser": ["http://[::1]:9090"], "tls-cert": "/etc/nimble/cert.pem"}`.
+//! The options of the file named by `--config` are added to the command line, which the service
+//! then parses and validates as usual. An option given on the command line overrides the file.
+//!
+//! Strings may refer to environment variables as `${NAME}`, which keeps credentials out of the
+//! file; `$$` stands for a literal `$`. Numbers are passed as they are written, `true` passes a
+//! flag and `false` or `null` omits the option, and arrays are joined with commas for options
+//! that take lists.
+use crate::errors::ConfigError;
+use serde_json::{Map, Value};
+use std::{ffi::OsString, path::Path};
+
+/// the long name of the option that names the configuration file
+pub const CONFIG_OPTION: &str = "config";
+
+/// Returns the command-line arguments for the options in the configuration file `bytes`, looking
+/// up the environment variables that its strings refer to with `env`. The options for which
+/// `is_given` holds are left out; it is asked with the long name of an option with underscores
+/// for its dashes, which is how the services name the options they parse.
+pub fn config_args(
+  bytes: &[u8],
+  env: impl Fn(&str) -> Option<String>,
+  is_given: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, ConfigError> {
+  let options = match serde_json::from_slice::<Map<String, Value>>(bytes) {
+    Ok(options) => options,
+    Err(_) => return Err(ConfigError::InvalidConfigFile),
+  };
+  let mut args = Vec::new();
+  for (name, value) in options {
+    // a file cannot name another file
+    if name.is_empty() || name.starts_with('-') || name == CONFIG_OPTION {
+      return Err(ConfigError::InvalidOptionName);
+    }
+    if is_given(&name.replace('-', "_")) {
+      continue;
+    }
+    let value = match value {
+      Value::Null | Value::Bool(false) => continue,
+      Value::Bool(true) => None,
+      Value::Array(values) => Some(
+        values
+          .iter()
+          .map(|value| scalar(value, &env))
+          .collect::<Result<Vec<String>, ConfigError>>()?
+          .join(","),
+      ),
+      value => Some(scalar(&value, &env)?),
+    };
+    args.push(format!("--{}", name));
+    args.extend(value);
+  }
+  Ok(args)
+}
+
+fn scalar(value: &Value, env: &impl Fn(&str) -> Option<String>) -> Result<String, ConfigError> {
+  match value {
+    Value::String(s) => interpolate(s, env),
+    Value::Number(n) => Ok(n.to_string()),
+    Value::Bool(b) => Ok(b.to_string()),
+    _ => Err(ConfigError::InvalidOptionValue),
+  }
+}
+
+// Replaces `${NAME}` in `s` with the value of the environment variable NAME, and `$$` with `$`
+fn interpolate(s: &str, env: &impl Fn(&str) -> Option<String>) -> Result<String, ConfigError> {
+  let mut out = String::with_capacity(s.len());
+  let mut rest = s;
+  while let Some(start) = rest.find('$') {
+    out.push_str(&rest[..start]);
+    rest = &rest[start + 1..];
+    if let Some(after) = rest.strip_prefix('$') {
+      out.push('$');
+      rest = after;
+    } else if let Some(after) = rest.strip_prefix('{') {
+      let end = after.find('}').ok_or(ConfigError::InvalidInterpolation)?;
+      let name = &after[..end];
+      if name.is_empty() {
+        return Err(ConfigError::InvalidInterpolation);
+      }
+      out.push_str(&env(name).ok_or(ConfigError::UndefinedVariable)?);
+      rest = &after[end + 1..];
+    } else {
+      return Err(ConfigError::InvalidInterpolation);
+    }
+  }
+  out.push_str(rest);
+  Ok(out)
+}
+
+/// Returns `args` with the options of the configuration file that `--config FILE` or
+/// `--config=FILE` in them names, if any, inserted after the name of the binary, except for the
+/// options for which `is_given` holds, as in `config_args`
+pub fn args_with_config_file(
+  args: impl IntoIterator<Item = OsString>,
+  is_given: impl Fn(&str) -> bool,
+) -> Result<Vec<OsString>, ConfigError> {
+  let args = args.into_iter().collect::<Vec<OsString>>();
+  let flag = format!("--{}", CONFIG_OPTION);
+  let mut path = None;
+  for (index, arg) in args.iter().enumerate().skip(1) {
+    let arg = arg.to_string_lossy();
+    if arg == "--" {
+      break;
+    } else if arg == flag {
+      path = Some(
+        args
+          .get(index + 1)
+          .ok_or(ConfigError::MissingConfigFile)?
+          .clone(),
+      );
+    } else if let Some(value) = arg.strip_prefix(&format!("{}=", flag)) {
+      path = Some(OsString::from(value));
+    }
+  }
+  let path = match path {
+    Some(path) => path,
+    None => return Ok(args),
+  };
+
+  let bytes = std::fs::read(Path::new(&path)).map_err(|_e| ConfigError::FailedToReadConfigFile)?;
+  let file_args = config_args(&bytes, |name| std::env::var(name).ok(), is_given)?;
+  let mut merged = Vec::with_capacity(args.len() + file_args.len());
+  merged.extend(args.first().cloned());
+  merged.extend(file_args.into_iter().map(OsString::from));
+  merged.extend(args.into_iter().skip(1));
+  Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_config_args() {
+    let env = |name: &str| match name {
+      "STORE_KEY" => Some("s3cr3t".to_string()),
+      _ => None,
+    };
+    let cases: [(&[u8], &[&str]); 7] = [
+      (br#"{"port": 8080}"#, &["--port", "8080"]),
+      (
+        br#"{"endorser": ["http://[::1]:9090", "http://[::1]:9091"]}"#,
+        &["--endorser", "http://[::1]:9090,http://[::1]:9091"],
+      ),
+      (
+        br#"{"storage_master_key": "${STORE_KEY}"}"#,
+        &["--storage_master_key", "s3cr3t"],
+      ),
+      (br#"{"log-json": true}"#, &["--log-json"]),
+      (br#"{"enable-reflection": false}"#, &[]),
+      (br#"{"tls-ca": null}"#, &[]),
+      (
+        br#"{"advertise-uri": "https://$${HOST}"}"#,
+        &["--advertise-uri", "https://${HOST}"],
+      ),
+    ];
+    for (file, expected) in cases {
+      assert_eq!(config_args(file, env, |_name| false).unwrap(), expected);
+    }
+
+    // the options given on the command line are left out
+    let file = br#"{"tls-cert": "cert.pem", "tls-key": "key.pem"}"#;
+    assert_eq!(
+      config_args(file, env, |name| name == "tls_cert").unwrap(),
+      vec!["--tls-key", "key.pem"]
+    );
+
+    assert_eq!(
+      config_args(br#"{"key": "${MISSING}"}"#, env, |_name| false),
+      Err(ConfigError::UndefinedVariable)
+    );
+    assert_eq!(
+      config_args(br#"{"key": "${STORE_KEY"}"#, env, |_name| false),
+      Err(ConfigError::InvalidInterpolation)
+    );
+    assert_eq!(
+      config_args(br#"{"key": {"nested": 1}}"#, env, |_name| false),
+      Err(ConfigError::InvalidOptionValue)
+    );
+    assert_eq!(
+      config_args(br#"{"config": "other.json"}"#, env, |_name| false),
+      Err(ConfigError::InvalidOptionName)
+    );
+    assert_eq!(
+      config_args(br#"["--port", "8080"]"#, env, |_name| false),
+      Err(ConfigError::InvalidConfigFile)
+    );
+  }
+
+  #[test]
+  fn test_args_with_config_file() {
+    let path = std::env::temp_dir().join(format!("nimble-config-{}.json", std::process::id()));
+    std::fs::write(&path, br#"{"port": 8080}"#).unwrap();
+    let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<OsString>>();
+
+    let config = format!("--config={}", path.display());
+    assert_eq!(
+      args_with_config_file(args(&["endorser", &config, "-t", "::"]), |_name| false).unwrap(),
+      args(&["endorser", "--port", "8080", &config, "-t", "::"])
+    );
+    assert_eq!(
+      args_with_config_file(args(&["endorser", &config, "-p", "9090"]), |name| name
+        == "port")
+      .unwrap(),
+      args(&["endorser", &config, "-p", "9090"])
+    );
+    assert_eq!(
+      args_with_config_file(args(&["endorser", "-p", "9090"]), |_name| false).unwrap(),
+      args(&["endorser", "-p", "9090"])
+    );
+    assert_eq!(
+      args_with_config_file(args(&["endorser", "--config"]), |_name| false),
+      Err(ConfigError::MissingConfigFile)
+    );
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(
+      args_with_config_file(args(&["endorser", &config]), |_name| false),
+      Err(ConfigError::FailedToReadConfigFile)
+    );
+  }
+}
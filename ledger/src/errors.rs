@@ -56,4 +56,70 @@ pub enum VerificationError {
   InsufficentEndorsers,
   /// returned if the ledger tail maps are inconsistent
   InconsistentLedgerTailMaps,
+  /// returned if the endorsement policy in a genesis block is malformed
+  InvalidEndorsementPolicy,
+  /// returned if the receipts do not satisfy the ledger's endorsement policy
+  EndorsementPolicyNotSatisfied,
+  /// returned if the access policy in a genesis block is malformed
+  InvalidAccessPolicy,
+  /// returned if a request is not permitted by the ledger's access policy
+  AccessDenied,
+  /// returned if an inclusion proof does not link the entry to the tail it comes with
+  InvalidInclusionProof,
+  /// returned if a checkpoint proof does not link the entry to the checkpoint it comes with
+  InvalidCheckpointProof,
+  /// returned if a block that starts like an envelope is not a canonically encoded envelope
+  InvalidBlockEnvelope,
+  /// returned if a block is larger than the deployment allows
+  BlockTooLarge,
+  /// returned if a block is not an envelope of a content type that the deployment allows
+  ContentTypeNotAllowed,
+  /// returned if a block is not a well-formed reference to a blob
+  InvalidBlobReference,
+  /// returned if a block is not a well-formed hash of an external payload
+  InvalidExternalBlock,
+  /// returned if a key handover is not signed by an endorser of its view, or a view change does
+  /// not replace exactly the handed-over keys
+  InvalidKeyHandover,
+  /// returned if misbehavior evidence does not hold two conflicting statements signed by one
+  /// endorser of their views
+  InvalidMisbehaviorEvidence,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SecretError {
+  /// returned if the provider does not hold the requested secret
+  NotFound,
+  /// returned if the secret provider uri is not supported
+  InvalidProvider,
+  /// returned if the secret exists but cannot be read
+  FailedToRead,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+  /// returned if `--config` is not followed by the path of a file
+  MissingConfigFile,
+  /// returned if the configuration file cannot be read
+  FailedToReadConfigFile,
+  /// returned if the configuration file is not a JSON object
+  InvalidConfigFile,
+  /// returned if a key of the configuration file is not the long name of an option
+  InvalidOptionName,
+  /// returned if the value of an option is an object or an array that holds one
+  InvalidOptionValue,
+  /// returned if a `$` in a string starts neither `${NAME}` nor `$$`
+  InvalidInterpolation,
+  /// returned if a string refers to an environment variable that is not set
+  UndefinedVariable,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LoggingError {
+  /// returned if the log level is not one of error, warn, info, debug, or trace
+  InvalidLogLevel,
+  /// returned if a subscriber was already installed
+  AlreadyInitialized,
+  /// returned if the OTLP endpoint is not an http:// URL
+  InvalidOtlpEndpoint,
 }
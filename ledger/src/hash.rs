@@ -0,0 +1,118 @@
+//! The hash functions behind `NimbleDigest`. Every digest in the system (block and metablock
+//! hashes, ledger handles, view identities, and the messages that endorsers sign) comes from the
+//! algorithm selected when the ledger crate is built: SHA-256 by default, or SHA3-256 with the
+//! `sha3` feature. Both produce 32-byte digests, so the wire formats do not change, but the
+//! coordinator, endorsers, and clients of a deployment must be built with the same algorithm.
+use generic_array::{typenum::U32, GenericArray};
+
+/// The output of a hash function
+pub type HashOutput = GenericArray<u8, U32>;
+
+pub trait HashAlgorithm {
+  /// the name of the algorithm, for logs and diagnostics
+  const NAME: &'static str;
+
+  fn new() -> Self;
+
+  /// absorbs `bytes` into the hash
+  fn update(&mut self, bytes: &[u8]);
+
+  fn finalize(self) -> HashOutput;
+
+  /// hashes `bytes` in one call
+  fn digest(bytes: &[u8]) -> HashOutput
+  where
+    Self: Sized,
+  {
+    let mut hasher = Self::new();
+    hasher.update(bytes);
+    hasher.finalize()
+  }
+}
+
+/// The algorithm that this build uses
+#[cfg(not(feature = "sha3"))]
+pub type NimbleHasher = Sha256Hasher;
+#[cfg(feature = "sha3")]
+pub type NimbleHasher = Sha3Hasher;
+
+pub struct Sha256Hasher(sha2::Sha256);
+
+impl HashAlgorithm for Sha256Hasher {
+  const NAME: &'static str = "SHA-256";
+
+  fn new() -> Self {
+    Sha256Hasher(<sha2::Sha256 as sha2::Digest>::new())
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    sha2::Digest::update(&mut self.0, bytes);
+  }
+
+  fn finalize(self) -> HashOutput {
+    sha2::Digest::finalize(self.0)
+  }
+}
+
+/// SHA3-256 as implemented by OpenSSL, which the crate already links for signatures.
+///
+/// The OpenSSL calls return `Result`, but they cannot fail here: the crate builds the vendored
+/// OpenSSL, which always includes SHA3-256, and `Hasher` tracks its own state, so the remaining
+/// failure is an allocation failure, which aborts elsewhere in Rust anyway. The trait therefore
+/// stays infallible, like the SHA-256 implementation, and the calls `expect` with the reason.
+pub struct Sha3Hasher(openssl::hash::Hasher);
+
+impl HashAlgorithm for Sha3Hasher {
+  const NAME: &'static str = "SHA3-256";
+
+  fn new() -> Self {
+    Sha3Hasher(
+      openssl::hash::Hasher::new(openssl::hash::MessageDigest::sha3_256())
+        .expect("vendored OpenSSL provides SHA3-256"),
+    )
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    self
+      .0
+      .update(bytes)
+      .expect("a SHA3-256 update only fails to allocate");
+  }
+
+  fn finalize(mut self) -> HashOutput {
+    let bytes = self
+      .0
+      .finish()
+      .expect("a SHA3-256 finish only fails to allocate");
+    *GenericArray::from_slice(&bytes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn hex_digest<H: HashAlgorithm>(bytes: &[u8]) -> String {
+    hex::encode(H::digest(bytes))
+  }
+
+  #[test]
+  fn test_hash_algorithms() {
+    assert_eq!(
+      hex_digest::<Sha256Hasher>(b""),
+      "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+      hex_digest::<Sha3Hasher>(b""),
+      "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+    );
+
+    // inputs hash the same whether they arrive at once or in pieces
+    let input = (0..5000).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+    let mut hasher = Sha3Hasher::new();
+    for piece in input.chunks(700) {
+      hasher.update(piece);
+    }
+    assert_eq!(hasher.finalize(), Sha3Hasher::digest(&input));
+  }
+}
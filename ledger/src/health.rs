@@ -0,0 +1,179 @@
+//! The standard gRPC health checking service (`grpc.health.v1.Health`), which load balancers and
+//! orchestrators such as Kubernetes probe to tell a server that is merely listening from one that
+//! is ready to serve requests. A service reports a status for each of the gRPC services it names;
+//! the empty name stands for the server as a whole. The statuses are set through a
+//! `HealthReporter` as the server's readiness changes, and `Watch` streams each change.
+use health_proto::{health_server::Health, HealthCheckRequest, HealthCheckResponse};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+pub mod health_proto {
+  tonic::include_proto!("grpc.health.v1");
+}
+
+pub use health_proto::{health_check_response::ServingStatus, health_server::HealthServer};
+
+/// The name under which a server reports its overall status
+pub const SERVER_STATUS: &str = "";
+
+const WATCH_CHANNEL_BUFFER: usize = 4;
+
+type Statuses = HashMap<String, ServingStatus>;
+
+/// Sets the statuses that the `HealthService` it was created with reports
+#[derive(Clone)]
+pub struct HealthReporter {
+  statuses: Arc<watch::Sender<Statuses>>,
+}
+
+/// Serves the statuses set by a `HealthReporter`; its clones serve the same statuses, so that a
+/// server that listens on several ports can answer probes on each
+#[derive(Clone)]
+pub struct HealthService {
+  statuses: watch::Receiver<Statuses>,
+}
+
+impl HealthReporter {
+  /// Returns a reporter and the health service it reports to, with every service in `services`
+  /// and the server itself reported as not serving
+  pub fn new(services: &[&str]) -> (Self, HealthService) {
+    let statuses = std::iter::once(SERVER_STATUS)
+      .chain(services.iter().copied())
+      .map(|service| (service.to_string(), ServingStatus::NotServing))
+      .collect::<Statuses>();
+    let (tx, rx) = watch::channel(statuses);
+    let reporter = HealthReporter {
+      statuses: Arc::new(tx),
+    };
+    (reporter, HealthService { statuses: rx })
+  }
+
+  pub fn set_status(&self, service: &str, status: ServingStatus) {
+    self
+      .statuses
+      .send_if_modified(|statuses| statuses.insert(service.to_string(), status) != Some(status));
+  }
+
+  /// Reports every service, and the server itself, as serving if `serving` is set and as not
+  /// serving otherwise
+  pub fn set_all(&self, serving: bool) {
+    let status = if serving {
+      ServingStatus::Serving
+    } else {
+      ServingStatus::NotServing
+    };
+    self.statuses.send_if_modified(|statuses| {
+      let mut modified = false;
+      for current in statuses.values_mut() {
+        modified |= *current != status;
+        *current = status;
+      }
+      modified
+    });
+  }
+
+  pub fn get_status(&self, service: &str) -> Option<ServingStatus> {
+    self.statuses.borrow().get(service).copied()
+  }
+}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+  async fn check(
+    &self,
+    request: Request<HealthCheckRequest>,
+  ) -> Result<Response<HealthCheckResponse>, Status> {
+    let HealthCheckRequest { service } = request.into_inner();
+    let status = self.statuses.borrow().get(&service).copied();
+    match status {
+      Some(status) => Ok(Response::new(HealthCheckResponse {
+        status: status as i32,
+      })),
+      None => Err(Status::not_found(format!("Unknown service {}", service))),
+    }
+  }
+
+  type WatchStream = ReceiverStream<Result<HealthCheckResponse, Status>>;
+
+  async fn watch(
+    &self,
+    request: Request<HealthCheckRequest>,
+  ) -> Result<Response<Self::WatchStream>, Status> {
+    let HealthCheckRequest { service } = request.into_inner();
+    let mut statuses = self.statuses.clone();
+    let (tx, rx) = mpsc::channel(WATCH_CHANNEL_BUFFER);
+    tokio::spawn(async move {
+      let mut last = None;
+      loop {
+        // an unknown service is reported as such, and may still become known later
+        let status = statuses
+          .borrow_and_update()
+          .get(&service)
+          .copied()
+          .unwrap_or(ServingStatus::ServiceUnknown);
+        if last != Some(status) {
+          let res = Ok(HealthCheckResponse {
+            status: status as i32,
+          });
+          if tx.send(res).await.is_err() {
+            break;
+          }
+          last = Some(status);
+        }
+        if statuses.changed().await.is_err() {
+          break;
+        }
+      }
+    });
+
+    Ok(Response::new(ReceiverStream::new(rx)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tokio_stream::StreamExt;
+
+  #[tokio::test]
+  async fn test_health_service() {
+    let (reporter, service) = HealthReporter::new(&["nimble.Test"]);
+    let check = |service_name: &str| {
+      service.check(Request::new(HealthCheckRequest {
+        service: service_name.to_string(),
+      }))
+    };
+    let not_serving = ServingStatus::NotServing as i32;
+    assert_eq!(check("").await.unwrap().into_inner().status, not_serving);
+    assert_eq!(
+      check("nimble.Test").await.unwrap().into_inner().status,
+      not_serving
+    );
+    assert_eq!(
+      check("nimble.Other").await.unwrap_err().code(),
+      tonic::Code::NotFound
+    );
+
+    let mut updates = service
+      .watch(Request::new(HealthCheckRequest {
+        service: "nimble.Test".to_string(),
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(updates.next().await.unwrap().unwrap().status, not_serving);
+
+    reporter.set_all(true);
+    assert_eq!(reporter.get_status(""), Some(ServingStatus::Serving));
+    assert_eq!(
+      updates.next().await.unwrap().unwrap().status,
+      ServingStatus::Serving as i32
+    );
+    reporter.set_status("nimble.Test", ServingStatus::NotServing);
+    assert_eq!(updates.next().await.unwrap().unwrap().status, not_serving);
+    assert_eq!(reporter.get_status(""), Some(ServingStatus::Serving));
+  }
+}
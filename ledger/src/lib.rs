@@ -1,16 +1,39 @@
+pub mod attestation;
+pub mod config;
 pub mod errors;
+pub mod hash;
+pub mod health;
+pub mod logging;
+pub mod messages;
+pub mod namespace;
+pub mod reflection;
+pub mod secrets;
+pub mod serde;
+pub mod shutdown;
 pub mod signature;
-use crate::signature::{PublicKey, PublicKeyTrait, Signature, SignatureTrait};
-use digest::Output;
+pub mod telemetry;
+pub mod zeroize;
+pub use crate::serde::{CustomSerde, CustomSerdeError};
+use crate::{
+  hash::{HashAlgorithm, HashOutput, NimbleHasher},
+  messages::{
+    AppendAttestation, KeyHandoverAttestation, ReadAttestation, SignedStatement,
+    ViewChangeAttestation, ViewTailAttestation,
+  },
+  serde::Reader,
+  signature::{PublicKey, PublicKeyTrait, Signature, SignatureScheme, SignatureTrait},
+  zeroize::Zeroize,
+};
 use errors::VerificationError;
-use generic_array::{typenum::U32, GenericArray};
+use generic_array::GenericArray;
+use rand::{rngs::OsRng, RngCore};
 use rayon::prelude::*;
-use sha2::{Digest, Sha256};
 use std::{
   cmp::Ordering,
   collections::{hash_map, HashMap, HashSet},
   convert::TryInto,
 };
+use subtle::ConstantTimeEq;
 
 #[allow(clippy::derive_partial_eq_without_eq)]
 pub mod endorser_proto {
@@ -22,16 +45,21 @@ use endorser_proto::{LedgerChunkEntry, LedgerTailMap, LedgerTailMapEntry};
 /// A cryptographic digest
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Copy, Ord, PartialOrd)]
 pub struct NimbleDigest {
-  digest: Output<Sha256>,
+  digest: HashOutput,
 }
 
 impl NimbleDigest {
-  pub fn new(d: Output<Sha256>) -> Self {
+  pub fn new(d: HashOutput) -> Self {
     NimbleDigest { digest: d }
   }
 
   pub fn num_bytes() -> usize {
-    <Sha256 as Digest>::output_size()
+    HashOutput::default().len()
+  }
+
+  /// the name of the hash function that this build uses
+  pub fn algorithm() -> &'static str {
+    NimbleHasher::NAME
   }
 
   pub fn to_bytes(self) -> Vec<u8> {
@@ -43,7 +71,7 @@ impl NimbleDigest {
     if bytes.len() != digest_len {
       Err(CustomSerdeError::IncorrectLength)
     } else {
-      let digest = GenericArray::<u8, U32>::from_slice(&bytes[0..digest_len]);
+      let digest = GenericArray::from_slice(&bytes[0..digest_len]);
       Ok(NimbleDigest { digest: *digest })
     }
   }
@@ -53,7 +81,7 @@ impl NimbleDigest {
       NimbleDigest::default()
     } else {
       NimbleDigest {
-        digest: Sha256::digest(bytes),
+        digest: NimbleHasher::digest(bytes),
       }
     }
   }
@@ -72,25 +100,24 @@ impl NimbleDigest {
 pub type Handle = NimbleDigest;
 
 // this function assumes the provided vector is sorted by handles
-pub fn produce_hash_of_state(ledger_tail_map: &Vec<LedgerTailMapEntry>) -> NimbleDigest {
+pub fn produce_hash_of_state(ledger_tail_map: &[LedgerTailMapEntry]) -> NimbleDigest {
   // for empty state, hash is a vector of zeros
   if ledger_tail_map.is_empty() {
     NimbleDigest::default()
   } else {
     let hash_inner = |ledger_tail_map_slice: &[LedgerTailMapEntry]| -> NimbleDigest {
-      let mut sha256 = Sha256::new();
+      let mut hasher = NimbleHasher::new();
       for entry in ledger_tail_map_slice {
-        sha256.update(&entry.handle);
-        sha256.update(&entry.metablock);
+        hasher.update(&entry.handle);
+        hasher.update(MetaBlock::body_of(&entry.metablock));
       }
-      NimbleDigest::new(sha256.finalize())
+      NimbleDigest::new(hasher.finalize())
     };
 
     let num_leaves = 32;
     // we ceil the slice size so the last slice contains fewer entries.
     let slice_size = (ledger_tail_map.len() as f64 / num_leaves as f64).ceil() as usize;
     let leaf_hashes = (0..num_leaves)
-      .into_iter()
       .collect::<Vec<usize>>()
       .par_iter()
       .map(|&i| {
@@ -108,16 +135,16 @@ pub fn produce_hash_of_state(ledger_tail_map: &Vec<LedgerTailMapEntry>) -> Nimbl
       })
       .collect::<Vec<NimbleDigest>>();
 
-    let mut sha256 = Sha256::new();
+    let mut hasher = NimbleHasher::new();
     for entry in leaf_hashes {
-      sha256.update(&entry.to_bytes());
+      hasher.update(&entry.to_bytes());
     }
-    NimbleDigest::new(sha256.finalize())
+    NimbleDigest::new(hasher.finalize())
   }
 }
 
 /// A cryptographic Nonce
-#[derive(Clone, Debug, Copy, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, Default, Eq)]
 pub struct Nonce {
   data: [u8; 16],
 }
@@ -128,16 +155,36 @@ impl Nonce {
       Err(CustomSerdeError::IncorrectLength)
     } else {
       Ok(Nonce {
-        data: nonce.try_into().unwrap(),
+        data: Reader::new("Nonce", nonce).take_array()?,
       })
     }
   }
 
+  /// a nonce drawn from the operating system's CSPRNG, which no one can predict
+  pub fn random() -> Nonce {
+    let mut data = [0u8; 16];
+    OsRng.fill_bytes(&mut data);
+    Nonce { data }
+  }
+
   pub fn num_bytes() -> usize {
     16
   }
 }
 
+// in constant time, so that the time to reject a guess does not tell how much of it was right
+impl PartialEq for Nonce {
+  fn eq(&self, other: &Self) -> bool {
+    self.data.ct_eq(&other.data).into()
+  }
+}
+
+impl Zeroize for Nonce {
+  fn zeroize(&mut self) {
+    self.data.zeroize();
+  }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Nonces {
   nonces: Vec<Nonce>,
@@ -161,7 +208,7 @@ impl Nonces {
   }
 
   pub fn contains(&self, nonce: &Nonce) -> bool {
-    self.nonces.iter().any(|nonce_iter| *nonce_iter == *nonce)
+    self.nonces.contains(nonce)
   }
 
   pub fn len(&self) -> usize {
@@ -195,14 +242,32 @@ impl Block {
   }
 }
 
+/// The version of the encoding of metablocks, which leads `MetaBlock::to_bytes`
+pub const METABLOCK_VERSION: u8 = 1;
+
+/// The version of the encoding of metablocks that carry an attested timestamp, which follows the
+/// fields of `METABLOCK_VERSION` as a little-endian u64
+pub const TIMESTAMPED_METABLOCK_VERSION: u8 = 2;
+
+/// The fields of a `MetaBlock`, for callers that take a metablock apart or put one together
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MetaBlockFields {
+  pub prev: NimbleDigest,
+  pub block_hash: NimbleDigest,
+  pub height: usize,
+  pub timestamp: Option<u64>, // ms since epoch
+}
+
 /// `MetaBlock` has three entries: (i) hash of the previous metadata,
 /// (ii) a hash of the current block, and (iii) a counter denoting the height
-/// of the current block in the ledger
+/// of the current block in the ledger. An appended entry may also carry the time at which it was
+/// appended, which its endorsers attested to.
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct MetaBlock {
   prev: NimbleDigest,
   block_hash: NimbleDigest,
   height: usize,
+  timestamp: Option<u64>, // ms since epoch
 }
 
 impl MetaBlock {
@@ -211,11 +276,117 @@ impl MetaBlock {
       prev: *prev,
       block_hash: *block_hash,
       height,
+      timestamp: None,
+    }
+  }
+
+  /// Returns the metablock with `timestamp` (ms since epoch) as the time of the append. The
+  /// timestamp is not part of `hash`, which keeps the hash chain of a ledger checkable from its
+  /// block hashes alone, but the endorsers sign it with the hash in `AppendAttestation`.
+  pub fn with_timestamp(mut self, timestamp: Option<u64>) -> Self {
+    self.timestamp = timestamp;
+    self
+  }
+
+  pub fn with_prev(mut self, prev: &NimbleDigest) -> Self {
+    self.prev = *prev;
+    self
+  }
+
+  pub fn with_block_hash(mut self, block_hash: &NimbleDigest) -> Self {
+    self.block_hash = *block_hash;
+    self
+  }
+
+  pub fn with_height(mut self, height: usize) -> Self {
+    self.height = height;
+    self
+  }
+
+  /// Returns the metablock of the entry that appends `block_hash` after this one, or None if the
+  /// height would overflow
+  pub fn next(&self, block_hash: &NimbleDigest) -> Option<MetaBlock> {
+    let height = self.height.checked_add(1)?;
+    Some(MetaBlock::new(&self.hash(), block_hash, height))
+  }
+
+  pub fn to_fields(&self) -> MetaBlockFields {
+    MetaBlockFields {
+      prev: self.prev,
+      block_hash: self.block_hash,
+      height: self.height,
+      timestamp: self.timestamp,
     }
   }
 
   pub fn num_bytes() -> usize {
-    NimbleDigest::num_bytes() * 2 + 0_u64.to_le_bytes().to_vec().len()
+    1 + MetaBlock::num_body_bytes()
+  }
+
+  /// the length of the encoding of a metablock with a timestamp
+  pub fn num_timestamped_bytes() -> usize {
+    MetaBlock::num_bytes() + std::mem::size_of::<u64>()
+  }
+
+  /// the length of the unversioned encoding that metablocks had before `METABLOCK_VERSION`, which
+  /// `from_bytes` still accepts so that stored metablocks and receipts remain readable
+  pub fn num_legacy_bytes() -> usize {
+    MetaBlock::num_body_bytes()
+  }
+
+  fn num_body_bytes() -> usize {
+    NimbleDigest::num_bytes() * 2 + std::mem::size_of::<u64>()
+  }
+
+  // The fields in a fixed layout: prev, block hash, and the height as a little-endian u64. The
+  // hash of a metablock covers only these, so it is the same in every version of the encoding.
+  fn body_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MetaBlock::num_body_bytes());
+    bytes.extend(&self.prev.to_bytes());
+    bytes.extend(&self.block_hash.to_bytes());
+    bytes.extend(&(self.height as u64).to_le_bytes());
+    bytes
+  }
+
+  // the body of an encoded metablock, in any encoding
+  fn body_of(bytes: &[u8]) -> &[u8] {
+    if bytes.len() == MetaBlock::num_bytes() && bytes[0] == METABLOCK_VERSION {
+      &bytes[1..]
+    } else if bytes.len() == MetaBlock::num_timestamped_bytes()
+      && bytes[0] == TIMESTAMPED_METABLOCK_VERSION
+    {
+      &bytes[1..MetaBlock::num_bytes()]
+    } else {
+      bytes
+    }
+  }
+
+  /// the hash of an encoded metablock, which equals `MetaBlock::hash` of the decoded metablock
+  /// whichever encoding the bytes have
+  pub fn hash_of_bytes(bytes: &[u8]) -> NimbleDigest {
+    NimbleDigest::digest(MetaBlock::body_of(bytes))
+  }
+
+  fn from_body_bytes(bytes: &[u8]) -> Result<MetaBlock, CustomSerdeError> {
+    let digest_len = NimbleDigest::num_bytes();
+    if bytes.len() != MetaBlock::num_body_bytes() {
+      return Err(CustomSerdeError::IncorrectLength);
+    }
+    let mut reader = Reader::new("MetaBlock", bytes);
+    let prev = NimbleDigest::from_bytes(reader.take(digest_len)?)?;
+    let block_hash = NimbleDigest::from_bytes(reader.take(digest_len)?)?;
+    // a height that does not fit the platform's usize is rejected rather than truncated
+    let height: usize = reader
+      .read_u64()?
+      .try_into()
+      .map_err(|_| CustomSerdeError::InternalError)?;
+    reader.finish()?;
+    Ok(MetaBlock {
+      prev,
+      block_hash,
+      height,
+      timestamp: None,
+    })
   }
 
   pub fn genesis(block_hash: &NimbleDigest) -> Self {
@@ -223,6 +394,7 @@ impl MetaBlock {
       prev: NimbleDigest::default(),
       block_hash: *block_hash,
       height: 0usize,
+      timestamp: None,
     }
   }
 
@@ -237,6 +409,17 @@ impl MetaBlock {
   pub fn get_block_hash(&self) -> &NimbleDigest {
     &self.block_hash
   }
+
+  /// the time (ms since epoch) at which the entry was appended, if its endorsers attested to one
+  pub fn get_timestamp(&self) -> Option<u64> {
+    self.timestamp
+  }
+}
+
+impl From<MetaBlockFields> for MetaBlock {
+  fn from(fields: MetaBlockFields) -> Self {
+    MetaBlock::new(&fields.prev, &fields.block_hash, fields.height).with_timestamp(fields.timestamp)
+  }
 }
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone)]
@@ -286,6 +469,13 @@ impl IdSig {
     &self.id
   }
 
+  /// returns the signature scheme of the endorser that produced the signature
+  pub fn get_scheme(&self) -> Result<SignatureScheme, VerificationError> {
+    Signature::from_bytes(&self.sig)
+      .map(|sig| sig.get_scheme())
+      .map_err(|_| VerificationError::InvalidSignature)
+  }
+
   pub fn verify(&self, message: &[u8]) -> Result<(), VerificationError> {
     let id = PublicKey::from_bytes(&self.id).map_err(|_| VerificationError::InvalidPublicKey)?;
     let sig = Signature::from_bytes(&self.sig).map_err(|_| VerificationError::InvalidSignature)?;
@@ -294,6 +484,18 @@ impl IdSig {
       .map_err(|_| VerificationError::InvalidSignature)
   }
 
+  /// Checks the signature of each of `id_sigs` on `message`. With the `parallel` feature, the
+  /// signatures are checked on rayon's thread pool, which pays off for receipts from large quorums
+  pub fn verify_all(id_sigs: &[IdSig], message: &[u8]) -> Result<(), VerificationError> {
+    if cfg!(feature = "parallel") {
+      id_sigs
+        .par_iter()
+        .try_for_each(|id_sig| id_sig.verify(message))
+    } else {
+      id_sigs.iter().try_for_each(|id_sig| id_sig.verify(message))
+    }
+  }
+
   pub fn verify_with_id(&self, id: &PublicKey, message: &[u8]) -> Result<(), VerificationError> {
     let sig = Signature::from_bytes(&self.sig).map_err(|_| VerificationError::InvalidSignature)?;
     sig
@@ -304,6 +506,12 @@ impl IdSig {
   pub fn num_bytes() -> usize {
     PublicKey::num_bytes() + Signature::num_bytes()
   }
+
+  /// the length of an untagged P-256 key and signature, the layout of receipts written before keys
+  /// and signatures carried their scheme, which `from_bytes` still accepts
+  pub fn num_untagged_bytes() -> usize {
+    PublicKey::num_untagged_bytes() + Signature::num_untagged_bytes()
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -353,6 +561,37 @@ impl Receipt {
   pub fn num_bytes() -> usize {
     NimbleDigest::num_bytes() + MetaBlock::num_bytes() + IdSig::num_bytes()
   }
+
+  /// the length of a receipt in the layout from before metablocks were versioned and keys and
+  /// signatures carried their scheme, which stored receipts and the openenclave endorser still use
+  pub fn num_legacy_bytes() -> usize {
+    NimbleDigest::num_bytes() + MetaBlock::num_legacy_bytes() + IdSig::num_untagged_bytes()
+  }
+
+  /// the length of a receipt whose metablock has a timestamp
+  pub fn num_timestamped_bytes() -> usize {
+    NimbleDigest::num_bytes() + MetaBlock::num_timestamped_bytes() + IdSig::num_bytes()
+  }
+
+  // The lengths of the metablock and the key and signature in every layout of a receipt, current
+  // layout first. Each layout has a length of its own, so the length of a receipt tells its layout.
+  fn layouts() -> Vec<(usize, usize)> {
+    let mut layouts = Vec::new();
+    for metablock_len in [
+      MetaBlock::num_bytes(),
+      MetaBlock::num_timestamped_bytes(),
+      MetaBlock::num_legacy_bytes(),
+    ] {
+      for id_sig_len in [IdSig::num_bytes(), IdSig::num_untagged_bytes()] {
+        layouts.push((metablock_len, id_sig_len));
+      }
+    }
+    layouts
+  }
+
+  fn len_of_layout((metablock_len, id_sig_len): (usize, usize)) -> usize {
+    NimbleDigest::num_bytes() + metablock_len + id_sig_len
+  }
 }
 
 const MIN_NUM_ENDORSERS: usize = 1;
@@ -364,6 +603,416 @@ pub fn compute_aggregated_block_hash(
   NimbleDigest::digest(hash_block_bytes).digest_with_bytes(hash_nonces_bytes)
 }
 
+/// Links an entry of a ledger to the ledger's tail, so that a client can check an entry read by
+/// its index against endorsers: the proof carries the metablock of the entry, the block hashes of
+/// the entries after it, and a read of the tail for a nonce that the client chose. The client
+/// verifies the tail read as any other, then recomputes the metablocks from the entry to the tail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+  metablock: MetaBlock,
+  block_hashes: Vec<NimbleDigest>,
+  tail_block: Vec<u8>,
+  tail_nonces: Vec<u8>,
+  tail_receipts: Vec<u8>,
+}
+
+impl InclusionProof {
+  pub fn new(
+    metablock: MetaBlock,
+    block_hashes: Vec<NimbleDigest>,
+    tail_block: Vec<u8>,
+    tail_nonces: Vec<u8>,
+    tail_receipts: Vec<u8>,
+  ) -> Self {
+    InclusionProof {
+      metablock,
+      block_hashes,
+      tail_block,
+      tail_nonces,
+      tail_receipts,
+    }
+  }
+
+  pub fn get_metablock(&self) -> &MetaBlock {
+    &self.metablock
+  }
+
+  pub fn get_block_hashes(&self) -> &[NimbleDigest] {
+    &self.block_hashes
+  }
+
+  pub fn get_tail_block(&self) -> &[u8] {
+    &self.tail_block
+  }
+
+  pub fn get_tail_nonces(&self) -> &[u8] {
+    &self.tail_nonces
+  }
+
+  pub fn get_tail_receipts(&self) -> &[u8] {
+    &self.tail_receipts
+  }
+
+  /// checks that the proof starts at the entry with `block_bytes` and `nonces_bytes` at `index`,
+  /// and returns the metablock of the tail that the entry leads to
+  pub fn verify_chain(
+    &self,
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    index: usize,
+  ) -> Result<MetaBlock, VerificationError> {
+    let block_hash = compute_aggregated_block_hash(
+      &NimbleDigest::digest(block_bytes).to_bytes(),
+      &NimbleDigest::digest(nonces_bytes).to_bytes(),
+    );
+    if self.metablock.get_height() != index || *self.metablock.get_block_hash() != block_hash {
+      return Err(VerificationError::InvalidInclusionProof);
+    }
+
+    let mut metablock = self.metablock.clone();
+    for block_hash in &self.block_hashes {
+      let height = metablock.get_height().checked_add(1);
+      if height.is_none() {
+        return Err(VerificationError::InvalidInclusionProof);
+      }
+      metablock = MetaBlock::new(&metablock.hash(), block_hash, height.unwrap());
+    }
+    Ok(metablock)
+  }
+}
+
+/// Links an entry of a ledger whose receipts were compacted away to the nearest checkpoint before
+/// it, an entry that kept its receipts: the proof carries the checkpoint and the block hashes of
+/// the entries between the two. The client verifies the checkpoint's receipts as those of any
+/// other entry, then recomputes the metablocks from the checkpoint to the entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointProof {
+  index: usize,
+  block: Vec<u8>,
+  nonces: Vec<u8>,
+  receipts: Vec<u8>,
+  block_hashes: Vec<NimbleDigest>,
+}
+
+impl CheckpointProof {
+  pub fn new(
+    index: usize,
+    block: Vec<u8>,
+    nonces: Vec<u8>,
+    receipts: Vec<u8>,
+    block_hashes: Vec<NimbleDigest>,
+  ) -> Self {
+    CheckpointProof {
+      index,
+      block,
+      nonces,
+      receipts,
+      block_hashes,
+    }
+  }
+
+  pub fn get_index(&self) -> usize {
+    self.index
+  }
+
+  pub fn get_block(&self) -> &[u8] {
+    &self.block
+  }
+
+  pub fn get_nonces(&self) -> &[u8] {
+    &self.nonces
+  }
+
+  pub fn get_receipts(&self) -> &[u8] {
+    &self.receipts
+  }
+
+  pub fn get_block_hashes(&self) -> &[NimbleDigest] {
+    &self.block_hashes
+  }
+
+  /// checks that the proof leads from `checkpoint`, the endorsed metablock of the checkpoint, to
+  /// the entry with `block_bytes` and `nonces_bytes` at `index`, and returns the entry's metablock
+  pub fn verify_chain(
+    &self,
+    checkpoint: &MetaBlock,
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    index: usize,
+  ) -> Result<MetaBlock, VerificationError> {
+    if checkpoint.get_height() != self.index
+      || self.index.checked_add(self.block_hashes.len()) != Some(index)
+    {
+      return Err(VerificationError::InvalidCheckpointProof);
+    }
+
+    let block_hash = compute_aggregated_block_hash(
+      &NimbleDigest::digest(block_bytes).to_bytes(),
+      &NimbleDigest::digest(nonces_bytes).to_bytes(),
+    );
+    let mut metablock = checkpoint.clone();
+    for hash in &self.block_hashes {
+      metablock = MetaBlock::new(&metablock.hash(), hash, metablock.get_height() + 1);
+    }
+    // the last hash is the entry's own, or the checkpoint is the entry
+    if *metablock.get_block_hash() != block_hash {
+      return Err(VerificationError::InvalidCheckpointProof);
+    }
+    Ok(metablock)
+  }
+}
+
+/// A snapshot of a ledger's tail, taken by reading the tail for a nonce that the coordinator
+/// chose: a quorum of endorsers signed the tail's metablock, and with it the ledger's handle,
+/// height, and tail hash, in the view of the receipts. A client that verified a snapshot can later
+/// verify any entry up to its height by the hash chain from the entry to the snapshot's tail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerSnapshot {
+  handle: Handle,
+  height: usize,
+  block: Vec<u8>,
+  nonces: Vec<u8>,
+  nonce: Vec<u8>,
+  receipts: Vec<u8>,
+}
+
+type SnapshotFields = (Vec<u8>, u64, Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>);
+
+impl LedgerSnapshot {
+  pub fn new(
+    handle: &Handle,
+    height: usize,
+    block: Vec<u8>,
+    nonces: Vec<u8>,
+    nonce: Vec<u8>,
+    receipts: Vec<u8>,
+  ) -> Self {
+    LedgerSnapshot {
+      handle: *handle,
+      height,
+      block,
+      nonces,
+      nonce,
+      receipts,
+    }
+  }
+
+  pub fn get_handle(&self) -> &Handle {
+    &self.handle
+  }
+
+  pub fn get_height(&self) -> usize {
+    self.height
+  }
+
+  pub fn get_block(&self) -> &[u8] {
+    &self.block
+  }
+
+  pub fn get_nonces(&self) -> &[u8] {
+    &self.nonces
+  }
+
+  pub fn get_nonce(&self) -> &[u8] {
+    &self.nonce
+  }
+
+  pub fn get_receipts(&self) -> &[u8] {
+    &self.receipts
+  }
+}
+
+impl CustomSerde for LedgerSnapshot {
+  fn to_bytes(&self) -> Vec<u8> {
+    let fields: SnapshotFields = (
+      self.handle.to_bytes(),
+      self.height as u64,
+      self.block.clone(),
+      self.nonces.clone(),
+      self.nonce.clone(),
+      self.receipts.clone(),
+    );
+    bincode::serialize(&fields).unwrap()
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    // the same encoding as `bincode::deserialize`, but no length field read from the bytes may
+    // claim more than the bytes hold, so a forged snapshot cannot exhaust memory
+    use bincode::Options;
+    let res = bincode::options()
+      .with_fixint_encoding()
+      .allow_trailing_bytes()
+      .with_limit(bytes.len() as u64)
+      .deserialize::<SnapshotFields>(bytes);
+    if res.is_err() {
+      return Err(CustomSerdeError::InternalError);
+    }
+    let (handle, height, block, nonces, nonce, receipts) = res.unwrap();
+    Ok(LedgerSnapshot {
+      handle: NimbleDigest::from_bytes(&handle)?,
+      height: height
+        .try_into()
+        .map_err(|_| CustomSerdeError::InternalError)?,
+      block,
+      nonces,
+      nonce,
+      receipts,
+    })
+  }
+}
+
+/// An endorser's statement, signed with its old key in `view`, that a new key takes the old key's
+/// place in the views that follow. The view change that rotates the key carries the handover in
+/// its configuration, so that verifiers can accept the new key on the old key's word.
+#[derive(Debug, Clone)]
+pub struct KeyHandover {
+  view: NimbleDigest,
+  new_pk: Vec<u8>,
+  id_sig: IdSig, // the old key and its signature
+}
+
+impl KeyHandover {
+  pub fn new(view: &NimbleDigest, new_pk: &PublicKey, id_sig: IdSig) -> Self {
+    KeyHandover {
+      view: *view,
+      new_pk: new_pk.to_bytes(),
+      id_sig,
+    }
+  }
+
+  pub fn get_view(&self) -> &NimbleDigest {
+    &self.view
+  }
+
+  pub fn get_old_pk(&self) -> &Vec<u8> {
+    self.id_sig.get_id()
+  }
+
+  pub fn get_new_pk(&self) -> &Vec<u8> {
+    &self.new_pk
+  }
+
+  /// checks that the old key signed the handover to the new key
+  pub fn verify(&self, group_identity: &NimbleDigest) -> Result<(), VerificationError> {
+    KeyHandoverAttestation::new(
+      group_identity,
+      &self.view,
+      &NimbleDigest::digest(&self.new_pk),
+    )
+    .verify(&self.id_sig)
+  }
+}
+
+impl CustomSerde for KeyHandover {
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = self.view.to_bytes();
+    bytes.extend(&self.new_pk);
+    bytes.extend(self.id_sig.to_bytes());
+    bytes
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    if bytes.len() != NimbleDigest::num_bytes() + PublicKey::num_bytes() + IdSig::num_bytes() {
+      return Err(CustomSerdeError::IncorrectLength);
+    }
+    let mut reader = Reader::new("KeyHandover", bytes);
+    let view = NimbleDigest::from_bytes(reader.take(NimbleDigest::num_bytes())?)?;
+    let new_pk = PublicKey::from_bytes(reader.take(PublicKey::num_bytes())?)
+      .map_err(|_| CustomSerdeError::InternalError)?
+      .to_bytes();
+    let id_sig = IdSig::from_bytes(reader.remaining())?;
+    Ok(KeyHandover {
+      view,
+      new_pk,
+      id_sig,
+    })
+  }
+}
+
+/// Evidence that an endorser equivocated: two receipts, signed by the same endorser, that attest
+/// to appends of different metablocks at the same height of the same ledger. An honest endorser
+/// signs each height of a ledger once, so the evidence alone convicts the endorser, whoever
+/// presents it.
+#[derive(Clone, Debug)]
+pub struct MisbehaviorEvidence {
+  handle: Handle,
+  first: Receipt,
+  second: Receipt,
+}
+
+impl MisbehaviorEvidence {
+  /// returns the evidence if `first` and `second` conflict: they must be signed by the same key
+  /// for different metablocks at the same height. The signatures are checked by `verify`.
+  pub fn new(handle: &Handle, first: &Receipt, second: &Receipt) -> Option<Self> {
+    if first.get_id_sig().get_id() != second.get_id_sig().get_id()
+      || first.get_height() != second.get_height()
+      || first.get_metablock() == second.get_metablock()
+    {
+      return None;
+    }
+    Some(MisbehaviorEvidence {
+      handle: *handle,
+      first: first.clone(),
+      second: second.clone(),
+    })
+  }
+
+  pub fn get_handle(&self) -> &Handle {
+    &self.handle
+  }
+
+  /// the public key of the endorser that signed both statements
+  pub fn get_endorser(&self) -> &Vec<u8> {
+    self.first.get_id_sig().get_id()
+  }
+
+  pub fn get_height(&self) -> usize {
+    self.first.get_height()
+  }
+
+  pub fn get_receipts(&self) -> (&Receipt, &Receipt) {
+    (&self.first, &self.second)
+  }
+
+  /// checks that the endorser signed both appends in views of the deployment `group_identity`
+  pub fn verify(&self, group_identity: &NimbleDigest) -> Result<(), VerificationError> {
+    if MisbehaviorEvidence::new(&self.handle, &self.first, &self.second).is_none() {
+      return Err(VerificationError::InvalidMisbehaviorEvidence);
+    }
+    for receipt in [&self.first, &self.second] {
+      AppendAttestation::for_metablock(
+        group_identity,
+        receipt.get_view(),
+        &self.handle,
+        receipt.get_metablock(),
+      )
+      .verify(receipt.get_id_sig())
+      .map_err(|_e| VerificationError::InvalidMisbehaviorEvidence)?;
+    }
+    Ok(())
+  }
+}
+
+impl CustomSerde for MisbehaviorEvidence {
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = self.handle.to_bytes();
+    bytes.extend(self.first.to_bytes());
+    bytes.extend(self.second.to_bytes());
+    bytes
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    if bytes.len() != NimbleDigest::num_bytes() + 2 * Receipt::num_bytes() {
+      return Err(CustomSerdeError::IncorrectLength);
+    }
+    let mut reader = Reader::new("MisbehaviorEvidence", bytes);
+    let handle = NimbleDigest::from_bytes(reader.take(NimbleDigest::num_bytes())?)?;
+    let first = Receipt::from_bytes(reader.take(Receipt::num_bytes())?)?;
+    let second = Receipt::from_bytes(reader.remaining())?;
+    MisbehaviorEvidence::new(&handle, &first, &second).ok_or_else(|| reader.invalid())
+  }
+}
+
 pub fn retrieve_public_keys_from_config(
   config: &[u8],
 ) -> Result<HashSet<Vec<u8>>, VerificationError> {
@@ -380,6 +1029,27 @@ pub fn retrieve_public_keys_from_config(
   Ok(pks)
 }
 
+/// Why the signature of one endorser does not count toward the quorum of a set of receipts
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignerFault {
+  /// the key is not one of the endorsers of the receipt's view for the ledger
+  Unknown,
+  /// the key signed different metablocks in the same view
+  Duplicated,
+  /// the signature does not verify, or the key or the signature is malformed
+  BadSignature,
+}
+
+/// The outcome of verifying a set of receipts signature by signature
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReceiptsReport {
+  /// the height that the receipts attest to, or the error that `Receipts::verify_with_handle`
+  /// returns for them
+  pub result: Result<usize, VerificationError>,
+  /// the public key of each endorser whose signature is at fault, with the fault
+  pub faults: Vec<(Vec<u8>, SignerFault)>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Receipts {
   receipts: HashMap<ExtendedMetaBlock, Vec<IdSig>>,
@@ -416,6 +1086,19 @@ impl Receipts {
     &self.receipts
   }
 
+  /// returns the distinct identities of the endorsers that contributed signatures
+  pub fn get_signer_ids(&self) -> Vec<Vec<u8>> {
+    let mut ids = Vec::new();
+    for id_sigs in self.receipts.values() {
+      for id_sig in id_sigs {
+        if !ids.contains(id_sig.get_id()) {
+          ids.push(id_sig.get_id().clone());
+        }
+      }
+    }
+    ids
+  }
+
   pub fn add(&mut self, receipt: &Receipt) {
     let ex_meta_block = ExtendedMetaBlock::new(receipt.get_view(), receipt.get_metablock());
     if let hash_map::Entry::Occupied(mut e) = self.receipts.entry(ex_meta_block.clone()) {
@@ -434,6 +1117,29 @@ impl Receipts {
     }
   }
 
+  /// keeps the `max` receipts backed by the most signatures and drops the rest
+  pub fn retain_strongest(&mut self, max: usize) {
+    if self.receipts.len() <= max {
+      return;
+    }
+
+    let mut strengths = self
+      .receipts
+      .iter()
+      .map(|(ex_meta_block, id_sigs)| (ex_meta_block.clone(), id_sigs.len()))
+      .collect::<Vec<(ExtendedMetaBlock, usize)>>();
+    // order by the number of signatures and break ties by view so the outcome is deterministic
+    strengths.sort_by(|(a, a_len), (b, b_len)| {
+      b_len
+        .cmp(a_len)
+        .then_with(|| a.get_view().to_bytes().cmp(&b.get_view().to_bytes()))
+    });
+
+    for (ex_meta_block, _len) in strengths.iter().skip(max) {
+      self.receipts.remove(ex_meta_block);
+    }
+  }
+
   pub fn merge_receipts(&mut self, receipts: &Receipts) {
     for (ex_meta_block, id_sigs) in receipts.get() {
       for id_sig in id_sigs {
@@ -471,7 +1177,92 @@ impl Receipts {
     Err(VerificationError::InsufficientReceipts)
   }
 
-  pub fn verify_read_latest(
+  /// checks that the receipts for some view form a quorum of the endorsers assigned to the
+  /// ledger `handle` whose signers also satisfy `policy`
+  pub fn check_policy(
+    &self,
+    verifier_state: &VerifierState,
+    handle: &Handle,
+    policy: &EndorsementPolicy,
+  ) -> Result<usize, VerificationError> {
+    for (ex_meta_block, id_sigs) in &self.receipts {
+      let view = ex_meta_block.get_view();
+      let pks = verifier_state.get_pks_for_ledger(view, handle)?;
+      let signers = id_sigs
+        .iter()
+        .map(|id_sig| id_sig.get_id())
+        .filter(|id| pks.contains(*id))
+        .cloned()
+        .collect::<HashSet<Vec<u8>>>();
+
+      if signers.len() > pks.len() / 2 && policy.is_satisfied_by(&signers, &pks) {
+        return Ok(ex_meta_block.get_metablock().get_height());
+      }
+    }
+
+    Err(VerificationError::EndorsementPolicyNotSatisfied)
+  }
+
+  /// checks that the receipts of the entry of `block_bytes` at `height` of the ledger `handle`
+  /// satisfy `policy`: only signers whose signatures on the entry's metablock verify count, on
+  /// the statement of its append or, with `nonce_bytes`, of a read of it with the nonce
+  #[allow(clippy::too_many_arguments)]
+  pub fn verify_policy(
+    &self,
+    verifier_state: &VerifierState,
+    handle: &Handle,
+    block_bytes: &[u8],
+    hash_nonces_bytes: &[u8],
+    height: usize,
+    nonce_bytes: Option<&[u8]>,
+    policy: &EndorsementPolicy,
+  ) -> Result<(), VerificationError> {
+    let block_hash = compute_aggregated_block_hash(
+      &NimbleDigest::digest(block_bytes).to_bytes(),
+      hash_nonces_bytes,
+    );
+
+    for (ex_meta_block, id_sigs) in &self.receipts {
+      let metablock = ex_meta_block.get_metablock();
+      if *metablock.get_block_hash() != block_hash || metablock.get_height() != height {
+        continue;
+      }
+
+      let pks = verifier_state.get_pks_for_ledger(ex_meta_block.get_view(), handle)?;
+      let mut messages = vec![Receipts::attestation(
+        verifier_state,
+        ex_meta_block,
+        handle,
+        None,
+      )];
+      if nonce_bytes.is_some() {
+        messages.push(Receipts::attestation(
+          verifier_state,
+          ex_meta_block,
+          handle,
+          nonce_bytes,
+        ));
+      }
+      let signers = id_sigs
+        .iter()
+        .filter(|id_sig| pks.contains(id_sig.get_id()))
+        .filter(|id_sig| {
+          messages
+            .iter()
+            .any(|m| id_sig.verify(&m.to_bytes()).is_ok())
+        })
+        .map(|id_sig| id_sig.get_id().clone())
+        .collect::<HashSet<Vec<u8>>>();
+
+      if signers.len() > pks.len() / 2 && policy.is_satisfied_by(&signers, &pks) {
+        return Ok(());
+      }
+    }
+
+    Err(VerificationError::EndorsementPolicyNotSatisfied)
+  }
+
+  pub fn verify_read_latest(
     &self,
     verifier_state: &VerifierState,
     handle_bytes: &[u8],
@@ -520,6 +1311,26 @@ impl Receipts {
     hash_nonces_bytes: &[u8],
     expected_height: Option<usize>,
     nonce_bytes: Option<&[u8]>,
+  ) -> Result<usize, VerificationError> {
+    self.verify_with_handle(
+      verifier_state,
+      &NimbleDigest::digest(handle_bytes),
+      block_bytes,
+      hash_nonces_bytes,
+      expected_height,
+      nonce_bytes,
+    )
+  }
+
+  /// same as `verify` but takes the digest of the handle, which is all a store keeps
+  pub fn verify_with_handle(
+    &self,
+    verifier_state: &VerifierState,
+    handle: &Handle,
+    block_bytes: &[u8],
+    hash_nonces_bytes: &[u8],
+    expected_height: Option<usize>,
+    nonce_bytes: Option<&[u8]>,
   ) -> Result<usize, VerificationError> {
     let block_hash = compute_aggregated_block_hash(
       &NimbleDigest::digest(block_bytes).to_bytes(),
@@ -527,7 +1338,7 @@ impl Receipts {
     );
 
     for (ex_meta_block, id_sigs) in &self.receipts {
-      let pks = verifier_state.get_pks_for_view(ex_meta_block.get_view())?;
+      let pks = verifier_state.get_pks_for_ledger(ex_meta_block.get_view(), handle)?;
       if id_sigs.len() < pks.len() / 2 + 1 {
         continue;
       }
@@ -542,30 +1353,160 @@ impl Receipts {
           return Err(VerificationError::InvalidHeight);
         }
       }
-      // update the message
-      let tail_hash = match nonce_bytes {
-        Some(n) => ex_meta_block.get_metablock().hash().digest_with_bytes(n),
-        None => ex_meta_block.get_metablock().hash(),
-      };
-
-      let message = verifier_state.get_group_identity().digest_with(
-        &ex_meta_block
-          .get_view()
-          .digest_with(&NimbleDigest::digest(handle_bytes).digest_with(&tail_hash)),
-      );
+      let message = Receipts::attestation(verifier_state, ex_meta_block, handle, nonce_bytes);
 
-      let mut num_receipts = 0;
-      for id_sig in id_sigs {
-        id_sig
-          .verify(&message.to_bytes())
-          .map_err(|_e| VerificationError::InvalidSignature)?;
-        if pks.contains(id_sig.get_id()) {
-          num_receipts += 1;
+      IdSig::verify_all(id_sigs, &message.to_bytes())?;
+      let num_receipts = id_sigs
+        .iter()
+        .filter(|id_sig| pks.contains(id_sig.get_id()))
+        .count();
+
+      if num_receipts > pks.len() / 2 {
+        return Ok(ex_meta_block.get_metablock().get_height());
+      }
+    }
+
+    Err(VerificationError::InvalidReceipt)
+  }
+
+  /// same as `verify_with_handle`, but also checks every signature and reports the endorsers
+  /// whose signatures do not count toward the quorum, so that a caller can tell a misbehaving
+  /// endorser from a receipt that merely lacks signatures
+  pub fn verify_with_report(
+    &self,
+    verifier_state: &VerifierState,
+    handle: &Handle,
+    block_bytes: &[u8],
+    hash_nonces_bytes: &[u8],
+    expected_height: Option<usize>,
+    nonce_bytes: Option<&[u8]>,
+  ) -> ReceiptsReport {
+    let result = self.verify_with_handle(
+      verifier_state,
+      handle,
+      block_bytes,
+      hash_nonces_bytes,
+      expected_height,
+      nonce_bytes,
+    );
+
+    let mut faults: Vec<(Vec<u8>, SignerFault)> = Vec::new();
+    let mut signed = HashMap::<(NimbleDigest, Vec<u8>), MetaBlock>::new();
+    // visits the metablocks in a fixed order so that the report does not depend on hashing
+    let mut ex_meta_blocks = self.receipts.keys().collect::<Vec<&ExtendedMetaBlock>>();
+    ex_meta_blocks.sort_by_key(|ex_meta_block| {
+      (
+        ex_meta_block.get_view().to_bytes(),
+        ex_meta_block.get_metablock().to_bytes(),
+      )
+    });
+    for ex_meta_block in ex_meta_blocks {
+      let view = ex_meta_block.get_view();
+      let pks = verifier_state
+        .get_pks_for_ledger(view, handle)
+        .unwrap_or_default();
+      let message = Receipts::attestation(verifier_state, ex_meta_block, handle, nonce_bytes);
+      for id_sig in &self.receipts[ex_meta_block] {
+        let id = id_sig.get_id();
+        let fault = if !pks.contains(id) {
+          Some(SignerFault::Unknown)
+        } else if id_sig.verify(&message.to_bytes()).is_err() {
+          Some(SignerFault::BadSignature)
+        } else {
+          match signed.entry((*view, id.clone())) {
+            hash_map::Entry::Occupied(e) if e.get() != ex_meta_block.get_metablock() => {
+              Some(SignerFault::Duplicated)
+            },
+            hash_map::Entry::Occupied(_) => None,
+            hash_map::Entry::Vacant(e) => {
+              e.insert(ex_meta_block.get_metablock().clone());
+              None
+            },
+          }
+        };
+        if let Some(fault) = fault {
+          if !faults.iter().any(|(faulty_id, _fault)| faulty_id == id) {
+            faults.push((id.clone(), fault));
+          }
         }
       }
+    }
+
+    ReceiptsReport { result, faults }
+  }
+
+  // the statement that the endorsers of `ex_meta_block` sign: a receipt with a nonce attests to a
+  // read rather than to an append
+  fn attestation(
+    verifier_state: &VerifierState,
+    ex_meta_block: &ExtendedMetaBlock,
+    handle: &Handle,
+    nonce_bytes: Option<&[u8]>,
+  ) -> NimbleDigest {
+    let group_identity = verifier_state.get_group_identity();
+    let view = ex_meta_block.get_view();
+    let metablock = ex_meta_block.get_metablock();
+    match nonce_bytes {
+      Some(n) => ReadAttestation::new(group_identity, view, handle, &metablock.hash(), n).message(),
+      None => AppendAttestation::for_metablock(group_identity, view, handle, metablock).message(),
+    }
+  }
+
+  /// Returns the statement, in the canonical layout of `messages`, over which the endorsers signed
+  /// the receipts of a read of the latest entry of `handle` with `nonce_bytes`: the
+  /// `ReadAttestation` of the nonce if they signed for the read, and otherwise the
+  /// `AppendAttestation` of the entry, whose nonces then include the nonce. The statement is told
+  /// apart by one signature of the largest group of receipts, and the receipts are not checked.
+  pub fn read_latest_statement(
+    &self,
+    group_identity: &NimbleDigest,
+    handle: &Handle,
+    nonce_bytes: &[u8],
+  ) -> Option<Vec<u8>> {
+    let (ex_meta_block, id_sigs) = self
+      .receipts
+      .iter()
+      .max_by_key(|(_ex_meta_block, id_sigs)| id_sigs.len())?;
+    let view = ex_meta_block.get_view();
+    let metablock = ex_meta_block.get_metablock();
+    let read = ReadAttestation::new(group_identity, view, handle, &metablock.hash(), nonce_bytes);
+    match id_sigs.first() {
+      Some(id_sig) if read.verify(id_sig).is_ok() => Some(read.to_bytes()),
+      Some(_) => {
+        Some(AppendAttestation::for_metablock(group_identity, view, handle, metablock).to_bytes())
+      },
+      None => None,
+    }
+  }
+
+  /// checks that a majority of the endorsers of the view at the tail of the view ledger signed
+  /// the tail, whose block is `block_bytes`, together with `nonce_bytes`, and returns its height
+  pub fn verify_read_view_tail(
+    &self,
+    verifier_state: &VerifierState,
+    block_bytes: &[u8],
+    nonce_bytes: &[u8],
+  ) -> Result<usize, VerificationError> {
+    let block_hash = NimbleDigest::digest(block_bytes);
+
+    for (ex_meta_block, id_sigs) in &self.receipts {
+      let view = ex_meta_block.get_view();
+      let metablock = ex_meta_block.get_metablock();
+      if metablock.hash() != *view || *metablock.get_block_hash() != block_hash {
+        continue;
+      }
+
+      let pks = verifier_state.get_pks_for_view(view)?;
+      let message =
+        ViewTailAttestation::new(verifier_state.get_group_identity(), view, nonce_bytes).message();
+      let num_receipts = id_sigs
+        .iter()
+        .filter(|id_sig| pks.contains(id_sig.get_id()))
+        .filter(|id_sig| id_sig.verify(&message.to_bytes()).is_ok())
+        .count();
 
       if num_receipts > pks.len() / 2 {
-        return Ok(ex_meta_block.get_metablock().get_height());
+        return Ok(metablock.get_height());
       }
     }
 
@@ -582,7 +1523,7 @@ impl Receipts {
     old_metablock: &MetaBlock,
     new_metablock: &MetaBlock,
     ledger_tail_maps: &Vec<LedgerTailMap>,
-    ledger_chunks: &Vec<LedgerChunkEntry>,
+    ledger_chunks: &[LedgerChunkEntry],
   ) -> Result<(), VerificationError> {
     // check the conditions when this is the first view change
     if old_metablock.get_height() == 0 {
@@ -661,6 +1602,7 @@ impl Receipts {
     let mut j: usize = 0;
     while i < cut_diffs.len() && j < ledger_chunks.len() {
       if cut_diffs[i].low == cut_diffs[i].high {
+        i += 1;
         continue;
       }
       if cut_diffs[i].handle.cmp(&ledger_chunks[j].handle) != Ordering::Equal
@@ -680,14 +1622,13 @@ impl Receipts {
         eprintln!("height overflow");
         return Err(VerificationError::InvalidHeight);
       }
-      let mut prev = NimbleDigest::from_bytes(&chunk.hash).unwrap();
+      let mut prev =
+        NimbleDigest::from_bytes(&chunk.hash).map_err(|_e| VerificationError::InvalidBlockHash)?;
       for block_hash in &chunk.block_hashes {
         height += 1;
-        let metablock = MetaBlock::new(
-          &prev,
-          &NimbleDigest::from_bytes(block_hash).unwrap(),
-          height as usize,
-        );
+        let block_hash =
+          NimbleDigest::from_bytes(block_hash).map_err(|_e| VerificationError::InvalidBlockHash)?;
+        let metablock = MetaBlock::new(&prev, &block_hash, height as usize);
         prev = metablock.hash();
         ledger_entries.insert((chunk.handle.clone(), height), metablock.to_bytes());
       }
@@ -696,6 +1637,11 @@ impl Receipts {
       j += 1;
     }
 
+    // trailing cut diffs without a gap have no chunk either
+    while i < cut_diffs.len() && cut_diffs[i].low == cut_diffs[i].high {
+      i += 1;
+    }
+
     if i != cut_diffs.len() || j != ledger_chunks.len() {
       eprintln!("incorrect information for comparing cuts");
       return Err(VerificationError::InconsistentLedgerTailMaps);
@@ -705,7 +1651,7 @@ impl Receipts {
       for entry in &ledger_tail_map.entries {
         let res = ledger_entries.get(&(entry.handle.clone(), entry.height));
         if let Some(metablock) = res {
-          if entry.metablock.cmp(metablock) != Ordering::Equal {
+          if MetaBlock::body_of(&entry.metablock) != MetaBlock::body_of(metablock) {
             eprintln!("metablock1={:?}", entry.metablock);
             eprintln!("metablock2={:?}", metablock);
             return Err(VerificationError::InconsistentLedgerTailMaps);
@@ -727,8 +1673,12 @@ impl Receipts {
         return Err(VerificationError::InvalidMetaBlock);
       }
 
-      let message =
-        group_identity.digest_with(&ex_meta_block.get_view().digest_with(&new_metablock_hash));
+      let message = ViewChangeAttestation::new(
+        group_identity,
+        ex_meta_block.get_view(),
+        &new_metablock_hash,
+      )
+      .message();
 
       for id_sig in id_sigs {
         id_sig.verify(&message.to_bytes()).map_err(|_e| {
@@ -736,21 +1686,28 @@ impl Receipts {
           VerificationError::InvalidSignature
         })?;
 
-        if new_pks.contains(id_sig.get_id()) {
-          if *ex_meta_block.get_view() != max_cut_hash {
-            eprintln!("the hashed state is invalid");
-            return Err(VerificationError::InvalidView);
-          }
+        // an endorser that stays in the view, as when another endorser rotates its key, signs
+        // both as an old endorser over its ledger tail map and as a new one over the max cut,
+        // and each of its receipts needs to be valid in only one of these roles
+        let is_new = new_pks.contains(id_sig.get_id());
+        let is_old = old_pks.contains(id_sig.get_id());
+        let counts_for_new = is_new && *ex_meta_block.get_view() == max_cut_hash;
+        let counts_for_old = is_old && state_hashes.contains(ex_meta_block.get_view());
+
+        if is_new && !counts_for_new && !counts_for_old {
+          eprintln!("the hashed state is invalid");
+          return Err(VerificationError::InvalidView);
+        }
+        if counts_for_new {
           num_receipts_for_new_pks += 1;
         }
 
-        if old_pks.contains(id_sig.get_id()) {
-          if state_hashes.contains(ex_meta_block.get_view()) {
-            used_ledger_tail_maps.insert(*ex_meta_block.get_view());
-          } else {
-            eprintln!("ledger tail map is missing");
-            return Err(VerificationError::MissingLedgerTailMap);
-          }
+        if is_old && !counts_for_old && !counts_for_new {
+          eprintln!("ledger tail map is missing");
+          return Err(VerificationError::MissingLedgerTailMap);
+        }
+        if counts_for_old {
+          used_ledger_tail_maps.insert(*ex_meta_block.get_view());
           num_receipts_for_old_pks += 1;
         }
       }
@@ -793,11 +1750,12 @@ impl Receipts {
         continue;
       }
 
-      let message = verifier_state.get_group_identity().digest_with(
-        &ex_meta_block
-          .get_view()
-          .digest_with(&ex_meta_block.get_metablock().hash()),
-      );
+      let message = ViewChangeAttestation::new(
+        verifier_state.get_group_identity(),
+        ex_meta_block.get_view(),
+        &ex_meta_block.get_metablock().hash(),
+      )
+      .message();
 
       let mut num_receipts = 0;
       for id_sig in id_sigs {
@@ -841,6 +1799,8 @@ pub struct VerifierState {
   group_identity: NimbleDigest,
   view_ledger_height: usize,
   verified_views: HashSet<NimbleDigest>,
+  // The number of endorsers each ledger is assigned to within a view (0 assigns all of them)
+  shard_size: usize,
 }
 
 impl VerifierState {
@@ -850,6 +1810,7 @@ impl VerifierState {
       group_identity: NimbleDigest::default(),
       view_ledger_height: 0,
       verified_views: HashSet::new(),
+      shard_size: 0,
     }
   }
 
@@ -868,6 +1829,24 @@ impl VerifierState {
     }
   }
 
+  /// returns the endorsers of `view` that the ledger `handle` is assigned to
+  pub fn get_pks_for_ledger(
+    &self,
+    view: &NimbleDigest,
+    handle: &Handle,
+  ) -> Result<HashSet<Vec<u8>>, VerificationError> {
+    let pks = self.get_pks_for_view(view)?;
+    Ok(shard_endorsers(pks, handle, self.shard_size))
+  }
+
+  pub fn get_shard_size(&self) -> usize {
+    self.shard_size
+  }
+
+  pub fn set_shard_size(&mut self, shard_size: usize) {
+    self.shard_size = shard_size;
+  }
+
   pub fn get_group_identity(&self) -> &NimbleDigest {
     &self.group_identity
   }
@@ -903,6 +1882,54 @@ impl VerifierState {
     }
   }
 
+  /// checks that `handover` is signed by a key of the endorsers of the view it names
+  pub fn verify_key_handover(&self, handover: &KeyHandover) -> Result<(), VerificationError> {
+    let pks = self.get_pks_for_view(handover.get_view())?;
+    if !pks.contains(handover.get_old_pk()) || handover.verify(&self.group_identity).is_err() {
+      return Err(VerificationError::InvalidKeyHandover);
+    }
+    Ok(())
+  }
+
+  /// Applies a view change that rotates endorsers' keys. The key handovers in its configuration
+  /// stand in for an attestation: the view must follow a view that the state knows, every
+  /// handover must be signed in that view by one of its endorsers, and the new view must list
+  /// the same endorsers with each handed-over key replaced by its successor.
+  pub fn apply_key_rotation(
+    &mut self,
+    config: &[u8],
+    receipts_bytes: &[u8],
+  ) -> Result<(), VerificationError> {
+    let receipts =
+      Receipts::from_bytes(receipts_bytes).map_err(|_e| VerificationError::InvalidReceipt)?;
+    let metablock = receipts.get_metablock()?;
+    let mut pks = self.get_pks_for_view(metablock.get_prev())?.clone();
+
+    let handovers = attestation::retrieve_key_handovers_from_config(config)?;
+    if handovers.is_empty() {
+      return Err(VerificationError::InvalidKeyHandover);
+    }
+    for handover in &handovers {
+      if handover.get_view() != metablock.get_prev() {
+        return Err(VerificationError::InvalidKeyHandover);
+      }
+      self.verify_key_handover(handover)?;
+      pks.remove(handover.get_old_pk());
+      pks.insert(handover.get_new_pk().clone());
+    }
+    if retrieve_public_keys_from_config(config)? != pks {
+      return Err(VerificationError::InvalidKeyHandover);
+    }
+
+    // a quorum of the new view's endorsers must still sign it
+    self.verified_views.insert(metablock.hash());
+    let res = self.apply_view_change(config, receipts_bytes, None);
+    if res.is_err() {
+      self.verified_views.remove(&metablock.hash());
+    }
+    res
+  }
+
   pub fn verify_new_ledger(
     &self,
     handle_bytes: &[u8],
@@ -925,6 +1952,34 @@ impl VerifierState {
     }
   }
 
+  /// checks that the receipts of the entry of `block_bytes` and `hash_nonces_bytes` at `height` of
+  /// a ledger satisfy the ledger's endorsement policy, which can be obtained with
+  /// `EndorsementPolicy::from_genesis_bytes` on the genesis block; `nonce_bytes` is the nonce of
+  /// a read of the entry as the ledger's tail
+  #[allow(clippy::too_many_arguments)]
+  pub fn verify_endorsement_policy(
+    &self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    hash_nonces_bytes: &[u8],
+    height: usize,
+    nonce_bytes: Option<&[u8]>,
+    receipts_bytes: &[u8],
+    policy: &EndorsementPolicy,
+  ) -> Result<(), VerificationError> {
+    let receipts =
+      Receipts::from_bytes(receipts_bytes).map_err(|_e| VerificationError::InvalidReceipt)?;
+    receipts.verify_policy(
+      self,
+      &NimbleDigest::digest(handle_bytes),
+      block_bytes,
+      hash_nonces_bytes,
+      height,
+      nonce_bytes,
+      policy,
+    )
+  }
+
   pub fn verify_append(
     &self,
     handle_bytes: &[u8],
@@ -962,6 +2017,18 @@ impl VerifierState {
     receipts.verify_read_latest(self, handle_bytes, block_bytes, nonces_bytes, nonce_bytes)
   }
 
+  /// checks the receipts of the view ledger's tail read with `nonce_bytes` and returns its height
+  pub fn verify_read_view_tail(
+    &self,
+    block_bytes: &[u8],
+    nonce_bytes: &[u8],
+    receipts_bytes: &[u8],
+  ) -> Result<usize, VerificationError> {
+    let receipts =
+      Receipts::from_bytes(receipts_bytes).map_err(|_e| VerificationError::InvalidReceipt)?;
+    receipts.verify_read_view_tail(self, block_bytes, nonce_bytes)
+  }
+
   pub fn verify_read_by_index(
     &self,
     handle_bytes: &[u8],
@@ -988,7 +2055,81 @@ impl VerifierState {
   }
 }
 
-pub fn compute_max_cut(ledger_tail_maps: &Vec<LedgerTailMap>) -> Vec<LedgerTailMapEntry> {
+/// Checks the response to a read of the latest entry of `handle_bytes` with `nonce_bytes` with
+/// only the public keys of the endorsers of the view that the receipts were issued in, as a
+/// party that follows no view ledger can: `statement` must be the `ReadAttestation` of the nonce
+/// or the `AppendAttestation` of an entry whose nonces include it, it must be about the entry of
+/// `block_bytes` and `nonces_bytes`, and a majority of `view_keys` must have signed it. Returns
+/// the height of the entry.
+pub fn verify_read_latest_statement(
+  statement: &[u8],
+  handle_bytes: &[u8],
+  block_bytes: &[u8],
+  nonces_bytes: &[u8],
+  nonce_bytes: &[u8],
+  receipts_bytes: &[u8],
+  view_keys: &[Vec<u8>],
+) -> Result<usize, VerificationError> {
+  let receipts =
+    Receipts::from_bytes(receipts_bytes).map_err(|_e| VerificationError::InvalidReceipt)?;
+  let handle = NimbleDigest::digest(handle_bytes);
+  let block_hash = compute_aggregated_block_hash(
+    &NimbleDigest::digest(block_bytes).to_bytes(),
+    &NimbleDigest::digest(nonces_bytes).to_bytes(),
+  );
+  let (ex_meta_block, id_sigs) = receipts
+    .receipts
+    .iter()
+    .find(|(ex_meta_block, _id_sigs)| *ex_meta_block.get_metablock().get_block_hash() == block_hash)
+    .ok_or(VerificationError::InvalidBlockHash)?;
+  let view = ex_meta_block.get_view();
+  let metablock = ex_meta_block.get_metablock();
+
+  let message = if let Ok(read) = ReadAttestation::from_bytes(statement) {
+    if read.nonce != nonce_bytes {
+      return Err(VerificationError::InvalidNonce);
+    }
+    if read
+      != ReadAttestation::new(
+        &read.group_identity,
+        view,
+        &handle,
+        &metablock.hash(),
+        nonce_bytes,
+      )
+    {
+      return Err(VerificationError::InvalidReceipt);
+    }
+    read.message()
+  } else if let Ok(append) = AppendAttestation::from_bytes(statement) {
+    let nonces = Nonces::from_bytes(nonces_bytes).map_err(|_e| VerificationError::InvalidNonces)?;
+    let nonce = Nonce::from_bytes(nonce_bytes).map_err(|_e| VerificationError::InvalidNonce)?;
+    if !nonces.contains(&nonce) {
+      return Err(VerificationError::InvalidNonce);
+    }
+    if append != AppendAttestation::for_metablock(&append.group_identity, view, &handle, metablock)
+    {
+      return Err(VerificationError::InvalidReceipt);
+    }
+    append.message()
+  } else {
+    return Err(VerificationError::InvalidReceipt);
+  };
+
+  let mut signers = HashSet::new();
+  for id_sig in id_sigs {
+    if view_keys.contains(id_sig.get_id()) && id_sig.verify(&message.to_bytes()).is_ok() {
+      signers.insert(id_sig.get_id().clone());
+    }
+  }
+  if signers.len() > view_keys.len() / 2 {
+    Ok(metablock.get_height())
+  } else {
+    Err(VerificationError::InsufficientReceipts)
+  }
+}
+
+pub fn compute_max_cut(ledger_tail_maps: &[LedgerTailMap]) -> Vec<LedgerTailMapEntry> {
   if ledger_tail_maps.is_empty() {
     Vec::new()
   } else {
@@ -1036,7 +2177,7 @@ pub struct CutDiff {
   pub high: usize,
 }
 
-pub fn compute_cut_diffs(ledger_tail_maps: &Vec<LedgerTailMap>) -> Vec<CutDiff> {
+pub fn compute_cut_diffs(ledger_tail_maps: &[LedgerTailMap]) -> Vec<CutDiff> {
   if ledger_tail_maps.len() <= 1 {
     Vec::new()
   } else {
@@ -1044,7 +2185,7 @@ pub fn compute_cut_diffs(ledger_tail_maps: &Vec<LedgerTailMap>) -> Vec<CutDiff>
     for entry in &ledger_tail_maps[0].entries {
       cut_diffs.push(CutDiff {
         handle: entry.handle.clone(),
-        hash: NimbleDigest::digest(&entry.metablock),
+        hash: MetaBlock::hash_of_bytes(&entry.metablock),
         low: entry.height as usize,
         high: entry.height as usize,
       });
@@ -1056,18 +2197,20 @@ pub fn compute_cut_diffs(ledger_tail_maps: &Vec<LedgerTailMap>) -> Vec<CutDiff>
         match cut_diffs[i].handle.cmp(&ledger_tail_map.entries[j].handle) {
           Ordering::Equal => {
             if (ledger_tail_map.entries[j].height as usize) < cut_diffs[i].low {
-              cut_diffs[i].hash = NimbleDigest::digest(&ledger_tail_map.entries[j].metablock);
+              cut_diffs[i].hash = MetaBlock::hash_of_bytes(&ledger_tail_map.entries[j].metablock);
               cut_diffs[i].low = ledger_tail_map.entries[j].height as usize;
             } else if (ledger_tail_map.entries[j].height as usize) > cut_diffs[i].high {
               cut_diffs[i].high = ledger_tail_map.entries[j].height as usize;
             }
+            i += 1;
+            j += 1;
           },
           Ordering::Greater => {
             cut_diffs.insert(
               i,
               CutDiff {
                 handle: ledger_tail_map.entries[j].handle.clone(),
-                hash: NimbleDigest::digest(&ledger_tail_map.entries[j].metablock),
+                hash: MetaBlock::hash_of_bytes(&ledger_tail_map.entries[j].metablock),
                 low: ledger_tail_map.entries[j].height as usize,
                 high: ledger_tail_map.entries[j].height as usize,
               },
@@ -1083,7 +2226,7 @@ pub fn compute_cut_diffs(ledger_tail_maps: &Vec<LedgerTailMap>) -> Vec<CutDiff>
       while j < ledger_tail_map.entries.len() {
         cut_diffs.push(CutDiff {
           handle: ledger_tail_map.entries[j].handle.clone(),
-          hash: NimbleDigest::digest(&ledger_tail_map.entries[j].metablock),
+          hash: MetaBlock::hash_of_bytes(&ledger_tail_map.entries[j].metablock),
           low: ledger_tail_map.entries[j].height as usize,
           high: ledger_tail_map.entries[j].height as usize,
         });
@@ -1096,124 +2239,703 @@ pub fn compute_cut_diffs(ledger_tail_maps: &Vec<LedgerTailMap>) -> Vec<CutDiff>
 
 pub type EndorserHostnames = Vec<(Vec<u8>, String)>;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum CustomSerdeError {
-  /// returned if the supplied byte array is of incorrect length
-  IncorrectLength,
-  /// returned if deserializing any byte entry into the Rust type fails
-  InternalError,
+/// Assigns the ledger `handle` to `shard_size` of the endorsers in `pks` by ranking each endorser
+/// on the hash of the handle and its public key (rendezvous hashing), so that every party derives
+/// the same assignment from the view and adding or removing an endorser moves few ledgers.
+/// A `shard_size` of 0, or one covering every endorser, assigns the ledger to all of them.
+pub fn shard_endorsers(
+  pks: &HashSet<Vec<u8>>,
+  handle: &Handle,
+  shard_size: usize,
+) -> HashSet<Vec<u8>> {
+  if shard_size == 0 || shard_size >= pks.len() {
+    return pks.clone();
+  }
+
+  let mut ranked = pks
+    .iter()
+    .map(|pk| (handle.digest_with_bytes(pk).to_bytes(), pk))
+    .collect::<Vec<_>>();
+  ranked.sort();
+  ranked
+    .into_iter()
+    .take(shard_size)
+    .map(|(_rank, pk)| pk.clone())
+    .collect()
 }
 
-pub trait CustomSerde
-where
-  Self: Sized,
-{
-  fn to_bytes(&self) -> Vec<u8>;
-  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError>;
+const ENDORSEMENT_POLICY_MAGIC: &[u8] = b"NIMBLE-ENDORSEMENT-POLICY";
+
+/// The endorsement policy of a ledger, fixed at creation by prefixing the genesis block with
+/// the encoded policy. Ledgers without the prefix use the default majority quorum.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum EndorsementPolicy {
+  /// a majority of the endorsers in the view must sign
+  #[default]
+  Majority,
+  /// every endorser assigned to the ledger in the view must sign
+  All,
+  /// a majority must sign and the majority must include the named endorsers
+  Required(Vec<Vec<u8>>),
 }
 
-impl CustomSerde for Nonce {
-  fn to_bytes(&self) -> Vec<u8> {
-    self.data.to_vec()
+impl EndorsementPolicy {
+  /// returns the genesis block bytes that create a ledger with this policy and `app_bytes`
+  pub fn to_genesis_bytes(&self, app_bytes: &[u8]) -> Vec<u8> {
+    let mut bytes = match self {
+      EndorsementPolicy::Majority => Vec::new(),
+      EndorsementPolicy::All => [ENDORSEMENT_POLICY_MAGIC, &[1]].concat(),
+      EndorsementPolicy::Required(ids) => {
+        let mut bytes = [ENDORSEMENT_POLICY_MAGIC, &[2]].concat();
+        bytes.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+        for id in ids {
+          bytes.extend_from_slice(id);
+        }
+        bytes
+      },
+    };
+    bytes.extend_from_slice(app_bytes);
+    bytes
   }
 
-  fn from_bytes(bytes: &[u8]) -> Result<Nonce, CustomSerdeError> {
-    match Nonce::new(bytes) {
-      Ok(nonce) => Ok(nonce),
-      Err(_) => Err(CustomSerdeError::IncorrectLength),
+  /// parses the policy out of genesis block bytes and returns it with the application bytes
+  pub fn from_genesis_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), VerificationError> {
+    if !bytes.starts_with(ENDORSEMENT_POLICY_MAGIC) {
+      return Ok((EndorsementPolicy::Majority, bytes));
     }
-  }
-}
-impl CustomSerde for Nonces {
-  fn to_bytes(&self) -> Vec<u8> {
-    let mut data = Vec::with_capacity(self.nonces.len() * Nonce::num_bytes());
-    for nonce in self.get() {
-      data.extend(nonce.to_bytes());
+
+    let rest = &bytes[ENDORSEMENT_POLICY_MAGIC.len()..];
+    match rest.first() {
+      Some(1) => Ok((EndorsementPolicy::All, &rest[1..])),
+      Some(2) => {
+        let mut reader = Reader::new("EndorsementPolicy", &rest[1..]);
+        let num_ids = reader
+          .read_u32()
+          .map_err(|_e| VerificationError::InvalidEndorsementPolicy)?
+          as usize;
+        if num_ids == 0 {
+          return Err(VerificationError::InvalidEndorsementPolicy);
+        }
+        let mut ids = Vec::new();
+        for id in reader
+          .take_chunks(num_ids, PublicKey::num_bytes())
+          .map_err(|_e| VerificationError::InvalidEndorsementPolicy)?
+        {
+          PublicKey::from_bytes(id).map_err(|_e| VerificationError::InvalidEndorsementPolicy)?;
+          ids.push(id.to_vec());
+        }
+        Ok((EndorsementPolicy::Required(ids), reader.remaining()))
+      },
+      _ => Err(VerificationError::InvalidEndorsementPolicy),
     }
-    data
   }
 
-  fn from_bytes(bytes: &[u8]) -> Result<Nonces, CustomSerdeError> {
-    if bytes.len() % Nonce::num_bytes() != 0 {
-      Err(CustomSerdeError::IncorrectLength)
-    } else {
-      let mut nonces = Nonces::new();
-      let mut pos = 0;
-      while pos < bytes.len() {
-        let nonce = Nonce::from_bytes(&bytes[pos..pos + Nonce::num_bytes()])?;
-        nonces.add(nonce);
-        pos += Nonce::num_bytes();
-      }
-      Ok(nonces)
+  /// checks whether `signers` satisfy the policy in a view with endorsers `pks`
+  pub fn is_satisfied_by(&self, signers: &HashSet<Vec<u8>>, pks: &HashSet<Vec<u8>>) -> bool {
+    match self {
+      EndorsementPolicy::Majority => signers.len() > pks.len() / 2,
+      EndorsementPolicy::All => pks.iter().all(|pk| signers.contains(pk)),
+      EndorsementPolicy::Required(ids) => ids.iter().all(|id| signers.contains(id)),
     }
   }
 }
 
-impl CustomSerde for Block {
-  fn to_bytes(&self) -> Vec<u8> {
-    self.block.clone()
-  }
+const ACCESS_POLICY_MAGIC: &[u8] = b"NIMBLE-ACCESS-POLICY";
 
-  fn from_bytes(bytes: &[u8]) -> Result<Block, CustomSerdeError> {
-    Ok(Block {
-      block: bytes.to_vec(),
-    })
-  }
-}
+/// gRPC metadata key carrying the public key a client acts as under a ledger's access policy
+pub const CLIENT_PUBLIC_KEY_METADATA: &str = "x-nimble-client-pk-bin";
 
-impl CustomSerde for NimbleDigest {
-  fn to_bytes(&self) -> Vec<u8> {
-    self.digest.as_slice().to_vec()
-  }
+/// gRPC metadata key carrying the client's signature over the access request message
+pub const CLIENT_SIGNATURE_METADATA: &str = "x-nimble-client-sig-bin";
 
-  fn from_bytes(bytes: &[u8]) -> Result<NimbleDigest, CustomSerdeError> {
-    let digest_len = NimbleDigest::num_bytes();
-    if bytes.len() != digest_len {
-      Err(CustomSerdeError::IncorrectLength)
-    } else {
-      let digest = GenericArray::<u8, U32>::from_slice(&bytes[0..digest_len]);
-      Ok(NimbleDigest { digest: *digest })
-    }
-  }
+/// gRPC metadata key carrying the API key that names a client to the coordinator's rate limits
+pub const API_KEY_METADATA: &str = "x-nimble-api-key";
+
+/// gRPC metadata key carrying how many milliseconds a client that was rate limited should wait
+pub const RETRY_AFTER_METADATA: &str = "retry-after-ms";
+
+/// Who may read a ledger under its access policy
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReadVisibility {
+  /// anyone may read the ledger
+  #[default]
+  Public,
+  /// only the writers named in the policy may read the ledger
+  Writers,
 }
 
-impl CustomSerde for MetaBlock {
-  fn to_bytes(&self) -> Vec<u8> {
-    let mut bytes = Vec::new();
-    let height_u64 = self.height as u64;
-    bytes.extend(&self.prev.to_bytes());
-    bytes.extend(&self.block_hash.to_bytes());
-    bytes.extend(&height_u64.to_le_bytes().to_vec());
+/// The access policy of a ledger, fixed at creation by prefixing the application bytes of the
+/// genesis block (after any endorsement policy) with the encoded policy. Since the genesis block
+/// is endorsed, the policy is as tamper-evident as the ledger itself. Writers are identified by
+/// their public keys; an empty list of writers lets anyone append. Ledgers without the prefix are
+/// open to everyone.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AccessPolicy {
+  writers: Vec<Vec<u8>>,
+  visibility: ReadVisibility,
+}
+
+/// A request a client makes under a ledger's access policy
+pub enum AccessRequest<'a> {
+  Append {
+    block: &'a [u8],
+    expected_height: usize,
+  },
+  AppendBatch {
+    blocks: &'a [Vec<u8>],
+    expected_height: usize,
+  },
+  ReadLatest {
+    nonce: &'a [u8],
+  },
+  ReadByIndex {
+    index: usize,
+  },
+  ReadStats,
+  ReadAsOfView {
+    view_height: usize,
+  },
+}
+
+impl<'a> AccessRequest<'a> {
+  pub fn is_write(&self) -> bool {
+    matches!(
+      self,
+      AccessRequest::Append { .. } | AccessRequest::AppendBatch { .. }
+    )
+  }
+
+  /// returns the message a client signs to make this request on the ledger `handle_bytes`;
+  /// appends cover the expected height so a signed request cannot be replayed at a later height
+  pub fn message(&self, handle_bytes: &[u8]) -> Vec<u8> {
+    let (tag, payload) = match self {
+      AccessRequest::Append {
+        block,
+        expected_height,
+      } => (
+        0u8,
+        NimbleDigest::digest(block).digest_with_bytes(&(*expected_height as u64).to_le_bytes()),
+      ),
+      AccessRequest::AppendBatch {
+        blocks,
+        expected_height,
+      } => (
+        5u8,
+        blocks
+          .iter()
+          .fold(NimbleDigest::default(), |acc, block| {
+            acc.digest_with(&NimbleDigest::digest(block))
+          })
+          .digest_with_bytes(&(*expected_height as u64).to_le_bytes()),
+      ),
+      AccessRequest::ReadLatest { nonce } => (1u8, NimbleDigest::digest(nonce)),
+      AccessRequest::ReadByIndex { index } => {
+        (2u8, NimbleDigest::digest(&(*index as u64).to_le_bytes()))
+      },
+      AccessRequest::ReadStats => (3u8, NimbleDigest::default()),
+      AccessRequest::ReadAsOfView { view_height } => (
+        4u8,
+        NimbleDigest::digest(&(*view_height as u64).to_le_bytes()),
+      ),
+    };
+    NimbleDigest::digest(&[tag])
+      .digest_with(&NimbleDigest::digest(handle_bytes))
+      .digest_with(&payload)
+      .to_bytes()
+  }
+}
+
+impl AccessPolicy {
+  pub fn new(writers: Vec<Vec<u8>>, visibility: ReadVisibility) -> Self {
+    AccessPolicy {
+      writers,
+      visibility,
+    }
+  }
+
+  pub fn get_writers(&self) -> &Vec<Vec<u8>> {
+    &self.writers
+  }
+
+  pub fn get_visibility(&self) -> ReadVisibility {
+    self.visibility
+  }
+
+  /// returns true if the policy lets anyone read and append
+  pub fn is_open(&self) -> bool {
+    self.writers.is_empty() && self.visibility == ReadVisibility::Public
+  }
+
+  /// returns the application bytes that carry this policy followed by `app_bytes`
+  pub fn to_genesis_bytes(&self, app_bytes: &[u8]) -> Vec<u8> {
+    if self.is_open() {
+      return app_bytes.to_vec();
+    }
+
+    let mut bytes = ACCESS_POLICY_MAGIC.to_vec();
+    bytes.push(match self.visibility {
+      ReadVisibility::Public => 0,
+      ReadVisibility::Writers => 1,
+    });
+    bytes.extend_from_slice(&(self.writers.len() as u32).to_le_bytes());
+    for writer in &self.writers {
+      bytes.extend_from_slice(writer);
+    }
+    bytes.extend_from_slice(app_bytes);
     bytes
   }
 
-  fn from_bytes(bytes: &[u8]) -> Result<MetaBlock, CustomSerdeError> {
-    let digest_len = NimbleDigest::num_bytes();
+  /// parses the policy out of the application bytes of a genesis block (what remains after
+  /// `EndorsementPolicy::from_genesis_bytes`) and returns it with the rest of the bytes
+  pub fn from_genesis_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), VerificationError> {
+    if !bytes.starts_with(ACCESS_POLICY_MAGIC) {
+      return Ok((AccessPolicy::default(), bytes));
+    }
 
-    if bytes.len() != MetaBlock::num_bytes() {
-      eprintln!(
-        "bytes len={} but MetaBlock expects {}",
-        bytes.len(),
-        MetaBlock::num_bytes()
-      );
+    let mut reader = Reader::new("AccessPolicy", &bytes[ACCESS_POLICY_MAGIC.len()..]);
+    let visibility = match reader.read_u8() {
+      Ok(0) => ReadVisibility::Public,
+      Ok(1) => ReadVisibility::Writers,
+      _ => return Err(VerificationError::InvalidAccessPolicy),
+    };
+    let num_writers = reader
+      .read_u32()
+      .map_err(|_e| VerificationError::InvalidAccessPolicy)? as usize;
+    let mut writers = Vec::new();
+    for pk in reader
+      .take_chunks(num_writers, PublicKey::num_bytes())
+      .map_err(|_e| VerificationError::InvalidAccessPolicy)?
+    {
+      PublicKey::from_bytes(pk).map_err(|_e| VerificationError::InvalidAccessPolicy)?;
+      writers.push(pk.to_vec());
+    }
+    Ok((AccessPolicy::new(writers, visibility), reader.remaining()))
+  }
+
+  /// checks that the client identified by `credentials` (public key and signature over the
+  /// request message) may make `request` on the ledger `handle_bytes`
+  pub fn authorize(
+    &self,
+    handle_bytes: &[u8],
+    request: &AccessRequest,
+    credentials: Option<(&[u8], &[u8])>,
+  ) -> Result<(), VerificationError> {
+    let restricted = if request.is_write() {
+      !self.writers.is_empty()
+    } else {
+      self.visibility == ReadVisibility::Writers
+    };
+    if !restricted {
+      return Ok(());
+    }
+
+    let (pk_bytes, sig_bytes) = credentials.ok_or(VerificationError::AccessDenied)?;
+    if !self.writers.iter().any(|w| w == pk_bytes) {
+      return Err(VerificationError::AccessDenied);
+    }
+    let pk = PublicKey::from_bytes(pk_bytes).map_err(|_e| VerificationError::AccessDenied)?;
+    let sig = Signature::from_bytes(sig_bytes).map_err(|_e| VerificationError::AccessDenied)?;
+    sig
+      .verify(&pk, &request.message(handle_bytes))
+      .map_err(|_e| VerificationError::AccessDenied)
+  }
+}
+
+const LEDGER_HANDLE_MAGIC: &[u8] = b"NIMBLE-LEDGER-HANDLE";
+
+/// Derives the handle of a ledger from a nonce that the client picks and the application bytes
+/// of the ledger's genesis block, past its endorsement and access policies. The client knows the
+/// handle before it creates the ledger, and clients that pick different nonces never race for the
+/// same handle.
+pub fn derive_ledger_handle(
+  client_nonce: &[u8],
+  genesis_bytes: &[u8],
+) -> Result<Vec<u8>, VerificationError> {
+  let (_policy, app_bytes) = EndorsementPolicy::from_genesis_bytes(genesis_bytes)?;
+  let (_access_policy, app_bytes) = AccessPolicy::from_genesis_bytes(app_bytes)?;
+  let mut bytes = LEDGER_HANDLE_MAGIC.to_vec();
+  bytes.extend_from_slice(&(client_nonce.len() as u64).to_le_bytes());
+  bytes.extend_from_slice(client_nonce);
+  bytes.extend_from_slice(app_bytes);
+  Ok(NimbleDigest::digest(&bytes).to_bytes())
+}
+
+const BLOCK_ENVELOPE_MAGIC: &[u8] = b"NIMBLE-BLOCK-ENVELOPE";
+const BLOCK_ENVELOPE_VERSION: u8 = 1;
+const MAX_CONTENT_TYPE_LEN: usize = 255;
+
+/// An optional framing of a block's bytes that tags the payload with its content type, the client
+/// that wrote it, and when (milliseconds since the UNIX epoch, as claimed by the client). Blocks
+/// stay opaque to the ledger: a block that does not start with the envelope's magic is a plain
+/// block. The encoding is canonical, so the bytes of a block decide the envelope and vice versa.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockEnvelope {
+  content_type: String,
+  client_id: Vec<u8>,
+  timestamp: u64,
+  payload: Vec<u8>,
+}
+
+impl BlockEnvelope {
+  pub fn new(content_type: &str, client_id: &[u8], timestamp: u64, payload: &[u8]) -> Self {
+    BlockEnvelope {
+      content_type: content_type.to_string(),
+      client_id: client_id.to_vec(),
+      timestamp,
+      payload: payload.to_vec(),
+    }
+  }
+
+  pub fn get_content_type(&self) -> &str {
+    &self.content_type
+  }
+
+  pub fn get_client_id(&self) -> &[u8] {
+    &self.client_id
+  }
+
+  pub fn get_timestamp(&self) -> u64 {
+    self.timestamp
+  }
+
+  pub fn get_payload(&self) -> &[u8] {
+    &self.payload
+  }
+
+  /// returns true if `block_bytes` claim to be an envelope, whether or not they decode as one
+  pub fn is_envelope(block_bytes: &[u8]) -> bool {
+    block_bytes.starts_with(BLOCK_ENVELOPE_MAGIC)
+  }
+
+  // content types are non-empty printable ASCII without spaces, such as "application/json"
+  fn is_valid_content_type(content_type: &str) -> bool {
+    !content_type.is_empty()
+      && content_type.len() <= MAX_CONTENT_TYPE_LEN
+      && content_type.bytes().all(|b| b.is_ascii_graphic())
+  }
+
+  // decodes the bytes that follow the magic
+  fn parse(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    let mut reader = Reader::new("BlockEnvelope", bytes);
+    if reader.read_u8()? != BLOCK_ENVELOPE_VERSION {
+      return Err(CustomSerdeError::UnsupportedVersion);
+    }
+    let invalid = reader.invalid();
+    let content_type = std::str::from_utf8(reader.take_prefixed(1)?)
+      .ok()
+      .filter(|content_type| Self::is_valid_content_type(content_type))
+      .ok_or(invalid)?;
+    let client_id = reader.take_prefixed(2)?;
+    let timestamp = reader.read_u64()?;
+    let payload = reader.take_prefixed(4)?;
+    reader.finish()?;
+    Ok(BlockEnvelope::new(
+      content_type,
+      client_id,
+      timestamp,
+      payload,
+    ))
+  }
+
+  /// returns the block bytes of the envelope: the magic, a version byte, the content type with a
+  /// one-byte length, the client id with a two-byte length, the timestamp, and the payload with a
+  /// four-byte length, all integers little endian
+  pub fn to_bytes(&self) -> Result<Vec<u8>, VerificationError> {
+    if !Self::is_valid_content_type(&self.content_type)
+      || self.client_id.len() > u16::MAX as usize
+      || self.payload.len() > u32::MAX as usize
+    {
+      return Err(VerificationError::InvalidBlockEnvelope);
+    }
+
+    let mut bytes = BLOCK_ENVELOPE_MAGIC.to_vec();
+    bytes.push(BLOCK_ENVELOPE_VERSION);
+    bytes.push(self.content_type.len() as u8);
+    bytes.extend_from_slice(self.content_type.as_bytes());
+    bytes.extend_from_slice(&(self.client_id.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&self.client_id);
+    bytes.extend_from_slice(&self.timestamp.to_le_bytes());
+    bytes.extend_from_slice(&(self.payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&self.payload);
+    Ok(bytes)
+  }
+
+  /// decodes an envelope from block bytes, rejecting any encoding but the canonical one
+  pub fn from_bytes(block_bytes: &[u8]) -> Result<Self, VerificationError> {
+    if !Self::is_envelope(block_bytes) {
+      return Err(VerificationError::InvalidBlockEnvelope);
+    }
+
+    Self::parse(&block_bytes[BLOCK_ENVELOPE_MAGIC.len()..])
+      .map_err(|_e| VerificationError::InvalidBlockEnvelope)
+  }
+}
+
+const BLOB_REFERENCE_MAGIC: &[u8] = b"NIMBLE-BLOB-REFERENCE";
+
+/// The block of an entry appended in chunks: the ledger holds the hash and size of the payload,
+/// and the payload itself lives in the coordinator's blob store under its hash
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlobReference {
+  hash: NimbleDigest,
+  size: u64,
+}
+
+impl BlobReference {
+  /// returns the reference to `payload`
+  pub fn new(payload: &[u8]) -> Self {
+    BlobReference {
+      hash: NimbleDigest::digest(payload),
+      size: payload.len() as u64,
+    }
+  }
+
+  pub fn get_hash(&self) -> &NimbleDigest {
+    &self.hash
+  }
+
+  pub fn get_size(&self) -> u64 {
+    self.size
+  }
+
+  /// returns true if `block_bytes` claim to be a blob reference
+  pub fn is_blob_reference(block_bytes: &[u8]) -> bool {
+    block_bytes.starts_with(BLOB_REFERENCE_MAGIC)
+  }
+
+  /// checks that `payload` is the blob that this reference names
+  pub fn matches(&self, payload: &[u8]) -> bool {
+    *self == BlobReference::new(payload)
+  }
+
+  /// returns the block bytes of the reference: the magic, the hash, and the size little endian
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = BLOB_REFERENCE_MAGIC.to_vec();
+    bytes.extend_from_slice(&self.hash.to_bytes());
+    bytes.extend_from_slice(&self.size.to_le_bytes());
+    bytes
+  }
+
+  pub fn from_bytes(block_bytes: &[u8]) -> Result<Self, VerificationError> {
+    let hash_len = NimbleDigest::num_bytes();
+    if !Self::is_blob_reference(block_bytes)
+      || block_bytes.len() != BLOB_REFERENCE_MAGIC.len() + hash_len + 8
+    {
+      return Err(VerificationError::InvalidBlobReference);
+    }
+    let mut reader = Reader::new("BlobReference", &block_bytes[BLOB_REFERENCE_MAGIC.len()..]);
+    let res = reader
+      .take(hash_len)
+      .and_then(NimbleDigest::from_bytes)
+      .and_then(|hash| Ok((hash, reader.read_u64()?)));
+    if res.is_err() {
+      return Err(VerificationError::InvalidBlobReference);
+    }
+    let (hash, size) = res.unwrap();
+    Ok(BlobReference { hash, size })
+  }
+}
+
+const EXTERNAL_BLOCK_MAGIC: &[u8] = b"NIMBLE-EXTERNAL-BLOCK";
+
+/// The block of an entry appended by hash only: the ledger holds the hash of the payload that the
+/// client computed, and the client stores the payload elsewhere. Endorsers sign the entry as any
+/// other, so the receipts bind the ledger to the payload through its hash.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExternalBlock {
+  hash: NimbleDigest,
+}
+
+impl ExternalBlock {
+  /// returns the block of a payload whose hash the client computed, which must be a digest
+  pub fn new(hash_bytes: &[u8]) -> Result<Self, VerificationError> {
+    let hash =
+      NimbleDigest::from_bytes(hash_bytes).map_err(|_e| VerificationError::InvalidExternalBlock)?;
+    Ok(ExternalBlock { hash })
+  }
+
+  /// returns the block of `payload`
+  pub fn for_payload(payload: &[u8]) -> Self {
+    ExternalBlock {
+      hash: NimbleDigest::digest(payload),
+    }
+  }
+
+  pub fn get_hash(&self) -> &NimbleDigest {
+    &self.hash
+  }
+
+  /// returns true if `block_bytes` claim to hold the hash of an external payload
+  pub fn is_external_block(block_bytes: &[u8]) -> bool {
+    block_bytes.starts_with(EXTERNAL_BLOCK_MAGIC)
+  }
+
+  /// checks that `payload` is the payload that this block holds the hash of
+  pub fn matches(&self, payload: &[u8]) -> bool {
+    self.hash == NimbleDigest::digest(payload)
+  }
+
+  /// returns the block bytes: the magic and the hash
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = EXTERNAL_BLOCK_MAGIC.to_vec();
+    bytes.extend_from_slice(&self.hash.to_bytes());
+    bytes
+  }
+
+  pub fn from_bytes(block_bytes: &[u8]) -> Result<Self, VerificationError> {
+    if !Self::is_external_block(block_bytes) {
+      return Err(VerificationError::InvalidExternalBlock);
+    }
+    Self::new(&block_bytes[EXTERNAL_BLOCK_MAGIC.len()..])
+  }
+}
+
+/// The blocks that a deployment accepts in appends. By default any block is accepted, except
+/// one that starts like an envelope but is not a well-formed one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BlockValidation {
+  /// the largest block in bytes, or None for no limit
+  pub max_block_size: Option<usize>,
+  /// if set, every block must be an envelope of one of these content types
+  pub content_types: Option<HashSet<String>>,
+}
+
+impl BlockValidation {
+  /// checks `block_bytes` against the deployment's limits
+  pub fn validate(&self, block_bytes: &[u8]) -> Result<(), VerificationError> {
+    if self
+      .max_block_size
+      .is_some_and(|max| block_bytes.len() > max)
+    {
+      return Err(VerificationError::BlockTooLarge);
+    }
+    let envelope = if BlockEnvelope::is_envelope(block_bytes) {
+      Some(BlockEnvelope::from_bytes(block_bytes)?)
+    } else {
+      None
+    };
+    if let Some(content_types) = &self.content_types {
+      match envelope {
+        Some(envelope) if content_types.contains(envelope.get_content_type()) => {},
+        _ => return Err(VerificationError::ContentTypeNotAllowed),
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Details attached to the `Unavailable` status an endorser returns while it is locked for a
+/// view change (initialized but not yet activated), and to the `FailedPrecondition` status it
+/// returns while the coordinator has locked it; callers should retry or route around it
+pub const ENDORSER_LOCKED_DETAILS: &[u8] = b"locked for view change";
+
+impl CustomSerde for Nonce {
+  fn to_bytes(&self) -> Vec<u8> {
+    self.data.to_vec()
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Nonce, CustomSerdeError> {
+    match Nonce::new(bytes) {
+      Ok(nonce) => Ok(nonce),
+      Err(_) => Err(CustomSerdeError::IncorrectLength),
+    }
+  }
+}
+impl CustomSerde for Nonces {
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut data = Vec::with_capacity(self.nonces.len() * Nonce::num_bytes());
+    for nonce in self.get() {
+      data.extend(nonce.to_bytes());
+    }
+    data
+  }
+
+  #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+  fn from_bytes(bytes: &[u8]) -> Result<Nonces, CustomSerdeError> {
+    if bytes.len() % Nonce::num_bytes() != 0 {
       Err(CustomSerdeError::IncorrectLength)
     } else {
-      let prev = NimbleDigest::from_bytes(&bytes[0..digest_len])?;
-      let block_hash = NimbleDigest::from_bytes(&bytes[digest_len..2 * digest_len])?;
-      let height = u64::from_le_bytes(
-        bytes[2 * digest_len..]
-          .try_into()
-          .map_err(|_| CustomSerdeError::IncorrectLength)?,
-      ) as usize;
-      Ok(MetaBlock {
-        prev,
-        block_hash,
-        height,
-      })
+      let mut nonces = Nonces::new();
+      for nonce in bytes.chunks_exact(Nonce::num_bytes()) {
+        nonces.add(Nonce::from_bytes(nonce)?);
+      }
+      Ok(nonces)
     }
   }
 }
 
+impl CustomSerde for Block {
+  fn to_bytes(&self) -> Vec<u8> {
+    self.block.clone()
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Block, CustomSerdeError> {
+    Ok(Block {
+      block: bytes.to_vec(),
+    })
+  }
+}
+
+impl CustomSerde for NimbleDigest {
+  fn to_bytes(&self) -> Vec<u8> {
+    self.digest.as_slice().to_vec()
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<NimbleDigest, CustomSerdeError> {
+    let digest_len = NimbleDigest::num_bytes();
+    if bytes.len() != digest_len {
+      Err(CustomSerdeError::IncorrectLength)
+    } else {
+      let digest = GenericArray::from_slice(&bytes[0..digest_len]);
+      Ok(NimbleDigest { digest: *digest })
+    }
+  }
+}
+
+// metablocks without a timestamp keep the encoding of `METABLOCK_VERSION`, so that the endorsers
+// and clients that predate timestamps can still read them
+impl CustomSerde for MetaBlock {
+  fn to_bytes(&self) -> Vec<u8> {
+    match self.timestamp {
+      None => {
+        let mut bytes = vec![METABLOCK_VERSION];
+        bytes.extend(self.body_bytes());
+        bytes
+      },
+      Some(timestamp) => {
+        let mut bytes = vec![TIMESTAMPED_METABLOCK_VERSION];
+        bytes.extend(self.body_bytes());
+        bytes.extend(&timestamp.to_le_bytes());
+        bytes
+      },
+    }
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<MetaBlock, CustomSerdeError> {
+    if bytes.len() == MetaBlock::num_legacy_bytes() {
+      return MetaBlock::from_body_bytes(bytes);
+    }
+    let version = if bytes.len() == MetaBlock::num_bytes() {
+      METABLOCK_VERSION
+    } else if bytes.len() == MetaBlock::num_timestamped_bytes() {
+      TIMESTAMPED_METABLOCK_VERSION
+    } else {
+      return Err(CustomSerdeError::IncorrectLength);
+    };
+    let mut reader = Reader::new("MetaBlock", bytes);
+    if reader.read_u8()? != version {
+      return Err(CustomSerdeError::UnsupportedVersion);
+    }
+    let metablock = MetaBlock::from_body_bytes(reader.take(MetaBlock::num_body_bytes())?)?;
+    if version == METABLOCK_VERSION {
+      return Ok(metablock);
+    }
+    let timestamp = reader.read_u64()?;
+    reader.finish()?;
+    Ok(metablock.with_timestamp(Some(timestamp)))
+  }
+}
+
 impl CustomSerde for IdSig {
   fn to_bytes(&self) -> Vec<u8> {
     let mut bytes = Vec::new();
@@ -1222,17 +2944,25 @@ impl CustomSerde for IdSig {
     bytes
   }
 
+  // an untagged P-256 key and signature get the scheme identifier, so that the signer is known by
+  // the same id whichever layout its receipts were read in, and an entry's receipts are written
+  // back in one layout
   fn from_bytes(bytes: &[u8]) -> Result<IdSig, CustomSerdeError> {
-    if bytes.len() != IdSig::num_bytes() {
-      eprintln!(
-        "bytes len={} but IdSig expects {}",
-        bytes.len(),
-        IdSig::num_bytes()
-      );
+    let (id_len, tag) = if bytes.len() == IdSig::num_bytes() {
+      (PublicKey::num_bytes(), None)
+    } else if bytes.len() == IdSig::num_untagged_bytes() {
+      (
+        PublicKey::num_untagged_bytes(),
+        Some(SignatureScheme::P256.get_id()),
+      )
+    } else {
       return Err(CustomSerdeError::IncorrectLength);
-    }
-    let id = bytes[0..PublicKey::num_bytes()].to_vec();
-    let sig = bytes[PublicKey::num_bytes()..].to_vec();
+    };
+    let mut reader = Reader::new("IdSig", bytes);
+    let mut id = tag.into_iter().collect::<Vec<u8>>();
+    id.extend(reader.take(id_len)?);
+    let mut sig = tag.into_iter().collect::<Vec<u8>>();
+    sig.extend(reader.remaining());
 
     Ok(IdSig { id, sig })
   }
@@ -1248,19 +2978,18 @@ impl CustomSerde for Receipt {
   }
 
   fn from_bytes(bytes: &[u8]) -> Result<Receipt, CustomSerdeError> {
-    if bytes.len() != Receipt::num_bytes() {
-      eprintln!("bytes len {} is incorrect for receipt", bytes.len());
-      return Err(CustomSerdeError::IncorrectLength);
-    }
+    let metablock_len = match Receipt::layouts()
+      .into_iter()
+      .find(|layout| Receipt::len_of_layout(*layout) == bytes.len())
+    {
+      Some((metablock_len, _id_sig_len)) => metablock_len,
+      None => return Err(CustomSerdeError::IncorrectLength),
+    };
 
-    let view = NimbleDigest::from_bytes(&bytes[0..NimbleDigest::num_bytes()])?;
-    let metablock = MetaBlock::from_bytes(
-      &bytes[NimbleDigest::num_bytes()..NimbleDigest::num_bytes() + MetaBlock::num_bytes()],
-    )?;
-    let id_sig = IdSig::from_bytes(
-      &bytes[NimbleDigest::num_bytes() + MetaBlock::num_bytes()
-        ..NimbleDigest::num_bytes() + MetaBlock::num_bytes() + IdSig::num_bytes()],
-    )?;
+    let mut reader = Reader::new("Receipt", bytes);
+    let view = NimbleDigest::from_bytes(reader.take(NimbleDigest::num_bytes())?)?;
+    let metablock = MetaBlock::from_bytes(reader.take(metablock_len)?)?;
+    let id_sig = IdSig::from_bytes(reader.remaining())?;
 
     Ok(Receipt {
       view,
@@ -1288,16 +3017,31 @@ impl CustomSerde for Receipts {
     bytes
   }
 
+  // the receipts of an entry are all for one metablock and were written in one layout, so they
+  // have the same length: that of the current layout or of one that `Receipt::from_bytes` still
+  // accepts
   fn from_bytes(bytes: &[u8]) -> Result<Receipts, CustomSerdeError> {
-    if bytes.len() % Receipt::num_bytes() != 0 {
+    let res = Receipts::from_receipt_bytes(bytes, Receipt::num_bytes());
+    if res.is_err() && !bytes.is_empty() {
+      for layout in Receipt::layouts().into_iter().skip(1) {
+        if let Ok(receipts) = Receipts::from_receipt_bytes(bytes, Receipt::len_of_layout(layout)) {
+          return Ok(receipts);
+        }
+      }
+    }
+    res
+  }
+}
+
+impl Receipts {
+  #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+  fn from_receipt_bytes(bytes: &[u8], receipt_len: usize) -> Result<Receipts, CustomSerdeError> {
+    if bytes.len() % receipt_len != 0 {
       return Err(CustomSerdeError::IncorrectLength);
     }
-    let mut pos = 0;
     let mut receipts = Receipts::new();
-    while pos < bytes.len() {
-      let receipt = Receipt::from_bytes(&bytes[pos..pos + Receipt::num_bytes()])?;
-      receipts.add(&receipt);
-      pos += Receipt::num_bytes();
+    for receipt in bytes.chunks(receipt_len) {
+      receipts.add(&Receipt::from_bytes(receipt)?);
     }
     Ok(receipts)
   }
@@ -1318,7 +3062,7 @@ impl NimbleHashTrait for Block {
 
 impl NimbleHashTrait for MetaBlock {
   fn hash(&self) -> NimbleDigest {
-    NimbleDigest::digest(&self.to_bytes())
+    NimbleDigest::digest(&self.body_bytes())
   }
 }
 
@@ -1331,6 +3075,7 @@ impl NimbleHashTrait for Nonces {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::signature::{PrivateKey, PrivateKeyTrait};
   use rand::Rng;
 
   #[test]
@@ -1346,6 +3091,24 @@ mod tests {
   }
 
   #[test]
+  pub fn test_nonce_equality() {
+    let nonce_1 = Nonce::random();
+    let nonce_2 = Nonce::random();
+    assert_ne!(nonce_1, nonce_2);
+    assert_eq!(nonce_1, Nonce::new(&nonce_1.to_bytes()).unwrap());
+
+    let mut bytes = nonce_1.to_bytes();
+    bytes[15] ^= 1;
+    assert_ne!(nonce_1, Nonce::new(&bytes).unwrap());
+
+    let mut nonce = nonce_1;
+    nonce.zeroize();
+    assert_eq!(nonce, Nonce::default());
+  }
+
+  // the expected digests are those of SHA-256
+  #[test]
+  #[cfg(not(feature = "sha3"))]
   pub fn test_nimble_digest_hash_correctness_and_equality() {
     let message_1 = "1".as_bytes();
     let message_2 = "2".as_bytes();
@@ -1373,7 +3136,9 @@ mod tests {
     );
   }
 
+  // the expected digests are those of SHA-256
   #[test]
+  #[cfg(not(feature = "sha3"))]
   pub fn test_block_hash_results() {
     let message_1 = "1".as_bytes();
 
@@ -1407,4 +3172,1078 @@ mod tests {
     let hash = produce_hash_of_state(&map);
     assert_ne!(hash, NimbleDigest::default());
   }
+
+  #[test]
+  pub fn test_ledger_snapshot_encoding() {
+    let snapshot = LedgerSnapshot::new(
+      &NimbleDigest::digest("handle".as_bytes()),
+      7,
+      vec![1, 2, 3],
+      Nonces::new().to_bytes(),
+      vec![4; 16],
+      Receipts::new().to_bytes(),
+    );
+    let bytes = snapshot.to_bytes();
+    assert_eq!(LedgerSnapshot::from_bytes(&bytes), Ok(snapshot));
+    assert_eq!(
+      LedgerSnapshot::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(CustomSerdeError::InternalError)
+    );
+
+    // a length field that claims more than the bytes hold is rejected before it is allocated
+    let mut forged = u64::MAX.to_le_bytes().to_vec();
+    forged.extend(&bytes[8..]);
+    assert_eq!(
+      LedgerSnapshot::from_bytes(&forged),
+      Err(CustomSerdeError::InternalError)
+    );
+  }
+
+  #[test]
+  pub fn test_parsers_reject_malformed_input() {
+    let metablock = MetaBlock::new(
+      &NimbleDigest::digest("prev".as_bytes()),
+      &NimbleDigest::digest("block".as_bytes()),
+      7,
+    );
+    let sk = PrivateKey::new();
+    let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+    let receipt = Receipt::new(
+      NimbleDigest::digest("view".as_bytes()),
+      metablock.clone(),
+      IdSig::new(sk.get_public_key().unwrap(), sig),
+    );
+    let envelope = BlockEnvelope::new("text/plain", b"client", 1, b"payload")
+      .to_bytes()
+      .unwrap();
+    let seeds = vec![
+      metablock.to_bytes(),
+      receipt.to_bytes(),
+      envelope,
+      BlobReference::new(b"blob").to_bytes(),
+      ExternalBlock::for_payload(b"payload").to_bytes(),
+      LedgerSnapshot::new(&metablock.hash(), 7, vec![1], vec![], vec![2; 16], vec![]).to_bytes(),
+    ];
+
+    // every truncation and every corrupted byte of a valid encoding is either rejected or
+    // decoded, and none of the parsers panics on it
+    let mut rng = rand::thread_rng();
+    for seed in seeds {
+      let mut inputs = (0..seed.len())
+        .map(|len| seed[..len].to_vec())
+        .collect::<Vec<_>>();
+      for pos in 0..seed.len() {
+        let mut corrupted = seed.clone();
+        corrupted[pos] = rng.gen();
+        inputs.push(corrupted);
+      }
+      for input in inputs {
+        let _ = MetaBlock::from_bytes(&input);
+        let _ = Receipt::from_bytes(&input);
+        let _ = Receipts::from_bytes(&input);
+        let _ = KeyHandover::from_bytes(&input);
+        let _ = LedgerSnapshot::from_bytes(&input);
+        let _ = BlockEnvelope::from_bytes(&input);
+        let _ = BlobReference::from_bytes(&input);
+        let _ = ExternalBlock::from_bytes(&input);
+        let _ = EndorsementPolicy::from_genesis_bytes(&input);
+        let _ = AccessPolicy::from_genesis_bytes(&input);
+      }
+    }
+
+    // a truncated envelope reports where the payload that is cut short starts
+    let envelope = BlockEnvelope::new("text/plain", b"client", 1, b"payload")
+      .to_bytes()
+      .unwrap();
+    let body = &envelope[BLOCK_ENVELOPE_MAGIC.len()..envelope.len() - 1];
+    assert_eq!(
+      BlockEnvelope::parse(body),
+      Err(CustomSerdeError::Truncated {
+        what: "BlockEnvelope",
+        offset: body.len() - (b"payload".len() - 1),
+        needed: 1,
+      })
+    );
+  }
+
+  #[test]
+  pub fn test_baseline_receipt_decodes() {
+    // a receipt serialized by the code before this series: the view, an unversioned metablock,
+    // and an untagged P-256 key and signature on the view's digest with the metablock's hash
+    let bytes = hex::decode(concat!(
+      "2bcb43cbc8f6b7ef66331532881143fcbae60a879db3a8fb853f645bb24c2b3c",
+      "84fd9bac333ad79154348296204fa7f8c537a96e08983e5f73b3f5aca8e8edf7",
+      "496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+      "0700000000000000",
+      "039ff9465583eaa03442a1c2f24d899f1992096a6c3aad3440b94a3fa1e526bd4b",
+      "48cd73245a83c7f2754ac1ee07fdad73745512562830c45e3878180f4710b35c",
+      "119f60e3720e9190acbcb80d48b482bff54424601efa8ab1765fffeee5b5053d",
+    ))
+    .unwrap();
+    assert_eq!(bytes.len(), Receipt::num_legacy_bytes());
+
+    let receipt = Receipt::from_bytes(&bytes).unwrap();
+    let view = NimbleDigest::digest(b"view");
+    let metablock = MetaBlock::new(
+      &NimbleDigest::digest(b"prev"),
+      &NimbleDigest::digest(b"block"),
+      7,
+    );
+    assert_eq!(receipt.get_view(), &view);
+    assert_eq!(receipt.get_metablock(), &metablock);
+    // the signer gets the tag of P-256, as the keys of the view ledger do
+    let id_sig = receipt.get_id_sig();
+    assert_eq!(id_sig.get_id()[0], SignatureScheme::P256.get_id());
+    assert_eq!(id_sig.get_scheme().unwrap(), SignatureScheme::P256);
+    assert!(id_sig
+      .verify(&view.digest_with(&metablock.hash()).to_bytes())
+      .is_ok());
+
+    // receipts in the baseline layout decode as a set, and are written back in the current one
+    let mut two = bytes.clone();
+    two.extend(&bytes);
+    let receipts = Receipts::from_bytes(&two).unwrap();
+    assert_eq!(receipts.get_signer_ids().len(), 1);
+    let current = receipts.to_bytes();
+    assert_eq!(current.len(), Receipt::num_bytes());
+    let reread = Receipt::from_bytes(&current).unwrap();
+    assert_eq!(reread.get_id_sig().get_id(), id_sig.get_id());
+    assert!(IdSig::from_bytes(&bytes[bytes.len() - 1..]).is_err());
+  }
+
+  #[test]
+  pub fn test_metablock_encoding() {
+    let prev = NimbleDigest::digest("prev".as_bytes());
+    let block_hash = NimbleDigest::digest("block".as_bytes());
+    let metablock = MetaBlock::new(&prev, &block_hash, 7);
+
+    let bytes = metablock.to_bytes();
+    assert_eq!(bytes.len(), MetaBlock::num_bytes());
+    assert_eq!(bytes[0], METABLOCK_VERSION);
+    assert_eq!(MetaBlock::from_bytes(&bytes).unwrap(), metablock);
+
+    // metablocks stored before the encoding was versioned still decode, and hash alike
+    let legacy = bytes[1..].to_vec();
+    assert_eq!(legacy.len(), MetaBlock::num_legacy_bytes());
+    assert_eq!(MetaBlock::from_bytes(&legacy).unwrap(), metablock);
+    assert_eq!(MetaBlock::hash_of_bytes(&legacy), metablock.hash());
+    assert_eq!(MetaBlock::hash_of_bytes(&bytes), metablock.hash());
+
+    let mut unknown = bytes.clone();
+    unknown[0] = METABLOCK_VERSION + 1;
+    assert_eq!(
+      MetaBlock::from_bytes(&unknown),
+      Err(CustomSerdeError::UnsupportedVersion)
+    );
+    assert_eq!(
+      MetaBlock::from_bytes(&bytes[2..]),
+      Err(CustomSerdeError::IncorrectLength)
+    );
+
+    // receipts with either encoding of their metablock decode to the same receipt
+    let sk = PrivateKey::new();
+    let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+    let receipt = Receipt::new(
+      prev,
+      metablock,
+      IdSig::new(sk.get_public_key().unwrap(), sig),
+    );
+    let receipt_bytes = receipt.to_bytes();
+    assert_eq!(receipt_bytes.len(), Receipt::num_bytes());
+    let mut legacy_receipt = receipt_bytes.clone();
+    legacy_receipt.remove(NimbleDigest::num_bytes());
+    let decoded = Receipt::from_bytes(&legacy_receipt).unwrap();
+    assert_eq!(decoded.to_bytes(), receipt_bytes);
+
+    // a timestamp has an encoding of its own, and is left out of the hash
+    let timestamped = receipt
+      .get_metablock()
+      .clone()
+      .with_timestamp(Some(1_700_000_000_000));
+    let bytes = timestamped.to_bytes();
+    assert_eq!(bytes.len(), MetaBlock::num_timestamped_bytes());
+    assert_eq!(bytes[0], TIMESTAMPED_METABLOCK_VERSION);
+    assert_eq!(MetaBlock::from_bytes(&bytes).unwrap(), timestamped);
+    assert_eq!(timestamped.hash(), receipt.get_metablock().hash());
+    assert_eq!(MetaBlock::hash_of_bytes(&bytes), timestamped.hash());
+    let mut unknown = bytes.clone();
+    unknown[0] = METABLOCK_VERSION;
+    assert_eq!(
+      MetaBlock::from_bytes(&unknown),
+      Err(CustomSerdeError::UnsupportedVersion)
+    );
+    let timestamped_receipt = Receipt::new(prev, timestamped, receipt.get_id_sig().clone());
+    let mut receipts = Receipts::new();
+    receipts.add(&timestamped_receipt);
+    let receipts_bytes = receipts.to_bytes();
+    assert_eq!(receipts_bytes.len(), Receipt::num_timestamped_bytes());
+    assert_eq!(
+      Receipts::from_bytes(&receipts_bytes)
+        .unwrap()
+        .get_metablock()
+        .unwrap()
+        .get_timestamp(),
+      Some(1_700_000_000_000)
+    );
+  }
+
+  #[test]
+  pub fn test_metablock_fields() {
+    let genesis = MetaBlock::genesis(&NimbleDigest::digest(b"genesis"));
+    let block_hash = NimbleDigest::digest(b"block");
+    let next = genesis.next(&block_hash).unwrap();
+    assert_eq!(next, MetaBlock::new(&genesis.hash(), &block_hash, 1));
+
+    // the fields take a metablock apart and put it back together
+    let timestamped = next.clone().with_timestamp(Some(42));
+    let fields = timestamped.to_fields();
+    assert_eq!(fields.prev, genesis.hash());
+    assert_eq!(*timestamped.get_block_hash(), fields.block_hash);
+    assert_eq!((fields.height, fields.timestamp), (1, Some(42)));
+    assert_eq!(MetaBlock::from(fields), timestamped);
+
+    // the builders change one field at a time
+    let other = NimbleDigest::digest(b"other");
+    let rebuilt = MetaBlock::default()
+      .with_prev(&genesis.hash())
+      .with_block_hash(&other)
+      .with_height(1);
+    assert_eq!(rebuilt, MetaBlock::new(&genesis.hash(), &other, 1));
+    assert_eq!(rebuilt.with_block_hash(&block_hash).hash(), next.hash());
+
+    assert!(MetaBlock::default()
+      .with_height(usize::MAX)
+      .next(&block_hash)
+      .is_none());
+  }
+
+  #[test]
+  pub fn test_retain_strongest_receipts() {
+    let metablock = MetaBlock::genesis(&NimbleDigest::digest("block".as_bytes()));
+    let old_view = NimbleDigest::digest("old view".as_bytes());
+    let new_view = NimbleDigest::digest("new view".as_bytes());
+
+    let mut receipts = Receipts::new();
+    let add_receipts = |receipts: &mut Receipts, view: &NimbleDigest, num: usize| {
+      for _ in 0..num {
+        let sk = PrivateKey::new();
+        let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+        let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+        receipts.add(&Receipt::new(*view, metablock.clone(), id_sig));
+      }
+    };
+    add_receipts(&mut receipts, &old_view, 1);
+    add_receipts(&mut receipts, &new_view, 3);
+    assert_eq!(receipts.get().len(), 2);
+    assert_eq!(receipts.get_signer_ids().len(), 4);
+
+    receipts.retain_strongest(2);
+    assert_eq!(receipts.get().len(), 2);
+
+    receipts.retain_strongest(1);
+    assert_eq!(receipts.get().len(), 1);
+    let ex_meta_block = ExtendedMetaBlock::new(&new_view, &metablock);
+    assert_eq!(receipts.get()[&ex_meta_block].len(), 3);
+  }
+
+  #[test]
+  pub fn test_verify_all_id_sigs() {
+    let message = NimbleDigest::digest("message".as_bytes()).to_bytes();
+    let mut id_sigs = (0..7)
+      .map(|_| {
+        let sk = PrivateKey::new();
+        IdSig::new(sk.get_public_key().unwrap(), sk.sign(&message).unwrap())
+      })
+      .collect::<Vec<IdSig>>();
+    assert_eq!(IdSig::verify_all(&[], &message), Ok(()));
+    assert_eq!(IdSig::verify_all(&id_sigs, &message), Ok(()));
+
+    // one signature on another message fails the lot, wherever it is in the quorum
+    let sk = PrivateKey::new();
+    let forged = IdSig::new(
+      sk.get_public_key().unwrap(),
+      sk.sign("other".as_bytes()).unwrap(),
+    );
+    id_sigs.insert(3, forged);
+    assert_eq!(
+      IdSig::verify_all(&id_sigs, &message),
+      Err(VerificationError::InvalidSignature)
+    );
+  }
+
+  #[test]
+  pub fn test_block_envelope() {
+    let envelope = BlockEnvelope::new("application/json", b"client-1", 1700000000000, b"{}");
+    let bytes = envelope.to_bytes().unwrap();
+    assert!(BlockEnvelope::is_envelope(&bytes));
+    assert_eq!(BlockEnvelope::from_bytes(&bytes), Ok(envelope.clone()));
+
+    // only the canonical encoding decodes
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(
+      BlockEnvelope::from_bytes(&trailing),
+      Err(VerificationError::InvalidBlockEnvelope)
+    );
+    assert_eq!(
+      BlockEnvelope::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(VerificationError::InvalidBlockEnvelope)
+    );
+    assert!(BlockEnvelope::new("", b"", 0, b"").to_bytes().is_err());
+    assert!(BlockEnvelope::new("text plain", b"", 0, b"")
+      .to_bytes()
+      .is_err());
+
+    // plain blocks pass unless the deployment requires envelopes
+    let mut validation = BlockValidation::default();
+    assert!(validation.validate(b"plain").is_ok());
+    assert!(validation.validate(&bytes).is_ok());
+    assert_eq!(
+      validation.validate(&trailing),
+      Err(VerificationError::InvalidBlockEnvelope)
+    );
+    validation.max_block_size = Some(bytes.len() - 1);
+    assert_eq!(
+      validation.validate(&bytes),
+      Err(VerificationError::BlockTooLarge)
+    );
+    validation.max_block_size = Some(bytes.len());
+    validation.content_types = Some(HashSet::from(["application/json".to_string()]));
+    assert!(validation.validate(&bytes).is_ok());
+    assert_eq!(
+      validation.validate(b"plain"),
+      Err(VerificationError::ContentTypeNotAllowed)
+    );
+    let other = BlockEnvelope::new("text/plain", b"", 0, b"")
+      .to_bytes()
+      .unwrap();
+    assert_eq!(
+      validation.validate(&other),
+      Err(VerificationError::ContentTypeNotAllowed)
+    );
+  }
+
+  #[test]
+  pub fn test_blob_reference() {
+    let payload = vec![9u8; 4096];
+    let reference = BlobReference::new(&payload);
+    assert_eq!(reference.get_size(), 4096);
+    assert!(reference.matches(&payload));
+    assert!(!reference.matches(&payload[1..]));
+
+    let bytes = reference.to_bytes();
+    assert!(BlobReference::is_blob_reference(&bytes));
+    assert_eq!(BlobReference::from_bytes(&bytes), Ok(reference));
+    assert_eq!(
+      BlobReference::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(VerificationError::InvalidBlobReference)
+    );
+    assert!(BlobReference::from_bytes(&payload).is_err());
+  }
+
+  #[test]
+  pub fn test_external_block() {
+    let payload = vec![7u8; 4096];
+    let hash = NimbleDigest::digest(&payload).to_bytes();
+    let block = ExternalBlock::new(&hash).unwrap();
+    assert_eq!(block, ExternalBlock::for_payload(&payload));
+    assert!(block.matches(&payload));
+    assert!(!block.matches(&payload[1..]));
+    assert_eq!(
+      ExternalBlock::new(&hash[1..]),
+      Err(VerificationError::InvalidExternalBlock)
+    );
+
+    let bytes = block.to_bytes();
+    assert!(ExternalBlock::is_external_block(&bytes));
+    assert_eq!(ExternalBlock::from_bytes(&bytes), Ok(block));
+    assert_eq!(
+      ExternalBlock::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(VerificationError::InvalidExternalBlock)
+    );
+    assert!(ExternalBlock::from_bytes(&hash).is_err());
+  }
+
+  #[test]
+  pub fn test_access_policy() {
+    let app_bytes = "app".as_bytes();
+    let handle_bytes = "handle".as_bytes();
+    let writer = PrivateKey::new();
+    let other = PrivateKey::new();
+    let writer_pk = writer.get_public_key().unwrap().to_bytes();
+    let other_pk = other.get_public_key().unwrap().to_bytes();
+
+    // encoding, nested after the endorsement policy
+    let (policy, bytes) = AccessPolicy::from_genesis_bytes(app_bytes).unwrap();
+    assert!(policy.is_open());
+    assert_eq!(bytes, app_bytes);
+    let policy = AccessPolicy::new(vec![writer_pk.clone()], ReadVisibility::Writers);
+    let genesis_bytes =
+      EndorsementPolicy::All.to_genesis_bytes(&policy.to_genesis_bytes(app_bytes));
+    let (endorsement_policy, rest) = EndorsementPolicy::from_genesis_bytes(&genesis_bytes).unwrap();
+    assert_eq!(endorsement_policy, EndorsementPolicy::All);
+    let (parsed, bytes) = AccessPolicy::from_genesis_bytes(rest).unwrap();
+    assert_eq!(parsed, policy);
+    assert_eq!(bytes, app_bytes);
+    let mut truncated = policy.to_genesis_bytes(&[]);
+    truncated.pop();
+    assert!(AccessPolicy::from_genesis_bytes(&truncated).is_err());
+
+    // enforcement
+    let sign = |sk: &PrivateKey, request: &AccessRequest| {
+      let sig = sk.sign(&request.message(handle_bytes)).unwrap();
+      (sk.get_public_key().unwrap().to_bytes(), sig.to_bytes())
+    };
+    let append = AccessRequest::Append {
+      block: app_bytes,
+      expected_height: 1,
+    };
+    let (pk, sig) = sign(&writer, &append);
+    assert!(policy
+      .authorize(handle_bytes, &append, Some((&pk, &sig)))
+      .is_ok());
+    assert_eq!(
+      policy.authorize(handle_bytes, &append, None),
+      Err(VerificationError::AccessDenied)
+    );
+    // a signed append cannot be replayed at another height
+    let replayed = AccessRequest::Append {
+      block: app_bytes,
+      expected_height: 2,
+    };
+    assert_eq!(
+      policy.authorize(handle_bytes, &replayed, Some((&pk, &sig))),
+      Err(VerificationError::AccessDenied)
+    );
+    // a signed batch covers its blocks in order
+    let blocks = vec![app_bytes.to_vec(), vec![1]];
+    let batch = AccessRequest::AppendBatch {
+      blocks: &blocks,
+      expected_height: 1,
+    };
+    let (batch_pk, batch_sig) = sign(&writer, &batch);
+    assert!(policy
+      .authorize(handle_bytes, &batch, Some((&batch_pk, &batch_sig)))
+      .is_ok());
+    let reordered = vec![vec![1], app_bytes.to_vec()];
+    let reordered = AccessRequest::AppendBatch {
+      blocks: &reordered,
+      expected_height: 1,
+    };
+    assert_eq!(
+      policy.authorize(handle_bytes, &reordered, Some((&batch_pk, &batch_sig))),
+      Err(VerificationError::AccessDenied)
+    );
+    // a valid signature from a key outside the policy is rejected
+    let (pk, sig) = sign(&other, &append);
+    assert_eq!(pk, other_pk);
+    assert_eq!(
+      policy.authorize(handle_bytes, &append, Some((&pk, &sig))),
+      Err(VerificationError::AccessDenied)
+    );
+
+    let read = AccessRequest::ReadByIndex { index: 0 };
+    assert!(policy.authorize(handle_bytes, &read, None).is_err());
+    let (pk, sig) = sign(&writer, &read);
+    assert!(policy
+      .authorize(handle_bytes, &read, Some((&pk, &sig)))
+      .is_ok());
+    let public = AccessPolicy::new(vec![writer_pk], ReadVisibility::Public);
+    assert!(public.authorize(handle_bytes, &read, None).is_ok());
+    assert!(public.authorize(handle_bytes, &append, None).is_err());
+  }
+
+  #[test]
+  pub fn test_inclusion_proof() {
+    let entries = (0..4u8)
+      .map(|i| (vec![i], Nonces::new().to_bytes()))
+      .collect::<Vec<_>>();
+    let block_hashes = entries
+      .iter()
+      .map(|(block, nonces)| {
+        compute_aggregated_block_hash(
+          &NimbleDigest::digest(block).to_bytes(),
+          &NimbleDigest::digest(nonces).to_bytes(),
+        )
+      })
+      .collect::<Vec<_>>();
+    let mut metablocks = vec![MetaBlock::genesis(&block_hashes[0])];
+    for (i, block_hash) in block_hashes.iter().enumerate().skip(1) {
+      let prev = metablocks[i - 1].hash();
+      metablocks.push(MetaBlock::new(&prev, block_hash, i));
+    }
+
+    let proof = InclusionProof::new(
+      metablocks[1].clone(),
+      block_hashes[2..].to_vec(),
+      Vec::new(),
+      Vec::new(),
+      Vec::new(),
+    );
+    let (block, nonces) = &entries[1];
+    assert_eq!(
+      proof.verify_chain(block, nonces, 1),
+      Ok(metablocks[3].clone())
+    );
+    assert_eq!(
+      proof.verify_chain(&entries[2].0, nonces, 1),
+      Err(VerificationError::InvalidInclusionProof)
+    );
+    assert_eq!(
+      proof.verify_chain(block, nonces, 2),
+      Err(VerificationError::InvalidInclusionProof)
+    );
+
+    // a proof that skips an entry does not lead to the tail
+    let proof = InclusionProof::new(
+      metablocks[1].clone(),
+      block_hashes[3..].to_vec(),
+      Vec::new(),
+      Vec::new(),
+      Vec::new(),
+    );
+    assert_ne!(
+      proof.verify_chain(block, nonces, 1),
+      Ok(metablocks[3].clone())
+    );
+
+    // a checkpoint proof leads from a checkpoint to a later entry
+    let proof = CheckpointProof::new(
+      1,
+      Vec::new(),
+      Vec::new(),
+      Vec::new(),
+      block_hashes[2..].to_vec(),
+    );
+    let (block, nonces) = &entries[3];
+    assert_eq!(
+      proof.verify_chain(&metablocks[1], block, nonces, 3),
+      Ok(metablocks[3].clone())
+    );
+    assert_eq!(
+      proof.verify_chain(&metablocks[1], &entries[2].0, nonces, 3),
+      Err(VerificationError::InvalidCheckpointProof)
+    );
+    assert_eq!(
+      proof.verify_chain(&metablocks[0], block, nonces, 3),
+      Err(VerificationError::InvalidCheckpointProof)
+    );
+    assert_eq!(
+      proof.verify_chain(&metablocks[1], block, nonces, 2),
+      Err(VerificationError::InvalidCheckpointProof)
+    );
+  }
+
+  #[test]
+  pub fn test_derive_ledger_handle() {
+    let handle = derive_ledger_handle(&[1, 2], b"app").unwrap();
+    assert_eq!(handle.len(), NimbleDigest::num_bytes());
+    assert_eq!(handle, derive_ledger_handle(&[1, 2], b"app").unwrap());
+    assert_ne!(handle, derive_ledger_handle(&[1, 3], b"app").unwrap());
+    assert_ne!(
+      handle,
+      derive_ledger_handle(&[1], &[2, b'a', b'p', b'p']).unwrap()
+    );
+
+    // the handle depends on the application bytes, not on the policies that precede them
+    let genesis_bytes = EndorsementPolicy::All.to_genesis_bytes(b"app");
+    assert_eq!(
+      handle,
+      derive_ledger_handle(&[1, 2], &genesis_bytes).unwrap()
+    );
+    let mut malformed = ENDORSEMENT_POLICY_MAGIC.to_vec();
+    malformed.push(9);
+    assert!(derive_ledger_handle(&[1, 2], &malformed).is_err());
+  }
+
+  #[test]
+  pub fn test_endorsement_policy() {
+    let app_bytes = "app".as_bytes();
+    let sks = (0..3).map(|_| PrivateKey::new()).collect::<Vec<_>>();
+    let ids = sks
+      .iter()
+      .map(|sk| sk.get_public_key().unwrap().to_bytes())
+      .collect::<Vec<_>>();
+
+    // encoding
+    let (policy, bytes) = EndorsementPolicy::from_genesis_bytes(app_bytes).unwrap();
+    assert_eq!(policy, EndorsementPolicy::Majority);
+    assert_eq!(bytes, app_bytes);
+    for policy in [
+      EndorsementPolicy::All,
+      EndorsementPolicy::Required(vec![ids[2].clone()]),
+    ] {
+      let genesis_bytes = policy.to_genesis_bytes(app_bytes);
+      let (parsed, bytes) = EndorsementPolicy::from_genesis_bytes(&genesis_bytes).unwrap();
+      assert_eq!(parsed, policy);
+      assert_eq!(bytes, app_bytes);
+    }
+    let mut truncated = EndorsementPolicy::Required(vec![ids[2].clone()]).to_genesis_bytes(&[]);
+    truncated.pop();
+    assert!(EndorsementPolicy::from_genesis_bytes(&truncated).is_err());
+
+    // enforcement: endorsers 0 and 1 sign, which is a majority but excludes endorser 2
+    let metablock = MetaBlock::genesis(&NimbleDigest::digest(app_bytes));
+    let view = NimbleDigest::digest("view".as_bytes());
+    let mut vs = VerifierState::new();
+    vs.vk_map.insert(view, ids.iter().cloned().collect());
+    let mut receipts = Receipts::new();
+    for sk in &sks[0..2] {
+      let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+      let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+      receipts.add(&Receipt::new(view, metablock.clone(), id_sig));
+    }
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    assert!(receipts
+      .check_policy(&vs, &handle, &EndorsementPolicy::Majority)
+      .is_ok());
+    assert!(receipts
+      .check_policy(
+        &vs,
+        &handle,
+        &EndorsementPolicy::Required(vec![ids[0].clone()])
+      )
+      .is_ok());
+    assert_eq!(
+      receipts.check_policy(&vs, &handle, &EndorsementPolicy::All),
+      Err(VerificationError::EndorsementPolicyNotSatisfied)
+    );
+    assert_eq!(
+      receipts.check_policy(
+        &vs,
+        &handle,
+        &EndorsementPolicy::Required(vec![ids[2].clone()])
+      ),
+      Err(VerificationError::EndorsementPolicyNotSatisfied)
+    );
+
+    // verification: only signatures on the entry's metablock count toward the policy
+    let handle_bytes = "handle".as_bytes();
+    let hash_nonces = NimbleDigest::default().to_bytes();
+    let metablock = MetaBlock::genesis(&compute_aggregated_block_hash(
+      &NimbleDigest::digest(app_bytes).to_bytes(),
+      &hash_nonces,
+    ));
+    let append =
+      AppendAttestation::for_metablock(vs.get_group_identity(), &view, &handle, &metablock);
+    let receipts_of = |signers: &[(&PrivateKey, &[u8])]| {
+      let mut receipts = Receipts::new();
+      for (sk, message) in signers {
+        let sig = sk.sign(message).unwrap();
+        let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+        receipts.add(&Receipt::new(view, metablock.clone(), id_sig));
+      }
+      receipts.to_bytes()
+    };
+    let verify = |receipts: &[u8], height: usize, policy: &EndorsementPolicy| {
+      vs.verify_endorsement_policy(
+        handle_bytes,
+        app_bytes,
+        &hash_nonces,
+        height,
+        None,
+        receipts,
+        policy,
+      )
+    };
+    let message = append.message().to_bytes();
+    let signed = receipts_of(&[
+      (&sks[0], &message),
+      (&sks[1], &message),
+      (&sks[2], &message),
+    ]);
+    assert!(verify(&signed, 0, &EndorsementPolicy::All).is_ok());
+    assert_eq!(
+      verify(&signed, 1, &EndorsementPolicy::All),
+      Err(VerificationError::EndorsementPolicyNotSatisfied)
+    );
+    let forged = receipts_of(&[
+      (&sks[0], &message),
+      (&sks[1], &message),
+      (&sks[2], "other".as_bytes()),
+    ]);
+    assert!(verify(&forged, 0, &EndorsementPolicy::Majority).is_ok());
+    assert_eq!(
+      verify(
+        &forged,
+        0,
+        &EndorsementPolicy::Required(vec![ids[2].clone()])
+      ),
+      Err(VerificationError::EndorsementPolicyNotSatisfied)
+    );
+  }
+
+  #[test]
+  pub fn test_fuzz_cut_computations() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..500 {
+      // tail maps are sorted by handle, as endorsers produce them, and share some handles
+      let ledger_tail_maps = (0..rng.gen_range(0..4))
+        .map(|_| {
+          let mut handles = (0..rng.gen_range(0..5))
+            .map(|_| vec![rng.gen_range(0..6u8); 32])
+            .collect::<Vec<_>>();
+          handles.sort();
+          handles.dedup();
+          LedgerTailMap {
+            entries: handles
+              .into_iter()
+              .map(|handle| LedgerTailMapEntry {
+                handle,
+                height: rng.gen_range(0..4),
+                metablock: rng.gen::<[u8; 32]>().to_vec(),
+                block: Vec::new(),
+                nonces: Vec::new(),
+              })
+              .collect(),
+          }
+        })
+        .collect::<Vec<_>>();
+
+      let max_cut = compute_max_cut(&ledger_tail_maps);
+      for ledger_tail_map in &ledger_tail_maps {
+        for entry in &ledger_tail_map.entries {
+          let max_entry = max_cut.iter().find(|e| e.handle == entry.handle).unwrap();
+          assert!(max_entry.height >= entry.height);
+        }
+      }
+
+      for cut_diff in compute_cut_diffs(&ledger_tail_maps) {
+        assert!(cut_diff.low <= cut_diff.high);
+      }
+
+      // malformed ledger chunks must be rejected rather than panic
+      let ledger_chunks = (0..rng.gen_range(0..3))
+        .map(|_| LedgerChunkEntry {
+          handle: vec![rng.gen_range(0..6u8); 32],
+          hash: vec![0u8; rng.gen_range(0..40)],
+          height: rng.gen_range(0..4),
+          block_hashes: vec![vec![0u8; rng.gen_range(0..40)]; rng.gen_range(0..3)],
+        })
+        .collect::<Vec<_>>();
+      let pk = PrivateKey::new().get_public_key().unwrap();
+      let config: EndorserHostnames = vec![(pk.to_bytes(), "endorser".to_string())];
+      let config = bincode::serialize(&config).unwrap();
+      let old_metablock =
+        MetaBlock::new(&NimbleDigest::default(), &NimbleDigest::digest(&config), 1);
+      let new_metablock = MetaBlock::new(&old_metablock.hash(), &NimbleDigest::digest(&config), 2);
+      let _ = Receipts::new().verify_view_change(
+        &config,
+        &config,
+        &pk,
+        &NimbleDigest::digest(&config),
+        &old_metablock,
+        &new_metablock,
+        &ledger_tail_maps,
+        &ledger_chunks,
+      );
+    }
+  }
+
+  #[test]
+  pub fn test_verify_read_view_tail() {
+    let sks = (0..3).map(|_| PrivateKey::new()).collect::<Vec<_>>();
+    let config = "config".as_bytes();
+    let metablock = MetaBlock::new(&NimbleDigest::default(), &NimbleDigest::digest(config), 1);
+    let view = metablock.hash();
+    let mut vs = VerifierState::new();
+    vs.set_group_identity(NimbleDigest::digest(config));
+    vs.vk_map.insert(
+      view,
+      sks
+        .iter()
+        .map(|sk| sk.get_public_key().unwrap().to_bytes())
+        .collect(),
+    );
+
+    let nonce = "nonce".as_bytes();
+    let message = vs
+      .get_group_identity()
+      .digest_with(&view.digest_with(&view.digest_with_bytes(nonce)));
+    let mut receipts = Receipts::new();
+    for sk in &sks[..2] {
+      let sig = sk.sign(&message.to_bytes()).unwrap();
+      let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+      receipts.add(&Receipt::new(view, metablock.clone(), id_sig));
+    }
+    let receipts_bytes = receipts.to_bytes();
+    assert_eq!(
+      vs.verify_read_view_tail(config, nonce, &receipts_bytes),
+      Ok(1)
+    );
+
+    // the receipts are bound to the nonce and the block
+    assert!(vs
+      .verify_read_view_tail(config, "other".as_bytes(), &receipts_bytes)
+      .is_err());
+    assert!(vs
+      .verify_read_view_tail("other".as_bytes(), nonce, &receipts_bytes)
+      .is_err());
+  }
+
+  #[test]
+  pub fn test_shard_endorsers() {
+    let pks = (0..7)
+      .map(|_| PrivateKey::new().get_public_key().unwrap().to_bytes())
+      .collect::<HashSet<Vec<u8>>>();
+    let handle = NimbleDigest::digest("handle".as_bytes());
+
+    assert_eq!(shard_endorsers(&pks, &handle, 0), pks);
+    assert_eq!(shard_endorsers(&pks, &handle, 10), pks);
+
+    let shard = shard_endorsers(&pks, &handle, 3);
+    assert_eq!(shard.len(), 3);
+    assert!(shard.is_subset(&pks));
+    assert_eq!(shard_endorsers(&pks, &handle, 3), shard);
+
+    // removing an endorser outside the shard leaves the assignment unchanged
+    let outsider = pks.difference(&shard).next().unwrap().clone();
+    let mut fewer_pks = pks.clone();
+    fewer_pks.remove(&outsider);
+    assert_eq!(shard_endorsers(&fewer_pks, &handle, 3), shard);
+
+    // a quorum of the shard suffices once the verifier knows the shard size
+    let sks = (0..5).map(|_| PrivateKey::new()).collect::<Vec<_>>();
+    let view = NimbleDigest::digest("view".as_bytes());
+    let mut vs = VerifierState::new();
+    vs.vk_map.insert(
+      view,
+      sks
+        .iter()
+        .map(|sk| sk.get_public_key().unwrap().to_bytes())
+        .collect(),
+    );
+    vs.set_shard_size(3);
+    let shard = vs.get_pks_for_ledger(&view, &handle).unwrap();
+    let metablock = MetaBlock::genesis(&NimbleDigest::digest("block".as_bytes()));
+    let mut receipts = Receipts::new();
+    for sk in &sks {
+      let pk = sk.get_public_key().unwrap();
+      if shard.contains(&pk.to_bytes()) && receipts.get_signer_ids().len() < 2 {
+        let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+        receipts.add(&Receipt::new(view, metablock.clone(), IdSig::new(pk, sig)));
+      }
+    }
+    assert!(receipts.check_quorum(&vs).is_err());
+    assert!(receipts
+      .check_policy(&vs, &handle, &EndorsementPolicy::Majority)
+      .is_ok());
+  }
+
+  #[test]
+  pub fn test_verify_with_report() {
+    let sks = (0..4).map(|_| PrivateKey::new()).collect::<Vec<_>>();
+    let view = NimbleDigest::digest("view".as_bytes());
+    let mut vs = VerifierState::new();
+    vs.vk_map.insert(
+      view,
+      sks
+        .iter()
+        .map(|sk| sk.get_public_key().unwrap().to_bytes())
+        .collect(),
+    );
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    let hash_nonces = Nonces::new().hash().to_bytes();
+    let block_hash = compute_aggregated_block_hash(
+      &NimbleDigest::digest("block".as_bytes()).to_bytes(),
+      &hash_nonces,
+    );
+    let prev = NimbleDigest::digest("prev".as_bytes());
+    let metablock = MetaBlock::new(&prev, &block_hash, 1);
+    let other_metablock = MetaBlock::new(&prev, &block_hash, 2);
+    let sign = |sk: &PrivateKey, metablock: &MetaBlock| {
+      let message =
+        AppendAttestation::new(vs.get_group_identity(), &view, &handle, &metablock.hash())
+          .message();
+      let sig = sk.sign(&message.to_bytes()).unwrap();
+      Receipt::new(
+        view,
+        metablock.clone(),
+        IdSig::new(sk.get_public_key().unwrap(), sig),
+      )
+    };
+    let verify = |receipts: &Receipts| {
+      receipts.verify_with_report(&vs, &handle, b"block", &hash_nonces, Some(1), None)
+    };
+
+    let mut receipts = Receipts::new();
+    for sk in &sks[..3] {
+      receipts.add(&sign(sk, &metablock));
+    }
+    let report = verify(&receipts);
+    assert_eq!(report.result, Ok(1));
+    assert!(report.faults.is_empty());
+
+    // an outsider, a signature on another statement, and an endorser that signs two metablocks
+    // in one view are each reported
+    let outsider = PrivateKey::new();
+    receipts.add(&sign(&outsider, &metablock));
+    let wrong = Receipt::new(
+      view,
+      metablock.clone(),
+      IdSig::new(
+        sks[3].get_public_key().unwrap(),
+        sks[3].sign(&metablock.hash().to_bytes()).unwrap(),
+      ),
+    );
+    receipts.add(&wrong);
+    receipts.add(&sign(&sks[0], &other_metablock));
+    let report = verify(&receipts);
+    assert_eq!(report.result, Err(VerificationError::InvalidSignature));
+    let fault_of = |sk: &PrivateKey| {
+      let id = sk.get_public_key().unwrap().to_bytes();
+      report
+        .faults
+        .iter()
+        .find(|(faulty_id, _fault)| *faulty_id == id)
+        .map(|(_id, fault)| *fault)
+    };
+    assert_eq!(report.faults.len(), 3);
+    assert_eq!(fault_of(&outsider), Some(SignerFault::Unknown));
+    assert_eq!(fault_of(&sks[3]), Some(SignerFault::BadSignature));
+    assert_eq!(fault_of(&sks[0]), Some(SignerFault::Duplicated));
+    assert_eq!(fault_of(&sks[1]), None);
+  }
+
+  #[test]
+  pub fn test_read_latest_statement() {
+    let sks = (0..3).map(|_| PrivateKey::new()).collect::<Vec<_>>();
+    let view_keys = sks
+      .iter()
+      .map(|sk| sk.get_public_key().unwrap().to_bytes())
+      .collect::<Vec<_>>();
+    let group_identity = NimbleDigest::digest("group".as_bytes());
+    let view = NimbleDigest::digest("view".as_bytes());
+    let handle_bytes = "handle".as_bytes();
+    let handle = NimbleDigest::digest(handle_bytes);
+    let nonce = Nonce::random().to_bytes();
+    let mut nonces = Nonces::new();
+    nonces.add(Nonce::from_bytes(&nonce).unwrap());
+    let block_hash = |nonces: &Nonces| {
+      compute_aggregated_block_hash(
+        &NimbleDigest::digest("block".as_bytes()).to_bytes(),
+        &nonces.hash().to_bytes(),
+      )
+    };
+    let receipts_of = |metablock: &MetaBlock, message: &NimbleDigest, signers: &[PrivateKey]| {
+      let mut receipts = Receipts::new();
+      for sk in signers {
+        let sig = sk.sign(&message.to_bytes()).unwrap();
+        receipts.add(&Receipt::new(
+          view,
+          metablock.clone(),
+          IdSig::new(sk.get_public_key().unwrap(), sig),
+        ));
+      }
+      receipts
+    };
+    let verify = |statement: &[u8], nonces: &Nonces, receipts: &Receipts| {
+      verify_read_latest_statement(
+        statement,
+        handle_bytes,
+        b"block",
+        &nonces.to_bytes(),
+        &nonce,
+        &receipts.to_bytes(),
+        &view_keys,
+      )
+    };
+
+    // endorsers that sign for the read sign over the nonce
+    let metablock = MetaBlock::new(&view, &block_hash(&Nonces::new()), 3);
+    let read = ReadAttestation::new(&group_identity, &view, &handle, &metablock.hash(), &nonce);
+    let receipts = receipts_of(&metablock, &read.message(), &sks[..2]);
+    let statement = receipts
+      .read_latest_statement(&group_identity, &handle, &nonce)
+      .unwrap();
+    assert_eq!(statement, read.to_bytes());
+    assert_eq!(verify(&statement, &Nonces::new(), &receipts), Ok(3));
+    let other_nonce = ReadAttestation::new(
+      &group_identity,
+      &view,
+      &handle,
+      &metablock.hash(),
+      &[0u8; 16],
+    );
+    assert_eq!(
+      verify(&other_nonce.to_bytes(), &Nonces::new(), &receipts),
+      Err(VerificationError::InvalidNonce)
+    );
+    let minority = receipts_of(&metablock, &read.message(), &sks[..1]);
+    assert_eq!(
+      verify(&statement, &Nonces::new(), &minority),
+      Err(VerificationError::InsufficientReceipts)
+    );
+
+    // an entry that includes the nonce is fresh with the receipts of its append
+    let metablock = MetaBlock::new(&view, &block_hash(&nonces), 4);
+    let append = AppendAttestation::for_metablock(&group_identity, &view, &handle, &metablock);
+    let receipts = receipts_of(&metablock, &append.message(), &sks);
+    let statement = receipts
+      .read_latest_statement(&group_identity, &handle, &nonce)
+      .unwrap();
+    assert_eq!(statement, append.to_bytes());
+    assert_eq!(verify(&statement, &nonces, &receipts), Ok(4));
+    assert!(verify(&statement, &Nonces::new(), &receipts).is_err());
+    assert!(Receipts::new()
+      .read_latest_statement(&group_identity, &handle, &nonce)
+      .is_none());
+  }
+
+  #[test]
+  pub fn test_misbehavior_evidence() {
+    let group_identity = NimbleDigest::digest("group".as_bytes());
+    let view = NimbleDigest::digest("view".as_bytes());
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    let prev = NimbleDigest::digest("prev".as_bytes());
+    let sign = |sk: &PrivateKey, block: &str, height: usize| {
+      let metablock = MetaBlock::new(&prev, &NimbleDigest::digest(block.as_bytes()), height);
+      let message = AppendAttestation::new(&group_identity, &view, &handle, &metablock.hash());
+      let sig = sk.sign(&message.message().to_bytes()).unwrap();
+      Receipt::new(
+        view,
+        metablock,
+        IdSig::new(sk.get_public_key().unwrap(), sig),
+      )
+    };
+    let sk = PrivateKey::new();
+    let other = PrivateKey::new();
+
+    // receipts that do not conflict are no evidence
+    assert!(MisbehaviorEvidence::new(&handle, &sign(&sk, "a", 1), &sign(&sk, "a", 1)).is_none());
+    assert!(MisbehaviorEvidence::new(&handle, &sign(&sk, "a", 1), &sign(&sk, "b", 2)).is_none());
+    assert!(MisbehaviorEvidence::new(&handle, &sign(&sk, "a", 1), &sign(&other, "b", 1)).is_none());
+
+    let evidence =
+      MisbehaviorEvidence::new(&handle, &sign(&sk, "a", 1), &sign(&sk, "b", 1)).unwrap();
+    assert_eq!(*evidence.get_handle(), handle);
+    assert_eq!(
+      *evidence.get_endorser(),
+      sk.get_public_key().unwrap().to_bytes()
+    );
+    assert_eq!(evidence.get_height(), 1);
+    assert!(evidence.verify(&group_identity).is_ok());
+    assert_eq!(
+      evidence.verify(&view),
+      Err(VerificationError::InvalidMisbehaviorEvidence)
+    );
+
+    let bytes = evidence.to_bytes();
+    let restored = MisbehaviorEvidence::from_bytes(&bytes).unwrap();
+    assert_eq!(restored.to_bytes(), bytes);
+    assert!(restored.verify(&group_identity).is_ok());
+    assert!(MisbehaviorEvidence::from_bytes(&bytes[1..]).is_err());
+
+    // receipts that were altered to conflict no longer verify
+    let (first, _second) = evidence.get_receipts();
+    let forged = Receipt::new(
+      view,
+      MetaBlock::new(&prev, &NimbleDigest::digest("c".as_bytes()), 1),
+      first.get_id_sig().clone(),
+    );
+    let evidence = MisbehaviorEvidence::new(&handle, first, &forged).unwrap();
+    assert!(evidence.verify(&group_identity).is_err());
+  }
 }
@@ -0,0 +1,517 @@
+//! Logging for the Nimble services. Request handlers run in `tracing` spans that carry the
+//! request ID, the ledger handle, the height, and the endorser involved, and every log line names
+//! the spans it was written in. The subscriber here prints one line per event, as text or as
+//! JSON. It also lets a service find the request ID of the span it runs in, so the coordinator
+//! can forward it to endorsers in the `x-nimble-request-id` metadata of its calls, and keeps the
+//! trace context of every span, which it forwards the same way and exports as the `telemetry`
+//! module describes.
+use crate::{
+  errors::LoggingError,
+  telemetry::{
+    unix_nanos, FinishedSpan, OtlpExporter, SpanKind, TraceContext, KIND_FIELD, NAME_FIELD,
+    TRACEPARENT_FIELD, TRACEPARENT_METADATA,
+  },
+};
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  fmt::{self, Write as _},
+  io::Write as _,
+  str::FromStr,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, OnceLock, RwLock,
+  },
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{
+  field::{Field, Visit},
+  level_filters::LevelFilter,
+  span, Event, Level, Metadata, Subscriber,
+};
+
+/// The gRPC metadata key that carries the ID of a request from the coordinator to endorsers
+pub const REQUEST_ID_METADATA: &str = "x-nimble-request-id";
+
+/// The span field that holds the ID of a request
+pub const REQUEST_ID_FIELD: &str = "request_id";
+
+// the crates whose debug and info events are logged; other crates log only warnings and errors
+const NIMBLE_TARGETS: [&str; 5] = ["coordinator", "endorser", "ledger", "store", "endpoint"];
+
+struct SpanData {
+  name: &'static str,
+  parent: Option<u64>,
+  fields: Vec<(&'static str, String)>,
+  // the handles of the span, which end it once they are all dropped, and its children
+  refs: usize,
+  handles: usize,
+  context: TraceContext,
+  // the span ID of the parent, which is the caller's span if the request carried a trace context
+  parent_span_id: Option<[u8; 8]>,
+  kind: SpanKind,
+  export_name: Option<String>,
+  start_unix_nanos: u64,
+}
+
+impl SpanData {
+  fn finish(&self) -> FinishedSpan {
+    FinishedSpan {
+      name: self
+        .export_name
+        .clone()
+        .unwrap_or_else(|| self.name.to_string()),
+      kind: self.kind,
+      context: self.context,
+      parent_span_id: self.parent_span_id,
+      start_unix_nanos: self.start_unix_nanos,
+      end_unix_nanos: unix_nanos(),
+      attributes: self.fields.clone(),
+    }
+  }
+}
+
+type SpanRegistry = Arc<RwLock<HashMap<u64, SpanData>>>;
+
+static REGISTRY: OnceLock<SpanRegistry> = OnceLock::new();
+
+thread_local! {
+  // the spans entered on this thread, innermost last
+  static CURRENT_SPANS: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+  message: Option<String>,
+  fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for FieldVisitor {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.record(field, value.to_string());
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    self.record(field, format!("{:?}", value));
+  }
+}
+
+impl FieldVisitor {
+  fn record(&mut self, field: &Field, value: String) {
+    if field.name() == "message" {
+      self.message = Some(value);
+    } else {
+      self.fields.push((field.name(), value));
+    }
+  }
+}
+
+/// A subscriber that prints every enabled event to stderr with the spans it occurred in, and
+/// exports the spans of sampled traces once they end, if it has an exporter
+pub struct Logger {
+  level: LevelFilter,
+  json: bool,
+  spans: SpanRegistry,
+  next_id: AtomicU64,
+  exporter: Option<OtlpExporter>,
+}
+
+impl Logger {
+  pub fn new(level: LevelFilter, json: bool) -> Self {
+    Logger {
+      level,
+      json,
+      spans: Arc::new(RwLock::new(HashMap::new())),
+      next_id: AtomicU64::new(1),
+      exporter: None,
+    }
+  }
+
+  pub fn with_exporter(self, exporter: OtlpExporter) -> Self {
+    Logger {
+      exporter: Some(exporter),
+      ..self
+    }
+  }
+
+  fn is_nimble_target(target: &str) -> bool {
+    NIMBLE_TARGETS
+      .iter()
+      .any(|crate_name| target.split("::").next() == Some(crate_name))
+  }
+
+  // the names and fields of the current spans, outermost first
+  fn current_spans(&self) -> Vec<(&'static str, Vec<(&'static str, String)>)> {
+    let mut chain = Vec::new();
+    let mut id = CURRENT_SPANS.with(|spans| spans.borrow().last().copied());
+    if let Ok(spans) = self.spans.read() {
+      while let Some(span) = id.and_then(|id| spans.get(&id)) {
+        chain.push((span.name, span.fields.clone()));
+        id = span.parent;
+      }
+    }
+    chain.reverse();
+    chain
+  }
+
+  fn format_text(
+    metadata: &Metadata<'_>,
+    spans: &[(&'static str, Vec<(&'static str, String)>)],
+    visitor: &FieldVisitor,
+  ) -> String {
+    let mut line = format!("{} {:>5} ", timestamp(), metadata.level());
+    for (name, fields) in spans {
+      line.push_str(name);
+      if !fields.is_empty() {
+        let fields = fields
+          .iter()
+          .map(|(key, value)| format!("{}={}", key, value))
+          .collect::<Vec<String>>();
+        let _ = write!(line, "{{{}}}", fields.join(" "));
+      }
+      line.push_str(": ");
+    }
+    let _ = write!(line, "{}:", metadata.target());
+    if let Some(message) = &visitor.message {
+      let _ = write!(line, " {}", message);
+    }
+    for (key, value) in &visitor.fields {
+      let _ = write!(line, " {}={}", key, value);
+    }
+    line
+  }
+
+  fn format_json(
+    metadata: &Metadata<'_>,
+    spans: &[(&'static str, Vec<(&'static str, String)>)],
+    visitor: &FieldVisitor,
+  ) -> String {
+    let mut fields = serde_json::Map::new();
+    if let Some(message) = &visitor.message {
+      fields.insert("message".to_string(), message.clone().into());
+    }
+    for (key, value) in &visitor.fields {
+      fields.insert(key.to_string(), value.clone().into());
+    }
+    let spans = spans
+      .iter()
+      .map(|(name, span_fields)| {
+        let mut span = serde_json::Map::new();
+        span.insert("name".to_string(), name.to_string().into());
+        for (key, value) in span_fields {
+          span.insert(key.to_string(), value.clone().into());
+        }
+        serde_json::Value::Object(span)
+      })
+      .collect::<Vec<serde_json::Value>>();
+    serde_json::json!({
+      "timestamp": timestamp(),
+      "level": metadata.level().as_str(),
+      "target": metadata.target(),
+      "fields": fields,
+      "spans": spans,
+    })
+    .to_string()
+  }
+}
+
+// seconds since the Unix epoch, with milliseconds
+fn timestamp() -> String {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default();
+  format!("{}.{:03}", now.as_secs(), now.subsec_millis())
+}
+
+impl Subscriber for Logger {
+  fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+    if !Logger::is_nimble_target(metadata.target()) {
+      return *metadata.level() <= Level::WARN && *metadata.level() <= self.level;
+    }
+    // spans are always kept, so that their request IDs are forwarded at any log level
+    metadata.is_span() || *metadata.level() <= self.level
+  }
+
+  fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    let parent = if attrs.is_root() {
+      None
+    } else if attrs.is_contextual() {
+      CURRENT_SPANS.with(|spans| spans.borrow().last().copied())
+    } else {
+      attrs.parent().map(|parent| parent.into_u64())
+    };
+    let mut visitor = FieldVisitor::default();
+    attrs.record(&mut visitor);
+    // the fields that describe the trace are kept apart from the ones that are logged
+    let mut remote = None;
+    let mut kind = SpanKind::Internal;
+    let mut export_name = None;
+    visitor.fields.retain(|(key, value)| {
+      match *key {
+        TRACEPARENT_FIELD => remote = TraceContext::from_traceparent(value),
+        KIND_FIELD => kind = SpanKind::from_name(value).unwrap_or(kind),
+        NAME_FIELD => export_name = Some(value.clone()),
+        _ => return true,
+      }
+      false
+    });
+    if let Ok(mut spans) = self.spans.write() {
+      // a span keeps its parent, so that it still finds the request ID after the parent closes
+      let parent_data = parent.and_then(|parent| spans.get_mut(&parent));
+      let (context, parent_span_id) = match (remote, parent_data) {
+        (Some(remote), _) => (remote.child(), Some(remote.span_id)),
+        (None, Some(parent)) => (parent.context.child(), Some(parent.context.span_id)),
+        (None, None) => (TraceContext::root(), None),
+      };
+      if let Some(parent) = parent.and_then(|parent| spans.get_mut(&parent)) {
+        parent.refs += 1;
+      }
+      spans.insert(
+        id,
+        SpanData {
+          name: attrs.metadata().name(),
+          parent,
+          fields: visitor.fields,
+          refs: 1,
+          handles: 1,
+          context,
+          parent_span_id,
+          kind,
+          export_name,
+          start_unix_nanos: unix_nanos(),
+        },
+      );
+    }
+    span::Id::from_u64(id)
+  }
+
+  fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+    let mut visitor = FieldVisitor::default();
+    values.record(&mut visitor);
+    if let Ok(mut spans) = self.spans.write() {
+      if let Some(span) = spans.get_mut(&span.into_u64()) {
+        span.fields.extend(visitor.fields);
+      }
+    }
+  }
+
+  fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+  fn event(&self, event: &Event<'_>) {
+    let mut visitor = FieldVisitor::default();
+    event.record(&mut visitor);
+    let spans = self.current_spans();
+    let line = if self.json {
+      Logger::format_json(event.metadata(), &spans, &visitor)
+    } else {
+      Logger::format_text(event.metadata(), &spans, &visitor)
+    };
+    let _ = writeln!(std::io::stderr(), "{}", line);
+  }
+
+  fn enter(&self, span: &span::Id) {
+    CURRENT_SPANS.with(|spans| spans.borrow_mut().push(span.into_u64()));
+  }
+
+  fn exit(&self, span: &span::Id) {
+    CURRENT_SPANS.with(|spans| {
+      let mut spans = spans.borrow_mut();
+      if let Some(pos) = spans.iter().rposition(|id| *id == span.into_u64()) {
+        spans.remove(pos);
+      }
+    });
+  }
+
+  fn clone_span(&self, id: &span::Id) -> span::Id {
+    if let Ok(mut spans) = self.spans.write() {
+      if let Some(span) = spans.get_mut(&id.into_u64()) {
+        span.refs += 1;
+        span.handles += 1;
+      }
+    }
+    id.clone()
+  }
+
+  fn try_close(&self, id: span::Id) -> bool {
+    let mut closed = false;
+    let mut finished = None;
+    if let Ok(mut spans) = self.spans.write() {
+      // the span ends when its last handle is dropped, even if its children live on
+      if let Some(span) = spans.get_mut(&id.into_u64()) {
+        span.handles -= 1;
+        if span.handles == 0 && span.context.sampled && self.exporter.is_some() {
+          finished = Some(span.finish());
+        }
+      }
+      let mut next = Some(id.into_u64());
+      while let Some(span_id) = next.take() {
+        if let Some(span) = spans.get_mut(&span_id) {
+          span.refs -= 1;
+          if span.refs == 0 {
+            next = span.parent;
+            spans.remove(&span_id);
+            closed |= span_id == id.into_u64();
+          }
+        }
+      }
+    }
+    if let (Some(exporter), Some(finished)) = (&self.exporter, finished) {
+      exporter.export(finished);
+    }
+    closed
+  }
+}
+
+/// Installs a `Logger` at `level` (error, warn, info, debug, or trace) as the subscriber of the
+/// process, writing JSON lines if `json` is set
+pub fn init(level: &str, json: bool) -> Result<(), LoggingError> {
+  init_with_exporter(level, json, None)
+}
+
+/// Installs a `Logger` as `init` does, which also exports spans with `exporter`, if given
+pub fn init_with_exporter(
+  level: &str,
+  json: bool,
+  exporter: Option<OtlpExporter>,
+) -> Result<(), LoggingError> {
+  let level = LevelFilter::from_str(level).map_err(|_e| LoggingError::InvalidLogLevel)?;
+  let mut logger = Logger::new(level, json);
+  if let Some(exporter) = exporter {
+    logger = logger.with_exporter(exporter);
+  }
+  let spans = logger.spans.clone();
+  if tracing::subscriber::set_global_default(logger).is_err() {
+    return Err(LoggingError::AlreadyInitialized);
+  }
+  let _ = REGISTRY.set(spans);
+  Ok(())
+}
+
+/// Returns the request ID of the innermost current span that has one, if a `Logger` is installed
+pub fn current_request_id() -> Option<String> {
+  let spans = REGISTRY.get()?.read().ok()?;
+  let mut id = CURRENT_SPANS.with(|current| current.borrow().last().copied());
+  while let Some(span) = id.and_then(|id| spans.get(&id)) {
+    if let Some((_key, value)) = span.fields.iter().find(|(key, _)| *key == REQUEST_ID_FIELD) {
+      return Some(value.clone());
+    }
+    id = span.parent;
+  }
+  None
+}
+
+/// Returns the trace context of the innermost current span, if a `Logger` is installed
+pub fn current_trace_context() -> Option<TraceContext> {
+  let spans = REGISTRY.get()?.read().ok()?;
+  let id = CURRENT_SPANS.with(|current| current.borrow().last().copied())?;
+  spans.get(&id).map(|span| span.context)
+}
+
+/// Returns the request ID in the metadata of a gRPC request, if it has one
+pub fn request_id_from_metadata(metadata: &tonic::metadata::MetadataMap) -> Option<String> {
+  let id = metadata.get(REQUEST_ID_METADATA)?.to_str().ok()?;
+  Some(id.to_string())
+}
+
+/// Returns `message` as a gRPC request that carries the request ID and the trace context of the
+/// current span
+pub fn request_with_id<T>(message: T) -> tonic::Request<T> {
+  let mut request = tonic::Request::new(message);
+  if let Some(value) = current_request_id().and_then(|id| id.parse().ok()) {
+    request.metadata_mut().insert(REQUEST_ID_METADATA, value);
+  }
+  if let Some(value) =
+    current_trace_context().and_then(|context| context.to_traceparent().parse().ok())
+  {
+    request.metadata_mut().insert(TRACEPARENT_METADATA, value);
+  }
+  request
+}
+
+/// Shortens an identifier such as a handle or a public key to its first bytes in hex, which is
+/// enough to tell them apart in logs
+pub fn short_id(bytes: &[u8]) -> String {
+  bytes.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tracing::info_span;
+
+  #[test]
+  fn test_spans_carry_request_ids() {
+    let logger = Logger::new(LevelFilter::INFO, false);
+    let spans = logger.spans.clone();
+    tracing::subscriber::with_default(logger, || {
+      let request = info_span!("append", request_id = "abc", handle = %short_id(&[0xab; 32]));
+      let _request = request.enter();
+      let endorser = info_span!("endorser", uri = "http://[::1]:9090");
+      let _endorser = endorser.enter();
+
+      let id = CURRENT_SPANS.with(|current| *current.borrow().last().unwrap());
+      let spans = spans.read().unwrap();
+      let endorser = spans.get(&id).unwrap();
+      assert_eq!(endorser.name, "endorser");
+      let request = spans.get(&endorser.parent.unwrap()).unwrap();
+      assert_eq!(
+        request.fields,
+        vec![
+          ("request_id", "abc".to_string()),
+          ("handle", "abababababababab".to_string())
+        ]
+      );
+    });
+    // closed spans are dropped
+    assert!(spans.read().unwrap().is_empty());
+    assert!(LevelFilter::from_str("verbose").is_err());
+  }
+
+  #[test]
+  fn test_spans_carry_trace_contexts() {
+    let (exporter, mut finished) = OtlpExporter::channel();
+    let logger = Logger::new(LevelFilter::INFO, false).with_exporter(exporter);
+    let spans = logger.spans.clone();
+    let caller = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    let (request_context, endorser_context) = tracing::subscriber::with_default(logger, || {
+      let request = info_span!("grpc", otel.kind = "server", traceparent = %caller);
+      let _request = request.enter();
+      let append = info_span!("Append", request_id = "abc");
+      let _append = append.enter();
+      let endorser = info_span!("endorser", otel.kind = "client");
+      let endorser_context = {
+        let _endorser = endorser.enter();
+        let id = CURRENT_SPANS.with(|current| *current.borrow().last().unwrap());
+        spans.read().unwrap().get(&id).unwrap().context
+      };
+      // the endorser's call outlives the request, which still ends when its handle is dropped
+      drop(_append);
+      drop(append);
+      let request_id = CURRENT_SPANS.with(|current| *current.borrow().last().unwrap());
+      let request_context = spans.read().unwrap().get(&request_id).unwrap().context;
+      drop(endorser);
+      (request_context, endorser_context)
+    });
+
+    // the request continues the caller's trace, and its spans are exported as they end
+    let caller = TraceContext::from_traceparent(caller).unwrap();
+    assert_eq!(request_context.trace_id, caller.trace_id);
+    assert_eq!(endorser_context.trace_id, caller.trace_id);
+    let finished = std::iter::from_fn(|| finished.try_recv().ok()).collect::<Vec<_>>();
+    let names = finished
+      .iter()
+      .map(|span| span.name.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(names, vec!["Append", "endorser", "grpc"]);
+    let (append, endorser, request) = (&finished[0], &finished[1], &finished[2]);
+    assert_eq!(request.parent_span_id, Some(caller.span_id));
+    assert_eq!(request.kind, SpanKind::Server);
+    assert!(request.attributes.is_empty());
+    assert_eq!(append.parent_span_id, Some(request_context.span_id));
+    assert_eq!(append.attributes, vec![("request_id", "abc".to_string())]);
+    assert_eq!(endorser.parent_span_id, Some(append.context.span_id));
+    assert_eq!(endorser.kind, SpanKind::Client);
+    assert!(spans.read().unwrap().is_empty());
+  }
+}
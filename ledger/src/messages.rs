@@ -0,0 +1,431 @@
+//! The statements that endorsers sign. An endorser never signs a statement directly but the
+//! digest that `SignedStatement::message` computes from it, which chains the fields from the last
+//! to the first: for fields `a, b, c` the message is `a.digest_with(b.digest_with(c))`, and a
+//! trailing nonce is absorbed with `digest_with_bytes`. The first field is always the group
+//! identity, so signatures cannot be replayed across deployments. `to_bytes` and `from_bytes` give
+//! the fields in a canonical layout, led by a tag that names the kind of statement, so that other
+//! implementations can exchange and check statements without the types of this crate.
+use crate::{
+  errors::VerificationError,
+  serde::{CustomSerde, CustomSerdeError, Reader},
+  IdSig, MetaBlock, NimbleDigest, NimbleHashTrait,
+};
+
+const APPEND_TAG: u8 = 1;
+const READ_TAG: u8 = 2;
+const VIEW_CHANGE_TAG: u8 = 3;
+const VIEW_TAIL_TAG: u8 = 4;
+const KEY_HANDOVER_TAG: u8 = 5;
+
+pub trait SignedStatement: CustomSerde {
+  /// the digest that endorsers sign for the statement
+  fn message(&self) -> NimbleDigest;
+
+  /// checks that `id_sig` is a signature on the statement by the key it names
+  fn verify(&self, id_sig: &IdSig) -> Result<(), VerificationError> {
+    id_sig.verify(&self.message().to_bytes())
+  }
+}
+
+/// Signed when a ledger is created or extended: the metablock is the ledger's tail in the view.
+/// A timestamp, which the metablock's hash does not cover, is absorbed like a nonce.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppendAttestation {
+  pub group_identity: NimbleDigest,
+  pub view: NimbleDigest,
+  pub handle: NimbleDigest,
+  pub metablock_hash: NimbleDigest,
+  pub timestamp: Option<u64>,
+}
+
+/// Signed when a ledger's tail is read: the client's nonce shows that the statement is fresh
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadAttestation {
+  pub group_identity: NimbleDigest,
+  pub view: NimbleDigest,
+  pub handle: NimbleDigest,
+  pub metablock_hash: NimbleDigest,
+  pub nonce: Vec<u8>,
+}
+
+/// Signed when an endorser joins or leaves a view: the hash of the endorser's state, which is the
+/// view of the receipt, together with the new tail of the view ledger
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViewChangeAttestation {
+  pub group_identity: NimbleDigest,
+  pub state_hash: NimbleDigest,
+  pub view_ledger_tail_hash: NimbleDigest,
+}
+
+/// Signed when the tail of the view ledger is read with a client's nonce
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViewTailAttestation {
+  pub group_identity: NimbleDigest,
+  pub view: NimbleDigest,
+  pub nonce: Vec<u8>,
+}
+
+/// Signed with an endorser's old key when the endorser rotates its key: the hash of the new
+/// public key, in the view whose endorsers include the old key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyHandoverAttestation {
+  pub group_identity: NimbleDigest,
+  pub view: NimbleDigest,
+  pub new_key_hash: NimbleDigest,
+}
+
+impl AppendAttestation {
+  pub fn new(
+    group_identity: &NimbleDigest,
+    view: &NimbleDigest,
+    handle: &NimbleDigest,
+    metablock_hash: &NimbleDigest,
+  ) -> Self {
+    AppendAttestation {
+      group_identity: *group_identity,
+      view: *view,
+      handle: *handle,
+      metablock_hash: *metablock_hash,
+      timestamp: None,
+    }
+  }
+
+  /// the statement that `metablock` is the tail of the ledger `handle`, with its timestamp
+  pub fn for_metablock(
+    group_identity: &NimbleDigest,
+    view: &NimbleDigest,
+    handle: &NimbleDigest,
+    metablock: &MetaBlock,
+  ) -> Self {
+    AppendAttestation {
+      timestamp: metablock.get_timestamp(),
+      ..AppendAttestation::new(group_identity, view, handle, &metablock.hash())
+    }
+  }
+}
+
+impl ReadAttestation {
+  pub fn new(
+    group_identity: &NimbleDigest,
+    view: &NimbleDigest,
+    handle: &NimbleDigest,
+    metablock_hash: &NimbleDigest,
+    nonce: &[u8],
+  ) -> Self {
+    ReadAttestation {
+      group_identity: *group_identity,
+      view: *view,
+      handle: *handle,
+      metablock_hash: *metablock_hash,
+      nonce: nonce.to_vec(),
+    }
+  }
+}
+
+impl ViewChangeAttestation {
+  pub fn new(
+    group_identity: &NimbleDigest,
+    state_hash: &NimbleDigest,
+    view_ledger_tail_hash: &NimbleDigest,
+  ) -> Self {
+    ViewChangeAttestation {
+      group_identity: *group_identity,
+      state_hash: *state_hash,
+      view_ledger_tail_hash: *view_ledger_tail_hash,
+    }
+  }
+}
+
+impl ViewTailAttestation {
+  pub fn new(group_identity: &NimbleDigest, view: &NimbleDigest, nonce: &[u8]) -> Self {
+    ViewTailAttestation {
+      group_identity: *group_identity,
+      view: *view,
+      nonce: nonce.to_vec(),
+    }
+  }
+}
+
+impl KeyHandoverAttestation {
+  pub fn new(
+    group_identity: &NimbleDigest,
+    view: &NimbleDigest,
+    new_key_hash: &NimbleDigest,
+  ) -> Self {
+    KeyHandoverAttestation {
+      group_identity: *group_identity,
+      view: *view,
+      new_key_hash: *new_key_hash,
+    }
+  }
+}
+
+impl SignedStatement for AppendAttestation {
+  fn message(&self) -> NimbleDigest {
+    let metablock = match self.timestamp {
+      Some(timestamp) => self
+        .metablock_hash
+        .digest_with_bytes(&timestamp.to_le_bytes()),
+      None => self.metablock_hash,
+    };
+    self
+      .group_identity
+      .digest_with(&self.view.digest_with(&self.handle.digest_with(&metablock)))
+  }
+}
+
+impl SignedStatement for ReadAttestation {
+  fn message(&self) -> NimbleDigest {
+    self.group_identity.digest_with(
+      &self.view.digest_with(
+        &self
+          .handle
+          .digest_with(&self.metablock_hash.digest_with_bytes(&self.nonce)),
+      ),
+    )
+  }
+}
+
+impl SignedStatement for ViewChangeAttestation {
+  fn message(&self) -> NimbleDigest {
+    self
+      .group_identity
+      .digest_with(&self.state_hash.digest_with(&self.view_ledger_tail_hash))
+  }
+}
+
+impl SignedStatement for ViewTailAttestation {
+  fn message(&self) -> NimbleDigest {
+    self.group_identity.digest_with(
+      &self
+        .view
+        .digest_with(&self.view.digest_with_bytes(&self.nonce)),
+    )
+  }
+}
+
+impl SignedStatement for KeyHandoverAttestation {
+  fn message(&self) -> NimbleDigest {
+    self
+      .group_identity
+      .digest_with(&self.view.digest_with(&self.new_key_hash))
+  }
+}
+
+fn encode(tag: u8, digests: &[&NimbleDigest], nonce: Option<&[u8]>) -> Vec<u8> {
+  let mut bytes = vec![tag];
+  for digest in digests {
+    bytes.extend(digest.to_bytes());
+  }
+  if let Some(nonce) = nonce {
+    bytes.extend(nonce);
+  }
+  bytes
+}
+
+// checks the tag and returns the digests that follow it, and the bytes after them
+fn decode(
+  tag: u8,
+  num_digests: usize,
+  bytes: &[u8],
+) -> Result<(Vec<NimbleDigest>, &[u8]), CustomSerdeError> {
+  if bytes.len() < 1 + num_digests * NimbleDigest::num_bytes() {
+    return Err(CustomSerdeError::IncorrectLength);
+  }
+  let mut reader = Reader::new("SignedStatement", bytes);
+  if reader.read_u8()? != tag {
+    return Err(CustomSerdeError::InternalError);
+  }
+  let digests = reader
+    .take_chunks(num_digests, NimbleDigest::num_bytes())?
+    .map(NimbleDigest::from_bytes)
+    .collect::<Result<Vec<NimbleDigest>, CustomSerdeError>>()?;
+  Ok((digests, reader.remaining()))
+}
+
+impl CustomSerde for AppendAttestation {
+  fn to_bytes(&self) -> Vec<u8> {
+    encode(
+      APPEND_TAG,
+      &[
+        &self.group_identity,
+        &self.view,
+        &self.handle,
+        &self.metablock_hash,
+      ],
+      self
+        .timestamp
+        .map(|timestamp| timestamp.to_le_bytes())
+        .as_ref()
+        .map(|timestamp| &timestamp[..]),
+    )
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    let (d, rest) = decode(APPEND_TAG, 4, bytes)?;
+    let timestamp = if rest.is_empty() {
+      None
+    } else {
+      let mut reader = Reader::new("AppendAttestation", rest);
+      let timestamp = reader.read_u64()?;
+      reader.finish()?;
+      Some(timestamp)
+    };
+    Ok(AppendAttestation {
+      timestamp,
+      ..AppendAttestation::new(&d[0], &d[1], &d[2], &d[3])
+    })
+  }
+}
+
+impl CustomSerde for ReadAttestation {
+  fn to_bytes(&self) -> Vec<u8> {
+    encode(
+      READ_TAG,
+      &[
+        &self.group_identity,
+        &self.view,
+        &self.handle,
+        &self.metablock_hash,
+      ],
+      Some(&self.nonce),
+    )
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    let (d, nonce) = decode(READ_TAG, 4, bytes)?;
+    Ok(ReadAttestation::new(&d[0], &d[1], &d[2], &d[3], nonce))
+  }
+}
+
+impl CustomSerde for ViewChangeAttestation {
+  fn to_bytes(&self) -> Vec<u8> {
+    encode(
+      VIEW_CHANGE_TAG,
+      &[
+        &self.group_identity,
+        &self.state_hash,
+        &self.view_ledger_tail_hash,
+      ],
+      None,
+    )
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    let (d, rest) = decode(VIEW_CHANGE_TAG, 3, bytes)?;
+    if !rest.is_empty() {
+      return Err(CustomSerdeError::IncorrectLength);
+    }
+    Ok(ViewChangeAttestation::new(&d[0], &d[1], &d[2]))
+  }
+}
+
+impl CustomSerde for ViewTailAttestation {
+  fn to_bytes(&self) -> Vec<u8> {
+    encode(
+      VIEW_TAIL_TAG,
+      &[&self.group_identity, &self.view],
+      Some(&self.nonce),
+    )
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    let (d, nonce) = decode(VIEW_TAIL_TAG, 2, bytes)?;
+    Ok(ViewTailAttestation::new(&d[0], &d[1], nonce))
+  }
+}
+
+impl CustomSerde for KeyHandoverAttestation {
+  fn to_bytes(&self) -> Vec<u8> {
+    encode(
+      KEY_HANDOVER_TAG,
+      &[&self.group_identity, &self.view, &self.new_key_hash],
+      None,
+    )
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError> {
+    let (d, rest) = decode(KEY_HANDOVER_TAG, 3, bytes)?;
+    if !rest.is_empty() {
+      return Err(CustomSerdeError::IncorrectLength);
+    }
+    Ok(KeyHandoverAttestation::new(&d[0], &d[1], &d[2]))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::signature::{PrivateKey, PrivateKeyTrait};
+
+  #[test]
+  fn test_signed_statements() {
+    let group_identity = NimbleDigest::digest("group".as_bytes());
+    let view = NimbleDigest::digest("view".as_bytes());
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    let metablock_hash = NimbleDigest::digest("metablock".as_bytes());
+    let nonce = vec![7u8; 16];
+
+    // the messages keep the layout that endorsers have always signed
+    let append = AppendAttestation::new(&group_identity, &view, &handle, &metablock_hash);
+    assert_eq!(
+      append.message(),
+      group_identity.digest_with(&view.digest_with(&handle.digest_with(&metablock_hash)))
+    );
+    let read = ReadAttestation::new(&group_identity, &view, &handle, &metablock_hash, &nonce);
+    assert_eq!(
+      read.message(),
+      group_identity.digest_with(
+        &view.digest_with(&handle.digest_with(&metablock_hash.digest_with_bytes(&nonce)))
+      )
+    );
+
+    // statements round-trip through their canonical form, and their kinds are not confused
+    assert_eq!(
+      AppendAttestation::from_bytes(&append.to_bytes()).unwrap(),
+      append
+    );
+    assert_eq!(ReadAttestation::from_bytes(&read.to_bytes()).unwrap(), read);
+    let view_change = ViewChangeAttestation::new(&group_identity, &view, &metablock_hash);
+    assert_eq!(
+      ViewChangeAttestation::from_bytes(&view_change.to_bytes()).unwrap(),
+      view_change
+    );
+    let view_tail = ViewTailAttestation::new(&group_identity, &view, &nonce);
+    assert_eq!(
+      ViewTailAttestation::from_bytes(&view_tail.to_bytes()).unwrap(),
+      view_tail
+    );
+    let handover = KeyHandoverAttestation::new(&group_identity, &view, &metablock_hash);
+    assert_eq!(
+      KeyHandoverAttestation::from_bytes(&handover.to_bytes()).unwrap(),
+      handover
+    );
+    assert!(ViewChangeAttestation::from_bytes(&handover.to_bytes()).is_err());
+
+    // a timestamp is absorbed after the metablock's hash, as a nonce is
+    let timestamped = AppendAttestation {
+      timestamp: Some(1_700_000_000_000),
+      ..append.clone()
+    };
+    assert_eq!(
+      timestamped.message(),
+      group_identity.digest_with(&view.digest_with(
+        &handle.digest_with(&metablock_hash.digest_with_bytes(&1_700_000_000_000u64.to_le_bytes()))
+      ))
+    );
+    assert_eq!(
+      AppendAttestation::from_bytes(&timestamped.to_bytes()).unwrap(),
+      timestamped
+    );
+    assert!(AppendAttestation::from_bytes(&timestamped.to_bytes()[..134]).is_err());
+    assert!(ReadAttestation::from_bytes(&append.to_bytes()).is_err());
+    assert!(AppendAttestation::from_bytes(&read.to_bytes()).is_err());
+
+    let sk = PrivateKey::new();
+    let sig = sk.sign(&append.message().to_bytes()).unwrap();
+    let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+    assert!(append.verify(&id_sig).is_ok());
+    assert!(read.verify(&id_sig).is_err());
+  }
+}
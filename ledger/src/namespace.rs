@@ -0,0 +1,92 @@
+//! Namespaces that keep the ledgers of different tenants apart. A ledger created in a namespace
+//! is known by a handle that carries the namespace, `nimble-ns:<namespace>/<handle>`, so the
+//! digest under which the store and the endorsers keep the ledger mixes in the namespace, and two
+//! tenants that pick the same handle get different ledgers. Credentials that are scoped to a set
+//! of namespaces only reach the ledgers whose handles carry one of them.
+
+/// the most bytes in the name of a namespace
+pub const MAX_NAMESPACE_SIZE: usize = 64;
+
+const NAMESPACED_HANDLE_PREFIX: &[u8] = b"nimble-ns:";
+const NAMESPACE_SEPARATOR: u8 = b'/';
+
+/// whether `namespace` is a valid name: 1 to `MAX_NAMESPACE_SIZE` lowercase letters, digits,
+/// dashes and underscores, which stores can use in the names of their tables
+pub fn is_valid_namespace(namespace: &str) -> bool {
+  !namespace.is_empty()
+    && namespace.len() <= MAX_NAMESPACE_SIZE
+    && namespace
+      .bytes()
+      .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+/// Returns the handle of the ledger `handle` in `namespace`, or `handle` itself if the namespace
+/// is empty. The namespace must be valid.
+pub fn namespaced_handle(namespace: &str, handle: &[u8]) -> Vec<u8> {
+  if namespace.is_empty() {
+    return handle.to_vec();
+  }
+  let mut bytes =
+    Vec::with_capacity(NAMESPACED_HANDLE_PREFIX.len() + namespace.len() + 1 + handle.len());
+  bytes.extend_from_slice(NAMESPACED_HANDLE_PREFIX);
+  bytes.extend_from_slice(namespace.as_bytes());
+  bytes.push(NAMESPACE_SEPARATOR);
+  bytes.extend_from_slice(handle);
+  bytes
+}
+
+/// Returns the namespace that `handle_bytes` carries, or None for a ledger outside namespaces
+pub fn namespace_of(handle_bytes: &[u8]) -> Option<&str> {
+  let rest = handle_bytes.strip_prefix(NAMESPACED_HANDLE_PREFIX)?;
+  let end = rest.iter().position(|b| *b == NAMESPACE_SEPARATOR)?;
+  let namespace = std::str::from_utf8(&rest[..end]).ok()?;
+  if is_valid_namespace(namespace) {
+    Some(namespace)
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::NimbleDigest;
+
+  #[test]
+  fn test_namespaced_handles() {
+    let handle = namespaced_handle("acme", b"orders");
+    assert_eq!(handle, b"nimble-ns:acme/orders");
+    assert_eq!(namespace_of(&handle), Some("acme"));
+    assert_eq!(namespace_of(b"orders"), None);
+    assert_eq!(namespaced_handle("", b"orders"), b"orders");
+
+    // the same handle in different namespaces names different ledgers
+    assert_ne!(
+      NimbleDigest::digest(&handle),
+      NimbleDigest::digest(&namespaced_handle("globex", b"orders"))
+    );
+    assert_ne!(
+      NimbleDigest::digest(&handle),
+      NimbleDigest::digest(b"orders")
+    );
+
+    // handles may hold the separator, and only the first one ends the namespace
+    assert_eq!(
+      namespace_of(&namespaced_handle("acme", b"a/b")),
+      Some("acme")
+    );
+    assert_eq!(namespace_of(b"nimble-ns:Acme/orders"), None);
+    assert_eq!(namespace_of(b"nimble-ns:acme"), None);
+
+    assert!(is_valid_namespace("tenant_42-eu"));
+    for invalid in [
+      "",
+      "a.b",
+      "a/b",
+      "ACME",
+      &"a".repeat(MAX_NAMESPACE_SIZE + 1),
+    ] {
+      assert!(!is_valid_namespace(invalid));
+    }
+  }
+}
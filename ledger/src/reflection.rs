@@ -0,0 +1,294 @@
+//! The standard gRPC server reflection service (`grpc.reflection.v1alpha.ServerReflection`), with
+//! which tools such as grpcurl list a server's services and fetch the descriptors of their methods
+//! and messages instead of reading the .proto files. The descriptors are the file descriptor sets
+//! that the build embeds from the protocol definitions.
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+use reflection_proto::{
+  server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+  server_reflection_server::ServerReflection, ErrorResponse, ExtensionNumberResponse,
+  FileDescriptorResponse, ListServiceResponse, ServerReflectionRequest, ServerReflectionResponse,
+  ServiceResponse,
+};
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status, Streaming};
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+pub mod reflection_proto {
+  tonic::include_proto!("grpc.reflection.v1alpha");
+}
+
+pub use reflection_proto::server_reflection_server::ServerReflectionServer;
+
+/// The descriptors of the endorser service
+pub const ENDORSER_DESCRIPTOR_SET: &[u8] =
+  tonic::include_file_descriptor_set!("endorser_descriptor");
+
+/// The descriptors of the health checking service
+pub const HEALTH_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("health_descriptor");
+
+/// The descriptors of the reflection service itself
+pub const REFLECTION_DESCRIPTOR_SET: &[u8] =
+  tonic::include_file_descriptor_set!("reflection_descriptor");
+
+const RESPONSE_CHANNEL_BUFFER: usize = 4;
+
+// The files of the descriptor sets, indexed by name and by the symbols they define
+struct Descriptors {
+  services: Vec<String>,
+  files: HashMap<String, FileDescriptorProto>,
+  symbols: HashMap<String, String>, // fully qualified symbol -> name of the file defining it
+}
+
+/// Describes the services of the file descriptor sets it was created with
+pub struct ReflectionService {
+  descriptors: Arc<Descriptors>,
+}
+
+// Adds the fully qualified names of `message` and the types nested in it to `symbols`
+fn add_message_symbols(
+  prefix: &str,
+  message: &DescriptorProto,
+  file: &str,
+  symbols: &mut HashMap<String, String>,
+) {
+  let name = format!("{}.{}", prefix, message.name());
+  for nested in &message.nested_type {
+    add_message_symbols(&name, nested, file, symbols);
+  }
+  for nested in &message.enum_type {
+    symbols.insert(format!("{}.{}", name, nested.name()), file.to_string());
+  }
+  symbols.insert(name, file.to_string());
+}
+
+impl ReflectionService {
+  /// Returns a service that describes the services defined in the encoded file descriptor sets
+  pub fn new(descriptor_sets: &[&[u8]]) -> Result<Self, prost::DecodeError> {
+    let mut descriptors = Descriptors {
+      services: Vec::new(),
+      files: HashMap::new(),
+      symbols: HashMap::new(),
+    };
+    for bytes in descriptor_sets {
+      for file in FileDescriptorSet::decode(*bytes)?.file {
+        let name = file.name().to_string();
+        let package = file.package();
+        let qualify = |symbol: &str| match package {
+          "" => symbol.to_string(),
+          package => format!("{}.{}", package, symbol),
+        };
+        for service in &file.service {
+          let service_name = qualify(service.name());
+          for method in &service.method {
+            let method_name = format!("{}.{}", service_name, method.name());
+            descriptors.symbols.insert(method_name, name.clone());
+          }
+          descriptors
+            .symbols
+            .insert(service_name.clone(), name.clone());
+          descriptors.services.push(service_name);
+        }
+        for message in &file.message_type {
+          add_message_symbols(package, message, &name, &mut descriptors.symbols);
+        }
+        for enum_type in &file.enum_type {
+          descriptors
+            .symbols
+            .insert(qualify(enum_type.name()), name.clone());
+        }
+        descriptors.files.insert(name, file);
+      }
+    }
+    descriptors.services.sort();
+    descriptors.services.dedup();
+    Ok(ReflectionService {
+      descriptors: Arc::new(descriptors),
+    })
+  }
+}
+
+impl Descriptors {
+  // Returns the encoded descriptors of the file `name` and the files it depends on, first the
+  // file itself, as clients expect
+  fn file_with_dependencies(&self, name: &str) -> Option<Vec<Vec<u8>>> {
+    let mut encoded = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pending = vec![name.to_string()];
+    while let Some(name) = pending.pop() {
+      if !seen.insert(name.clone()) {
+        continue;
+      }
+      let file = self.files.get(&name)?;
+      encoded.push(file.encode_to_vec());
+      pending.extend(file.dependency.iter().cloned());
+    }
+    Some(encoded)
+  }
+
+  fn respond(&self, request: ServerReflectionRequest) -> ServerReflectionResponse {
+    let not_found = |what: &str| {
+      MessageResponse::ErrorResponse(ErrorResponse {
+        error_code: Code::NotFound as i32,
+        error_message: format!("{} not found", what),
+      })
+    };
+    let files = |name: &str, what: &str| match self.file_with_dependencies(name) {
+      Some(file_descriptor_proto) => {
+        MessageResponse::FileDescriptorResponse(FileDescriptorResponse {
+          file_descriptor_proto,
+        })
+      },
+      None => not_found(what),
+    };
+
+    let message_response = match &request.message_request {
+      Some(MessageRequest::ListServices(_)) => {
+        MessageResponse::ListServicesResponse(ListServiceResponse {
+          service: self
+            .services
+            .iter()
+            .map(|name| ServiceResponse { name: name.clone() })
+            .collect(),
+        })
+      },
+      Some(MessageRequest::FileByFilename(name)) => files(name, name),
+      Some(MessageRequest::FileContainingSymbol(symbol)) => match self.symbols.get(symbol) {
+        Some(name) => files(name, symbol),
+        None => not_found(symbol),
+      },
+      // the protocol definitions declare no extensions
+      Some(MessageRequest::FileContainingExtension(extension)) => {
+        not_found(&extension.containing_type)
+      },
+      Some(MessageRequest::AllExtensionNumbersOfType(base_type_name)) => {
+        if self.symbols.contains_key(base_type_name) {
+          MessageResponse::AllExtensionNumbersResponse(ExtensionNumberResponse {
+            base_type_name: base_type_name.clone(),
+            extension_number: Vec::new(),
+          })
+        } else {
+          not_found(base_type_name)
+        }
+      },
+      None => MessageResponse::ErrorResponse(ErrorResponse {
+        error_code: Code::InvalidArgument as i32,
+        error_message: String::from("The request is empty"),
+      }),
+    };
+    ServerReflectionResponse {
+      valid_host: request.host.clone(),
+      original_request: Some(request),
+      message_response: Some(message_response),
+    }
+  }
+}
+
+#[tonic::async_trait]
+impl ServerReflection for ReflectionService {
+  type ServerReflectionInfoStream = ReceiverStream<Result<ServerReflectionResponse, Status>>;
+
+  async fn server_reflection_info(
+    &self,
+    request: Request<Streaming<ServerReflectionRequest>>,
+  ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+    let mut requests = request.into_inner();
+    let descriptors = self.descriptors.clone();
+    let (tx, rx) = mpsc::channel(RESPONSE_CHANNEL_BUFFER);
+    tokio::spawn(async move {
+      loop {
+        let res = match requests.message().await {
+          Ok(Some(request)) => Ok(descriptors.respond(request)),
+          Ok(None) => break,
+          Err(status) => Err(status),
+        };
+        let failed = res.is_err();
+        if tx.send(res).await.is_err() || failed {
+          break;
+        }
+      }
+    });
+
+    Ok(Response::new(ReceiverStream::new(rx)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn request(message_request: MessageRequest) -> ServerReflectionRequest {
+    ServerReflectionRequest {
+      host: String::new(),
+      message_request: Some(message_request),
+    }
+  }
+
+  #[test]
+  fn test_reflection_service() {
+    let service = ReflectionService::new(&[
+      ENDORSER_DESCRIPTOR_SET,
+      HEALTH_DESCRIPTOR_SET,
+      REFLECTION_DESCRIPTOR_SET,
+    ])
+    .unwrap();
+    let descriptors = &service.descriptors;
+
+    let response = descriptors.respond(request(MessageRequest::ListServices(String::new())));
+    match response.message_response {
+      Some(MessageResponse::ListServicesResponse(ListServiceResponse { service })) => {
+        let names = service.into_iter().map(|s| s.name).collect::<Vec<_>>();
+        assert_eq!(
+          names,
+          vec![
+            "endorser_proto.EndorserCall",
+            "grpc.health.v1.Health",
+            "grpc.reflection.v1alpha.ServerReflection",
+          ]
+        );
+      },
+      response => panic!("unexpected response {:?}", response),
+    }
+
+    // services, methods and messages are found in the file that defines them
+    for symbol in [
+      "endorser_proto.EndorserCall",
+      "endorser_proto.EndorserCall.Append",
+      "endorser_proto.AppendReq",
+      "grpc.health.v1.HealthCheckResponse.ServingStatus",
+    ] {
+      let response = descriptors.respond(request(MessageRequest::FileContainingSymbol(
+        symbol.to_string(),
+      )));
+      match response.message_response {
+        Some(MessageResponse::FileDescriptorResponse(files)) => {
+          let file = FileDescriptorProto::decode(&files.file_descriptor_proto[0][..]).unwrap();
+          assert!(symbol.starts_with(file.package()));
+        },
+        response => panic!("no file for {}: {:?}", symbol, response),
+      }
+    }
+
+    let response = descriptors.respond(request(MessageRequest::FileByFilename(
+      "health.proto".to_string(),
+    )));
+    assert!(matches!(
+      response.message_response,
+      Some(MessageResponse::FileDescriptorResponse(_))
+    ));
+    let response = descriptors.respond(request(MessageRequest::FileContainingSymbol(
+      "endorser_proto.Unknown".to_string(),
+    )));
+    match response.message_response {
+      Some(MessageResponse::ErrorResponse(error)) => {
+        assert_eq!(error.error_code, Code::NotFound as i32)
+      },
+      response => panic!("unexpected response {:?}", response),
+    }
+  }
+}
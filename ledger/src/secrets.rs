@@ -0,0 +1,167 @@
+//! Access to credentials, keys and other secret material. Services name the secrets they need
+//! and read them through a `SecretProvider` whenever they are used, so a rotated secret takes
+//! effect without a restart.
+use crate::errors::SecretError;
+use std::{
+  collections::HashMap,
+  path::PathBuf,
+  sync::RwLock,
+  time::{Duration, Instant},
+};
+
+pub trait SecretProvider: Send + Sync {
+  /// Returns the current value of the secret called `name`
+  fn get_secret(&self, name: &str) -> Result<String, SecretError>;
+}
+
+/// Reads secrets from environment variables named `<prefix><name>`
+pub struct EnvSecrets {
+  prefix: String,
+}
+
+impl EnvSecrets {
+  pub fn new(prefix: &str) -> Self {
+    EnvSecrets {
+      prefix: prefix.to_string(),
+    }
+  }
+}
+
+impl SecretProvider for EnvSecrets {
+  fn get_secret(&self, name: &str) -> Result<String, SecretError> {
+    match std::env::var(format!("{}{}", self.prefix, name)) {
+      Ok(value) => Ok(value),
+      Err(std::env::VarError::NotPresent) => Err(SecretError::NotFound),
+      Err(std::env::VarError::NotUnicode(_)) => Err(SecretError::FailedToRead),
+    }
+  }
+}
+
+/// Reads each secret from a file named after it in a directory, which is how Kubernetes and
+/// the key vault agents mount secrets. The file is read on every lookup, so rewriting it
+/// rotates the secret.
+pub struct FileSecrets {
+  dir: PathBuf,
+}
+
+impl FileSecrets {
+  pub fn new(dir: &str) -> Self {
+    FileSecrets {
+      dir: PathBuf::from(dir),
+    }
+  }
+}
+
+impl SecretProvider for FileSecrets {
+  fn get_secret(&self, name: &str) -> Result<String, SecretError> {
+    // a secret name must not escape the directory
+    if name.is_empty() || name.contains('/') || name.contains('\\') || name.starts_with('.') {
+      return Err(SecretError::NotFound);
+    }
+    match std::fs::read_to_string(self.dir.join(name)) {
+      Ok(value) => Ok(value.trim_end_matches(&['\r', '\n'][..]).to_string()),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(SecretError::NotFound),
+      Err(_) => Err(SecretError::FailedToRead),
+    }
+  }
+}
+
+/// Caches the secrets of another provider for `ttl`, for providers that are expensive to query.
+/// A rotated secret is picked up once its cached value expires.
+pub struct CachedSecrets {
+  provider: Box<dyn SecretProvider>,
+  ttl: Duration,
+  cache: RwLock<HashMap<String, (String, Instant)>>,
+}
+
+impl CachedSecrets {
+  pub fn new(provider: Box<dyn SecretProvider>, ttl: Duration) -> Self {
+    CachedSecrets {
+      provider,
+      ttl,
+      cache: RwLock::new(HashMap::new()),
+    }
+  }
+}
+
+impl SecretProvider for CachedSecrets {
+  fn get_secret(&self, name: &str) -> Result<String, SecretError> {
+    if let Ok(cache) = self.cache.read() {
+      if let Some((value, fetched)) = cache.get(name) {
+        if fetched.elapsed() < self.ttl {
+          return Ok(value.clone());
+        }
+      }
+    }
+
+    let value = self.provider.get_secret(name)?;
+    if let Ok(mut cache) = self.cache.write() {
+      cache.insert(name.to_string(), (value.clone(), Instant::now()));
+    }
+    Ok(value)
+  }
+}
+
+/// Builds the provider described by `uri`: `env` or `env:<prefix>` for environment variables,
+/// and `file:<dir>` for a directory of secret files. A `;ttl=<secs>` suffix caches lookups.
+pub fn secret_provider_from_uri(uri: &str) -> Result<Box<dyn SecretProvider>, SecretError> {
+  let (location, ttl) = match uri.split_once(";ttl=") {
+    Some((location, secs)) => match secs.parse::<u64>() {
+      Ok(secs) => (location, Some(Duration::from_secs(secs))),
+      Err(_) => return Err(SecretError::InvalidProvider),
+    },
+    None => (uri, None),
+  };
+
+  let provider: Box<dyn SecretProvider> = if location == "env" {
+    Box::new(EnvSecrets::new(""))
+  } else if let Some(prefix) = location.strip_prefix("env:") {
+    Box::new(EnvSecrets::new(prefix))
+  } else if let Some(dir) = location.strip_prefix("file:") {
+    if dir.is_empty() {
+      return Err(SecretError::InvalidProvider);
+    }
+    Box::new(FileSecrets::new(dir))
+  } else {
+    return Err(SecretError::InvalidProvider);
+  };
+
+  Ok(match ttl {
+    Some(ttl) => Box::new(CachedSecrets::new(provider, ttl)),
+    None => provider,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  pub fn test_secret_rotation() {
+    let dir = std::env::temp_dir().join(format!("nimble-secrets-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("STORAGE_MASTER_KEY"), "first\n").unwrap();
+
+    let uri = format!("file:{}", dir.display());
+    let files = secret_provider_from_uri(&uri).unwrap();
+    let cached = secret_provider_from_uri(&format!("{};ttl=3600", uri)).unwrap();
+    assert_eq!(files.get_secret("STORAGE_MASTER_KEY").unwrap(), "first");
+    assert_eq!(cached.get_secret("STORAGE_MASTER_KEY").unwrap(), "first");
+    assert_eq!(files.get_secret("COSMOS_URL"), Err(SecretError::NotFound));
+    assert_eq!(files.get_secret("../etc"), Err(SecretError::NotFound));
+
+    // rewriting the file rotates the secret; the cache serves the old value until it expires
+    std::fs::write(dir.join("STORAGE_MASTER_KEY"), "second").unwrap();
+    assert_eq!(files.get_secret("STORAGE_MASTER_KEY").unwrap(), "second");
+    assert_eq!(cached.get_secret("STORAGE_MASTER_KEY").unwrap(), "first");
+    let expired = CachedSecrets::new(
+      Box::new(FileSecrets::new(&dir.display().to_string())),
+      Duration::ZERO,
+    );
+    assert_eq!(expired.get_secret("STORAGE_MASTER_KEY").unwrap(), "second");
+
+    assert!(secret_provider_from_uri("vault:foo").is_err());
+    assert!(secret_provider_from_uri("env;ttl=x").is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
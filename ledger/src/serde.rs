@@ -0,0 +1,228 @@
+//! Parsing of the bytes that services exchange. The bytes come from peers that may be faulty or
+//! malicious, so every parser here checks lengths before it slices and returns an error instead
+//! of panicking on input it does not expect.
+
+use std::{convert::TryInto, fmt};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CustomSerdeError {
+  /// returned if the supplied byte array is of incorrect length
+  IncorrectLength,
+  /// returned if deserializing any byte entry into the Rust type fails
+  InternalError,
+  /// returned if the bytes carry a format version that this build does not know
+  UnsupportedVersion,
+  /// returned if the bytes of `what` end before a field that needs `needed` more bytes
+  Truncated {
+    what: &'static str,
+    offset: usize,
+    needed: usize,
+  },
+  /// returned if bytes remain after the last field of `what`
+  TrailingBytes {
+    what: &'static str,
+    offset: usize,
+    remaining: usize,
+  },
+  /// returned if a field of `what` holds a value that the format does not allow
+  InvalidValue { what: &'static str, offset: usize },
+}
+
+impl fmt::Display for CustomSerdeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CustomSerdeError::IncorrectLength => write!(f, "incorrect length"),
+      CustomSerdeError::InternalError => write!(f, "malformed bytes"),
+      CustomSerdeError::UnsupportedVersion => write!(f, "unsupported version"),
+      CustomSerdeError::Truncated {
+        what,
+        offset,
+        needed,
+      } => write!(
+        f,
+        "{} ends at byte {} but needs {} more",
+        what, offset, needed
+      ),
+      CustomSerdeError::TrailingBytes {
+        what,
+        offset,
+        remaining,
+      } => write!(
+        f,
+        "{} has {} bytes left over at byte {}",
+        what, remaining, offset
+      ),
+      CustomSerdeError::InvalidValue { what, offset } => {
+        write!(f, "{} has an invalid value at byte {}", what, offset)
+      },
+    }
+  }
+}
+
+pub trait CustomSerde
+where
+  Self: Sized,
+{
+  fn to_bytes(&self) -> Vec<u8>;
+  fn from_bytes(bytes: &[u8]) -> Result<Self, CustomSerdeError>;
+}
+
+/// A cursor over the bytes of one encoded `what` that hands out fields front to back. Every read
+/// checks the bytes that remain, so parsers never index past the end of their input.
+pub struct Reader<'a> {
+  what: &'static str,
+  bytes: &'a [u8],
+  offset: usize,
+}
+
+impl<'a> Reader<'a> {
+  pub fn new(what: &'static str, bytes: &'a [u8]) -> Self {
+    Reader {
+      what,
+      bytes,
+      offset: 0,
+    }
+  }
+
+  /// the position of the next field in the input
+  pub fn offset(&self) -> usize {
+    self.offset
+  }
+
+  /// the bytes that have not been read yet
+  pub fn remaining(&self) -> &'a [u8] {
+    &self.bytes[self.offset..]
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.offset == self.bytes.len()
+  }
+
+  /// reads the next `len` bytes
+  pub fn take(&mut self, len: usize) -> Result<&'a [u8], CustomSerdeError> {
+    let rest = self.remaining();
+    if rest.len() < len {
+      return Err(CustomSerdeError::Truncated {
+        what: self.what,
+        offset: self.offset,
+        needed: len - rest.len(),
+      });
+    }
+    self.offset += len;
+    Ok(&rest[..len])
+  }
+
+  /// reads the next `N` bytes as an array
+  pub fn take_array<const N: usize>(&mut self) -> Result<[u8; N], CustomSerdeError> {
+    let offset = self.offset;
+    let what = self.what;
+    self
+      .take(N)?
+      .try_into()
+      .map_err(|_e| CustomSerdeError::InvalidValue { what, offset })
+  }
+
+  pub fn read_u8(&mut self) -> Result<u8, CustomSerdeError> {
+    Ok(self.take_array::<1>()?[0])
+  }
+
+  pub fn read_u16(&mut self) -> Result<u16, CustomSerdeError> {
+    Ok(u16::from_le_bytes(self.take_array()?))
+  }
+
+  pub fn read_u32(&mut self) -> Result<u32, CustomSerdeError> {
+    Ok(u32::from_le_bytes(self.take_array()?))
+  }
+
+  pub fn read_u64(&mut self) -> Result<u64, CustomSerdeError> {
+    Ok(u64::from_le_bytes(self.take_array()?))
+  }
+
+  /// reads a length field of `len_bytes` bytes followed by that many bytes
+  pub fn take_prefixed(&mut self, len_bytes: usize) -> Result<&'a [u8], CustomSerdeError> {
+    let len = match len_bytes {
+      1 => self.read_u8()? as usize,
+      2 => self.read_u16()? as usize,
+      4 => self.read_u32()? as usize,
+      _ => return Err(self.invalid()),
+    };
+    self.take(len)
+  }
+
+  /// reads `count` fields of `len` bytes each, rejecting a count whose size overflows before
+  /// allocating anything for it
+  pub fn take_chunks(
+    &mut self,
+    count: usize,
+    len: usize,
+  ) -> Result<std::slice::ChunksExact<'a, u8>, CustomSerdeError> {
+    let total = count.checked_mul(len).ok_or_else(|| self.invalid())?;
+    if len == 0 {
+      return Err(self.invalid());
+    }
+    Ok(self.take(total)?.chunks_exact(len))
+  }
+
+  /// the error for a field that was read at the current offset but holds a value the format
+  /// does not allow
+  pub fn invalid(&self) -> CustomSerdeError {
+    CustomSerdeError::InvalidValue {
+      what: self.what,
+      offset: self.offset,
+    }
+  }
+
+  /// checks that the whole input was read
+  pub fn finish(self) -> Result<(), CustomSerdeError> {
+    if self.is_empty() {
+      Ok(())
+    } else {
+      Err(CustomSerdeError::TrailingBytes {
+        what: self.what,
+        offset: self.offset,
+        remaining: self.bytes.len() - self.offset,
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_reader() {
+    let bytes = [1u8, 2, 0, 3, 0, 0, 0, 2, 7, 8, 9];
+    let mut reader = Reader::new("test", &bytes);
+    assert_eq!(reader.read_u8().unwrap(), 1);
+    assert_eq!(reader.read_u16().unwrap(), 2);
+    assert_eq!(reader.read_u32().unwrap(), 3);
+    assert_eq!(reader.take_prefixed(1).unwrap(), &[7, 8]);
+    assert_eq!(reader.offset(), 10);
+    assert_eq!(
+      reader.take(2).unwrap_err(),
+      CustomSerdeError::Truncated {
+        what: "test",
+        offset: 10,
+        needed: 1,
+      }
+    );
+    assert_eq!(
+      reader.finish(),
+      Err(CustomSerdeError::TrailingBytes {
+        what: "test",
+        offset: 10,
+        remaining: 1,
+      })
+    );
+
+    // a count whose size overflows is rejected without reading
+    let mut reader = Reader::new("test", &bytes);
+    assert!(matches!(
+      reader.take_chunks(usize::MAX, 2),
+      Err(CustomSerdeError::InvalidValue { .. })
+    ));
+    assert_eq!(reader.take_chunks(2, 3).unwrap().count(), 2);
+    assert_eq!(reader.remaining(), &bytes[6..]);
+  }
+}
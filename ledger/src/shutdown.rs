@@ -0,0 +1,73 @@
+//! Graceful shutdown of the Nimble services. On SIGTERM, or Ctrl-C from a terminal, a service
+//! stops accepting requests and lets the requests in flight finish before it exits, but waits for
+//! them no longer than its shutdown timeout, so that a stuck request cannot keep it from exiting.
+use std::future::Future;
+use tokio::sync::watch;
+
+/// Returns the name of the signal that asks the process to shut down, once it receives one
+pub async fn shutdown_signal() -> &'static str {
+  #[cfg(unix)]
+  {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+      Ok(mut terminate) => tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = terminate.recv() => "SIGTERM",
+      },
+      Err(_) => {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+      },
+    }
+  }
+  #[cfg(not(unix))]
+  {
+    let _ = tokio::signal::ctrl_c().await;
+    "Ctrl-C"
+  }
+}
+
+/// Tells the servers of a service that it shuts down, so that they stop accepting requests
+pub struct Shutdown {
+  started: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+  fn default() -> Self {
+    Shutdown {
+      started: watch::channel(false).0,
+    }
+  }
+}
+
+impl Shutdown {
+  /// Returns a future that completes once the shutdown starts, for a server to stop with
+  pub fn started(&self) -> impl Future<Output = ()> + Send + 'static {
+    let mut started = self.started.subscribe();
+    async move {
+      let _ = started.wait_for(|started| *started).await;
+    }
+  }
+
+  pub fn start(&self) {
+    self.started.send_replace(true);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_shutdown() {
+    let shutdown = Shutdown::default();
+    let before = tokio::spawn(shutdown.started());
+    tokio::task::yield_now().await;
+    assert!(!before.is_finished());
+
+    // the servers that start waiting once the shutdown started stop at once, too
+    shutdown.start();
+    before.await.unwrap();
+    shutdown.started().await;
+  }
+}
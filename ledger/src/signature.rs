@@ -1,3 +1,4 @@
+use crate::zeroize::Zeroizing;
 use core::fmt::Debug;
 use itertools::concat;
 use openssl::{
@@ -5,7 +6,8 @@ use openssl::{
   ec::*,
   ecdsa::EcdsaSig,
   nid::Nid,
-  pkey::{Private, Public},
+  pkey::{Id, PKey, Private, Public},
+  sign::{Signer, Verifier},
 };
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -20,6 +22,8 @@ pub enum CryptoError {
   InvalidPrivateKeyPem,
   /// returned if there is an error when deriving a signature from DER
   FailedToGetSigFromDER,
+  /// returned if a key or signature uses a scheme that is unknown or not compiled in
+  UnsupportedScheme,
 }
 
 pub trait PublicKeyTrait {
@@ -53,152 +57,344 @@ pub trait SignatureTrait {
   fn to_bytes(&self) -> Vec<u8>;
 }
 
-/// Types and concrete implementations of types for ECDSA algorithm with P-256 using OpenSSL
+/// The signature schemes that keys can use. Serialized keys and signatures start with the
+/// identifier of their scheme, so endorsers that use different schemes can serve in the same view
+/// and every receipt records the scheme that signed it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SignatureScheme {
+  /// ECDSA with P-256, which is the default
+  P256,
+  /// ECDSA with secp256k1
+  #[cfg(feature = "secp256k1")]
+  Secp256k1,
+  /// EdDSA with Curve25519
+  #[cfg(feature = "ed25519")]
+  Ed25519,
+}
+
+// the width of key material and signatures within their serialization, which is fixed so that
+// receipts can be parsed without length prefixes; Ed25519 keys are padded with a zero byte
+const KEY_MATERIAL_BYTES: usize = 33;
+const SIGNATURE_MATERIAL_BYTES: usize = 64;
+
+impl SignatureScheme {
+  /// the schemes compiled into this build
+  pub fn all() -> Vec<SignatureScheme> {
+    vec![
+      SignatureScheme::P256,
+      #[cfg(feature = "secp256k1")]
+      SignatureScheme::Secp256k1,
+      #[cfg(feature = "ed25519")]
+      SignatureScheme::Ed25519,
+    ]
+  }
+
+  pub fn get_id(&self) -> u8 {
+    match self {
+      SignatureScheme::P256 => 1,
+      #[cfg(feature = "secp256k1")]
+      SignatureScheme::Secp256k1 => 2,
+      #[cfg(feature = "ed25519")]
+      SignatureScheme::Ed25519 => 3,
+    }
+  }
+
+  pub fn from_id(id: u8) -> Result<Self, CryptoError> {
+    SignatureScheme::all()
+      .into_iter()
+      .find(|scheme| scheme.get_id() == id)
+      .ok_or(CryptoError::UnsupportedScheme)
+  }
+
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      SignatureScheme::P256 => "p256",
+      #[cfg(feature = "secp256k1")]
+      SignatureScheme::Secp256k1 => "secp256k1",
+      #[cfg(feature = "ed25519")]
+      SignatureScheme::Ed25519 => "ed25519",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Result<Self, CryptoError> {
+    SignatureScheme::all()
+      .into_iter()
+      .find(|scheme| scheme.get_name() == name)
+      .ok_or(CryptoError::UnsupportedScheme)
+  }
+
+  // the curve of an ECDSA scheme, or None for EdDSA
+  fn curve(&self) -> Option<Nid> {
+    match self {
+      SignatureScheme::P256 => Some(Nid::X9_62_PRIME256V1),
+      #[cfg(feature = "secp256k1")]
+      SignatureScheme::Secp256k1 => Some(Nid::SECP256K1),
+      #[cfg(feature = "ed25519")]
+      SignatureScheme::Ed25519 => None,
+    }
+  }
+}
+
+/// Types and concrete implementations of types for the supported schemes using OpenSSL
 pub struct PublicKey {
-  key: EcKey<Public>,
+  scheme: SignatureScheme,
+  key: PKey<Public>,
 }
 
+// OpenSSL holds the key material and clears it when the key is freed
 pub struct PrivateKey {
-  key: EcKey<Private>,
+  scheme: SignatureScheme,
+  key: PKey<Private>,
 }
 
 pub struct Signature {
-  sig: EcdsaSig,
+  scheme: SignatureScheme,
+  sig: Vec<u8>,
 }
 
 impl PublicKeyTrait for PublicKey {
   fn num_bytes() -> usize {
-    33
+    1 + KEY_MATERIAL_BYTES
   }
 
+  /// parses a serialized key; an untagged compressed P-256 point, as produced by earlier versions
+  /// and by clients, is accepted as well
   fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
-    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
-    let point = {
-      let mut ctx = BigNumContext::new().unwrap();
-      let res = EcPoint::from_bytes(&group, bytes, &mut ctx);
-      if res.is_err() {
-        return Err(CryptoError::InvalidPublicKeyBytes);
-      }
-      res.unwrap()
+    let (scheme, material) = if bytes.len() == Self::num_bytes() {
+      (SignatureScheme::from_id(bytes[0])?, &bytes[1..])
+    } else if bytes.len() == KEY_MATERIAL_BYTES {
+      (SignatureScheme::P256, bytes)
+    } else {
+      return Err(CryptoError::InvalidPublicKeyBytes);
     };
 
-    let res = EcKey::from_public_key(&group, &point);
-    if let Ok(key) = res {
-      Ok(PublicKey { key })
+    let key = match scheme.curve() {
+      Some(nid) => {
+        let res = EcGroup::from_curve_name(nid)
+          .and_then(|group| Ok((BigNumContext::new()?, group)))
+          .and_then(|(mut ctx, group)| {
+            Ok((EcPoint::from_bytes(&group, material, &mut ctx)?, group))
+          });
+        if res.is_err() {
+          return Err(CryptoError::InvalidPublicKeyBytes);
+        }
+        let (point, group) = res.unwrap();
+        EcKey::from_public_key(&group, &point).and_then(PKey::from_ec_key)
+      },
+      None => {
+        let (material, padding) = material.split_at(KEY_MATERIAL_BYTES - 1);
+        if padding.iter().any(|b| *b != 0) {
+          return Err(CryptoError::InvalidPublicKeyBytes);
+        }
+        PKey::public_key_from_raw_bytes(material, Id::ED25519)
+      },
+    };
+    if let Ok(key) = key {
+      Ok(PublicKey { scheme, key })
     } else {
       Err(CryptoError::InvalidPublicKeyBytes)
     }
   }
 
   fn to_bytes(&self) -> Vec<u8> {
-    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
-    let mut ctx = BigNumContext::new().unwrap();
-    self
-      .key
-      .public_key()
-      .to_bytes(&group, PointConversionForm::COMPRESSED, &mut ctx)
-      .unwrap()
+    let mut bytes = vec![self.scheme.get_id()];
+    bytes.extend(self.to_raw());
+    bytes.resize(Self::num_bytes(), 0);
+    bytes
   }
 }
 
 impl PublicKey {
+  /// the length of an untagged P-256 key, the encoding of keys before they carried their scheme
+  pub fn num_untagged_bytes() -> usize {
+    KEY_MATERIAL_BYTES
+  }
+
+  pub fn get_scheme(&self) -> SignatureScheme {
+    self.scheme
+  }
+
   pub fn to_der(&self) -> Vec<u8> {
     self.key.public_key_to_der().unwrap()
   }
 
-  pub fn to_uncompressed(&self) -> Vec<u8> {
-    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+  // encodes the point of an ECDSA key
+  fn ec_point_bytes(&self, form: PointConversionForm) -> Vec<u8> {
+    let group = EcGroup::from_curve_name(self.scheme.curve().unwrap()).unwrap();
     let mut ctx = BigNumContext::new().unwrap();
     self
       .key
+      .ec_key()
+      .unwrap()
       .public_key()
-      .to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut ctx)
+      .to_bytes(&group, form, &mut ctx)
       .unwrap()
   }
+
+  /// returns the key without the scheme identifier: a compressed point for ECDSA keys and the
+  /// 32-byte key for Ed25519
+  pub fn to_raw(&self) -> Vec<u8> {
+    match self.scheme.curve() {
+      Some(_) => self.ec_point_bytes(PointConversionForm::COMPRESSED),
+      None => self.key.raw_public_key().unwrap(),
+    }
+  }
+
+  /// returns an uncompressed point for ECDSA keys; Ed25519 keys have a single encoding
+  pub fn to_uncompressed(&self) -> Vec<u8> {
+    match self.scheme.curve() {
+      Some(_) => self.ec_point_bytes(PointConversionForm::UNCOMPRESSED),
+      None => self.to_raw(),
+    }
+  }
 }
 
 impl PrivateKeyTrait for PrivateKey {
   fn new() -> Self {
-    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
-    let key = EcKey::generate(&group).unwrap();
-    PrivateKey { key }
+    PrivateKey::generate(SignatureScheme::P256)
   }
 
   fn get_public_key(&self) -> Result<PublicKey, CryptoError> {
-    let key = {
-      let point = self.key.public_key();
-      let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
-      let res = EcKey::from_public_key(&group, point);
-      if res.is_err() {
-        return Err(CryptoError::InvalidPublicKeyBytes);
-      }
-      res.unwrap()
+    let key = match self.scheme.curve() {
+      Some(nid) => {
+        let group = EcGroup::from_curve_name(nid).unwrap();
+        let ec_key = self.key.ec_key().unwrap();
+        EcKey::from_public_key(&group, ec_key.public_key()).and_then(PKey::from_ec_key)
+      },
+      None => self
+        .key
+        .raw_public_key()
+        .and_then(|raw| PKey::public_key_from_raw_bytes(&raw, Id::ED25519)),
     };
-    Ok(PublicKey { key })
+    if key.is_err() {
+      return Err(CryptoError::InvalidPublicKeyBytes);
+    }
+    Ok(PublicKey {
+      scheme: self.scheme,
+      key: key.unwrap(),
+    })
   }
 
   fn sign(&self, msg: &[u8]) -> Result<Signature, CryptoError> {
-    let sig = {
-      let res = EcdsaSig::sign(msg, &self.key);
-      if res.is_err() {
-        return Err(CryptoError::SignatureGenerationError);
-      }
-      res.unwrap()
+    let sig = match self.scheme.curve() {
+      Some(_) => {
+        let res = EcdsaSig::sign(msg, &self.key.ec_key().unwrap());
+        if res.is_err() {
+          return Err(CryptoError::SignatureGenerationError);
+        }
+        let sig = res.unwrap();
+        let r = sig.r().to_vec_padded((SIGNATURE_MATERIAL_BYTES / 2) as i32);
+        let s = sig.s().to_vec_padded((SIGNATURE_MATERIAL_BYTES / 2) as i32);
+        if r.is_err() || s.is_err() {
+          return Err(CryptoError::SignatureGenerationError);
+        }
+        concat(vec![r.unwrap(), s.unwrap()]).to_vec()
+      },
+      None => {
+        let res = Signer::new_without_digest(&self.key).and_then(|mut signer| {
+          let mut sig = vec![0u8; SIGNATURE_MATERIAL_BYTES];
+          signer.sign_oneshot(&mut sig, msg).map(|_len| sig)
+        });
+        if res.is_err() {
+          return Err(CryptoError::SignatureGenerationError);
+        }
+        res.unwrap()
+      },
     };
-    Ok(Signature { sig })
+    Ok(Signature {
+      scheme: self.scheme,
+      sig,
+    })
   }
 }
 
 impl PrivateKey {
+  /// generates a key for `scheme`
+  pub fn generate(scheme: SignatureScheme) -> PrivateKey {
+    let key = match scheme.curve() {
+      Some(nid) => {
+        let group = EcGroup::from_curve_name(nid).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+      },
+      None => PKey::generate_ed25519().unwrap(),
+    };
+    PrivateKey { scheme, key }
+  }
+
+  pub fn get_scheme(&self) -> SignatureScheme {
+    self.scheme
+  }
+
+  /// returns the key in PKCS#8 PEM, which `from_pem` reads back, and zeroizes it once dropped
+  pub fn to_pem(&self) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(self.key.private_key_to_pem_pkcs8().unwrap())
+  }
+
+  /// reads a key of any supported scheme, which is inferred from the key itself
   pub fn from_pem(pem: &[u8]) -> Result<PrivateKey, CryptoError> {
-    let res = EcKey::private_key_from_pem(pem);
+    let res = PKey::private_key_from_pem(pem);
     if res.is_err() {
       return Err(CryptoError::InvalidPrivateKeyPem);
     }
     let key = res.unwrap();
-    Ok(PrivateKey { key })
+    let scheme = match key.id() {
+      Id::EC => {
+        let curve = key.ec_key().ok().and_then(|k| k.group().curve_name());
+        SignatureScheme::all()
+          .into_iter()
+          .find(|scheme| scheme.curve().is_some() && scheme.curve() == curve)
+      },
+      Id::ED25519 => SignatureScheme::all()
+        .into_iter()
+        .find(|scheme| scheme.curve().is_none()),
+      _ => None,
+    };
+    match scheme {
+      Some(scheme) => Ok(PrivateKey { scheme, key }),
+      None => Err(CryptoError::InvalidPrivateKeyPem),
+    }
   }
 }
 
 impl SignatureTrait for Signature {
   fn num_bytes() -> usize {
-    64
+    1 + SIGNATURE_MATERIAL_BYTES
   }
 
+  /// parses a serialized signature; an untagged P-256 signature, as produced by earlier versions
+  /// and by clients, is accepted as well
   fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
-    if bytes.len() != Self::num_bytes() {
-      return Err(CryptoError::InvalidSignature);
-    }
-
-    let r = {
-      let res = BigNum::from_slice(&bytes[0..Self::num_bytes() / 2]);
+    let (scheme, sig) = if bytes.len() == Self::num_bytes() {
+      let res = SignatureScheme::from_id(bytes[0]);
       if res.is_err() {
         return Err(CryptoError::InvalidSignature);
       }
-      res.unwrap()
-    };
-    let s = {
-      let res = BigNum::from_slice(&bytes[Self::num_bytes() / 2..]);
-      if res.is_err() {
-        return Err(CryptoError::InvalidSignature);
-      }
-      res.unwrap()
-    };
-
-    let sig = {
-      let res = EcdsaSig::from_private_components(r, s);
-      if res.is_err() {
-        return Err(CryptoError::InvalidSignature);
-      }
-      res.unwrap()
+      (res.unwrap(), &bytes[1..])
+    } else if bytes.len() == SIGNATURE_MATERIAL_BYTES {
+      (SignatureScheme::P256, bytes)
+    } else {
+      return Err(CryptoError::InvalidSignature);
     };
 
-    Ok(Signature { sig })
+    Ok(Signature {
+      scheme,
+      sig: sig.to_vec(),
+    })
   }
 
   fn verify(&self, pk: &PublicKey, msg: &[u8]) -> Result<(), CryptoError> {
-    let res = self.sig.verify(msg, &pk.key);
-    if let Ok(true) = res {
+    if self.scheme != pk.scheme {
+      return Err(CryptoError::InvalidSignature);
+    }
+    let res = match self.scheme.curve() {
+      Some(_) => self
+        .to_ecdsa_sig()
+        .and_then(|sig| sig.verify(msg, &pk.key.ec_key().unwrap()).ok()),
+      None => Verifier::new_without_digest(&pk.key)
+        .and_then(|mut verifier| verifier.verify_oneshot(&self.sig, msg))
+        .ok(),
+    };
+    if let Some(true) = res {
       Ok(())
     } else {
       Err(CryptoError::InvalidSignature)
@@ -206,28 +402,59 @@ impl SignatureTrait for Signature {
   }
 
   fn to_bytes(&self) -> Vec<u8> {
-    let r = self
-      .sig
-      .r()
-      .to_vec_padded((Self::num_bytes() / 2) as i32)
-      .unwrap();
-    let s = self
-      .sig
-      .s()
-      .to_vec_padded((Self::num_bytes() / 2) as i32)
-      .unwrap();
-    concat(vec![r, s]).to_vec()
+    concat(vec![vec![self.scheme.get_id()], self.sig.clone()]).to_vec()
   }
 }
 
 impl Signature {
+  /// the length of an untagged P-256 signature, the encoding of signatures before they carried
+  /// their scheme
+  pub fn num_untagged_bytes() -> usize {
+    SIGNATURE_MATERIAL_BYTES
+  }
+
+  pub fn get_scheme(&self) -> SignatureScheme {
+    self.scheme
+  }
+
+  // the signature of an ECDSA scheme as its (r, s) components
+  fn to_ecdsa_sig(&self) -> Option<EcdsaSig> {
+    let r = BigNum::from_slice(&self.sig[0..SIGNATURE_MATERIAL_BYTES / 2]);
+    let s = BigNum::from_slice(&self.sig[SIGNATURE_MATERIAL_BYTES / 2..]);
+    if r.is_err() || s.is_err() {
+      return None;
+    }
+    EcdsaSig::from_private_components(r.unwrap(), s.unwrap()).ok()
+  }
+
+  /// returns the signature without the scheme identifier
+  pub fn to_raw(&self) -> Vec<u8> {
+    self.sig.clone()
+  }
+
+  /// returns the DER encoding of an ECDSA signature; Ed25519 signatures have no DER encoding, so
+  /// they are returned raw
   pub fn to_der(&self) -> Vec<u8> {
-    self.sig.to_der().unwrap()
+    match self.scheme.curve() {
+      Some(_) => self.to_ecdsa_sig().unwrap().to_der().unwrap(),
+      None => self.to_raw(),
+    }
   }
 
+  /// parses the DER encoding of a P-256 signature
   pub fn from_der(der: &[u8]) -> Result<Self, CryptoError> {
     match EcdsaSig::from_der(der) {
-      Ok(sig) => Ok(Signature { sig }),
+      Ok(sig) => {
+        let r = sig.r().to_vec_padded((SIGNATURE_MATERIAL_BYTES / 2) as i32);
+        let s = sig.s().to_vec_padded((SIGNATURE_MATERIAL_BYTES / 2) as i32);
+        if r.is_err() || s.is_err() {
+          return Err(CryptoError::FailedToGetSigFromDER);
+        }
+        Ok(Signature {
+          scheme: SignatureScheme::P256,
+          sig: concat(vec![r.unwrap(), s.unwrap()]).to_vec(),
+        })
+      },
       Err(_) => Err(CryptoError::FailedToGetSigFromDER),
     }
   }
@@ -296,4 +523,64 @@ mod tests {
     let res = sig.verify(&pk, &m);
     assert!(res.is_ok());
   }
+
+  #[test]
+  fn test_signature_schemes() {
+    let msg = b"hello world";
+    let sks = SignatureScheme::all()
+      .into_iter()
+      .map(PrivateKey::generate)
+      .collect::<Vec<PrivateKey>>();
+    for sk in &sks {
+      let pk = sk.get_public_key().unwrap();
+      let sig = sk.sign(msg.as_slice()).unwrap();
+
+      // keys and signatures have a fixed width and carry their scheme
+      let pk_bytes = pk.to_bytes();
+      let sig_bytes = sig.to_bytes();
+      assert_eq!(pk_bytes.len(), PublicKey::num_bytes());
+      assert_eq!(sig_bytes.len(), Signature::num_bytes());
+      assert_eq!(
+        SignatureScheme::from_id(pk_bytes[0]).unwrap(),
+        sk.get_scheme()
+      );
+      let pk = PublicKey::from_bytes(&pk_bytes).unwrap();
+      let sig = Signature::from_bytes(&sig_bytes).unwrap();
+      assert_eq!(sig.get_scheme(), sk.get_scheme());
+      assert!(sig.verify(&pk, msg.as_slice()).is_ok());
+      assert!(sig.verify(&pk, b"hello world2").is_err());
+
+      // a signature does not verify under a key of another scheme or with another tag
+      for other in &sks {
+        if other.get_scheme() != sk.get_scheme() {
+          assert!(sig
+            .verify(&other.get_public_key().unwrap(), msg.as_slice())
+            .is_err());
+        }
+      }
+      let scheme = SignatureScheme::from_name(sk.get_scheme().get_name()).unwrap();
+      assert_eq!(scheme, sk.get_scheme());
+      assert_eq!(
+        PrivateKey::from_pem(&sk.to_pem()).unwrap().get_scheme(),
+        scheme
+      );
+    }
+
+    // untagged P-256 keys and signatures are still accepted
+    let sk = PrivateKey::new();
+    let pk = sk.get_public_key().unwrap();
+    let sig = sk.sign(msg.as_slice()).unwrap();
+    let pk = PublicKey::from_bytes(&pk.to_raw()).unwrap();
+    let sig = Signature::from_bytes(&sig.to_raw()).unwrap();
+    assert!(sig.verify(&pk, msg.as_slice()).is_ok());
+    let sig = Signature::from_der(&sig.to_der()).unwrap();
+    assert!(sig.verify(&pk, msg.as_slice()).is_ok());
+
+    let mut pk_bytes = pk.to_bytes();
+    pk_bytes[0] = 0xff;
+    assert_eq!(
+      PublicKey::from_bytes(&pk_bytes).err(),
+      Some(CryptoError::UnsupportedScheme)
+    );
+  }
 }
@@ -0,0 +1,366 @@
+//! Distributed tracing for the Nimble services. Every span that the `logging` subscriber keeps
+//! belongs to a trace, as the W3C trace-context specification has it: a request that arrives with
+//! a `traceparent` header continues the caller's trace, and the coordinator passes the trace on to
+//! endorsers in the `traceparent` metadata of its calls. When a service is given an OTLP endpoint,
+//! the spans are exported to it in batches as OTLP/HTTP JSON, so a collector such as the
+//! OpenTelemetry Collector or Jaeger shows where the time of a request goes: the coordinator's
+//! RPC, each endorser call and the endorser's own handling of it, and each store operation.
+//!
+//! Spans may set two fields that are not kept as attributes: `otel.kind` (server, client, or
+//! internal) and `otel.name`, which replaces the span's name in the export.
+use crate::errors::LoggingError;
+use hyper::{client::HttpConnector, Body, Client, Method, Request, Uri};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// The gRPC metadata key, and HTTP header, that carries the trace context of a request
+pub const TRACEPARENT_METADATA: &str = "traceparent";
+
+/// The span field that holds the trace context that a request arrived with
+pub const TRACEPARENT_FIELD: &str = "traceparent";
+
+/// The span field that holds the kind of a span
+pub const KIND_FIELD: &str = "otel.kind";
+
+/// The span field that holds the name under which a span is exported
+pub const NAME_FIELD: &str = "otel.name";
+
+// the most spans sent in one export, and the most that wait to be sent; more are dropped
+const MAX_EXPORT_BATCH: usize = 512;
+const MAX_QUEUED_SPANS: usize = 8192;
+
+// how long spans wait to be batched before they are sent
+const EXPORT_INTERVAL: Duration = Duration::from_secs(2);
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The trace that a span belongs to, and the span's ID in it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+  pub trace_id: [u8; 16],
+  pub span_id: [u8; 8],
+  /// whether the trace is recorded, which callers decide for the services they call
+  pub sampled: bool,
+}
+
+impl TraceContext {
+  /// Starts a new trace
+  pub fn root() -> Self {
+    TraceContext {
+      trace_id: random_id(),
+      span_id: random_id(),
+      sampled: true,
+    }
+  }
+
+  /// Returns the context of a span whose parent has this context
+  pub fn child(&self) -> Self {
+    TraceContext {
+      span_id: random_id(),
+      ..*self
+    }
+  }
+
+  /// Parses a `traceparent` header, `00-<trace ID>-<parent span ID>-<flags>` in lowercase hex
+  pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+    let parts = traceparent.split('-').collect::<Vec<&str>>();
+    if parts.len() < 4 || parts[0].len() != 2 || parts[0] == "ff" {
+      return None;
+    }
+    // later versions may append fields, which this version ignores
+    if parts[0] == "00" && parts.len() != 4 {
+      return None;
+    }
+    let lowercase_hex = |s: &str| s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    if !parts[..4].iter().all(|part| lowercase_hex(part)) {
+      return None;
+    }
+    let mut trace_id = [0u8; 16];
+    let mut span_id = [0u8; 8];
+    let mut flags = [0u8; 1];
+    hex::decode_to_slice(parts[1], &mut trace_id).ok()?;
+    hex::decode_to_slice(parts[2], &mut span_id).ok()?;
+    hex::decode_to_slice(parts[3], &mut flags).ok()?;
+    // IDs of all zeros are invalid
+    if trace_id == [0; 16] || span_id == [0; 8] {
+      return None;
+    }
+    Some(TraceContext {
+      trace_id,
+      span_id,
+      sampled: flags[0] & 1 == 1,
+    })
+  }
+
+  /// Returns the `traceparent` header that makes this span the parent of the callee's spans
+  pub fn to_traceparent(&self) -> String {
+    format!(
+      "00-{}-{}-{:02x}",
+      hex::encode(self.trace_id),
+      hex::encode(self.span_id),
+      self.sampled as u8
+    )
+  }
+}
+
+// a random ID that is not all zeros
+fn random_id<const N: usize>() -> [u8; N] {
+  let mut id = [0u8; N];
+  while id == [0; N] {
+    rand::Rng::fill(&mut rand::thread_rng(), &mut id[..]);
+  }
+  id
+}
+
+/// Nanoseconds since the Unix epoch
+pub fn unix_nanos() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_nanos() as u64
+}
+
+/// The kinds of spans, as OTLP numbers them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+  Internal = 1,
+  Server = 2,
+  Client = 3,
+}
+
+impl SpanKind {
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "internal" => Some(SpanKind::Internal),
+      "server" => Some(SpanKind::Server),
+      "client" => Some(SpanKind::Client),
+      _ => None,
+    }
+  }
+}
+
+/// A span that ended, ready to be exported
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FinishedSpan {
+  pub name: String,
+  pub kind: SpanKind,
+  pub context: TraceContext,
+  /// the span ID of the parent, which may be a span of the caller
+  pub parent_span_id: Option<[u8; 8]>,
+  pub start_unix_nanos: u64,
+  pub end_unix_nanos: u64,
+  pub attributes: Vec<(&'static str, String)>,
+}
+
+/// Returns the body of an OTLP/HTTP JSON export request with `spans` of the service
+pub fn otlp_request_body(service_name: &str, spans: &[FinishedSpan]) -> serde_json::Value {
+  let attribute = |key: &str, value: &str| {
+    serde_json::json!({
+      "key": key,
+      "value": { "stringValue": value },
+    })
+  };
+  let spans = spans
+    .iter()
+    .map(|span| {
+      let mut json = serde_json::json!({
+        "traceId": hex::encode(span.context.trace_id),
+        "spanId": hex::encode(span.context.span_id),
+        "name": span.name,
+        "kind": span.kind as u8,
+        "startTimeUnixNano": span.start_unix_nanos.to_string(),
+        "endTimeUnixNano": span.end_unix_nanos.to_string(),
+        "attributes": span
+          .attributes
+          .iter()
+          .map(|(key, value)| attribute(key, value))
+          .collect::<Vec<serde_json::Value>>(),
+      });
+      if let Some(parent_span_id) = span.parent_span_id {
+        json["parentSpanId"] = hex::encode(parent_span_id).into();
+      }
+      json
+    })
+    .collect::<Vec<serde_json::Value>>();
+  serde_json::json!({
+    "resourceSpans": [{
+      "resource": { "attributes": [attribute("service.name", service_name)] },
+      "scopeSpans": [{
+        "scope": { "name": "nimble" },
+        "spans": spans,
+      }],
+    }],
+  })
+}
+
+/// Sends finished spans to an OTLP/HTTP endpoint from a background task, which batches them. A
+/// span that finds the queue full is dropped rather than slowing the service down.
+#[derive(Clone, Debug)]
+pub struct OtlpExporter {
+  spans: mpsc::Sender<FinishedSpan>,
+}
+
+impl OtlpExporter {
+  /// Starts exporting to the collector at `endpoint`, e.g., `http://localhost:4318`, as
+  /// `service_name`; the spans go to the `/v1/traces` path of the endpoint. It must be called
+  /// from within a Tokio runtime.
+  pub fn start(endpoint: &str, service_name: &str) -> Result<Self, LoggingError> {
+    let base = endpoint.trim_end_matches('/');
+    if !base.starts_with("http://") {
+      return Err(LoggingError::InvalidOtlpEndpoint);
+    }
+    let uri = format!("{}/v1/traces", base)
+      .parse::<Uri>()
+      .map_err(|_e| LoggingError::InvalidOtlpEndpoint)?;
+    let (sender, receiver) = mpsc::channel(MAX_QUEUED_SPANS);
+    tokio::spawn(run_exporter(uri, service_name.to_string(), receiver));
+    Ok(OtlpExporter { spans: sender })
+  }
+
+  // an exporter whose spans are received from the returned channel instead of sent
+  #[cfg(test)]
+  pub(crate) fn channel() -> (Self, mpsc::Receiver<FinishedSpan>) {
+    let (sender, receiver) = mpsc::channel(MAX_QUEUED_SPANS);
+    (OtlpExporter { spans: sender }, receiver)
+  }
+
+  pub fn export(&self, span: FinishedSpan) {
+    let _ = self.spans.try_send(span);
+  }
+}
+
+async fn run_exporter(uri: Uri, service_name: String, mut receiver: mpsc::Receiver<FinishedSpan>) {
+  let client: Client<HttpConnector, Body> = Client::new();
+  let mut failing = false;
+  while let Some(span) = receiver.recv().await {
+    let mut batch = vec![span];
+    let deadline = tokio::time::Instant::now() + EXPORT_INTERVAL;
+    while batch.len() < MAX_EXPORT_BATCH {
+      match tokio::time::timeout_at(deadline, receiver.recv()).await {
+        Ok(Some(span)) => batch.push(span),
+        Ok(None) | Err(_) => break,
+      }
+    }
+
+    let body = otlp_request_body(&service_name, &batch).to_string();
+    let request = Request::builder()
+      .method(Method::POST)
+      .uri(uri.clone())
+      .header("content-type", "application/json")
+      .body(Body::from(body));
+    let res = match request {
+      Ok(request) => tokio::time::timeout(EXPORT_TIMEOUT, client.request(request))
+        .await
+        .map_err(|_e| "timed out".to_string())
+        .and_then(|res| res.map_err(|e| e.to_string()))
+        .and_then(|resp| {
+          if resp.status().is_success() {
+            Ok(())
+          } else {
+            Err(format!("status {}", resp.status()))
+          }
+        }),
+      Err(e) => Err(e.to_string()),
+    };
+    // a collector that is down is reported once, not for every batch
+    match res {
+      Ok(()) => failing = false,
+      Err(error) if !failing => {
+        failing = true;
+        tracing::warn!(%uri, %error, spans = batch.len(), "failed to export spans");
+      },
+      Err(_) => {},
+    }
+  }
+}
+
+/// Returns the span that a gRPC server runs a request in, which continues the caller's trace if
+/// the request carries a `traceparent` header; for `Server::trace_fn`
+pub fn grpc_request_span(request: &tonic::codegen::http::Request<()>) -> tracing::Span {
+  let traceparent = request
+    .headers()
+    .get(TRACEPARENT_METADATA)
+    .and_then(|value| value.to_str().ok())
+    .unwrap_or_default();
+  tracing::info_span!(
+    "grpc",
+    otel.kind = "server",
+    path = %request.uri().path(),
+    traceparent = %traceparent
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_trace_context() {
+    let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    let context = TraceContext::from_traceparent(traceparent).unwrap();
+    assert_eq!(
+      hex::encode(context.trace_id),
+      "4bf92f3577b34da6a3ce929d0e0e4736"
+    );
+    assert!(context.sampled);
+    assert_eq!(context.to_traceparent(), traceparent);
+
+    let child = context.child();
+    assert_eq!(child.trace_id, context.trace_id);
+    assert_ne!(child.span_id, context.span_id);
+    assert_ne!(TraceContext::root().trace_id, context.trace_id);
+
+    // later versions may carry more fields
+    assert!(TraceContext::from_traceparent(
+      "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra"
+    )
+    .is_some_and(|context| !context.sampled));
+    for invalid in [
+      "",
+      "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+      "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+      "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+      "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+      "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+      "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+      "00-4bf92f3577b34da6-00f067aa0ba902b7-01",
+    ] {
+      assert!(
+        TraceContext::from_traceparent(invalid).is_none(),
+        "{}",
+        invalid
+      );
+    }
+  }
+
+  #[test]
+  fn test_otlp_request_body() {
+    let context =
+      TraceContext::from_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+        .unwrap();
+    let span = FinishedSpan {
+      name: "Append".to_string(),
+      kind: SpanKind::Server,
+      context,
+      parent_span_id: Some([1; 8]),
+      start_unix_nanos: 1_000,
+      end_unix_nanos: 2_000,
+      attributes: vec![("handle", "abab".to_string())],
+    };
+    let body = otlp_request_body("coordinator", &[span]);
+    let resource = &body["resourceSpans"][0];
+    assert_eq!(
+      resource["resource"]["attributes"][0]["value"]["stringValue"],
+      "coordinator"
+    );
+    let span = &resource["scopeSpans"][0]["spans"][0];
+    assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(span["spanId"], "00f067aa0ba902b7");
+    assert_eq!(span["parentSpanId"], "0101010101010101");
+    assert_eq!(span["kind"], 2);
+    assert_eq!(span["startTimeUnixNano"], "1000");
+    assert_eq!(span["attributes"][0]["key"], "handle");
+
+    assert!(OtlpExporter::start("https://collector:4318", "coordinator").is_err());
+  }
+}
@@ -0,0 +1,80 @@
+use super::Handle;
+use crate::{content::ContentStore, errors::StorageError};
+use async_trait::async_trait;
+use hex;
+use std::{
+  collections::HashMap,
+  fs,
+  io::ErrorKind,
+  path::{Path, PathBuf},
+};
+
+/// A content store that keeps each piece of content in a file of a directory, named by the hex
+/// encoding of the content's hash
+#[derive(Debug)]
+pub struct FileContentStore {
+  dir_path: PathBuf,
+}
+
+impl FileContentStore {
+  pub fn new(args: &HashMap<String, String>) -> Result<Self, StorageError> {
+    if !args.contains_key("NIMBLE_CONTENT_DIR") {
+      return Err(StorageError::MissingArguments);
+    }
+    let dir_path = Path::new(&args["NIMBLE_CONTENT_DIR"]).to_path_buf();
+
+    // Try to create directory. If it exists that's fine.
+    if let Err(e) = fs::create_dir_all(&dir_path) {
+      eprintln!("Unable to create path {:?}, error: {:?}", &dir_path, e);
+      return Err(StorageError::InvalidDBName);
+    }
+    Ok(FileContentStore { dir_path })
+  }
+
+  fn content_path(&self, handle: &Handle) -> PathBuf {
+    self.dir_path.join(hex::encode(handle.to_bytes()))
+  }
+}
+
+#[async_trait]
+impl ContentStore for FileContentStore {
+  async fn put(&self, data: &[u8]) -> Result<Handle, StorageError> {
+    let handle = Handle::digest(data);
+    let path = self.content_path(&handle);
+    if path.exists() {
+      // the file holds the same data, since it is named by the data's hash
+      return Ok(handle);
+    }
+
+    // write to a temporary file first so that a crash never leaves a partial file under the name
+    let tmp_path = path.with_extension(format!("{}.tmp", rand::random::<u64>()));
+    let res = fs::write(&tmp_path, data).and_then(|()| fs::rename(&tmp_path, &path));
+    if let Err(e) = res {
+      eprintln!("Failed to write the content {:?} ({:?})", path, e);
+      let _ = fs::remove_file(&tmp_path);
+      return Err(StorageError::UnhandledError);
+    }
+    Ok(handle)
+  }
+
+  async fn get(&self, handle: &Handle) -> Result<Vec<u8>, StorageError> {
+    match fs::read(self.content_path(handle)) {
+      Ok(data) => Ok(data),
+      Err(e) if e.kind() == ErrorKind::NotFound => Err(StorageError::KeyDoesNotExist),
+      Err(e) => {
+        eprintln!("Failed to read the content {:?} ({:?})", handle, e);
+        Err(StorageError::UnhandledError)
+      },
+    }
+  }
+
+  async fn reset_store(&self) -> Result<(), StorageError> {
+    match fs::remove_dir_all(&self.dir_path).and_then(|()| fs::create_dir_all(&self.dir_path)) {
+      Ok(()) => Ok(()),
+      Err(e) => {
+        eprintln!("Failed to reset the content store {:?}", e);
+        Err(StorageError::UnhandledError)
+      },
+    }
+  }
+}
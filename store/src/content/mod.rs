@@ -1,7 +1,9 @@
 use crate::errors::StorageError;
 use async_trait::async_trait;
 use ledger::Handle;
+use std::collections::HashMap;
 
+pub mod filestore;
 pub mod in_memory;
 
 #[async_trait]
@@ -10,3 +12,70 @@ pub trait ContentStore {
   async fn get(&self, handle: &Handle) -> Result<Vec<u8>, StorageError>;
   async fn reset_store(&self) -> Result<(), StorageError>; // only used for testing
 }
+
+pub type BoxedContentStore = Box<dyn ContentStore + Send + Sync>;
+
+/// The backend names accepted by `open_content_store`
+pub const CONTENT_STORE_TYPES: [&str; 2] = ["memory", "filestore"];
+
+/// Opens the content store backend named `store_type`, passing it the backend-specific `args`
+pub fn open_content_store(
+  store_type: &str,
+  args: &HashMap<String, String>,
+) -> Result<BoxedContentStore, StorageError> {
+  let store: BoxedContentStore = match store_type {
+    "memory" => Box::new(in_memory::InMemoryContentStore::new()),
+    "filestore" => Box::new(filestore::FileContentStore::new(args)?),
+    _ => return Err(StorageError::UnknownStoreType),
+  };
+  Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    content::{open_content_store, ContentStore},
+    errors::StorageError,
+  };
+  use ledger::NimbleDigest;
+  use std::collections::HashMap;
+
+  async fn check_content_store(store: &dyn ContentStore) {
+    let data = vec![7u8; 1000];
+    let handle = store.put(&data).await.unwrap();
+    assert_eq!(handle, NimbleDigest::digest(&data));
+    assert_eq!(store.get(&handle).await.unwrap(), data);
+
+    // putting the same content again is idempotent
+    assert_eq!(store.put(&data).await.unwrap(), handle);
+    let res = store.get(&NimbleDigest::digest(b"missing")).await;
+    assert_eq!(res.unwrap_err(), StorageError::KeyDoesNotExist);
+  }
+
+  #[tokio::test]
+  async fn check_in_memory_content_store() {
+    let store = open_content_store("memory", &HashMap::new()).unwrap();
+    check_content_store(&*store).await;
+    assert!(open_content_store("table", &HashMap::new()).is_err());
+  }
+
+  #[tokio::test]
+  async fn check_file_content_store() {
+    if std::env::var_os("NIMBLE_CONTENT_DIR").is_none() {
+      // The right env variables are not available so let's skip tests
+      return;
+    }
+
+    let mut args = HashMap::<String, String>::new();
+    args.insert(
+      String::from("NIMBLE_CONTENT_DIR"),
+      std::env::var_os("NIMBLE_CONTENT_DIR")
+        .unwrap()
+        .into_string()
+        .unwrap(),
+    );
+    let store = open_content_store("filestore", &args).unwrap();
+    store.reset_store().await.unwrap();
+    check_content_store(&*store).await;
+  }
+}
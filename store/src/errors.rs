@@ -50,6 +50,29 @@ pub enum StorageError {
   UnhandledError,
   /// return if the name for the nimble database is not acceptable for the store
   InvalidDBName,
+  /// return if the requested read consistency level is not supported by the store
+  InvalidReadConsistency,
+  /// return if the requested receipt retention policy is not supported by the store
+  InvalidReceiptRetention,
+  /// return if the requested receipt compaction policy is not supported by the store
+  InvalidReceiptCompaction,
+  /// return if the requested store type is not a known backend
+  UnknownStoreType,
+  /// return if a replicated entry neither extends nor matches the standby's copy of a ledger
+  ReplicationConflict,
+  /// return if the store cannot hold leases, so coordinators cannot elect a leader with it
+  LeasesNotSupported,
+  /// return if the store cannot keep a table of recently seen append requests, so appends with
+  /// client request IDs cannot be deduplicated
+  RequestIdsNotSupported,
+  /// return if the store cannot keep an index of ledger labels
+  LabelsNotSupported,
+  /// return if the store cannot list the ledgers of a namespace
+  NamespacesNotSupported,
+  /// return if the store cannot attach nonces to the entries of a ledger
+  NoncesNotSupported,
+  /// return if the snapshot file of an in-memory store cannot be read or written
+  SnapshotFileFailed,
 }
 
 use std::fmt::Display;
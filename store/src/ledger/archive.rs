@@ -0,0 +1,433 @@
+//! Archives of the ledgers of a store, for moving them to a store of another type. An archive is
+//! JSON lines: a header, then every entry of the view ledger and of each ledger in order, with
+//! its block, nonces and receipts (which carry the entry's metablock) in base64url. Importing an
+//! archive loads it into an in-memory store and verifies it there, the view ledger against the
+//! attestation and every ledger's receipts and hash chain against the view ledger, before it is
+//! copied into the target store, so a damaged archive leaves the target as it was.
+use crate::ledger::{
+  in_memory::InMemoryLedgerStore,
+  verify::{replay_view_ledger, verify_ledger},
+  LedgerEntry, LedgerStore,
+};
+use ledger::{Block, CustomSerde, Handle, NimbleDigest, Nonces, Receipts};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+
+const ARCHIVE_FORMAT: &str = "nimble-archive";
+const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveHeader {
+  format: String,
+  version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedEntry {
+  ledger: Option<String>, // the base64url handle of the ledger, or None for the view ledger
+  index: usize,
+  block: String,
+  nonces: String,
+  receipts: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  timestamp: Option<u64>, // untrusted coordinator time at which the entry was stored
+}
+
+impl ArchivedEntry {
+  fn new(handle_opt: Option<&Handle>, index: usize, entry: &LedgerEntry) -> Self {
+    ArchivedEntry {
+      ledger: handle_opt.map(|handle| base64_url::encode(&handle.to_bytes())),
+      index,
+      block: base64_url::encode(&entry.get_block().to_bytes()),
+      nonces: base64_url::encode(&entry.get_nonces().to_bytes()),
+      receipts: base64_url::encode(&entry.get_receipts().to_bytes()),
+      timestamp: entry.get_timestamp(),
+    }
+  }
+
+  fn decode(&self) -> Result<(Option<Handle>, Block, Nonces, Receipts), String> {
+    let field = |name: &str, value: &str| {
+      base64_url::decode(value).map_err(|_e| format!("entry {}: invalid {}", self.index, name))
+    };
+    let handle_opt = match &self.ledger {
+      Some(ledger) => Some(
+        NimbleDigest::from_bytes(&field("ledger", ledger)?)
+          .map_err(|_e| format!("entry {}: invalid ledger", self.index))?,
+      ),
+      None => None,
+    };
+    let block = Block::from_bytes(&field("block", &self.block)?)
+      .map_err(|_e| format!("entry {}: invalid block", self.index))?;
+    let nonces = Nonces::from_bytes(&field("nonces", &self.nonces)?)
+      .map_err(|_e| format!("entry {}: invalid nonces", self.index))?;
+    let receipts = Receipts::from_bytes(&field("receipts", &self.receipts)?)
+      .map_err(|_e| format!("entry {}: invalid receipts", self.index))?;
+    Ok((handle_opt, block, nonces, receipts))
+  }
+}
+
+/// What was archived or copied
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ArchiveReport {
+  /// the number of views of the view ledger
+  pub views: usize,
+  /// the number of ledgers
+  pub ledgers: usize,
+  /// the number of entries of the ledgers, their genesis entries included
+  pub entries: usize,
+}
+
+/// Writes the view ledger and every ledger of `store` to `out` as an archive
+pub async fn export_store(
+  store: &(dyn LedgerStore + Send + Sync),
+  out: &mut dyn Write,
+) -> Result<ArchiveReport, String> {
+  let mut write = |line: String| writeln!(out, "{}", line).map_err(|e| format!("write ({})", e));
+  let header = ArchiveHeader {
+    format: ARCHIVE_FORMAT.to_string(),
+    version: ARCHIVE_VERSION,
+  };
+  write(serde_json::to_string(&header).unwrap())?;
+
+  let mut report = ArchiveReport::default();
+  let (_tail, views) = store
+    .read_view_ledger_tail()
+    .await
+    .map_err(|e| format!("read the view ledger tail ({:?})", e))?;
+  // the view ledger starts with a placeholder entry at index 0, which every store creates
+  for index in 1..=views {
+    let entry = store
+      .read_view_ledger_by_index(index)
+      .await
+      .map_err(|e| format!("read view {} ({:?})", index, e))?;
+    write(serde_json::to_string(&ArchivedEntry::new(None, index, &entry)).unwrap())?;
+  }
+  report.views = views;
+
+  let handles = store
+    .list_ledgers()
+    .await
+    .map_err(|e| format!("list the ledgers ({:?})", e))?;
+  for handle in handles {
+    let (_tail, height) = store
+      .read_ledger_tail(&handle)
+      .await
+      .map_err(|e| format!("read the tail of a ledger ({:?})", e))?;
+    for index in 0..=height {
+      let entry = store
+        .read_ledger_by_index(&handle, index)
+        .await
+        .map_err(|e| format!("read entry {} of a ledger ({:?})", index, e))?;
+      write(serde_json::to_string(&ArchivedEntry::new(Some(&handle), index, &entry)).unwrap())?;
+    }
+    report.ledgers += 1;
+    report.entries += height + 1;
+  }
+  Ok(report)
+}
+
+// Stores an entry of a ledger, or of the view ledger, in `store`, after the entries before it
+async fn load_entry(
+  store: &(dyn LedgerStore + Send + Sync),
+  handle_opt: Option<&Handle>,
+  index: usize,
+  block: Block,
+  nonces: &Nonces,
+  receipts: &Receipts,
+) -> Result<(), String> {
+  let res = match handle_opt {
+    None => {
+      let res = store.append_view_ledger(&block, index).await;
+      match res {
+        Ok(_index) if receipts.is_empty() => Ok(()),
+        Ok(_index) => store.attach_view_ledger_receipts(index, receipts).await,
+        Err(error) => Err(error),
+      }
+    },
+    Some(handle) => {
+      let res = if index == 0 {
+        store.create_ledger(handle, block).await
+      } else {
+        // the nonces of an entry are attached to the tail before the entry is appended
+        let mut res = Ok(());
+        for nonce in nonces.get() {
+          if let Err(error) = store.attach_ledger_nonce(handle, nonce).await {
+            res = Err(error);
+            break;
+          }
+        }
+        match res {
+          Ok(()) => store
+            .append_ledger(handle, &block, index)
+            .await
+            .map(|_res| ()),
+          Err(error) => Err(error),
+        }
+      };
+      match res {
+        Ok(()) if receipts.is_empty() => Ok(()),
+        Ok(()) => store.attach_ledger_receipts(handle, index, receipts).await,
+        Err(error) => Err(error),
+      }
+    },
+  };
+  res.map_err(|e| format!("store entry {} ({:?})", index, e))
+}
+
+/// Reads the archive `input` into an in-memory store, checking that it holds the view ledger and
+/// every ledger in order, and returns the store and what it holds. The entries are not verified.
+pub async fn read_archive(
+  input: &mut dyn BufRead,
+) -> Result<(InMemoryLedgerStore, ArchiveReport), String> {
+  let mut lines = input.lines();
+  let header = match lines.next() {
+    Some(Ok(line)) => serde_json::from_str::<ArchiveHeader>(&line).ok(),
+    Some(Err(e)) => return Err(format!("read ({})", e)),
+    None => None,
+  };
+  match header {
+    Some(header) if header.format == ARCHIVE_FORMAT && header.version == ARCHIVE_VERSION => {},
+    _ => return Err("the input is not a Nimble archive of a supported version".to_string()),
+  }
+
+  let staging = InMemoryLedgerStore::new();
+  let mut report = ArchiveReport::default();
+  let mut current: Option<Handle> = None;
+  let mut next_index = 1; // the view ledger comes first, from index 1
+  for line in lines {
+    let line = line.map_err(|e| format!("read ({})", e))?;
+    let archived = serde_json::from_str::<ArchivedEntry>(&line)
+      .map_err(|e| format!("parse an entry ({})", e))?;
+    let (handle_opt, block, nonces, receipts) = archived.decode()?;
+    if handle_opt != current {
+      if handle_opt.is_none() {
+        return Err("the view ledger follows a ledger".to_string());
+      }
+      current = handle_opt;
+      next_index = 0;
+      report.ledgers += 1;
+    }
+    if archived.index != next_index {
+      return Err(format!(
+        "entry {} is out of order (expected {})",
+        archived.index, next_index
+      ));
+    }
+    load_entry(
+      &staging,
+      handle_opt.as_ref(),
+      archived.index,
+      block,
+      &nonces,
+      &receipts,
+    )
+    .await?;
+    match handle_opt {
+      Some(_handle) => report.entries += 1,
+      None => report.views += 1,
+    }
+    next_index += 1;
+  }
+  Ok((staging, report))
+}
+
+/// Copies the view ledger and every ledger of `source` into `target`, whose view ledger must be
+/// empty and which must not hold any of the ledgers
+pub async fn copy_store(
+  source: &(dyn LedgerStore + Send + Sync),
+  target: &(dyn LedgerStore + Send + Sync),
+) -> Result<ArchiveReport, String> {
+  let (_tail, target_views) = target
+    .read_view_ledger_tail()
+    .await
+    .map_err(|e| format!("read the target's view ledger ({:?})", e))?;
+  if target_views != 0 {
+    return Err("the target store has a view ledger already".to_string());
+  }
+
+  let mut report = ArchiveReport::default();
+  let (_tail, views) = source
+    .read_view_ledger_tail()
+    .await
+    .map_err(|e| format!("read the view ledger tail ({:?})", e))?;
+  for index in 1..=views {
+    let entry = source
+      .read_view_ledger_by_index(index)
+      .await
+      .map_err(|e| format!("read view {} ({:?})", index, e))?;
+    load_entry(
+      target,
+      None,
+      index,
+      entry.get_block().clone(),
+      entry.get_nonces(),
+      entry.get_receipts(),
+    )
+    .await?;
+  }
+  report.views = views;
+
+  let handles = source
+    .list_ledgers()
+    .await
+    .map_err(|e| format!("list the ledgers ({:?})", e))?;
+  for handle in handles {
+    let (_tail, height) = source
+      .read_ledger_tail(&handle)
+      .await
+      .map_err(|e| format!("read the tail of a ledger ({:?})", e))?;
+    for index in 0..=height {
+      let entry = source
+        .read_ledger_by_index(&handle, index)
+        .await
+        .map_err(|e| format!("read entry {} of a ledger ({:?})", index, e))?;
+      load_entry(
+        target,
+        Some(&handle),
+        index,
+        entry.get_block().clone(),
+        entry.get_nonces(),
+        entry.get_receipts(),
+      )
+      .await?;
+    }
+    report.ledgers += 1;
+    report.entries += height + 1;
+  }
+  Ok(report)
+}
+
+/// Imports the archive `input` into `target`, after verifying the view ledger against
+/// `attestations` and every ledger against the view ledger. An archive that fails to verify
+/// leaves `target` as it was.
+pub async fn import_archive(
+  input: &mut dyn BufRead,
+  target: &(dyn LedgerStore + Send + Sync),
+  attestations: &[u8],
+) -> Result<ArchiveReport, String> {
+  let (staging, _report) = read_archive(input).await?;
+  let (vs, _views) = replay_view_ledger(&staging, attestations).await?;
+  let handles = staging
+    .list_ledgers()
+    .await
+    .map_err(|e| format!("list the ledgers ({:?})", e))?;
+  for handle in handles {
+    verify_ledger(&staging, &vs, &handle)
+      .await
+      .map_err(|e| format!("{}: {}", base64_url::encode(&handle.to_bytes()), e))?;
+  }
+  copy_store(&staging, target).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    IdSig, MetaBlock, NimbleHashTrait, Nonce, Receipt,
+  };
+
+  async fn sample_store() -> (InMemoryLedgerStore, Handle) {
+    let store = InMemoryLedgerStore::new();
+    store
+      .append_view_ledger(&Block::new(b"view 1"), 1)
+      .await
+      .unwrap();
+
+    let genesis = Block::new(b"genesis");
+    let handle = genesis.hash();
+    store.create_ledger(&handle, genesis.clone()).await.unwrap();
+    store
+      .attach_ledger_nonce(&handle, &Nonce::new(&[7; 16]).unwrap())
+      .await
+      .unwrap();
+    store
+      .append_ledger(&handle, &Block::new(b"entry 1"), 1)
+      .await
+      .unwrap();
+
+    let metablock = MetaBlock::genesis(&genesis.hash());
+    let sk = PrivateKey::new();
+    let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+    let mut receipts = Receipts::new();
+    receipts.add(&Receipt::new(
+      NimbleDigest::digest(b"view 1"),
+      metablock,
+      IdSig::new(sk.get_public_key().unwrap(), sig),
+    ));
+    store
+      .attach_ledger_receipts(&handle, 0, &receipts)
+      .await
+      .unwrap();
+    (store, handle)
+  }
+
+  #[tokio::test]
+  async fn test_archive_round_trip() {
+    let (store, handle) = sample_store().await;
+    let mut archive = Vec::new();
+    let report = export_store(&store, &mut archive).await.unwrap();
+    let expected = ArchiveReport {
+      views: 1,
+      ledgers: 1,
+      entries: 2,
+    };
+    assert_eq!(report, expected);
+
+    let (staging, report) = read_archive(&mut archive.as_slice()).await.unwrap();
+    assert_eq!(report, expected);
+    for index in 0..=1 {
+      let original = store.read_ledger_by_index(&handle, index).await.unwrap();
+      let read = staging.read_ledger_by_index(&handle, index).await.unwrap();
+      assert_eq!(read.get_block().to_bytes(), original.get_block().to_bytes());
+      assert_eq!(
+        read.get_nonces().to_bytes(),
+        original.get_nonces().to_bytes()
+      );
+      assert_eq!(
+        read.get_receipts().to_bytes(),
+        original.get_receipts().to_bytes()
+      );
+    }
+    let view = staging.read_view_ledger_by_index(1).await.unwrap();
+    assert_eq!(view.get_block().to_bytes(), b"view 1");
+
+    // a copy goes only to a store without a view ledger
+    let target = InMemoryLedgerStore::new();
+    assert_eq!(copy_store(&staging, &target).await.unwrap(), expected);
+    assert!(copy_store(&staging, &target).await.is_err());
+
+    // an archive whose view ledger is not endorsed does not verify, and is not imported
+    let target = InMemoryLedgerStore::new();
+    assert!(import_archive(&mut archive.as_slice(), &target, &[])
+      .await
+      .is_err());
+    assert_eq!(target.read_view_ledger_tail().await.unwrap().1, 0);
+    assert!(target.list_ledgers().await.unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_malformed_archives() {
+    let (store, _handle) = sample_store().await;
+    let mut archive = Vec::new();
+    export_store(&store, &mut archive).await.unwrap();
+    let text = String::from_utf8(archive).unwrap();
+    let lines = text.lines().collect::<Vec<&str>>();
+
+    let malformed = [
+      // no header
+      lines[1..].join("\n"),
+      // a header of another version
+      text.replacen("\"version\":1", "\"version\":2", 1),
+      // a missing entry of a ledger
+      [lines[0], lines[1], lines[3]].join("\n"),
+      // a view ledger after a ledger
+      [lines[0], lines[2], lines[1]].join("\n"),
+      // an entry that is not base64url
+      text.replacen("\"block\":\"", "\"block\":\"*", 1),
+    ];
+    for archive in malformed {
+      assert!(read_archive(&mut archive.as_bytes()).await.is_err());
+    }
+  }
+}
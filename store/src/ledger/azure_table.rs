@@ -1,6 +1,6 @@
 use crate::{
   errors::{LedgerStoreError, StorageError},
-  ledger::{LedgerEntry, LedgerStore},
+  ledger::{current_timestamp, LedgerEntry, LedgerStore, ReceiptRetention},
 };
 use async_trait::async_trait;
 use azure_data_tables::{clients::TableClient, prelude::*};
@@ -90,6 +90,14 @@ fn parse_error_status(code: StatusCode) -> LedgerStoreError {
   }
 }
 
+// describes why a connection string was rejected without repeating any part of it
+fn connection_string_error(error: &azure_storage::Error) -> &'static str {
+  match error {
+    azure_storage::Error::ConnectionStringError(_) => "malformed or incomplete",
+    _ => "unsupported endpoint or credentials",
+  }
+}
+
 fn string_decode(s: &str) -> Result<Vec<u8>, LedgerStoreError> {
   match base64_url::decode(s) {
     Ok(v) => Ok(v),
@@ -128,6 +136,9 @@ struct DBEntry {
   pub block: String,
   pub receipts: String,
   pub nonces: String,
+  // untrusted wall-clock time at which the coordinator stored the entry (ms since the UNIX epoch)
+  #[serde(default)]
+  pub timestamp: i64,
 }
 
 // This is a projection so you only modify the receipt, not the rest
@@ -150,22 +161,34 @@ struct DBEntryNonceProjection {
   pub nonces: String,
 }
 
+// This is a projection so listing ledgers only fetches the keys of their tails
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBEntryKeyProjection {
+  #[serde(rename = "PartitionKey")]
+  pub handle: String,
+}
+
 #[derive(Debug)]
 pub struct TableLedgerStore {
   client: Arc<TableClient>,
   view_handle: Handle,
   cache: CacheMap,
+  receipt_retention: ReceiptRetention,
 }
 
 impl TableLedgerStore {
+  /// Opens the table named by `NIMBLE_DB` (default "nimbletablestore"), creating it if needed.
+  /// The storage account is given either by `STORAGE_CONNECTION_STRING`, as shown in the Azure
+  /// portal, or by `STORAGE_ACCOUNT` and `STORAGE_MASTER_KEY`.
   pub async fn new(args: &HashMap<String, String>) -> Result<Self, LedgerStoreError> {
-    if !args.contains_key("STORAGE_ACCOUNT") || !args.contains_key("STORAGE_MASTER_KEY") {
+    let has_access_key =
+      args.contains_key("STORAGE_ACCOUNT") && args.contains_key("STORAGE_MASTER_KEY");
+    if !args.contains_key("STORAGE_CONNECTION_STRING") && !has_access_key {
       return Err(LedgerStoreError::LedgerError(
         StorageError::MissingArguments,
       ));
     }
-    let account = args["STORAGE_ACCOUNT"].clone();
-    let master_key = args["STORAGE_MASTER_KEY"].clone();
+    let receipt_retention = ReceiptRetention::from_args(args)?;
 
     // Below is the desired name of the container that will hold the blobs
     // (it can be anything initially, but afterwards, it needs to be the same
@@ -176,8 +199,24 @@ impl TableLedgerStore {
     }
 
     let http_client = azure_core::new_http_client();
-    let storage_client =
-      StorageAccountClient::new_access_key(http_client.clone(), &account, &master_key);
+    let storage_client = if let Some(connection_string) = args.get("STORAGE_CONNECTION_STRING") {
+      let res = StorageAccountClient::new_connection_string(http_client.clone(), connection_string);
+      if let Err(e) = res {
+        // the error may quote the connection string, which holds the account key
+        eprintln!(
+          "Invalid storage connection string: {}",
+          connection_string_error(&e)
+        );
+        return Err(LedgerStoreError::LedgerError(StorageError::InvalidDBUri));
+      }
+      res.unwrap()
+    } else {
+      StorageAccountClient::new_access_key(
+        http_client.clone(),
+        &args["STORAGE_ACCOUNT"],
+        &args["STORAGE_MASTER_KEY"],
+      )
+    };
     let table_service = match storage_client.as_storage_client().as_table_service_client() {
       Ok(v) => v,
       Err(e) => {
@@ -203,6 +242,7 @@ impl TableLedgerStore {
       client: table_client,
       view_handle,
       cache,
+      receipt_retention,
     };
 
     // Try to create table. If it exists that's fine.
@@ -237,6 +277,7 @@ impl TableLedgerStore {
               block: base64_url::encode(&Block::new(&[0; 0]).to_bytes()),
               receipts: base64_url::encode(&Receipts::new().to_bytes()),
               nonces: base64_url::encode(&Nonces::new().to_bytes()),
+              timestamp: checked_conversion!(current_timestamp(), i64),
             };
 
             azure_op(
@@ -407,9 +448,18 @@ async fn attach_ledger_receipts_internal(
   idx: usize,
   receipt: &Receipts,
   index: &str,
+  receipt_retention: ReceiptRetention,
 ) -> Result<(), LedgerStoreError> {
   loop {
-    let res = attach_ledger_receipts_op(handle_string, idx, receipt, ledger.clone(), index).await;
+    let res = attach_ledger_receipts_op(
+      handle_string,
+      idx,
+      receipt,
+      ledger.clone(),
+      index,
+      receipt_retention,
+    )
+    .await;
 
     match res {
       Ok(v) => {
@@ -451,12 +501,8 @@ async fn find_db_entry(
     let e = parse_error_status(get_error_status!(err));
 
     match e {
-      LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist) => {
-        if row != TAIL {
-          return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
-        } else {
-          return Err(e);
-        }
+      LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist) if row != TAIL => {
+        return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
       },
       _ => {
         return Err(e);
@@ -525,6 +571,7 @@ async fn append_ledger_internal(
     block: base64_url::encode(&block.to_bytes()),
     receipts: base64_url::encode(&Receipts::new().to_bytes()),
     nonces: base64_url::encode(&Nonces::new().to_bytes()), // clear out the nonces in tail
+    timestamp: checked_conversion!(current_timestamp(), i64),
   };
 
   let indexed_entry = DBEntry {
@@ -534,6 +581,7 @@ async fn append_ledger_internal(
     block: base64_url::encode(&block.to_bytes()),
     receipts: base64_url::encode(&Receipts::new().to_bytes()),
     nonces: base64_url::encode(&cache_entry.get_nonces().to_bytes()),
+    timestamp: checked_conversion!(current_timestamp(), i64),
   };
 
   // 4. Try to insert the new entry into the ledger and set the tail
@@ -604,6 +652,7 @@ async fn attach_ledger_receipts_op(
   receipts: &Receipts,
   ledger: Arc<TableClient>,
   index: &str,
+  receipt_retention: ReceiptRetention,
 ) -> Result<(), LedgerStoreError> {
   // 1. Fetch the receipt at this index
   let (entry, etag) = find_db_entry(ledger.clone(), handle, index).await?;
@@ -629,6 +678,7 @@ async fn attach_ledger_receipts_op(
   };
 
   fetched_receipts.merge_receipts(receipts);
+  receipt_retention.apply(&mut fetched_receipts);
 
   // 3. Update the row with the updated receipt
   let merge_entry = DBEntryReceiptProjection {
@@ -663,8 +713,8 @@ async fn read_ledger_internal(
   req_idx: Option<usize>,
   ledger: Arc<TableClient>,
 ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
-  let actual_idx = if req_idx.is_some() {
-    req_idx.unwrap()
+  let actual_idx = if let Some(idx) = req_idx {
+    idx
   } else {
     let (entry, _etag) = find_db_entry(ledger.clone(), handle, TAIL).await?;
     entry.height as usize
@@ -697,10 +747,12 @@ async fn read_ledger_internal(
 
   let nonce_list = decode_nonces_string(&entry.nonces)?;
 
-  Ok((
-    LedgerEntry::new(ret_block, ret_receipts, Some(nonce_list)),
-    checked_conversion!(entry.height, usize),
-  ))
+  let mut ledger_entry = LedgerEntry::new(ret_block, ret_receipts, Some(nonce_list));
+  if entry.timestamp != 0 {
+    ledger_entry.set_timestamp(checked_conversion!(entry.timestamp, u64));
+  }
+
+  Ok((ledger_entry, checked_conversion!(entry.height, usize)))
 }
 
 async fn get_cached_entry(
@@ -814,6 +866,7 @@ impl LedgerStore for TableLedgerStore {
       block: base64_url::encode(&genesis_block.to_bytes()),
       receipts: base64_url::encode(&Receipts::new().to_bytes()),
       nonces,
+      timestamp: checked_conversion!(current_timestamp(), i64),
     };
 
     azure_op(
@@ -874,8 +927,43 @@ impl LedgerStore for TableLedgerStore {
     let handle_string = base64_url::encode(&handle.to_bytes());
     let index = idx.to_string();
 
-    attach_ledger_receipts_internal(ledger, &handle_string, &self.cache, idx, receipts, &index)
-      .await
+    let receipt_retention = if *handle == self.view_handle {
+      ReceiptRetention::All
+    } else {
+      self.receipt_retention
+    };
+
+    attach_ledger_receipts_internal(
+      ledger,
+      &handle_string,
+      &self.cache,
+      idx,
+      receipts,
+      &index,
+      receipt_retention,
+    )
+    .await
+  }
+
+  async fn drop_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let ledger = self.client.clone();
+    let handle_string = base64_url::encode(&handle.to_bytes());
+    let index = idx.to_string();
+
+    attach_ledger_receipts_internal(
+      ledger,
+      &handle_string,
+      &self.cache,
+      idx,
+      &Receipts::new(),
+      &index,
+      ReceiptRetention::None,
+    )
+    .await
   }
 
   async fn attach_ledger_nonce(
@@ -959,6 +1047,42 @@ impl LedgerStore for TableLedgerStore {
     Ok(height)
   }
 
+  async fn list_ledgers(&self) -> Result<Vec<Handle>, LedgerStoreError> {
+    // every ledger has exactly one TAIL row, in the partition named by its handle
+    let filter = format!("RowKey eq '{}'", TAIL);
+    let mut handles = Vec::new();
+    let mut continuation = None;
+    loop {
+      let mut query = self
+        .client
+        .query()
+        .filter(Filter::new(filter.as_str()))
+        .select(Select::new("PartitionKey"));
+      if let Some(c) = continuation {
+        query = query.continuation_next_partition_and_row_key(c);
+      }
+      let res = query.execute::<DBEntryKeyProjection>().await;
+      if let Err(err) = res {
+        return Err(parse_error_status(get_error_status!(err)));
+      }
+      let res = res.unwrap();
+
+      for entity in &res.entities {
+        let handle = NimbleDigest::from_bytes(&string_decode(&entity.handle)?);
+        match handle {
+          Ok(handle) if handle != self.view_handle => handles.push(handle),
+          _ => {},
+        }
+      }
+      continuation = res.continuation_next_partition_and_row_key;
+      if continuation.is_none() {
+        break;
+      }
+    }
+    handles.sort();
+    Ok(handles)
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     let ledger = self.client.clone();
     ledger
@@ -1,6 +1,6 @@
 use crate::{
   errors::{LedgerStoreError, StorageError},
-  ledger::{LedgerEntry, LedgerStore},
+  ledger::{current_timestamp, LedgerEntry, LedgerStore, ReceiptRetention},
 };
 use async_trait::async_trait;
 use bincode;
@@ -39,6 +39,9 @@ type FileMap = Arc<RwLock<HashMap<Handle, FileLock>>>;
 struct StoreEntry {
   pub block: Vec<u8>,
   pub receipts: Vec<u8>,
+  // untrusted wall-clock time at which the coordinator stored the entry (ms since the UNIX epoch);
+  // entries written before this field existed are zero-padded, so they read back as 0
+  pub timestamp: u64,
 }
 
 #[derive(Debug)]
@@ -46,6 +49,7 @@ pub struct FileStore {
   dir_path: PathBuf,
   open_files: FileMap,
   view_handle: Handle,
+  receipt_retention: ReceiptRetention,
 }
 
 impl FileStore {
@@ -56,6 +60,7 @@ impl FileStore {
       ));
     }
     let dir_path = Path::new(&args["NIMBLE_FSTORE_DIR"]).to_path_buf();
+    let receipt_retention = ReceiptRetention::from_args(args)?;
 
     let view_handle = match NimbleDigest::from_bytes(&vec![0u8; NimbleDigest::num_bytes()]) {
       Ok(e) => e,
@@ -103,6 +108,7 @@ impl FileStore {
       let entry = StoreEntry {
         block: Block::new(&[0; 0]).to_bytes(),
         receipts: Receipts::new().to_bytes(),
+        timestamp: current_timestamp(),
       };
 
       // Guaranteed to be the size of 1 file entry
@@ -115,10 +121,71 @@ impl FileStore {
       dir_path,
       open_files,
       view_handle,
+      receipt_retention,
     };
 
     Ok(file_store)
   }
+
+  // merges `receipts` into the receipts of the entry at `idx` and applies `receipt_retention`
+  fn update_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+    receipt_retention: ReceiptRetention,
+  ) -> Result<(), LedgerStoreError> {
+    // 1. Get the desired offset
+    let offset = match idx.checked_mul(ENTRY_SIZE) {
+      Some(v) => checked_conversion!(v, u64),
+      None => {
+        return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
+      },
+    };
+
+    let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
+
+    let mut ledger = match ledger_lock.write() {
+      Ok(v) => v,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerWriteLockFailed,
+        ));
+      },
+    };
+
+    let seek_from = SeekFrom::Start(offset);
+
+    // 2. Find the appropriate entry in the ledger
+    let mut serialized_entry = [0; ENTRY_SIZE];
+    read_at(seek_from, &mut ledger, &mut serialized_entry)?;
+
+    let mut ledger_entry: StoreEntry = match bincode::deserialize(&serialized_entry) {
+      Ok(e) => e,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::DeserializationError,
+        ));
+      },
+    };
+
+    // 3. Recover the contents of the ledger entry
+    let mut ledger_entry_receipts =
+      Receipts::from_bytes(&ledger_entry.receipts).expect("failed to deserialize receipt");
+
+    // 4. Update receipt
+    ledger_entry_receipts.merge_receipts(receipts);
+    receipt_retention.apply(&mut ledger_entry_receipts);
+    ledger_entry.receipts = ledger_entry_receipts.to_bytes();
+
+    // 5. Re-serialize
+    let ser_entry = serialize_entry(&ledger_entry)?;
+
+    // 6. Update entry
+    write_at(seek_from, &mut ledger, &ser_entry)?;
+
+    Ok(())
+  }
 }
 
 fn serialize_entry(entry: &StoreEntry) -> Result<Vec<u8>, LedgerStoreError> {
@@ -211,7 +278,7 @@ fn open_and_lock(
 
     // Check if the ledger exists.
     let mut options = OpenOptions::new();
-    let file_name = dir_path.join(&hex::encode(&handle.to_bytes()));
+    let file_name = dir_path.join(hex::encode(handle.to_bytes()));
     let ledger = match options
       .read(true)
       .write(true)
@@ -291,6 +358,13 @@ async fn read_ledger_op(
     },
   };
 
+  // an index past the end of the file is not in the ledger, as in the other stores
+  if let Ok(m) = ledger.metadata() {
+    if offset.saturating_add(ENTRY_SIZE as u64) > m.len() {
+      return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
+    }
+  }
+
   let mut serialized_entry = [0; ENTRY_SIZE];
   read_at(SeekFrom::Start(offset), &mut ledger, &mut serialized_entry)?;
 
@@ -304,14 +378,16 @@ async fn read_ledger_op(
   };
 
   // 3. Return ledger entry by deserializing its contents
-  Ok((
-    LedgerEntry::new(
-      Block::from_bytes(&entry.block).unwrap(),
-      Receipts::from_bytes(&entry.receipts).unwrap(),
-      None, //TODO
-    ),
-    index,
-  ))
+  let mut ledger_entry = LedgerEntry::new(
+    Block::from_bytes(&entry.block).unwrap(),
+    Receipts::from_bytes(&entry.receipts).unwrap(),
+    None, //TODO
+  );
+  if entry.timestamp != 0 {
+    ledger_entry.set_timestamp(entry.timestamp);
+  }
+
+  Ok((ledger_entry, index))
 }
 
 #[async_trait]
@@ -350,6 +426,7 @@ impl LedgerStore for FileStore {
     let init_entry = StoreEntry {
       block: genesis_block.to_bytes(),
       receipts: Receipts::new().to_bytes(),
+      timestamp: current_timestamp(),
     };
 
     // Serialize the entry
@@ -400,6 +477,7 @@ impl LedgerStore for FileStore {
     let new_entry = StoreEntry {
       block: block.to_bytes(),
       receipts: Receipts::new().to_bytes(),
+      timestamp: current_timestamp(),
     };
 
     let ser_entry = serialize_entry(&new_entry)?;
@@ -414,7 +492,9 @@ impl LedgerStore for FileStore {
     handle: &Handle,
     receipt: &Nonce,
   ) -> Result<usize, LedgerStoreError> {
-    unimplemented!()
+    Err(LedgerStoreError::LedgerError(
+      StorageError::NoncesNotSupported,
+    ))
   }
 
   async fn attach_ledger_receipts(
@@ -423,55 +503,20 @@ impl LedgerStore for FileStore {
     idx: usize,
     receipts: &Receipts,
   ) -> Result<(), LedgerStoreError> {
-    // 1. Get the desired offset
-    let offset = match idx.checked_mul(ENTRY_SIZE) {
-      Some(v) => checked_conversion!(v, u64),
-      None => {
-        return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
-      },
-    };
-
-    let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
-
-    let mut ledger = match ledger_lock.write() {
-      Ok(v) => v,
-      Err(_) => {
-        return Err(LedgerStoreError::LedgerError(
-          StorageError::LedgerWriteLockFailed,
-        ));
-      },
-    };
-
-    let seek_from = SeekFrom::Start(offset);
-
-    // 2. Find the appropriate entry in the ledger
-    let mut serialized_entry = [0; ENTRY_SIZE];
-    read_at(seek_from, &mut ledger, &mut serialized_entry)?;
-
-    let mut ledger_entry: StoreEntry = match bincode::deserialize(&serialized_entry) {
-      Ok(e) => e,
-      Err(_) => {
-        return Err(LedgerStoreError::LedgerError(
-          StorageError::DeserializationError,
-        ));
-      },
+    let receipt_retention = if *handle == self.view_handle {
+      ReceiptRetention::All
+    } else {
+      self.receipt_retention
     };
+    self.update_ledger_receipts(handle, idx, receipts, receipt_retention)
+  }
 
-    // 3. Recover the contents of the ledger entry
-    let mut ledger_entry_receipts =
-      Receipts::from_bytes(&ledger_entry.receipts).expect("failed to deserialize receipt");
-
-    // 4. Update receipt
-    ledger_entry_receipts.merge_receipts(receipts);
-    ledger_entry.receipts = ledger_entry_receipts.to_bytes();
-
-    // 5. Re-serialize
-    let ser_entry = serialize_entry(&ledger_entry)?;
-
-    // 6. Update entry
-    write_at(seek_from, &mut ledger, &ser_entry)?;
-
-    Ok(())
+  async fn drop_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<(), LedgerStoreError> {
+    self.update_ledger_receipts(handle, idx, &Receipts::new(), ReceiptRetention::None)
   }
 
   async fn read_ledger_tail(
@@ -522,6 +567,32 @@ impl LedgerStore for FileStore {
     Ok(res.0)
   }
 
+  async fn list_ledgers(&self) -> Result<Vec<Handle>, LedgerStoreError> {
+    let dir = match fs::read_dir(&self.dir_path) {
+      Ok(dir) => dir,
+      Err(e) => {
+        eprintln!("Error reading the store directory {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+
+    let mut handles = Vec::new();
+    for file in dir.flatten() {
+      // every ledger lives in a file named by the hex encoding of its handle
+      let bytes = match file.file_name().to_str().map(hex::decode) {
+        Some(Ok(bytes)) => bytes,
+        _ => continue,
+      };
+      if let Ok(handle) = NimbleDigest::from_bytes(&bytes) {
+        if handle != self.view_handle {
+          handles.push(handle);
+        }
+      }
+    }
+    handles.sort();
+    Ok(handles)
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     match fs::remove_dir_all(&self.dir_path) {
       Ok(_) => Ok(()),
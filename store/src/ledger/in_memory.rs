@@ -1,58 +1,356 @@
 use super::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
 use crate::{
   errors::{LedgerStoreError, StorageError},
-  ledger::{LedgerEntry, LedgerStore},
+  ledger::{
+    current_timestamp, AppendRequest, Lease, LedgerEntry, LedgerStore, LedgerSummary,
+    ReceiptRetention,
+  },
 };
 use async_trait::async_trait;
+use ledger::CustomSerde;
+use serde::{Deserialize, Serialize};
 use std::{
   collections::{hash_map, HashMap},
+  fs,
+  path::Path,
   sync::{Arc, RwLock},
 };
+use tokio::sync::mpsc::UnboundedSender;
 
 type LedgerArray = Arc<RwLock<Vec<LedgerEntry>>>;
 type NonceArray = Arc<RwLock<Vec<Nonce>>>;
+// the index at which an append request stored its block, or None while it is in progress, and
+// when the record expires (ms since the UNIX epoch)
+type AppendRequestMap = Arc<RwLock<HashMap<(Handle, Vec<u8>), (Option<usize>, u64)>>>;
 
-#[derive(Debug, Default)]
+// the number of shards of the maps from handles to ledgers and to their nonces
+const NUM_SHARDS: usize = 64;
+
+// A map from handles to the lock of each ledger, split into shards that are each behind their own
+// lock. The lock of a shard is only held to look up or insert a ledger's lock, so creating a
+// ledger does not stall the ledgers of other shards, and appends to different ledgers only take
+// their own ledgers' locks.
+#[derive(Debug)]
+struct ShardedMap<V> {
+  shards: Vec<RwLock<HashMap<Handle, V>>>,
+}
+
+impl<V> Default for ShardedMap<V> {
+  fn default() -> Self {
+    ShardedMap {
+      shards: (0..NUM_SHARDS)
+        .map(|_| RwLock::new(HashMap::new()))
+        .collect(),
+    }
+  }
+}
+
+impl<V: Clone> ShardedMap<V> {
+  fn shard(&self, handle: &Handle) -> &RwLock<HashMap<Handle, V>> {
+    // handles are digests, so their first byte spreads them evenly over the shards
+    &self.shards[handle.to_bytes()[0] as usize % self.shards.len()]
+  }
+
+  // returns the value of `handle`, after releasing the lock of its shard
+  fn get(&self, handle: &Handle) -> Result<V, LedgerStoreError> {
+    if let Ok(shard) = self.shard(handle).read() {
+      match shard.get(handle) {
+        Some(value) => Ok(value.clone()),
+        None => Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)),
+      }
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
+
+  // returns every handle and its value, locking one shard at a time
+  fn entries(&self) -> Result<Vec<(Handle, V)>, LedgerStoreError> {
+    let mut entries = Vec::new();
+    for shard in &self.shards {
+      let shard = shard
+        .read()
+        .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerMapReadLockFailed))?;
+      entries.extend(shard.iter().map(|(handle, value)| (*handle, value.clone())));
+    }
+    Ok(entries)
+  }
+}
+
+/// The state of an entry of an in-memory store after a change, streamed to a warm standby.
+/// Events carry the whole entry, so applying one twice or after a snapshot that already
+/// includes it is harmless. Nonces waiting for the next append are not replicated, and neither
+/// are ledger labels or the namespaces of ledgers.
+#[derive(Clone, Debug)]
+pub struct ReplicationEvent {
+  /// the ledger of the entry, or None for the view ledger
+  pub handle: Option<Handle>,
+  pub index: usize,
+  pub entry: LedgerEntry,
+}
+
+// An entry of a snapshot file, which holds the bincode of the list of them
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+  handle: Option<Vec<u8>>, // the handle of the ledger, or None for the view ledger
+  index: usize,
+  block: Vec<u8>,
+  nonces: Vec<u8>,
+  receipts: Vec<u8>,
+  timestamp: Option<u64>,
+}
+
+impl SnapshotEntry {
+  fn new(event: &ReplicationEvent) -> Self {
+    SnapshotEntry {
+      handle: event.handle.map(|handle| handle.to_bytes()),
+      index: event.index,
+      block: event.entry.get_block().to_bytes(),
+      nonces: event.entry.get_nonces().to_bytes(),
+      receipts: event.entry.get_receipts().to_bytes(),
+      timestamp: event.entry.get_timestamp(),
+    }
+  }
+
+  fn decode(&self) -> Result<ReplicationEvent, LedgerStoreError> {
+    let invalid = |_e| LedgerStoreError::LedgerError(StorageError::DeserializationError);
+    let handle = match &self.handle {
+      Some(handle) => Some(Handle::from_bytes(handle).map_err(invalid)?),
+      None => None,
+    };
+    let mut entry = LedgerEntry::new(
+      Block::from_bytes(&self.block).map_err(invalid)?,
+      Receipts::from_bytes(&self.receipts).map_err(invalid)?,
+      Some(Nonces::from_bytes(&self.nonces).map_err(invalid)?),
+    );
+    if let Some(timestamp) = self.timestamp {
+      entry.set_timestamp(timestamp);
+    }
+    Ok(ReplicationEvent {
+      handle,
+      index: self.index,
+      entry,
+    })
+  }
+}
+
+/// Clones share the underlying ledgers
+#[derive(Clone, Debug, Default)]
 pub struct InMemoryLedgerStore {
-  ledgers: Arc<RwLock<HashMap<Handle, LedgerArray>>>,
-  nonces: Arc<RwLock<HashMap<Handle, NonceArray>>>,
+  ledgers: Arc<ShardedMap<LedgerArray>>,
+  nonces: Arc<ShardedMap<NonceArray>>,
   view_ledger: Arc<RwLock<Vec<LedgerEntry>>>,
+  receipt_retention: ReceiptRetention,
+  replication: Option<UnboundedSender<ReplicationEvent>>,
+  leases: Arc<RwLock<HashMap<String, Lease>>>,
+  append_requests: AppendRequestMap,
+  labels: Arc<RwLock<HashMap<String, Vec<u8>>>>, // the handle each label is bound to
+  namespaces: Arc<RwLock<HashMap<String, Vec<Handle>>>>, // the ledgers of each namespace
 }
 
 impl InMemoryLedgerStore {
   pub fn new() -> Self {
-    let ledgers = HashMap::new();
     let mut view_ledger = Vec::new();
 
     let view_ledger_entry = LedgerEntry::new(Block::new(&[0; 0]), Receipts::new(), None);
     view_ledger.push(view_ledger_entry);
 
     InMemoryLedgerStore {
-      ledgers: Arc::new(RwLock::new(ledgers)),
-      nonces: Arc::new(RwLock::new(HashMap::new())),
+      ledgers: Arc::new(ShardedMap::default()),
+      nonces: Arc::new(ShardedMap::default()),
       view_ledger: Arc::new(RwLock::new(view_ledger)),
+      receipt_retention: ReceiptRetention::default(),
+      replication: None,
+      leases: Arc::new(RwLock::new(HashMap::new())),
+      append_requests: Arc::new(RwLock::new(HashMap::new())),
+      labels: Arc::new(RwLock::new(HashMap::new())),
+      namespaces: Arc::new(RwLock::new(HashMap::new())),
     }
   }
 
-  fn drain_nonces(&self, handle: &Handle) -> Result<Nonces, LedgerStoreError> {
-    if let Ok(nonce_map) = self.nonces.read() {
-      if nonce_map.contains_key(handle) {
-        if let Ok(mut nonces) = nonce_map[handle].write() {
-          Ok(Nonces::from_vec(nonces.drain(..).collect()))
-        } else {
-          Err(LedgerStoreError::LedgerError(
-            StorageError::LedgerWriteLockFailed,
-          ))
-        }
-      } else {
-        eprintln!("Unable to drain nonce because key does not exist");
-        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+  /// creates an empty store configured by the store arguments, i.e. RECEIPT_RETENTION
+  pub fn from_args(args: &HashMap<String, String>) -> Result<Self, LedgerStoreError> {
+    Ok(InMemoryLedgerStore::new().with_receipt_retention(ReceiptRetention::from_args(args)?))
+  }
+
+  pub fn with_receipt_retention(mut self, receipt_retention: ReceiptRetention) -> Self {
+    self.receipt_retention = receipt_retention;
+    self
+  }
+
+  /// streams every change to an entry into `sender`; events of a ledger are sent while its
+  /// lock is held, so they arrive in the order the changes were made
+  pub fn with_replication(mut self, sender: UnboundedSender<ReplicationEvent>) -> Self {
+    self.replication = Some(sender);
+    self
+  }
+
+  fn replicate(&self, handle: Option<Handle>, index: usize, entry: &LedgerEntry) {
+    if let Some(sender) = &self.replication {
+      // the receiver only goes away when the process is shutting down
+      let _ = sender.send(ReplicationEvent {
+        handle,
+        index,
+        entry: entry.clone(),
+      });
+    }
+  }
+
+  /// returns the handles of all ledgers in the store
+  pub fn get_handles(&self) -> Vec<Handle> {
+    match self.ledgers.entries() {
+      Ok(entries) => entries
+        .into_iter()
+        .map(|(handle, _ledger)| handle)
+        .collect(),
+      Err(_) => Vec::new(),
+    }
+  }
+
+  /// returns events that recreate every entry of the store, view ledger first
+  pub fn snapshot(&self) -> Result<Vec<ReplicationEvent>, LedgerStoreError> {
+    let mut events = Vec::new();
+    if let Ok(view_ledger_array) = self.view_ledger.read() {
+      for (index, entry) in view_ledger_array.iter().enumerate().skip(1) {
+        events.push(ReplicationEvent {
+          handle: None,
+          index,
+          entry: entry.clone(),
+        });
       }
     } else {
-      Err(LedgerStoreError::LedgerError(
-        StorageError::LedgerMapReadLockFailed,
-      ))
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::ViewLedgerReadLockFailed,
+      ));
+    }
+
+    // ledgers created while the snapshot is taken may be missing from it, but their entries are
+    // streamed as events anyway
+    for (handle, ledger) in self.ledgers.entries()? {
+      let ledgers = ledger
+        .read()
+        .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerReadLockFailed))?;
+      for (index, entry) in ledgers.iter().enumerate() {
+        events.push(ReplicationEvent {
+          handle: Some(handle),
+          index,
+          entry: entry.clone(),
+        });
+      }
+    }
+    Ok(events)
+  }
+
+  /// writes every entry of the store to a snapshot file at `path` and returns the number of
+  /// entries written. The snapshot goes to a temporary file first, which then replaces the
+  /// previous snapshot, so a crash while writing leaves the previous one intact. Nonces waiting
+  /// for the next append, ledger labels, and the namespaces of ledgers are not in snapshots.
+  pub fn save_snapshot(&self, path: &Path) -> Result<usize, LedgerStoreError> {
+    let entries = self
+      .snapshot()?
+      .iter()
+      .map(SnapshotEntry::new)
+      .collect::<Vec<SnapshotEntry>>();
+    let bytes = bincode::serialize(&entries)
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::SerializationError))?;
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    fs::write(&temp_path, bytes)
+      .and_then(|()| fs::rename(&temp_path, path))
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::SnapshotFileFailed))?;
+    Ok(entries.len())
+  }
+
+  /// loads the snapshot file at `path` into the store and returns the number of entries loaded,
+  /// or 0 if there is no file yet
+  pub fn load_snapshot(&self, path: &Path) -> Result<usize, LedgerStoreError> {
+    let bytes = match fs::read(path) {
+      Ok(bytes) => bytes,
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+      Err(_e) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::SnapshotFileFailed,
+        ))
+      },
+    };
+    let entries: Vec<SnapshotEntry> = bincode::deserialize(&bytes)
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::DeserializationError))?;
+    for entry in &entries {
+      self.apply_replication_event(&entry.decode()?)?;
+    }
+    Ok(entries.len())
+  }
+
+  /// applies an event streamed from a primary's store: the entry at `index` is appended if it
+  /// extends the ledger, or has its receipts replaced if the ledger already holds the same block
+  pub fn apply_replication_event(&self, event: &ReplicationEvent) -> Result<(), LedgerStoreError> {
+    fn apply(
+      entries: &mut Vec<LedgerEntry>,
+      event: &ReplicationEvent,
+    ) -> Result<(), LedgerStoreError> {
+      if event.index == entries.len() {
+        entries.push(event.entry.clone());
+        Ok(())
+      } else if event.index < entries.len()
+        && entries[event.index].block.to_bytes() == event.entry.block.to_bytes()
+      {
+        entries[event.index].receipts = event.entry.receipts.clone();
+        Ok(())
+      } else {
+        Err(LedgerStoreError::LedgerError(
+          StorageError::ReplicationConflict,
+        ))
+      }
     }
+
+    match event.handle {
+      None => {
+        let mut view_ledger_array = self
+          .view_ledger
+          .write()
+          .map_err(|_e| LedgerStoreError::LedgerError(StorageError::ViewLedgerWriteLockFailed))?;
+        apply(&mut view_ledger_array, event)
+      },
+      Some(handle) => {
+        let ledger = if event.index == 0 {
+          let mut ledgers_shard =
+            self.ledgers.shard(&handle).write().map_err(|_e| {
+              LedgerStoreError::LedgerError(StorageError::LedgerMapWriteLockFailed)
+            })?;
+          if let Ok(mut nonces_shard) = self.nonces.shard(&handle).write() {
+            nonces_shard
+              .entry(handle)
+              .or_insert_with(|| Arc::new(RwLock::new(Vec::new())));
+          }
+          ledgers_shard
+            .entry(handle)
+            .or_insert_with(|| Arc::new(RwLock::new(Vec::new())))
+            .clone()
+        } else {
+          match self.ledgers.get(&handle) {
+            Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)) => {
+              return Err(LedgerStoreError::LedgerError(
+                StorageError::ReplicationConflict,
+              ))
+            },
+            res => res?,
+          }
+        };
+        let mut ledgers = ledger
+          .write()
+          .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerWriteLockFailed))?;
+        apply(&mut ledgers, event)
+      },
+    }
+  }
+
+  fn drain_nonces(&self, handle: &Handle) -> Result<Nonces, LedgerStoreError> {
+    let nonce_array = self.nonces.get(handle)?;
+    let mut nonces = nonce_array
+      .write()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerWriteLockFailed))?;
+    Ok(Nonces::from_vec(nonces.drain(..).collect()))
   }
 }
 
@@ -63,13 +361,15 @@ impl LedgerStore for InMemoryLedgerStore {
     handle: &NimbleDigest,
     genesis_block: Block,
   ) -> Result<(), LedgerStoreError> {
-    let genesis_ledger_entry = LedgerEntry::new(genesis_block, Receipts::new(), None);
-    if let Ok(mut ledgers_map) = self.ledgers.write() {
-      if let Ok(mut nonce_map) = self.nonces.write() {
-        if let hash_map::Entry::Vacant(e) = ledgers_map.entry(*handle) {
+    let mut genesis_ledger_entry = LedgerEntry::new(genesis_block, Receipts::new(), None);
+    genesis_ledger_entry.set_timestamp(current_timestamp());
+    if let Ok(mut ledgers_shard) = self.ledgers.shard(handle).write() {
+      if let Ok(mut nonces_shard) = self.nonces.shard(handle).write() {
+        if let hash_map::Entry::Vacant(e) = ledgers_shard.entry(*handle) {
+          self.replicate(Some(*handle), 0, &genesis_ledger_entry);
           e.insert(Arc::new(RwLock::new(vec![genesis_ledger_entry])));
 
-          if let hash_map::Entry::Vacant(n) = nonce_map.entry(*handle) {
+          if let hash_map::Entry::Vacant(n) = nonces_shard.entry(*handle) {
             n.insert(Arc::new(RwLock::new(Vec::new())));
             Ok(())
           } else {
@@ -96,37 +396,26 @@ impl LedgerStore for InMemoryLedgerStore {
     block: &Block,
     expected_height: usize,
   ) -> Result<(usize, Nonces), LedgerStoreError> {
-    if let Ok(ledgers_map) = self.ledgers.read() {
-      if ledgers_map.contains_key(handle) {
-        if let Ok(mut ledgers) = ledgers_map[handle].write() {
-          if expected_height == ledgers.len() {
-            let nonces = self.drain_nonces(handle)?;
-
-            let ledger_entry = LedgerEntry {
-              block: block.clone(),
-              receipts: Receipts::new(),
-              nonces: nonces.clone(),
-            };
-            ledgers.push(ledger_entry);
-
-            Ok(((ledgers.len() - 1), nonces))
-          } else {
-            Err(LedgerStoreError::LedgerError(
-              StorageError::IncorrectConditionalData,
-            ))
-          }
-        } else {
-          Err(LedgerStoreError::LedgerError(
-            StorageError::LedgerWriteLockFailed,
-          ))
-        }
-      } else {
-        eprintln!("Key does not exist in the ledger map");
-        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
-      }
+    let ledger = self.ledgers.get(handle)?;
+    let mut ledgers = ledger
+      .write()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerWriteLockFailed))?;
+    if expected_height == ledgers.len() {
+      let nonces = self.drain_nonces(handle)?;
+
+      let ledger_entry = LedgerEntry {
+        block: block.clone(),
+        receipts: Receipts::new(),
+        nonces: nonces.clone(),
+        timestamp: Some(current_timestamp()),
+      };
+      self.replicate(Some(*handle), ledgers.len(), &ledger_entry);
+      ledgers.push(ledger_entry);
+
+      Ok(((ledgers.len() - 1), nonces))
     } else {
       Err(LedgerStoreError::LedgerError(
-        StorageError::LedgerMapReadLockFailed,
+        StorageError::IncorrectConditionalData,
       ))
     }
   }
@@ -137,28 +426,36 @@ impl LedgerStore for InMemoryLedgerStore {
     idx: usize,
     receipts: &Receipts,
   ) -> Result<(), LedgerStoreError> {
-    if let Ok(ledgers_map) = self.ledgers.read() {
-      if ledgers_map.contains_key(handle) {
-        if let Ok(mut ledgers) = ledgers_map[handle].write() {
-          let height = idx;
-          if height < ledgers.len() {
-            ledgers[height].receipts.merge_receipts(receipts);
-            Ok(())
-          } else {
-            Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
-          }
-        } else {
-          Err(LedgerStoreError::LedgerError(
-            StorageError::LedgerWriteLockFailed,
-          ))
-        }
-      } else {
-        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
-      }
+    let ledger = self.ledgers.get(handle)?;
+    let mut ledgers = ledger
+      .write()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerWriteLockFailed))?;
+    let height = idx;
+    if height < ledgers.len() {
+      ledgers[height].receipts.merge_receipts(receipts);
+      self.receipt_retention.apply(&mut ledgers[height].receipts);
+      self.replicate(Some(*handle), height, &ledgers[height]);
+      Ok(())
     } else {
-      Err(LedgerStoreError::LedgerError(
-        StorageError::LedgerMapReadLockFailed,
-      ))
+      Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
+    }
+  }
+
+  async fn drop_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let ledger = self.ledgers.get(handle)?;
+    let mut ledgers = ledger
+      .write()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerWriteLockFailed))?;
+    if idx < ledgers.len() {
+      ledgers[idx].receipts = Receipts::new();
+      self.replicate(Some(*handle), idx, &ledgers[idx]);
+      Ok(())
+    } else {
+      Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
     }
   }
 
@@ -167,68 +464,34 @@ impl LedgerStore for InMemoryLedgerStore {
     handle: &Handle,
     nonce: &Nonce,
   ) -> Result<usize, LedgerStoreError> {
-    if let Ok(ledgers_map) = self.ledgers.read() {
-      if ledgers_map.contains_key(handle) {
-        if let Ok(ledgers) = ledgers_map[handle].read() {
-          let height = ledgers.len();
-
-          if let Ok(nonce_map) = self.nonces.read() {
-            if nonce_map.contains_key(handle) {
-              if let Ok(mut nonces) = nonce_map[handle].write() {
-                // add nonce to the nonces list of this ledger and return the next
-                // height at which it should be appended
-                nonces.push(nonce.to_owned());
-                Ok(height)
-              } else {
-                Err(LedgerStoreError::LedgerError(
-                  StorageError::LedgerWriteLockFailed,
-                ))
-              }
-            } else {
-              Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
-            }
-          } else {
-            Err(LedgerStoreError::LedgerError(
-              StorageError::LedgerReadLockFailed,
-            ))
-          }
-        } else {
-          Err(LedgerStoreError::LedgerError(
-            StorageError::LedgerReadLockFailed,
-          ))
-        }
-      } else {
-        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
-      }
-    } else {
-      Err(LedgerStoreError::LedgerError(
-        StorageError::LedgerMapReadLockFailed,
-      ))
-    }
+    let ledger = self.ledgers.get(handle)?;
+    let ledgers = ledger
+      .read()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerReadLockFailed))?;
+    let height = ledgers.len();
+
+    // the lock of the ledger is held until the nonce is added, so that it goes to the append at
+    // `height`
+    let nonce_array = self.nonces.get(handle)?;
+    let mut nonces = nonce_array
+      .write()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerWriteLockFailed))?;
+    // add nonce to the nonces list of this ledger and return the next
+    // height at which it should be appended
+    nonces.push(nonce.to_owned());
+    Ok(height)
   }
 
   async fn read_ledger_tail(
     &self,
     handle: &Handle,
   ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
-    if let Ok(ledgers_map) = self.ledgers.read() {
-      if ledgers_map.contains_key(handle) {
-        if let Ok(ledgers) = ledgers_map[handle].read() {
-          let ledgers_entry = ledgers[ledgers.len() - 1].clone();
-          Ok((ledgers_entry, ledgers.len() - 1))
-        } else {
-          Err(LedgerStoreError::LedgerError(
-            StorageError::LedgerReadLockFailed,
-          ))
-        }
-      } else {
-        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
-      }
-    } else {
-      Err(LedgerStoreError::LedgerError(
-        StorageError::LedgerMapReadLockFailed,
-      ))
-    }
+    let ledger = self.ledgers.get(handle)?;
+    let ledgers = ledger
+      .read()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerReadLockFailed))?;
+    let ledgers_entry = ledgers[ledgers.len() - 1].clone();
+    Ok((ledgers_entry, ledgers.len() - 1))
   }
 
   async fn read_ledger_by_index(
@@ -236,26 +499,31 @@ impl LedgerStore for InMemoryLedgerStore {
     handle: &Handle,
     idx: usize,
   ) -> Result<LedgerEntry, LedgerStoreError> {
-    if let Ok(ledgers_map) = self.ledgers.read() {
-      if ledgers_map.contains_key(handle) {
-        if let Ok(ledgers) = ledgers_map[handle].read() {
-          if idx < ledgers.len() {
-            Ok(ledgers[idx].clone())
-          } else {
-            Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
-          }
-        } else {
-          Err(LedgerStoreError::LedgerError(
-            StorageError::LedgerReadLockFailed,
-          ))
-        }
-      } else {
-        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
-      }
+    let ledger = self.ledgers.get(handle)?;
+    let ledgers = ledger
+      .read()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerReadLockFailed))?;
+    if idx < ledgers.len() {
+      Ok(ledgers[idx].clone())
     } else {
-      Err(LedgerStoreError::LedgerError(
-        StorageError::LedgerMapReadLockFailed,
-      ))
+      Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
+    }
+  }
+
+  async fn read_ledger_range(
+    &self,
+    handle: &Handle,
+    start: usize,
+    end: usize,
+  ) -> Result<Vec<LedgerEntry>, LedgerStoreError> {
+    let ledger = self.ledgers.get(handle)?;
+    let ledgers = ledger
+      .read()
+      .map_err(|_e| LedgerStoreError::LedgerError(StorageError::LedgerReadLockFailed))?;
+    if start < ledgers.len() {
+      Ok(ledgers[start..end.clamp(start, ledgers.len())].to_vec())
+    } else {
+      Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
     }
   }
 
@@ -266,7 +534,9 @@ impl LedgerStore for InMemoryLedgerStore {
   ) -> Result<usize, LedgerStoreError> {
     if let Ok(mut view_ledger_array) = self.view_ledger.write() {
       if expected_height == view_ledger_array.len() {
-        let ledger_entry = LedgerEntry::new(block.clone(), Receipts::new(), None);
+        let mut ledger_entry = LedgerEntry::new(block.clone(), Receipts::new(), None);
+        ledger_entry.set_timestamp(current_timestamp());
+        self.replicate(None, view_ledger_array.len(), &ledger_entry);
         view_ledger_array.push(ledger_entry);
         Ok(view_ledger_array.len() - 1)
       } else {
@@ -290,6 +560,7 @@ impl LedgerStore for InMemoryLedgerStore {
       let height = idx;
       if height < view_ledger_array.len() {
         view_ledger_array[height].receipts.merge_receipts(receipts);
+        self.replicate(None, height, &view_ledger_array[height]);
         Ok(())
       } else {
         Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
@@ -326,6 +597,226 @@ impl LedgerStore for InMemoryLedgerStore {
     }
   }
 
+  async fn list_ledgers(&self) -> Result<Vec<Handle>, LedgerStoreError> {
+    let mut handles = self.get_handles();
+    handles.sort();
+    Ok(handles)
+  }
+
+  async fn list_ledger_summaries(
+    &self,
+    after: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<LedgerSummary>, LedgerStoreError> {
+    let mut ledgers = self
+      .ledgers
+      .entries()?
+      .into_iter()
+      .filter(|(handle, _ledger)| after.is_none_or(|after| handle > after))
+      .collect::<Vec<(Handle, LedgerArray)>>();
+    ledgers.sort_by_key(|(handle, _ledger)| *handle);
+
+    let mut summaries = Vec::new();
+    for (handle, ledger) in ledgers.into_iter().take(limit) {
+      let ledger = match ledger.read() {
+        Ok(ledger) => ledger,
+        Err(_) => {
+          return Err(LedgerStoreError::LedgerError(
+            StorageError::LedgerReadLockFailed,
+          ))
+        },
+      };
+      // a ledger is created with its genesis entry, so it is never empty
+      summaries.push(LedgerSummary {
+        handle,
+        height: ledger.len() - 1,
+        genesis: ledger[0].clone(),
+      });
+    }
+    Ok(summaries)
+  }
+
+  async fn create_ledger_in_namespace(
+    &self,
+    namespace: &str,
+    handle: &Handle,
+    genesis_block: Block,
+  ) -> Result<(), LedgerStoreError> {
+    self.create_ledger(handle, genesis_block).await?;
+    if let Ok(mut namespaces) = self.namespaces.write() {
+      namespaces
+        .entry(namespace.to_string())
+        .or_default()
+        .push(*handle);
+      Ok(())
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapWriteLockFailed,
+      ))
+    }
+  }
+
+  async fn list_ledgers_in_namespace(
+    &self,
+    namespace: &str,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    if let Ok(namespaces) = self.namespaces.read() {
+      let mut handles = namespaces.get(namespace).cloned().unwrap_or_default();
+      handles.sort();
+      Ok(handles)
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
+
+  async fn acquire_lease(
+    &self,
+    name: &str,
+    holder: &str,
+    duration_ms: u64,
+  ) -> Result<Lease, LedgerStoreError> {
+    let mut leases = match self.leases.write() {
+      Ok(leases) => leases,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerMapWriteLockFailed,
+        ))
+      },
+    };
+    let now = current_timestamp();
+    let lease = leases.entry(name.to_string()).or_insert(Lease {
+      holder: holder.to_string(),
+      expires_at: 0,
+    });
+    if lease.holder == holder || lease.expires_at <= now {
+      *lease = Lease {
+        holder: holder.to_string(),
+        expires_at: now.saturating_add(duration_ms),
+      };
+    }
+    Ok(lease.clone())
+  }
+
+  async fn claim_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+    duration_ms: u64,
+  ) -> Result<AppendRequest, LedgerStoreError> {
+    let mut append_requests = match self.append_requests.write() {
+      Ok(append_requests) => append_requests,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerMapWriteLockFailed,
+        ))
+      },
+    };
+    let now = current_timestamp();
+    append_requests.retain(|_, (_, expires_at)| *expires_at > now);
+    match append_requests.entry((*handle, request_id.to_vec())) {
+      hash_map::Entry::Occupied(record) => match record.get().0 {
+        Some(idx) => Ok(AppendRequest::Completed(idx)),
+        None => Ok(AppendRequest::InProgress),
+      },
+      hash_map::Entry::Vacant(record) => {
+        record.insert((None, now.saturating_add(duration_ms)));
+        Ok(AppendRequest::Claimed)
+      },
+    }
+  }
+
+  async fn complete_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+    idx: usize,
+    duration_ms: u64,
+  ) -> Result<(), LedgerStoreError> {
+    if let Ok(mut append_requests) = self.append_requests.write() {
+      append_requests.insert(
+        (*handle, request_id.to_vec()),
+        (Some(idx), current_timestamp().saturating_add(duration_ms)),
+      );
+      Ok(())
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapWriteLockFailed,
+      ))
+    }
+  }
+
+  async fn release_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    if let Ok(mut append_requests) = self.append_requests.write() {
+      append_requests.remove(&(*handle, request_id.to_vec()));
+      Ok(())
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapWriteLockFailed,
+      ))
+    }
+  }
+
+  async fn add_ledger_label(
+    &self,
+    label: &str,
+    handle_bytes: &[u8],
+  ) -> Result<bool, LedgerStoreError> {
+    let mut labels = match self.labels.write() {
+      Ok(labels) => labels,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerMapWriteLockFailed,
+        ))
+      },
+    };
+    match labels.entry(label.to_string()) {
+      hash_map::Entry::Occupied(bound) if bound.get() != handle_bytes => {
+        Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey))
+      },
+      hash_map::Entry::Occupied(_bound) => Ok(false),
+      hash_map::Entry::Vacant(unbound) => {
+        unbound.insert(handle_bytes.to_vec());
+        Ok(true)
+      },
+    }
+  }
+
+  async fn remove_ledger_label(
+    &self,
+    label: &str,
+    handle_bytes: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    if let Ok(mut labels) = self.labels.write() {
+      if labels.get(label).is_some_and(|bound| bound == handle_bytes) {
+        labels.remove(label);
+      }
+      Ok(())
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapWriteLockFailed,
+      ))
+    }
+  }
+
+  async fn find_ledger_by_label(&self, label: &str) -> Result<Vec<u8>, LedgerStoreError> {
+    if let Ok(labels) = self.labels.read() {
+      match labels.get(label) {
+        Some(handle_bytes) => Ok(handle_bytes.clone()),
+        None => Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)),
+      }
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     // not really needed for in-memory since state is already volatile.
     // this API is only for testing persistent storage services.
@@ -333,3 +824,177 @@ impl LedgerStore for InMemoryLedgerStore {
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::NimbleHashTrait;
+  use std::{sync::mpsc, thread, time::Duration};
+
+  fn genesis(i: usize) -> Block {
+    Block::new(format!("genesis {}", i).as_bytes())
+  }
+
+  #[test]
+  fn test_ledgers_lock_independently() {
+    let store = InMemoryLedgerStore::new();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    // a ledger in the same shard as the first one, and one in another shard
+    let blocks = (0..1000).map(genesis).collect::<Vec<Block>>();
+    let first = &blocks[0];
+    let shard_of = |block: &Block| block.hash().to_bytes()[0] as usize % NUM_SHARDS;
+    let same = blocks[1..]
+      .iter()
+      .find(|block| shard_of(block) == shard_of(first))
+      .unwrap();
+    let other = blocks[1..]
+      .iter()
+      .find(|block| shard_of(block) != shard_of(first))
+      .unwrap();
+    for block in [first, same, other] {
+      runtime
+        .block_on(store.create_ledger(&block.hash(), block.clone()))
+        .unwrap();
+    }
+
+    // while an append to the first ledger holds its lock, the other ledgers are appended to and
+    // new ledgers are created
+    let ledger = store.ledgers.get(&first.hash()).unwrap();
+    let held = ledger.write().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let handles = [same.hash(), other.hash()];
+    let (appender, creator) = (store.clone(), blocks[999].clone());
+    thread::spawn(move || {
+      let runtime = tokio::runtime::Runtime::new().unwrap();
+      runtime.block_on(async {
+        for handle in &handles {
+          appender
+            .append_ledger(handle, &Block::new(b"block"), 1)
+            .await
+            .unwrap();
+          appender.read_ledger_tail(handle).await.unwrap();
+        }
+        appender
+          .create_ledger(&creator.hash(), creator.clone())
+          .await
+          .unwrap();
+      });
+      sender.send(()).unwrap();
+    });
+    assert!(receiver.recv_timeout(Duration::from_secs(10)).is_ok());
+    drop(held);
+
+    let (_tail, height) = runtime
+      .block_on(store.read_ledger_tail(&first.hash()))
+      .unwrap();
+    assert_eq!(height, 0);
+    assert_eq!(runtime.block_on(store.list_ledgers()).unwrap().len(), 4);
+  }
+
+  #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+  async fn test_concurrent_appends() {
+    const NUM_LEDGERS: usize = 64;
+    const NUM_APPENDS: usize = 100;
+
+    let store = InMemoryLedgerStore::new();
+    for i in 0..NUM_LEDGERS {
+      store
+        .create_ledger(&genesis(i).hash(), genesis(i))
+        .await
+        .unwrap();
+    }
+
+    // every task appends to its own ledger, with a nonce before each append, while another task
+    // keeps creating ledgers
+    let start = std::time::Instant::now();
+    let mut tasks = Vec::new();
+    for i in 0..NUM_LEDGERS {
+      let store = store.clone();
+      tasks.push(tokio::spawn(async move {
+        let handle = genesis(i).hash();
+        for height in 1..=NUM_APPENDS {
+          let nonce = Nonce::new(&[height as u8; 16]).unwrap();
+          assert_eq!(
+            store.attach_ledger_nonce(&handle, &nonce).await.unwrap(),
+            height
+          );
+          let block = Block::new(format!("block {} of {}", height, i).as_bytes());
+          let (idx, nonces) = store.append_ledger(&handle, &block, height).await.unwrap();
+          assert_eq!(idx, height);
+          assert_eq!(nonces.len(), 1);
+        }
+      }));
+    }
+    let creator = store.clone();
+    tasks.push(tokio::spawn(async move {
+      for i in NUM_LEDGERS..2 * NUM_LEDGERS {
+        creator
+          .create_ledger(&genesis(i).hash(), genesis(i))
+          .await
+          .unwrap();
+      }
+    }));
+    for task in tasks {
+      task.await.unwrap();
+    }
+    println!(
+      "{} appends to {} ledgers in {:?}",
+      NUM_LEDGERS * NUM_APPENDS,
+      NUM_LEDGERS,
+      start.elapsed()
+    );
+
+    let summaries = store.list_ledger_summaries(None, usize::MAX).await.unwrap();
+    assert_eq!(summaries.len(), 2 * NUM_LEDGERS);
+    for i in 0..NUM_LEDGERS {
+      let handle = genesis(i).hash();
+      let (tail, height) = store.read_ledger_tail(&handle).await.unwrap();
+      assert_eq!(height, NUM_APPENDS);
+      assert_eq!(
+        tail.get_block().to_bytes(),
+        format!("block {} of {}", NUM_APPENDS, i).as_bytes()
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn test_snapshot_survives_restart() {
+    let path = std::env::temp_dir().join(format!("nimble-snapshot-{}.bin", rand::random::<u64>()));
+    let store = InMemoryLedgerStore::new();
+    assert_eq!(store.load_snapshot(&path).unwrap(), 0);
+
+    let handle = genesis(0).hash();
+    store.create_ledger(&handle, genesis(0)).await.unwrap();
+    let nonce = Nonce::new(&[7; 16]).unwrap();
+    store.attach_ledger_nonce(&handle, &nonce).await.unwrap();
+    store
+      .append_ledger(&handle, &Block::new(b"block 1"), 1)
+      .await
+      .unwrap();
+    store
+      .append_view_ledger(&Block::new(b"view 1"), 1)
+      .await
+      .unwrap();
+    assert_eq!(store.save_snapshot(&path).unwrap(), 3);
+
+    // a store that loads the snapshot, as a restarted coordinator would, holds the same entries
+    let restarted = InMemoryLedgerStore::new();
+    assert_eq!(restarted.load_snapshot(&path).unwrap(), 3);
+    let (tail, height) = restarted.read_ledger_tail(&handle).await.unwrap();
+    assert_eq!(
+      (tail.get_block().to_bytes(), height),
+      (b"block 1".to_vec(), 1)
+    );
+    assert_eq!(tail.get_nonces().len(), 1);
+    let (view_tail, view_height) = restarted.read_view_ledger_tail().await.unwrap();
+    assert_eq!(
+      (view_tail.get_block().to_bytes(), view_height),
+      (b"view 1".to_vec(), 1)
+    );
+
+    // a damaged snapshot is rejected
+    std::fs::write(&path, b"not a snapshot").unwrap();
+    assert!(InMemoryLedgerStore::new().load_snapshot(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+  }
+}
@@ -1,18 +1,26 @@
 use async_trait::async_trait;
 use ledger::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
 
+pub mod archive;
 pub mod azure_table;
 pub mod filestore;
 pub mod in_memory;
 pub mod mongodb_cosmos;
+pub mod traced;
+pub mod verify;
 
-use crate::errors::LedgerStoreError;
+use crate::errors::{LedgerStoreError, StorageError};
+use std::{
+  collections::HashMap,
+  time::{SystemTime, UNIX_EPOCH},
+};
 
 #[derive(Debug, Default, Clone)]
 pub struct LedgerEntry {
   block: Block,
   receipts: Receipts,
   nonces: Nonces,
+  timestamp: Option<u64>,
 }
 
 impl LedgerEntry {
@@ -25,6 +33,7 @@ impl LedgerEntry {
       } else {
         Nonces::new()
       },
+      timestamp: None,
     }
   }
 
@@ -43,6 +52,190 @@ impl LedgerEntry {
   pub fn get_nonces(&self) -> &Nonces {
     &self.nonces
   }
+
+  /// Returns the coordinator's wall-clock time (milliseconds since the UNIX epoch) at which the
+  /// entry was stored, if the store recorded one. It is not covered by any endorser signature,
+  /// so it is only useful as a rough, untrusted ordering hint.
+  pub fn get_timestamp(&self) -> Option<u64> {
+    self.timestamp
+  }
+
+  pub fn set_timestamp(&mut self, timestamp: u64) {
+    self.timestamp = Some(timestamp);
+  }
+}
+
+/// A ledger as a page of `LedgerStore::list_ledger_summaries` describes it
+#[derive(Debug, Clone)]
+pub struct LedgerSummary {
+  pub handle: Handle,
+  /// the index of the ledger's tail
+  pub height: usize,
+  /// the first entry of the ledger, whose timestamp is when the ledger was created
+  pub genesis: LedgerEntry,
+}
+
+/// Returns the current wall-clock time in milliseconds since the UNIX epoch
+pub fn current_timestamp() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or(0)
+}
+
+/// Policy deciding which receipts of an entry a store keeps after new receipts are merged in.
+/// Receipts obtained in older views are superseded once the entry is re-endorsed, so
+/// deployments that do not need the history can bound it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReceiptRetention {
+  /// keep every receipt ever attached to an entry
+  #[default]
+  All,
+  /// keep only the receipt backed by the most signatures
+  Strongest,
+  /// keep up to the given number of receipts, preferring those with the most signatures
+  Bounded(usize),
+  /// keep no receipts; only used to compact the entries between checkpoints
+  None,
+}
+
+impl ReceiptRetention {
+  /// parses the RECEIPT_RETENTION store argument: "all", "strongest", or "bounded:<n>"
+  pub fn from_args(args: &HashMap<String, String>) -> Result<Self, LedgerStoreError> {
+    let policy = match args.get("RECEIPT_RETENTION") {
+      Some(p) => p.to_lowercase(),
+      None => return Ok(ReceiptRetention::default()),
+    };
+
+    match policy.as_str() {
+      "all" => Ok(ReceiptRetention::All),
+      "strongest" => Ok(ReceiptRetention::Strongest),
+      _ => match policy.strip_prefix("bounded:").map(|n| n.parse::<usize>()) {
+        Some(Ok(n)) if n > 0 => Ok(ReceiptRetention::Bounded(n)),
+        _ => Err(LedgerStoreError::LedgerError(
+          StorageError::InvalidReceiptRetention,
+        )),
+      },
+    }
+  }
+
+  pub fn apply(&self, receipts: &mut Receipts) {
+    match self {
+      ReceiptRetention::All => (),
+      ReceiptRetention::Strongest => receipts.retain_strongest(1),
+      ReceiptRetention::Bounded(n) => receipts.retain_strongest(*n),
+      ReceiptRetention::None => receipts.retain_strongest(0),
+    }
+  }
+}
+
+/// Policy deciding which old entries of a ledger keep their receipts. Every entry whose index is
+/// a multiple of the checkpoint interval is a checkpoint and keeps them, as do the entries near
+/// the tail and the last entry endorsed in each view; the other entries keep only their blocks and
+/// nonces, from which a verifier recomputes the metablocks from the checkpoint before them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ReceiptCompaction {
+  /// the distance between checkpoints, and from the tail to the entries that may be compacted;
+  /// 0 disables compaction
+  pub checkpoint_interval: usize,
+  /// entries stored less than this long ago (ms) are not compacted
+  pub min_age_ms: u64,
+}
+
+impl ReceiptCompaction {
+  /// parses the RECEIPT_COMPACTION store argument: "none", "every:<n>", or "every:<n>,age:<secs>"
+  pub fn from_args(args: &HashMap<String, String>) -> Result<Self, LedgerStoreError> {
+    let policy = match args.get("RECEIPT_COMPACTION") {
+      Some(p) => p.to_lowercase(),
+      None => return Ok(ReceiptCompaction::default()),
+    };
+    if policy == "none" {
+      return Ok(ReceiptCompaction::default());
+    }
+
+    let invalid = LedgerStoreError::LedgerError(StorageError::InvalidReceiptCompaction);
+    let (interval, age) = match policy.split_once(',') {
+      Some((interval, age)) => (interval, Some(age)),
+      None => (policy.as_str(), None),
+    };
+    let checkpoint_interval = match interval.strip_prefix("every:").map(|n| n.parse::<usize>()) {
+      Some(Ok(n)) if n > 0 => n,
+      _ => return Err(invalid),
+    };
+    let min_age_ms = match age.map(|a| a.strip_prefix("age:").map(|s| s.parse::<u64>())) {
+      None => 0,
+      Some(Some(Ok(secs))) => secs.saturating_mul(1000),
+      _ => return Err(invalid),
+    };
+    Ok(ReceiptCompaction {
+      checkpoint_interval,
+      min_age_ms,
+    })
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.checkpoint_interval > 0
+  }
+
+  pub fn is_checkpoint(&self, index: usize) -> bool {
+    !self.is_enabled() || index.is_multiple_of(self.checkpoint_interval)
+  }
+
+  // Returns whether the entry at `index` of a ledger whose tail is at `height` is old enough to be
+  // compacted; entries are appended in order, so no later entry is either if it is not
+  fn is_old(&self, index: usize, height: usize, entry: &LedgerEntry) -> bool {
+    if index.saturating_add(self.checkpoint_interval) > height {
+      return false;
+    }
+    match (self.min_age_ms, entry.get_timestamp()) {
+      (0, _) => true,
+      (min_age_ms, Some(timestamp)) => timestamp.saturating_add(min_age_ms) <= current_timestamp(),
+      (_, None) => false,
+    }
+  }
+}
+
+// Returns whether `next` was endorsed in every view that `entry` was endorsed in; otherwise
+// `entry` is the last entry of its ledger endorsed in one of its views and keeps its receipts,
+// which reads of the ledger as of that view return
+fn endorsed_in_same_views(entry: &LedgerEntry, next: &LedgerEntry) -> bool {
+  let next_views = next
+    .get_receipts()
+    .get()
+    .keys()
+    .map(|ex_meta_block| *ex_meta_block.get_view())
+    .collect::<Vec<NimbleDigest>>();
+  entry
+    .get_receipts()
+    .get()
+    .keys()
+    .all(|ex_meta_block| next_views.contains(ex_meta_block.get_view()))
+}
+
+/// A lease on a name, which coordinators that share a store take to elect a leader among them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lease {
+  /// the holder of the lease, such as the URI of a coordinator
+  pub holder: String,
+  /// when the lease expires (ms since the UNIX epoch)
+  pub expires_at: u64,
+}
+
+impl Lease {
+  pub fn is_held_by(&self, holder: &str) -> bool {
+    self.holder == holder && self.expires_at > current_timestamp()
+  }
+}
+
+/// The state of a client request ID in a store's table of recently seen append requests
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppendRequest {
+  /// the request ID was not seen or its record expired, and the caller now holds a claim on it
+  Claimed,
+  /// another append with the request ID holds an unexpired claim on it
+  InProgress,
+  /// an append with the request ID stored its block at this index of the ledger
+  Completed(usize),
 }
 
 #[async_trait]
@@ -58,6 +251,9 @@ pub trait LedgerStore {
     block: &Block,
     expected_height: usize,
   ) -> Result<(usize, Nonces), LedgerStoreError>;
+  /// merges `receipt` into the entry at `idx` of a ledger and applies the store's receipt
+  /// retention policy to the result; entries of the view ledger keep every receipt, since they
+  /// need receipts from both the finalized and the new view
   async fn attach_ledger_receipts(
     &self,
     handle: &Handle,
@@ -78,6 +274,25 @@ pub trait LedgerStore {
     handle: &Handle,
     idx: usize,
   ) -> Result<LedgerEntry, LedgerStoreError>;
+  /// returns the entries of a ledger at indices `start..end`, or fewer if the ledger ends first;
+  /// fails with `InvalidIndex` if the ledger has no entry at `start`. Backends that can read
+  /// several entries at once override it.
+  async fn read_ledger_range(
+    &self,
+    handle: &Handle,
+    start: usize,
+    end: usize,
+  ) -> Result<Vec<LedgerEntry>, LedgerStoreError> {
+    let mut entries = Vec::with_capacity(end.saturating_sub(start));
+    for idx in start..end {
+      match self.read_ledger_by_index(handle, idx).await {
+        Ok(entry) => entries.push(entry),
+        Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex)) if idx > start => break,
+        Err(error) => return Err(error),
+      }
+    }
+    Ok(entries)
+  }
   async fn append_view_ledger(
     &self,
     block: &Block,
@@ -91,19 +306,246 @@ pub trait LedgerStore {
   async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError>;
   async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError>;
 
+  /// returns the handles of every ledger in the store, in sorted order; the view ledger is not
+  /// included
+  async fn list_ledgers(&self) -> Result<Vec<Handle>, LedgerStoreError>;
+
+  /// returns up to `limit` ledgers whose handles sort after `after`, or from the first one if
+  /// it is None, in sorted order; the handle of the last one is the cursor for the next page.
+  /// Backends that can list a range of ledgers without listing them all override it.
+  async fn list_ledger_summaries(
+    &self,
+    after: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<LedgerSummary>, LedgerStoreError> {
+    let handles = self
+      .list_ledgers()
+      .await?
+      .into_iter()
+      .filter(|handle| after.is_none_or(|after| handle > after))
+      .take(limit)
+      .collect::<Vec<Handle>>();
+    let mut summaries = Vec::with_capacity(handles.len());
+    for handle in handles {
+      let (_tail, height) = self.read_ledger_tail(&handle).await?;
+      let genesis = self.read_ledger_by_index(&handle, 0).await?;
+      summaries.push(LedgerSummary {
+        handle,
+        height,
+        genesis,
+      });
+    }
+    Ok(summaries)
+  }
+
+  /// creates a ledger like `create_ledger` in `namespace`, whose handle carries the namespace.
+  /// Backends that partition their data by namespace override it; the others keep the ledger
+  /// apart from those of other namespaces by its handle only.
+  async fn create_ledger_in_namespace(
+    &self,
+    _namespace: &str,
+    handle: &Handle,
+    genesis_block: Block,
+  ) -> Result<(), LedgerStoreError> {
+    self.create_ledger(handle, genesis_block).await
+  }
+
+  /// returns the handles of the ledgers created in `namespace`, in sorted order
+  async fn list_ledgers_in_namespace(
+    &self,
+    _namespace: &str,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::NamespacesNotSupported,
+    ))
+  }
+
+  /// rewrites the receipts of the entries of a ledger from index `from` on so they conform to the
+  /// store's receipt retention policy, and returns the index from which the next compaction of
+  /// the ledger starts; attaching receipts applies the policy to the merged result, so entries
+  /// before that index keep conforming
+  async fn compact_ledger_receipts(
+    &self,
+    handle: &Handle,
+    from: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    let (_entry, height) = self.read_ledger_tail(handle).await?;
+    for idx in from..=height {
+      self
+        .attach_ledger_receipts(handle, idx, &Receipts::new())
+        .await?;
+    }
+    Ok(height + 1)
+  }
+
+  /// removes every receipt of the entry at `idx` of a ledger, leaving its block and nonces
+  async fn drop_ledger_receipts(&self, handle: &Handle, idx: usize)
+    -> Result<(), LedgerStoreError>;
+
+  /// drops the receipts of the entries of a ledger from index `from` on that `policy` compacts,
+  /// and returns the index of the first entry that is not old enough to be compacted yet, from
+  /// which the next compaction of the ledger starts
+  async fn compact_ledger_to_checkpoints(
+    &self,
+    handle: &Handle,
+    policy: &ReceiptCompaction,
+    from: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    if !policy.is_enabled() {
+      return Ok(from);
+    }
+    let (_tail, height) = self.read_ledger_tail(handle).await?;
+    let mut index = from;
+    while index < height {
+      let entry = self.read_ledger_by_index(handle, index).await?;
+      if !policy.is_old(index, height, &entry) {
+        break;
+      }
+      if !policy.is_checkpoint(index) && !entry.get_receipts().is_empty() {
+        let next = self.read_ledger_by_index(handle, index + 1).await?;
+        if endorsed_in_same_views(&entry, &next) {
+          self.drop_ledger_receipts(handle, index).await?;
+        }
+      }
+      index += 1;
+    }
+    Ok(index)
+  }
+
+  /// takes the lease `name` for `holder` until `duration_ms` from now if the lease is free,
+  /// expired, or already held by `holder`, and returns the lease as it stands afterwards; the
+  /// check and the update are atomic, so at most one holder has an unexpired lease
+  async fn acquire_lease(
+    &self,
+    _name: &str,
+    _holder: &str,
+    _duration_ms: u64,
+  ) -> Result<Lease, LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::LeasesNotSupported,
+    ))
+  }
+
+  /// claims `request_id` for an append to a ledger until `duration_ms` from now, unless the
+  /// table holds an unexpired record of it, whose state is returned instead; the check and the
+  /// claim are atomic, so concurrent retries of an append cannot both append
+  async fn claim_append_request(
+    &self,
+    _handle: &Handle,
+    _request_id: &[u8],
+    _duration_ms: u64,
+  ) -> Result<AppendRequest, LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::RequestIdsNotSupported,
+    ))
+  }
+
+  /// records that the append that claimed `request_id` stored its block at `idx`, and keeps the
+  /// record until `duration_ms` from now
+  async fn complete_append_request(
+    &self,
+    _handle: &Handle,
+    _request_id: &[u8],
+    _idx: usize,
+    _duration_ms: u64,
+  ) -> Result<(), LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::RequestIdsNotSupported,
+    ))
+  }
+
+  /// drops the claim on `request_id` of an append that failed, so that a retry can claim it
+  async fn release_append_request(
+    &self,
+    _handle: &Handle,
+    _request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::RequestIdsNotSupported,
+    ))
+  }
+
+  /// binds `label` to the ledger `handle_bytes` unless the label is bound already, and returns
+  /// whether it bound the label; fails with `DuplicateKey` if the label is bound to another
+  /// ledger. The check and the binding are atomic.
+  async fn add_ledger_label(
+    &self,
+    _label: &str,
+    _handle_bytes: &[u8],
+  ) -> Result<bool, LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::LabelsNotSupported,
+    ))
+  }
+
+  /// unbinds `label` from the ledger `handle_bytes`, if it is bound to it
+  async fn remove_ledger_label(
+    &self,
+    _label: &str,
+    _handle_bytes: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::LabelsNotSupported,
+    ))
+  }
+
+  /// returns the handle of the ledger that `label` is bound to; fails with `KeyDoesNotExist` if
+  /// the label is not bound
+  async fn find_ledger_by_label(&self, _label: &str) -> Result<Vec<u8>, LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::LabelsNotSupported,
+    ))
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError>; // only used for testing
 }
 
+/// A ledger store whose backend is chosen at runtime
+pub type BoxedLedgerStore = Box<dyn LedgerStore + Send + Sync>;
+
+/// The backend names accepted by `open_ledger_store`
+pub const LEDGER_STORE_TYPES: [&str; 4] = ["memory", "filestore", "table", "mongodb_cosmos"];
+
+/// Opens the ledger store backend named `store_type`, passing it the backend-specific `args`
+pub async fn open_ledger_store(
+  store_type: &str,
+  args: &HashMap<String, String>,
+) -> Result<BoxedLedgerStore, LedgerStoreError> {
+  let store: BoxedLedgerStore = match store_type {
+    "memory" => Box::new(in_memory::InMemoryLedgerStore::from_args(args)?),
+    "filestore" => Box::new(filestore::FileStore::new(args).await?),
+    "table" => Box::new(azure_table::TableLedgerStore::new(args).await?),
+    "mongodb_cosmos" => Box::new(mongodb_cosmos::MongoCosmosLedgerStore::new(args).await?),
+    _ => {
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::UnknownStoreType,
+      ))
+    },
+  };
+  Ok(store)
+}
+
 #[cfg(test)]
 mod tests {
   use crate::ledger::{
-    azure_table::TableLedgerStore, filestore::FileStore, in_memory::InMemoryLedgerStore,
-    mongodb_cosmos::MongoCosmosLedgerStore, LedgerStore,
+    azure_table::TableLedgerStore,
+    filestore::FileStore,
+    in_memory::InMemoryLedgerStore,
+    mongodb_cosmos::{MongoCosmosLedgerStore, ReadConsistency},
+    open_ledger_store,
+    verify::{audit_store, replay_view_ledger, verify_ledger, IssueKind},
+    AppendRequest, LedgerStore, LedgerStoreError, ReceiptCompaction, ReceiptRetention,
+    StorageError,
   };
-  use ledger::{Block, CustomSerde, NimbleHashTrait};
-  use std::collections::HashMap;
+  use ledger::{
+    namespace::namespaced_handle,
+    signature::{PrivateKey, PrivateKeyTrait},
+    Block, CustomSerde, IdSig, MetaBlock, NimbleDigest, NimbleHashTrait, Receipt, Receipts,
+    VerifierState,
+  };
+  use std::{collections::HashMap, str::FromStr};
 
-  pub async fn check_store_creation_and_operations(state: &dyn LedgerStore) {
+  pub async fn check_store_creation_and_operations(state: &(dyn LedgerStore + Send + Sync)) {
     let initial_value: Vec<u8> = vec![
       1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
       1, 2,
@@ -144,6 +586,31 @@ mod tests {
 
     let data_at_index = res.unwrap();
     assert_eq!(data_at_index.block.to_bytes(), initial_value);
+    assert!(data_at_index.get_timestamp().is_some());
+
+    // a range past the tail ends at the tail, but must start within the ledger
+    let res = state.read_ledger_range(&handle, 0, 5).await;
+    let entries = res.unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].get_block().to_bytes(), new_value_appended);
+    let res = state.read_ledger_range(&handle, 1, 2).await;
+    assert_eq!(res.unwrap().len(), 1);
+    let res = state.read_ledger_range(&handle, 2, 5).await;
+    assert!(res.is_err());
+
+    let res = state.list_ledgers().await;
+    assert!(res.is_ok());
+    assert!(res.unwrap().contains(&handle));
+
+    let res = state.list_ledger_summaries(None, usize::MAX).await;
+    let summary = res
+      .unwrap()
+      .into_iter()
+      .find(|summary| summary.handle == handle)
+      .unwrap();
+    assert_eq!(summary.height, 1);
+    assert_eq!(summary.genesis.get_block().to_bytes(), initial_value);
+    assert!(summary.genesis.get_timestamp().is_some());
 
     let res = state.reset_store().await;
     assert!(res.is_ok());
@@ -155,6 +622,432 @@ mod tests {
     check_store_creation_and_operations(&state).await;
   }
 
+  #[tokio::test]
+  pub async fn check_in_memory_leases() {
+    let state = InMemoryLedgerStore::new();
+    let lease = state.acquire_lease("leader", "a", 60_000).await.unwrap();
+    assert!(lease.is_held_by("a"));
+
+    // another holder cannot take an unexpired lease, but the holder can renew it
+    let lease = state.acquire_lease("leader", "b", 60_000).await.unwrap();
+    assert!(lease.is_held_by("a") && !lease.is_held_by("b"));
+    let renewed = state.acquire_lease("leader", "a", 120_000).await.unwrap();
+    assert!(renewed.expires_at > lease.expires_at);
+
+    // once the lease expires, another holder takes it
+    state.acquire_lease("other", "a", 0).await.unwrap();
+    let lease = state.acquire_lease("other", "b", 60_000).await.unwrap();
+    assert!(lease.is_held_by("b"));
+  }
+
+  #[tokio::test]
+  pub async fn check_in_memory_append_requests() {
+    let state = InMemoryLedgerStore::new();
+    let handle = Block::new(&[1, 2, 3]).hash();
+    let other = Block::new(&[4, 5, 6]).hash();
+
+    // a retry sees the claim of the first attempt, and then the index it completed with
+    let claim = state.claim_append_request(&handle, b"req", 60_000).await;
+    assert_eq!(claim.unwrap(), AppendRequest::Claimed);
+    let claim = state.claim_append_request(&handle, b"req", 60_000).await;
+    assert_eq!(claim.unwrap(), AppendRequest::InProgress);
+    let res = state
+      .complete_append_request(&handle, b"req", 7, 60_000)
+      .await;
+    assert!(res.is_ok());
+    let claim = state.claim_append_request(&handle, b"req", 60_000).await;
+    assert_eq!(claim.unwrap(), AppendRequest::Completed(7));
+
+    // request IDs are scoped to a ledger
+    let claim = state.claim_append_request(&other, b"req", 60_000).await;
+    assert_eq!(claim.unwrap(), AppendRequest::Claimed);
+
+    // a released or expired claim can be taken again
+    let res = state.release_append_request(&other, b"req").await;
+    assert!(res.is_ok());
+    let claim = state.claim_append_request(&other, b"req", 0).await;
+    assert_eq!(claim.unwrap(), AppendRequest::Claimed);
+    let claim = state.claim_append_request(&other, b"req", 60_000).await;
+    assert_eq!(claim.unwrap(), AppendRequest::Claimed);
+  }
+
+  #[tokio::test]
+  pub async fn check_in_memory_ledger_labels() {
+    let state = InMemoryLedgerStore::new();
+    let res = state.find_ledger_by_label("orders").await;
+    assert!(matches!(
+      res,
+      Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+    ));
+
+    // a label is bound to one ledger, which may bind it again
+    assert!(state.add_ledger_label("orders", b"handle").await.unwrap());
+    assert!(!state.add_ledger_label("orders", b"handle").await.unwrap());
+    let res = state.add_ledger_label("orders", b"other").await;
+    assert!(matches!(
+      res,
+      Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey))
+    ));
+    assert_eq!(
+      state.find_ledger_by_label("orders").await.unwrap(),
+      b"handle"
+    );
+
+    // only the ledger it is bound to unbinds a label
+    assert!(state.remove_ledger_label("orders", b"other").await.is_ok());
+    assert!(state.find_ledger_by_label("orders").await.is_ok());
+    assert!(state.remove_ledger_label("orders", b"handle").await.is_ok());
+    assert!(state.find_ledger_by_label("orders").await.is_err());
+    assert!(state.add_ledger_label("orders", b"other").await.unwrap());
+  }
+
+  #[tokio::test]
+  pub async fn check_in_memory_namespaces() {
+    let state = InMemoryLedgerStore::new();
+    let acme = NimbleDigest::digest(&namespaced_handle("acme", b"orders"));
+    let globex = NimbleDigest::digest(&namespaced_handle("globex", b"orders"));
+    let plain = NimbleDigest::digest(b"orders");
+    for (namespace, handle) in [("acme", &acme), ("globex", &globex)] {
+      state
+        .create_ledger_in_namespace(namespace, handle, Block::new(namespace.as_bytes()))
+        .await
+        .unwrap();
+    }
+    state
+      .create_ledger(&plain, Block::new(b"plain"))
+      .await
+      .unwrap();
+
+    // every namespace lists its own ledgers, while the store lists them all
+    assert_eq!(
+      state.list_ledgers_in_namespace("acme").await.unwrap(),
+      vec![acme]
+    );
+    assert!(state
+      .list_ledgers_in_namespace("initech")
+      .await
+      .unwrap()
+      .is_empty());
+    assert_eq!(state.list_ledgers().await.unwrap().len(), 3);
+    let (tail, _height) = state.read_ledger_tail(&globex).await.unwrap();
+    assert_eq!(tail.get_block().to_bytes(), b"globex");
+  }
+
+  #[tokio::test]
+  pub async fn check_offline_verification() {
+    let state = InMemoryLedgerStore::new();
+    let handle = NimbleDigest::digest("handle".as_bytes());
+    state
+      .create_ledger(&handle, Block::new(&[1, 2, 3]))
+      .await
+      .unwrap();
+    state
+      .append_ledger(&handle, &Block::new(&[4, 5, 6]), 1)
+      .await
+      .unwrap();
+    assert_eq!(state.list_ledgers().await.unwrap(), vec![handle]);
+
+    // a store without an endorsed view ledger cannot be verified
+    assert!(replay_view_ledger(&state, &[]).await.is_err());
+
+    // entries without receipts are counted rather than checked
+    let report = verify_ledger(&state, &VerifierState::new(), &handle)
+      .await
+      .unwrap();
+    assert_eq!(report.height, 1);
+    assert_eq!(report.endorsed, 0);
+    assert_eq!(report.unendorsed, 2);
+
+    let other = NimbleDigest::digest("other".as_bytes());
+    assert!(verify_ledger(&state, &VerifierState::new(), &other)
+      .await
+      .is_err());
+  }
+
+  #[tokio::test]
+  pub async fn check_offline_audit() {
+    let state = InMemoryLedgerStore::new();
+    let genesis_block = Block::new(&[1, 2, 3]);
+    let handle = genesis_block.hash();
+    state
+      .create_ledger(&handle, genesis_block.clone())
+      .await
+      .unwrap();
+    for index in 1..=2 {
+      state
+        .append_ledger(&handle, &Block::new(&[index as u8]), index)
+        .await
+        .unwrap();
+    }
+
+    // receipts from a view that the view ledger does not hold, on the genesis entry and the tail
+    let sk = PrivateKey::new();
+    for index in [0, 2] {
+      let entry = state.read_ledger_by_index(&handle, index).await.unwrap();
+      let metablock = MetaBlock::new(
+        &NimbleDigest::digest(&[index as u8]),
+        &entry.get_block().hash(),
+        index,
+      );
+      let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+      let mut receipts = Receipts::new();
+      receipts.add(&Receipt::new(
+        NimbleDigest::digest("view".as_bytes()),
+        metablock,
+        IdSig::new(sk.get_public_key().unwrap(), sig),
+      ));
+      state
+        .attach_ledger_receipts(&handle, index, &receipts)
+        .await
+        .unwrap();
+    }
+
+    // the audit goes on past the view ledger and the first bad entry, and reports each issue
+    let report = audit_store(&state, &[], None).await;
+    assert!(!report.passed());
+    let issues = report
+      .issues
+      .iter()
+      .map(|issue| (issue.ledger.is_some(), issue.index, issue.kind))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      issues,
+      vec![
+        (false, None, IssueKind::EmptyViewLedger),
+        (true, Some(0), IssueKind::InvalidReceipts),
+        (true, Some(2), IssueKind::InvalidReceipts),
+      ]
+    );
+    assert_eq!(report.ledgers.len(), 1);
+    assert_eq!(report.ledgers[0].report.height, 2);
+    assert_eq!(report.ledgers[0].report.unendorsed, 1);
+    assert_eq!(report.ledgers[0].issues, 2);
+
+    // the first issue is what verifying the ledger reports
+    assert_eq!(
+      verify_ledger(&state, &VerifierState::new(), &handle).await,
+      Err(report.issues[1].detail.clone())
+    );
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["issues"][0]["kind"], "empty_view_ledger");
+    assert_eq!(json["issues"][1]["index"], 0);
+    assert_eq!(json["ledgers"][0]["height"], 2);
+  }
+
+  #[tokio::test]
+  pub async fn check_open_ledger_store() {
+    let args = HashMap::<String, String>::new();
+    let state = open_ledger_store("memory", &args).await.unwrap();
+    check_store_creation_and_operations(state.as_ref()).await;
+
+    assert!(open_ledger_store("unknown", &args).await.is_err());
+  }
+
+  #[test]
+  pub fn check_receipt_retention_parsing() {
+    let mut args = HashMap::<String, String>::new();
+    assert_eq!(
+      ReceiptRetention::from_args(&args).unwrap(),
+      ReceiptRetention::All
+    );
+    args.insert(String::from("RECEIPT_RETENTION"), String::from("strongest"));
+    assert_eq!(
+      ReceiptRetention::from_args(&args).unwrap(),
+      ReceiptRetention::Strongest
+    );
+    args.insert(String::from("RECEIPT_RETENTION"), String::from("bounded:3"));
+    assert_eq!(
+      ReceiptRetention::from_args(&args).unwrap(),
+      ReceiptRetention::Bounded(3)
+    );
+    args.insert(String::from("RECEIPT_RETENTION"), String::from("bounded:0"));
+    assert!(ReceiptRetention::from_args(&args).is_err());
+  }
+
+  #[tokio::test]
+  pub async fn check_in_memory_receipt_compaction() {
+    let genesis_block = Block::new(&[1, 2, 3]);
+    let handle = genesis_block.hash();
+
+    let state = InMemoryLedgerStore::new();
+    state
+      .create_ledger(&handle, genesis_block.clone())
+      .await
+      .unwrap();
+
+    // attach a receipt from each of two views
+    let metablock = MetaBlock::genesis(&genesis_block.hash());
+    for view in ["view 1", "view 2"] {
+      let sk = PrivateKey::new();
+      let sig = sk.sign(&metablock.hash().to_bytes()).unwrap();
+      let id_sig = IdSig::new(sk.get_public_key().unwrap(), sig);
+      let mut receipts = Receipts::new();
+      receipts.add(&Receipt::new(
+        NimbleDigest::digest(view.as_bytes()),
+        metablock.clone(),
+        id_sig,
+      ));
+      state
+        .attach_ledger_receipts(&handle, 0, &receipts)
+        .await
+        .unwrap();
+    }
+
+    let entry = state.read_ledger_by_index(&handle, 0).await.unwrap();
+    assert_eq!(entry.get_receipts().get().len(), 2);
+
+    let state = state.with_receipt_retention(ReceiptRetention::Strongest);
+    assert_eq!(state.compact_ledger_receipts(&handle, 0).await.unwrap(), 1);
+    let entry = state.read_ledger_by_index(&handle, 0).await.unwrap();
+    assert_eq!(entry.get_receipts().get().len(), 1);
+  }
+
+  #[test]
+  pub fn check_receipt_compaction_parsing() {
+    let mut args = HashMap::<String, String>::new();
+    assert!(!ReceiptCompaction::from_args(&args).unwrap().is_enabled());
+    args.insert(
+      String::from("RECEIPT_COMPACTION"),
+      String::from("every:100"),
+    );
+    assert_eq!(
+      ReceiptCompaction::from_args(&args).unwrap(),
+      ReceiptCompaction {
+        checkpoint_interval: 100,
+        min_age_ms: 0
+      }
+    );
+    args.insert(
+      String::from("RECEIPT_COMPACTION"),
+      String::from("every:100,age:60"),
+    );
+    assert_eq!(
+      ReceiptCompaction::from_args(&args).unwrap().min_age_ms,
+      60_000
+    );
+    for invalid in ["every:0", "every:x", "every:100,60", "age:60"] {
+      args.insert(String::from("RECEIPT_COMPACTION"), String::from(invalid));
+      assert!(ReceiptCompaction::from_args(&args).is_err());
+    }
+  }
+
+  #[tokio::test]
+  pub async fn check_in_memory_checkpoint_compaction() {
+    let genesis_block = Block::new(&[1, 2, 3]);
+    let handle = genesis_block.hash();
+    let state = InMemoryLedgerStore::new();
+    state
+      .create_ledger(&handle, genesis_block.clone())
+      .await
+      .unwrap();
+    for height in 1..=8 {
+      state
+        .append_ledger(&handle, &Block::new(&[height as u8]), height)
+        .await
+        .unwrap();
+    }
+
+    // entries 0 to 5 are endorsed in one view and the rest in the next
+    let metablock = MetaBlock::genesis(&genesis_block.hash());
+    let sk = PrivateKey::new();
+    let id_sig = IdSig::new(
+      sk.get_public_key().unwrap(),
+      sk.sign(&metablock.hash().to_bytes()).unwrap(),
+    );
+    for index in 0..=8 {
+      let view = if index <= 5 { "view 1" } else { "view 2" };
+      let mut receipts = Receipts::new();
+      receipts.add(&Receipt::new(
+        NimbleDigest::digest(view.as_bytes()),
+        metablock.clone(),
+        id_sig.clone(),
+      ));
+      state
+        .attach_ledger_receipts(&handle, index, &receipts)
+        .await
+        .unwrap();
+    }
+
+    // checkpoints every 3 entries; the last 3 entries and the last entry of the first view keep
+    // their receipts too
+    let policy = ReceiptCompaction {
+      checkpoint_interval: 3,
+      min_age_ms: 0,
+    };
+    let next = state
+      .compact_ledger_to_checkpoints(&handle, &policy, 0)
+      .await
+      .unwrap();
+    assert_eq!(next, 6);
+    for index in 0..=8 {
+      let entry = state.read_ledger_by_index(&handle, index).await.unwrap();
+      let compacted = [1, 2, 4].contains(&index);
+      assert_eq!(
+        entry.get_receipts().is_empty(),
+        compacted,
+        "entry {}",
+        index
+      );
+    }
+
+    // entries are not compacted before they are old enough
+    let policy = ReceiptCompaction {
+      checkpoint_interval: 3,
+      min_age_ms: 60_000,
+    };
+    let next = state
+      .compact_ledger_to_checkpoints(&handle, &policy, 0)
+      .await
+      .unwrap();
+    assert_eq!(next, 0);
+  }
+
+  #[tokio::test]
+  pub async fn check_in_memory_replication() {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let primary = InMemoryLedgerStore::new().with_replication(sender);
+    check_store_creation_and_operations(&primary).await;
+    primary
+      .append_view_ledger(&Block::new(&[7, 7, 7]), 1)
+      .await
+      .unwrap();
+
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+      events.push(event);
+    }
+    assert_eq!(events.len(), 3);
+
+    // a standby that joins late catches up from a snapshot; replaying events it already
+    // has must not change it
+    let standby = InMemoryLedgerStore::new();
+    for event in primary.snapshot().unwrap().iter().chain(events.iter()) {
+      standby.apply_replication_event(event).unwrap();
+    }
+    let handle = primary.get_handles()[0];
+    assert_eq!(standby.get_handles(), vec![handle]);
+    for idx in 0..2 {
+      let expected = primary.read_ledger_by_index(&handle, idx).await.unwrap();
+      let actual = standby.read_ledger_by_index(&handle, idx).await.unwrap();
+      assert_eq!(
+        actual.get_block().to_bytes(),
+        expected.get_block().to_bytes()
+      );
+      assert_eq!(actual.get_timestamp(), expected.get_timestamp());
+    }
+    let (view_tail, view_height) = standby.read_view_ledger_tail().await.unwrap();
+    assert_eq!(view_height, 1);
+    assert_eq!(view_tail.get_block().to_bytes(), vec![7, 7, 7]);
+
+    // a gap or a different block at an existing index is a conflict
+    let mut conflicting = events[1].clone();
+    conflicting.index = 5;
+    assert!(standby.apply_replication_event(&conflicting).is_err());
+    conflicting.index = 0;
+    assert!(standby.apply_replication_event(&conflicting).is_err());
+  }
+
   #[tokio::test]
   pub async fn check_mongo_cosmos_store() {
     if std::env::var_os("COSMOS_URL").is_none() {
@@ -174,10 +1067,24 @@ mod tests {
     check_store_creation_and_operations(&state).await;
   }
 
+  #[test]
+  pub fn check_read_consistency_parsing() {
+    assert_eq!(
+      ReadConsistency::from_str("strong").unwrap(),
+      ReadConsistency::Strong
+    );
+    assert_eq!(
+      ReadConsistency::from_str("Eventual").unwrap(),
+      ReadConsistency::Eventual
+    );
+    assert!(ReadConsistency::from_str("bounded").is_err());
+  }
+
   #[tokio::test]
   pub async fn check_azure_table_store() {
-    if std::env::var_os("STORAGE_ACCOUNT").is_none()
-      || std::env::var_os("STORAGE_MASTER_KEY").is_none()
+    let has_access_key = std::env::var_os("STORAGE_ACCOUNT").is_some()
+      && std::env::var_os("STORAGE_MASTER_KEY").is_some();
+    if (!has_access_key && std::env::var_os("STORAGE_CONNECTION_STRING").is_none())
       || std::env::var_os("LEDGER_STORE").is_none()
     {
       // The right env variables are not available so let's skip tests
@@ -190,21 +1097,15 @@ mod tests {
     }
 
     let mut args = HashMap::<String, String>::new();
-    args.insert(
-      String::from("STORAGE_ACCOUNT"),
-      std::env::var_os("STORAGE_ACCOUNT")
-        .unwrap()
-        .into_string()
-        .unwrap(),
-    );
-
-    args.insert(
-      String::from("STORAGE_MASTER_KEY"),
-      std::env::var_os("STORAGE_MASTER_KEY")
-        .unwrap()
-        .into_string()
-        .unwrap(),
-    );
+    for name in [
+      "STORAGE_ACCOUNT",
+      "STORAGE_MASTER_KEY",
+      "STORAGE_CONNECTION_STRING",
+    ] {
+      if let Ok(value) = std::env::var(name) {
+        args.insert(String::from(name), value);
+      }
+    }
 
     let state = TableLedgerStore::new(&args).await.unwrap();
     check_store_creation_and_operations(&state).await;
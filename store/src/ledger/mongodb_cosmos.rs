@@ -1,6 +1,9 @@
 use crate::{
   errors::{LedgerStoreError, StorageError},
-  ledger::{LedgerEntry, LedgerStore},
+  ledger::{
+    current_timestamp, AppendRequest, Lease, LedgerEntry, LedgerStore, LedgerSummary,
+    ReceiptRetention,
+  },
 };
 use async_trait::async_trait;
 use bincode;
@@ -9,13 +12,18 @@ use ledger::{Block, CustomSerde, Handle, NimbleDigest, Nonce, Nonces, Receipts};
 use mongodb::{
   bson::{doc, spec::BinarySubtype, Binary},
   error::WriteFailure::WriteError,
-  Client, Collection,
+  options::{
+    FindOneAndUpdateOptions, FindOneOptions, FindOptions, Hint, IndexOptions, ReadConcern,
+    ReadPreference, ReadPreferenceOptions, ReturnDocument, SelectionCriteria,
+  },
+  Client, Collection, IndexModel,
 };
 use serde::{Deserialize, Serialize};
 use std::{
   collections::HashMap,
   convert::TryFrom,
   fmt::Debug,
+  str::FromStr,
   sync::{Arc, RwLock},
 };
 
@@ -53,7 +61,8 @@ macro_rules! with_retry {
               if cmd_err.code == WRITE_CONFLICT_CODE {
                 continue;
               } else if cmd_err.code == REQUEST_RATE_TOO_HIGH_CODE {
-                std::thread::sleep(std::time::Duration::from_millis(RETRY_SLEEP));
+                // yield the worker thread while throttled, so other requests keep running
+                tokio::time::sleep(std::time::Duration::from_millis(RETRY_SLEEP)).await;
                 continue;
               } else {
                 return Err(LedgerStoreError::MongoDBError(mongodb_error));
@@ -112,11 +121,175 @@ struct SerializedLedgerEntry {
   pub receipts: Vec<u8>,
 }
 
+// A lease lives in its own collection, whose name is not the hex encoding of a handle, so
+// `list_ledgers` never mistakes it for a ledger
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBLease {
+  #[serde(rename = "_id")]
+  name: String,
+  holder: String,
+  expires_at: i64, // ms since the UNIX epoch
+}
+
+// Append requests live in their own collection too, keyed by the hex encoding of the handle
+// and of the request ID
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBAppendRequest {
+  #[serde(rename = "_id")]
+  key: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  index: Option<i64>, // None while the append is in progress
+  expires_at: i64, // ms since the UNIX epoch
+}
+
+// A ledger label lives in a collection of its own too, keyed by the label
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBLedgerLabel {
+  #[serde(rename = "_id")]
+  label: String,
+  handle: Binary, // the handle the label is bound to
+}
+
+// The namespace of a ledger created in one lives in a collection of its own too, keyed by the
+// hex encoding of the handle, so the store finds the collection that holds the ledger
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBLedgerNamespace {
+  #[serde(rename = "_id")]
+  handle: String,
+  namespace: String,
+}
+
+// A ledger lives in a collection named by the hex encoding of its handle, prefixed with its
+// namespace and a dot if it was created in one, so the ledgers of a namespace share a prefix
+fn ledger_collection_name(namespace: Option<&str>, handle: &Handle) -> String {
+  match namespace {
+    Some(namespace) => format!("{}.{}", namespace, hex::encode(handle.to_bytes())),
+    None => hex::encode(handle.to_bytes()),
+  }
+}
+
+// Returns the handle of the ledger that the collection `name` holds, if it holds one
+fn parse_ledger_collection_name(name: &str) -> Option<Handle> {
+  let encoded = name
+    .rsplit_once('.')
+    .map_or(name, |(_namespace, encoded)| encoded);
+  NimbleDigest::from_bytes(&hex::decode(encoded).ok()?).ok()
+}
+
+fn append_request_key(handle: &Handle, request_id: &[u8]) -> String {
+  format!(
+    "{}:{}",
+    hex::encode(handle.to_bytes()),
+    hex::encode(request_id)
+  )
+}
+
+fn is_duplicate_key_error(error: &mongodb::error::Error) -> bool {
+  match error.kind.as_ref() {
+    mongodb::error::ErrorKind::Command(cmd_err) => cmd_err.code == DUPLICATE_KEY_CODE,
+    mongodb::error::ErrorKind::Write(WriteError(write_error)) => {
+      write_error.code == DUPLICATE_KEY_CODE
+    },
+    _ => false,
+  }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct DBEntry {
   #[serde(rename = "_id")]
   index: i64,
   value: Binary, // SerializedLedgerEntry
+  // untrusted wall-clock time at which the coordinator stored the entry (ms since the UNIX epoch)
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  timestamp: Option<i64>,
+  // The index on `_id` cannot cover any other field, so each entry repeats its height (in the
+  // view ledger, the height of the view) in a field that the height index covers. Reads that
+  // fetch the entry do not project it.
+  #[serde(default)]
+  height: i64,
+}
+
+// The projection of an entry that the height index covers
+#[derive(Deserialize, Debug)]
+struct DBHeight {
+  height: i64,
+}
+
+/// Consistency level requested from Cosmos DB for a class of read operations.
+/// Weaker levels cost fewer RUs but may return stale entries, which is safe
+/// whenever the caller verifies the returned receipts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReadConsistency {
+  /// reads are served by the primary with a majority read concern
+  Strong,
+  /// reads may be served by the nearest replica with a local read concern
+  Eventual,
+}
+
+impl FromStr for ReadConsistency {
+  type Err = LedgerStoreError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "strong" => Ok(ReadConsistency::Strong),
+      "eventual" => Ok(ReadConsistency::Eventual),
+      _ => Err(LedgerStoreError::LedgerError(
+        StorageError::InvalidReadConsistency,
+      )),
+    }
+  }
+}
+
+impl ReadConsistency {
+  fn read_concern(&self) -> ReadConcern {
+    match self {
+      ReadConsistency::Strong => ReadConcern::majority(),
+      ReadConsistency::Eventual => ReadConcern::local(),
+    }
+  }
+
+  fn selection_criteria(&self) -> SelectionCriteria {
+    match self {
+      ReadConsistency::Strong => SelectionCriteria::ReadPreference(ReadPreference::Primary),
+      ReadConsistency::Eventual => SelectionCriteria::ReadPreference(ReadPreference::Nearest {
+        options: ReadPreferenceOptions::default(),
+      }),
+    }
+  }
+
+  // Point reads of an entry are pinned to the index on `_id` and only project the fields we
+  // deserialize, so the server never falls back to scanning the collection. They need the entry's
+  // value, which no index holds, so they cannot be covered.
+  fn find_one_options(&self) -> FindOneOptions {
+    FindOneOptions::builder()
+      .read_concern(self.read_concern())
+      .selection_criteria(self.selection_criteria())
+      .hint(Hint::Name(LEDGER_INDEX_NAME.to_string()))
+      .projection(doc! {"_id": 1, "value": 1, "timestamp": 1})
+      .build()
+  }
+
+  // Reads of a ledger's height are answered from the height index alone: the projection only
+  // holds the indexed field, so the server never fetches an entry
+  fn height_options(&self) -> FindOneOptions {
+    FindOneOptions::builder()
+      .read_concern(self.read_concern())
+      .selection_criteria(self.selection_criteria())
+      .hint(Hint::Name(HEIGHT_INDEX_NAME.to_string()))
+      .sort(doc! {"height": -1})
+      .projection(doc! {"_id": 0, "height": 1})
+      .build()
+  }
+}
+
+fn parse_read_consistency(
+  args: &HashMap<String, String>,
+  key: &str,
+) -> Result<ReadConsistency, LedgerStoreError> {
+  match args.get(key) {
+    Some(level) => ReadConsistency::from_str(level),
+    None => Ok(ReadConsistency::Strong),
+  }
 }
 
 #[derive(Debug)]
@@ -125,6 +298,10 @@ pub struct MongoCosmosLedgerStore {
   view_handle: Handle,
   dbname: String,
   cache: CacheMap,
+  collections: Arc<RwLock<HashMap<Handle, String>>>, // the collection that holds each ledger
+  tail_reads: ReadConsistency,
+  historical_reads: ReadConsistency,
+  receipt_retention: ReceiptRetention,
 }
 
 impl MongoCosmosLedgerStore {
@@ -144,6 +321,12 @@ impl MongoCosmosLedgerStore {
       nimble_db_name = args["NIMBLE_DB"].clone();
     }
 
+    // Reads of the tail and reads by index can be served at different consistency levels;
+    // both default to strong reads
+    let tail_reads = parse_read_consistency(args, "TAIL_READ_CONSISTENCY")?;
+    let historical_reads = parse_read_consistency(args, "HISTORICAL_READ_CONSISTENCY")?;
+    let receipt_retention = ReceiptRetention::from_args(args)?;
+
     let res = Client::with_uri_str(&conn_string).await;
     if res.is_err() {
       eprintln!("Connection with cosmosdb failed");
@@ -167,6 +350,10 @@ impl MongoCosmosLedgerStore {
       dbname: nimble_db_name.clone(),
       view_handle,
       cache,
+      collections: Arc::new(RwLock::new(HashMap::new())),
+      tail_reads,
+      historical_reads,
+      receipt_retention,
     };
 
     // Check if the view ledger exists, if not, create a new one
@@ -191,12 +378,14 @@ impl MongoCosmosLedgerStore {
           let tail_entry = DBEntry {
             index: 0_i64,
             value: bson_entry.clone(),
+            timestamp: None,
+            height: 0_i64,
           };
 
           ledger_store
             .client
             .database(&nimble_db_name)
-            .collection::<DBEntry>(&hex::encode(&view_handle.to_bytes()))
+            .collection::<DBEntry>(&hex::encode(view_handle.to_bytes()))
             .insert_one(tail_entry, None)
             .await?;
 
@@ -211,12 +400,125 @@ impl MongoCosmosLedgerStore {
       let ledger = ledger_store
         .client
         .database(&nimble_db_name)
-        .collection::<DBEntry>(&hex::encode(&view_handle.to_bytes()));
+        .collection::<DBEntry>(&hex::encode(view_handle.to_bytes()));
       fix_cached_height(&ledger_store.view_handle, &ledger_store.cache, &ledger).await?;
     }
 
+    // Make sure the view ledger and every other ledger can be queried by height without scanning
+    // them; ledgers created later get the index when they are created
+    let ledger = ledger_store
+      .client
+      .database(&nimble_db_name)
+      .collection::<DBEntry>(&hex::encode(view_handle.to_bytes()));
+    ensure_ledger_index(&ledger).await?;
+    for handle in ledger_store.list_ledger_collections(None).await? {
+      let ledger = ledger_store.ledger_collection(&handle).await?;
+      ensure_ledger_index(&ledger).await?;
+    }
+
     Ok(ledger_store)
   }
+
+  fn remember_collection(&self, handle: &Handle, name: &str) {
+    if let Ok(mut collections) = self.collections.write() {
+      collections.insert(*handle, name.to_string());
+    }
+  }
+
+  // Returns the collection that holds the ledger `handle`, looking up the ledger's namespace the
+  // first time. A ledger outside namespaces is only remembered once its collection exists, so
+  // a ledger that another coordinator creates in a namespace later is still found.
+  async fn ledger_collection(
+    &self,
+    handle: &Handle,
+  ) -> Result<Collection<DBEntry>, LedgerStoreError> {
+    let database = self.client.database(&self.dbname);
+    if *handle == self.view_handle {
+      return Ok(database.collection::<DBEntry>(&ledger_collection_name(None, handle)));
+    }
+    if let Ok(collections) = self.collections.read() {
+      if let Some(name) = collections.get(handle) {
+        return Ok(database.collection::<DBEntry>(name));
+      }
+    }
+
+    let namespaces = database.collection::<DBLedgerNamespace>(LEDGER_NAMESPACES_COLLECTION);
+    let key = hex::encode(handle.to_bytes());
+    let name = match namespaces.find_one(doc! {"_id": &key}, None).await? {
+      Some(entry) => {
+        let name = ledger_collection_name(Some(&entry.namespace), handle);
+        self.remember_collection(handle, &name);
+        name
+      },
+      None => {
+        let name = ledger_collection_name(None, handle);
+        let existing = database.list_collection_names(doc! {"name": &name}).await?;
+        if !existing.is_empty() {
+          self.remember_collection(handle, &name);
+        }
+        name
+      },
+    };
+    Ok(database.collection::<DBEntry>(&name))
+  }
+
+  // Returns the handles of the ledgers whose collections `filter` matches, in sorted order
+  async fn list_ledger_collections(
+    &self,
+    filter: Option<mongodb::bson::Document>,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    let names = self
+      .client
+      .database(&self.dbname)
+      .list_collection_names(filter)
+      .await?;
+
+    let mut handles = names
+      .iter()
+      .filter_map(|name| parse_ledger_collection_name(name))
+      .filter(|handle| *handle != self.view_handle)
+      .collect::<Vec<Handle>>();
+    handles.sort();
+    Ok(handles)
+  }
+}
+
+async fn ensure_ledger_index(ledger: &Collection<DBEntry>) -> Result<(), LedgerStoreError> {
+  // Each ledger lives in its own collection keyed by height, so the index on `_id` that MongoDB
+  // and Cosmos DB build implicitly is the (handle, height) index. Height reads need the secondary
+  // index on the height field, which is verified and built here.
+  let index_names = ledger.list_index_names().await?;
+  if index_names.iter().any(|name| name == HEIGHT_INDEX_NAME) {
+    return Ok(());
+  }
+
+  // Entries stored before the height field existed get it from their `_id` first, or the index
+  // would rank them below every other entry
+  let options = FindOptions::builder().projection(doc! {"_id": 1}).build();
+  let mut missing = ledger
+    .clone_with_type::<mongodb::bson::Document>()
+    .find(doc! {"height": {"$exists": false}}, options)
+    .await?;
+  while missing.advance().await? {
+    let index = missing
+      .deserialize_current()?
+      .get_i64("_id")
+      .map_err(|_| LedgerStoreError::LedgerError(StorageError::DeserializationError))?;
+    ledger
+      .update_one(doc! {"_id": index}, doc! {"$set": {"height": index}}, None)
+      .await?;
+  }
+
+  let index = IndexModel::builder()
+    .keys(doc! {"height": -1})
+    .options(
+      IndexOptions::builder()
+        .name(HEIGHT_INDEX_NAME.to_string())
+        .build(),
+    )
+    .build();
+  ledger.create_index(index, None).await?;
+  Ok(())
 }
 
 async fn find_db_entry(
@@ -278,6 +580,8 @@ async fn append_ledger_op(
   let new_entry = DBEntry {
     index: height_plus_one,
     value: bson_new_ledger_entry,
+    timestamp: i64::try_from(current_timestamp()).ok(),
+    height: height_plus_one,
   };
 
   // 4. Try to insert the new entry into the ledger.
@@ -293,6 +597,7 @@ async fn attach_ledger_receipts_op(
   idx: usize,
   receipts: &Receipts,
   ledger: &Collection<DBEntry>,
+  receipt_retention: ReceiptRetention,
 ) -> Result<(), LedgerStoreError> {
   // 1. Get the desired index.
   let index = checked_conversion!(idx, i64);
@@ -310,6 +615,7 @@ async fn attach_ledger_receipts_op(
 
   // 4. Update receipt
   ledger_entry_receipts.merge_receipts(receipts);
+  receipt_retention.apply(&mut ledger_entry_receipts);
   ledger_entry.receipts = ledger_entry_receipts.to_bytes();
 
   // 5. Re-serialize into bson binary
@@ -352,9 +658,12 @@ async fn create_ledger_op(
   let genesis_entry = DBEntry {
     index: 0,
     value: bson_init_data_ledger_entry,
+    timestamp: i64::try_from(current_timestamp()).ok(),
+    height: 0,
   };
 
   ledger.insert_one(&genesis_entry, None).await?;
+  ensure_ledger_index(ledger).await?;
 
   // Update the ledger's cache height with the the latest height (which is 0)
   update_cache_entry(handle, cache, 0)?;
@@ -365,9 +674,10 @@ async fn create_ledger_op(
 async fn read_ledger_op(
   idx: Option<usize>,
   ledger: &Collection<DBEntry>,
+  consistency: ReadConsistency,
 ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
   let index = match idx {
-    None => find_ledger_height(ledger).await?,
+    None => find_ledger_height_with(ledger, consistency).await?,
     Some(i) => {
       checked_conversion!(i, i64)
     },
@@ -378,7 +688,7 @@ async fn read_ledger_op(
       doc! {
           "_id": index,
       },
-      None,
+      consistency.find_one_options(),
     )
     .await;
 
@@ -398,11 +708,14 @@ async fn read_ledger_op(
   let entry: SerializedLedgerEntry =
     bincode::deserialize(&bson_entry.bytes).expect("failed to deserialize entry");
 
-  let res = LedgerEntry::new(
+  let mut res = LedgerEntry::new(
     Block::from_bytes(&entry.block).unwrap(),
     Receipts::from_bytes(&entry.receipts).unwrap(),
     None, //TODO
   );
+  if let Some(timestamp) = ledger_entry.timestamp {
+    res.set_timestamp(checked_conversion!(timestamp, u64));
+  }
 
   Ok((res, checked_conversion!(index, usize)))
 }
@@ -491,17 +804,24 @@ fn update_cache_entry(
 }
 
 async fn find_ledger_height(ledger: &Collection<DBEntry>) -> Result<i64, LedgerStoreError> {
-  // There are two methods for computing height estimated_document_count returns
-  // height from metadata stored in mongodb. This is an estimate in the sense
-  // that it might return a stale count the if the database shutdown in an unclean way and restarted.
-  // In contrast, count_documents returns an accurate count but requires scanning all docs.
-  let count = checked_conversion!(ledger.estimated_document_count(None).await?, i64);
-
-  // The height or offset is count - 1 since we index from 0.
-  if count > 0 {
-    Ok(count - 1)
-  } else {
-    Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+  // the height is used to decide the index of the next append, so it is always read strongly
+  find_ledger_height_with(ledger, ReadConsistency::Strong).await
+}
+
+async fn find_ledger_height_with(
+  ledger: &Collection<DBEntry>,
+  consistency: ReadConsistency,
+) -> Result<i64, LedgerStoreError> {
+  // The height is that of the last entry, which the height index yields directly. Unlike the
+  // collection's estimated document count, it is never stale after an unclean shutdown, and
+  // unlike an exact count it does not scan the collection.
+  let tail = ledger
+    .clone_with_type::<DBHeight>()
+    .find_one(doc! {}, consistency.height_options())
+    .await?;
+  match tail {
+    Some(tail) => Ok(tail.height),
+    None => Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)),
   }
 }
 
@@ -510,16 +830,28 @@ async fn loop_and_read(
   index: Option<usize>,
   ledger: &Collection<DBEntry>,
   cache: &CacheMap,
+  consistency: ReadConsistency,
 ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
   loop {
-    with_retry!(read_ledger_op(index, ledger).await, handle, cache, ledger);
+    with_retry!(
+      read_ledger_op(index, ledger, consistency).await,
+      handle,
+      cache,
+      ledger
+    );
   }
 }
 
+const LEDGER_INDEX_NAME: &str = "_id_";
+const HEIGHT_INDEX_NAME: &str = "nimble_height";
 const RETRY_SLEEP: u64 = 50; // ms
 const WRITE_CONFLICT_CODE: i32 = 112;
 const DUPLICATE_KEY_CODE: i32 = 11000;
 const REQUEST_RATE_TOO_HIGH_CODE: i32 = 16500;
+const LEASES_COLLECTION: &str = "nimble_leases";
+const APPEND_REQUESTS_COLLECTION: &str = "nimble_append_requests";
+const LEDGER_LABELS_COLLECTION: &str = "nimble_ledger_labels";
+const LEDGER_NAMESPACES_COLLECTION: &str = "nimble_ledger_namespaces";
 
 #[async_trait]
 impl LedgerStore for MongoCosmosLedgerStore {
@@ -528,10 +860,7 @@ impl LedgerStore for MongoCosmosLedgerStore {
     handle: &Handle,
     genesis_block: Block,
   ) -> Result<(), LedgerStoreError> {
-    let client = self.client.clone();
-    let ledger = client
-      .database(&self.dbname)
-      .collection::<DBEntry>(&hex::encode(&handle.to_bytes()));
+    let ledger = self.ledger_collection(handle).await?;
 
     loop {
       with_retry!(
@@ -549,10 +878,7 @@ impl LedgerStore for MongoCosmosLedgerStore {
     block: &Block,
     expected_height: usize,
   ) -> Result<(usize, Nonces), LedgerStoreError> {
-    let client = self.client.clone();
-    let ledger = client
-      .database(&self.dbname)
-      .collection::<DBEntry>(&hex::encode(handle.to_bytes()));
+    let ledger = self.ledger_collection(handle).await?;
 
     loop {
       with_retry!(
@@ -570,14 +896,34 @@ impl LedgerStore for MongoCosmosLedgerStore {
     idx: usize,
     receipts: &Receipts,
   ) -> Result<(), LedgerStoreError> {
-    let client = self.client.clone();
-    let ledger = client
-      .database(&self.dbname)
-      .collection::<DBEntry>(&hex::encode(&handle.to_bytes()));
+    let ledger = self.ledger_collection(handle).await?;
+
+    let receipt_retention = if *handle == self.view_handle {
+      ReceiptRetention::All
+    } else {
+      self.receipt_retention
+    };
 
     loop {
       with_retry!(
-        attach_ledger_receipts_op(idx, receipts, &ledger).await,
+        attach_ledger_receipts_op(idx, receipts, &ledger, receipt_retention).await,
+        handle,
+        &self.cache,
+        &ledger
+      );
+    }
+  }
+
+  async fn drop_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let ledger = self.ledger_collection(handle).await?;
+
+    loop {
+      with_retry!(
+        attach_ledger_receipts_op(idx, &Receipts::new(), &ledger, ReceiptRetention::None).await,
         handle,
         &self.cache,
         &ledger
@@ -591,19 +937,18 @@ impl LedgerStore for MongoCosmosLedgerStore {
     handle: &Handle,
     nonce: &Nonce,
   ) -> Result<usize, LedgerStoreError> {
-    unimplemented!()
+    Err(LedgerStoreError::LedgerError(
+      StorageError::NoncesNotSupported,
+    ))
   }
 
   async fn read_ledger_tail(
     &self,
     handle: &Handle,
   ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
-    let client = self.client.clone();
-    let ledger = client
-      .database(&self.dbname)
-      .collection::<DBEntry>(&hex::encode(&handle.to_bytes()));
+    let ledger = self.ledger_collection(handle).await?;
 
-    loop_and_read(handle, None, &ledger, &self.cache).await
+    loop_and_read(handle, None, &ledger, &self.cache, self.tail_reads).await
   }
 
   async fn read_ledger_by_index(
@@ -611,12 +956,16 @@ impl LedgerStore for MongoCosmosLedgerStore {
     handle: &Handle,
     index: usize,
   ) -> Result<LedgerEntry, LedgerStoreError> {
-    let client = self.client.clone();
-    let ledger = client
-      .database(&self.dbname)
-      .collection::<DBEntry>(&hex::encode(&handle.to_bytes()));
-
-    let (entry, _height) = loop_and_read(handle, Some(index), &ledger, &self.cache).await?;
+    let ledger = self.ledger_collection(handle).await?;
+
+    let (entry, _height) = loop_and_read(
+      handle,
+      Some(index),
+      &ledger,
+      &self.cache,
+      self.historical_reads,
+    )
+    .await?;
     Ok(entry)
   }
 
@@ -649,6 +998,246 @@ impl LedgerStore for MongoCosmosLedgerStore {
     Ok(res.0)
   }
 
+  async fn list_ledgers(&self) -> Result<Vec<Handle>, LedgerStoreError> {
+    self.list_ledger_collections(None).await
+  }
+
+  async fn list_ledger_summaries(
+    &self,
+    after: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<LedgerSummary>, LedgerStoreError> {
+    // the names of the collections of namespaced ledgers do not sort as their handles, so the
+    // cursor is applied to the handles
+    let mut handles = self.list_ledger_collections(None).await?;
+    handles.retain(|handle| after.is_none_or(|after| handle > after));
+    handles.truncate(limit);
+
+    let mut summaries = Vec::with_capacity(handles.len());
+    for handle in handles {
+      let ledger = self.ledger_collection(&handle).await?;
+      let height = find_ledger_height_with(&ledger, self.tail_reads).await?;
+      let (genesis, _index) = read_ledger_op(Some(0), &ledger, self.historical_reads).await?;
+      summaries.push(LedgerSummary {
+        handle,
+        height: checked_conversion!(height, usize),
+        genesis,
+      });
+    }
+    Ok(summaries)
+  }
+
+  async fn create_ledger_in_namespace(
+    &self,
+    namespace: &str,
+    handle: &Handle,
+    genesis_block: Block,
+  ) -> Result<(), LedgerStoreError> {
+    // the namespace is recorded first, so that every coordinator finds the ledger's collection
+    // once it exists; the handle carries the namespace, so a retry records the same one
+    let namespaces = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLedgerNamespace>(LEDGER_NAMESPACES_COLLECTION);
+    let entry = DBLedgerNamespace {
+      handle: hex::encode(handle.to_bytes()),
+      namespace: namespace.to_string(),
+    };
+    if let Err(error) = namespaces.insert_one(entry, None).await {
+      if !is_duplicate_key_error(&error) {
+        return Err(LedgerStoreError::MongoDBError(error));
+      }
+    }
+    self.remember_collection(handle, &ledger_collection_name(Some(namespace), handle));
+    self.create_ledger(handle, genesis_block).await
+  }
+
+  async fn list_ledgers_in_namespace(
+    &self,
+    namespace: &str,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    // namespaces hold no characters that are special in a regular expression
+    let filter = doc! {"name": {"$regex": format!("^{}\\.", namespace)}};
+    self.list_ledger_collections(Some(filter)).await
+  }
+
+  async fn acquire_lease(
+    &self,
+    name: &str,
+    holder: &str,
+    duration_ms: u64,
+  ) -> Result<Lease, LedgerStoreError> {
+    let leases = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLease>(LEASES_COLLECTION);
+    let now = checked_conversion!(current_timestamp(), i64);
+    let expires_at = checked_conversion!(current_timestamp().saturating_add(duration_ms), i64);
+
+    // the lease is updated only if it is ours or expired; otherwise the upsert collides with
+    // the existing lease and the current holder is read back
+    let filter = doc! {
+      "_id": name,
+      "$or": [{"holder": holder}, {"expires_at": {"$lte": now}}],
+    };
+    let update = doc! {"$set": {"holder": holder, "expires_at": expires_at}};
+    let options = FindOneAndUpdateOptions::builder()
+      .upsert(true)
+      .return_document(ReturnDocument::After)
+      .build();
+    let lease = match leases.find_one_and_update(filter, update, options).await {
+      Ok(lease) => lease,
+      Err(error) => {
+        if !is_duplicate_key_error(&error) {
+          return Err(LedgerStoreError::MongoDBError(error));
+        }
+        leases.find_one(doc! {"_id": name}, None).await?
+      },
+    };
+    match lease {
+      Some(lease) => Ok(Lease {
+        holder: lease.holder,
+        expires_at: checked_conversion!(lease.expires_at, u64),
+      }),
+      None => Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)),
+    }
+  }
+
+  async fn claim_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+    duration_ms: u64,
+  ) -> Result<AppendRequest, LedgerStoreError> {
+    let append_requests = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBAppendRequest>(APPEND_REQUESTS_COLLECTION);
+    let key = append_request_key(handle, request_id);
+    let now = checked_conversion!(current_timestamp(), i64);
+    let expires_at = checked_conversion!(current_timestamp().saturating_add(duration_ms), i64);
+
+    // an expired record is replaced by the claim; otherwise the upsert collides with the
+    // unexpired record, which is read back
+    let filter = doc! {"_id": &key, "expires_at": {"$lte": now}};
+    let update = doc! {"$set": {"expires_at": expires_at}, "$unset": {"index": ""}};
+    let options = FindOneAndUpdateOptions::builder().upsert(true).build();
+    match append_requests
+      .find_one_and_update(filter, update, options)
+      .await
+    {
+      Ok(_) => Ok(AppendRequest::Claimed),
+      Err(error) => {
+        if !is_duplicate_key_error(&error) {
+          return Err(LedgerStoreError::MongoDBError(error));
+        }
+        match append_requests.find_one(doc! {"_id": &key}, None).await? {
+          Some(DBAppendRequest {
+            index: Some(index), ..
+          }) => Ok(AppendRequest::Completed(checked_conversion!(index, usize))),
+          Some(_) => Ok(AppendRequest::InProgress),
+          None => Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)),
+        }
+      },
+    }
+  }
+
+  async fn complete_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+    idx: usize,
+    duration_ms: u64,
+  ) -> Result<(), LedgerStoreError> {
+    let append_requests = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBAppendRequest>(APPEND_REQUESTS_COLLECTION);
+    let index = checked_conversion!(idx, i64);
+    let expires_at = checked_conversion!(current_timestamp().saturating_add(duration_ms), i64);
+    append_requests
+      .update_one(
+        doc! {"_id": append_request_key(handle, request_id)},
+        doc! {"$set": {"index": index, "expires_at": expires_at}},
+        None,
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn release_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    let append_requests = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBAppendRequest>(APPEND_REQUESTS_COLLECTION);
+    append_requests
+      .delete_one(doc! {"_id": append_request_key(handle, request_id)}, None)
+      .await?;
+    Ok(())
+  }
+
+  async fn add_ledger_label(
+    &self,
+    label: &str,
+    handle_bytes: &[u8],
+  ) -> Result<bool, LedgerStoreError> {
+    let labels = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLedgerLabel>(LEDGER_LABELS_COLLECTION);
+    let binding = DBLedgerLabel {
+      label: label.to_string(),
+      handle: handle_bytes.to_vec().to_bson_binary(),
+    };
+    match labels.insert_one(binding, None).await {
+      Ok(_) => Ok(true),
+      Err(error) => {
+        if !is_duplicate_key_error(&error) {
+          return Err(LedgerStoreError::MongoDBError(error));
+        }
+        // the label is taken, which is fine if it is taken by this ledger
+        if self.find_ledger_by_label(label).await? == handle_bytes {
+          Ok(false)
+        } else {
+          Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey))
+        }
+      },
+    }
+  }
+
+  async fn remove_ledger_label(
+    &self,
+    label: &str,
+    handle_bytes: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    let labels = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLedgerLabel>(LEDGER_LABELS_COLLECTION);
+    labels
+      .delete_one(
+        doc! {"_id": label, "handle": handle_bytes.to_vec().to_bson_binary()},
+        None,
+      )
+      .await?;
+    Ok(())
+  }
+
+  async fn find_ledger_by_label(&self, label: &str) -> Result<Vec<u8>, LedgerStoreError> {
+    let labels = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLedgerLabel>(LEDGER_LABELS_COLLECTION);
+    match labels.find_one(doc! {"_id": label}, None).await? {
+      Some(binding) => Ok(binding.handle.bytes),
+      None => Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)),
+    }
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     let client = self.client.clone();
     client
@@ -0,0 +1,307 @@
+//! A ledger store that runs each operation of another store in a `store` span, so that the
+//! traces of requests show the time spent in the store, operation by operation
+use crate::{
+  errors::LedgerStoreError,
+  ledger::{
+    AppendRequest, BoxedLedgerStore, Lease, LedgerEntry, LedgerStore, LedgerSummary,
+    ReceiptCompaction,
+  },
+};
+use async_trait::async_trait;
+use ledger::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
+use tracing::{info_span, Instrument};
+
+// runs `$call` in a span for the store operation `$op`
+macro_rules! traced {
+  ($op:literal, $call:expr) => {
+    $call
+      .instrument(info_span!(
+        "store",
+        otel.name = concat!("store ", $op),
+        op = $op
+      ))
+      .await
+  };
+}
+
+pub struct TracedLedgerStore {
+  inner: BoxedLedgerStore,
+}
+
+impl TracedLedgerStore {
+  pub fn new(inner: BoxedLedgerStore) -> Self {
+    TracedLedgerStore { inner }
+  }
+}
+
+#[async_trait]
+impl LedgerStore for TracedLedgerStore {
+  async fn create_ledger(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "create_ledger",
+      self.inner.create_ledger(handle, genesis_block)
+    )
+  }
+
+  async fn append_ledger(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    traced!(
+      "append_ledger",
+      self.inner.append_ledger(handle, block, expected_height)
+    )
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipt: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "attach_ledger_receipts",
+      self.inner.attach_ledger_receipts(handle, idx, receipt)
+    )
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
+    traced!(
+      "attach_ledger_nonce",
+      self.inner.attach_ledger_nonce(handle, nonce)
+    )
+  }
+
+  async fn read_ledger_tail(
+    &self,
+    handle: &Handle,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    traced!("read_ledger_tail", self.inner.read_ledger_tail(handle))
+  }
+
+  async fn read_ledger_by_index(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    traced!(
+      "read_ledger_by_index",
+      self.inner.read_ledger_by_index(handle, idx)
+    )
+  }
+
+  async fn read_ledger_range(
+    &self,
+    handle: &Handle,
+    start: usize,
+    end: usize,
+  ) -> Result<Vec<LedgerEntry>, LedgerStoreError> {
+    traced!(
+      "read_ledger_range",
+      self.inner.read_ledger_range(handle, start, end)
+    )
+  }
+
+  async fn append_view_ledger(
+    &self,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    traced!(
+      "append_view_ledger",
+      self.inner.append_view_ledger(block, expected_height)
+    )
+  }
+
+  async fn attach_view_ledger_receipts(
+    &self,
+    idx: usize,
+    receipt: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "attach_view_ledger_receipts",
+      self.inner.attach_view_ledger_receipts(idx, receipt)
+    )
+  }
+
+  async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    traced!("read_view_ledger_tail", self.inner.read_view_ledger_tail())
+  }
+
+  async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError> {
+    traced!(
+      "read_view_ledger_by_index",
+      self.inner.read_view_ledger_by_index(idx)
+    )
+  }
+
+  async fn list_ledgers(&self) -> Result<Vec<Handle>, LedgerStoreError> {
+    traced!("list_ledgers", self.inner.list_ledgers())
+  }
+
+  async fn list_ledger_summaries(
+    &self,
+    after: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<LedgerSummary>, LedgerStoreError> {
+    traced!(
+      "list_ledger_summaries",
+      self.inner.list_ledger_summaries(after, limit)
+    )
+  }
+
+  async fn create_ledger_in_namespace(
+    &self,
+    namespace: &str,
+    handle: &Handle,
+    genesis_block: Block,
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "create_ledger_in_namespace",
+      self
+        .inner
+        .create_ledger_in_namespace(namespace, handle, genesis_block)
+    )
+  }
+
+  async fn list_ledgers_in_namespace(
+    &self,
+    namespace: &str,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    traced!(
+      "list_ledgers_in_namespace",
+      self.inner.list_ledgers_in_namespace(namespace)
+    )
+  }
+
+  async fn compact_ledger_receipts(
+    &self,
+    handle: &Handle,
+    from: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    traced!(
+      "compact_ledger_receipts",
+      self.inner.compact_ledger_receipts(handle, from)
+    )
+  }
+
+  async fn drop_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "drop_ledger_receipts",
+      self.inner.drop_ledger_receipts(handle, idx)
+    )
+  }
+
+  async fn compact_ledger_to_checkpoints(
+    &self,
+    handle: &Handle,
+    policy: &ReceiptCompaction,
+    from: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    traced!(
+      "compact_ledger_to_checkpoints",
+      self
+        .inner
+        .compact_ledger_to_checkpoints(handle, policy, from)
+    )
+  }
+
+  async fn acquire_lease(
+    &self,
+    name: &str,
+    holder: &str,
+    duration_ms: u64,
+  ) -> Result<Lease, LedgerStoreError> {
+    traced!(
+      "acquire_lease",
+      self.inner.acquire_lease(name, holder, duration_ms)
+    )
+  }
+
+  async fn claim_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+    duration_ms: u64,
+  ) -> Result<AppendRequest, LedgerStoreError> {
+    traced!(
+      "claim_append_request",
+      self
+        .inner
+        .claim_append_request(handle, request_id, duration_ms)
+    )
+  }
+
+  async fn complete_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+    idx: usize,
+    duration_ms: u64,
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "complete_append_request",
+      self
+        .inner
+        .complete_append_request(handle, request_id, idx, duration_ms)
+    )
+  }
+
+  async fn release_append_request(
+    &self,
+    handle: &Handle,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "release_append_request",
+      self.inner.release_append_request(handle, request_id)
+    )
+  }
+
+  async fn add_ledger_label(
+    &self,
+    label: &str,
+    handle_bytes: &[u8],
+  ) -> Result<bool, LedgerStoreError> {
+    traced!(
+      "add_ledger_label",
+      self.inner.add_ledger_label(label, handle_bytes)
+    )
+  }
+
+  async fn remove_ledger_label(
+    &self,
+    label: &str,
+    handle_bytes: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    traced!(
+      "remove_ledger_label",
+      self.inner.remove_ledger_label(label, handle_bytes)
+    )
+  }
+
+  async fn find_ledger_by_label(&self, label: &str) -> Result<Vec<u8>, LedgerStoreError> {
+    traced!(
+      "find_ledger_by_label",
+      self.inner.find_ledger_by_label(label)
+    )
+  }
+
+  async fn reset_store(&self) -> Result<(), LedgerStoreError> {
+    traced!("reset_store", self.inner.reset_store())
+  }
+}
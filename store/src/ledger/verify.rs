@@ -0,0 +1,415 @@
+use crate::ledger::LedgerStore;
+use ledger::{
+  compute_aggregated_block_hash, CustomSerde, Handle, MetaBlock, NimbleHashTrait, SignerFault,
+  VerifierState,
+};
+use serde::Serialize;
+
+/// What verifying the entries of one ledger found
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LedgerReport {
+  /// the height of the ledger's tail
+  pub height: usize,
+  /// the number of entries whose receipts verified
+  pub endorsed: usize,
+  /// the number of entries stored without receipts
+  pub unendorsed: usize,
+}
+
+/// The kinds of inconsistencies that an audit reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+  /// the store failed to return the view ledger, a ledger, or one of their entries
+  Unreadable,
+  /// the view ledger holds no views
+  EmptyViewLedger,
+  /// a view change is not vouched for by the receipts of the view before it, or, for the tail,
+  /// by the attestation
+  InvalidViewChange,
+  /// the receipts of an entry fail to verify for its block, nonces, and height
+  InvalidReceipts,
+  /// the metablock of an entry does not extend the metablock of the entry before it
+  BrokenHashChain,
+}
+
+/// An endorser whose signature on an entry is at fault
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FaultySigner {
+  /// the base64url public key of the endorser
+  pub public_key: String,
+  /// unknown, duplicated, or bad_signature, as `SignerFault` in the `ledger` crate
+  pub fault: String,
+}
+
+/// An inconsistency that an audit found
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AuditIssue {
+  /// the base64url handle of the ledger, or None for the view ledger
+  pub ledger: Option<String>,
+  /// the index of the entry, or None if the issue is with the ledger as a whole
+  pub index: Option<usize>,
+  pub kind: IssueKind,
+  pub detail: String,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub faulty_signers: Vec<FaultySigner>,
+}
+
+impl AuditIssue {
+  fn new(
+    handle_opt: Option<&Handle>,
+    index: Option<usize>,
+    kind: IssueKind,
+    detail: String,
+  ) -> Self {
+    AuditIssue {
+      ledger: handle_opt.map(|handle| base64_url::encode(&handle.to_bytes())),
+      index,
+      kind,
+      detail,
+      faulty_signers: Vec::new(),
+    }
+  }
+}
+
+/// The endorsers of a view, as the view ledger establishes them
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ViewEndorsers {
+  /// the index of the view change in the view ledger
+  pub index: usize,
+  /// the base64url identity of the view, the hash of the view change's metablock
+  pub view: String,
+  /// the base64url public keys of the endorsers, sorted
+  pub endorsers: Vec<String>,
+}
+
+/// What auditing the view ledger found
+#[derive(Debug, Default)]
+pub struct ViewLedgerAudit {
+  /// the verifier state with the views that were vouched for
+  pub vs: VerifierState,
+  /// the number of views in the view ledger
+  pub views: usize,
+  /// the endorsers of each view that was vouched for, in the order of the view ledger
+  pub endorsers: Vec<ViewEndorsers>,
+  /// the issues found, at most one since a view change that fails to verify leaves the views
+  /// before it without a voucher
+  pub issues: Vec<AuditIssue>,
+}
+
+/// Replays the view ledger of a store the way a client does, as `replay_view_ledger`, but
+/// reports what it found instead of failing: the views it could verify, with their endorsers, and
+/// the issue that stopped it, if any
+pub async fn audit_view_ledger(
+  store: &(dyn LedgerStore + Send + Sync),
+  attestations: &[u8],
+) -> ViewLedgerAudit {
+  let mut audit = ViewLedgerAudit::default();
+  let issue = |index_opt, kind, detail| AuditIssue::new(None, index_opt, kind, detail);
+  let view_height = match store.read_view_ledger_tail().await {
+    Ok((_tail, view_height)) => view_height,
+    Err(e) => {
+      audit.issues.push(issue(
+        None,
+        IssueKind::Unreadable,
+        format!("read the view ledger tail ({:?})", e),
+      ));
+      return audit;
+    },
+  };
+  audit.views = view_height;
+  if view_height == 0 {
+    audit.issues.push(issue(
+      None,
+      IssueKind::EmptyViewLedger,
+      "the view ledger is empty".to_string(),
+    ));
+    return audit;
+  }
+
+  match store.read_view_ledger_by_index(1).await {
+    Ok(genesis) => audit.vs.set_group_identity(genesis.get_block().hash()),
+    Err(e) => {
+      audit.issues.push(issue(
+        Some(1),
+        IssueKind::Unreadable,
+        format!("read view 1 ({:?})", e),
+      ));
+      return audit;
+    },
+  }
+  for index in (1..=view_height).rev() {
+    let entry = match store.read_view_ledger_by_index(index).await {
+      Ok(entry) => entry,
+      Err(e) => {
+        audit.issues.push(issue(
+          Some(index),
+          IssueKind::Unreadable,
+          format!("read view {} ({:?})", index, e),
+        ));
+        break;
+      },
+    };
+    let attestations_opt = if index == view_height {
+      Some(attestations)
+    } else {
+      None
+    };
+    let res = audit.vs.apply_view_change(
+      &entry.get_block().to_bytes(),
+      &entry.get_receipts().to_bytes(),
+      attestations_opt,
+    );
+    if let Err(e) = res {
+      audit.issues.push(issue(
+        Some(index),
+        IssueKind::InvalidViewChange,
+        format!("apply view change {} ({:?})", index, e),
+      ));
+      break;
+    }
+    if let Ok(metablock) = entry.get_receipts().get_metablock() {
+      let view = metablock.hash();
+      if let Ok(pks) = audit.vs.get_pks_for_view(&view) {
+        let mut endorsers = pks.iter().map(base64_url::encode).collect::<Vec<String>>();
+        endorsers.sort();
+        audit.endorsers.push(ViewEndorsers {
+          index,
+          view: base64_url::encode(&view.to_bytes()),
+          endorsers,
+        });
+      }
+    }
+  }
+  audit.endorsers.reverse();
+  audit
+}
+
+/// Replays the view ledger of a store the way a client does: the tail is vouched for by
+/// `attestations` and every earlier view by the view change that follows it. Returns the
+/// verifier state and the number of views replayed.
+pub async fn replay_view_ledger(
+  store: &(dyn LedgerStore + Send + Sync),
+  attestations: &[u8],
+) -> Result<(VerifierState, usize), String> {
+  let mut audit = audit_view_ledger(store, attestations).await;
+  match audit.issues.pop() {
+    Some(issue) => Err(issue.detail),
+    None => Ok((audit.vs, audit.views)),
+  }
+}
+
+fn signer_fault_name(fault: &SignerFault) -> &'static str {
+  match fault {
+    SignerFault::Unknown => "unknown",
+    SignerFault::Duplicated => "duplicated",
+    SignerFault::BadSignature => "bad_signature",
+  }
+}
+
+/// Audits every entry of a ledger against the replayed view ledger, as `verify_ledger`, but goes
+/// on past the entries that fail, and returns what it counted with every issue it found. After an
+/// entry whose receipts fail, the chain goes on from the metablock that the receipts carry, so
+/// that a tampered block is not also reported as breaking the chain at the entry after it.
+pub async fn audit_ledger(
+  store: &(dyn LedgerStore + Send + Sync),
+  vs: &VerifierState,
+  handle: &Handle,
+) -> (LedgerReport, Vec<AuditIssue>) {
+  let mut report = LedgerReport::default();
+  let mut issues = Vec::new();
+  let issue = |index_opt, kind, detail| AuditIssue::new(Some(handle), index_opt, kind, detail);
+  let height = match store.read_ledger_tail(handle).await {
+    Ok((_tail, height)) => height,
+    Err(e) => {
+      issues.push(issue(
+        None,
+        IssueKind::Unreadable,
+        format!("read the tail ({:?})", e),
+      ));
+      return (report, issues);
+    },
+  };
+  report.height = height;
+
+  let mut prev: Option<MetaBlock> = None;
+  for index in 0..=height {
+    let entry = match store.read_ledger_by_index(handle, index).await {
+      Ok(entry) => entry,
+      Err(e) => {
+        issues.push(issue(
+          Some(index),
+          IssueKind::Unreadable,
+          format!("read entry {} ({:?})", index, e),
+        ));
+        prev = None;
+        continue;
+      },
+    };
+    // the metablock that the entry's block and nonces give, if the entry before it is known
+    let recomputed = |prev: Option<MetaBlock>| {
+      prev.map(|prev| {
+        let block_hash = compute_aggregated_block_hash(
+          &entry.get_block().hash().to_bytes(),
+          &entry.get_nonces().hash().to_bytes(),
+        );
+        MetaBlock::new(&prev.hash(), &block_hash, index)
+      })
+    };
+    let receipts = entry.get_receipts();
+    if receipts.is_empty() {
+      report.unendorsed += 1;
+      prev = recomputed(prev);
+      continue;
+    }
+
+    let receipts_report = receipts.verify_with_report(
+      vs,
+      handle,
+      &entry.get_block().to_bytes(),
+      &entry.get_nonces().hash().to_bytes(),
+      Some(index),
+      None,
+    );
+    if let Err(e) = receipts_report.result {
+      // names the endorsers whose signatures are at fault, which an operator should remove
+      let faults = receipts_report
+        .faults
+        .iter()
+        .map(|(pk, fault)| format!("{}: {:?}", base64_url::encode(pk), fault))
+        .collect::<Vec<String>>();
+      let mut invalid = issue(
+        Some(index),
+        IssueKind::InvalidReceipts,
+        format!(
+          "verify entry {} ({:?}; faulty signers [{}])",
+          index,
+          e,
+          faults.join(", ")
+        ),
+      );
+      invalid.faulty_signers = receipts_report
+        .faults
+        .iter()
+        .map(|(pk, fault)| FaultySigner {
+          public_key: base64_url::encode(pk),
+          fault: signer_fault_name(fault).to_string(),
+        })
+        .collect();
+      issues.push(invalid);
+      prev = receipts.get_metablock().ok().or_else(|| recomputed(prev));
+      continue;
+    }
+    let metablock = match receipts.get_metablock() {
+      Ok(metablock) => metablock,
+      Err(e) => {
+        issues.push(issue(
+          Some(index),
+          IssueKind::InvalidReceipts,
+          format!("entry {} ({:?})", index, e),
+        ));
+        prev = recomputed(prev);
+        continue;
+      },
+    };
+    if let Some(prev) = &prev {
+      if *metablock.get_prev() != prev.hash() {
+        issues.push(issue(
+          Some(index),
+          IssueKind::BrokenHashChain,
+          format!("entry {} does not extend the previous entry", index),
+        ));
+      }
+    }
+    prev = Some(metablock);
+    report.endorsed += 1;
+  }
+  (report, issues)
+}
+
+/// Verifies every entry of a ledger against the replayed view ledger: each endorsed entry must
+/// carry a quorum receipt for its block, nonces, and height, and its metablock must extend the
+/// metablock of the entry before it. Entries without receipts, such as those compacted between
+/// checkpoints, are counted, and the metablocks of the entries after them are checked against the
+/// metablocks recomputed from their blocks and nonces.
+pub async fn verify_ledger(
+  store: &(dyn LedgerStore + Send + Sync),
+  vs: &VerifierState,
+  handle: &Handle,
+) -> Result<LedgerReport, String> {
+  let (report, issues) = audit_ledger(store, vs, handle).await;
+  match issues.into_iter().next() {
+    Some(issue) => Err(issue.detail),
+    None => Ok(report),
+  }
+}
+
+/// What auditing one ledger found
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct LedgerAudit {
+  /// the base64url handle of the ledger
+  pub ledger: String,
+  #[serde(flatten)]
+  pub report: LedgerReport,
+  /// the number of issues found in the ledger
+  pub issues: usize,
+}
+
+/// The machine-readable report of an audit of a store
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+  /// the number of views in the view ledger
+  pub views: usize,
+  /// the endorsers of each view that was vouched for
+  pub view_endorsers: Vec<ViewEndorsers>,
+  pub ledgers: Vec<LedgerAudit>,
+  /// every inconsistency found, in the view ledger first and then ledger by ledger
+  pub issues: Vec<AuditIssue>,
+}
+
+impl AuditReport {
+  pub fn passed(&self) -> bool {
+    self.issues.is_empty()
+  }
+}
+
+/// Audits the view ledger of a store against `attestations` and then each of `handles`, or every
+/// ledger in the store if none are given, against the views that the view ledger vouches for
+pub async fn audit_store(
+  store: &(dyn LedgerStore + Send + Sync),
+  attestations: &[u8],
+  handles: Option<Vec<Handle>>,
+) -> AuditReport {
+  let view_audit = audit_view_ledger(store, attestations).await;
+  let mut report = AuditReport {
+    views: view_audit.views,
+    view_endorsers: view_audit.endorsers,
+    ledgers: Vec::new(),
+    issues: view_audit.issues,
+  };
+  let handles = match handles {
+    Some(handles) => handles,
+    None => match store.list_ledgers().await {
+      Ok(handles) => handles,
+      Err(e) => {
+        report.issues.push(AuditIssue::new(
+          None,
+          None,
+          IssueKind::Unreadable,
+          format!("list the ledgers ({:?})", e),
+        ));
+        return report;
+      },
+    },
+  };
+  for handle in handles {
+    let (ledger_report, issues) = audit_ledger(store, &view_audit.vs, &handle).await;
+    report.ledgers.push(LedgerAudit {
+      ledger: base64_url::encode(&handle.to_bytes()),
+      report: ledger_report,
+      issues: issues.len(),
+    });
+    report.issues.extend(issues);
+  }
+  report
+}
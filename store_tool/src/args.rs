@@ -0,0 +1,106 @@
+//! The options of the store tools that name the store to open
+use clap::{App, Arg, ArgMatches};
+use ledger::{Handle, NimbleDigest};
+use std::collections::HashMap;
+use store::ledger::{open_ledger_store, BoxedLedgerStore};
+
+// the attestation the coordinator reports for the view ledger, which replaying it checks
+pub const ATTESTATION_STR: &str = "THIS IS A PLACE HOLDER FOR ATTESTATION";
+
+/// Adds the options that name the store to `app`
+pub fn with_store_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+  app
+    .arg(
+      Arg::with_name("store")
+        .short("s")
+        .long("store")
+        .help("The type of store to open.")
+        .possible_values(&store::ledger::LEDGER_STORE_TYPES)
+        .default_value("filestore"),
+    )
+    .arg(
+      Arg::with_name("nimbledb")
+        .short("n")
+        .long("nimbledb")
+        .help("The database name")
+        .default_value("nimble_cosmosdb"),
+    )
+    .arg(
+      Arg::with_name("cosmosurl")
+        .short("c")
+        .long("cosmosurl")
+        .takes_value(true)
+        .help("The COSMOS URL"),
+    )
+    .arg(
+      Arg::with_name("storage_account")
+        .short("a")
+        .long("storage_account")
+        .takes_value(true)
+        .help("The storage account name"),
+    )
+    .arg(
+      Arg::with_name("storage_master_key")
+        .short("k")
+        .long("storage_master_key")
+        .takes_value(true)
+        .help("The storage master key"),
+    )
+    .arg(
+      Arg::with_name("fstore_dir")
+        .short("d")
+        .long("fstore_dir")
+        .takes_value(true)
+        .help("The directory of the file store (default: $NIMBLE_FSTORE_DIR)"),
+    )
+}
+
+/// Parses the base64url handle of a ledger, or exits if it is not one
+pub fn parse_handle(s: &str) -> Handle {
+  let res = base64_url::decode(s)
+    .ok()
+    .and_then(|bytes| NimbleDigest::from_bytes(&bytes).ok());
+  match res {
+    Some(handle) => handle,
+    None => {
+      eprintln!("{} is not the base64url encoding of a ledger handle", s);
+      std::process::exit(1);
+    },
+  }
+}
+
+/// Opens the store that the options name, or exits if it fails to open
+pub async fn open_store(cli_matches: &ArgMatches<'_>) -> BoxedLedgerStore {
+  let store_type = cli_matches.value_of("store").unwrap();
+
+  let mut ledger_store_args = HashMap::<String, String>::new();
+  if let Some(x) = cli_matches.value_of("cosmosurl") {
+    ledger_store_args.insert(String::from("COSMOS_URL"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("nimbledb") {
+    ledger_store_args.insert(String::from("NIMBLE_DB"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("storage_account") {
+    ledger_store_args.insert(String::from("STORAGE_ACCOUNT"), x.to_string());
+  }
+  if let Some(x) = cli_matches.value_of("storage_master_key") {
+    ledger_store_args.insert(String::from("STORAGE_MASTER_KEY"), x.to_string());
+  }
+  if let Ok(x) = std::env::var("STORAGE_CONNECTION_STRING") {
+    ledger_store_args.insert(String::from("STORAGE_CONNECTION_STRING"), x);
+  }
+  if let Some(x) = cli_matches.value_of("fstore_dir") {
+    ledger_store_args.insert(String::from("NIMBLE_FSTORE_DIR"), x.to_string());
+  } else if let Ok(x) = std::env::var("NIMBLE_FSTORE_DIR") {
+    ledger_store_args.insert(String::from("NIMBLE_FSTORE_DIR"), x);
+  }
+
+  let res = open_ledger_store(store_type, &ledger_store_args).await;
+  match res {
+    Ok(store) => store,
+    Err(error) => {
+      eprintln!("Failed to open the {} store ({:?})", store_type, error);
+      std::process::exit(1);
+    },
+  }
+}
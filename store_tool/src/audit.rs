@@ -0,0 +1,82 @@
+use clap::{App, Arg};
+use ledger::Handle;
+use std::{fs::File, io::BufReader};
+use store::ledger::{archive::read_archive, verify::audit_store};
+
+mod args;
+use args::{open_store, parse_handle, with_store_args, ATTESTATION_STR};
+
+#[tokio::main]
+async fn main() {
+  let config = App::new("nimble-audit").about(
+    "Audits the view ledger and every ledger of an archive or a store, and reports every \
+       inconsistency as JSON",
+  );
+  let config = with_store_args(config)
+    .arg(
+      Arg::with_name("archive")
+        .long("archive")
+        .takes_value(true)
+        .help("Audit an archive exported with nimble-store --export instead of a store"),
+    )
+    .arg(
+      Arg::with_name("ledger")
+        .short("l")
+        .long("ledger")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("Audit only the ledger with this base64url handle; may be repeated"),
+    )
+    .arg(
+      Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .help("Write the report to this file instead of stdout"),
+    );
+  let cli_matches = config.get_matches();
+  let handles = cli_matches
+    .values_of("ledger")
+    .map(|values| values.map(parse_handle).collect::<Vec<Handle>>());
+
+  let report = match cli_matches.value_of("archive") {
+    Some(path) => {
+      let res = match File::open(path) {
+        Ok(file) => read_archive(&mut BufReader::new(file)).await,
+        Err(e) => Err(format!("open {} ({})", path, e)),
+      };
+      match res {
+        Ok((staging, _report)) => audit_store(&staging, ATTESTATION_STR.as_bytes(), handles).await,
+        Err(error) => {
+          eprintln!("Failed to read the archive: {}", error);
+          std::process::exit(1);
+        },
+      }
+    },
+    None => {
+      let store = open_store(&cli_matches).await;
+      audit_store(store.as_ref(), ATTESTATION_STR.as_bytes(), handles).await
+    },
+  };
+
+  let json = serde_json::to_string_pretty(&report).unwrap();
+  match cli_matches.value_of("output") {
+    Some(path) => {
+      if let Err(e) = std::fs::write(path, json) {
+        eprintln!("Failed to write the report to {} ({})", path, e);
+        std::process::exit(1);
+      }
+    },
+    None => println!("{}", json),
+  }
+  eprintln!(
+    "audited {} views and {} ledgers: {} issues",
+    report.views,
+    report.ledgers.len(),
+    report.issues.len()
+  );
+  if !report.passed() {
+    std::process::exit(1);
+  }
+}
@@ -0,0 +1,316 @@
+use clap::{App, Arg};
+use ledger::{CustomSerde, Handle, VerifierState};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs::File,
+  io::{BufReader, BufWriter, Write},
+};
+use store::ledger::{
+  archive::{export_store, import_archive, ArchiveReport},
+  verify::{replay_view_ledger, verify_ledger},
+  BoxedLedgerStore, LedgerEntry,
+};
+
+mod args;
+use args::{open_store, parse_handle, with_store_args, ATTESTATION_STR};
+
+// the name under which the view ledger can be dumped
+const VIEW_LEDGER: &str = "view";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DumpedEntry {
+  #[serde(rename = "Index")]
+  pub index: usize,
+  #[serde(rename = "Height")]
+  pub height: Option<usize>,
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Nonces")]
+  pub nonces: Vec<String>,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+  #[serde(rename = "Signers")]
+  pub signers: Vec<String>,
+  #[serde(rename = "Timestamp")]
+  pub timestamp: Option<u64>,
+}
+
+impl DumpedEntry {
+  fn new(index: usize, entry: &LedgerEntry) -> Self {
+    let receipts = entry.get_receipts();
+    DumpedEntry {
+      index,
+      height: receipts.get_metablock().ok().map(|m| m.get_height()),
+      block: base64_url::encode(&entry.get_block().to_bytes()),
+      nonces: entry
+        .get_nonces()
+        .get()
+        .iter()
+        .map(|n| base64_url::encode(&n.to_bytes()))
+        .collect(),
+      receipts: base64_url::encode(&receipts.to_bytes()),
+      signers: receipts
+        .get_signer_ids()
+        .iter()
+        .map(base64_url::encode)
+        .collect(),
+      timestamp: entry.get_timestamp(),
+    }
+  }
+}
+
+fn parse_index(s: Option<&str>, name: &str) -> Option<usize> {
+  s.map(|s| match s.parse() {
+    Ok(v) => v,
+    Err(_) => {
+      eprintln!("Failed to parse --{} {}", name, s);
+      std::process::exit(1);
+    },
+  })
+}
+
+async fn read_entry(
+  store: &BoxedLedgerStore,
+  handle_opt: Option<&Handle>,
+  index: usize,
+) -> Result<LedgerEntry, String> {
+  let res = match handle_opt {
+    Some(handle) => store.read_ledger_by_index(handle, index).await,
+    None => store.read_view_ledger_by_index(index).await,
+  };
+  res.map_err(|e| format!("read entry {} ({:?})", index, e))
+}
+
+async fn dump(
+  store: &BoxedLedgerStore,
+  handle_opt: Option<&Handle>,
+  from: Option<usize>,
+  to: Option<usize>,
+  json: bool,
+) -> Result<(), String> {
+  let res = match handle_opt {
+    Some(handle) => store.read_ledger_tail(handle).await,
+    None => store.read_view_ledger_tail().await,
+  };
+  let (_tail, height) = res.map_err(|e| format!("read the tail ({:?})", e))?;
+  // the view ledger starts with a placeholder entry at index 0
+  let first = if handle_opt.is_some() { 0 } else { 1 };
+  let from = from.unwrap_or(first).max(first);
+  let to = to.unwrap_or(height).min(height);
+
+  let mut entries = Vec::new();
+  for index in from..=to {
+    let entry = read_entry(store, handle_opt, index).await?;
+    let dumped = DumpedEntry::new(index, &entry);
+    if json {
+      entries.push(dumped);
+    } else {
+      println!(
+        "{}: height {}, block {} bytes, {} nonces, {} signers, timestamp {}",
+        dumped.index,
+        dumped
+          .height
+          .map(|h| h.to_string())
+          .unwrap_or_else(|| "-".to_string()),
+        entry.get_block().len(),
+        dumped.nonces.len(),
+        dumped.signers.len(),
+        dumped
+          .timestamp
+          .map(|t| t.to_string())
+          .unwrap_or_else(|| "-".to_string()),
+      );
+    }
+  }
+  if json {
+    println!("{}", serde_json::to_string_pretty(&entries).unwrap());
+  }
+  Ok(())
+}
+
+/// Verifies the given ledgers, or every ledger in the store, and returns whether all of them
+/// passed; failures are reported as they are found rather than stopping at the first one
+async fn verify(store: &BoxedLedgerStore, handles: Vec<Handle>) -> bool {
+  let mut passed = true;
+  let vs = match replay_view_ledger(store.as_ref(), ATTESTATION_STR.as_bytes()).await {
+    Ok((vs, views)) => {
+      println!("[PASS] view ledger: {} views", views);
+      vs
+    },
+    Err(error) => {
+      println!("[FAIL] view ledger: {}", error);
+      passed = false;
+      // without the view ledger, entries can still be checked for receipts
+      VerifierState::new()
+    },
+  };
+
+  for handle in handles {
+    let name = base64_url::encode(&handle.to_bytes());
+    match verify_ledger(store.as_ref(), &vs, &handle).await {
+      Ok(report) => println!(
+        "[PASS] {}: height {}, {} endorsed, {} unendorsed",
+        name, report.height, report.endorsed, report.unendorsed
+      ),
+      Err(error) => {
+        println!("[FAIL] {}: {}", name, error);
+        passed = false;
+      },
+    }
+  }
+  passed
+}
+
+async fn export(store: &BoxedLedgerStore, path: &str) -> Result<ArchiveReport, String> {
+  let file = File::create(path).map_err(|e| format!("create {} ({})", path, e))?;
+  let mut out = BufWriter::new(file);
+  let report = export_store(store.as_ref(), &mut out).await?;
+  out.flush().map_err(|e| format!("write {} ({})", path, e))?;
+  Ok(report)
+}
+
+async fn import(store: &BoxedLedgerStore, path: &str) -> Result<ArchiveReport, String> {
+  let file = File::open(path).map_err(|e| format!("open {} ({})", path, e))?;
+  import_archive(
+    &mut BufReader::new(file),
+    store.as_ref(),
+    ATTESTATION_STR.as_bytes(),
+  )
+  .await
+}
+
+#[tokio::main]
+async fn main() {
+  let config = App::new("nimble-store")
+    .about("Inspects and verifies a Nimble ledger store without going through the coordinator");
+  let config = with_store_args(config)
+    .arg(
+      Arg::with_name("list")
+        .short("l")
+        .long("list")
+        .help("List the ledgers in the store with their heights"),
+    )
+    .arg(
+      Arg::with_name("dump")
+        .long("dump")
+        .takes_value(true)
+        .help("Dump the entries of a ledger, given by its base64url handle, or of the view ledger"),
+    )
+    .arg(
+      Arg::with_name("from")
+        .long("from")
+        .takes_value(true)
+        .requires("dump")
+        .help("The first index to dump"),
+    )
+    .arg(
+      Arg::with_name("to")
+        .long("to")
+        .takes_value(true)
+        .requires("dump")
+        .help("The last index to dump"),
+    )
+    .arg(
+      Arg::with_name("json")
+        .long("json")
+        .requires("dump")
+        .help("Dump entries as JSON"),
+    )
+    .arg(
+      Arg::with_name("verify")
+        .short("v")
+        .long("verify")
+        .takes_value(true)
+        .min_values(0)
+        .help(
+          "Verify the receipts and hash chain of a ledger, or of every ledger if none is given",
+        ),
+    )
+    .arg(
+      Arg::with_name("export")
+        .long("export")
+        .takes_value(true)
+        .conflicts_with("import")
+        .help("Export the view ledger and every ledger to an archive file"),
+    )
+    .arg(
+      Arg::with_name("import")
+        .long("import")
+        .takes_value(true)
+        .help(
+          "Verify an archive file and import it into the store, whose view ledger must be empty",
+        ),
+    );
+  let cli_matches = config.get_matches();
+  let store = open_store(&cli_matches).await;
+
+  if cli_matches.is_present("list") {
+    let handles = match store.list_ledgers().await {
+      Ok(handles) => handles,
+      Err(error) => {
+        eprintln!("list failed ({:?})", error);
+        std::process::exit(1);
+      },
+    };
+    for handle in handles {
+      let name = base64_url::encode(&handle.to_bytes());
+      match store.read_ledger_tail(&handle).await {
+        Ok((_tail, height)) => println!("{} {}", name, height),
+        Err(error) => println!("{} ({:?})", name, error),
+      }
+    }
+  }
+  if let Some(x) = cli_matches.value_of("dump") {
+    let handle_opt = if x == VIEW_LEDGER {
+      None
+    } else {
+      Some(parse_handle(x))
+    };
+    let from = parse_index(cli_matches.value_of("from"), "from");
+    let to = parse_index(cli_matches.value_of("to"), "to");
+    let json = cli_matches.is_present("json");
+    if let Err(error) = dump(&store, handle_opt.as_ref(), from, to, json).await {
+      eprintln!("dump failed: {}", error);
+      std::process::exit(1);
+    }
+  }
+  if cli_matches.is_present("verify") {
+    let handles = match cli_matches.value_of("verify") {
+      Some(x) => vec![parse_handle(x)],
+      None => match store.list_ledgers().await {
+        Ok(handles) => handles,
+        Err(error) => {
+          eprintln!("verify failed to list ledgers ({:?})", error);
+          std::process::exit(1);
+        },
+      },
+    };
+    if !verify(&store, handles).await {
+      std::process::exit(1);
+    }
+  }
+  if let Some(x) = cli_matches.value_of("export") {
+    match export(&store, x).await {
+      Ok(report) => println!(
+        "exported {} views and {} ledgers with {} entries to {}",
+        report.views, report.ledgers, report.entries, x
+      ),
+      Err(error) => {
+        eprintln!("export failed: {}", error);
+        std::process::exit(1);
+      },
+    }
+  }
+  if let Some(x) = cli_matches.value_of("import") {
+    match import(&store, x).await {
+      Ok(report) => println!(
+        "imported {} views and {} ledgers with {} entries from {}",
+        report.views, report.ledgers, report.entries, x
+      ),
+      Err(error) => {
+        eprintln!("import failed: {}", error);
+        std::process::exit(1);
+      },
+    }
+  }
+}
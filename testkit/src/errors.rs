@@ -0,0 +1,19 @@
+use coordinator::errors::CoordinatorError;
+use endorser::errors::EndorserError;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TestkitError {
+  /// returned if no endorser of the harness has the given index
+  InvalidEndorserIndex,
+  /// returned if an endorser is restarted while it is running
+  EndorserRunning,
+  /// returned if the key of an endorser is asked for but the endorser keeps it in its storage
+  KeyNotAvailable,
+  /// returned if the harness fails to acquire the lock on an endorser
+  FailedToAcquireLock,
+  /// returned if an endorser fails to start or to restore its state; carries the endorser's error
+  FailedToStartEndorser(EndorserError),
+  /// returned if the coordinator fails to start or to set up the first view; carries the
+  /// coordinator's error
+  FailedToStartCoordinator(CoordinatorError),
+}
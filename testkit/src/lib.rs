@@ -0,0 +1,1109 @@
+//! A harness that runs a coordinator and its endorsers in one process, for tests of the protocol
+//! from end to end without launching binaries. The coordinator reaches each endorser's gRPC
+//! service over an in-memory connection, so the calls take the same path as over the network,
+//! and a test can drop or delay the calls to an endorser and crash and restart it:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), nimble_testkit::TestkitError> {
+//! use nimble_testkit::{Fault, Testkit};
+//! let testkit = Testkit::new(3).await?;
+//! testkit.set_fault(0, Fault::Drop)?;
+//! let receipts = testkit
+//!   .coordinator()
+//!   .create_ledger(None, b"handle", b"genesis")
+//!   .await
+//!   .unwrap();
+//! assert_eq!(receipts.get_signer_ids().len(), 2);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The harness injects no faults of its own: which calls fail, and when, is up to the test.
+mod errors;
+
+pub use crate::errors::TestkitError;
+
+use coordinator::coordinator_state::{CoordinatorState, EndorserConnector};
+use endorser::{service::EndorserServiceState, signer::SoftwareSigner};
+use ledger::{
+  endorser_proto::endorser_call_server::EndorserCallServer,
+  signature::{PrivateKey, SignatureScheme},
+  zeroize::Zeroizing,
+  ENDORSER_LOCKED_DETAILS,
+};
+use std::{
+  convert::Infallible,
+  future::Future,
+  io,
+  path::{Path, PathBuf},
+  pin::Pin,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, RwLock,
+  },
+  task::{Context, Poll},
+  time::Duration,
+};
+use store::ledger::in_memory::InMemoryLedgerStore;
+use tokio::{io::DuplexStream, sync::mpsc};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{
+  body::BoxBody,
+  codegen::Bytes,
+  transport::{NamedService, Server, Uri},
+  Code, Status,
+};
+use tower::Service;
+
+// endorser i is reached at http://endorser-i
+const ENDORSER_HOST_PREFIX: &str = "endorser-";
+const CONNECTION_BUFFER: usize = 64 * 1024; // bytes buffered in each direction of a connection
+
+type EndorserServer = EndorserCallServer<EndorserServiceState>;
+type KeyPem = Option<Zeroizing<Vec<u8>>>;
+
+/// A fault injected into the calls that an endorser receives
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fault {
+  /// the calls are served
+  None,
+  /// the calls are lost, and the coordinator sees them time out
+  Drop,
+  /// the calls are served after the delay
+  Delay(Duration),
+  /// the next calls, as many as given, are refused as by an endorser locked for a view change
+  Locked(usize),
+}
+
+// An endorser of the harness. The connections opened to it outlive its crashes, as a crash is
+// seen by the coordinator through the calls on them failing.
+struct Endorser {
+  // the directory in which the endorser persists its state, if it does
+  storage: Option<PathBuf>,
+  // the endorser's service, which is None while the endorser is crashed
+  service: RwLock<Option<EndorserServer>>,
+  // the PEM encoding of the key that an endorser which does not persist its state started with
+  key: RwLock<KeyPem>,
+  fault: RwLock<Fault>,
+  calls: AtomicUsize,
+  // hands the server ends of new connections to the endorser's server
+  connections: mpsc::UnboundedSender<Result<DuplexStream, io::Error>>,
+}
+
+impl Endorser {
+  // starts an endorser, which restores its state from `storage` if there is one
+  fn start(storage: Option<PathBuf>) -> Result<Arc<Endorser>, TestkitError> {
+    let (connections, incoming) = mpsc::unbounded_channel();
+    let (service, key) = new_service(&storage)?;
+    let endorser = Arc::new(Endorser {
+      service: RwLock::new(Some(service)),
+      key: RwLock::new(key),
+      storage,
+      fault: RwLock::new(Fault::None),
+      calls: AtomicUsize::new(0),
+      connections,
+    });
+    let server = Server::builder()
+      .add_service(EndorserService(endorser.clone()))
+      .serve_with_incoming(UnboundedReceiverStream::new(incoming));
+    tokio::spawn(server);
+    Ok(endorser)
+  }
+
+  fn connect(&self) -> Result<DuplexStream, io::Error> {
+    let running = match self.service.read() {
+      Ok(service) => service.is_some(),
+      Err(_) => false,
+    };
+    if !running {
+      return Err(io::Error::new(
+        io::ErrorKind::ConnectionRefused,
+        "the endorser crashed",
+      ));
+    }
+    let (client, server) = tokio::io::duplex(CONNECTION_BUFFER);
+    if self.connections.send(Ok(server)).is_err() {
+      return Err(io::Error::new(
+        io::ErrorKind::ConnectionRefused,
+        "the endorser's server stopped",
+      ));
+    }
+    Ok(client)
+  }
+}
+
+// Starts an endorser's service, and returns it with the PEM encoding of its key if the endorser
+// keeps the key in memory only
+fn new_service(storage: &Option<PathBuf>) -> Result<(EndorserServer, KeyPem), TestkitError> {
+  let (res, key) = match storage {
+    Some(dir) => (
+      EndorserServiceState::with_storage(dir, SignatureScheme::P256),
+      None,
+    ),
+    None => {
+      let key = PrivateKey::generate(SignatureScheme::P256);
+      let pem = key.to_pem();
+      (
+        EndorserServiceState::with_signer(Box::new(SoftwareSigner::new(key)), None),
+        Some(pem),
+      )
+    },
+  };
+  if let Err(error) = res {
+    return Err(TestkitError::FailedToStartEndorser(error));
+  }
+  Ok((EndorserCallServer::new(res.unwrap()), key))
+}
+
+// The endorser's service as its server sees it, which applies the injected faults before the
+// endorser serves a call
+#[derive(Clone)]
+struct EndorserService(Arc<Endorser>);
+
+impl Service<http::Request<hyper::Body>> for EndorserService {
+  type Response = http::Response<BoxBody>;
+  type Error = Infallible;
+  type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+  fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future {
+    let endorser = &self.0;
+    endorser.calls.fetch_add(1, Ordering::SeqCst);
+    let fault = match endorser.fault.write() {
+      Ok(mut fault) => {
+        let current = *fault;
+        // a locked endorser refuses as many calls as it was given, and is unlocked afterwards
+        if let Fault::Locked(calls) = current {
+          *fault = if calls > 1 {
+            Fault::Locked(calls - 1)
+          } else {
+            Fault::None
+          };
+        }
+        current
+      },
+      Err(_) => Fault::None,
+    };
+    let service = endorser.service.read().ok().and_then(|s| s.clone());
+    Box::pin(async move {
+      // a crashed endorser closes its connections, which gRPC reports as a transport error
+      let mut service = match service {
+        Some(service) => service,
+        None => return Ok(Status::unknown("transport error").to_http()),
+      };
+      match fault {
+        Fault::None => {},
+        Fault::Drop => return Ok(Status::deadline_exceeded("the call was dropped").to_http()),
+        Fault::Delay(delay) => tokio::time::sleep(delay).await,
+        Fault::Locked(_) => {
+          let status = Status::with_details(
+            Code::Unavailable,
+            "Endorser is locked for view change",
+            Bytes::from_static(ENDORSER_LOCKED_DETAILS),
+          );
+          return Ok(status.to_http());
+        },
+      }
+      service.call(request).await
+    })
+  }
+}
+
+impl NamedService for EndorserService {
+  const NAME: &'static str = <EndorserCallServer<EndorserServiceState> as NamedService>::NAME;
+}
+
+fn endorser_index(uri: &Uri) -> Option<usize> {
+  uri
+    .host()
+    .and_then(|host| host.strip_prefix(ENDORSER_HOST_PREFIX))
+    .and_then(|index| index.parse().ok())
+}
+
+/// A coordinator with an in-memory ledger store and in-process endorsers
+pub struct Testkit {
+  coordinator: CoordinatorState,
+  store: InMemoryLedgerStore,
+  endorsers: Arc<RwLock<Vec<Arc<Endorser>>>>,
+  // the directory under which the endorsers persist their state, if they do
+  storage: Option<PathBuf>,
+}
+
+impl Testkit {
+  /// Starts a coordinator whose first view consists of `num_endorsers` endorsers
+  pub async fn new(num_endorsers: usize) -> Result<Testkit, TestkitError> {
+    Testkit::start(num_endorsers, None).await
+  }
+
+  /// Like `new`, but every endorser persists its state in a directory of its own under `dir`,
+  /// so that it keeps its key and its ledgers when it restarts after a crash
+  pub async fn with_storage(num_endorsers: usize, dir: &Path) -> Result<Testkit, TestkitError> {
+    Testkit::start(num_endorsers, Some(dir.to_path_buf())).await
+  }
+
+  async fn start(num_endorsers: usize, storage: Option<PathBuf>) -> Result<Testkit, TestkitError> {
+    let store = InMemoryLedgerStore::new();
+    let endorsers = Arc::new(RwLock::new(Vec::new()));
+    let coordinator = Testkit::open_coordinator(&store, &endorsers).await?;
+    let testkit = Testkit {
+      coordinator,
+      store,
+      endorsers,
+      storage,
+    };
+    let uris = testkit.add_endorsers(num_endorsers)?;
+    let res = testkit.coordinator.replace_endorsers(&uris).await;
+    if let Err(error) = res {
+      return Err(TestkitError::FailedToStartCoordinator(error));
+    }
+    Ok(testkit)
+  }
+
+  async fn open_coordinator(
+    store: &InMemoryLedgerStore,
+    endorsers: &Arc<RwLock<Vec<Arc<Endorser>>>>,
+  ) -> Result<CoordinatorState, TestkitError> {
+    let endorsers = endorsers.clone();
+    let connector: EndorserConnector = Arc::new(move |uri: Uri| {
+      let endorser = endorser_index(&uri).and_then(|index| match endorsers.read() {
+        Ok(endorsers) => endorsers.get(index).cloned(),
+        Err(_) => None,
+      });
+      Box::pin(async move {
+        match endorser {
+          Some(endorser) => endorser.connect(),
+          None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no endorser at {}", uri),
+          )),
+        }
+      })
+    });
+    let res = CoordinatorState::with_store_and_connector(Box::new(store.clone()), connector).await;
+    if let Err(error) = res {
+      return Err(TestkitError::FailedToStartCoordinator(error));
+    }
+    Ok(res.unwrap())
+  }
+
+  pub fn coordinator(&self) -> &CoordinatorState {
+    &self.coordinator
+  }
+
+  /// the ledger store, which outlives the coordinator across its restarts
+  pub fn store(&self) -> &InMemoryLedgerStore {
+    &self.store
+  }
+
+  /// the URI at which the coordinator reaches endorser `index`
+  pub fn endorser_uri(index: usize) -> String {
+    format!("http://{}{}", ENDORSER_HOST_PREFIX, index)
+  }
+
+  /// Starts `num_endorsers` more endorsers and returns their URIs; they serve no view until a
+  /// view change lists them
+  pub fn add_endorsers(&self, num_endorsers: usize) -> Result<Vec<String>, TestkitError> {
+    let mut endorsers = self
+      .endorsers
+      .write()
+      .map_err(|_e| TestkitError::FailedToAcquireLock)?;
+    let mut uris = Vec::new();
+    for _ in 0..num_endorsers {
+      let index = endorsers.len();
+      let storage = self
+        .storage
+        .as_ref()
+        .map(|dir| dir.join(format!("{}{}", ENDORSER_HOST_PREFIX, index)));
+      endorsers.push(Endorser::start(storage)?);
+      uris.push(Testkit::endorser_uri(index));
+    }
+    Ok(uris)
+  }
+
+  fn endorser(&self, index: usize) -> Result<Arc<Endorser>, TestkitError> {
+    let endorsers = self
+      .endorsers
+      .read()
+      .map_err(|_e| TestkitError::FailedToAcquireLock)?;
+    match endorsers.get(index) {
+      Some(endorser) => Ok(endorser.clone()),
+      None => Err(TestkitError::InvalidEndorserIndex),
+    }
+  }
+
+  /// Applies `fault` to the calls that endorser `index` receives from now on
+  pub fn set_fault(&self, index: usize, fault: Fault) -> Result<(), TestkitError> {
+    let endorser = self.endorser(index)?;
+    let mut current = endorser
+      .fault
+      .write()
+      .map_err(|_e| TestkitError::FailedToAcquireLock)?;
+    *current = fault;
+    Ok(())
+  }
+
+  /// the number of calls that endorser `index` received, including the ones it failed
+  pub fn calls(&self, index: usize) -> Result<usize, TestkitError> {
+    Ok(self.endorser(index)?.calls.load(Ordering::SeqCst))
+  }
+
+  /// Crashes endorser `index`, which loses the state it did not persist. Its calls fail as if
+  /// their connections were closed, and new connections to it are refused.
+  pub fn crash_endorser(&self, index: usize) -> Result<(), TestkitError> {
+    let endorser = self.endorser(index)?;
+    let mut service = endorser
+      .service
+      .write()
+      .map_err(|_e| TestkitError::FailedToAcquireLock)?;
+    *service = None;
+    Ok(())
+  }
+
+  /// Restarts endorser `index` after a crash. An endorser that persists its state restores it;
+  /// one that does not starts afresh with a new key, as a new endorser would.
+  pub fn restart_endorser(&self, index: usize) -> Result<(), TestkitError> {
+    let endorser = self.endorser(index)?;
+    let mut service = endorser
+      .service
+      .write()
+      .map_err(|_e| TestkitError::FailedToAcquireLock)?;
+    if service.is_some() {
+      return Err(TestkitError::EndorserRunning);
+    }
+    let (new, key) = new_service(&endorser.storage)?;
+    *service = Some(new);
+    *endorser
+      .key
+      .write()
+      .map_err(|_e| TestkitError::FailedToAcquireLock)? = key;
+    Ok(())
+  }
+
+  /// the key with which endorser `index` started, so that a test can sign as the endorser would,
+  /// for example to make it equivocate. Only endorsers that do not persist their state have it.
+  pub fn endorser_key(&self, index: usize) -> Result<PrivateKey, TestkitError> {
+    let endorser = self.endorser(index)?;
+    let key = endorser
+      .key
+      .read()
+      .map_err(|_e| TestkitError::FailedToAcquireLock)?;
+    match key.as_ref().map(|pem| PrivateKey::from_pem(pem)) {
+      Some(Ok(key)) => Ok(key),
+      _ => Err(TestkitError::KeyNotAvailable),
+    }
+  }
+
+  /// Replaces the coordinator with a new one over the same ledger store, which resumes from the
+  /// view in the store as a restarted coordinator does
+  pub async fn restart_coordinator(&mut self) -> Result<(), TestkitError> {
+    self.coordinator = Testkit::open_coordinator(&self.store, &self.endorsers).await?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use coordinator::{
+    coordinator_state::{
+      AdminAction, AdminEvent, ADMIN_LEDGER_HANDLE, ENDORSER_LOCKED_MAX_RETRIES,
+      MAX_LEDGER_LABEL_SIZE,
+    },
+    endorser_health::{EndorserHealth, DOWN_AFTER_FAILURES},
+    errors::CoordinatorError,
+  };
+  use ledger::{
+    messages::{AppendAttestation, SignedStatement},
+    namespace::namespaced_handle,
+    signature::{PrivateKeyTrait, PublicKeyTrait},
+    AccessPolicy, CustomSerde, IdSig, MetaBlock, MisbehaviorEvidence, NimbleDigest,
+    NimbleHashTrait, ReadVisibility, Receipt,
+  };
+  use std::{collections::HashSet, time::Instant};
+  use store::ledger::{current_timestamp, LedgerStore};
+
+  // the number of endorsers that signed the entry at `index` of a ledger; appends return once a
+  // quorum signed, so the other endorsers are given time to sign first
+  async fn signers(testkit: &Testkit, handle: &[u8], index: usize) -> usize {
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let entry = testkit
+      .store()
+      .read_ledger_by_index(&NimbleDigest::digest(handle), index)
+      .await
+      .unwrap();
+    entry.get_receipts().get_signer_ids().len()
+  }
+
+  #[tokio::test]
+  async fn test_quorum_with_faults() {
+    let testkit = Testkit::new(3).await.unwrap();
+    let coordinator = testkit.coordinator();
+    let handle = b"quorum-handle";
+    coordinator
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+    assert_eq!(signers(&testkit, handle, 0).await, 3);
+
+    // an endorser whose calls are lost is routed around while a quorum remains
+    testkit.set_fault(0, Fault::Drop).unwrap();
+    let calls = testkit.calls(0).unwrap();
+    coordinator
+      .append_ledger(None, handle, b"one", 1)
+      .await
+      .unwrap();
+    assert_eq!(signers(&testkit, handle, 1).await, 2);
+    assert!(testkit.calls(0).unwrap() > calls);
+
+    // without a quorum the entry is signed by too few endorsers for clients to accept it
+    testkit.set_fault(1, Fault::Drop).unwrap();
+    let (_, receipts) = coordinator
+      .append_ledger(None, handle, b"two", 2)
+      .await
+      .unwrap();
+    assert_eq!(receipts.get_signer_ids().len(), 1);
+
+    // delayed endorsers still sign, and the endorsers that missed the last entry catch up
+    let delay = Duration::from_millis(100);
+    for index in 0..3 {
+      testkit.set_fault(index, Fault::Delay(delay)).unwrap();
+    }
+    let start = Instant::now();
+    coordinator
+      .append_ledger(None, handle, b"three", 3)
+      .await
+      .unwrap();
+    assert!(start.elapsed() >= delay);
+    assert_eq!(signers(&testkit, handle, 3).await, 3);
+
+    assert_eq!(
+      testkit.set_fault(3, Fault::None),
+      Err(TestkitError::InvalidEndorserIndex)
+    );
+  }
+
+  #[tokio::test]
+  async fn test_locked_endorser_retries() {
+    let testkit = Testkit::new(3).await.unwrap();
+    let coordinator = testkit.coordinator();
+    let handle = b"locked-handle";
+    coordinator
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+
+    // an endorser locked for a view change is retried, and signs once it is unlocked
+    testkit.set_fault(0, Fault::Locked(1)).unwrap();
+    let calls = testkit.calls(0).unwrap();
+    coordinator
+      .append_ledger(None, handle, b"one", 1)
+      .await
+      .unwrap();
+    assert_eq!(signers(&testkit, handle, 1).await, 3);
+    assert!(testkit.calls(0).unwrap() >= calls + 2);
+
+    // an endorser that stays locked through the retries is routed around but stays connected
+    let retries = ENDORSER_LOCKED_MAX_RETRIES;
+    testkit.set_fault(0, Fault::Locked(retries + 1)).unwrap();
+    let calls = testkit.calls(0).unwrap();
+    let (_, receipts) = coordinator
+      .append_ledger(None, handle, b"two", 2)
+      .await
+      .unwrap();
+    assert_eq!(receipts.get_signer_ids().len(), 2);
+    let start = Instant::now();
+    while testkit.calls(0).unwrap() < calls + retries + 1
+      && start.elapsed() < Duration::from_secs(5)
+    {
+      tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(testkit.calls(0).unwrap(), calls + retries + 1);
+    assert_eq!(signers(&testkit, handle, 2).await, 2);
+    assert_eq!(coordinator.get_endorser_pks().len(), 3);
+  }
+
+  #[tokio::test]
+  async fn test_crash_and_view_change() {
+    let dir = std::env::temp_dir().join(format!("nimble-testkit-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let testkit = Testkit::with_storage(3, &dir).await.unwrap();
+    let coordinator = testkit.coordinator();
+    let handle = b"crash-handle";
+    coordinator
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+
+    // a crashed endorser does not sign
+    testkit.crash_endorser(2).unwrap();
+    coordinator
+      .append_ledger(None, handle, b"one", 1)
+      .await
+      .unwrap();
+    assert_eq!(signers(&testkit, handle, 1).await, 2);
+    assert_eq!(
+      testkit.restart_endorser(1),
+      Err(TestkitError::EndorserRunning)
+    );
+
+    // the restarted endorser restores its state and is readmitted into the view, if the
+    // coordinator stopped sending to it, and catches up
+    testkit.restart_endorser(2).unwrap();
+    let uri = Testkit::endorser_uri(2);
+    assert_eq!(coordinator.readmit_endorsers(&[uri]).await.unwrap(), 3);
+    coordinator
+      .append_ledger(None, handle, b"two", 2)
+      .await
+      .unwrap();
+    assert_eq!(signers(&testkit, handle, 2).await, 3);
+
+    // a view change moves the ledgers to new endorsers
+    let uris = testkit.add_endorsers(2).unwrap();
+    coordinator.replace_endorsers(&uris).await.unwrap();
+    assert_eq!(coordinator.get_view_height().unwrap(), 2);
+    let mut endorser_uris = coordinator.get_endorser_uris();
+    endorser_uris.sort();
+    assert_eq!(endorser_uris, uris);
+    coordinator
+      .append_ledger(None, handle, b"three", 3)
+      .await
+      .unwrap();
+    assert_eq!(signers(&testkit, handle, 3).await, 2);
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+
+  #[tokio::test]
+  async fn test_restart_coordinator() {
+    let mut testkit = Testkit::new(2).await.unwrap();
+    let handle = b"restart-handle";
+    testkit
+      .coordinator()
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+
+    // the restarted coordinator reconnects to the endorsers of the view in the store
+    testkit.restart_coordinator().await.unwrap();
+    let coordinator = testkit.coordinator();
+    assert_eq!(coordinator.get_view_height().unwrap(), 1);
+    assert_eq!(coordinator.get_endorser_pks().len(), 2);
+    let (_, receipts) = coordinator
+      .append_ledger(None, handle, b"one", 1)
+      .await
+      .unwrap();
+    assert_eq!(receipts.get_signer_ids().len(), 2);
+
+    // appends that skip a height are rejected as they would be over the network
+    let res = coordinator.append_ledger(None, handle, b"three", 3).await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::HeightMismatch);
+  }
+
+  #[tokio::test]
+  async fn test_endorser_allowlist() {
+    let mut testkit = Testkit::new(3).await.unwrap();
+    let uris = testkit.add_endorsers(2).unwrap();
+    let allowlist = HashSet::from([testkit
+      .endorser_key(3)
+      .unwrap()
+      .get_public_key()
+      .unwrap()
+      .to_bytes()]);
+    let coordinator = testkit.coordinator();
+    coordinator
+      .set_endorser_allowlist(allowlist.clone())
+      .await
+      .unwrap();
+
+    // the endorsers of the current view stay allowed
+    let first = Testkit::endorser_uri(0);
+    coordinator
+      .remove_endorsers(std::slice::from_ref(&first))
+      .await
+      .unwrap();
+    let res = coordinator
+      .readmit_endorsers(std::slice::from_ref(&first))
+      .await;
+    assert_eq!(res, Ok(3));
+
+    // a restarted coordinator does not reconnect to an endorser that came back with another key
+    testkit.crash_endorser(0).unwrap();
+    testkit.restart_endorser(0).unwrap();
+    let new_key = testkit.endorser_key(0).unwrap().get_public_key().unwrap();
+    testkit.restart_coordinator().await.unwrap();
+    let coordinator = testkit.coordinator();
+    let pks = coordinator.get_endorser_pks();
+    assert_eq!(pks.len(), 2);
+    assert!(!pks.contains(&new_key.to_bytes()));
+
+    // an endorser whose key is not listed is not admitted to a view
+    coordinator.set_endorser_allowlist(allowlist).await.unwrap();
+    let res = coordinator.replace_endorsers(&uris[1..]).await;
+    assert_eq!(res, Err(CoordinatorError::NoNewEndorsers));
+    coordinator.replace_endorsers(&uris).await.unwrap();
+    assert_eq!(coordinator.get_endorser_uris(), vec![uris[0].clone()]);
+  }
+
+  #[tokio::test]
+  async fn test_list_ledgers() {
+    let testkit = Testkit::new(1).await.unwrap();
+    let coordinator = testkit.coordinator();
+    let handles = (0..5)
+      .map(|i| format!("list-handle-{}", i))
+      .collect::<Vec<_>>();
+    for handle in &handles {
+      coordinator
+        .create_ledger(None, handle.as_bytes(), b"app bytes")
+        .await
+        .unwrap();
+    }
+    coordinator
+      .append_ledger(None, handles[0].as_bytes(), b"one", 1)
+      .await
+      .unwrap();
+    // a ledger that only its writers may read is not listed
+    let writer = PrivateKey::new().get_public_key().unwrap().to_bytes();
+    let private = AccessPolicy::new(vec![writer], ReadVisibility::Writers);
+    coordinator
+      .create_ledger(None, b"private", &private.to_genesis_bytes(b"app bytes"))
+      .await
+      .unwrap();
+
+    let mut listed = Vec::new();
+    let mut cursor = None;
+    loop {
+      let (ledgers, next) = coordinator
+        .list_ledgers(cursor.as_ref(), 2, None)
+        .await
+        .unwrap();
+      assert!(ledgers.len() <= 2);
+      listed.extend(ledgers);
+      match next {
+        Some(next) => cursor = Some(next),
+        None => break,
+      }
+    }
+    // the admin ledger, which recorded the first view change, is listed with the others
+    let mut expected = handles
+      .iter()
+      .map(|handle| NimbleDigest::digest(handle.as_bytes()))
+      .chain([NimbleDigest::digest(ADMIN_LEDGER_HANDLE)])
+      .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(
+      listed
+        .iter()
+        .map(|ledger| ledger.handle)
+        .collect::<Vec<_>>(),
+      expected
+    );
+    let first = listed
+      .iter()
+      .find(|ledger| ledger.handle == NimbleDigest::digest(handles[0].as_bytes()))
+      .unwrap();
+    assert_eq!(first.height, 1);
+    assert!(first.created_at.is_some());
+    assert_eq!(first.app_bytes_digest, NimbleDigest::digest(b"app bytes"));
+  }
+
+  #[tokio::test]
+  async fn test_ledger_namespaces() {
+    let testkit = Testkit::new(1).await.unwrap();
+    let coordinator = testkit.coordinator();
+    // two tenants that pick the same handle get ledgers of their own
+    let acme = namespaced_handle("acme", b"orders");
+    let globex = namespaced_handle("globex", b"orders");
+    for handle in [&acme, &globex] {
+      coordinator
+        .create_ledger(None, handle, b"app bytes")
+        .await
+        .unwrap();
+    }
+    coordinator
+      .append_ledger(None, &acme, b"one", 1)
+      .await
+      .unwrap();
+    coordinator
+      .create_ledger(None, b"orders", b"app bytes")
+      .await
+      .unwrap();
+
+    let namespaces = HashSet::from([String::from("acme")]);
+    let (ledgers, next) = coordinator
+      .list_ledgers(None, 0, Some(&namespaces))
+      .await
+      .unwrap();
+    assert_eq!(next, None);
+    assert_eq!(ledgers.len(), 1);
+    assert_eq!(ledgers[0].handle, NimbleDigest::digest(&acme));
+    assert_eq!(ledgers[0].height, 1);
+    let genesis = coordinator.read_ledger_by_index(&globex, 0).await.unwrap();
+    assert!(!genesis.get_receipts().is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_ledger_labels() {
+    let testkit = Testkit::new(1).await.unwrap();
+    let coordinator = testkit.coordinator();
+    coordinator
+      .create_labeled_ledger(b"orders-handle", b"genesis", "orders")
+      .await
+      .unwrap();
+    assert_eq!(
+      coordinator.get_ledger_by_label("orders").await.unwrap(),
+      b"orders-handle"
+    );
+    assert_eq!(
+      coordinator.get_ledger_by_label("invoices").await,
+      Err(CoordinatorError::UnknownLabel)
+    );
+
+    // a label names one ledger, and a taken label does not create another
+    let res = coordinator
+      .create_labeled_ledger(b"other-handle", b"genesis", "orders")
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::LabelAlreadyTaken);
+    let res = coordinator.get_ledger_height(b"other-handle").await;
+    assert_eq!(res, Err(CoordinatorError::UnknownLedger));
+
+    // a creation that fails does not keep the label, unless it retries one that bound it
+    let res = coordinator
+      .create_labeled_ledger(b"orders-handle", b"genesis", "invoices")
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::LedgerAlreadyExists);
+    assert!(coordinator.get_ledger_by_label("invoices").await.is_err());
+    let res = coordinator
+      .create_labeled_ledger(b"orders-handle", b"genesis", "orders")
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::LedgerAlreadyExists);
+    assert!(coordinator.get_ledger_by_label("orders").await.is_ok());
+
+    let long_label = "l".repeat(MAX_LEDGER_LABEL_SIZE + 1);
+    let res = coordinator
+      .create_labeled_ledger(b"long-handle", b"genesis", &long_label)
+      .await;
+    assert_eq!(res.unwrap_err(), CoordinatorError::InvalidLabel);
+  }
+
+  #[tokio::test]
+  async fn test_pipelined_appends() {
+    const WORKERS: usize = 4;
+    const APPENDS: usize = 16;
+
+    let testkit = Testkit::new(3).await.unwrap();
+    let coordinator = testkit.coordinator();
+    coordinator.set_append_pipeline_depth(APPENDS).unwrap();
+    let handle = b"pipelined-handle";
+    coordinator
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+
+    // slow endorsers make appends queue behind the round in flight
+    for index in 0..3 {
+      testkit
+        .set_fault(index, Fault::Delay(Duration::from_millis(50)))
+        .unwrap();
+    }
+    let calls = testkit.calls(0).unwrap();
+    // each worker appends every WORKERS-th height, once the height before it is stored
+    let append = |first: usize| async move {
+      let mut height = first;
+      while height <= APPENDS {
+        let block = format!("block {}", height);
+        let res = coordinator
+          .append_ledger(None, handle, block.as_bytes(), height)
+          .await;
+        match res {
+          Ok((_hash_nonces, receipts)) => {
+            let metablock = receipts.get_metablock().unwrap();
+            assert_eq!(metablock.get_height(), height);
+            assert!(receipts.get_signer_ids().len() >= 2);
+            height += WORKERS;
+          },
+          Err(CoordinatorError::HeightMismatch) => {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+          },
+          Err(error) => panic!("Failed to append at height {} ({:?})", height, error),
+        }
+      }
+    };
+    tokio::join!(append(1), append(2), append(3), append(4));
+
+    // the appends shared rounds, and every entry is chained onto the one before it
+    assert!(testkit.calls(0).unwrap() - calls < APPENDS);
+    let digest = NimbleDigest::digest(handle);
+    let mut prev = testkit
+      .store()
+      .read_ledger_by_index(&digest, 0)
+      .await
+      .unwrap()
+      .get_receipts()
+      .get_metablock()
+      .unwrap();
+    for index in 1..=APPENDS {
+      let entry = testkit
+        .store()
+        .read_ledger_by_index(&digest, index)
+        .await
+        .unwrap();
+      let metablock = entry.get_receipts().get_metablock().unwrap();
+      assert_eq!(*metablock.get_prev(), prev.hash());
+      prev = metablock;
+    }
+  }
+
+  #[tokio::test]
+  async fn test_expel_equivocating_endorser() {
+    let testkit = Testkit::new(3).await.unwrap();
+    let coordinator = testkit.coordinator();
+    let handle = b"equivocation-handle";
+    coordinator
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, handle, b"one", 1)
+      .await
+      .unwrap();
+    assert_eq!(signers(&testkit, handle, 1).await, 3);
+
+    // endorser 0 signs a second, conflicting entry at height 1
+    let key = testkit.endorser_key(0).unwrap();
+    let pk = key.get_public_key().unwrap();
+    let entry = testkit
+      .store()
+      .read_ledger_by_index(&NimbleDigest::digest(handle), 1)
+      .await
+      .unwrap();
+    let (ex_meta_block, _id_sigs) = entry.get_receipts().get().iter().next().unwrap();
+    let view = *ex_meta_block.get_view();
+    let signed = ex_meta_block.get_metablock();
+    let group_identity = testkit
+      .store()
+      .read_view_ledger_by_index(1)
+      .await
+      .unwrap()
+      .get_block()
+      .hash();
+    let sign = |metablock: &MetaBlock, group_identity: &NimbleDigest| {
+      let message = AppendAttestation::new(
+        group_identity,
+        &view,
+        &NimbleDigest::digest(handle),
+        &metablock.hash(),
+      );
+      let sig = key.sign(&message.message().to_bytes()).unwrap();
+      Receipt::new(view, metablock.clone(), IdSig::new(pk.clone(), sig))
+    };
+    let forged = MetaBlock::new(
+      signed.get_prev(),
+      &NimbleDigest::digest(b"forged"),
+      signed.get_height(),
+    );
+    let evidence = MisbehaviorEvidence::new(
+      &NimbleDigest::digest(handle),
+      &sign(signed, &group_identity),
+      &sign(&forged, &group_identity),
+    )
+    .unwrap();
+
+    // evidence signed for another deployment is rejected
+    let foreign = MisbehaviorEvidence::new(
+      &NimbleDigest::digest(handle),
+      &sign(signed, &view),
+      &sign(&forged, &view),
+    )
+    .unwrap();
+    assert_eq!(
+      coordinator.report_misbehavior(foreign),
+      Err(CoordinatorError::InvalidMisbehaviorEvidence)
+    );
+
+    // the evidence is recorded and the endorser leaves the view, which the others keep serving
+    coordinator.report_misbehavior(evidence.clone()).unwrap();
+    let expelled = coordinator.handle_misbehavior().await.unwrap();
+    assert_eq!(expelled, vec![Testkit::endorser_uri(0)]);
+    assert!(coordinator.handle_misbehavior().await.unwrap().is_empty());
+    assert_eq!(coordinator.get_view_height().unwrap(), 2);
+    assert!(!coordinator.get_endorser_pks().contains(&pk.to_bytes()));
+    assert_eq!(coordinator.get_endorser_pks().len(), 2);
+    assert_eq!(
+      coordinator
+        .read_misbehavior_evidence(0)
+        .await
+        .unwrap()
+        .to_bytes(),
+      evidence.to_bytes()
+    );
+    let (_, receipts) = coordinator
+      .append_ledger(None, handle, b"two", 2)
+      .await
+      .unwrap();
+    assert_eq!(receipts.get_signer_ids().len(), 2);
+
+    // the expulsion is in the admin ledger, after the first view change
+    let entry = coordinator.read_admin_event(1).await.unwrap();
+    let event: AdminEvent = serde_json::from_slice(&entry.get_block().to_bytes()).unwrap();
+    assert_eq!(
+      event.action,
+      AdminAction::ExpelEndorser {
+        uri: Testkit::endorser_uri(0),
+        evidence_index: 0,
+        view_height: 2,
+      }
+    );
+  }
+
+  #[tokio::test]
+  async fn test_reconnect_to_restarted_endorser() {
+    let dir = std::env::temp_dir().join(format!("nimble-testkit-reconnect-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let testkit = Testkit::with_storage(3, &dir).await.unwrap();
+    let coordinator = testkit.coordinator();
+    let uri = Testkit::endorser_uri(0);
+    let health = |uri: &str| {
+      coordinator
+        .get_endorser_health()
+        .into_iter()
+        .find(|report| report.uri == uri)
+        .unwrap()
+    };
+    assert!(coordinator.is_quorum_reachable().await);
+    assert_eq!(health(&uri).health, EndorserHealth::Healthy);
+
+    // a crashed endorser is degraded, then down once it failed enough probes in a row
+    testkit.crash_endorser(0).unwrap();
+    assert!(coordinator.is_quorum_reachable().await);
+    assert_eq!(health(&uri).health, EndorserHealth::Degraded);
+    for _ in 1..DOWN_AFTER_FAILURES {
+      assert!(coordinator.is_quorum_reachable().await);
+    }
+    let report = health(&uri);
+    assert_eq!(report.health, EndorserHealth::Down);
+    assert_eq!(report.consecutive_failures, DOWN_AFTER_FAILURES);
+    assert!(report.last_error.is_some());
+    assert_eq!(
+      health(&Testkit::endorser_uri(1)).health,
+      EndorserHealth::Healthy
+    );
+
+    // the coordinator reconnects once the backoff elapsed, and not while the endorser is down
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(coordinator.reconnect_endorsers().await.is_empty());
+    assert_eq!(health(&uri).consecutive_failures, DOWN_AFTER_FAILURES + 1);
+    testkit.restart_endorser(0).unwrap();
+    assert!(coordinator.reconnect_endorsers().await.is_empty());
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    assert_eq!(coordinator.reconnect_endorsers().await, vec![uri.clone()]);
+    let report = health(&uri);
+    assert_eq!(report.health, EndorserHealth::Healthy);
+    assert_eq!(report.consecutive_failures, 0);
+    assert_eq!(report.reconnects, 1);
+    assert!(coordinator.is_quorum_reachable().await);
+    assert_eq!(health(&uri).health, EndorserHealth::Healthy);
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+
+  #[tokio::test]
+  async fn test_drain() {
+    let testkit = Testkit::new(3).await.unwrap();
+    let coordinator = testkit.coordinator();
+    let handle = b"drain-handle";
+    coordinator
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+
+    // the receipt of an endorser that answers after the quorum is attached before the drain ends
+    testkit
+      .set_fault(2, Fault::Delay(Duration::from_millis(300)))
+      .unwrap();
+    coordinator
+      .append_ledger(None, handle, b"one", 1)
+      .await
+      .unwrap();
+    let guard = coordinator.drain(Duration::from_secs(10)).await.unwrap();
+    let entry = testkit
+      .store()
+      .read_ledger_by_index(&NimbleDigest::digest(handle), 1)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_receipts().get_signer_ids().len(), 3);
+
+    // no view change starts while the coordinator exits
+    testkit.set_fault(2, Fault::None).unwrap();
+    let uris = testkit.add_endorsers(1).unwrap();
+    let view_change = coordinator.replace_endorsers(&uris);
+    tokio::pin!(view_change);
+    assert!(
+      tokio::time::timeout(Duration::from_millis(200), &mut view_change)
+        .await
+        .is_err()
+    );
+    drop(guard);
+    view_change.await.unwrap();
+    assert_eq!(coordinator.get_view_height().unwrap(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_attested_timestamps() {
+    let testkit = Testkit::new(3).await.unwrap();
+    let coordinator = testkit.coordinator();
+    coordinator.set_attest_timestamps(true).unwrap();
+    let handle = b"timestamp-handle";
+    coordinator
+      .create_ledger(None, handle, b"genesis")
+      .await
+      .unwrap();
+    let group_identity = testkit
+      .store()
+      .read_view_ledger_by_index(1)
+      .await
+      .unwrap()
+      .get_block()
+      .hash();
+
+    // the endorsers sign the timestamp of the append with its metablock
+    let before = current_timestamp();
+    testkit.set_fault(0, Fault::Drop).unwrap();
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(None, handle, b"one", 1)
+      .await
+      .unwrap();
+    let metablock = receipts.get_metablock().unwrap();
+    let timestamp = metablock.get_timestamp().unwrap();
+    assert!(before <= timestamp && timestamp <= current_timestamp());
+    for (ex_meta_block, id_sigs) in receipts.get() {
+      let message = AppendAttestation::for_metablock(
+        &group_identity,
+        ex_meta_block.get_view(),
+        &NimbleDigest::digest(handle),
+        ex_meta_block.get_metablock(),
+      );
+      for id_sig in id_sigs {
+        assert!(message.verify(id_sig).is_ok());
+      }
+    }
+
+    // the endorser that catches up on the entry cannot attest to its time, so its receipt for
+    // the entry is not kept, while the next entry is signed by all with a later timestamp
+    testkit.set_fault(0, Fault::None).unwrap();
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(None, handle, b"two", 2)
+      .await
+      .unwrap();
+    let next = receipts.get_metablock().unwrap();
+    assert_eq!(*next.get_prev(), metablock.hash());
+    assert!(next.get_timestamp().unwrap() >= timestamp);
+    assert_eq!(signers(&testkit, handle, 2).await, 3);
+    let entry = testkit
+      .store()
+      .read_ledger_by_index(&NimbleDigest::digest(handle), 1)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_receipts().get_metablock().unwrap(), metablock);
+  }
+}
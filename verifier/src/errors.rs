@@ -0,0 +1,34 @@
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VerifierError {
+  /// returned if a view change past the first view is applied before the group identity is known
+  MissingGroupIdentity,
+  /// returned if the receipts of a view change are not signed by the endorsers of the view
+  InvalidViewChange,
+  /// returned if receipts are issued in a view that the verifier has not applied
+  UnknownView,
+  /// returned if no metablock is signed by a quorum of the endorsers of its view
+  NoQuorum,
+  /// returned if the endorsed block hash does not match the block and nonces in the response
+  InvalidBlockHash,
+  /// returned if the endorsed height does not match the expected height
+  InvalidHeight,
+  /// returned if a receipt carries a signature that does not verify
+  InvalidSignature,
+  /// returned if a read is neither signed over the client's nonce nor includes it in the nonces
+  NonceNotIncluded,
+  /// returned if a read returns an entry older than one the verifier already saw endorsed
+  StaleRead,
+  /// returned if two different entries are endorsed at the same height of a ledger
+  ForkDetected,
+  /// returned if the response cannot be deserialized
+  MalformedResponse,
+  /// returned if an inclusion proof does not link the entry to the tail read that comes with it
+  InvalidInclusionProof,
+  /// returned if a checkpoint proof does not link the entry to the checkpoint that comes with it
+  InvalidCheckpointProof,
+  /// returned if a snapshot is not of the ledger, or its height does not match its receipts
+  InvalidSnapshot,
+  /// returned if a view change that rotates keys does not carry valid handovers from the keys it
+  /// replaces
+  InvalidKeyHandover,
+}
@@ -0,0 +1,877 @@
+//! A client library that verifies the responses of a Nimble coordinator on its own, without
+//! trusting the coordinator or the endpoint. It tracks the views of the endorser group, checks
+//! that every response is endorsed by a quorum of the endorsers of a known view, and remembers
+//! the newest entry it saw endorsed for each ledger, so that it detects stale reads and forks
+//! across responses rather than only within one.
+
+pub mod errors;
+
+use crate::errors::VerifierError;
+use ledger::{
+  errors::VerificationError, CheckpointProof, CustomSerde, Handle, InclusionProof, LedgerSnapshot,
+  MetaBlock, NimbleDigest, NimbleHashTrait, Nonce, Nonces, Receipt, Receipts,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// A view of the endorser group that was applied to the verifier
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct View {
+  id: NimbleDigest,
+  config: Vec<u8>,
+}
+
+impl View {
+  /// the hash of the view ledger's metablock, which receipts issued in the view refer to
+  pub fn get_id(&self) -> &NimbleDigest {
+    &self.id
+  }
+
+  /// the configuration of the endorsers in the view, as stored in the view ledger
+  pub fn get_config(&self) -> &[u8] {
+    &self.config
+  }
+}
+
+/// The newest entry of a ledger that the verifier saw endorsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerTail {
+  height: usize,
+  metablock: MetaBlock,
+}
+
+impl LedgerTail {
+  pub fn get_height(&self) -> usize {
+    self.height
+  }
+
+  pub fn get_metablock(&self) -> &MetaBlock {
+    &self.metablock
+  }
+}
+
+// maps an error from checking a group of receipts to the failure it indicates
+fn receipt_error(error: VerificationError) -> VerifierError {
+  match error {
+    VerificationError::ViewNotFound => VerifierError::UnknownView,
+    VerificationError::InvalidBlockHash => VerifierError::InvalidBlockHash,
+    VerificationError::InvalidHeight => VerifierError::InvalidHeight,
+    VerificationError::InvalidSignature | VerificationError::InvalidPublicKey => {
+      VerifierError::InvalidSignature
+    },
+    VerificationError::InvalidReceipt | VerificationError::InsufficientReceipts => {
+      VerifierError::NoQuorum
+    },
+    _ => VerifierError::MalformedResponse,
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct VerifierState {
+  state: ledger::VerifierState,
+  views: BTreeMap<usize, View>,
+  tails: HashMap<Handle, LedgerTail>,
+}
+
+impl VerifierState {
+  pub fn new() -> Self {
+    VerifierState {
+      state: ledger::VerifierState::new(),
+      views: BTreeMap::new(),
+      tails: HashMap::new(),
+    }
+  }
+
+  pub fn get_group_identity(&self) -> &NimbleDigest {
+    self.state.get_group_identity()
+  }
+
+  /// sets the group identity from the configuration of the first view, which a verifier that
+  /// starts from the tail of the view ledger must obtain before applying any view change
+  pub fn set_group_identity(&mut self, first_config: &[u8]) {
+    self
+      .state
+      .set_group_identity(NimbleDigest::digest(first_config));
+  }
+
+  pub fn set_shard_size(&mut self, shard_size: usize) {
+    self.state.set_shard_size(shard_size);
+  }
+
+  /// returns the height of the newest view applied to the verifier
+  pub fn get_view_height(&self) -> usize {
+    self.state.get_view_ledger_height()
+  }
+
+  /// returns the view at `height` of the view ledger, if it was applied
+  pub fn get_view(&self, height: usize) -> Option<&View> {
+    self.views.get(&height)
+  }
+
+  /// returns the newest entry of the ledger `handle_bytes` that the verifier saw endorsed
+  pub fn get_tail(&self, handle_bytes: &[u8]) -> Option<&LedgerTail> {
+    self.tails.get(&NimbleDigest::digest(handle_bytes))
+  }
+
+  /// Applies an entry of the view ledger, given its configuration and receipts, and returns its
+  /// height. The first view sets the group identity if it is not set. Views other than the newest
+  /// are verified through the view change that follows them, so they must be applied newest first
+  /// after the newest is applied with `attestations`.
+  pub fn apply_view_change(
+    &mut self,
+    config: &[u8],
+    receipts_bytes: &[u8],
+    attestations: Option<&[u8]>,
+  ) -> Result<usize, VerifierError> {
+    let res = Receipts::from_bytes(receipts_bytes);
+    if res.is_err() {
+      return Err(VerifierError::MalformedResponse);
+    }
+    let res = res.unwrap().get_metablock();
+    if res.is_err() {
+      return Err(VerifierError::InvalidViewChange);
+    }
+    let metablock = res.unwrap();
+
+    if *self.get_group_identity() == NimbleDigest::default() {
+      if metablock.get_height() != 1 {
+        return Err(VerifierError::MissingGroupIdentity);
+      }
+      self.set_group_identity(config);
+    }
+
+    let res = self
+      .state
+      .apply_view_change(config, receipts_bytes, attestations);
+    if let Err(error) = res {
+      return Err(match error {
+        VerificationError::ViewNotFound => VerifierError::UnknownView,
+        _ => VerifierError::InvalidViewChange,
+      });
+    }
+
+    self.views.insert(
+      metablock.get_height(),
+      View {
+        id: metablock.hash(),
+        config: config.to_vec(),
+      },
+    );
+    Ok(metablock.get_height())
+  }
+
+  /// Applies an entry of the view ledger that rotates the keys of some endorsers, and returns its
+  /// height. It follows a view that was applied, and instead of attestations the key handovers in
+  /// its configuration show that the endorsers of that view chose the new keys.
+  pub fn apply_key_rotation(
+    &mut self,
+    config: &[u8],
+    receipts_bytes: &[u8],
+  ) -> Result<usize, VerifierError> {
+    let res = Receipts::from_bytes(receipts_bytes);
+    if res.is_err() {
+      return Err(VerifierError::MalformedResponse);
+    }
+    let res = res.unwrap().get_metablock();
+    if res.is_err() {
+      return Err(VerifierError::InvalidViewChange);
+    }
+    let metablock = res.unwrap();
+
+    let res = self.state.apply_key_rotation(config, receipts_bytes);
+    if let Err(error) = res {
+      return Err(match error {
+        VerificationError::ViewNotFound => VerifierError::UnknownView,
+        VerificationError::InvalidKeyHandover => VerifierError::InvalidKeyHandover,
+        _ => VerifierError::InvalidViewChange,
+      });
+    }
+
+    self.views.insert(
+      metablock.get_height(),
+      View {
+        id: metablock.hash(),
+        config: config.to_vec(),
+      },
+    );
+    Ok(metablock.get_height())
+  }
+
+  // Returns the metablock that a quorum of endorsers signed. Each group of receipts is checked on
+  // its own, so a group that fails does not hide one that is endorsed, and the endorsed metablock
+  // is known rather than inferred from the response.
+  fn endorsed_metablock(
+    &self,
+    handle: &Handle,
+    receipts_bytes: &[u8],
+    block_bytes: &[u8],
+    hash_nonces_bytes: &[u8],
+    expected_height: Option<usize>,
+    nonce_bytes: Option<&[u8]>,
+  ) -> Result<MetaBlock, VerifierError> {
+    let res = Receipts::from_bytes(receipts_bytes);
+    if res.is_err() {
+      return Err(VerifierError::MalformedResponse);
+    }
+    let receipts = res.unwrap();
+
+    let mut error = VerifierError::NoQuorum;
+    for (ex_meta_block, id_sigs) in receipts.get() {
+      let mut group = Receipts::new();
+      for id_sig in id_sigs {
+        group.add(&Receipt::new(
+          *ex_meta_block.get_view(),
+          ex_meta_block.get_metablock().clone(),
+          id_sig.clone(),
+        ));
+      }
+      let res = group.verify_with_handle(
+        &self.state,
+        handle,
+        block_bytes,
+        hash_nonces_bytes,
+        expected_height,
+        nonce_bytes,
+      );
+      match res {
+        Ok(_height) => return Ok(ex_meta_block.get_metablock().clone()),
+        Err(VerificationError::InvalidReceipt) => {},
+        Err(e) => error = receipt_error(e),
+      }
+    }
+    Err(error)
+  }
+
+  // checks an endorsed entry against the tail the verifier knows and advances the tail
+  fn observe(
+    &mut self,
+    handle: Handle,
+    metablock: MetaBlock,
+    latest: bool,
+  ) -> Result<(), VerifierError> {
+    let height = metablock.get_height();
+    if let Some(tail) = self.tails.get(&handle) {
+      // metablocks recomputed from a hash chain carry no timestamp, so they are compared by hash
+      if height == tail.height && metablock.hash() != tail.metablock.hash() {
+        return Err(VerifierError::ForkDetected);
+      }
+      if height == tail.height + 1 && *metablock.get_prev() != tail.metablock.hash() {
+        return Err(VerifierError::ForkDetected);
+      }
+      if latest && height < tail.height {
+        return Err(VerifierError::StaleRead);
+      }
+      if height <= tail.height {
+        return Ok(());
+      }
+    }
+    self.tails.insert(handle, LedgerTail { height, metablock });
+    Ok(())
+  }
+
+  /// verifies the response to a request that created the ledger `handle_bytes` with `block_bytes`
+  pub fn verify_new_ledger(
+    &mut self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    receipts_bytes: &[u8],
+  ) -> Result<(), VerifierError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let metablock = self.endorsed_metablock(
+      &handle,
+      receipts_bytes,
+      block_bytes,
+      &NimbleDigest::default().to_bytes(),
+      Some(0),
+      None,
+    )?;
+    self.observe(handle, metablock, false)
+  }
+
+  /// verifies the response to a request that appended `block_bytes` at `expected_height`
+  pub fn verify_append(
+    &mut self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    hash_nonces_bytes: &[u8],
+    expected_height: usize,
+    receipts_bytes: &[u8],
+  ) -> Result<(), VerifierError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let metablock = self.endorsed_metablock(
+      &handle,
+      receipts_bytes,
+      block_bytes,
+      hash_nonces_bytes,
+      Some(expected_height),
+      None,
+    )?;
+    self.observe(handle, metablock, false)
+  }
+
+  // returns the metablock of a tail read for `nonce_bytes`, which is endorsed over the nonce or
+  // includes it in its nonces
+  fn fresh_metablock(
+    &self,
+    handle: &Handle,
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    nonce_bytes: &[u8],
+    receipts_bytes: &[u8],
+  ) -> Result<MetaBlock, VerifierError> {
+    let hash_nonces = NimbleDigest::digest(nonces_bytes).to_bytes();
+    let res = self.endorsed_metablock(
+      handle,
+      receipts_bytes,
+      block_bytes,
+      &hash_nonces,
+      None,
+      Some(nonce_bytes),
+    );
+    match res {
+      Ok(metablock) => Ok(metablock),
+      Err(_) => {
+        // the nonce arrived after the entry was endorsed, so the entry must include it
+        let metablock = self.endorsed_metablock(
+          handle,
+          receipts_bytes,
+          block_bytes,
+          &hash_nonces,
+          None,
+          None,
+        )?;
+        let nonces = Nonces::from_bytes(nonces_bytes);
+        let nonce = Nonce::from_bytes(nonce_bytes);
+        if nonces.is_err() || nonce.is_err() {
+          return Err(VerifierError::MalformedResponse);
+        }
+        if !nonces.unwrap().contains(&nonce.unwrap()) {
+          return Err(VerifierError::NonceNotIncluded);
+        }
+        Ok(metablock)
+      },
+    }
+  }
+
+  /// Verifies the response to a read of the latest entry with `nonce_bytes` and returns the height
+  /// of the entry. The entry must be endorsed over the nonce or include it in its nonces, and it
+  /// must be no older than an entry of the ledger that the verifier already saw endorsed.
+  pub fn verify_read_latest(
+    &mut self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    nonce_bytes: &[u8],
+    receipts_bytes: &[u8],
+  ) -> Result<usize, VerifierError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let metablock = self.fresh_metablock(
+      &handle,
+      block_bytes,
+      nonces_bytes,
+      nonce_bytes,
+      receipts_bytes,
+    )?;
+
+    let height = metablock.get_height();
+    self.observe(handle, metablock, true)?;
+    Ok(height)
+  }
+
+  /// Verifies a snapshot of the ledger `handle_bytes` that the coordinator took with the Checkpoint
+  /// RPC, and returns the metablock of its tail
+  pub fn verify_snapshot(
+    &mut self,
+    handle_bytes: &[u8],
+    snapshot: &LedgerSnapshot,
+  ) -> Result<MetaBlock, VerifierError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    if *snapshot.get_handle() != handle {
+      return Err(VerifierError::InvalidSnapshot);
+    }
+    let metablock = self.fresh_metablock(
+      &handle,
+      snapshot.get_block(),
+      snapshot.get_nonces(),
+      snapshot.get_nonce(),
+      snapshot.get_receipts(),
+    )?;
+    if metablock.get_height() != snapshot.get_height() {
+      return Err(VerifierError::InvalidSnapshot);
+    }
+    self.observe(handle, metablock.clone(), false)?;
+    Ok(metablock)
+  }
+
+  /// verifies the response to a read of the entry at `index`
+  pub fn verify_read_by_index(
+    &mut self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    index: usize,
+    receipts_bytes: &[u8],
+  ) -> Result<(), VerifierError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let hash_nonces = NimbleDigest::digest(nonces_bytes).to_bytes();
+    let metablock = self.endorsed_metablock(
+      &handle,
+      receipts_bytes,
+      block_bytes,
+      &hash_nonces,
+      Some(index),
+      None,
+    )?;
+    self.observe(handle, metablock, false)
+  }
+
+  /// verifies an entry read by its index whose receipts cannot be checked on their own, such as
+  /// one endorsed in a view the verifier has not applied, using a proof that links it to a read
+  /// of the tail for `nonce_bytes`. Returns the height of the tail.
+  pub fn verify_read_by_index_with_proof(
+    &mut self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    index: usize,
+    nonce_bytes: &[u8],
+    proof: &InclusionProof,
+  ) -> Result<usize, VerifierError> {
+    let height = self.verify_read_latest(
+      handle_bytes,
+      proof.get_tail_block(),
+      proof.get_tail_nonces(),
+      nonce_bytes,
+      proof.get_tail_receipts(),
+    )?;
+
+    // a fresh read is never older than the tail the verifier tracks, so it is that tail
+    let handle = NimbleDigest::digest(handle_bytes);
+    let tail = match self.tails.get(&handle) {
+      Some(tail) if tail.height == height => tail.metablock.clone(),
+      _ => return Err(VerifierError::StaleRead),
+    };
+    match proof.verify_chain(block_bytes, nonces_bytes, index) {
+      Ok(metablock) if metablock.hash() == tail.hash() => {},
+      _ => return Err(VerifierError::InvalidInclusionProof),
+    }
+    self.observe(handle, proof.get_metablock().clone(), false)?;
+    Ok(height)
+  }
+
+  /// verifies an entry read by its index against a snapshot of the ledger, using a proof that
+  /// links the entry to the snapshot's tail
+  pub fn verify_read_by_index_with_snapshot(
+    &mut self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    index: usize,
+    snapshot: &LedgerSnapshot,
+    proof: &InclusionProof,
+  ) -> Result<(), VerifierError> {
+    let tail = self.verify_snapshot(handle_bytes, snapshot)?;
+    match proof.verify_chain(block_bytes, nonces_bytes, index) {
+      Ok(metablock) if metablock.hash() == tail.hash() => {},
+      _ => return Err(VerifierError::InvalidInclusionProof),
+    }
+    let handle = NimbleDigest::digest(handle_bytes);
+    self.observe(handle, proof.get_metablock().clone(), false)
+  }
+
+  /// verifies an entry read by its index whose receipts were compacted away, using a proof that
+  /// links it to the nearest checkpoint before it
+  pub fn verify_read_by_index_with_checkpoint(
+    &mut self,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    nonces_bytes: &[u8],
+    index: usize,
+    proof: &CheckpointProof,
+  ) -> Result<(), VerifierError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let hash_nonces = NimbleDigest::digest(proof.get_nonces()).to_bytes();
+    let checkpoint = self.endorsed_metablock(
+      &handle,
+      proof.get_receipts(),
+      proof.get_block(),
+      &hash_nonces,
+      Some(proof.get_index()),
+      None,
+    )?;
+    let res = proof.verify_chain(&checkpoint, block_bytes, nonces_bytes, index);
+    if res.is_err() {
+      return Err(VerifierError::InvalidCheckpointProof);
+    }
+    self.observe(handle, res.unwrap(), false)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    attestation::encode_view_config_with_handovers,
+    compute_aggregated_block_hash,
+    messages::{KeyHandoverAttestation, SignedStatement},
+    signature::{PrivateKey, PrivateKeyTrait, PublicKeyTrait},
+    EndorserHostnames, IdSig, KeyHandover,
+  };
+
+  const ATTESTATION_STR: &str = "THIS IS A PLACE HOLDER FOR ATTESTATION";
+
+  // the block hash that endorsers sign for a block and the hash of its nonces
+  fn block_hash(block_bytes: &[u8], hash_nonces_bytes: &[u8]) -> NimbleDigest {
+    compute_aggregated_block_hash(
+      &NimbleDigest::digest(block_bytes).to_bytes(),
+      hash_nonces_bytes,
+    )
+  }
+
+  fn sign(
+    sks: &[PrivateKey],
+    identity: &NimbleDigest,
+    view: &NimbleDigest,
+    tail_hash: &NimbleDigest,
+    metablock: &MetaBlock,
+  ) -> Vec<u8> {
+    let message = identity.digest_with(&view.digest_with(tail_hash));
+    let mut receipts = Receipts::new();
+    for sk in sks {
+      let signature = sk.sign(&message.to_bytes()).unwrap();
+      let id_sig = IdSig::new(sk.get_public_key().unwrap(), signature);
+      receipts.add(&Receipt::new(*view, metablock.clone(), id_sig));
+    }
+    receipts.to_bytes()
+  }
+
+  #[test]
+  fn test_verifier_state() {
+    let sks = (0..3)
+      .map(|_| PrivateKey::new())
+      .collect::<Vec<PrivateKey>>();
+    let hostnames: EndorserHostnames = sks
+      .iter()
+      .enumerate()
+      .map(|(i, sk)| {
+        (
+          sk.get_public_key().unwrap().to_bytes(),
+          format!("endorser-{}", i),
+        )
+      })
+      .collect();
+    let config = bincode::serialize(&hostnames).unwrap();
+    let identity = NimbleDigest::digest(&config);
+
+    // the first view sets the group identity; a later one cannot be applied without it
+    let view1 = MetaBlock::new(&NimbleDigest::default(), &identity, 1);
+    let r = sign(
+      &sks,
+      &identity,
+      &NimbleDigest::default(),
+      &view1.hash(),
+      &view1,
+    );
+    let view2 = MetaBlock::new(&view1.hash(), &identity, 2);
+    let r2 = sign(
+      &sks,
+      &identity,
+      &NimbleDigest::default(),
+      &view2.hash(),
+      &view2,
+    );
+    let mut vs = VerifierState::new();
+    assert_eq!(
+      vs.apply_view_change(&config, &r2, Some(ATTESTATION_STR.as_bytes())),
+      Err(VerifierError::MissingGroupIdentity)
+    );
+    assert_eq!(
+      vs.apply_view_change(&config, &r, Some(ATTESTATION_STR.as_bytes())),
+      Ok(1)
+    );
+    assert_eq!(*vs.get_group_identity(), identity);
+    assert_eq!(vs.get_view_height(), 1);
+    assert_eq!(vs.get_view(1).unwrap().get_id(), &view1.hash());
+    let view = view1.hash();
+
+    // a new ledger needs a quorum of the view's endorsers
+    let handle_bytes = b"handle";
+    let handle = NimbleDigest::digest(handle_bytes);
+    let genesis = MetaBlock::genesis(&block_hash(b"genesis", &NimbleDigest::default().to_bytes()));
+    let tail_hash = handle.digest_with(&genesis.hash());
+    let r = sign(&sks[..1], &identity, &view, &tail_hash, &genesis);
+    assert_eq!(
+      vs.verify_new_ledger(handle_bytes, b"genesis", &r),
+      Err(VerifierError::NoQuorum)
+    );
+    let r = sign(
+      &sks[..2],
+      &identity,
+      &NimbleDigest::default(),
+      &tail_hash,
+      &genesis,
+    );
+    assert_eq!(
+      vs.verify_new_ledger(handle_bytes, b"genesis", &r),
+      Err(VerifierError::UnknownView)
+    );
+    let r = sign(&sks[..2], &identity, &view, &tail_hash, &genesis);
+    vs.verify_new_ledger(handle_bytes, b"genesis", &r).unwrap();
+    assert_eq!(vs.get_tail(handle_bytes).unwrap().get_height(), 0);
+
+    // appends are checked against the block and the expected height
+    let nonce = Nonce::new(&[7u8; 16]).unwrap();
+    let nonces = Nonces::from_vec(vec![nonce]).to_bytes();
+    let hash_nonces = NimbleDigest::digest(&nonces).to_bytes();
+    let entry1 = MetaBlock::new(&genesis.hash(), &block_hash(b"block1", &hash_nonces), 1);
+    let r1 = sign(
+      &sks,
+      &identity,
+      &view,
+      &handle.digest_with(&entry1.hash()),
+      &entry1,
+    );
+    assert_eq!(
+      vs.verify_append(handle_bytes, b"other", &hash_nonces, 1, &r1),
+      Err(VerifierError::InvalidBlockHash)
+    );
+    assert_eq!(
+      vs.verify_append(handle_bytes, b"block1", &hash_nonces, 2, &r1),
+      Err(VerifierError::InvalidHeight)
+    );
+    vs.verify_append(handle_bytes, b"block1", &hash_nonces, 1, &r1)
+      .unwrap();
+    assert_eq!(vs.get_tail(handle_bytes).unwrap().get_metablock(), &entry1);
+
+    // a read is fresh if it is signed over the nonce or includes the nonce
+    let other_nonce = [8u8; 16];
+    let tail_hash = handle.digest_with(&entry1.hash().digest_with_bytes(&other_nonce));
+    let r = sign(&sks, &identity, &view, &tail_hash, &entry1);
+    assert_eq!(
+      vs.verify_read_latest(handle_bytes, b"block1", &nonces, &other_nonce, &r),
+      Ok(1)
+    );
+    assert_eq!(
+      vs.verify_read_latest(handle_bytes, b"block1", &nonces, &nonce.to_bytes(), &r1),
+      Ok(1)
+    );
+    assert_eq!(
+      vs.verify_read_latest(handle_bytes, b"block1", &nonces, &[9u8; 16], &r1),
+      Err(VerifierError::NonceNotIncluded)
+    );
+
+    // once the ledger is seen at height 2, a read of height 1 is stale
+    let hash_nonces2 = NimbleDigest::digest(&Nonces::new().to_bytes()).to_bytes();
+    let entry2 = MetaBlock::new(&entry1.hash(), &block_hash(b"block2", &hash_nonces2), 2);
+    let r2 = sign(
+      &sks,
+      &identity,
+      &view,
+      &handle.digest_with(&entry2.hash()),
+      &entry2,
+    );
+    vs.verify_append(handle_bytes, b"block2", &hash_nonces2, 2, &r2)
+      .unwrap();
+    assert_eq!(
+      vs.verify_read_latest(handle_bytes, b"block1", &nonces, &nonce.to_bytes(), &r1),
+      Err(VerifierError::StaleRead)
+    );
+    vs.verify_read_by_index(handle_bytes, b"block1", &nonces, 1, &r1)
+      .unwrap();
+
+    // a different entry endorsed at a height the verifier saw is a fork
+    let forked = MetaBlock::new(&genesis.hash(), &block_hash(b"forked", &hash_nonces2), 2);
+    let r = sign(
+      &sks,
+      &identity,
+      &view,
+      &handle.digest_with(&forked.hash()),
+      &forked,
+    );
+    assert_eq!(
+      vs.verify_read_by_index(handle_bytes, b"forked", &Nonces::new().to_bytes(), 2, &r),
+      Err(VerifierError::ForkDetected)
+    );
+    assert_eq!(
+      vs.verify_append(handle_bytes, b"block1", &hash_nonces, 1, &[1, 2, 3]),
+      Err(VerifierError::MalformedResponse)
+    );
+
+    // an entry is proven by the hash chain from it to a fresh read of the tail
+    let tail_nonce = [10u8; 16];
+    let tail_hash = handle.digest_with(&entry2.hash().digest_with_bytes(&tail_nonce));
+    let r = sign(&sks, &identity, &view, &tail_hash, &entry2);
+    let hash_block2 = block_hash(b"block2", &hash_nonces2);
+    let proof = InclusionProof::new(
+      entry1.clone(),
+      vec![hash_block2],
+      b"block2".to_vec(),
+      Nonces::new().to_bytes(),
+      r,
+    );
+    assert_eq!(
+      vs.verify_read_by_index_with_proof(handle_bytes, b"block1", &nonces, 1, &tail_nonce, &proof),
+      Ok(2)
+    );
+    assert_eq!(
+      vs.verify_read_by_index_with_proof(handle_bytes, b"other", &nonces, 1, &tail_nonce, &proof),
+      Err(VerifierError::InvalidInclusionProof)
+    );
+    assert!(vs
+      .verify_read_by_index_with_proof(handle_bytes, b"block1", &nonces, 1, &[11u8; 16], &proof)
+      .is_err());
+
+    // an entry without receipts is proven by the hash chain from the checkpoint before it
+    let proof = CheckpointProof::new(
+      1,
+      b"block1".to_vec(),
+      nonces.clone(),
+      r1.clone(),
+      vec![hash_block2],
+    );
+    let nonces2 = Nonces::new().to_bytes();
+    assert_eq!(
+      vs.verify_read_by_index_with_checkpoint(handle_bytes, b"block2", &nonces2, 2, &proof),
+      Ok(())
+    );
+    assert_eq!(
+      vs.verify_read_by_index_with_checkpoint(handle_bytes, b"other", &nonces2, 2, &proof),
+      Err(VerifierError::InvalidCheckpointProof)
+    );
+
+    // a snapshot is a read of the tail for the coordinator's nonce, against which entries are
+    // proven later
+    let snapshot_nonce = [12u8; 16];
+    let tail_hash = handle.digest_with(&entry2.hash().digest_with_bytes(&snapshot_nonce));
+    let r = sign(&sks, &identity, &view, &tail_hash, &entry2);
+    let snapshot = LedgerSnapshot::new(
+      &handle,
+      2,
+      b"block2".to_vec(),
+      nonces2.clone(),
+      snapshot_nonce.to_vec(),
+      r.clone(),
+    );
+    assert_eq!(
+      vs.verify_snapshot(handle_bytes, &snapshot),
+      Ok(entry2.clone())
+    );
+    assert_eq!(
+      vs.verify_snapshot(b"other", &snapshot),
+      Err(VerifierError::InvalidSnapshot)
+    );
+    let proof = InclusionProof::new(
+      entry1.clone(),
+      vec![hash_block2],
+      Vec::new(),
+      Vec::new(),
+      Vec::new(),
+    );
+    assert_eq!(
+      vs.verify_read_by_index_with_snapshot(handle_bytes, b"block1", &nonces, 1, &snapshot, &proof),
+      Ok(())
+    );
+    let wrong_height = LedgerSnapshot::new(
+      &handle,
+      3,
+      b"block2".to_vec(),
+      nonces2,
+      snapshot_nonce.to_vec(),
+      r,
+    );
+    assert_eq!(
+      vs.verify_snapshot(handle_bytes, &wrong_height),
+      Err(VerifierError::InvalidSnapshot)
+    );
+  }
+
+  #[test]
+  fn test_key_rotation() {
+    let sks = (0..3)
+      .map(|_| PrivateKey::new())
+      .collect::<Vec<PrivateKey>>();
+    let hostnames = |sks: &[PrivateKey]| -> EndorserHostnames {
+      sks
+        .iter()
+        .enumerate()
+        .map(|(i, sk)| {
+          (
+            sk.get_public_key().unwrap().to_bytes(),
+            format!("endorser-{}", i),
+          )
+        })
+        .collect()
+    };
+    let config = bincode::serialize(&hostnames(&sks)).unwrap();
+    let identity = NimbleDigest::digest(&config);
+    let view1 = MetaBlock::new(&NimbleDigest::default(), &identity, 1);
+    let r = sign(
+      &sks,
+      &identity,
+      &NimbleDigest::default(),
+      &view1.hash(),
+      &view1,
+    );
+    let mut vs = VerifierState::new();
+    assert_eq!(
+      vs.apply_view_change(&config, &r, Some(ATTESTATION_STR.as_bytes())),
+      Ok(1)
+    );
+
+    // the first endorser hands over from its key to a new one in the first view
+    let handover = |old_sk: &PrivateKey, new_sk: &PrivateKey| {
+      let new_pk = new_sk.get_public_key().unwrap();
+      let message = KeyHandoverAttestation::new(
+        &identity,
+        &view1.hash(),
+        &NimbleDigest::digest(&new_pk.to_bytes()),
+      )
+      .message();
+      let signature = old_sk.sign(&message.to_bytes()).unwrap();
+      KeyHandover::new(
+        &view1.hash(),
+        &new_pk,
+        IdSig::new(old_sk.get_public_key().unwrap(), signature),
+      )
+    };
+    let mut new_sks = sks;
+    let old_sk = std::mem::replace(&mut new_sks[0], PrivateKey::new());
+    let rotation = |handovers: &[KeyHandover]| {
+      let config =
+        encode_view_config_with_handovers(&hostnames(&new_sks), &Vec::new(), handovers).unwrap();
+      let view2 = MetaBlock::new(&view1.hash(), &NimbleDigest::digest(&config), 2);
+      let r = sign(
+        &new_sks,
+        &identity,
+        &NimbleDigest::default(),
+        &view2.hash(),
+        &view2,
+      );
+      (config, view2, r)
+    };
+
+    // the view change needs an attestation or a handover from every replaced key
+    let (config2, _view2, r2) = rotation(&[]);
+    assert_eq!(
+      vs.apply_view_change(&config2, &r2, None),
+      Err(VerifierError::InvalidViewChange)
+    );
+    assert_eq!(
+      vs.apply_key_rotation(&config2, &r2),
+      Err(VerifierError::InvalidKeyHandover)
+    );
+    let (config2, _view2, r2) = rotation(&[handover(&PrivateKey::new(), &new_sks[0])]);
+    assert_eq!(
+      vs.apply_key_rotation(&config2, &r2),
+      Err(VerifierError::InvalidKeyHandover)
+    );
+    let (config2, _view2, r2) = rotation(&[handover(&old_sk, &PrivateKey::new())]);
+    assert_eq!(
+      vs.apply_key_rotation(&config2, &r2),
+      Err(VerifierError::InvalidKeyHandover)
+    );
+
+    let (config2, view2, r2) = rotation(&[handover(&old_sk, &new_sks[0])]);
+    assert_eq!(vs.apply_key_rotation(&config2, &r2), Ok(2));
+    assert_eq!(vs.get_view(2).unwrap().get_id(), &view2.hash());
+  }
+}